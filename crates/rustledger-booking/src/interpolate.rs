@@ -4,7 +4,7 @@
 
 use rust_decimal::Decimal;
 use rust_decimal::prelude::Signed;
-use rustledger_core::{Amount, IncompleteAmount, InternedStr, Transaction};
+use rustledger_core::{Amount, IncompleteAmount, InternedStr, MixedAmount, Transaction};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -45,7 +45,7 @@ pub struct InterpolationResult {
     /// Which posting indices were filled in.
     pub filled_indices: Vec<usize>,
     /// Residuals after interpolation (should all be near zero).
-    pub residuals: HashMap<InternedStr, Decimal>,
+    pub residuals: MixedAmount,
 }
 
 /// Interpolate missing amounts in a transaction.
@@ -77,7 +77,7 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
     let mut filled_indices = Vec::new();
 
     // Calculate initial residuals from postings with amounts
-    let mut residuals: HashMap<InternedStr, Decimal> = HashMap::new();
+    let mut residuals = MixedAmount::new();
     let mut missing_by_currency: HashMap<InternedStr, Vec<usize>> = HashMap::new();
     let mut unassigned_missing: Vec<usize> = Vec::new();
 
@@ -98,59 +98,64 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
                         (&cost_spec.number_per, &cost_spec.currency)
                     {
                         let cost_amount = amount.number * per_unit;
-                        *residuals.entry(cost_curr.clone()).or_default() += cost_amount;
+                        residuals.add_number(cost_curr.clone(), cost_amount);
                     } else if let (Some(total), Some(cost_curr)) =
                         (&cost_spec.number_total, &cost_spec.currency)
                     {
                         // For total cost, sign depends on units sign
-                        *residuals.entry(cost_curr.clone()).or_default() +=
-                            *total * amount.number.signum();
+                        residuals.add_number(cost_curr.clone(), *total * amount.number.signum());
                     } else {
                         // Cost spec without amount/currency - fall back to units
-                        *residuals.entry(amount.currency.clone()).or_default() += amount.number;
+                        residuals.add_number(amount.currency.clone(), amount.number);
                     }
                 } else if let Some(price) = &posting.price {
                     // Price annotation: converts units to price currency
                     match price {
                         rustledger_core::PriceAnnotation::Unit(price_amt) => {
                             let converted = amount.number.abs() * price_amt.number;
-                            *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                converted * amount.number.signum();
+                            residuals.add_number(
+                                price_amt.currency.clone(),
+                                converted * amount.number.signum(),
+                            );
                         }
                         rustledger_core::PriceAnnotation::Total(price_amt) => {
-                            *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                price_amt.number * amount.number.signum();
+                            residuals.add_number(
+                                price_amt.currency.clone(),
+                                price_amt.number * amount.number.signum(),
+                            );
                         }
                         rustledger_core::PriceAnnotation::UnitIncomplete(inc) => {
                             if let Some(price_amt) = inc.as_amount() {
                                 let converted = amount.number.abs() * price_amt.number;
-                                *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                    converted * amount.number.signum();
+                                residuals.add_number(
+                                    price_amt.currency.clone(),
+                                    converted * amount.number.signum(),
+                                );
                             } else {
                                 // Can't calculate, fall back to units
-                                *residuals.entry(amount.currency.clone()).or_default() +=
-                                    amount.number;
+                                residuals.add_number(amount.currency.clone(), amount.number);
                             }
                         }
                         rustledger_core::PriceAnnotation::TotalIncomplete(inc) => {
                             if let Some(price_amt) = inc.as_amount() {
-                                *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                    price_amt.number * amount.number.signum();
+                                residuals.add_number(
+                                    price_amt.currency.clone(),
+                                    price_amt.number * amount.number.signum(),
+                                );
                             } else {
                                 // Can't calculate, fall back to units
-                                *residuals.entry(amount.currency.clone()).or_default() +=
-                                    amount.number;
+                                residuals.add_number(amount.currency.clone(), amount.number);
                             }
                         }
                         // Empty price annotations - fall back to units
                         rustledger_core::PriceAnnotation::UnitEmpty
                         | rustledger_core::PriceAnnotation::TotalEmpty => {
-                            *residuals.entry(amount.currency.clone()).or_default() += amount.number;
+                            residuals.add_number(amount.currency.clone(), amount.number);
                         }
                     }
                 } else {
                     // Simple posting: weight is just the units
-                    *residuals.entry(amount.currency.clone()).or_default() += amount.number;
+                    residuals.add_number(amount.currency.clone(), amount.number);
                 }
             }
             Some(IncompleteAmount::CurrencyOnly(currency)) => {
@@ -182,7 +187,7 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
 
                 if let Some(curr) = currency {
                     // We have currency from context, make it complete
-                    *residuals.entry(curr.clone()).or_default() += *number;
+                    residuals.add_number(curr.clone(), *number);
                 } else {
                     // Can't determine currency yet
                     unassigned_missing.push(i);
@@ -218,7 +223,7 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
     // Fill in known-currency missing postings
     for (currency, indices) in missing_by_currency {
         let idx = indices[0];
-        let residual = residuals.get(&currency).copied().unwrap_or(Decimal::ZERO);
+        let residual = residuals.number(&currency);
 
         result.postings[idx].units = Some(IncompleteAmount::Complete(Amount::new(
             -residual, &currency,
@@ -226,7 +231,7 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
         filled_indices.push(idx);
 
         // Update residual
-        residuals.insert(currency, Decimal::ZERO);
+        residuals.set(currency, Decimal::ZERO);
     }
 
     // Handle unassigned missing postings
@@ -252,7 +257,7 @@ pub fn interpolate(transaction: &Transaction) -> Result<InterpolationResult, Int
                     -*residual, currency,
                 )));
                 filled_indices.push(*idx);
-                residuals.insert(currency.clone(), Decimal::ZERO);
+                residuals.set(currency.clone(), Decimal::ZERO);
             } else if !non_zero_residuals.is_empty() {
                 // Use the first currency
                 let (currency, _) = &non_zero_residuals[0];
@@ -444,7 +449,7 @@ mod tests {
         );
         // There should be NO HOOL residual
         assert!(
-            !result.residuals.contains_key("HOOL"),
+            !result.residuals.contains("HOOL"),
             "should not have HOOL residual"
         );
     }
@@ -4,6 +4,7 @@
 //! - Transaction interpolation (filling in missing amounts)
 //! - Transaction balancing verification
 //! - Tolerance calculation
+//! - Rounding residuals booked to `option "account_rounding"`
 //!
 //! # Interpolation
 //!
@@ -24,13 +25,15 @@
 
 mod interpolate;
 mod pad;
+mod rounding;
 
 pub use interpolate::{InterpolationError, InterpolationResult, interpolate};
 pub use pad::{PadError, PadResult, expand_pads, merge_with_padding, process_pads};
+pub use rounding::absorb_rounding;
 
 use rust_decimal::Decimal;
 use rust_decimal::prelude::Signed;
-use rustledger_core::{Amount, IncompleteAmount, InternedStr, Transaction};
+use rustledger_core::{Amount, IncompleteAmount, InternedStr, MixedAmount, Transaction};
 use std::collections::HashMap;
 
 /// Calculate the tolerance for a set of amounts.
@@ -53,11 +56,11 @@ pub fn calculate_tolerance(amounts: &[&Amount]) -> HashMap<InternedStr, Decimal>
 
 /// Calculate the residual (imbalance) of a transaction.
 ///
-/// Returns a map of currency -> residual amount.
+/// Returns the residual amount per currency.
 /// A balanced transaction has all residuals within tolerance.
 #[must_use]
-pub fn calculate_residual(transaction: &Transaction) -> HashMap<InternedStr, Decimal> {
-    let mut residuals: HashMap<InternedStr, Decimal> = HashMap::new();
+pub fn calculate_residual(transaction: &Transaction) -> MixedAmount {
+    let mut residuals = MixedAmount::new();
 
     for posting in &transaction.postings {
         // Only process complete amounts
@@ -73,16 +76,15 @@ pub fn calculate_residual(transaction: &Transaction) -> HashMap<InternedStr, Dec
                     (&cost_spec.number_per, &cost_spec.currency)
                 {
                     let cost_amount = units.number * per_unit;
-                    *residuals.entry(cost_curr.clone()).or_default() += cost_amount;
+                    residuals.add_number(cost_curr.clone(), cost_amount);
                 } else if let (Some(total), Some(cost_curr)) =
                     (&cost_spec.number_total, &cost_spec.currency)
                 {
                     // For total cost, the sign depends on the units sign
-                    *residuals.entry(cost_curr.clone()).or_default() +=
-                        *total * units.number.signum();
+                    residuals.add_number(cost_curr.clone(), *total * units.number.signum());
                 } else {
                     // Cost spec without amount/currency - fall back to units
-                    *residuals.entry(units.currency.clone()).or_default() += units.number;
+                    residuals.add_number(units.currency.clone(), units.number);
                 }
             } else if let Some(price) = &posting.price {
                 // Price annotation: converts units to price currency for balance purposes.
@@ -90,42 +92,50 @@ pub fn calculate_residual(transaction: &Transaction) -> HashMap<InternedStr, Dec
                 match price {
                     rustledger_core::PriceAnnotation::Unit(price_amt) => {
                         let converted = units.number.abs() * price_amt.number;
-                        *residuals.entry(price_amt.currency.clone()).or_default() +=
-                            converted * units.number.signum();
+                        residuals.add_number(
+                            price_amt.currency.clone(),
+                            converted * units.number.signum(),
+                        );
                     }
                     rustledger_core::PriceAnnotation::Total(price_amt) => {
-                        *residuals.entry(price_amt.currency.clone()).or_default() +=
-                            price_amt.number * units.number.signum();
+                        residuals.add_number(
+                            price_amt.currency.clone(),
+                            price_amt.number * units.number.signum(),
+                        );
                     }
                     // Incomplete price annotations - extract what we can
                     rustledger_core::PriceAnnotation::UnitIncomplete(inc) => {
                         if let Some(price_amt) = inc.as_amount() {
                             let converted = units.number.abs() * price_amt.number;
-                            *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                converted * units.number.signum();
+                            residuals.add_number(
+                                price_amt.currency.clone(),
+                                converted * units.number.signum(),
+                            );
                         } else {
                             // Can't calculate price conversion, fall back to units
-                            *residuals.entry(units.currency.clone()).or_default() += units.number;
+                            residuals.add_number(units.currency.clone(), units.number);
                         }
                     }
                     rustledger_core::PriceAnnotation::TotalIncomplete(inc) => {
                         if let Some(price_amt) = inc.as_amount() {
-                            *residuals.entry(price_amt.currency.clone()).or_default() +=
-                                price_amt.number * units.number.signum();
+                            residuals.add_number(
+                                price_amt.currency.clone(),
+                                price_amt.number * units.number.signum(),
+                            );
                         } else {
                             // Can't calculate price conversion, fall back to units
-                            *residuals.entry(units.currency.clone()).or_default() += units.number;
+                            residuals.add_number(units.currency.clone(), units.number);
                         }
                     }
                     // Empty price annotations - fall back to units
                     rustledger_core::PriceAnnotation::UnitEmpty
                     | rustledger_core::PriceAnnotation::TotalEmpty => {
-                        *residuals.entry(units.currency.clone()).or_default() += units.number;
+                        residuals.add_number(units.currency.clone(), units.number);
                     }
                 }
             } else {
                 // Simple posting: weight is just the units
-                *residuals.entry(units.currency.clone()).or_default() += units.number;
+                residuals.add_number(units.currency.clone(), units.number);
             }
         }
     }
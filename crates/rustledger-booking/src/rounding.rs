@@ -0,0 +1,125 @@
+//! Absorbing rounding residuals into a rounding account.
+//!
+//! A balanced transaction may still leave a small residual within tolerance,
+//! e.g. `3 HOOL {33.333 USD}` against `-100.00 USD`. With
+//! `option "account_rounding"` set, such residuals are booked to that
+//! account so that sums over the ledger come out exact.
+
+use rust_decimal::Decimal;
+use rustledger_core::{Amount, IncompleteAmount, Posting, Transaction};
+
+use crate::{calculate_residual, calculate_tolerance};
+
+/// Book the residuals of a transaction that are within tolerance to
+/// `account`.
+///
+/// An existing plain posting to `account` in the residual's currency is
+/// adjusted; otherwise a posting is added. Residuals beyond tolerance are
+/// left alone for validation to report. Returns whether the transaction
+/// changed.
+pub fn absorb_rounding(transaction: &mut Transaction, account: &str) -> bool {
+    let amounts: Vec<&Amount> = transaction
+        .postings
+        .iter()
+        .filter_map(|posting| posting.units.as_ref()?.as_amount())
+        .collect();
+    let tolerances = calculate_tolerance(&amounts);
+
+    let mut changed = false;
+    for (currency, residual) in calculate_residual(transaction) {
+        let tolerance = tolerances
+            .get(&currency)
+            .copied()
+            .unwrap_or(Decimal::new(5, 3)); // Default 0.005
+        if residual.is_zero() || residual.abs() > tolerance {
+            continue;
+        }
+
+        let existing = transaction.postings.iter_mut().find(|posting| {
+            posting.account == account
+                && posting.cost.is_none()
+                && posting.price.is_none()
+                && posting
+                    .units
+                    .as_ref()
+                    .and_then(IncompleteAmount::as_amount)
+                    .is_some_and(|units| units.currency == currency)
+        });
+        match existing {
+            Some(posting) => {
+                if let Some(IncompleteAmount::Complete(units)) = &mut posting.units {
+                    units.number -= residual;
+                }
+            }
+            None => transaction
+                .postings
+                .push(Posting::new(account, Amount::new(-residual, &currency))),
+        }
+        changed = true;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{CostSpec, NaiveDate};
+
+    fn purchase() -> Transaction {
+        Transaction::new(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), "Buy")
+            .with_posting(
+                Posting::new("Assets:Stock", Amount::new(dec!(3), "HOOL")).with_cost(
+                    CostSpec::empty()
+                        .with_number_per(dec!(33.333))
+                        .with_currency("USD"),
+                ),
+            )
+            .with_posting(Posting::new(
+                "Assets:Cash",
+                Amount::new(dec!(-100.00), "USD"),
+            ))
+    }
+
+    #[test]
+    fn test_absorb_rounding_adds_posting() {
+        let mut txn = purchase();
+        assert!(absorb_rounding(&mut txn, "Equity:Rounding"));
+
+        assert_eq!(txn.postings.len(), 3);
+        let rounding = txn.postings[2].units.as_ref().unwrap().as_amount().unwrap();
+        assert_eq!(txn.postings[2].account, "Equity:Rounding");
+        assert_eq!(rounding.number, dec!(0.001));
+        assert_eq!(rounding.currency, "USD");
+        assert!(calculate_residual(&txn).is_zero());
+    }
+
+    #[test]
+    fn test_absorb_rounding_adjusts_existing_posting() {
+        let mut txn = purchase().with_posting(Posting::new(
+            "Equity:Rounding",
+            Amount::new(dec!(0.002), "USD"),
+        ));
+        // Residual is now 0.001 the other way
+        assert!(absorb_rounding(&mut txn, "Equity:Rounding"));
+
+        assert_eq!(txn.postings.len(), 3);
+        let rounding = txn.postings[2].units.as_ref().unwrap().as_amount().unwrap();
+        assert_eq!(rounding.number, dec!(0.001));
+    }
+
+    #[test]
+    fn test_absorb_rounding_ignores_large_residual() {
+        let mut txn = Transaction::new(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), "Off")
+            .with_posting(Posting::new(
+                "Expenses:Food",
+                Amount::new(dec!(50.00), "USD"),
+            ))
+            .with_posting(Posting::new(
+                "Assets:Cash",
+                Amount::new(dec!(-45.00), "USD"),
+            ));
+        assert!(!absorb_rounding(&mut txn, "Equity:Rounding"));
+        assert_eq!(txn.postings.len(), 2);
+    }
+}
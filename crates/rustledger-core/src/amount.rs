@@ -3,12 +3,18 @@
 //! An [`Amount`] is the fundamental unit of value in Beancount, combining a decimal
 //! number with a currency code. It supports arithmetic operations and tolerance-based
 //! comparison for balance checking.
+//!
+//! [`AmountParser`] reads amounts as they are written outside of ledgers, in
+//! bank statements and forms: `$1,234.56`, `(50.00)`, `12,50 €`.
 
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+use thiserror::Error;
 
+use crate::currency::{Currency, CurrencySymbols};
 use crate::intern::InternedStr;
 #[cfg(feature = "rkyv")]
 use crate::intern::{AsDecimal, AsInternedStr};
@@ -181,6 +187,25 @@ impl Amount {
             currency: self.currency.clone(),
         }
     }
+
+    /// Parse an amount such as `1,234.56 USD` or `$1,234.56`.
+    ///
+    /// Uses an [`AmountParser`] with common currency symbols and `.` as the
+    /// decimal separator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustledger_core::Amount;
+    /// use rust_decimal_macros::dec;
+    ///
+    /// assert_eq!(Amount::parse("1,234.56 USD").unwrap(), Amount::new(dec!(1234.56), "USD"));
+    /// assert_eq!(Amount::parse("(€5)").unwrap(), Amount::new(dec!(-5), "EUR"));
+    /// assert!(Amount::parse("1,234.56").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Self, ParseAmountError> {
+        AmountParser::new().parse(s)
+    }
 }
 
 impl fmt::Display for Amount {
@@ -189,6 +214,238 @@ impl fmt::Display for Amount {
     }
 }
 
+impl FromStr for Amount {
+    type Err = ParseAmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// An amount string that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid amount \"{input}\": {reason}")]
+pub struct ParseAmountError {
+    /// The rejected input.
+    pub input: String,
+    /// Why it was rejected.
+    pub reason: &'static str,
+}
+
+/// Parser for amounts written outside of ledgers.
+///
+/// The currency may come before or after the number, as a code (`USD`) or a
+/// symbol from the parser's [`CurrencySymbols`]. A number may be negated
+/// with a sign on either side of the currency (`-$5`, `$-5`, `5-`) or with
+/// parentheses (`($5.00)`), and its digits grouped with `,`, `'` or spaces
+/// (`.` instead of `,` with a decimal comma).
+///
+/// # Example
+///
+/// ```
+/// use rustledger_core::amount::AmountParser;
+/// use rustledger_core::Amount;
+/// use rust_decimal_macros::dec;
+///
+/// let parser = AmountParser::new().with_decimal_comma(true).with_default_currency("EUR");
+/// assert_eq!(parser.parse("-1.234,50 €").unwrap(), Amount::new(dec!(-1234.50), "EUR"));
+/// assert_eq!(parser.parse("7,00").unwrap(), Amount::new(dec!(7.00), "EUR"));
+/// assert_eq!(parser.parse("CHF 7").unwrap(), Amount::new(dec!(7), "CHF"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AmountParser {
+    symbols: CurrencySymbols,
+    decimal_comma: bool,
+    default_currency: Option<InternedStr>,
+}
+
+impl Default for AmountParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AmountParser {
+    /// Create a parser with common currency symbols and `.` as the decimal
+    /// separator.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            symbols: CurrencySymbols::common(),
+            decimal_comma: false,
+            default_currency: None,
+        }
+    }
+
+    /// Use the given currency symbols.
+    #[must_use]
+    pub fn with_symbols(mut self, symbols: CurrencySymbols) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Use `,` as the decimal separator and `.` to group digits.
+    #[must_use]
+    pub const fn with_decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Use this currency for amounts written without one.
+    #[must_use]
+    pub fn with_default_currency(mut self, currency: impl Into<InternedStr>) -> Self {
+        self.default_currency = Some(currency.into());
+        self
+    }
+
+    /// Parse an amount, falling back to the default currency.
+    pub fn parse(&self, s: &str) -> Result<Amount, ParseAmountError> {
+        let (number, currency) = self.parse_number(s)?;
+        let currency = currency
+            .or_else(|| self.default_currency.clone())
+            .ok_or_else(|| ParseAmountError {
+                input: s.to_string(),
+                reason: "has no currency",
+            })?;
+        Ok(Amount::new(number, currency))
+    }
+
+    /// Parse the number of an amount, and its currency if written.
+    pub fn parse_number(
+        &self,
+        s: &str,
+    ) -> Result<(Decimal, Option<InternedStr>), ParseAmountError> {
+        self.split(s.trim()).map_err(|reason| ParseAmountError {
+            input: s.to_string(),
+            reason,
+        })
+    }
+
+    fn split(&self, text: &str) -> Result<(Decimal, Option<InternedStr>), &'static str> {
+        let decimal_separator = if self.decimal_comma { ',' } else { '.' };
+        let (Some(first), Some(last)) = (
+            text.find(|c: char| c.is_ascii_digit()),
+            text.rfind(|c: char| c.is_ascii_digit()),
+        ) else {
+            return Err("has no number");
+        };
+        // A leading decimal separator belongs to the number: `.50`
+        let start = if text[..first].ends_with(decimal_separator) {
+            first - 1
+        } else {
+            first
+        };
+        let (mut prefix, digits, mut suffix) = (
+            text[..start].trim(),
+            &text[start..=last],
+            text[last + 1..].trim(),
+        );
+
+        let open = prefix
+            .strip_prefix('(')
+            .or_else(|| prefix.strip_suffix('('));
+        let close = suffix
+            .strip_suffix(')')
+            .or_else(|| suffix.strip_prefix(')'));
+        let parenthesized = match (open, close) {
+            (Some(inner_prefix), Some(inner_suffix)) => {
+                prefix = inner_prefix.trim();
+                suffix = inner_suffix.trim();
+                true
+            }
+            _ => false,
+        };
+        let (prefix_sign, prefix) = take_sign(prefix);
+        let (suffix_sign, suffix) = take_sign(suffix);
+        let negative = match (parenthesized, prefix_sign, suffix_sign) {
+            (true, None, None) => true,
+            (false, Some(negative), None) | (false, None, Some(negative)) => negative,
+            (false, None, None) => false,
+            _ => return Err("has more than one sign"),
+        };
+
+        let currency = match (self.currency(prefix)?, self.currency(suffix)?) {
+            (Some(before), Some(after)) if before != after => {
+                return Err("has two different currencies");
+            }
+            (before, after) => before.or(after),
+        };
+
+        let grouping = if self.decimal_comma { '.' } else { ',' };
+        let mut number = String::with_capacity(digits.len() + 1);
+        if negative {
+            number.push('-');
+        }
+        // Digits since the last grouping separator: groups have 3 digits, or
+        // 2 before the last one in Indian grouping (`1,23,456`)
+        let mut group: Option<usize> = None;
+        let mut fraction = false;
+        for c in digits.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                if let Some(len) = group.as_mut() {
+                    *len += 1;
+                }
+            } else if c == decimal_separator {
+                if fraction {
+                    return Err("has more than one decimal separator");
+                }
+                if group.is_some_and(|len| len != 3) {
+                    return Err("has misplaced digit grouping");
+                }
+                fraction = true;
+                group = None;
+                if !number.ends_with(|c: char| c.is_ascii_digit()) {
+                    number.push('0');
+                }
+                number.push('.');
+            } else if c == grouping || matches!(c, '\'' | ' ' | '\u{a0}' | '\u{202f}') {
+                if fraction {
+                    return Err("groups digits after the decimal separator");
+                }
+                if group.is_some_and(|len| !(2..=3).contains(&len)) {
+                    return Err("has misplaced digit grouping");
+                }
+                group = Some(0);
+            } else {
+                return Err("has an invalid number");
+            }
+        }
+        if group.is_some_and(|len| len != 3) {
+            return Err("has misplaced digit grouping");
+        }
+        let number = Decimal::from_str(&number).map_err(|_| "has an invalid number")?;
+
+        Ok((number, currency))
+    }
+
+    /// The currency a symbol or code next to the number stands for.
+    fn currency(&self, token: &str) -> Result<Option<InternedStr>, &'static str> {
+        if token.is_empty() {
+            Ok(None)
+        } else if let Some(currency) = self.symbols.get(token) {
+            Ok(Some(currency.clone()))
+        } else if Currency::is_valid(token) {
+            Ok(Some(token.into()))
+        } else {
+            Err("has an unknown currency")
+        }
+    }
+}
+
+/// Remove a sign from either end of `text`, returning whether it negates.
+fn take_sign(text: &str) -> (Option<bool>, &str) {
+    for (sign, negative) in [('-', true), ('\u{2212}', true), ('+', false)] {
+        if let Some(rest) = text.strip_prefix(sign) {
+            return (Some(negative), rest.trim_start());
+        }
+        if let Some(rest) = text.strip_suffix(sign) {
+            return (Some(negative), rest.trim_end());
+        }
+    }
+    (None, text)
+}
+
 // Arithmetic operations on references
 
 impl Add for &Amount {
@@ -518,6 +775,74 @@ mod tests {
         assert!(a.eq_with_tolerance(&d, dec!(0.0)));
     }
 
+    #[test]
+    fn test_parse() {
+        for (input, expected) in [
+            ("1,234.56 USD", Amount::new(dec!(1234.56), "USD")),
+            ("USD 1,234.56", Amount::new(dec!(1234.56), "USD")),
+            ("$1,234.56", Amount::new(dec!(1234.56), "USD")),
+            ("$1,234.56 USD", Amount::new(dec!(1234.56), "USD")),
+            ("-$5.00", Amount::new(dec!(-5.00), "USD")),
+            ("$-5.00", Amount::new(dec!(-5.00), "USD")),
+            ("5.00- EUR", Amount::new(dec!(-5.00), "EUR")),
+            ("(£50.00)", Amount::new(dec!(-50.00), "GBP")),
+            ("£(50.00)", Amount::new(dec!(-50.00), "GBP")),
+            ("+.5 BTC", Amount::new(dec!(0.5), "BTC")),
+            ("1 234 567 ¥", Amount::new(dec!(1234567), "JPY")),
+            ("CHF 1'000.05", Amount::new(dec!(1000.05), "CHF")),
+            ("10 AAPL", Amount::new(dec!(10), "AAPL")),
+            ("1,23,456 INR", Amount::new(dec!(123456), "INR")),
+        ] {
+            assert_eq!(Amount::parse(input), Ok(expected), "{input}");
+        }
+        assert_eq!("€3".parse::<Amount>().unwrap(), Amount::new(dec!(3), "EUR"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (input, reason) in [
+            ("", "has no number"),
+            ("N/A", "has no number"),
+            ("$", "has no number"),
+            ("1,234.56", "has no currency"),
+            ("1.234,56 EUR", "groups digits after the decimal separator"),
+            ("1.2.3 EUR", "has more than one decimal separator"),
+            ("12,50 EUR", "has misplaced digit grouping"),
+            ("1,2345 EUR", "has misplaced digit grouping"),
+            ("12x4 USD", "has an invalid number"),
+            ("(-5) USD", "has more than one sign"),
+            ("$5 EUR", "has two different currencies"),
+            ("5 dollars", "has an unknown currency"),
+        ] {
+            let err = Amount::parse(input).unwrap_err();
+            assert_eq!(err.reason, reason, "{input}");
+            assert_eq!(err.input, input);
+        }
+    }
+
+    #[test]
+    fn test_amount_parser_options() {
+        let parser = AmountParser::new().with_decimal_comma(true);
+        assert_eq!(
+            parser.parse_number("-12,50 €").unwrap(),
+            (dec!(-12.50), Some("EUR".into()))
+        );
+        assert_eq!(parser.parse_number("(7,00)").unwrap(), (dec!(-7.00), None));
+        assert_eq!(
+            parser.parse("1.234,5 kr").unwrap(),
+            Amount::new(dec!(1234.5), "SEK")
+        );
+
+        let mut symbols = CurrencySymbols::new();
+        symbols.insert("$", "CAD");
+        let parser = AmountParser::new()
+            .with_symbols(symbols)
+            .with_default_currency("CAD");
+        assert_eq!(parser.parse("$5").unwrap(), Amount::new(dec!(5), "CAD"));
+        assert_eq!(parser.parse("5").unwrap(), Amount::new(dec!(5), "CAD"));
+        assert!(parser.parse("5 €").is_err());
+    }
+
     #[test]
     #[allow(clippy::many_single_char_names)]
     fn test_eq_auto_tolerance() {
@@ -260,19 +260,20 @@ impl CostSpec {
     /// Resolve this cost spec to a concrete cost, given the number of units.
     ///
     /// If `number_total` is specified, the per-unit cost is calculated as
-    /// `number_total / units`.
+    /// `number_total / units`. The cost is dated `date` unless the spec has
+    /// a date.
     ///
-    /// Returns `None` if required fields (currency) are missing.
+    /// Returns `None` if required fields (number, currency) are missing, or
+    /// if a total cost is spread over zero units.
     #[must_use]
     pub fn resolve(&self, units: Decimal, date: NaiveDate) -> Option<Cost> {
         let currency = self.currency.clone()?;
 
         let number = if let Some(per) = self.number_per {
             per
-        } else if let Some(total) = self.number_total {
-            total / units.abs()
         } else {
-            return None;
+            let total = self.number_total?;
+            total.checked_div(units.abs())?
         };
 
         Some(Cost {
@@ -427,4 +428,26 @@ mod tests {
         assert_eq!(cost.number, dec!(150.00)); // 1500 / 10
         assert_eq!(cost.currency, "USD");
     }
+
+    #[test]
+    fn test_cost_spec_resolve_incomplete() {
+        let date = date(2024, 1, 15);
+        let total = CostSpec::empty()
+            .with_number_total(dec!(1500.00))
+            .with_currency("USD");
+
+        assert!(total.resolve(Decimal::ZERO, date).is_none());
+        assert!(
+            CostSpec::empty()
+                .with_currency("USD")
+                .resolve(dec!(10), date)
+                .is_none()
+        );
+        assert!(
+            CostSpec::empty()
+                .with_number_per(dec!(150))
+                .resolve(dec!(10), date)
+                .is_none()
+        );
+    }
 }
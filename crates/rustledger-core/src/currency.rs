@@ -0,0 +1,655 @@
+//! Currency codes and what is known about them.
+//!
+//! [`Currency`] is a commodity code checked against beancount's syntax: 2 to
+//! 24 characters, starting with an uppercase letter (or `/` for options and
+//! futures contracts), ending with an uppercase letter or digit, with
+//! digits, `'`, `.`, `_` and `-` allowed in between.
+//!
+//! [`CurrencyRegistry`] maps codes to a display name and a default number
+//! of decimal places. With the `iso4217` feature (on by default) it can be
+//! seeded with the ISO 4217 currencies and common cryptocurrencies; ledgers
+//! extend it with the `name` and `precision` metadata of their `commodity`
+//! directives.
+//!
+//! # Example
+//!
+//! ```
+//! use rustledger_core::{Commodity, Directive, MetaValue, NaiveDate};
+//! use rustledger_core::currency::{Currency, CurrencyRegistry};
+//!
+//! assert!(Currency::new("VACHR").is_ok());
+//! assert!(Currency::new("usd").is_err());
+//!
+//! let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+//! let mut vachr = Commodity::new(date, "VACHR");
+//! vachr.meta.insert("name".to_string(), MetaValue::String("Vacation hours".into()));
+//!
+//! let mut registry = CurrencyRegistry::new();
+//! registry.register_commodities(&[Directive::Commodity(vachr)]);
+//! assert_eq!(registry.name("VACHR"), Some("Vacation hours"));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{Directive, InternedStr, MetaValue};
+
+/// Longest currency code beancount accepts.
+pub const MAX_CURRENCY_LEN: usize = 24;
+
+/// A currency code that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid currency \"{code}\": {reason}")]
+pub struct InvalidCurrency {
+    /// The rejected code.
+    pub code: String,
+    /// Why it was rejected.
+    pub reason: &'static str,
+}
+
+/// A validated currency or commodity code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency(InternedStr);
+
+impl Currency {
+    /// Validate a currency code.
+    pub fn new(code: impl Into<InternedStr>) -> Result<Self, InvalidCurrency> {
+        let code = code.into();
+        match check(&code) {
+            Ok(()) => Ok(Self(code)),
+            Err(reason) => Err(InvalidCurrency {
+                code: code.to_string(),
+                reason,
+            }),
+        }
+    }
+
+    /// Whether `code` is a valid currency code.
+    #[must_use]
+    pub fn is_valid(code: &str) -> bool {
+        check(code).is_ok()
+    }
+
+    /// The code as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The code as an interned string, as used by [`Amount`](crate::Amount).
+    #[must_use]
+    pub const fn as_interned(&self) -> &InternedStr {
+        &self.0
+    }
+}
+
+/// Check a code against beancount's currency syntax.
+fn check(code: &str) -> Result<(), &'static str> {
+    let body = code.strip_prefix('/').unwrap_or(code);
+    let Some(first) = body.chars().next() else {
+        return Err("is empty");
+    };
+    if code.len() < 2 {
+        return Err("must be at least 2 characters");
+    }
+    if code.len() > MAX_CURRENCY_LEN {
+        return Err("must be at most 24 characters");
+    }
+    if !(first.is_ascii_uppercase() || (first.is_ascii_digit() && body.len() < code.len())) {
+        return Err("must start with an uppercase letter");
+    }
+    if !body
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(c))
+    {
+        return Err("may only contain uppercase letters, digits, and ' . _ -");
+    }
+    if !body
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        return Err("must end with an uppercase letter or digit");
+    }
+    Ok(())
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Currency {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Currency {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Currency {
+    type Err = InvalidCurrency;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = InvalidCurrency;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.0.to_string()
+    }
+}
+
+impl From<Currency> for InternedStr {
+    fn from(currency: Currency) -> Self {
+        currency.0
+    }
+}
+
+impl PartialEq<str> for Currency {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Currency {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// What is known about a currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CurrencyInfo {
+    /// Display name, e.g. "Swiss Franc".
+    pub name: Option<String>,
+    /// Usual number of decimal places, e.g. 2 for USD and 0 for JPY.
+    pub precision: Option<u32>,
+}
+
+/// Names and default precisions of currencies, by code.
+#[derive(Debug, Clone, Default)]
+pub struct CurrencyRegistry {
+    currencies: HashMap<String, CurrencyInfo>,
+}
+
+impl CurrencyRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the ISO 4217 currencies and common
+    /// cryptocurrencies.
+    #[cfg(feature = "iso4217")]
+    #[must_use]
+    pub fn iso4217() -> Self {
+        let mut registry = Self::new();
+        for &(code, name, precision) in ISO_4217.iter().chain(CRYPTO) {
+            registry.register(
+                code,
+                CurrencyInfo {
+                    name: Some(name.to_string()),
+                    precision,
+                },
+            );
+        }
+        registry
+    }
+
+    /// Add or replace a currency.
+    pub fn register(&mut self, code: impl Into<String>, info: CurrencyInfo) {
+        self.currencies.insert(code.into(), info);
+    }
+
+    /// Add the currencies declared by `commodity` directives.
+    ///
+    /// A `name` string and a `precision` number in a directive's metadata
+    /// override what the registry already knows; a declaration without them
+    /// only adds the code.
+    pub fn register_commodities<'a>(
+        &mut self,
+        directives: impl IntoIterator<Item = &'a Directive>,
+    ) {
+        for directive in directives {
+            let Directive::Commodity(comm) = directive else {
+                continue;
+            };
+            let info = self
+                .currencies
+                .entry(comm.currency.to_string())
+                .or_insert(CurrencyInfo {
+                    name: None,
+                    precision: None,
+                });
+            if let Some(MetaValue::String(name)) = comm.meta.get("name") {
+                info.name = Some(name.clone());
+            }
+            if let Some(MetaValue::Number(precision)) = comm.meta.get("precision") {
+                if let Some(precision) = precision.to_u32() {
+                    info.precision = Some(precision);
+                }
+            }
+        }
+    }
+
+    /// Information about a currency.
+    #[must_use]
+    pub fn get(&self, code: &str) -> Option<&CurrencyInfo> {
+        self.currencies.get(code)
+    }
+
+    /// Whether the registry knows a currency.
+    #[must_use]
+    pub fn contains(&self, code: &str) -> bool {
+        self.currencies.contains_key(code)
+    }
+
+    /// Display name of a currency.
+    #[must_use]
+    pub fn name(&self, code: &str) -> Option<&str> {
+        self.get(code)?.name.as_deref()
+    }
+
+    /// Usual number of decimal places of a currency.
+    #[must_use]
+    pub fn precision(&self, code: &str) -> Option<u32> {
+        self.get(code)?.precision
+    }
+
+    /// Number of known currencies.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.currencies.len()
+    }
+
+    /// Whether no currencies are known.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.currencies.is_empty()
+    }
+}
+
+/// Currency symbols and the codes they stand for, e.g. `$` for `USD`.
+///
+/// Used by [`AmountParser`](crate::amount::AmountParser) to read amounts
+/// written the way banks and people write them. [`CurrencySymbols::common`]
+/// holds widely used symbols; symbols shared by several currencies (`$`,
+/// `¥`, `kr`) map to the most common one and can be overridden.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CurrencySymbols {
+    symbols: HashMap<String, InternedStr>,
+}
+
+impl CurrencySymbols {
+    /// Create an empty symbol map.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a symbol map with common currency symbols.
+    #[must_use]
+    pub fn common() -> Self {
+        let mut symbols = Self::new();
+        for &(symbol, code) in COMMON_SYMBOLS {
+            symbols.insert(symbol, code);
+        }
+        symbols
+    }
+
+    /// Add or replace a symbol.
+    pub fn insert(&mut self, symbol: impl Into<String>, currency: impl Into<InternedStr>) {
+        self.symbols.insert(symbol.into(), currency.into());
+    }
+
+    /// The currency a symbol stands for.
+    #[must_use]
+    pub fn get(&self, symbol: &str) -> Option<&InternedStr> {
+        self.symbols.get(symbol)
+    }
+
+    /// Number of known symbols.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Whether no symbols are known.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+/// Common currency symbols: symbol, code.
+const COMMON_SYMBOLS: &[(&str, &str)] = &[
+    ("$", "USD"),
+    ("US$", "USD"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₹", "INR"),
+    ("₩", "KRW"),
+    ("₽", "RUB"),
+    ("₺", "TRY"),
+    ("₪", "ILS"),
+    ("₫", "VND"),
+    ("₱", "PHP"),
+    ("฿", "THB"),
+    ("₴", "UAH"),
+    ("zł", "PLN"),
+    ("Kč", "CZK"),
+    ("kr", "SEK"),
+    ("Fr.", "CHF"),
+    ("A$", "AUD"),
+    ("C$", "CAD"),
+    ("NZ$", "NZD"),
+    ("HK$", "HKD"),
+    ("S$", "SGD"),
+    ("R$", "BRL"),
+    ("₿", "BTC"),
+];
+
+/// Active ISO 4217 currencies and precious metals: code, name, minor units.
+#[cfg(feature = "iso4217")]
+const ISO_4217: &[(&str, &str, Option<u32>)] = &[
+    ("AED", "UAE Dirham", Some(2)),
+    ("AFN", "Afghani", Some(2)),
+    ("ALL", "Lek", Some(2)),
+    ("AMD", "Armenian Dram", Some(2)),
+    ("ANG", "Netherlands Antillean Guilder", Some(2)),
+    ("AOA", "Kwanza", Some(2)),
+    ("ARS", "Argentine Peso", Some(2)),
+    ("AUD", "Australian Dollar", Some(2)),
+    ("AWG", "Aruban Florin", Some(2)),
+    ("AZN", "Azerbaijan Manat", Some(2)),
+    ("BAM", "Convertible Mark", Some(2)),
+    ("BBD", "Barbados Dollar", Some(2)),
+    ("BDT", "Taka", Some(2)),
+    ("BGN", "Bulgarian Lev", Some(2)),
+    ("BHD", "Bahraini Dinar", Some(3)),
+    ("BIF", "Burundi Franc", Some(0)),
+    ("BMD", "Bermudian Dollar", Some(2)),
+    ("BND", "Brunei Dollar", Some(2)),
+    ("BOB", "Boliviano", Some(2)),
+    ("BRL", "Brazilian Real", Some(2)),
+    ("BSD", "Bahamian Dollar", Some(2)),
+    ("BTN", "Ngultrum", Some(2)),
+    ("BWP", "Pula", Some(2)),
+    ("BYN", "Belarusian Ruble", Some(2)),
+    ("BZD", "Belize Dollar", Some(2)),
+    ("CAD", "Canadian Dollar", Some(2)),
+    ("CDF", "Congolese Franc", Some(2)),
+    ("CHF", "Swiss Franc", Some(2)),
+    ("CLP", "Chilean Peso", Some(0)),
+    ("CNY", "Yuan Renminbi", Some(2)),
+    ("COP", "Colombian Peso", Some(2)),
+    ("CRC", "Costa Rican Colon", Some(2)),
+    ("CUP", "Cuban Peso", Some(2)),
+    ("CVE", "Cabo Verde Escudo", Some(2)),
+    ("CZK", "Czech Koruna", Some(2)),
+    ("DJF", "Djibouti Franc", Some(0)),
+    ("DKK", "Danish Krone", Some(2)),
+    ("DOP", "Dominican Peso", Some(2)),
+    ("DZD", "Algerian Dinar", Some(2)),
+    ("EGP", "Egyptian Pound", Some(2)),
+    ("ERN", "Nakfa", Some(2)),
+    ("ETB", "Ethiopian Birr", Some(2)),
+    ("EUR", "Euro", Some(2)),
+    ("FJD", "Fiji Dollar", Some(2)),
+    ("FKP", "Falkland Islands Pound", Some(2)),
+    ("GBP", "Pound Sterling", Some(2)),
+    ("GEL", "Lari", Some(2)),
+    ("GHS", "Ghana Cedi", Some(2)),
+    ("GIP", "Gibraltar Pound", Some(2)),
+    ("GMD", "Dalasi", Some(2)),
+    ("GNF", "Guinean Franc", Some(0)),
+    ("GTQ", "Quetzal", Some(2)),
+    ("GYD", "Guyana Dollar", Some(2)),
+    ("HKD", "Hong Kong Dollar", Some(2)),
+    ("HNL", "Lempira", Some(2)),
+    ("HTG", "Gourde", Some(2)),
+    ("HUF", "Forint", Some(2)),
+    ("IDR", "Rupiah", Some(2)),
+    ("ILS", "New Israeli Sheqel", Some(2)),
+    ("INR", "Indian Rupee", Some(2)),
+    ("IQD", "Iraqi Dinar", Some(3)),
+    ("IRR", "Iranian Rial", Some(2)),
+    ("ISK", "Iceland Krona", Some(0)),
+    ("JMD", "Jamaican Dollar", Some(2)),
+    ("JOD", "Jordanian Dinar", Some(3)),
+    ("JPY", "Yen", Some(0)),
+    ("KES", "Kenyan Shilling", Some(2)),
+    ("KGS", "Som", Some(2)),
+    ("KHR", "Riel", Some(2)),
+    ("KMF", "Comorian Franc", Some(0)),
+    ("KPW", "North Korean Won", Some(2)),
+    ("KRW", "Won", Some(0)),
+    ("KWD", "Kuwaiti Dinar", Some(3)),
+    ("KYD", "Cayman Islands Dollar", Some(2)),
+    ("KZT", "Tenge", Some(2)),
+    ("LAK", "Lao Kip", Some(2)),
+    ("LBP", "Lebanese Pound", Some(2)),
+    ("LKR", "Sri Lanka Rupee", Some(2)),
+    ("LRD", "Liberian Dollar", Some(2)),
+    ("LSL", "Loti", Some(2)),
+    ("LYD", "Libyan Dinar", Some(3)),
+    ("MAD", "Moroccan Dirham", Some(2)),
+    ("MDL", "Moldovan Leu", Some(2)),
+    ("MGA", "Malagasy Ariary", Some(2)),
+    ("MKD", "Denar", Some(2)),
+    ("MMK", "Kyat", Some(2)),
+    ("MNT", "Tugrik", Some(2)),
+    ("MOP", "Pataca", Some(2)),
+    ("MRU", "Ouguiya", Some(2)),
+    ("MUR", "Mauritius Rupee", Some(2)),
+    ("MVR", "Rufiyaa", Some(2)),
+    ("MWK", "Malawi Kwacha", Some(2)),
+    ("MXN", "Mexican Peso", Some(2)),
+    ("MYR", "Malaysian Ringgit", Some(2)),
+    ("MZN", "Mozambique Metical", Some(2)),
+    ("NAD", "Namibia Dollar", Some(2)),
+    ("NGN", "Naira", Some(2)),
+    ("NIO", "Cordoba Oro", Some(2)),
+    ("NOK", "Norwegian Krone", Some(2)),
+    ("NPR", "Nepalese Rupee", Some(2)),
+    ("NZD", "New Zealand Dollar", Some(2)),
+    ("OMR", "Rial Omani", Some(3)),
+    ("PAB", "Balboa", Some(2)),
+    ("PEN", "Sol", Some(2)),
+    ("PGK", "Kina", Some(2)),
+    ("PHP", "Philippine Peso", Some(2)),
+    ("PKR", "Pakistan Rupee", Some(2)),
+    ("PLN", "Zloty", Some(2)),
+    ("PYG", "Guarani", Some(0)),
+    ("QAR", "Qatari Rial", Some(2)),
+    ("RON", "Romanian Leu", Some(2)),
+    ("RSD", "Serbian Dinar", Some(2)),
+    ("RUB", "Russian Ruble", Some(2)),
+    ("RWF", "Rwanda Franc", Some(0)),
+    ("SAR", "Saudi Riyal", Some(2)),
+    ("SBD", "Solomon Islands Dollar", Some(2)),
+    ("SCR", "Seychelles Rupee", Some(2)),
+    ("SDG", "Sudanese Pound", Some(2)),
+    ("SEK", "Swedish Krona", Some(2)),
+    ("SGD", "Singapore Dollar", Some(2)),
+    ("SHP", "Saint Helena Pound", Some(2)),
+    ("SLE", "Leone", Some(2)),
+    ("SOS", "Somali Shilling", Some(2)),
+    ("SRD", "Surinam Dollar", Some(2)),
+    ("SSP", "South Sudanese Pound", Some(2)),
+    ("STN", "Dobra", Some(2)),
+    ("SVC", "El Salvador Colon", Some(2)),
+    ("SYP", "Syrian Pound", Some(2)),
+    ("SZL", "Lilangeni", Some(2)),
+    ("THB", "Baht", Some(2)),
+    ("TJS", "Somoni", Some(2)),
+    ("TMT", "Turkmenistan New Manat", Some(2)),
+    ("TND", "Tunisian Dinar", Some(3)),
+    ("TOP", "Pa'anga", Some(2)),
+    ("TRY", "Turkish Lira", Some(2)),
+    ("TTD", "Trinidad and Tobago Dollar", Some(2)),
+    ("TWD", "New Taiwan Dollar", Some(2)),
+    ("TZS", "Tanzanian Shilling", Some(2)),
+    ("UAH", "Hryvnia", Some(2)),
+    ("UGX", "Uganda Shilling", Some(0)),
+    ("USD", "US Dollar", Some(2)),
+    ("UYU", "Peso Uruguayo", Some(2)),
+    ("UZS", "Uzbekistan Sum", Some(2)),
+    ("VES", "Bolivar Soberano", Some(2)),
+    ("VND", "Dong", Some(0)),
+    ("VUV", "Vatu", Some(0)),
+    ("WST", "Tala", Some(2)),
+    ("XAF", "CFA Franc BEAC", Some(0)),
+    ("XAG", "Silver", None),
+    ("XAU", "Gold", None),
+    ("XCD", "East Caribbean Dollar", Some(2)),
+    ("XDR", "SDR (Special Drawing Right)", None),
+    ("XOF", "CFA Franc BCEAO", Some(0)),
+    ("XPD", "Palladium", None),
+    ("XPF", "CFP Franc", Some(0)),
+    ("XPT", "Platinum", None),
+    ("YER", "Yemeni Rial", Some(2)),
+    ("ZAR", "Rand", Some(2)),
+    ("ZMW", "Zambian Kwacha", Some(2)),
+    ("ZWG", "Zimbabwe Gold", Some(2)),
+];
+
+/// Common cryptocurrencies: code, name, smallest unit.
+#[cfg(feature = "iso4217")]
+const CRYPTO: &[(&str, &str, Option<u32>)] = &[
+    ("ADA", "Cardano", Some(6)),
+    ("BTC", "Bitcoin", Some(8)),
+    ("DOGE", "Dogecoin", Some(8)),
+    ("DOT", "Polkadot", Some(10)),
+    ("ETH", "Ether", Some(18)),
+    ("LTC", "Litecoin", Some(8)),
+    ("SOL", "Solana", Some(9)),
+    ("USDC", "USD Coin", Some(6)),
+    ("USDT", "Tether", Some(6)),
+    ("XMR", "Monero", Some(12)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Commodity, NaiveDate};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_currency_syntax() {
+        for code in [
+            "USD", "VACHR", "BRK.B", "RGAGX_2", "TEST'S", "/ESZ24", "/6EZ4",
+        ] {
+            assert!(Currency::is_valid(code), "{code} should be valid");
+        }
+
+        let reason = |code: &str| Currency::new(code).unwrap_err().reason;
+        assert_eq!(reason(""), "is empty");
+        assert_eq!(reason("U"), "must be at least 2 characters");
+        assert_eq!(reason(&"A".repeat(25)), "must be at most 24 characters");
+        assert_eq!(reason("usd"), "must start with an uppercase letter");
+        assert_eq!(reason("1USD"), "must start with an uppercase letter");
+        assert_eq!(
+            reason("US D"),
+            "may only contain uppercase letters, digits, and ' . _ -"
+        );
+        assert_eq!(reason("USD-"), "must end with an uppercase letter or digit");
+
+        let err = Currency::new("usd").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid currency \"usd\": must start with an uppercase letter"
+        );
+    }
+
+    #[test]
+    fn test_currency_conversions() {
+        let currency: Currency = "EUR".parse().unwrap();
+        assert_eq!(currency, "EUR");
+        assert_eq!(currency.to_string(), "EUR");
+        assert_eq!(InternedStr::from(currency.clone()), "EUR");
+
+        let json = serde_json::to_string(&currency).unwrap();
+        assert_eq!(json, "\"EUR\"");
+        assert_eq!(serde_json::from_str::<Currency>(&json).unwrap(), currency);
+        assert!(serde_json::from_str::<Currency>("\"eur\"").is_err());
+    }
+
+    #[cfg(feature = "iso4217")]
+    #[test]
+    fn test_iso4217_registry() {
+        let registry = CurrencyRegistry::iso4217();
+        assert_eq!(registry.name("CHF"), Some("Swiss Franc"));
+        assert_eq!(registry.precision("USD"), Some(2));
+        assert_eq!(registry.precision("JPY"), Some(0));
+        assert_eq!(registry.precision("KWD"), Some(3));
+        assert_eq!(registry.precision("XAU"), None);
+        assert_eq!(registry.precision("BTC"), Some(8));
+        assert!(!registry.contains("AAPL"));
+        assert!(
+            ISO_4217
+                .iter()
+                .chain(CRYPTO)
+                .all(|(code, _, _)| Currency::is_valid(code))
+        );
+    }
+
+    #[test]
+    fn test_register_commodities() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut usd = Commodity::new(date, "USD");
+        usd.meta
+            .insert("name".to_string(), MetaValue::String("Dollar".to_string()));
+        let mut vachr = Commodity::new(date, "VACHR");
+        vachr
+            .meta
+            .insert("precision".to_string(), MetaValue::Number(dec!(1)));
+        let directives = vec![
+            Directive::Commodity(usd),
+            Directive::Commodity(vachr),
+            Directive::Commodity(Commodity::new(date, "AAPL")),
+        ];
+
+        let mut registry = CurrencyRegistry::new();
+        registry.register(
+            "USD",
+            CurrencyInfo {
+                name: Some("US Dollar".to_string()),
+                precision: Some(2),
+            },
+        );
+        registry.register_commodities(&directives);
+
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.name("USD"), Some("Dollar"));
+        assert_eq!(registry.precision("USD"), Some(2));
+        assert_eq!(registry.precision("VACHR"), Some(1));
+        assert!(registry.contains("AAPL"));
+        assert_eq!(registry.name("AAPL"), None);
+    }
+}
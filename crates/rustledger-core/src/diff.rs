@@ -0,0 +1,321 @@
+//! Semantic diff between two sets of directives.
+//!
+//! [`diff_ledgers`] compares directives by content rather than by position,
+//! so reordering a file or moving entries between included files is not a
+//! change. Directives are first paired by their formatted text, which
+//! ignores the `filename`/`lineno` metadata the loader attaches. The rest
+//! are paired by identity (same date, directive type and key, such as the
+//! account of an `open` or the narration of a transaction) and reported as
+//! modified. Whatever is still unpaired was added or removed.
+//!
+//! Changing a directive's date or type therefore shows up as a removal and
+//! an addition rather than a modification.
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::NaiveDate;
+
+use crate::format::{FormatConfig, format_directive};
+use crate::{Directive, DirectivePriority};
+
+/// A difference between two sets of directives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A directive only present in the new set.
+    Added(Directive),
+    /// A directive only present in the old set.
+    Removed(Directive),
+    /// A directive whose content changed.
+    Modified {
+        /// The directive in the old set.
+        old: Directive,
+        /// The directive in the new set.
+        new: Directive,
+    },
+}
+
+impl Change {
+    /// The date of the change (the new date for modifications).
+    #[must_use]
+    pub const fn date(&self) -> NaiveDate {
+        match self {
+            Self::Added(directive)
+            | Self::Removed(directive)
+            | Self::Modified { new: directive, .. } => directive.date(),
+        }
+    }
+
+    /// The directive as it was before the change, if it existed.
+    #[must_use]
+    pub const fn before(&self) -> Option<&Directive> {
+        match self {
+            Self::Added(_) => None,
+            Self::Removed(directive) | Self::Modified { old: directive, .. } => Some(directive),
+        }
+    }
+
+    /// The directive as it is after the change, if it still exists.
+    #[must_use]
+    pub const fn after(&self) -> Option<&Directive> {
+        match self {
+            Self::Removed(_) => None,
+            Self::Added(directive) | Self::Modified { new: directive, .. } => Some(directive),
+        }
+    }
+
+    fn priority(&self) -> DirectivePriority {
+        self.after()
+            .or_else(|| self.before())
+            .map_or(DirectivePriority::Custom, Directive::priority)
+    }
+}
+
+/// Number of added, removed and modified directives in a diff.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    /// Directives only present in the new set.
+    pub added: usize,
+    /// Directives only present in the old set.
+    pub removed: usize,
+    /// Directives whose content changed.
+    pub modified: usize,
+}
+
+impl DiffSummary {
+    /// Count the changes in a diff.
+    #[must_use]
+    pub fn of(changes: &[Change]) -> Self {
+        let mut summary = Self::default();
+        for change in changes {
+            match change {
+                Change::Added(_) => summary.added += 1,
+                Change::Removed(_) => summary.removed += 1,
+                Change::Modified { .. } => summary.modified += 1,
+            }
+        }
+        summary
+    }
+
+    /// Whether there are no changes at all.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.modified == 0
+    }
+}
+
+/// Compare two sets of directives.
+///
+/// The changes are ordered by date, then by directive type as in
+/// [`sort_directives`](crate::sort_directives); within that, modifications
+/// come first, then removals, then additions, each in input order.
+#[must_use]
+pub fn diff_ledgers(old: &[Directive], new: &[Directive]) -> Vec<Change> {
+    let config = FormatConfig::default();
+
+    // Pair identical directives, in order, by their formatted text
+    let mut by_content: HashMap<String, VecDeque<usize>> = HashMap::new();
+    for (i, directive) in old.iter().enumerate() {
+        by_content
+            .entry(format_directive(directive, &config))
+            .or_default()
+            .push_back(i);
+    }
+
+    let mut old_unmatched = vec![true; old.len()];
+    let mut new_unmatched: Vec<usize> = Vec::new();
+    for (j, directive) in new.iter().enumerate() {
+        let matched = by_content
+            .get_mut(&format_directive(directive, &config))
+            .and_then(VecDeque::pop_front);
+        match matched {
+            Some(i) => old_unmatched[i] = false,
+            None => new_unmatched.push(j),
+        }
+    }
+
+    // Pair the remainder by identity, strongest key first
+    let mut modified: Vec<(usize, usize)> = Vec::new();
+    for pass in 0..IDENTITY_PASSES {
+        let mut by_identity: HashMap<String, VecDeque<usize>> = HashMap::new();
+        for (i, directive) in old.iter().enumerate() {
+            if old_unmatched[i] {
+                if let Some(key) = identity(directive, pass) {
+                    by_identity.entry(key).or_default().push_back(i);
+                }
+            }
+        }
+
+        new_unmatched.retain(|&j| {
+            let matched =
+                identity(&new[j], pass).and_then(|key| by_identity.get_mut(&key)?.pop_front());
+            match matched {
+                Some(i) => {
+                    old_unmatched[i] = false;
+                    modified.push((i, j));
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+
+    modified.sort_by_key(|&(_, j)| j);
+    let mut changes: Vec<Change> = modified
+        .into_iter()
+        .map(|(i, j)| Change::Modified {
+            old: old[i].clone(),
+            new: new[j].clone(),
+        })
+        .collect();
+    changes.extend(
+        old.iter()
+            .zip(&old_unmatched)
+            .filter(|(_, unmatched)| **unmatched)
+            .map(|(directive, _)| Change::Removed(directive.clone())),
+    );
+    changes.extend(
+        new_unmatched
+            .into_iter()
+            .map(|j| Change::Added(new[j].clone())),
+    );
+
+    changes.sort_by(|a, b| {
+        a.date()
+            .cmp(&b.date())
+            .then_with(|| a.priority().cmp(&b.priority()))
+    });
+    changes
+}
+
+/// Number of identity keys tried when pairing modified directives.
+const IDENTITY_PASSES: usize = 2;
+
+/// The identity key of a directive for the given pass.
+///
+/// Transactions are first paired by payee and narration, then by the set of
+/// accounts they post to; other directives have a single key.
+fn identity(directive: &Directive, pass: usize) -> Option<String> {
+    let date = directive.date();
+    let key = match (directive, pass) {
+        (Directive::Transaction(txn), 0) => format!(
+            "txn|{}|{}",
+            txn.payee.as_deref().unwrap_or_default(),
+            txn.narration
+        ),
+        (Directive::Transaction(txn), 1) => {
+            let mut accounts: Vec<&str> = txn.postings.iter().map(|p| p.account.as_str()).collect();
+            accounts.sort_unstable();
+            accounts.dedup();
+            format!("txn-accounts|{}", accounts.join(","))
+        }
+        (_, 1..) => return None,
+        (Directive::Open(open), _) => format!("open|{}", open.account),
+        (Directive::Close(close), _) => format!("close|{}", close.account),
+        (Directive::Commodity(commodity), _) => format!("commodity|{}", commodity.currency),
+        (Directive::Balance(balance), _) => {
+            format!("balance|{}|{}", balance.account, balance.amount.currency)
+        }
+        (Directive::Pad(pad), _) => format!("pad|{}", pad.account),
+        (Directive::Note(note), _) => format!("note|{}", note.account),
+        (Directive::Document(document), _) => format!("document|{}", document.account),
+        (Directive::Event(event), _) => format!("event|{}", event.event_type),
+        (Directive::Query(query), _) => format!("query|{}", query.name),
+        (Directive::Price(price), _) => {
+            format!("price|{}|{}", price.currency, price.amount.currency)
+        }
+        (Directive::Custom(custom), _) => format!("custom|{}", custom.custom_type),
+    };
+    Some(format!("{date}|{key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Amount, Close, MetaValue, Open, Posting, Price, Transaction};
+    use rust_decimal_macros::dec;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn txn(day: u32, narration: &str, amount: rust_decimal::Decimal) -> Directive {
+        Directive::Transaction(
+            Transaction::new(date(day), narration)
+                .with_posting(Posting::new("Expenses:Food", Amount::new(amount, "USD")))
+                .with_posting(Posting::new("Assets:Cash", Amount::new(-amount, "USD"))),
+        )
+    }
+
+    #[test]
+    fn test_identical_ledgers_in_any_order() {
+        let old = vec![
+            Directive::Open(Open::new(date(1), "Assets:Cash")),
+            txn(2, "Lunch", dec!(10)),
+        ];
+        let mut new: Vec<_> = old.iter().rev().cloned().collect();
+        // Source locations are not content
+        if let Directive::Open(open) = &mut new[1] {
+            open.meta
+                .insert("lineno".to_string(), MetaValue::Number(dec!(42)));
+        }
+        assert_eq!(diff_ledgers(&old, &new), []);
+    }
+
+    #[test]
+    fn test_added_removed_modified() {
+        let old = vec![
+            Directive::Open(Open::new(date(1), "Assets:Cash")),
+            txn(2, "Lunch", dec!(10)),
+            Directive::Close(Close::new(date(9), "Assets:Old")),
+        ];
+        let new = vec![
+            Directive::Open(Open::new(date(1), "Assets:Cash")),
+            txn(2, "Lunch", dec!(12)),
+            Directive::Price(Price::new(date(3), "EUR", Amount::new(dec!(1.1), "USD"))),
+        ];
+
+        let changes = diff_ledgers(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(
+            &changes[0],
+            Change::Modified { old, new } if old == &txn(2, "Lunch", dec!(10)) && new == &txn(2, "Lunch", dec!(12))
+        ));
+        assert!(matches!(&changes[1], Change::Added(Directive::Price(_))));
+        assert!(matches!(&changes[2], Change::Removed(Directive::Close(_))));
+        assert_eq!(changes[2].date(), date(9));
+        assert!(changes[2].after().is_none());
+
+        assert_eq!(
+            DiffSummary::of(&changes),
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                modified: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_transactions_paired_by_accounts_when_renamed() {
+        let old = vec![txn(2, "Lunch", dec!(10)), txn(2, "Dinner", dec!(30))];
+        let new = vec![
+            txn(2, "Dinner", dec!(30)),
+            txn(2, "Lunch at work", dec!(10)),
+        ];
+
+        let changes = diff_ledgers(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].before(), Some(&txn(2, "Lunch", dec!(10))));
+        assert_eq!(changes[0].after(), Some(&txn(2, "Lunch at work", dec!(10))));
+    }
+
+    #[test]
+    fn test_duplicates_are_counted() {
+        let old = vec![txn(2, "Coffee", dec!(3))];
+        let new = vec![txn(2, "Coffee", dec!(3)), txn(2, "Coffee", dec!(3))];
+
+        let changes = diff_ledgers(&old, &new);
+        assert_eq!(changes, [Change::Added(txn(2, "Coffee", dec!(3)))]);
+        assert!(!DiffSummary::of(&changes).is_empty());
+    }
+}
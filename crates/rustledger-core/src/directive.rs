@@ -15,15 +15,15 @@
 //! - [`Price`] - Record a price for a commodity
 //! - [`Custom`] - Custom directive type
 
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
-use crate::intern::InternedStr;
 #[cfg(feature = "rkyv")]
-use crate::intern::{AsDecimal, AsInternedStr, AsNaiveDate, AsOptionInternedStr, AsVecInternedStr};
+use crate::intern::{AsDecimal, AsInternedStr, AsNaiveDate, AsOptionInternedStr};
+use crate::intern::{InternedSet, InternedStr};
 use crate::{Amount, CostSpec, IncompleteAmount};
 
 /// Metadata value types.
@@ -103,6 +103,9 @@ pub struct Posting {
     pub flag: Option<char>,
     /// Posting metadata
     pub meta: Metadata,
+    /// Trailing comment on the posting line, without the leading `;`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
 impl Posting {
@@ -116,6 +119,7 @@ impl Posting {
             price: None,
             flag: None,
             meta: Metadata::new(),
+            comment: None,
         }
     }
 
@@ -129,6 +133,7 @@ impl Posting {
             price: None,
             flag: None,
             meta: Metadata::new(),
+            comment: None,
         }
     }
 
@@ -142,6 +147,7 @@ impl Posting {
             price: None,
             flag: None,
             meta: Metadata::new(),
+            comment: None,
         }
     }
 
@@ -172,6 +178,13 @@ impl Posting {
         self
     }
 
+    /// Add a trailing comment.
+    #[must_use]
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
     /// Check if this posting has an amount.
     #[must_use]
     pub const fn has_units(&self) -> bool {
@@ -395,6 +408,14 @@ impl Directive {
         }
     }
 
+    /// Get the time of day from the `time` metadata, if present and valid.
+    ///
+    /// See [`parse_time`] for the accepted formats.
+    #[must_use]
+    pub fn time(&self) -> Option<NaiveTime> {
+        meta_time(self.meta())
+    }
+
     /// Get the sorting priority for this directive.
     ///
     /// Used to determine order when directives have the same date.
@@ -417,18 +438,64 @@ impl Directive {
     }
 }
 
-/// Sort directives by date, then by type priority.
+/// Metadata key holding a directive's time of day (e.g. `time: "09:30"`).
+pub const TIME_META_KEY: &str = "time";
+
+/// Parse a time-of-day metadata value.
+///
+/// Accepts `HH:MM`, `HH:MM:SS` and `HH:MM:SS.fff`.
+#[must_use]
+pub fn parse_time(s: &str) -> Option<NaiveTime> {
+    let s = s.trim();
+    ["%H:%M:%S%.f", "%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(s, fmt).ok())
+}
+
+/// Read the time of day from a metadata map.
+fn meta_time(meta: &Metadata) -> Option<NaiveTime> {
+    match meta.get(TIME_META_KEY)? {
+        MetaValue::String(s) => parse_time(s),
+        _ => None,
+    }
+}
+
+/// Compare two directives in ledger order: by date, then type priority,
+/// then time of day (directives without a time come first).
+#[must_use]
+pub fn cmp_directives(a: &Directive, b: &Directive) -> std::cmp::Ordering {
+    a.date()
+        .cmp(&b.date())
+        .then_with(|| a.priority().cmp(&b.priority()))
+        .then_with(|| a.time().cmp(&b.time()))
+}
+
+/// Sort directives by date, then by type priority, then by time of day.
 ///
 /// This is a stable sort that preserves file order for directives
-/// with the same date and type.
+/// with the same date, type and time.
 pub fn sort_directives(directives: &mut [Directive]) {
-    directives.sort_by(|a, b| {
-        // Primary: date ascending
-        a.date()
-            .cmp(&b.date())
-            // Secondary: type priority
-            .then_with(|| a.priority().cmp(&b.priority()))
-    });
+    directives.sort_by(cmp_directives);
+}
+
+/// Assign intra-day sequence numbers.
+///
+/// Each directive gets its position among the directives sharing its date,
+/// counted in the given order (normally load order). Together with the date
+/// this gives every directive a stable identity that breaks ties between
+/// entries with the same time.
+#[must_use]
+pub fn intraday_sequence<'a>(directives: impl IntoIterator<Item = &'a Directive>) -> Vec<u32> {
+    let mut counters: HashMap<NaiveDate, u32> = HashMap::new();
+    directives
+        .into_iter()
+        .map(|d| {
+            let counter = counters.entry(d.date()).or_default();
+            let seq = *counter;
+            *counter += 1;
+            seq
+        })
+        .collect()
 }
 
 /// A transaction directive.
@@ -453,11 +520,9 @@ pub struct Transaction {
     #[cfg_attr(feature = "rkyv", rkyv(with = AsInternedStr))]
     pub narration: InternedStr,
     /// Tags attached to this transaction
-    #[cfg_attr(feature = "rkyv", rkyv(with = AsVecInternedStr))]
-    pub tags: Vec<InternedStr>,
+    pub tags: InternedSet,
     /// Links attached to this transaction
-    #[cfg_attr(feature = "rkyv", rkyv(with = AsVecInternedStr))]
-    pub links: Vec<InternedStr>,
+    pub links: InternedSet,
     /// Transaction metadata
     pub meta: Metadata,
     /// Postings (account entries)
@@ -473,8 +538,8 @@ impl Transaction {
             flag: '*',
             payee: None,
             narration: narration.into(),
-            tags: Vec::new(),
-            links: Vec::new(),
+            tags: InternedSet::new(),
+            links: InternedSet::new(),
             meta: Metadata::new(),
             postings: Vec::new(),
         }
@@ -497,14 +562,14 @@ impl Transaction {
     /// Add a tag.
     #[must_use]
     pub fn with_tag(mut self, tag: impl Into<InternedStr>) -> Self {
-        self.tags.push(tag.into());
+        self.tags.insert(tag);
         self
     }
 
     /// Add a link.
     #[must_use]
     pub fn with_link(mut self, link: impl Into<InternedStr>) -> Self {
-        self.links.push(link.into());
+        self.links.insert(link);
         self
     }
 
@@ -515,6 +580,12 @@ impl Transaction {
         self
     }
 
+    /// Get the time of day from the `time` metadata, if present and valid.
+    #[must_use]
+    pub fn time(&self) -> Option<NaiveTime> {
+        meta_time(&self.meta)
+    }
+
     /// Check if this transaction is marked as complete (*).
     #[must_use]
     pub const fn is_complete(&self) -> bool {
@@ -1071,11 +1142,9 @@ pub struct Document {
     /// File path to the document
     pub path: String,
     /// Tags
-    #[cfg_attr(feature = "rkyv", rkyv(with = AsVecInternedStr))]
-    pub tags: Vec<InternedStr>,
+    pub tags: InternedSet,
     /// Links
-    #[cfg_attr(feature = "rkyv", rkyv(with = AsVecInternedStr))]
-    pub links: Vec<InternedStr>,
+    pub links: InternedSet,
     /// Metadata
     pub meta: Metadata,
 }
@@ -1088,8 +1157,8 @@ impl Document {
             date,
             account: account.into(),
             path: path.into(),
-            tags: Vec::new(),
-            links: Vec::new(),
+            tags: InternedSet::new(),
+            links: InternedSet::new(),
             meta: Metadata::new(),
         }
     }
@@ -1097,14 +1166,14 @@ impl Document {
     /// Add a tag.
     #[must_use]
     pub fn with_tag(mut self, tag: impl Into<InternedStr>) -> Self {
-        self.tags.push(tag.into());
+        self.tags.insert(tag);
         self
     }
 
     /// Add a link.
     #[must_use]
     pub fn with_link(mut self, link: impl Into<InternedStr>) -> Self {
-        self.links.push(link.into());
+        self.links.insert(link);
         self
     }
 
@@ -1382,6 +1451,47 @@ mod tests {
         assert_eq!(directives[1].type_name(), "balance");
     }
 
+    #[test]
+    fn test_sort_directives_by_time() {
+        let timed = |narration: &str, time: &str| {
+            let mut txn = Transaction::new(date(2024, 1, 1), narration);
+            txn.meta.insert(
+                TIME_META_KEY.to_string(),
+                MetaValue::String(time.to_string()),
+            );
+            Directive::Transaction(txn)
+        };
+        let mut directives = vec![
+            timed("afternoon", "14:05"),
+            timed("morning", "09:30:15"),
+            Directive::Transaction(Transaction::new(date(2024, 1, 1), "untimed")),
+            timed("bad", "noon"),
+        ];
+
+        sort_directives(&mut directives);
+
+        let order: Vec<_> = directives
+            .iter()
+            .map(|d| match d {
+                Directive::Transaction(t) => t.narration.to_string(),
+                _ => unreachable!(),
+            })
+            .collect();
+        // Missing or unparseable times sort first, in file order
+        assert_eq!(order, ["untimed", "bad", "morning", "afternoon"]);
+        assert_eq!(directives[2].time(), NaiveTime::from_hms_opt(9, 30, 15));
+    }
+
+    #[test]
+    fn test_intraday_sequence() {
+        let directives = vec![
+            Directive::Transaction(Transaction::new(date(2024, 1, 1), "a")),
+            Directive::Transaction(Transaction::new(date(2024, 1, 2), "b")),
+            Directive::Transaction(Transaction::new(date(2024, 1, 1), "c")),
+        ];
+        assert_eq!(intraday_sequence(&directives), [0, 0, 1]);
+    }
+
     #[test]
     fn test_transaction_flags() {
         let make_txn = |flag: char| Transaction::new(date(2024, 1, 15), "Test").with_flag(flag);
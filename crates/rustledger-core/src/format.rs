@@ -2,11 +2,16 @@
 //!
 //! Provides pretty-printing for beancount directives with configurable
 //! amount alignment.
+//!
+//! Every directive type is rendered with its metadata. Metadata keys are
+//! written in sorted order, so output is stable across runs; internal keys
+//! (`filename`, `lineno` and anything starting with `__`) are omitted, as
+//! beancount's own printer does.
 
 use crate::{
     Amount, Balance, Close, Commodity, CostSpec, Custom, Directive, Document, Event,
-    IncompleteAmount, MetaValue, Note, Open, Pad, Posting, Price, PriceAnnotation, Query,
-    Transaction,
+    IncompleteAmount, MetaValue, Metadata, Note, Open, Pad, Posting, Price, PriceAnnotation, Query,
+    Transaction, currency::CurrencyRegistry,
 };
 use std::fmt::Write;
 
@@ -19,6 +24,12 @@ pub struct FormatConfig {
     pub indent: String,
     /// Indentation for metadata.
     pub meta_indent: String,
+    /// Currencies whose precision posting amounts are padded to.
+    ///
+    /// `10 USD` is written as `10.00 USD` when the registry gives USD a
+    /// precision of 2. Numbers are only ever extended with zeros, never
+    /// rounded.
+    pub currencies: Option<CurrencyRegistry>,
 }
 
 impl Default for FormatConfig {
@@ -27,6 +38,7 @@ impl Default for FormatConfig {
             amount_column: 60,
             indent: "  ".to_string(),
             meta_indent: "    ".to_string(),
+            currencies: None,
         }
     }
 }
@@ -53,6 +65,13 @@ impl FormatConfig {
         }
     }
 
+    /// Pad posting amounts to the precision of the currencies in `registry`.
+    #[must_use]
+    pub fn with_currency_precision(mut self, registry: CurrencyRegistry) -> Self {
+        self.currencies = Some(registry);
+        self
+    }
+
     /// Create a new config with both column and indent settings.
     #[must_use]
     pub fn new(column: usize, indent_width: usize) -> Self {
@@ -62,14 +81,16 @@ impl FormatConfig {
             amount_column: column,
             indent,
             meta_indent,
+            currencies: None,
         }
     }
 }
 
 /// Format a directive to a string.
 pub fn format_directive(directive: &Directive, config: &FormatConfig) -> String {
-    match directive {
-        Directive::Transaction(txn) => format_transaction(txn, config),
+    let mut out = match directive {
+        // Transaction metadata goes before the postings
+        Directive::Transaction(txn) => return format_transaction(txn, config),
         Directive::Balance(bal) => format_balance(bal),
         Directive::Open(open) => format_open(open),
         Directive::Close(close) => format_close(close),
@@ -81,6 +102,40 @@ pub fn format_directive(directive: &Directive, config: &FormatConfig) -> String
         Directive::Document(doc) => format_document(doc),
         Directive::Price(price) => format_price(price),
         Directive::Custom(custom) => format_custom(custom),
+    };
+    format_meta(&mut out, directive.meta(), &config.indent);
+    out
+}
+
+/// Stable identifier of a directive's content.
+///
+/// This is a 64-bit FNV-1a hash of the directive formatted with the default
+/// [`FormatConfig`], as 16 hex digits. Source locations are not part of the
+/// formatted text, so the id survives moving an entry to another line or
+/// file, and unlike `std` hashers it does not change between builds, so it
+/// can be stored or put in links.
+#[must_use]
+pub fn directive_id(directive: &Directive) -> String {
+    let text = format_directive(directive, &FormatConfig::default());
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Append metadata lines at the given indentation.
+fn format_meta(out: &mut String, meta: &Metadata, indent: &str) {
+    let mut keys: Vec<&String> = meta
+        .keys()
+        .filter(|key| !key.starts_with("__") && *key != "filename" && *key != "lineno")
+        .collect();
+    keys.sort();
+
+    for key in keys {
+        match &meta[key] {
+            MetaValue::None => writeln!(out, "{indent}{key}:").unwrap(),
+            value => writeln!(out, "{indent}{key}: {}", format_meta_value(value)).unwrap(),
+        }
     }
 }
 
@@ -110,21 +165,13 @@ fn format_transaction(txn: &Transaction, config: &FormatConfig) -> String {
     out.push('\n');
 
     // Transaction-level metadata
-    for (key, value) in &txn.meta {
-        writeln!(
-            out,
-            "{}{}: {}",
-            &config.indent,
-            key,
-            format_meta_value(value)
-        )
-        .unwrap();
-    }
+    format_meta(&mut out, &txn.meta, &config.indent);
 
-    // Postings
+    // Postings and their metadata
     for posting in &txn.postings {
         out.push_str(&format_posting(posting, config));
         out.push('\n');
+        format_meta(&mut out, &posting.meta, &config.meta_indent);
     }
 
     out
@@ -144,7 +191,10 @@ fn format_posting(posting: &Posting, config: &FormatConfig) -> String {
     line.push_str(&posting.account);
 
     // Units, cost, price
-    if let Some(incomplete_amount) = &posting.units {
+    if let Some(units) = &posting.units {
+        let padded = padded_units(units, config);
+        let incomplete_amount = padded.as_ref().unwrap_or(units);
+
         // Calculate padding to align amount
         let current_len = line.len();
         let amount_str = format_incomplete_amount(incomplete_amount);
@@ -165,6 +215,10 @@ fn format_posting(posting: &Posting, config: &FormatConfig) -> String {
         line.push_str(&amount_with_extras);
     }
 
+    if let Some(comment) = &posting.comment {
+        write!(line, "  ; {comment}").unwrap();
+    }
+
     line
 }
 
@@ -200,6 +254,24 @@ fn format_posting_incomplete_amount(
     out
 }
 
+/// Posting units extended to their currency's precision, when the config
+/// has a registry that knows it and the number has fewer decimal places.
+fn padded_units(units: &IncompleteAmount, config: &FormatConfig) -> Option<IncompleteAmount> {
+    let IncompleteAmount::Complete(amount) = units else {
+        return None;
+    };
+    let precision = config.currencies.as_ref()?.precision(&amount.currency)?;
+    if amount.number.scale() >= precision {
+        return None;
+    }
+    let mut number = amount.number;
+    number.rescale(precision);
+    Some(IncompleteAmount::Complete(Amount::new(
+        number,
+        amount.currency.clone(),
+    )))
+}
+
 /// Format the amount part of a posting (units + cost + price).
 #[allow(dead_code)]
 fn format_posting_amount(
@@ -233,12 +305,19 @@ fn format_amount(amount: &Amount) -> String {
 fn format_cost_spec(spec: &CostSpec) -> String {
     let mut parts = Vec::new();
 
-    // Amount (per-unit or total)
-    if let (Some(num), Some(curr)) = (&spec.number_per, &spec.currency) {
-        parts.push(format!("{num} {curr}"));
-    } else if let (Some(num), Some(curr)) = (&spec.number_total, &spec.currency) {
-        // Total cost uses double braces
-        return format!("{{{{{num} {curr}}}}}");
+    // Amount (per-unit, total, or both as `per # total`)
+    match (&spec.number_per, &spec.number_total, &spec.currency) {
+        (Some(per), Some(total), Some(curr)) => parts.push(format!("{per} # {total} {curr}")),
+        (Some(num), None, Some(curr)) => parts.push(format!("{num} {curr}")),
+        (None, Some(num), Some(curr)) => {
+            // Total cost uses double braces
+            if spec.date.is_none() && spec.label.is_none() && !spec.merge {
+                return format!("{{{{{num} {curr}}}}}");
+            }
+            parts.push(format!("# {num} {curr}"));
+        }
+        (None, None, Some(curr)) => parts.push(curr.to_string()),
+        _ => {}
     }
 
     // Date
@@ -283,21 +362,17 @@ fn format_meta_value(value: &MetaValue) -> String {
         MetaValue::Number(n) => n.to_string(),
         MetaValue::Amount(a) => format_amount(a),
         MetaValue::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
-        MetaValue::None => String::new(),
+        MetaValue::None => "NULL".to_string(),
     }
 }
 
 /// Format a balance directive.
 fn format_balance(bal: &Balance) -> String {
-    let mut out = format!(
-        "{} balance {} {}",
-        bal.date,
-        bal.account,
-        format_amount(&bal.amount)
-    );
+    let mut out = format!("{} balance {} {}", bal.date, bal.account, bal.amount.number);
     if let Some(tol) = &bal.tolerance {
         write!(out, " ~ {tol}").unwrap();
     }
+    write!(out, " {}", bal.amount.currency).unwrap();
     out.push('\n');
     out
 }
@@ -362,12 +437,20 @@ fn format_note(note: &Note) -> String {
 
 /// Format a document directive.
 fn format_document(doc: &Document) -> String {
-    format!(
-        "{} document {} \"{}\"\n",
+    let mut out = format!(
+        "{} document {} \"{}\"",
         doc.date,
         doc.account,
         escape_string(&doc.path)
-    )
+    );
+    for tag in &doc.tags {
+        write!(out, " #{tag}").unwrap();
+    }
+    for link in &doc.links {
+        write!(out, " ^{link}").unwrap();
+    }
+    out.push('\n');
+    out
 }
 
 /// Format a price directive.
@@ -382,14 +465,22 @@ fn format_price(price: &Price) -> String {
 
 /// Format a custom directive.
 fn format_custom(custom: &Custom) -> String {
-    format!(
-        "{} custom \"{}\"\n",
+    let mut out = format!(
+        "{} custom \"{}\"",
         custom.date,
         escape_string(&custom.custom_type)
-    )
+    );
+    for value in &custom.values {
+        write!(out, " {}", format_meta_value(value)).unwrap();
+    }
+    out.push('\n');
+    out
 }
 
-/// Escape a string for output (handle quotes and backslashes).
+/// Escape a string for output (quotes, backslashes and line breaks).
+///
+/// Tabs and carriage returns are escaped too, so that raw statement rows
+/// kept as metadata stay on one line.
 fn escape_string(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     for c in s.chars() {
@@ -397,6 +488,8 @@ fn escape_string(s: &str) -> String {
             '"' => out.push_str("\\\""),
             '\\' => out.push_str("\\\\"),
             '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
             _ => out.push(c),
         }
     }
@@ -456,10 +549,182 @@ mod tests {
         assert_eq!(formatted, "2024-01-01 open Assets:Bank:Checking USD,EUR\n");
     }
 
+    #[test]
+    fn test_format_balance_tolerance() {
+        let bal = Balance::new(
+            date(2024, 1, 1),
+            "Assets:Bank",
+            Amount::new(dec!(10.00), "USD"),
+        )
+        .with_tolerance(dec!(0.01));
+        let formatted = format_balance(&bal);
+        assert_eq!(
+            formatted,
+            "2024-01-01 balance Assets:Bank 10.00 ~ 0.01 USD\n"
+        );
+    }
+
+    #[test]
+    fn test_format_directive_meta_sorted() {
+        let mut meta = Metadata::new();
+        meta.insert("zeta".to_string(), MetaValue::Number(dec!(1.50)));
+        meta.insert("alpha".to_string(), MetaValue::Date(date(2024, 2, 1)));
+        meta.insert(
+            "amount".to_string(),
+            MetaValue::Amount(Amount::new(dec!(3), "EUR")),
+        );
+        meta.insert("empty".to_string(), MetaValue::None);
+        meta.insert(
+            "filename".to_string(),
+            MetaValue::String("x.beancount".to_string()),
+        );
+        meta.insert("__hidden".to_string(), MetaValue::Bool(true));
+        let note =
+            Directive::Note(Note::new(date(2024, 1, 1), "Assets:Bank", "Called").with_meta(meta));
+
+        let formatted = format_directive(&note, &FormatConfig::default());
+        assert_eq!(
+            formatted,
+            "2024-01-01 note Assets:Bank \"Called\"\n  \
+             alpha: 2024-02-01\n  \
+             amount: 3 EUR\n  \
+             empty:\n  \
+             zeta: 1.50\n"
+        );
+    }
+
+    #[test]
+    fn test_format_posting_meta_and_comment() {
+        let mut posting = Posting::new("Assets:Cash", Amount::new(dec!(-5.00), "USD"))
+            .with_flag('!')
+            .with_comment("paid in coins");
+        posting
+            .meta
+            .insert("receipt".to_string(), MetaValue::Bool(true));
+        let txn = Transaction::new(date(2024, 1, 15), "Coffee")
+            .with_tag("morning")
+            .with_link("r-1")
+            .with_posting(Posting::auto("Expenses:Coffee").with_comment("balance"))
+            .with_posting(posting);
+
+        let config = FormatConfig::with_column(30);
+        let formatted = format_directive(&Directive::Transaction(txn), &config);
+        assert_eq!(
+            formatted,
+            "2024-01-15 * \"Coffee\" #morning ^r-1\n  \
+             Expenses:Coffee  ; balance\n  \
+             ! Assets:Cash      -5.00 USD  ; paid in coins\n    \
+             receipt: TRUE\n"
+        );
+    }
+
+    #[test]
+    fn test_format_document_tags_links() {
+        let doc = Document::new(date(2024, 1, 1), "Assets:Bank", "statements/jan.pdf")
+            .with_tag("statement")
+            .with_link("jan-2024");
+        assert_eq!(
+            format_document(&doc),
+            "2024-01-01 document Assets:Bank \"statements/jan.pdf\" #statement ^jan-2024\n"
+        );
+    }
+
+    #[test]
+    fn test_format_tags_sorted_and_deduplicated() {
+        let txn = Transaction::new(date(2024, 1, 1), "Trip")
+            .with_tag("travel")
+            .with_tag("food")
+            .with_tag("travel")
+            .with_link("b")
+            .with_link("a");
+        let formatted = format_transaction(&txn, &FormatConfig::default());
+        assert!(formatted.starts_with("2024-01-01 * \"Trip\" #food #travel ^a ^b\n"));
+    }
+
+    #[test]
+    fn test_format_custom_values() {
+        let custom = Custom::new(date(2024, 1, 1), "budget")
+            .with_value(MetaValue::Account("Expenses:Food".to_string()))
+            .with_value(MetaValue::String("monthly".to_string()))
+            .with_value(MetaValue::Amount(Amount::new(dec!(400), "USD")))
+            .with_value(MetaValue::Bool(false));
+        assert_eq!(
+            format_custom(&custom),
+            "2024-01-01 custom \"budget\" Expenses:Food \"monthly\" 400 USD FALSE\n"
+        );
+    }
+
+    #[test]
+    fn test_format_cost_spec() {
+        let per = CostSpec::empty()
+            .with_number_per(dec!(150))
+            .with_currency("USD")
+            .with_date(date(2024, 1, 1))
+            .with_label("lot1");
+        assert_eq!(format_cost_spec(&per), "{150 USD, 2024-01-01, \"lot1\"}");
+
+        let total = CostSpec::empty()
+            .with_number_total(dec!(1500))
+            .with_currency("USD");
+        assert_eq!(format_cost_spec(&total), "{{1500 USD}}");
+
+        let both = CostSpec::empty()
+            .with_number_per(dec!(150))
+            .with_number_total(dec!(5))
+            .with_currency("USD");
+        assert_eq!(format_cost_spec(&both), "{150 # 5 USD}");
+
+        assert_eq!(format_cost_spec(&CostSpec::empty()), "{}");
+    }
+
+    #[test]
+    fn test_directive_id() {
+        let txn = Transaction::new(date(2024, 1, 15), "Coffee")
+            .with_posting(Posting::new("Expenses:Food", Amount::new(dec!(5), "USD")))
+            .with_posting(Posting::auto("Assets:Cash"));
+        let id = directive_id(&Directive::Transaction(txn.clone()));
+        assert_eq!(id.len(), 16);
+
+        // Source locations don't change the id, content does
+        let mut moved = txn.clone();
+        moved
+            .meta
+            .insert("lineno".to_string(), MetaValue::Number(dec!(42)));
+        assert_eq!(directive_id(&Directive::Transaction(moved)), id);
+        let edited = txn.with_payee("Cafe");
+        assert_ne!(directive_id(&Directive::Transaction(edited)), id);
+    }
+
+    #[test]
+    fn test_format_pads_to_currency_precision() {
+        let mut registry = CurrencyRegistry::new();
+        for (code, precision) in [("USD", 2), ("JPY", 0)] {
+            registry.register(
+                code,
+                crate::CurrencyInfo {
+                    name: None,
+                    precision: Some(precision),
+                },
+            );
+        }
+        let config = FormatConfig::default().with_currency_precision(registry);
+
+        let padded = |amount: Amount| {
+            let posting = Posting::new("Assets:Cash", amount);
+            format_posting(&posting, &config)
+        };
+        assert!(padded(Amount::new(dec!(5), "USD")).ends_with(" 5.00 USD"));
+        assert!(padded(Amount::new(dec!(5.5), "USD")).ends_with(" 5.50 USD"));
+        assert!(padded(Amount::new(dec!(5.125), "USD")).ends_with(" 5.125 USD"));
+        assert!(padded(Amount::new(dec!(500), "JPY")).ends_with(" 500 JPY"));
+        assert!(padded(Amount::new(dec!(1), "VACHR")).ends_with(" 1 VACHR"));
+    }
+
     #[test]
     fn test_escape_string() {
         assert_eq!(escape_string("hello"), "hello");
         assert_eq!(escape_string("say \"hi\""), "say \\\"hi\\\"");
         assert_eq!(escape_string("line1\nline2"), "line1\\nline2");
+        assert_eq!(escape_string("a\tb\r"), "a\\tb\\r");
     }
 }
@@ -21,9 +21,13 @@
 //! // s3 is different
 //! assert!(!std::ptr::eq(s1.as_str().as_ptr(), s3.as_str().as_ptr()));
 //! ```
+//!
+//! Every [`StringInterner`] also feeds process-wide counters, read with
+//! [`stats`], which show how well interning deduplicates a workload.
 
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -231,6 +235,214 @@ impl std::borrow::Borrow<str> for InternedStr {
     }
 }
 
+/// A set of interned strings, such as the tags or links of a transaction.
+///
+/// Entries are kept sorted and free of duplicates, so two sets with the same
+/// members compare, hash and format identically whatever order they were
+/// written in. Membership tests use binary search and subset tests a single
+/// merge pass. The set dereferences to a sorted slice for iteration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct InternedSet(
+    #[cfg_attr(feature = "rkyv", rkyv(with = AsVecInternedStr))] Vec<InternedStr>,
+);
+
+impl InternedSet {
+    /// Create an empty set.
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add a string, returning `false` if it was already present.
+    pub fn insert(&mut self, value: impl Into<InternedStr>) -> bool {
+        let value = value.into();
+        match self.0.binary_search(&value) {
+            Ok(_) => false,
+            Err(index) => {
+                self.0.insert(index, value);
+                true
+            }
+        }
+    }
+
+    /// Remove a string, returning `false` if it was not present.
+    pub fn remove(&mut self, value: &str) -> bool {
+        match self.position(value) {
+            Ok(index) => {
+                self.0.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Check if the set contains a string.
+    pub fn contains(&self, value: &str) -> bool {
+        self.position(value).is_ok()
+    }
+
+    /// Check if every member of this set is also in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        if self.0.len() > other.0.len() {
+            return false;
+        }
+        let mut rest = other.0.iter();
+        self.0
+            .iter()
+            .all(|value| rest.any(|candidate| candidate == value))
+    }
+
+    /// Check if the two sets have no member in common.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        !self.0.iter().any(|value| other.contains(value))
+    }
+
+    /// The members as a sorted slice.
+    pub fn as_slice(&self) -> &[InternedStr] {
+        &self.0
+    }
+
+    fn position(&self, value: &str) -> Result<usize, usize> {
+        self.0.binary_search_by(|probe| probe.as_str().cmp(value))
+    }
+}
+
+impl std::ops::Deref for InternedSet {
+    type Target = [InternedStr];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Into<InternedStr>> FromIterator<T> for InternedSet {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut values: Vec<InternedStr> = iter.into_iter().map(Into::into).collect();
+        values.sort_unstable();
+        values.dedup();
+        Self(values)
+    }
+}
+
+impl<T: Into<InternedStr>> Extend<T> for InternedSet {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl From<Vec<InternedStr>> for InternedSet {
+    fn from(values: Vec<InternedStr>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a InternedSet {
+    type Item = &'a InternedStr;
+    type IntoIter = std::slice::Iter<'a, InternedStr>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for InternedSet {
+    type Item = InternedStr;
+    type IntoIter = std::vec::IntoIter<InternedStr>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<const N: usize> PartialEq<[&str; N]> for InternedSet {
+    fn eq(&self, other: &[&str; N]) -> bool {
+        self.0.len() == N && self.0.iter().zip(other).all(|(a, b)| a == b)
+    }
+}
+
+impl Serialize for InternedSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<InternedStr>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Process-wide interning counters, updated by every [`StringInterner`].
+static LOOKUPS: AtomicU64 = AtomicU64::new(0);
+static HITS: AtomicU64 = AtomicU64::new(0);
+static STRING_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Cumulative statistics of all string interners in the process.
+///
+/// Counters only grow: clearing or dropping an interner does not reset them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct InternStats {
+    /// Number of strings passed to an interner.
+    pub lookups: u64,
+    /// Lookups answered with an already interned string.
+    pub hits: u64,
+    /// Strings newly stored (lookups that missed).
+    pub strings: u64,
+    /// Bytes of string data newly stored.
+    pub bytes: u64,
+}
+
+impl InternStats {
+    /// Fraction of lookups that were deduplicated, between 0 and 1.
+    #[must_use]
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.lookups as f64
+        }
+    }
+}
+
+/// Read the process-wide interning statistics.
+///
+/// ```
+/// use rustledger_core::intern::{StringInterner, stats};
+///
+/// let before = stats();
+/// let mut interner = StringInterner::new();
+/// interner.intern("Assets:Bank");
+/// interner.intern("Assets:Bank");
+/// let after = stats();
+/// assert!(after.hits > before.hits);
+/// ```
+#[must_use]
+pub fn stats() -> InternStats {
+    let lookups = LOOKUPS.load(Ordering::Relaxed);
+    let hits = HITS.load(Ordering::Relaxed);
+    InternStats {
+        lookups,
+        hits,
+        strings: lookups.saturating_sub(hits),
+        bytes: STRING_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Record one interner lookup in the process-wide counters.
+fn record_lookup(hit: bool, len: usize) {
+    LOOKUPS.fetch_add(1, Ordering::Relaxed);
+    if hit {
+        HITS.fetch_add(1, Ordering::Relaxed);
+    } else {
+        STRING_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
 /// A string interner that deduplicates strings.
 ///
 /// This is useful for reducing memory usage when many strings with the
@@ -263,7 +475,9 @@ impl StringInterner {
     /// to the existing copy. Otherwise, stores the string and returns
     /// a reference to the new copy.
     pub fn intern(&mut self, s: &str) -> InternedStr {
-        if let Some(existing) = self.strings.get(s) {
+        let existing = self.strings.get(s);
+        record_lookup(existing.is_some(), s.len());
+        if let Some(existing) = existing {
             InternedStr(existing.clone())
         } else {
             let arc: Arc<str> = s.into();
@@ -274,7 +488,9 @@ impl StringInterner {
 
     /// Intern a string, taking ownership.
     pub fn intern_string(&mut self, s: String) -> InternedStr {
-        if let Some(existing) = self.strings.get(s.as_str()) {
+        let existing = self.strings.get(s.as_str());
+        record_lookup(existing.is_some(), s.len());
+        if let Some(existing) = existing {
             InternedStr(existing.clone())
         } else {
             let arc: Arc<str> = s.into();
@@ -298,6 +514,11 @@ impl StringInterner {
         self.strings.is_empty()
     }
 
+    /// Total bytes of string data held by this interner.
+    pub fn bytes(&self) -> usize {
+        self.strings.iter().map(|s| s.len()).sum()
+    }
+
     /// Get an iterator over all interned strings.
     pub fn iter(&self) -> impl Iterator<Item = &str> {
         self.strings.iter().map(std::convert::AsRef::as_ref)
@@ -530,6 +751,57 @@ mod tests {
         // s2 should find the same entry as s1
         assert_eq!(map.get(&s2), Some(&1));
     }
+
+    #[test]
+    fn test_intern_stats() {
+        // Counters are process-wide and other tests intern concurrently,
+        // so only check lower bounds
+        let before = stats();
+        let mut interner = StringInterner::new();
+        interner.intern("Expenses:Stats:Unique");
+        interner.intern("Expenses:Stats:Unique");
+        interner.intern_string("Expenses:Stats:Unique".to_string());
+        let after = stats();
+
+        assert!(after.lookups >= before.lookups + 3);
+        assert!(after.hits >= before.hits + 2);
+        assert!(after.bytes >= before.bytes + 21);
+        assert_eq!(interner.bytes(), 21);
+        assert!(after.hit_rate() > 0.0);
+        assert!((InternStats::default().hit_rate()).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_interned_set_normalizes() {
+        let mut set: InternedSet = ["trip", "food", "trip"].into_iter().collect();
+        assert_eq!(set, ["food", "trip"]);
+        assert!(!set.insert("food"));
+        assert!(set.insert("bar"));
+        assert_eq!(set, ["bar", "food", "trip"]);
+
+        let other: InternedSet = ["trip", "bar", "food"].into_iter().collect();
+        assert_eq!(set, other);
+        assert!(set.remove("bar"));
+        assert!(!set.remove("bar"));
+        assert!(set.contains("trip"));
+        assert!(!set.contains("bar"));
+    }
+
+    #[test]
+    fn test_interned_set_subset() {
+        let small: InternedSet = ["b", "d"].into_iter().collect();
+        let large: InternedSet = ["a", "b", "c", "d"].into_iter().collect();
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(InternedSet::new().is_subset(&small));
+        assert!(!small.is_disjoint(&large));
+        assert!(small.is_disjoint(&["a", "c"].into_iter().collect()));
+
+        let json = serde_json::to_string(&small).unwrap();
+        assert_eq!(json, r#"["b","d"]"#);
+        let parsed: InternedSet = serde_json::from_str(r#"["d","b","d"]"#).unwrap();
+        assert_eq!(parsed, small);
+    }
 }
 
 // rkyv wrapper for rust_decimal::Decimal - serialize as fixed 16 bytes
@@ -4,15 +4,16 @@
 //! [`Position`]s. It provides methods for adding and reducing positions
 //! using different booking methods (FIFO, LIFO, STRICT, NONE).
 
+use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::Signed;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 
 use crate::intern::InternedStr;
-use crate::{Amount, CostSpec, Position};
+use crate::{Amount, Cost, CostSpec, MixedAmount, Position};
 
 /// Booking method determines how lots are matched when reducing positions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
@@ -71,13 +72,131 @@ impl fmt::Display for BookingMethod {
     }
 }
 
+/// How to handle a reduction that exceeds the units held.
+///
+/// Accounts that are meant to go short (e.g. written options) use `Allow`
+/// or `Warn`; the excess is booked as a new negative lot instead of failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum NegativeLotsPolicy {
+    /// Go short silently.
+    Allow,
+    /// Go short, but report it.
+    Warn,
+    /// Reject the reduction.
+    #[default]
+    Error,
+}
+
+impl NegativeLotsPolicy {
+    /// Whether a reduction may go short under this policy.
+    #[must_use]
+    pub const fn allows_negative(self) -> bool {
+        !matches!(self, Self::Error)
+    }
+}
+
+impl FromStr for NegativeLotsPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("unknown negative lots policy: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for NegativeLotsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
 /// Result of a booking operation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BookingResult {
     /// Positions that were matched/reduced.
     pub matched: Vec<Position>,
+    /// The lots the reduction took units from, in matching order.
+    ///
+    /// Unlike [`matched`](Self::matched), each entry holds only the units
+    /// taken. AVERAGE booking reports a single lot at the average cost.
+    pub lots: Vec<LotMatch>,
     /// The cost basis of the matched positions (for capital gains).
     pub cost_basis: Option<Amount>,
+    /// The negative lot opened for units beyond those held, if the
+    /// reduction went short.
+    pub short: Option<Position>,
+}
+
+impl BookingResult {
+    /// Realized gain from reducing the matched lots at `price` per unit.
+    ///
+    /// Sums [`LotMatch::realized_gain`] over the lots held at a cost in
+    /// `price`'s currency; returns `None` if there are none.
+    pub fn realized_gain(&self, price: &Amount) -> Option<Amount> {
+        self.lots
+            .iter()
+            .filter_map(|lot| lot.realized_gain(price))
+            .reduce(|total, gain| Amount::new(total.number + gain.number, total.currency))
+    }
+}
+
+/// A lot matched by a reduction, and the units taken from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LotMatch {
+    /// Units taken from the lot, with the lot's sign: positive when a long
+    /// lot is sold, negative when a short lot is covered.
+    pub units: Amount,
+    /// The lot's cost, if it was held at cost.
+    pub cost: Option<Cost>,
+}
+
+impl LotMatch {
+    /// Acquisition date of the lot.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.cost.as_ref().and_then(|c| c.date)
+    }
+
+    /// Label of the lot.
+    pub fn label(&self) -> Option<&str> {
+        self.cost.as_ref().and_then(|c| c.label.as_deref())
+    }
+
+    /// Cost of the units taken.
+    pub fn cost_basis(&self) -> Option<Amount> {
+        self.cost.as_ref().map(|c| c.total_cost(self.units.number))
+    }
+
+    /// Gain from disposing of the taken units at `price` per unit.
+    ///
+    /// Returns `None` if the lot has no cost or its cost is in a different
+    /// currency than `price`.
+    pub fn realized_gain(&self, price: &Amount) -> Option<Amount> {
+        let cost = self.cost.as_ref()?;
+        if cost.currency != price.currency {
+            return None;
+        }
+        Some(Amount::new(
+            self.units.number * (price.number - cost.number),
+            price.currency.clone(),
+        ))
+    }
+}
+
+impl From<&Position> for LotMatch {
+    fn from(position: &Position) -> Self {
+        Self {
+            units: position.units.clone(),
+            cost: position.cost.clone(),
+        }
+    }
 }
 
 /// Error that can occur during booking.
@@ -170,7 +289,7 @@ impl std::error::Error for BookingError {}
 /// inv.add(Position::with_cost(Amount::new(dec!(10), "AAPL"), cost));
 /// assert_eq!(inv.units("AAPL"), dec!(10));
 /// ```
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[cfg_attr(
     feature = "rkyv",
     derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
@@ -179,7 +298,7 @@ pub struct Inventory {
     positions: Vec<Position>,
     /// Index for O(1) lookup of simple positions (no cost) by currency.
     /// Maps currency to position index in the `positions` Vec.
-    /// Not serialized - rebuilt on demand.
+    /// Not serialized - rebuilt on deserialization.
     #[serde(skip)]
     #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
     simple_index: HashMap<InternedStr, usize>,
@@ -194,6 +313,23 @@ impl PartialEq for Inventory {
 
 impl Eq for Inventory {}
 
+impl<'de> Deserialize<'de> for Inventory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct RawInventory {
+            positions: Vec<Position>,
+        }
+
+        let raw = RawInventory::deserialize(deserializer)?;
+        let mut inventory = Self {
+            positions: raw.positions,
+            simple_index: HashMap::new(),
+        };
+        inventory.rebuild_index();
+        Ok(inventory)
+    }
+}
+
 impl Inventory {
     /// Create an empty inventory.
     #[must_use]
@@ -258,13 +394,13 @@ impl Inventory {
     ///
     /// Returns the sum of all cost bases for positions of the given currency.
     #[must_use]
-    pub fn book_value(&self, units_currency: &str) -> HashMap<InternedStr, Decimal> {
-        let mut totals: HashMap<InternedStr, Decimal> = HashMap::new();
+    pub fn book_value(&self, units_currency: &str) -> MixedAmount {
+        let mut totals = MixedAmount::new();
 
         for pos in &self.positions {
             if pos.units.currency == units_currency {
                 if let Some(book) = pos.book_value() {
-                    *totals.entry(book.currency.clone()).or_default() += book.number;
+                    totals += &book;
                 }
             }
         }
@@ -331,6 +467,94 @@ impl Inventory {
         }
     }
 
+    /// Book units held at cost, as a posting with a cost spec would.
+    ///
+    /// When the inventory holds units of the currency with the opposite
+    /// sign, the units reduce matching lots with `method` and the booking
+    /// result is returned. Otherwise they are added as a new lot at
+    /// `cost_spec` resolved on `date`, and `None` is returned.
+    pub fn book_at_cost(
+        &mut self,
+        units: &Amount,
+        cost_spec: &CostSpec,
+        method: BookingMethod,
+        date: NaiveDate,
+    ) -> Result<Option<BookingResult>, BookingError> {
+        let held = self.units(&units.currency);
+        if !held.is_zero() && held.signum() != units.number.signum() {
+            return self.reduce(units, Some(cost_spec), method).map(Some);
+        }
+
+        let position = match cost_spec.resolve(units.number, date) {
+            Some(cost) => Position::with_cost(units.clone(), cost),
+            None => Position::simple(units.clone()),
+        };
+        self.add(position);
+        Ok(None)
+    }
+
+    /// Reduce positions, going short when `policy` allows it.
+    ///
+    /// Behaves like [`reduce`](Self::reduce), except that when the lots
+    /// matching `cost_spec` hold fewer units than requested and `policy`
+    /// allows negative lots, the matching lots are reduced to zero and the
+    /// excess is added as a new negative lot, returned in
+    /// [`BookingResult::short`]. The short lot carries the cost from
+    /// `cost_spec` when it is complete, dated `date` unless the spec has one.
+    pub fn reduce_with_policy(
+        &mut self,
+        units: &Amount,
+        cost_spec: Option<&CostSpec>,
+        method: BookingMethod,
+        policy: NegativeLotsPolicy,
+        date: NaiveDate,
+    ) -> Result<BookingResult, BookingError> {
+        if !policy.allows_negative() {
+            return self.reduce(units, cost_spec, method);
+        }
+
+        let spec = cost_spec.cloned().unwrap_or_default();
+        let available: Decimal = self
+            .positions
+            .iter()
+            .filter(|p| {
+                p.units.currency == units.currency
+                    && !p.is_empty()
+                    && p.units.number.signum() != units.number.signum()
+                    && p.matches_cost_spec(&spec)
+            })
+            .map(|p| p.units.number.abs())
+            .sum();
+        let requested = units.number.abs();
+        if requested <= available {
+            return self.reduce(units, cost_spec, method);
+        }
+
+        let mut result = if available.is_zero() {
+            BookingResult {
+                matched: vec![],
+                lots: vec![],
+                cost_basis: None,
+                short: None,
+            }
+        } else {
+            let held = Amount::new(available * units.number.signum(), units.currency.clone());
+            self.reduce(&held, cost_spec, method)?
+        };
+
+        let excess = Amount::new(
+            (requested - available) * units.number.signum(),
+            units.currency.clone(),
+        );
+        let short = match spec.resolve(excess.number, date) {
+            Some(cost) => Position::with_cost(excess, cost),
+            None => Position::simple(excess),
+        };
+        self.add(short.clone());
+        result.short = Some(short);
+        Ok(result)
+    }
+
     /// STRICT booking: require exactly one matching lot.
     /// Also allows "total match exception": if reduction equals total inventory, accept.
     fn reduce_strict(
@@ -545,8 +769,10 @@ impl Inventory {
         self.rebuild_index();
 
         Ok(BookingResult {
+            lots: matched.iter().map(LotMatch::from).collect(),
             matched,
             cost_basis: cost_currency.map(|c| Amount::new(cost_basis, c)),
+            short: None,
         })
     }
 
@@ -640,8 +866,10 @@ impl Inventory {
         self.rebuild_index();
 
         Ok(BookingResult {
+            lots: matched.iter().map(LotMatch::from).collect(),
             matched,
             cost_basis: cost_currency.map(|c| Amount::new(cost_basis, c)),
+            short: None,
         })
     }
 
@@ -675,12 +903,17 @@ impl Inventory {
 
         // Calculate total cost basis
         let book_values = self.book_value(&units.currency);
-        let cost_basis = if let Some((curr, &total)) = book_values.iter().next() {
-            let per_unit_cost = total / total_units;
-            Some(Amount::new(reduction * per_unit_cost, curr.clone()))
-        } else {
-            None
-        };
+        let average_cost = book_values
+            .iter()
+            .next()
+            .map(|(curr, &total)| Cost::new(total / total_units, curr.clone()));
+        let cost_basis = average_cost
+            .as_ref()
+            .map(|cost| Amount::new(reduction * cost.number, cost.currency.clone()));
+        let lots = vec![LotMatch {
+            units: Amount::new(reduction * total_units.signum(), units.currency.clone()),
+            cost: average_cost,
+        }];
 
         // Create merged position
         let new_units = total_units + units.number;
@@ -709,7 +942,9 @@ impl Inventory {
 
         Ok(BookingResult {
             matched,
+            lots,
             cost_basis,
+            short: None,
         })
     }
 
@@ -724,7 +959,9 @@ impl Inventory {
             self.add(Position::simple(units.clone()));
             return Ok(BookingResult {
                 matched: vec![],
+                lots: vec![],
                 cost_basis: None,
+                short: None,
             });
         }
 
@@ -781,8 +1018,10 @@ impl Inventory {
         }
 
         Ok(BookingResult {
+            lots: vec![LotMatch::from(&matched)],
             matched: vec![matched],
             cost_basis,
+            short: None,
         })
     }
 
@@ -860,6 +1099,122 @@ impl Inventory {
 
         result
     }
+
+    /// Compute the position-level differences from this inventory to `other`.
+    ///
+    /// Positions are grouped by currency and cost. Each delta holds the units
+    /// `other` has beyond `self`, so a positive delta is missing from `self`
+    /// and a negative delta is extra in `self`. Matching positions produce no
+    /// delta.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustledger_core::{Amount, Cost, Inventory, Position};
+    /// use rust_decimal_macros::dec;
+    ///
+    /// let actual = Inventory::new();
+    /// let expected: Inventory = [Position::with_cost(
+    ///     Amount::new(dec!(2), "AAPL"),
+    ///     Cost::new(dec!(150), "USD"),
+    /// )]
+    /// .into_iter()
+    /// .collect();
+    ///
+    /// let diff = actual.diff(&expected);
+    /// assert_eq!(diff.to_string(), "missing 2 AAPL {150 USD}");
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> InventoryDiff {
+        let mut deltas: Vec<Position> = Vec::new();
+
+        let sides = [
+            (&self.positions, Decimal::NEGATIVE_ONE),
+            (&other.positions, Decimal::ONE),
+        ];
+        for (positions, sign) in sides {
+            for pos in positions {
+                let existing = deltas
+                    .iter_mut()
+                    .find(|d| d.units.currency == pos.units.currency && d.cost == pos.cost);
+                if let Some(delta) = existing {
+                    delta.units.number += pos.units.number * sign;
+                } else {
+                    let mut delta = pos.clone();
+                    delta.units.number *= sign;
+                    deltas.push(delta);
+                }
+            }
+        }
+
+        deltas.retain(|d| !d.units.number.is_zero());
+        InventoryDiff { deltas }
+    }
+}
+
+/// Position-level differences between two inventories.
+///
+/// Produced by [`Inventory::diff`]. Displays as a comma-separated list such
+/// as `missing 2 AAPL {150 USD}, extra 5 USD`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryDiff {
+    deltas: Vec<Position>,
+}
+
+impl InventoryDiff {
+    /// Get the deltas (positive: missing from the left side, negative: extra).
+    #[must_use]
+    pub fn deltas(&self) -> &[Position] {
+        &self.deltas
+    }
+
+    /// Check if the two inventories held the same positions.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Positions present in the right side but missing from the left.
+    pub fn missing(&self) -> impl Iterator<Item = &Position> {
+        self.deltas
+            .iter()
+            .filter(|d| d.units.number.is_sign_positive())
+    }
+
+    /// Positions present in the left side beyond the right, as positive units.
+    pub fn extra(&self) -> impl Iterator<Item = Position> + '_ {
+        self.deltas
+            .iter()
+            .filter(|d| d.units.number.is_sign_negative())
+            .map(|d| Position {
+                units: -&d.units,
+                cost: d.cost.clone(),
+            })
+    }
+}
+
+impl fmt::Display for InventoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "(no differences)");
+        }
+
+        for (i, delta) in self.deltas.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if delta.units.number.is_sign_negative() {
+                let extra = Position {
+                    units: -&delta.units,
+                    cost: delta.cost.clone(),
+                };
+                write!(f, "extra {extra}")?;
+            } else {
+                write!(f, "missing {delta}")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for Inventory {
@@ -893,7 +1248,6 @@ impl FromIterator<Position> for Inventory {
 mod tests {
     use super::*;
     use crate::Cost;
-    use chrono::NaiveDate;
     use rust_decimal_macros::dec;
 
     fn date(year: i32, month: u32, day: u32) -> NaiveDate {
@@ -1055,6 +1409,127 @@ mod tests {
         assert_eq!(result.cost_basis.unwrap().number, dec!(2750.00));
     }
 
+    #[test]
+    fn test_reduce_lot_matches_and_realized_gain() {
+        let mut inv = Inventory::new();
+
+        let cost1 = Cost::new(dec!(100.00), "USD")
+            .with_date(date(2024, 1, 1))
+            .with_label("first");
+        let cost2 = Cost::new(dec!(150.00), "USD").with_date(date(2024, 2, 1));
+
+        inv.add(Position::with_cost(Amount::new(dec!(10), "AAPL"), cost1));
+        inv.add(Position::with_cost(Amount::new(dec!(10), "AAPL"), cost2));
+
+        let result = inv
+            .reduce(&Amount::new(dec!(-15), "AAPL"), None, BookingMethod::Fifo)
+            .unwrap();
+
+        assert_eq!(result.lots.len(), 2);
+        assert_eq!(result.lots[0].units, Amount::new(dec!(10), "AAPL"));
+        assert_eq!(result.lots[0].date(), Some(date(2024, 1, 1)));
+        assert_eq!(result.lots[0].label(), Some("first"));
+        assert_eq!(result.lots[1].units, Amount::new(dec!(5), "AAPL"));
+        assert_eq!(result.lots[1].date(), Some(date(2024, 2, 1)));
+        assert_eq!(result.lots[1].label(), None);
+        assert_eq!(
+            result.lots[1].cost_basis(),
+            Some(Amount::new(dec!(750.00), "USD"))
+        );
+
+        // 10 * (160 - 100) + 5 * (160 - 150) = 650
+        let price = Amount::new(dec!(160.00), "USD");
+        assert_eq!(
+            result.realized_gain(&price),
+            Some(Amount::new(dec!(650.00), "USD"))
+        );
+        assert_eq!(
+            result.lots[1].realized_gain(&price),
+            Some(Amount::new(dec!(50.00), "USD"))
+        );
+
+        // A price in another currency realizes nothing
+        assert_eq!(result.realized_gain(&Amount::new(dec!(160), "EUR")), None);
+    }
+
+    #[test]
+    fn test_book_at_cost() {
+        let mut inv = Inventory::new();
+        let spec = CostSpec::empty()
+            .with_number_per(dec!(100.00))
+            .with_currency("USD");
+
+        let result = inv
+            .book_at_cost(
+                &Amount::new(dec!(10), "AAPL"),
+                &spec,
+                BookingMethod::Strict,
+                date(2024, 1, 1),
+            )
+            .unwrap();
+        assert!(result.is_none());
+        let cost = inv.positions()[0].cost.as_ref().unwrap();
+        assert_eq!(cost.date, Some(date(2024, 1, 1)));
+
+        let result = inv
+            .book_at_cost(
+                &Amount::new(dec!(-4), "AAPL"),
+                &CostSpec::empty(),
+                BookingMethod::Strict,
+                date(2024, 2, 1),
+            )
+            .unwrap()
+            .expect("reduction");
+        assert_eq!(result.lots[0].units, Amount::new(dec!(4), "AAPL"));
+        assert_eq!(result.lots[0].date(), Some(date(2024, 1, 1)));
+        assert_eq!(inv.units("AAPL"), dec!(6));
+    }
+
+    #[test]
+    fn test_realized_gain_covering_short_lot() {
+        let mut inv = Inventory::new();
+        inv.add(Position::with_cost(
+            Amount::new(dec!(-10), "AAPL"),
+            Cost::new(dec!(150.00), "USD"),
+        ));
+
+        let result = inv
+            .reduce(&Amount::new(dec!(4), "AAPL"), None, BookingMethod::Fifo)
+            .unwrap();
+
+        assert_eq!(result.lots[0].units, Amount::new(dec!(-4), "AAPL"));
+        // Buying back below the short sale price is a gain: 4 * (150 - 140)
+        assert_eq!(
+            result.realized_gain(&Amount::new(dec!(140.00), "USD")),
+            Some(Amount::new(dec!(40.00), "USD"))
+        );
+    }
+
+    #[test]
+    fn test_reduce_average_lot_match() {
+        let mut inv = Inventory::new();
+        inv.add(Position::with_cost(
+            Amount::new(dec!(10), "AAPL"),
+            Cost::new(dec!(100.00), "USD"),
+        ));
+        inv.add(Position::with_cost(
+            Amount::new(dec!(10), "AAPL"),
+            Cost::new(dec!(200.00), "USD"),
+        ));
+
+        let result = inv
+            .reduce(&Amount::new(dec!(-5), "AAPL"), None, BookingMethod::Average)
+            .unwrap();
+
+        assert_eq!(result.lots.len(), 1);
+        assert_eq!(result.lots[0].units, Amount::new(dec!(5), "AAPL"));
+        assert_eq!(result.lots[0].cost.as_ref().unwrap().number, dec!(150));
+        assert_eq!(
+            result.realized_gain(&Amount::new(dec!(170), "USD")),
+            Some(Amount::new(dec!(100), "USD"))
+        );
+    }
+
     #[test]
     fn test_reduce_insufficient() {
         let mut inv = Inventory::new();
@@ -1069,6 +1544,101 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_reduce_with_policy_goes_short() {
+        let mut inv = Inventory::new();
+        let cost = Cost::new(dec!(150.00), "USD").with_date(date(2024, 1, 1));
+        inv.add(Position::with_cost(Amount::new(dec!(10), "AAPL"), cost));
+
+        let spec = CostSpec::empty()
+            .with_number_per(dec!(160.00))
+            .with_currency("USD");
+        let result = inv
+            .reduce_with_policy(
+                &Amount::new(dec!(-15), "AAPL"),
+                None,
+                BookingMethod::Fifo,
+                NegativeLotsPolicy::Allow,
+                date(2024, 2, 1),
+            )
+            .unwrap();
+
+        assert_eq!(inv.units("AAPL"), dec!(-5));
+        assert_eq!(result.cost_basis.unwrap().number, dec!(1500.00));
+        let short = result.short.unwrap();
+        assert_eq!(short.units.number, dec!(-5));
+        assert!(short.cost.is_none());
+
+        // Selling further short with a cost opens a lot at that cost
+        let result = inv
+            .reduce_with_policy(
+                &Amount::new(dec!(-2), "AAPL"),
+                Some(&spec),
+                BookingMethod::Fifo,
+                NegativeLotsPolicy::Warn,
+                date(2024, 3, 1),
+            )
+            .unwrap();
+        assert_eq!(result.matched.len(), 0);
+        let cost = result.short.unwrap().cost.unwrap();
+        assert_eq!(cost.number, dec!(160.00));
+        assert_eq!(cost.date, Some(date(2024, 3, 1)));
+        assert_eq!(inv.units("AAPL"), dec!(-7));
+    }
+
+    #[test]
+    fn test_reduce_with_policy_error() {
+        let mut inv = Inventory::new();
+        inv.add(Position::with_cost(
+            Amount::new(dec!(10), "AAPL"),
+            Cost::new(dec!(150.00), "USD"),
+        ));
+
+        let result = inv.reduce_with_policy(
+            &Amount::new(dec!(-15), "AAPL"),
+            None,
+            BookingMethod::Fifo,
+            NegativeLotsPolicy::Error,
+            date(2024, 2, 1),
+        );
+        assert!(matches!(
+            result,
+            Err(BookingError::InsufficientUnits { .. })
+        ));
+
+        // Within the units held, no short lot is opened
+        let mut inv = Inventory::new();
+        inv.add(Position::with_cost(
+            Amount::new(dec!(10), "AAPL"),
+            Cost::new(dec!(150.00), "USD"),
+        ));
+        let result = inv
+            .reduce_with_policy(
+                &Amount::new(dec!(-10), "AAPL"),
+                None,
+                BookingMethod::Fifo,
+                NegativeLotsPolicy::Allow,
+                date(2024, 2, 1),
+            )
+            .unwrap();
+        assert!(result.short.is_none());
+        assert!(inv.is_empty());
+    }
+
+    #[test]
+    fn test_negative_lots_policy_from_str() {
+        assert_eq!(
+            "allow".parse::<NegativeLotsPolicy>().unwrap(),
+            NegativeLotsPolicy::Allow
+        );
+        assert_eq!(
+            "WARN".parse::<NegativeLotsPolicy>().unwrap(),
+            NegativeLotsPolicy::Warn
+        );
+        assert!("sometimes".parse::<NegativeLotsPolicy>().is_err());
+        assert_eq!(NegativeLotsPolicy::default(), NegativeLotsPolicy::Error);
+    }
+
     #[test]
     fn test_book_value() {
         let mut inv = Inventory::new();
@@ -1108,4 +1678,76 @@ mod tests {
         let inv: Inventory = positions.into_iter().collect();
         assert_eq!(inv.units("USD"), dec!(150));
     }
+
+    #[test]
+    fn test_serde_roundtrip_rebuilds_index() {
+        let mut inv = Inventory::new();
+        inv.add(Position::simple(Amount::new(dec!(100), "USD")));
+        inv.add(Position::with_cost(
+            Amount::new(dec!(10), "AAPL"),
+            Cost::new(dec!(150), "USD"),
+        ));
+
+        let json = serde_json::to_string(&inv).unwrap();
+        let mut restored: Inventory = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, inv);
+
+        // Simple positions must still merge after deserialization
+        restored.add(Position::simple(Amount::new(dec!(50), "USD")));
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.units("USD"), dec!(150));
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let mut inv = Inventory::new();
+        inv.add(Position::simple(Amount::new(dec!(100), "USD")));
+
+        let diff = inv.diff(&inv.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "(no differences)");
+    }
+
+    #[test]
+    fn test_diff_missing_and_extra() {
+        let cost = Cost::new(dec!(150), "USD");
+
+        let mut actual = Inventory::new();
+        actual.add(Position::with_cost(
+            Amount::new(dec!(8), "AAPL"),
+            cost.clone(),
+        ));
+        actual.add(Position::simple(Amount::new(dec!(105), "USD")));
+
+        let mut expected = Inventory::new();
+        expected.add(Position::with_cost(Amount::new(dec!(10), "AAPL"), cost));
+        expected.add(Position::simple(Amount::new(dec!(100), "USD")));
+
+        let diff = actual.diff(&expected);
+        assert_eq!(diff.deltas().len(), 2);
+        assert_eq!(diff.missing().count(), 1);
+        assert_eq!(diff.extra().next().unwrap().units.number, dec!(5));
+        assert_eq!(diff.to_string(), "missing 2 AAPL {150 USD}, extra 5 USD");
+    }
+
+    #[test]
+    fn test_diff_distinguishes_lots() {
+        let mut actual = Inventory::new();
+        actual.add(Position::with_cost(
+            Amount::new(dec!(5), "AAPL"),
+            Cost::new(dec!(100), "USD"),
+        ));
+
+        let mut expected = Inventory::new();
+        expected.add(Position::with_cost(
+            Amount::new(dec!(5), "AAPL"),
+            Cost::new(dec!(150), "USD"),
+        ));
+
+        let diff = actual.diff(&expected);
+        assert_eq!(
+            diff.to_string(),
+            "extra 5 AAPL {100 USD}, missing 5 AAPL {150 USD}"
+        );
+    }
 }
@@ -3,12 +3,14 @@
 //! This crate provides the fundamental types used throughout the rustledger project:
 //!
 //! - [`Amount`] - A decimal number with a currency
+//! - [`MixedAmount`] - A sum of amounts in several currencies
 //! - [`Cost`] - Acquisition cost of a position (lot)
 //! - [`CostSpec`] - Specification for matching or creating costs
 //! - [`Position`] - Units held at a cost
 //! - [`Inventory`] - A collection of positions with booking support
 //! - [`BookingMethod`] - How to match lots when reducing positions
 //! - [`Directive`] - All directive types (Transaction, Balance, Open, etc.)
+//! - [`diff_ledgers`] - Semantic diff between two sets of directives
 //!
 //! # Example
 //!
@@ -44,26 +46,38 @@
 
 pub mod amount;
 pub mod cost;
+pub mod currency;
+pub mod diff;
 pub mod directive;
 pub mod format;
 pub mod intern;
 pub mod inventory;
+pub mod memory;
+pub mod mixed_amount;
+pub mod period;
 pub mod position;
 
-pub use amount::{Amount, IncompleteAmount};
+pub use amount::{Amount, AmountParser, IncompleteAmount, ParseAmountError};
 pub use cost::{Cost, CostSpec};
+pub use currency::{Currency, CurrencyInfo, CurrencyRegistry, CurrencySymbols};
+pub use diff::{Change, DiffSummary, diff_ledgers};
 pub use directive::{
     Balance, Close, Commodity, Custom, Directive, DirectivePriority, Document, Event, MetaValue,
-    Metadata, Note, Open, Pad, Posting, Price, PriceAnnotation, Query, Transaction,
-    sort_directives,
+    Metadata, Note, Open, Pad, Posting, Price, PriceAnnotation, Query, TIME_META_KEY, Transaction,
+    cmp_directives, intraday_sequence, parse_time, sort_directives,
 };
-pub use format::{FormatConfig, format_directive};
-pub use intern::{InternedStr, StringInterner};
-pub use inventory::{BookingError, BookingMethod, BookingResult, Inventory};
+pub use format::{FormatConfig, directive_id, format_directive};
+pub use intern::{InternedSet, InternedStr, StringInterner};
+pub use inventory::{
+    BookingError, BookingMethod, BookingResult, Inventory, InventoryDiff, LotMatch,
+    NegativeLotsPolicy,
+};
+pub use mixed_amount::MixedAmount;
+pub use period::{Period, PeriodKind, Periods};
 pub use position::Position;
 
 // Re-export commonly used external types
-pub use chrono::NaiveDate;
+pub use chrono::{NaiveDate, NaiveTime};
 pub use rust_decimal::Decimal;
 
 // Re-export rkyv wrappers when feature is enabled
@@ -0,0 +1,306 @@
+//! Approximate memory usage of a ledger.
+//!
+//! [`memory_usage`] walks the directives of a ledger and estimates the bytes
+//! they occupy, split by directive type, along with how much string data is
+//! shared through interning. The figures count inline sizes, vector and map
+//! capacities and string lengths; allocator overhead and hash table control
+//! bytes are ignored, so they are a lower bound meant for comparing ledgers
+//! and spotting what dominates, not an exact measurement.
+//!
+//! # Example
+//!
+//! ```
+//! use rustledger_core::{Directive, NaiveDate, Open, memory::memory_usage};
+//!
+//! let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+//! let directives = vec![
+//!     Directive::Open(Open::new(date, "Assets:Bank")),
+//!     Directive::Open(Open::new(date, "Assets:Cash")),
+//! ];
+//!
+//! let usage = memory_usage(&directives);
+//! assert_eq!(usage.by_type["open"].count, 2);
+//! assert_eq!(usage.strings.references, 2);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::mem::{size_of, size_of_val};
+
+use serde::Serialize;
+
+use crate::{
+    Amount, CostSpec, Directive, IncompleteAmount, InternedStr, MetaValue, Metadata,
+    PriceAnnotation,
+};
+
+/// Memory used by the directives of one type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TypeUsage {
+    /// Number of directives.
+    pub count: usize,
+    /// Approximate bytes, excluding interned string data.
+    pub bytes: usize,
+}
+
+/// How interned strings (accounts, currencies, payees, ...) are shared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StringUsage {
+    /// Interned string fields in the ledger.
+    pub references: usize,
+    /// Distinct allocations those fields point to.
+    pub allocations: usize,
+    /// Bytes of string data actually allocated.
+    pub bytes: usize,
+    /// Bytes the same fields would take if no string were shared.
+    pub unshared_bytes: usize,
+}
+
+/// Approximate memory usage of a ledger.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    /// Usage by directive type (`"transaction"`, `"price"`, ...).
+    pub by_type: BTreeMap<&'static str, TypeUsage>,
+    /// Interned string data, shared between directives.
+    pub strings: StringUsage,
+}
+
+impl MemoryUsage {
+    /// Total approximate bytes, including interned string data.
+    #[must_use]
+    pub fn total_bytes(&self) -> usize {
+        self.by_type
+            .values()
+            .map(|usage| usage.bytes)
+            .sum::<usize>()
+            + self.strings.bytes
+    }
+}
+
+/// Estimate the memory used by a ledger's directives.
+pub fn memory_usage<'a>(directives: impl IntoIterator<Item = &'a Directive>) -> MemoryUsage {
+    let mut counter = Counter::default();
+    let mut by_type: BTreeMap<&'static str, TypeUsage> = BTreeMap::new();
+
+    for directive in directives {
+        let bytes = size_of::<Directive>() + counter.directive_heap(directive);
+        let usage = by_type.entry(directive.type_name()).or_default();
+        usage.count += 1;
+        usage.bytes += bytes;
+    }
+
+    MemoryUsage {
+        by_type,
+        strings: StringUsage {
+            references: counter.references,
+            allocations: counter.allocations.len(),
+            bytes: counter.allocations.values().sum(),
+            unshared_bytes: counter.unshared_bytes,
+        },
+    }
+}
+
+/// Accumulates heap sizes and interned string sharing.
+#[derive(Default)]
+struct Counter {
+    /// Length of each distinct interned allocation, by address.
+    allocations: HashMap<usize, usize>,
+    references: usize,
+    unshared_bytes: usize,
+}
+
+impl Counter {
+    /// Record an interned string field. Its data is counted in
+    /// [`StringUsage`], not in the directive.
+    fn interned(&mut self, s: &str) {
+        self.references += 1;
+        self.unshared_bytes += s.len();
+        self.allocations.insert(s.as_ptr() as usize, s.len());
+    }
+
+    fn interned_vec(&mut self, strings: &[InternedStr]) -> usize {
+        for s in strings {
+            self.interned(s);
+        }
+        size_of_val(strings)
+    }
+
+    fn amount(&mut self, amount: &Amount) {
+        self.interned(&amount.currency);
+    }
+
+    fn incomplete_amount(&mut self, amount: &IncompleteAmount) {
+        if let Some(currency) = amount.currency() {
+            self.interned(currency);
+        }
+    }
+
+    fn cost(&mut self, cost: &CostSpec) -> usize {
+        if let Some(currency) = &cost.currency {
+            self.interned(currency);
+        }
+        cost.label.as_ref().map_or(0, String::capacity)
+    }
+
+    fn price(&mut self, price: &PriceAnnotation) {
+        match price {
+            PriceAnnotation::Unit(amount) | PriceAnnotation::Total(amount) => self.amount(amount),
+            PriceAnnotation::UnitIncomplete(amount) | PriceAnnotation::TotalIncomplete(amount) => {
+                self.incomplete_amount(amount);
+            }
+            PriceAnnotation::UnitEmpty | PriceAnnotation::TotalEmpty => {}
+        }
+    }
+
+    fn meta_value(value: &MetaValue) -> usize {
+        match value {
+            MetaValue::String(s)
+            | MetaValue::Account(s)
+            | MetaValue::Currency(s)
+            | MetaValue::Tag(s)
+            | MetaValue::Link(s) => s.capacity(),
+            _ => 0,
+        }
+    }
+
+    fn metadata(meta: &Metadata) -> usize {
+        meta.capacity() * size_of::<(String, MetaValue)>()
+            + meta
+                .iter()
+                .map(|(key, value)| key.capacity() + Self::meta_value(value))
+                .sum::<usize>()
+    }
+
+    /// Heap bytes owned by a directive, outside interned strings.
+    fn directive_heap(&mut self, directive: &Directive) -> usize {
+        let meta = Self::metadata(directive.meta());
+        meta + match directive {
+            Directive::Transaction(txn) => {
+                if let Some(payee) = &txn.payee {
+                    self.interned(payee);
+                }
+                self.interned(&txn.narration);
+                let mut bytes = self.interned_vec(&txn.tags) + self.interned_vec(&txn.links);
+                bytes += txn.postings.capacity() * size_of::<crate::Posting>();
+                for posting in &txn.postings {
+                    self.interned(&posting.account);
+                    if let Some(units) = &posting.units {
+                        self.incomplete_amount(units);
+                    }
+                    if let Some(cost) = &posting.cost {
+                        bytes += self.cost(cost);
+                    }
+                    if let Some(price) = &posting.price {
+                        self.price(price);
+                    }
+                    bytes += Self::metadata(&posting.meta);
+                    bytes += posting.comment.as_ref().map_or(0, String::capacity);
+                }
+                bytes
+            }
+            Directive::Balance(bal) => {
+                self.interned(&bal.account);
+                self.amount(&bal.amount);
+                0
+            }
+            Directive::Open(open) => {
+                self.interned(&open.account);
+                self.interned_vec(&open.currencies)
+                    + open.booking.as_ref().map_or(0, String::capacity)
+            }
+            Directive::Close(close) => {
+                self.interned(&close.account);
+                0
+            }
+            Directive::Commodity(commodity) => {
+                self.interned(&commodity.currency);
+                0
+            }
+            Directive::Pad(pad) => {
+                self.interned(&pad.account);
+                self.interned(&pad.source_account);
+                0
+            }
+            Directive::Event(event) => event.event_type.capacity() + event.value.capacity(),
+            Directive::Query(query) => query.name.capacity() + query.query.capacity(),
+            Directive::Note(note) => {
+                self.interned(&note.account);
+                note.comment.capacity()
+            }
+            Directive::Document(doc) => {
+                self.interned(&doc.account);
+                doc.path.capacity() + self.interned_vec(&doc.tags) + self.interned_vec(&doc.links)
+            }
+            Directive::Price(price) => {
+                self.interned(&price.currency);
+                self.amount(&price.amount);
+                0
+            }
+            Directive::Custom(custom) => {
+                custom.custom_type.capacity()
+                    + custom.values.capacity() * size_of::<MetaValue>()
+                    + custom.values.iter().map(Self::meta_value).sum::<usize>()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NaiveDate, Open, Posting, Price, Transaction};
+    use rust_decimal_macros::dec;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()
+    }
+
+    #[test]
+    fn test_memory_usage_by_type() {
+        let directives = vec![
+            Directive::Open(Open::new(date(), "Assets:Bank")),
+            Directive::Transaction(
+                Transaction::new(date(), "Lunch")
+                    .with_posting(Posting::new("Expenses:Food", Amount::new(dec!(12), "USD")))
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-12), "USD"))),
+            ),
+            Directive::Price(Price::new(date(), "EUR", Amount::new(dec!(1.1), "USD"))),
+        ];
+
+        let usage = memory_usage(&directives);
+        assert_eq!(usage.by_type.len(), 3);
+        assert_eq!(usage.by_type["transaction"].count, 1);
+        assert!(
+            usage.by_type["transaction"].bytes >= size_of::<Directive>() + 2 * size_of::<Posting>()
+        );
+        assert_eq!(usage.by_type["open"].bytes, size_of::<Directive>());
+        // Open account, narration, 2 accounts and 2 currencies, 2 price currencies
+        assert_eq!(usage.strings.references, 8);
+        assert_eq!(
+            usage.total_bytes(),
+            usage.by_type.values().map(|u| u.bytes).sum::<usize>() + usage.strings.bytes
+        );
+    }
+
+    #[test]
+    fn test_memory_usage_counts_shared_strings_once() {
+        let account = InternedStr::new("Assets:Bank");
+        let separate = vec![
+            Directive::Open(Open::new(date(), "Assets:Bank")),
+            Directive::Open(Open::new(date(), "Assets:Bank")),
+        ];
+        let shared = vec![
+            Directive::Open(Open::new(date(), account.clone())),
+            Directive::Open(Open::new(date(), account)),
+        ];
+
+        let separate = memory_usage(&separate).strings;
+        assert_eq!(separate.allocations, 2);
+        assert_eq!(separate.bytes, 22);
+
+        let shared = memory_usage(&shared).strings;
+        assert_eq!(shared.references, 2);
+        assert_eq!(shared.allocations, 1);
+        assert_eq!(shared.bytes, 11);
+        assert_eq!(shared.unshared_bytes, 22);
+    }
+}
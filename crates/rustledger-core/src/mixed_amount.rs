@@ -0,0 +1,349 @@
+//! Multi-currency amount type.
+//!
+//! A [`MixedAmount`] holds one number per currency, e.g. the residual of a
+//! transaction that mixes `USD` and `EUR` postings. Unlike an [`Inventory`],
+//! it tracks no lots or costs: it is just a sum per currency.
+//!
+//! Currencies are kept in sorted order, so iteration and display are
+//! deterministic. Zero entries are kept once a currency has been seen, so
+//! callers can tell "balanced in USD" apart from "no USD at all"; use
+//! [`MixedAmount::without_zeros`] to drop them.
+//!
+//! [`Inventory`]: crate::Inventory
+
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use crate::amount::Amount;
+use crate::intern::InternedStr;
+
+/// A sum of amounts in several currencies.
+///
+/// # Examples
+///
+/// ```
+/// use rustledger_core::{Amount, MixedAmount};
+/// use rust_decimal_macros::dec;
+///
+/// let mut total = MixedAmount::new();
+/// total += &Amount::new(dec!(10.00), "USD");
+/// total += &Amount::new(dec!(-4.00), "EUR");
+/// total += &Amount::new(dec!(2.50), "USD");
+///
+/// assert_eq!(total.number("USD"), dec!(12.50));
+/// assert_eq!(total.to_string(), "-4.00 EUR, 12.50 USD");
+/// assert_eq!((-total).number("EUR"), dec!(4.00));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MixedAmount {
+    amounts: BTreeMap<InternedStr, Decimal>,
+}
+
+impl MixedAmount {
+    /// Create an empty mixed amount.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            amounts: BTreeMap::new(),
+        }
+    }
+
+    /// Add a number in a currency.
+    pub fn add_number(&mut self, currency: impl Into<InternedStr>, number: Decimal) {
+        *self.amounts.entry(currency.into()).or_default() += number;
+    }
+
+    /// Set the number for a currency, replacing any previous value.
+    pub fn set(&mut self, currency: impl Into<InternedStr>, number: Decimal) {
+        self.amounts.insert(currency.into(), number);
+    }
+
+    /// Get the number for a currency, if the currency has been seen.
+    #[must_use]
+    pub fn get(&self, currency: &str) -> Option<&Decimal> {
+        self.amounts.get(currency)
+    }
+
+    /// Check whether a currency has been seen.
+    #[must_use]
+    pub fn contains(&self, currency: &str) -> bool {
+        self.amounts.contains_key(currency)
+    }
+
+    /// Get the number for a currency, zero if the currency has not been seen.
+    #[must_use]
+    pub fn number(&self, currency: &str) -> Decimal {
+        self.get(currency).copied().unwrap_or(Decimal::ZERO)
+    }
+
+    /// Get the amount in a currency, if the currency has been seen.
+    #[must_use]
+    pub fn amount(&self, currency: &str) -> Option<Amount> {
+        self.amounts
+            .get_key_value(currency)
+            .map(|(currency, number)| Amount::new(*number, currency))
+    }
+
+    /// Check whether every currency sums to zero.
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        self.amounts.values().all(Decimal::is_zero)
+    }
+
+    /// Check whether no currency has been seen.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+
+    /// Number of currencies.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.amounts.len()
+    }
+
+    /// Iterate over `(currency, number)` pairs in currency order.
+    pub fn iter(&self) -> impl Iterator<Item = (&InternedStr, &Decimal)> {
+        self.amounts.iter()
+    }
+
+    /// Iterate over the currencies in order.
+    pub fn currencies(&self) -> impl Iterator<Item = &InternedStr> {
+        self.amounts.keys()
+    }
+
+    /// The per-currency amounts, in currency order.
+    pub fn amounts(&self) -> impl Iterator<Item = Amount> + '_ {
+        self.amounts
+            .iter()
+            .map(|(currency, number)| Amount::new(*number, currency))
+    }
+
+    /// A copy without the currencies that sum to zero.
+    #[must_use]
+    pub fn without_zeros(&self) -> Self {
+        Self {
+            amounts: self
+                .amounts
+                .iter()
+                .filter(|(_, number)| !number.is_zero())
+                .map(|(currency, number)| (currency.clone(), *number))
+                .collect(),
+        }
+    }
+}
+
+impl From<Amount> for MixedAmount {
+    fn from(amount: Amount) -> Self {
+        let mut mixed = Self::new();
+        mixed.set(amount.currency, amount.number);
+        mixed
+    }
+}
+
+impl FromIterator<Amount> for MixedAmount {
+    fn from_iter<I: IntoIterator<Item = Amount>>(iter: I) -> Self {
+        let mut mixed = Self::new();
+        mixed.extend(iter);
+        mixed
+    }
+}
+
+impl Extend<Amount> for MixedAmount {
+    fn extend<I: IntoIterator<Item = Amount>>(&mut self, iter: I) {
+        for amount in iter {
+            self.add_number(amount.currency, amount.number);
+        }
+    }
+}
+
+impl IntoIterator for MixedAmount {
+    type Item = (InternedStr, Decimal);
+    type IntoIter = std::collections::btree_map::IntoIter<InternedStr, Decimal>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.amounts.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MixedAmount {
+    type Item = (&'a InternedStr, &'a Decimal);
+    type IntoIter = std::collections::btree_map::Iter<'a, InternedStr, Decimal>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.amounts.iter()
+    }
+}
+
+impl fmt::Display for MixedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.amounts.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (currency, number)) in self.amounts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{number} {currency}")?;
+        }
+        Ok(())
+    }
+}
+
+// Arithmetic with single-currency amounts
+
+impl AddAssign<&Amount> for MixedAmount {
+    fn add_assign(&mut self, amount: &Amount) {
+        self.add_number(amount.currency.clone(), amount.number);
+    }
+}
+
+impl SubAssign<&Amount> for MixedAmount {
+    fn sub_assign(&mut self, amount: &Amount) {
+        self.add_number(amount.currency.clone(), -amount.number);
+    }
+}
+
+// Arithmetic between mixed amounts
+
+impl AddAssign<&Self> for MixedAmount {
+    fn add_assign(&mut self, other: &Self) {
+        for (currency, number) in other {
+            self.add_number(currency.clone(), *number);
+        }
+    }
+}
+
+impl SubAssign<&Self> for MixedAmount {
+    fn sub_assign(&mut self, other: &Self) {
+        for (currency, number) in other {
+            self.add_number(currency.clone(), -*number);
+        }
+    }
+}
+
+impl Add for &MixedAmount {
+    type Output = MixedAmount;
+
+    fn add(self, other: &MixedAmount) -> MixedAmount {
+        let mut sum = self.clone();
+        sum += other;
+        sum
+    }
+}
+
+impl Sub for &MixedAmount {
+    type Output = MixedAmount;
+
+    fn sub(self, other: &MixedAmount) -> MixedAmount {
+        let mut difference = self.clone();
+        difference -= other;
+        difference
+    }
+}
+
+impl Neg for &MixedAmount {
+    type Output = MixedAmount;
+
+    fn neg(self) -> MixedAmount {
+        MixedAmount {
+            amounts: self
+                .amounts
+                .iter()
+                .map(|(currency, number)| (currency.clone(), -*number))
+                .collect(),
+        }
+    }
+}
+
+impl Add for MixedAmount {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += &other;
+        self
+    }
+}
+
+impl Sub for MixedAmount {
+    type Output = Self;
+
+    fn sub(mut self, other: Self) -> Self {
+        self -= &other;
+        self
+    }
+}
+
+impl Neg for MixedAmount {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_mixed_amount_add_amounts() {
+        let mut mixed = MixedAmount::new();
+        assert!(mixed.is_empty());
+        assert!(mixed.is_zero());
+
+        mixed += &Amount::new(dec!(10), "USD");
+        mixed -= &Amount::new(dec!(3), "EUR");
+        mixed -= &Amount::new(dec!(10), "USD");
+
+        assert_eq!(mixed.len(), 2);
+        assert_eq!(mixed.get("USD"), Some(&dec!(0)));
+        assert_eq!(mixed.number("EUR"), dec!(-3));
+        assert_eq!(mixed.number("GBP"), dec!(0));
+        assert!(mixed.get("GBP").is_none());
+        assert!(!mixed.is_zero());
+
+        let nonzero = mixed.without_zeros();
+        assert_eq!(nonzero.len(), 1);
+        assert_eq!(nonzero.amount("EUR"), Some(Amount::new(dec!(-3), "EUR")));
+    }
+
+    #[test]
+    fn test_mixed_amount_arithmetic() {
+        let a: MixedAmount = [
+            Amount::new(dec!(1), "USD"),
+            Amount::new(dec!(2), "EUR"),
+            Amount::new(dec!(4), "USD"),
+        ]
+        .into_iter()
+        .collect();
+        let b = MixedAmount::from(Amount::new(dec!(5), "USD"));
+
+        let difference = &a - &b;
+        assert_eq!(difference.number("USD"), dec!(0));
+        assert_eq!(difference.number("EUR"), dec!(2));
+
+        let sum = a.clone() + b;
+        assert_eq!(sum.number("USD"), dec!(10));
+
+        let negated = -a;
+        assert_eq!(negated.number("USD"), dec!(-5));
+        assert_eq!(negated.number("EUR"), dec!(-2));
+    }
+
+    #[test]
+    fn test_mixed_amount_display() {
+        assert_eq!(MixedAmount::new().to_string(), "0");
+
+        let mixed: MixedAmount = [Amount::new(dec!(1.50), "USD"), Amount::new(dec!(-2), "EUR")]
+            .into_iter()
+            .collect();
+        assert_eq!(mixed.to_string(), "-2 EUR, 1.50 USD");
+
+        let currencies: Vec<_> = mixed.currencies().map(InternedStr::as_str).collect();
+        assert_eq!(currencies, vec!["EUR", "USD"]);
+    }
+}
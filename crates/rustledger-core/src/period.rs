@@ -0,0 +1,377 @@
+//! Calendar periods: months, quarters and (fiscal) years.
+//!
+//! A [`Period`] is a span of whole months starting on the first of a month.
+//! Periods are written the way reports label them:
+//!
+//! | Period                    | Written as |
+//! |---------------------------|------------|
+//! | Month                     | `2024-05`  |
+//! | Calendar quarter          | `2024-Q2`  |
+//! | Calendar year             | `2024`     |
+//! | Fiscal year               | `FY2024`   |
+//!
+//! A fiscal year starts on the first of a configurable month and is named
+//! after the calendar year it ends in, so with an April start `FY2024`
+//! runs from 2023-04-01 to 2024-03-31.
+
+use chrono::{Datelike, Months, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
+
+/// The length of a [`Period`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PeriodKind {
+    /// A calendar month.
+    Month,
+    /// A calendar quarter.
+    Quarter,
+    /// A year starting on the first of `start_month` (1-12); a calendar
+    /// year when it is January.
+    Year {
+        /// Month the year starts in.
+        start_month: u32,
+    },
+}
+
+impl PeriodKind {
+    /// A calendar year.
+    pub const CALENDAR_YEAR: Self = Self::Year { start_month: 1 };
+
+    /// Number of months in a period of this kind.
+    #[must_use]
+    pub const fn months(self) -> u32 {
+        match self {
+            Self::Month => 1,
+            Self::Quarter => 3,
+            Self::Year { .. } => 12,
+        }
+    }
+}
+
+impl FromStr for PeriodKind {
+    type Err = String;
+
+    /// Parse `month`, `quarter` or `year`, optionally suffixed with `ly`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "month" | "monthly" => Ok(Self::Month),
+            "quarter" | "quarterly" => Ok(Self::Quarter),
+            "year" | "yearly" => Ok(Self::CALENDAR_YEAR),
+            _ => Err(format!("unknown period: {s}")),
+        }
+    }
+}
+
+/// A month, quarter or (fiscal) year.
+///
+/// # Examples
+///
+/// ```
+/// use rustledger_core::{NaiveDate, Period, PeriodKind};
+///
+/// let date = NaiveDate::from_ymd_opt(2024, 5, 17).unwrap();
+/// let quarter = Period::containing(date, PeriodKind::Quarter);
+/// assert_eq!(quarter.to_string(), "2024-Q2");
+/// assert_eq!(quarter.next().to_string(), "2024-Q3");
+///
+/// let fiscal = Period::parse("FY2024", 4).unwrap();
+/// assert_eq!(fiscal.start(), NaiveDate::from_ymd_opt(2023, 4, 1).unwrap());
+/// assert!(fiscal.contains(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Period {
+    /// First day of the period.
+    start: NaiveDate,
+    /// Length of the period.
+    kind: PeriodKind,
+}
+
+impl Period {
+    /// The period of the given kind that contains `date`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a year's `start_month` is not between 1 and 12.
+    #[must_use]
+    pub fn containing(date: NaiveDate, kind: PeriodKind) -> Self {
+        let first_month = match kind {
+            PeriodKind::Month => date.month(),
+            PeriodKind::Quarter => (date.month() - 1) / 3 * 3 + 1,
+            PeriodKind::Year { start_month } => start_month,
+        };
+        let mut start = NaiveDate::from_ymd_opt(date.year(), first_month, 1)
+            .expect("period start month must be between 1 and 12");
+        if start > date {
+            start = start - Months::new(12);
+        }
+        Self { start, kind }
+    }
+
+    /// The given month (1-12) of `year`.
+    #[must_use]
+    pub fn month(year: i32, month: u32) -> Option<Self> {
+        Some(Self {
+            start: NaiveDate::from_ymd_opt(year, month, 1)?,
+            kind: PeriodKind::Month,
+        })
+    }
+
+    /// The given calendar quarter (1-4) of `year`.
+    #[must_use]
+    pub fn quarter(year: i32, quarter: u32) -> Option<Self> {
+        if !(1..=4).contains(&quarter) {
+            return None;
+        }
+        Some(Self {
+            start: NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1)?,
+            kind: PeriodKind::Quarter,
+        })
+    }
+
+    /// The calendar year `year`.
+    #[must_use]
+    pub fn year(year: i32) -> Option<Self> {
+        Self::fiscal_year(year, 1)
+    }
+
+    /// The fiscal year ending in `year` that starts in `start_month` (1-12).
+    #[must_use]
+    pub fn fiscal_year(year: i32, start_month: u32) -> Option<Self> {
+        let start_year = if start_month == 1 { year } else { year - 1 };
+        Some(Self {
+            start: NaiveDate::from_ymd_opt(start_year, start_month, 1)?,
+            kind: PeriodKind::Year { start_month },
+        })
+    }
+
+    /// Parse a period written as `2024-05`, `2024-Q2`, `2024` or `FY2024`.
+    ///
+    /// Fiscal years start in `fiscal_start_month`; plain years are always
+    /// calendar years.
+    pub fn parse(s: &str, fiscal_start_month: u32) -> Result<Self, String> {
+        let invalid = || format!("invalid period: {s}");
+        let number = |digits: &str| -> Result<u32, String> {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse().map_err(|_| invalid())
+        };
+        let year = |digits: &str| -> Result<i32, String> {
+            if digits.len() != 4 {
+                return Err(invalid());
+            }
+            Ok(number(digits)? as i32)
+        };
+
+        let period = if let Some(fy) = s.strip_prefix("FY") {
+            Self::fiscal_year(year(fy)?, fiscal_start_month)
+        } else if let Some((y, rest)) = s.split_once('-') {
+            if let Some(q) = rest.strip_prefix('Q') {
+                Self::quarter(year(y)?, number(q)?)
+            } else if rest.len() == 2 {
+                Self::month(year(y)?, number(rest)?)
+            } else {
+                None
+            }
+        } else {
+            Self::year(year(s)?)
+        };
+        period.ok_or_else(invalid)
+    }
+
+    /// The kind of period.
+    #[must_use]
+    pub const fn kind(&self) -> PeriodKind {
+        self.kind
+    }
+
+    /// First day of the period.
+    #[must_use]
+    pub const fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    /// First day after the period.
+    #[must_use]
+    pub fn end(&self) -> NaiveDate {
+        self.start + Months::new(self.kind.months())
+    }
+
+    /// Last day of the period.
+    #[must_use]
+    pub fn last_day(&self) -> NaiveDate {
+        self.end().pred_opt().unwrap_or(self.start)
+    }
+
+    /// Whether `date` falls within the period.
+    #[must_use]
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date < self.end()
+    }
+
+    /// The period immediately after this one.
+    #[must_use]
+    pub fn next(&self) -> Self {
+        Self {
+            start: self.end(),
+            kind: self.kind,
+        }
+    }
+
+    /// The period immediately before this one.
+    #[must_use]
+    pub fn prev(&self) -> Self {
+        Self {
+            start: self.start - Months::new(self.kind.months()),
+            kind: self.kind,
+        }
+    }
+
+    /// The periods of a kind covering `from` through `to` (inclusive), in
+    /// order.
+    pub fn between(from: NaiveDate, to: NaiveDate, kind: PeriodKind) -> Periods {
+        Periods {
+            next: Self::containing(from, kind),
+            last: to,
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            PeriodKind::Month => write!(f, "{}-{:02}", self.start.year(), self.start.month()),
+            PeriodKind::Quarter => write!(
+                f,
+                "{}-Q{}",
+                self.start.year(),
+                (self.start.month() - 1) / 3 + 1
+            ),
+            PeriodKind::Year { start_month: 1 } => write!(f, "{}", self.start.year()),
+            PeriodKind::Year { .. } => write!(f, "FY{}", self.last_day().year()),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    /// Parse a period, taking fiscal years to be calendar years.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, 1)
+    }
+}
+
+/// Iterator over consecutive periods, created by [`Period::between`].
+#[derive(Debug, Clone)]
+pub struct Periods {
+    next: Period,
+    last: NaiveDate,
+}
+
+impl Iterator for Periods {
+    type Item = Period;
+
+    fn next(&mut self) -> Option<Period> {
+        if self.next.start() > self.last {
+            return None;
+        }
+        let period = self.next;
+        self.next = period.next();
+        Some(period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_containing() {
+        let day = date(2024, 5, 17);
+        assert_eq!(
+            Period::containing(day, PeriodKind::Month),
+            Period::month(2024, 5).unwrap()
+        );
+        assert_eq!(
+            Period::containing(day, PeriodKind::Quarter),
+            Period::quarter(2024, 2).unwrap()
+        );
+        assert_eq!(
+            Period::containing(day, PeriodKind::CALENDAR_YEAR),
+            Period::year(2024).unwrap()
+        );
+        assert_eq!(
+            Period::containing(day, PeriodKind::Year { start_month: 4 }),
+            Period::fiscal_year(2025, 4).unwrap()
+        );
+        assert_eq!(
+            Period::containing(date(2024, 3, 31), PeriodKind::Year { start_month: 4 }),
+            Period::fiscal_year(2024, 4).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        let fy = Period::fiscal_year(2024, 10).unwrap();
+        assert_eq!(fy.start(), date(2023, 10, 1));
+        assert_eq!(fy.end(), date(2024, 10, 1));
+        assert_eq!(fy.last_day(), date(2024, 9, 30));
+        assert!(fy.contains(date(2023, 10, 1)));
+        assert!(fy.contains(date(2024, 9, 30)));
+        assert!(!fy.contains(date(2024, 10, 1)));
+
+        let feb = Period::month(2024, 2).unwrap();
+        assert_eq!(feb.last_day(), date(2024, 2, 29));
+        assert_eq!(feb.prev(), Period::month(2024, 1).unwrap());
+        assert_eq!(
+            Period::month(2024, 12).unwrap().next(),
+            Period::month(2025, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for s in ["2024-05", "2024-Q2", "2024", "FY2024"] {
+            assert_eq!(Period::parse(s, 7).unwrap().to_string(), s);
+        }
+        assert_eq!(
+            Period::parse("FY2024", 7).unwrap(),
+            Period::fiscal_year(2024, 7).unwrap()
+        );
+        // A fiscal year starting in January is a calendar year
+        assert_eq!("FY2024".parse::<Period>().unwrap().to_string(), "2024");
+
+        for s in [
+            "2024-13", "2024-Q5", "2024-Q", "24", "FY", "2024-5", "2024-W01", "+2024",
+        ] {
+            assert!(s.parse::<Period>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        let quarters: Vec<String> =
+            Period::between(date(2023, 11, 15), date(2024, 4, 1), PeriodKind::Quarter)
+                .map(|p| p.to_string())
+                .collect();
+        assert_eq!(quarters, ["2023-Q4", "2024-Q1", "2024-Q2"]);
+
+        assert_eq!(
+            Period::between(date(2024, 2, 1), date(2024, 1, 1), PeriodKind::Month).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_period_kind_from_str() {
+        assert_eq!("monthly".parse(), Ok(PeriodKind::Month));
+        assert_eq!("Quarter".parse(), Ok(PeriodKind::Quarter));
+        assert_eq!("yearly".parse(), Ok(PeriodKind::CALENDAR_YEAR));
+        assert!("weekly".parse::<PeriodKind>().is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Expanding directories and zip archives into the statements they contain.
+//!
+//! Banks often hand out statements as a zip download, and users keep them in
+//! folders per account or year. [`SourceFiles::collect`] walks directories and
+//! `.zip` archives, including archives nested inside either, and yields every
+//! regular file along with an origin such as `downloads/2024.zip!jan.csv`.
+//!
+//! Importers read from paths, so archive members are extracted to a
+//! temporary directory that lives as long as the [`SourceFiles`].
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A file found while expanding an input path.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path to read the file from; archive members point into a temporary
+    /// directory.
+    pub path: PathBuf,
+    /// Where the file came from, with `!` separating an archive from the
+    /// member inside it.
+    pub origin: String,
+}
+
+/// The files contained in a path, with directories and archives expanded.
+#[derive(Debug, Default)]
+pub struct SourceFiles {
+    files: Vec<SourceFile>,
+    extracted: Vec<TempDir>,
+}
+
+impl SourceFiles {
+    /// Expand `path` into the files it contains.
+    ///
+    /// A plain file yields itself. Directory entries are visited in name
+    /// order; hidden entries and `__MACOSX` resource folders are skipped.
+    pub fn collect(path: &Path) -> Result<Self> {
+        let mut files = Self::default();
+        files.add(path, path.display().to_string())?;
+        Ok(files)
+    }
+
+    /// Iterate over the collected files.
+    pub fn iter(&self) -> std::slice::Iter<'_, SourceFile> {
+        self.files.iter()
+    }
+
+    /// Number of collected files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files were found.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn add(&mut self, path: &Path, origin: String) -> Result<()> {
+        if path.is_dir() {
+            for (child, name) in sorted_entries(path)? {
+                self.add(&child, format!("{origin}/{name}"))?;
+            }
+        } else if is_archive(path) {
+            let dir = extract_zip(path)?;
+            let entries = sorted_entries(dir.path())?;
+            self.extracted.push(dir);
+            for (child, name) in entries {
+                self.add(&child, format!("{origin}!{name}"))?;
+            }
+        } else {
+            self.files.push(SourceFile {
+                path: path.to_path_buf(),
+                origin,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a SourceFiles {
+    type Item = &'a SourceFile;
+    type IntoIter = std::slice::Iter<'a, SourceFile>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Whether `path` names a zip archive, judged by its extension.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Directory entries worth importing, sorted by name.
+fn sorted_entries(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == "__MACOSX" {
+            continue;
+        }
+        entries.push((entry.path(), name));
+    }
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(entries)
+}
+
+/// Extract a zip archive into a fresh temporary directory.
+///
+/// Members whose names would escape the directory are ignored.
+fn extract_zip(path: &Path) -> Result<TempDir> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {}", path.display()))?;
+    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+
+    for i in 0..archive.len() {
+        let mut member = archive
+            .by_index(i)
+            .with_context(|| format!("Failed to read entry {i} of {}", path.display()))?;
+        let Some(relative) = member.enclosed_name() else {
+            continue;
+        };
+        let outpath = dir.path().join(relative);
+        if member.is_dir() {
+            fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = File::create(&outpath)?;
+            io::copy(&mut member, &mut out)?;
+        }
+    }
+
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, members: &[(&str, &[u8])]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in members {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_collect_plain_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bank.csv");
+        fs::write(&path, "Date,Amount\n").unwrap();
+
+        let files = SourceFiles::collect(&path).unwrap();
+        assert_eq!(files.len(), 1);
+        let file = files.iter().next().unwrap();
+        assert_eq!(file.path, path);
+        assert_eq!(file.origin, path.display().to_string());
+    }
+
+    #[test]
+    fn test_collect_directory_with_nested_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("b.ofx"), "OFX").unwrap();
+        fs::write(dir.path().join(".DS_Store"), "").unwrap();
+        write_zip(
+            &dir.path().join("a.zip"),
+            &[
+                ("2024/jan.csv", b"jan".as_slice()),
+                ("feb.csv", b"feb".as_slice()),
+                ("__MACOSX/._feb.csv", b"".as_slice()),
+            ],
+        );
+
+        let files = SourceFiles::collect(dir.path()).unwrap();
+        let root = dir.path().display().to_string();
+        let origins: Vec<_> = files.iter().map(|f| f.origin.clone()).collect();
+        assert_eq!(
+            origins,
+            vec![
+                format!("{root}/a.zip!2024/jan.csv"),
+                format!("{root}/a.zip!feb.csv"),
+                format!("{root}/b.ofx"),
+            ]
+        );
+
+        let jan = files.iter().next().unwrap();
+        assert_eq!(fs::read_to_string(&jan.path).unwrap(), "jan");
+        assert_eq!(jan.path.file_name().unwrap(), "jan.csv");
+    }
+
+    #[test]
+    fn test_collect_invalid_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.zip");
+        fs::write(&path, "not a zip").unwrap();
+
+        let err = SourceFiles::collect(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("Failed to read zip archive"));
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("statements.zip")));
+        assert!(is_archive(Path::new("STATEMENTS.ZIP")));
+        assert!(!is_archive(Path::new("statement.csv")));
+        assert!(!is_archive(Path::new("zip")));
+    }
+}
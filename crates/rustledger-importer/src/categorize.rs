@@ -0,0 +1,355 @@
+//! Counter-account suggestions learned from an existing ledger.
+//!
+//! Importers only know one side of a transaction, so they book the other
+//! side to a placeholder such as `Expenses:Unknown`. The [`Categorizer`]
+//! fills it in the way `smart_importer`'s `predict_postings` does: it looks
+//! for the most similar past transaction on the same account and reuses its
+//! counter-account.
+//!
+//! Similarity is a nearest-neighbor score over tokenized payee and
+//! narration strings (Jaccard overlap), nudged by how close the amounts are.
+
+use std::collections::HashSet;
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use rustledger_core::{Directive, Transaction};
+
+/// Counter-accounts the built-in importers book unknown postings to.
+pub const PLACEHOLDER_ACCOUNTS: &[&str] = &["Expenses:Unknown", "Income:Unknown"];
+
+/// Default minimum score for a suggestion to be applied.
+const DEFAULT_MIN_SCORE: f64 = 0.5;
+
+/// Weight of the text similarity in the score; the rest is amount similarity.
+const TEXT_WEIGHT: f64 = 0.8;
+
+/// A past transaction remembered for matching.
+#[derive(Debug, Clone)]
+struct Example {
+    /// Account the importer would book to (the "source" side).
+    account: String,
+    /// Counter-account the transaction was categorized to.
+    counter_account: String,
+    /// Tokens from payee and narration.
+    tokens: HashSet<String>,
+    /// Amount posted to the source account.
+    amount: Option<Decimal>,
+}
+
+/// A suggested counter-account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The suggested account.
+    pub account: String,
+    /// Similarity score in `0.0..=1.0`.
+    pub score: f64,
+}
+
+/// Nearest-neighbor categorizer trained on historical transactions.
+#[derive(Debug, Clone)]
+pub struct Categorizer {
+    examples: Vec<Example>,
+    min_score: f64,
+}
+
+impl Categorizer {
+    /// Learn from the transactions in an existing ledger.
+    ///
+    /// Only transactions with exactly two postings to distinct accounts are
+    /// used, and placeholder accounts are never learned as answers.
+    pub fn from_directives(directives: &[Directive]) -> Self {
+        let mut examples = Vec::new();
+
+        for directive in directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+            let [first, second] = txn.postings.as_slice() else {
+                continue;
+            };
+            if first.account == second.account {
+                continue;
+            }
+
+            let tokens = transaction_tokens(txn);
+            if tokens.is_empty() {
+                continue;
+            }
+
+            // Either side may be the imported account, so remember both
+            for (source, counter) in [(first, second), (second, first)] {
+                if PLACEHOLDER_ACCOUNTS.contains(&counter.account.as_str()) {
+                    continue;
+                }
+                examples.push(Example {
+                    account: source.account.to_string(),
+                    counter_account: counter.account.to_string(),
+                    tokens: tokens.clone(),
+                    amount: source.amount().map(|a| a.number),
+                });
+            }
+        }
+
+        Self {
+            examples,
+            min_score: DEFAULT_MIN_SCORE,
+        }
+    }
+
+    /// Set the minimum score for a suggestion to be applied.
+    #[must_use]
+    pub const fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Number of transactions learned.
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    /// Whether nothing was learned.
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    /// Suggest a counter-account for an imported transaction.
+    ///
+    /// `account` is the account the importer booked to and `amount` the
+    /// amount posted to it. Returns the best match scoring at least the
+    /// minimum score.
+    pub fn suggest(
+        &self,
+        account: &str,
+        text: &str,
+        amount: Option<Decimal>,
+    ) -> Option<Suggestion> {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return None;
+        }
+
+        self.examples
+            .iter()
+            .filter(|example| example.account == account)
+            .map(|example| Suggestion {
+                account: example.counter_account.clone(),
+                score: TEXT_WEIGHT.mul_add(
+                    jaccard(&tokens, &example.tokens),
+                    (1.0 - TEXT_WEIGHT) * amount_similarity(amount, example.amount),
+                ),
+            })
+            .filter(|suggestion| suggestion.score >= self.min_score)
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    /// Replace placeholder counter-accounts with suggestions.
+    ///
+    /// Returns the number of postings recategorized.
+    pub fn categorize(&self, directives: &mut [Directive]) -> usize {
+        let mut recategorized = 0;
+
+        for directive in directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+            let Some(placeholder) = txn
+                .postings
+                .iter()
+                .position(|p| PLACEHOLDER_ACCOUNTS.contains(&p.account.as_str()))
+            else {
+                continue;
+            };
+            let Some(source) = txn.postings.iter().find(|p| {
+                !PLACEHOLDER_ACCOUNTS.contains(&p.account.as_str()) && p.amount().is_some()
+            }) else {
+                continue;
+            };
+
+            let text = transaction_text(txn);
+            let amount = source.amount().map(|a| a.number);
+            if let Some(suggestion) = self.suggest(&source.account, &text, amount) {
+                txn.postings[placeholder].account = suggestion.account.into();
+                recategorized += 1;
+            }
+        }
+
+        recategorized
+    }
+}
+
+/// Payee and narration joined for tokenizing.
+fn transaction_text(txn: &Transaction) -> String {
+    match &txn.payee {
+        Some(payee) => format!("{payee} {}", txn.narration),
+        None => txn.narration.to_string(),
+    }
+}
+
+fn transaction_tokens(txn: &Transaction) -> HashSet<String> {
+    tokenize(&transaction_text(txn))
+}
+
+/// Split text into lowercase alphanumeric tokens.
+///
+/// Numbers-only tokens (card numbers, references, dates) and single
+/// characters carry no signal about the category and are dropped.
+fn tokenize(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() > 1)
+        .filter(|token| !token.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Jaccard similarity of two token sets.
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Similarity of two amounts: 1 when equal, falling off with the relative
+/// difference, 0 when the signs differ or an amount is unknown.
+fn amount_similarity(a: Option<Decimal>, b: Option<Decimal>) -> f64 {
+    let (Some(a), Some(b)) = (a, b) else {
+        return 0.0;
+    };
+    if a.is_sign_negative() != b.is_sign_negative() {
+        return 0.0;
+    }
+    let (a, b) = (a.abs(), b.abs());
+    let larger = a.max(b);
+    if larger.is_zero() {
+        return 1.0;
+    }
+    (Decimal::ONE - (a - b).abs() / larger)
+        .to_f64()
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_core::{Amount, NaiveDate, Posting};
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn txn(payee: &str, counter: &str, amount: Decimal) -> Directive {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        Directive::Transaction(
+            Transaction::new(date, "Card purchase")
+                .with_payee(payee)
+                .with_posting(Posting::new(
+                    "Liabilities:CreditCard",
+                    Amount::new(-amount, "USD"),
+                ))
+                .with_posting(Posting::new(counter, Amount::new(amount, "USD"))),
+        )
+    }
+
+    fn history() -> Vec<Directive> {
+        vec![
+            txn(
+                "STARBUCKS #1234 SEATTLE",
+                "Expenses:Food:Coffee",
+                dec("4.50"),
+            ),
+            txn(
+                "WHOLE FOODS MARKET",
+                "Expenses:Food:Groceries",
+                dec("82.10"),
+            ),
+            txn("SHELL OIL 5551234", "Expenses:Transport:Fuel", dec("45.00")),
+        ]
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("STARBUCKS #1234 Seattle, WA");
+        let expected: HashSet<String> = ["starbucks", "seattle", "wa"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_suggest_nearest_payee() {
+        let categorizer = Categorizer::from_directives(&history());
+        assert_eq!(categorizer.len(), 6);
+
+        let suggestion = categorizer
+            .suggest(
+                "Liabilities:CreditCard",
+                "STARBUCKS #9876 SEATTLE Card purchase",
+                Some(dec("-5.25")),
+            )
+            .unwrap();
+        assert_eq!(suggestion.account, "Expenses:Food:Coffee");
+
+        // Unrelated payee, and an account with no history
+        assert!(
+            categorizer
+                .suggest("Liabilities:CreditCard", "AIRLINE TICKETS", None)
+                .is_none()
+        );
+        assert!(
+            categorizer
+                .suggest("Assets:Checking", "STARBUCKS SEATTLE Card purchase", None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_categorize_replaces_placeholder() {
+        let categorizer = Categorizer::from_directives(&history());
+        let date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let mut imported = vec![
+            Directive::Transaction(
+                Transaction::new(date, "Card purchase")
+                    .with_payee("Whole Foods Market #42")
+                    .with_posting(Posting::new(
+                        "Liabilities:CreditCard",
+                        Amount::new(dec("-64.00"), "USD"),
+                    ))
+                    .with_posting(Posting::auto("Expenses:Unknown")),
+            ),
+            Directive::Transaction(
+                Transaction::new(date, "Transfer")
+                    .with_posting(Posting::new(
+                        "Liabilities:CreditCard",
+                        Amount::new(dec("500"), "USD"),
+                    ))
+                    .with_posting(Posting::auto("Income:Unknown")),
+            ),
+        ];
+
+        assert_eq!(categorizer.categorize(&mut imported), 1);
+
+        let Directive::Transaction(first) = &imported[0] else {
+            unreachable!()
+        };
+        assert_eq!(
+            first.postings[1].account.as_str(),
+            "Expenses:Food:Groceries"
+        );
+        let Directive::Transaction(second) = &imported[1] else {
+            unreachable!()
+        };
+        assert_eq!(second.postings[1].account.as_str(), "Income:Unknown");
+    }
+
+    #[test]
+    fn test_amount_similarity() {
+        assert!((amount_similarity(Some(dec("10")), Some(dec("10"))) - 1.0).abs() < f64::EPSILON);
+        assert!((amount_similarity(Some(dec("5")), Some(dec("10"))) - 0.5).abs() < f64::EPSILON);
+        assert!(amount_similarity(Some(dec("-5")), Some(dec("10"))).abs() < f64::EPSILON);
+        assert!(amount_similarity(None, Some(dec("10"))).abs() < f64::EPSILON);
+    }
+}
@@ -36,6 +36,11 @@ pub struct CsvConfig {
     pub payee_column: Option<ColumnSpec>,
     /// The column name or index for the amount.
     pub amount_column: Option<ColumnSpec>,
+    /// The column name or index for the statement's transaction reference.
+    ///
+    /// Its value is stored as the transaction's import id, which
+    /// [`Deduplicator`](crate::Deduplicator) uses to skip re-imported rows.
+    pub id_column: Option<ColumnSpec>,
     /// The column name or index for debit amounts (if separate from credit).
     pub debit_column: Option<ColumnSpec>,
     /// The column name or index for credit amounts (if separate from debit).
@@ -48,6 +53,25 @@ pub struct CsvConfig {
     pub skip_rows: usize,
     /// Whether to invert the sign of amounts.
     pub invert_sign: bool,
+    /// Whether amounts use a comma as the decimal separator (`1.234,56`).
+    pub decimal_comma: bool,
+    /// The file's character encoding label (e.g. `windows-1252`).
+    ///
+    /// Files are decoded as UTF-8 when unset. Labels follow the WHATWG
+    /// Encoding Standard, as understood by `encoding_rs`.
+    pub encoding: Option<String>,
+    /// The column holding a split list such as `Groceries: 20.00; Household: 5.00`.
+    ///
+    /// Each entry becomes a posting on the account derived from its category
+    /// (see [`CsvConfig::split_account_prefix`]).
+    pub split_column: Option<ColumnSpec>,
+    /// The parent account for categories read from [`CsvConfig::split_column`].
+    pub split_account_prefix: String,
+    /// Separate amount columns, each booked to a fixed account.
+    pub category_columns: Vec<CategoryColumn>,
+    /// Whether to keep each row's raw text and line number as `source_desc`
+    /// and `source_line` metadata, for auditing.
+    pub source_metadata: bool,
 }
 
 impl Default for CsvConfig {
@@ -58,16 +82,35 @@ impl Default for CsvConfig {
             narration_column: Some(ColumnSpec::Name("Description".to_string())),
             payee_column: None,
             amount_column: Some(ColumnSpec::Name("Amount".to_string())),
+            id_column: None,
             debit_column: None,
             credit_column: None,
             has_header: true,
             delimiter: ',',
             skip_rows: 0,
             invert_sign: false,
+            decimal_comma: false,
+            encoding: None,
+            split_column: None,
+            split_account_prefix: "Expenses".to_string(),
+            category_columns: Vec::new(),
+            source_metadata: false,
         }
     }
 }
 
+/// An amount column whose values are booked to a fixed account.
+///
+/// Budgeting app exports (YNAB and similar) often spread one bank
+/// transaction over several category columns.
+#[derive(Debug, Clone)]
+pub struct CategoryColumn {
+    /// The column holding the amount.
+    pub column: ColumnSpec,
+    /// The account the amount is booked to.
+    pub account: String,
+}
+
 /// Specification for a column in the source file.
 #[derive(Debug, Clone)]
 pub enum ColumnSpec {
@@ -187,6 +230,18 @@ impl CsvConfigBuilder {
         self
     }
 
+    /// Set the transaction reference column by name.
+    pub fn id_column(mut self, name: impl Into<String>) -> Self {
+        self.config.id_column = Some(ColumnSpec::Name(name.into()));
+        self
+    }
+
+    /// Set the transaction reference column by index.
+    pub fn id_column_index(mut self, index: usize) -> Self {
+        self.config.id_column = Some(ColumnSpec::Index(index));
+        self
+    }
+
     /// Set separate debit column by name.
     pub fn debit_column(mut self, name: impl Into<String>) -> Self {
         self.config.debit_column = Some(ColumnSpec::Name(name.into()));
@@ -223,6 +278,51 @@ impl CsvConfigBuilder {
         self
     }
 
+    /// Set whether amounts use a comma as the decimal separator.
+    pub const fn decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.config.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Set the file's character encoding (e.g. `windows-1252`).
+    pub fn encoding(mut self, encoding: impl Into<String>) -> Self {
+        self.config.encoding = Some(encoding.into());
+        self
+    }
+
+    /// Set the split list column by name.
+    pub fn split_column(mut self, name: impl Into<String>) -> Self {
+        self.config.split_column = Some(ColumnSpec::Name(name.into()));
+        self
+    }
+
+    /// Set the split list column by index.
+    pub fn split_column_index(mut self, index: usize) -> Self {
+        self.config.split_column = Some(ColumnSpec::Index(index));
+        self
+    }
+
+    /// Set the parent account for split categories (default `Expenses`).
+    pub fn split_account_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.split_account_prefix = prefix.into();
+        self
+    }
+
+    /// Add an amount column, by name, booked to the given account.
+    pub fn category_column(mut self, name: impl Into<String>, account: impl Into<String>) -> Self {
+        self.config.category_columns.push(CategoryColumn {
+            column: ColumnSpec::Name(name.into()),
+            account: account.into(),
+        });
+        self
+    }
+
+    /// Set whether to keep each row's raw text and line number as metadata.
+    pub const fn source_metadata(mut self, source_metadata: bool) -> Self {
+        self.config.source_metadata = source_metadata;
+        self
+    }
+
     /// Build the importer configuration.
     pub fn build(self) -> ImporterConfig {
         ImporterConfig {
@@ -259,6 +359,12 @@ mod tests {
         assert_eq!(config.delimiter, ',');
         assert_eq!(config.skip_rows, 0);
         assert!(!config.invert_sign);
+        assert!(!config.decimal_comma);
+        assert!(config.encoding.is_none());
+        assert!(config.split_column.is_none());
+        assert_eq!(config.split_account_prefix, "Expenses");
+        assert_eq!(config.category_columns.len(), 0);
+        assert!(!config.source_metadata);
     }
 
     // ========== CsvConfigBuilder Tests ==========
@@ -415,6 +521,44 @@ mod tests {
         assert!(csv_config.invert_sign);
     }
 
+    #[test]
+    fn test_csv_config_builder_locale() {
+        let config = CsvConfigBuilder::new()
+            .delimiter(';')
+            .decimal_comma(true)
+            .encoding("windows-1252")
+            .build();
+        let ImporterType::Csv(csv_config) = &config.importer_type;
+        assert_eq!(csv_config.delimiter, ';');
+        assert!(csv_config.decimal_comma);
+        assert_eq!(csv_config.encoding.as_deref(), Some("windows-1252"));
+    }
+
+    #[test]
+    fn test_csv_config_builder_splits() {
+        let config = CsvConfigBuilder::new()
+            .split_column("Splits")
+            .split_account_prefix("Expenses:Budget")
+            .category_column("Household", "Expenses:Household")
+            .category_column("Fees", "Expenses:Bank:Fees")
+            .build();
+        let ImporterType::Csv(csv_config) = &config.importer_type;
+        assert!(matches!(csv_config.split_column, Some(ColumnSpec::Name(ref s)) if s == "Splits"));
+        assert_eq!(csv_config.split_account_prefix, "Expenses:Budget");
+        assert_eq!(csv_config.category_columns.len(), 2);
+        assert!(
+            matches!(csv_config.category_columns[1].column, ColumnSpec::Name(ref s) if s == "Fees")
+        );
+        assert_eq!(csv_config.category_columns[1].account, "Expenses:Bank:Fees");
+    }
+
+    #[test]
+    fn test_csv_config_builder_source_metadata() {
+        let config = CsvConfigBuilder::new().source_metadata(true).build();
+        let ImporterType::Csv(csv_config) = &config.importer_type;
+        assert!(csv_config.source_metadata);
+    }
+
     #[test]
     fn test_csv_config_builder_full_chain() {
         let config = CsvConfigBuilder::new()
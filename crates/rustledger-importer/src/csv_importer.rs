@@ -2,15 +2,14 @@
 
 use crate::ImportResult;
 use crate::config::{ColumnSpec, CsvConfig, ImporterConfig};
-use anyhow::{Context, Result};
+use crate::dedup::{set_import_id, set_source};
+use anyhow::{Context, Result, bail};
 use chrono::NaiveDate;
+use encoding_rs::{Encoding, UTF_8};
 use rust_decimal::Decimal;
-use rustledger_core::{Amount, Directive, Posting, Transaction};
+use rustledger_core::{Amount, AmountParser, Directive, Posting, Transaction};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Read};
 use std::path::Path;
-use std::str::FromStr;
 
 #[allow(unused_imports)]
 use rustledger_core::InternedStr;
@@ -27,12 +26,13 @@ impl CsvImporter {
     }
 
     /// Extract transactions from a file.
+    ///
+    /// The file is decoded from the configured encoding (UTF-8 by default).
     pub fn extract_file(&self, path: &Path, csv_config: &CsvConfig) -> Result<ImportResult> {
-        let file =
-            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
-        let mut reader = BufReader::new(file);
-        let mut content = String::new();
-        reader.read_to_string(&mut content)?;
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let content = decode(&bytes, csv_config.encoding.as_deref())
+            .with_context(|| format!("Failed to decode file: {}", path.display()))?;
         self.extract_string(&content, csv_config)
     }
 
@@ -57,20 +57,42 @@ impl CsvImporter {
 
         let mut directives = Vec::new();
         let mut warnings = Vec::new();
-        let mut row_num = csv_config.skip_rows;
-
-        for result in reader.records().skip(csv_config.skip_rows) {
+        let mut row_num = 0;
+        let mut record = csv::StringRecord::new();
+        // Line number at byte `line_offset`, for `source_line` metadata
+        let mut line = 1;
+        let mut line_offset = 0;
+
+        loop {
+            let start = reader.position().byte() as usize;
+            let read = reader.read_record(&mut record);
+            if matches!(read, Ok(false)) {
+                break;
+            }
             row_num += 1;
-            let record = match result {
-                Ok(r) => r,
-                Err(e) => {
-                    warnings.push(format!("Row {row_num}: parse error: {e}"));
-                    continue;
-                }
-            };
+            if row_num <= csv_config.skip_rows {
+                continue;
+            }
+            if let Err(e) = read {
+                warnings.push(format!("Row {row_num}: parse error: {e}"));
+                continue;
+            }
 
             match self.parse_row(&record, csv_config, &header_map, row_num) {
-                Ok(Some(txn)) => directives.push(Directive::Transaction(txn)),
+                Ok(Some(mut txn)) => {
+                    if csv_config.source_metadata {
+                        let end = reader.position().byte() as usize;
+                        let raw = content.get(start..end).unwrap_or_default();
+                        // The reader's own line count lags behind on CRLF
+                        // input, so count the newlines before the row instead
+                        let row = raw.trim_start_matches(['\r', '\n']);
+                        let row_start = end - row.len();
+                        line += content[line_offset..row_start].matches('\n').count() as u64;
+                        line_offset = row_start;
+                        set_source(&mut txn, row.trim_end_matches(['\r', '\n']), line);
+                    }
+                    directives.push(Directive::Transaction(txn));
+                }
                 Ok(None) => {} // Skip empty rows
                 Err(e) => {
                     warnings.push(format!("Row {row_num}: {e}"));
@@ -149,27 +171,88 @@ impl CsvImporter {
         let amount = Amount::new(final_amount, &currency);
         let posting = Posting::new(&self.config.account, amount);
 
-        // Create balancing posting (auto-interpolated)
-        let contra_account = if final_amount < Decimal::ZERO {
-            "Income:Unknown"
+        // Split postings carry the row's amount in the opposite direction
+        let splits = self.parse_splits(record, csv_config, header_map)?;
+        let direction = if final_amount < Decimal::ZERO {
+            Decimal::ONE
         } else {
-            "Expenses:Unknown"
+            Decimal::NEGATIVE_ONE
         };
-        let contra_posting = Posting::auto(contra_account);
+        let split_total: Decimal = splits.iter().map(|(_, value)| value).sum();
 
-        // Build the transaction
         let mut txn = Transaction::new(date, &narration)
             .with_flag('*')
-            .with_posting(posting)
-            .with_posting(contra_posting);
+            .with_posting(posting);
+        for (account, value) in splits {
+            txn = txn.with_posting(Posting::new(
+                &account,
+                Amount::new(value * direction, &currency),
+            ));
+        }
+
+        // Create balancing posting (auto-interpolated) for the unsplit remainder
+        if split_total * direction != -final_amount {
+            let contra_account = if final_amount < Decimal::ZERO {
+                "Income:Unknown"
+            } else {
+                "Expenses:Unknown"
+            };
+            txn = txn.with_posting(Posting::auto(contra_account));
+        }
 
         if let Some(p) = payee {
             txn = txn.with_payee(p);
         }
 
+        // Keep the statement's reference for deduplication
+        if let Some(col) = &csv_config.id_column {
+            let id = self
+                .get_column(record, col, header_map)
+                .with_context(|| format!("Row {row_num}: missing id column"))?;
+            set_import_id(&mut txn, id);
+        }
+
         Ok(Some(txn))
     }
 
+    /// Collect the `(account, amount)` splits of a row.
+    ///
+    /// Amounts are unsigned portions of the row's amount; a negative split
+    /// (e.g. a partial refund) moves money in the other direction.
+    fn parse_splits(
+        &self,
+        record: &csv::StringRecord,
+        csv_config: &CsvConfig,
+        header_map: &HashMap<String, usize>,
+    ) -> Result<Vec<(String, Decimal)>> {
+        let mut splits = Vec::new();
+
+        if let Some(col) = &csv_config.split_column {
+            let cell = self.get_column(record, col, header_map)?;
+            for entry in cell.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+                let (category, value) = entry.rsplit_once(':').with_context(|| {
+                    format!("invalid split '{entry}', expected 'Category: amount'")
+                })?;
+                let value = parse_money_string(value, csv_config.decimal_comma)
+                    .with_context(|| format!("invalid split amount in '{entry}'"))?;
+                let account = category_account(&csv_config.split_account_prefix, category)
+                    .with_context(|| format!("invalid split category in '{entry}'"))?;
+                splits.push((account, value));
+            }
+        }
+
+        for category in &csv_config.category_columns {
+            let cell = self.get_column(record, &category.column, header_map)?;
+            if let Some(value) = parse_money_string(cell, csv_config.decimal_comma) {
+                if value != Decimal::ZERO {
+                    splits.push((category.account.clone(), value));
+                }
+            }
+        }
+
+        Ok(splits)
+    }
+
     fn get_column<'a>(
         &self,
         record: &'a csv::StringRecord,
@@ -200,7 +283,7 @@ impl CsvImporter {
 
             if let Some(debit_col) = &csv_config.debit_column {
                 if let Ok(debit_str) = self.get_column(record, debit_col, header_map) {
-                    if let Some(val) = parse_money_string(debit_str) {
+                    if let Some(val) = parse_money_string(debit_str, csv_config.decimal_comma) {
                         amount -= val; // Debits are negative
                     }
                 }
@@ -208,7 +291,7 @@ impl CsvImporter {
 
             if let Some(credit_col) = &csv_config.credit_column {
                 if let Ok(credit_str) = self.get_column(record, credit_col, header_map) {
-                    if let Some(val) = parse_money_string(credit_str) {
+                    if let Some(val) = parse_money_string(credit_str, csv_config.decimal_comma) {
                         amount += val; // Credits are positive
                     }
                 }
@@ -224,60 +307,167 @@ impl CsvImporter {
             .context("No amount column configured")?;
 
         let amount_str = self.get_column(record, amount_col, header_map)?;
-        parse_money_string(amount_str).context("Failed to parse amount")
+        parse_money_string(amount_str, csv_config.decimal_comma).context("Failed to parse amount")
     }
 }
 
-/// Parse a money string, handling currency symbols, parentheses for negatives, etc.
-fn parse_money_string(s: &str) -> Option<Decimal> {
-    let s = s.trim();
-    if s.is_empty() {
-        return None;
-    }
-
-    // Check for parentheses indicating negative
-    let (is_negative, s) = if s.starts_with('(') && s.ends_with(')') {
-        (true, &s[1..s.len() - 1])
-    } else {
-        (false, s)
+/// Decode file content from the encoding with the given label.
+///
+/// A byte order mark, if present, takes precedence over the label.
+fn decode(bytes: &[u8], label: Option<&str>) -> Result<String> {
+    let encoding = match label {
+        Some(label) => Encoding::for_label(label.trim().as_bytes())
+            .with_context(|| format!("Unknown encoding '{label}'"))?,
+        None => UTF_8,
     };
 
-    // Remove currency symbols and commas
-    let cleaned: String = s
-        .chars()
-        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-' || *c == '+')
+    let (content, actual, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        bail!("File is not valid {}", actual.name());
+    }
+    Ok(content.into_owned())
+}
+
+/// Derive an account name from a budget category under `prefix`.
+///
+/// `Food: Dining out` under `Expenses` becomes `Expenses:Food:DiningOut`;
+/// `/` is also accepted as a category group separator.
+fn category_account(prefix: &str, category: &str) -> Option<String> {
+    let components: Vec<String> = category
+        .split([':', '/'])
+        .map(|part| {
+            part.split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                })
+                .collect::<String>()
+        })
+        .filter(|component| !component.is_empty())
         .collect();
 
-    if cleaned.is_empty() {
+    if components.is_empty() {
         return None;
     }
+    Some(format!("{prefix}:{}", components.join(":")))
+}
 
-    let value = Decimal::from_str(&cleaned).ok()?;
-
-    if is_negative {
-        Some(-value)
-    } else {
-        Some(value)
-    }
+/// Parse a money string, handling currency symbols, parentheses for negatives, etc.
+///
+/// With `decimal_comma`, `,` is the decimal separator and `.` groups
+/// thousands (`1.234,56`); otherwise the reverse. A currency written in the
+/// cell is ignored in favor of the configured one.
+fn parse_money_string(s: &str, decimal_comma: bool) -> Option<Decimal> {
+    AmountParser::new()
+        .with_decimal_comma(decimal_comma)
+        .parse_number(s)
+        .ok()
+        .map(|(number, _)| number)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::ImporterType;
+    use crate::dedup::{SOURCE_LINE_KEY, import_id, source_desc};
+    use rustledger_core::MetaValue;
+    use std::str::FromStr;
 
     #[test]
     fn test_parse_money_string() {
-        assert_eq!(parse_money_string("100.00"), Some(Decimal::from(100)));
-        assert_eq!(parse_money_string("$100.00"), Some(Decimal::from(100)));
         assert_eq!(
-            parse_money_string("1,234.56"),
+            parse_money_string("100.00", false),
+            Some(Decimal::from(100))
+        );
+        assert_eq!(
+            parse_money_string("$100.00", false),
+            Some(Decimal::from(100))
+        );
+        assert_eq!(
+            parse_money_string("1,234.56", false),
             Some(Decimal::from_str("1234.56").unwrap())
         );
-        assert_eq!(parse_money_string("-50.00"), Some(Decimal::from(-50)));
-        assert_eq!(parse_money_string("(50.00)"), Some(Decimal::from(-50)));
-        assert_eq!(parse_money_string(""), None);
-        assert_eq!(parse_money_string("N/A"), None);
+        assert_eq!(
+            parse_money_string("-50.00", false),
+            Some(Decimal::from(-50))
+        );
+        assert_eq!(
+            parse_money_string("(50.00)", false),
+            Some(Decimal::from(-50))
+        );
+        assert_eq!(parse_money_string("", false), None);
+        assert_eq!(parse_money_string("N/A", false), None);
+    }
+
+    #[test]
+    fn test_parse_money_string_decimal_comma() {
+        assert_eq!(
+            parse_money_string("1.234,56", true),
+            Some(Decimal::from_str("1234.56").unwrap())
+        );
+        assert_eq!(
+            parse_money_string("-12,50 €", true),
+            Some(Decimal::from_str("-12.50").unwrap())
+        );
+        assert_eq!(parse_money_string("(7,00)", true), Some(Decimal::from(-7)));
+        assert_eq!(
+            parse_money_string("1 234,56", true),
+            Some(Decimal::from_str("1234.56").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        // "Café" in Windows-1252
+        let bytes = b"Caf\xe9";
+        assert_eq!(decode(bytes, Some("windows-1252")).unwrap(), "Café");
+        assert_eq!(decode(bytes, Some("latin1")).unwrap(), "Café");
+        assert!(decode(bytes, None).is_err());
+        assert!(decode(bytes, Some("no-such-encoding")).is_err());
+
+        // A UTF-8 BOM is stripped
+        assert_eq!(decode(b"\xef\xbb\xbfDate", None).unwrap(), "Date");
+    }
+
+    #[test]
+    fn test_csv_import_european_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(
+            &path,
+            b"Buchungstag;Verwendungszweck;Betrag\n\
+15.01.2024;Caf\xe9 M\xfcller;-1.234,56\n\
+16.01.2024;Gehalt;2.500,00\n",
+        )
+        .unwrap();
+
+        let config = ImporterConfig::csv()
+            .account("Assets:Bank:Giro")
+            .currency("EUR")
+            .date_column("Buchungstag")
+            .date_format("%d.%m.%Y")
+            .narration_column("Verwendungszweck")
+            .amount_column("Betrag")
+            .delimiter(';')
+            .decimal_comma(true)
+            .encoding("windows-1252")
+            .build();
+
+        let result = config.extract(&path).unwrap();
+        assert_eq!(result.warnings, Vec::<String>::new());
+        assert_eq!(result.directives.len(), 2);
+
+        let Directive::Transaction(txn) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.narration.as_str(), "Café Müller");
+        assert_eq!(
+            txn.postings[0].amount().unwrap().number,
+            Decimal::from_str("-1234.56").unwrap()
+        );
     }
 
     #[test]
@@ -443,6 +633,72 @@ More info
         }
     }
 
+    #[test]
+    fn test_csv_import_with_id_column() {
+        let config = ImporterConfig::csv()
+            .account("Assets:Bank")
+            .currency("USD")
+            .id_column("Reference")
+            .build();
+
+        let csv_content = r"Date,Reference,Description,Amount
+2024-01-15,REF-001,Morning coffee,-5.00
+2024-01-16,,Lunch,-12.00
+";
+
+        let result = config.extract_from_string(csv_content).unwrap();
+        assert_eq!(result.directives.len(), 2);
+
+        let ids: Vec<_> = result
+            .directives
+            .iter()
+            .filter_map(|d| match d {
+                Directive::Transaction(txn) => Some(import_id(txn)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, vec![Some("REF-001"), None]);
+    }
+
+    #[test]
+    fn test_csv_import_source_metadata() {
+        let config = ImporterConfig::csv()
+            .account("Assets:Bank")
+            .currency("USD")
+            .source_metadata(true)
+            .build();
+
+        let csv_content = "Date,Description,Amount\r\n\
+                           2024-01-15,\"Coffee, \"\"to go\"\"\",-5.00\r\n\
+                           2024-01-16,Lunch,-12.00\r\n";
+
+        let result = config.extract_from_string(csv_content).unwrap();
+        let sources: Vec<_> = result
+            .directives
+            .iter()
+            .filter_map(|d| match d {
+                Directive::Transaction(txn) => Some((
+                    source_desc(txn).unwrap().to_string(),
+                    txn.meta[SOURCE_LINE_KEY].clone(),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                (
+                    r#"2024-01-15,"Coffee, ""to go""",-5.00"#.to_string(),
+                    MetaValue::Number(Decimal::from(2))
+                ),
+                (
+                    "2024-01-16,Lunch,-12.00".to_string(),
+                    MetaValue::Number(Decimal::from(3))
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_csv_import_empty_csv() {
         let config = ImporterConfig::csv()
@@ -541,13 +797,19 @@ More info
     #[test]
     fn test_parse_money_string_edge_cases() {
         // Whitespace
-        assert_eq!(parse_money_string("  100.00  "), Some(Decimal::from(100)));
+        assert_eq!(
+            parse_money_string("  100.00  ", false),
+            Some(Decimal::from(100))
+        );
         // Empty after strip
-        assert_eq!(parse_money_string("   "), None);
+        assert_eq!(parse_money_string("   ", false), None);
         // Just currency symbol
-        assert_eq!(parse_money_string("$"), None);
+        assert_eq!(parse_money_string("$", false), None);
         // Negative with currency
-        assert_eq!(parse_money_string("-$100.00"), Some(Decimal::from(-100)));
+        assert_eq!(
+            parse_money_string("-$100.00", false),
+            Some(Decimal::from(-100))
+        );
     }
 
     #[test]
@@ -752,12 +1014,19 @@ not-a-date,Coffee,-5.00
             narration_column: Some(ColumnSpec::Name("Description".to_string())),
             payee_column: None,
             amount_column: None,
+            id_column: None,
             debit_column: None,
             credit_column: None,
             has_header: true,
             delimiter: ',',
             skip_rows: 0,
             invert_sign: false,
+            decimal_comma: false,
+            encoding: None,
+            split_column: None,
+            split_account_prefix: "Expenses".to_string(),
+            category_columns: Vec::new(),
+            source_metadata: false,
         };
 
         let importer = CsvImporter::new(ImporterConfig {
@@ -869,4 +1138,128 @@ not-a-date,Coffee,-5.00
             assert_eq!(amount.number, Decimal::from(100));
         }
     }
+
+    #[test]
+    fn test_category_account() {
+        assert_eq!(
+            category_account("Expenses", "Groceries").as_deref(),
+            Some("Expenses:Groceries")
+        );
+        assert_eq!(
+            category_account("Expenses", " Food: dining out ").as_deref(),
+            Some("Expenses:Food:DiningOut")
+        );
+        assert_eq!(
+            category_account("Expenses:Budget", "Bills/Rent & Utilities").as_deref(),
+            Some("Expenses:Budget:Bills:RentUtilities")
+        );
+        assert_eq!(category_account("Expenses", " - "), None);
+    }
+
+    #[test]
+    fn test_csv_import_split_column() {
+        let config = ImporterConfig::csv()
+            .account("Assets:Bank:Checking")
+            .currency("USD")
+            .date_column("Date")
+            .narration_column("Description")
+            .amount_column("Amount")
+            .split_column("Categories")
+            .build();
+
+        let csv_content = r#"Date,Description,Amount,Categories
+2024-01-15,Supermarket,-25.00,"Groceries: 20.00; Household: 5.00"
+2024-01-16,Pharmacy,-30.00,Health: 12.50
+2024-01-17,Bakery,-4.00,
+"#;
+
+        let result = config.extract_from_string(csv_content).unwrap();
+        assert_eq!(result.warnings, Vec::<String>::new());
+        assert_eq!(result.directives.len(), 3);
+
+        // Fully split: no balancing posting
+        let Directive::Transaction(txn) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.postings.len(), 3);
+        assert_eq!(txn.postings[1].account.as_str(), "Expenses:Groceries");
+        assert_eq!(txn.postings[1].amount().unwrap().number, Decimal::from(20));
+        assert_eq!(txn.postings[2].account.as_str(), "Expenses:Household");
+        assert_eq!(txn.postings[2].amount().unwrap().number, Decimal::from(5));
+
+        // Partially split: the remainder goes to the balancing posting
+        let Directive::Transaction(txn) = &result.directives[1] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.postings.len(), 3);
+        assert_eq!(txn.postings[1].account.as_str(), "Expenses:Health");
+        assert_eq!(txn.postings[2].account.as_str(), "Income:Unknown");
+        assert!(txn.postings[2].units.is_none());
+
+        // No splits
+        let Directive::Transaction(txn) = &result.directives[2] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.postings.len(), 2);
+    }
+
+    #[test]
+    fn test_csv_import_split_column_invalid_entry() {
+        let config = ImporterConfig::csv()
+            .account("Assets:Bank")
+            .date_column("Date")
+            .amount_column("Amount")
+            .split_column("Categories")
+            .build();
+
+        let csv_content = "Date,Description,Amount,Categories\n\
+2024-01-15,Shop,-25.00,Groceries 20.00\n";
+
+        let result = config.extract_from_string(csv_content).unwrap();
+        assert_eq!(result.directives.len(), 0);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("invalid split"));
+    }
+
+    #[test]
+    fn test_csv_import_category_columns() {
+        let config = ImporterConfig::csv()
+            .account("Liabilities:CreditCard")
+            .currency("EUR")
+            .date_column("Date")
+            .narration_column("Payee")
+            .amount_column("Total")
+            .category_column("Groceries", "Expenses:Food:Groceries")
+            .category_column("Household", "Expenses:Household")
+            .delimiter(';')
+            .decimal_comma(true)
+            .build();
+
+        let csv_content = "Date;Payee;Total;Groceries;Household\n\
+2024-01-15;Supermarket;-25,50;20,50;5,00\n\
+2024-01-16;Refund;3,00;;3,00\n";
+
+        let result = config.extract_from_string(csv_content).unwrap();
+        assert_eq!(result.warnings, Vec::<String>::new());
+        assert_eq!(result.directives.len(), 2);
+
+        let Directive::Transaction(txn) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.postings.len(), 3);
+        assert_eq!(txn.postings[1].account.as_str(), "Expenses:Food:Groceries");
+        assert_eq!(
+            txn.postings[1].amount().unwrap().number,
+            Decimal::from_str("20.50").unwrap()
+        );
+        assert_eq!(txn.postings[2].account.as_str(), "Expenses:Household");
+
+        // Splits of an inflow book money back out of the category
+        let Directive::Transaction(txn) = &result.directives[1] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(txn.postings.len(), 2);
+        assert_eq!(txn.postings[1].account.as_str(), "Expenses:Household");
+        assert_eq!(txn.postings[1].amount().unwrap().number, Decimal::from(-3));
+    }
 }
@@ -0,0 +1,242 @@
+//! Dropping imported transactions that are already in the ledger.
+//!
+//! Importers that know a stable identifier for each transaction (an OFX
+//! `FITID`, a CSV reference column, an Open Banking transaction id) record
+//! it under the [`IMPORT_ID_KEY`] metadata key. The [`Deduplicator`]
+//! remembers the identifiers found in an existing ledger and removes
+//! imported transactions carrying one of them, so that overlapping
+//! downloads can be extracted repeatedly.
+//!
+//! Importers can also keep the raw statement row under [`SOURCE_DESC_KEY`]
+//! and its line number under [`SOURCE_LINE_KEY`], for auditing. For
+//! transactions without an identifier the raw row serves as a fingerprint;
+//! the line number is ignored, as it shifts between downloads.
+
+use std::collections::{HashMap, HashSet};
+
+use rust_decimal::Decimal;
+use rustledger_core::{Directive, MetaValue, Transaction};
+
+/// Metadata key holding the statement's identifier for a transaction.
+pub const IMPORT_ID_KEY: &str = "import_id";
+
+/// Metadata key the Open Banking importer used before [`IMPORT_ID_KEY`];
+/// still recognized in existing ledgers.
+pub const TRANSACTION_ID_KEY: &str = "transaction_id";
+
+/// Metadata key holding the raw statement row a transaction came from.
+pub const SOURCE_DESC_KEY: &str = "source_desc";
+
+/// Metadata key holding the line number of [`SOURCE_DESC_KEY`] in the
+/// statement.
+pub const SOURCE_LINE_KEY: &str = "source_line";
+
+/// Filter for transactions that have already been imported.
+#[derive(Debug, Clone, Default)]
+pub struct Deduplicator {
+    seen: HashSet<String>,
+    /// Raw rows of transactions without an identifier, with how often each
+    /// occurs.
+    rows: HashMap<String, usize>,
+}
+
+impl Deduplicator {
+    /// Remember the transaction identifiers and source rows in an existing
+    /// ledger.
+    pub fn from_directives(directives: &[Directive]) -> Self {
+        let mut dedup = Self::default();
+        for directive in directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+            if let Some(id) = import_id(txn) {
+                dedup.seen.insert(id.to_string());
+            } else if let Some(row) = source_desc(txn) {
+                *dedup.rows.entry(row.to_string()).or_default() += 1;
+            }
+        }
+        dedup
+    }
+
+    /// Number of known transactions.
+    pub fn len(&self) -> usize {
+        self.seen.len() + self.rows.values().sum::<usize>()
+    }
+
+    /// Whether no transactions are known.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty() && self.rows.is_empty()
+    }
+
+    /// Remove transactions that are already known.
+    ///
+    /// Repeated identifiers within `directives` are also dropped, keeping
+    /// the first occurrence. A transaction without an identifier is dropped
+    /// when the ledger has an unmatched transaction from the same source
+    /// row; identical rows within `directives` are kept, since a statement
+    /// can list the same purchase twice. Transactions with neither are
+    /// always kept. Returns the number of transactions removed.
+    pub fn remove_duplicates(&self, directives: &mut Vec<Directive>) -> usize {
+        let before = directives.len();
+        let mut duplicates = self.duplicates(directives).into_iter();
+        directives.retain(|_| !duplicates.next().unwrap_or(false));
+        before - directives.len()
+    }
+
+    /// Flag the directives [`remove_duplicates`](Self::remove_duplicates)
+    /// would remove, without removing them.
+    pub fn duplicates(&self, directives: &[Directive]) -> Vec<bool> {
+        let mut batch = HashSet::new();
+        let mut rows = self.rows.clone();
+
+        directives
+            .iter()
+            .map(|directive| {
+                let Directive::Transaction(txn) = directive else {
+                    return false;
+                };
+                if let Some(id) = import_id(txn) {
+                    return self.seen.contains(id) || !batch.insert(id.to_string());
+                }
+                match source_desc(txn).and_then(|row| rows.get_mut(row)) {
+                    Some(remaining) if *remaining > 0 => {
+                        *remaining -= 1;
+                        true
+                    }
+                    _ => false,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The statement's identifier for a transaction, if the importer recorded
+/// one.
+pub fn import_id(txn: &Transaction) -> Option<&str> {
+    [IMPORT_ID_KEY, TRANSACTION_ID_KEY]
+        .iter()
+        .find_map(|key| match txn.meta.get(*key)? {
+            MetaValue::String(id) => Some(id.as_str()),
+            _ => None,
+        })
+}
+
+/// Record the statement's identifier for a transaction.
+///
+/// Blank identifiers are ignored.
+pub fn set_import_id(txn: &mut Transaction, id: &str) {
+    let id = id.trim();
+    if !id.is_empty() {
+        txn.meta
+            .insert(IMPORT_ID_KEY.to_string(), MetaValue::String(id.to_string()));
+    }
+}
+
+/// The raw statement row a transaction came from, if the importer kept it.
+pub fn source_desc(txn: &Transaction) -> Option<&str> {
+    match txn.meta.get(SOURCE_DESC_KEY)? {
+        MetaValue::String(row) => Some(row.as_str()),
+        _ => None,
+    }
+}
+
+/// Record the raw statement row and its line number for a transaction.
+pub fn set_source(txn: &mut Transaction, row: &str, line: u64) {
+    txn.meta.insert(
+        SOURCE_DESC_KEY.to_string(),
+        MetaValue::String(row.to_string()),
+    );
+    txn.meta.insert(
+        SOURCE_LINE_KEY.to_string(),
+        MetaValue::Number(Decimal::from(line)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn txn(day: u32, id: Option<&str>) -> Directive {
+        let mut txn = Transaction::new(NaiveDate::from_ymd_opt(2024, 1, day).unwrap(), "Test");
+        if let Some(id) = id {
+            set_import_id(&mut txn, id);
+        }
+        Directive::Transaction(txn)
+    }
+
+    #[test]
+    fn test_remove_known_ids() {
+        let ledger = vec![txn(1, Some("a")), txn(2, None)];
+        let dedup = Deduplicator::from_directives(&ledger);
+        assert_eq!(dedup.len(), 1);
+
+        let mut imported = vec![txn(1, Some("a")), txn(3, Some("b")), txn(4, None)];
+        assert_eq!(dedup.remove_duplicates(&mut imported), 1);
+        assert_eq!(imported.len(), 2);
+        let Directive::Transaction(first) = &imported[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(import_id(first), Some("b"));
+    }
+
+    #[test]
+    fn test_remove_repeats_within_batch() {
+        let dedup = Deduplicator::default();
+        assert!(dedup.is_empty());
+
+        let mut imported = vec![
+            txn(1, Some("a")),
+            txn(2, Some("a")),
+            txn(3, None),
+            txn(4, None),
+        ];
+        assert_eq!(dedup.remove_duplicates(&mut imported), 1);
+        assert_eq!(imported.len(), 3);
+    }
+
+    #[test]
+    fn test_legacy_transaction_id_key() {
+        let Directive::Transaction(mut legacy) = txn(1, None) else {
+            unreachable!();
+        };
+        legacy.meta.insert(
+            TRANSACTION_ID_KEY.to_string(),
+            MetaValue::String("a".to_string()),
+        );
+        let dedup = Deduplicator::from_directives(&[Directive::Transaction(legacy)]);
+
+        let mut imported = vec![txn(1, Some("a")), txn(2, Some("  "))];
+        assert_eq!(dedup.remove_duplicates(&mut imported), 1);
+        let Directive::Transaction(blank) = &imported[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(import_id(blank), None);
+    }
+
+    #[test]
+    fn test_source_row_fingerprint() {
+        let row = |day, line| {
+            let Directive::Transaction(mut txn) = txn(day, None) else {
+                unreachable!();
+            };
+            set_source(&mut txn, "2024-01-05,Coffee,-4.50", line);
+            Directive::Transaction(txn)
+        };
+        let dedup = Deduplicator::from_directives(&[row(5, 2)]);
+        assert_eq!(dedup.len(), 1);
+
+        // The same row at another line is known once; the repeat is new
+        let mut imported = vec![row(5, 7), row(5, 8), txn(6, None)];
+        assert_eq!(dedup.remove_duplicates(&mut imported), 1);
+        assert_eq!(imported.len(), 2);
+        let Directive::Transaction(kept) = &imported[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(source_desc(kept), Some("2024-01-05,Coffee,-4.50"));
+        assert_eq!(
+            kept.meta.get(SOURCE_LINE_KEY),
+            Some(&MetaValue::Number(Decimal::from(8)))
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Filing downloaded statements into a documents tree.
+//!
+//! This is the counterpart of Python beancount's `bean-file`: once an
+//! importer has identified a file, the file is moved under the documents
+//! directory at a path derived from the importer's account, and named after
+//! the statement date:
+//!
+//! ```text
+//! downloads/chase.csv  ->  documents/Assets/Bank/Chase/2024-01-31.chase.csv
+//! ```
+//!
+//! Beancount's `option "documents"` picks such files up automatically, and a
+//! `document` directive is produced for ledgers that list them explicitly.
+
+use crate::Importer;
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use rustledger_core::{Directive, Document};
+use std::path::{Path, PathBuf};
+
+/// Where an identified file should be filed.
+#[derive(Debug, Clone)]
+pub struct FiledDocument {
+    /// The file as found in the downloads directory.
+    pub source: PathBuf,
+    /// The destination inside the documents tree.
+    pub destination: PathBuf,
+    /// A `document` directive pointing at the destination.
+    pub document: Document,
+}
+
+/// Work out where a file identified by `importer` belongs under `documents`.
+///
+/// Fails if the importer does not know which account the file belongs to.
+pub fn plan(importer: &dyn Importer, path: &Path, documents: &Path) -> Result<FiledDocument> {
+    let account = importer.account(path).with_context(|| {
+        format!(
+            "importer {} does not declare an account for {}",
+            importer.name(),
+            path.display()
+        )
+    })?;
+    let date = statement_date(importer, path);
+    let destination = filed_path(documents, account, date, path);
+    let document = Document::new(date, account, destination.to_string_lossy());
+
+    Ok(FiledDocument {
+        source: path.to_path_buf(),
+        destination,
+        document,
+    })
+}
+
+/// The date a file is filed under.
+///
+/// Uses the importer's statement date if it has one, otherwise the date of
+/// the latest extracted transaction, otherwise the file's modification time.
+pub fn statement_date(importer: &dyn Importer, path: &Path) -> NaiveDate {
+    importer
+        .date(path)
+        .or_else(|| {
+            let result = importer.extract(path).ok()?;
+            result
+                .directives
+                .iter()
+                .filter(|d| matches!(d, Directive::Transaction(_)))
+                .map(Directive::date)
+                .max()
+        })
+        .or_else(|| {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            Some(chrono::DateTime::<Local>::from(modified).date_naive())
+        })
+        .unwrap_or_else(|| Local::now().date_naive())
+}
+
+/// The path a file is filed to: `<documents>/<account components>/<name>`.
+pub fn filed_path(documents: &Path, account: &str, date: NaiveDate, path: &Path) -> PathBuf {
+    let mut destination = documents.to_path_buf();
+    destination.extend(account.split(':'));
+    destination.push(filed_name(date, path));
+    destination
+}
+
+/// The file name a file is filed under: `YYYY-MM-DD.<original name>`.
+///
+/// Names that already start with a date are kept as they are.
+pub fn filed_name(date: NaiveDate, path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dated = name
+        .get(..10)
+        .is_some_and(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").is_ok());
+    if dated {
+        name
+    } else {
+        format!("{}.{name}", date.format("%Y-%m-%d"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegistryConfig;
+    use crate::registry::ImporterRegistry;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_filed_name() {
+        let d = date(2024, 1, 31);
+        assert_eq!(
+            filed_name(d, Path::new("downloads/chase.csv")),
+            "2024-01-31.chase.csv"
+        );
+        assert_eq!(
+            filed_name(d, Path::new("2023-12-31.statement.pdf")),
+            "2023-12-31.statement.pdf"
+        );
+    }
+
+    #[test]
+    fn test_filed_path() {
+        let path = filed_path(
+            Path::new("documents"),
+            "Assets:Bank:Chase",
+            date(2024, 1, 31),
+            Path::new("/tmp/chase.csv"),
+        );
+        assert_eq!(
+            path,
+            Path::new("documents/Assets/Bank/Chase/2024-01-31.chase.csv")
+        );
+    }
+
+    #[test]
+    fn test_plan_uses_latest_transaction_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("chase.csv");
+        std::fs::write(
+            &file,
+            "Date,Description,Amount\n2024-01-05,Coffee,-4.50\n2024-01-28,Payroll,2000.00\n",
+        )
+        .unwrap();
+
+        let config = RegistryConfig::from_toml(
+            r#"
+            [[importer]]
+            name = "chase"
+            type = "csv"
+            match = ["*chase*.csv"]
+            account = "Assets:Bank:Chase"
+            "#,
+        )
+        .unwrap();
+        let registry = ImporterRegistry::from_registry_config(config);
+        let importer = registry.identify(&file).unwrap();
+
+        let filed = plan(importer.as_ref(), &file, Path::new("documents")).unwrap();
+        assert_eq!(
+            filed.destination,
+            Path::new("documents/Assets/Bank/Chase/2024-01-28.chase.csv")
+        );
+        assert_eq!(filed.document.date, date(2024, 1, 28));
+        assert_eq!(filed.document.account.as_str(), "Assets:Bank:Chase");
+    }
+}
@@ -0,0 +1,227 @@
+//! Exchange rates for foreign-currency imports.
+//!
+//! Statements of foreign-currency accounts, or card statements listing
+//! purchases abroad, give amounts in a currency the ledger is not kept in.
+//! [`FxRates::annotate`] attaches a per-unit price (`@`) in the importer's
+//! currency to such postings, so the counter-posting is booked in that
+//! currency:
+//!
+//! ```beancount
+//! 2024-03-05 * "Hotel Roma"
+//!   Assets:Wise:EUR   -120.00 EUR @ 1.0850 USD
+//!   Expenses:Unknown
+//! ```
+//!
+//! The rate used is the latest one on or before the transaction date, read
+//! from the `price` directives of an existing ledger; inverse prices are
+//! used when only the other direction is known.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rustledger_core::{Amount, Directive, PriceAnnotation};
+
+use crate::ImportResult;
+
+/// Historical exchange rates, by currency pair and date.
+#[derive(Debug, Clone, Default)]
+pub struct FxRates {
+    /// Units of the quote currency per unit of the base currency, keyed by
+    /// `(base, quote)`.
+    rates: HashMap<(String, String), BTreeMap<NaiveDate, Decimal>>,
+}
+
+impl FxRates {
+    /// Create an empty rate table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the rates of the `price` directives in an existing ledger.
+    pub fn from_directives(directives: &[Directive]) -> Self {
+        let mut rates = Self::new();
+        for directive in directives {
+            if let Directive::Price(price) = directive {
+                rates.insert(
+                    price.date,
+                    &price.currency,
+                    &price.amount.currency,
+                    price.amount.number,
+                );
+            }
+        }
+        rates
+    }
+
+    /// Record that one unit of `base` was worth `rate` units of `quote` on
+    /// `date`.
+    pub fn insert(&mut self, date: NaiveDate, base: &str, quote: &str, rate: Decimal) {
+        self.rates
+            .entry((base.to_string(), quote.to_string()))
+            .or_default()
+            .insert(date, rate);
+    }
+
+    /// Whether no rate is known.
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// The value of one unit of `base` in `quote` on `date`.
+    ///
+    /// Uses the latest rate on or before `date`, falling back to the
+    /// inverse of the latest `quote`/`base` rate.
+    pub fn rate(&self, date: NaiveDate, base: &str, quote: &str) -> Option<Decimal> {
+        let latest = |base: &str, quote: &str| {
+            self.rates
+                .get(&(base.to_string(), quote.to_string()))?
+                .range(..=date)
+                .next_back()
+                .map(|(_, rate)| *rate)
+        };
+        latest(base, quote).or_else(|| {
+            latest(quote, base)
+                .filter(|rate| !rate.is_zero())
+                .map(|rate| Decimal::ONE / rate)
+        })
+    }
+
+    /// Attach `@` prices in `currency` to imported postings in other
+    /// currencies.
+    ///
+    /// Postings that already have a price or cost are left alone. A
+    /// warning is added for every posting no rate is known for. Returns
+    /// the number of postings annotated.
+    pub fn annotate(&self, result: &mut ImportResult, currency: &str) -> usize {
+        let mut count = 0;
+        let mut warnings = Vec::new();
+
+        for directive in &mut result.directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+            for posting in &mut txn.postings {
+                if posting.price.is_some() || posting.cost.is_some() {
+                    continue;
+                }
+                let Some(units) = posting.amount() else {
+                    continue;
+                };
+                if units.currency == currency {
+                    continue;
+                }
+
+                match self.rate(txn.date, &units.currency, currency) {
+                    Some(rate) => {
+                        posting.price = Some(PriceAnnotation::Unit(Amount::new(rate, currency)));
+                        count += 1;
+                    }
+                    None => warnings.push(format!(
+                        "no {}/{currency} rate on or before {} for \"{}\"",
+                        units.currency, txn.date, txn.narration
+                    )),
+                }
+            }
+        }
+
+        result.warnings.extend(warnings);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_core::{CostSpec, Posting, Price, Transaction};
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn purchase(day: NaiveDate, amount: Decimal, currency: &str) -> Directive {
+        Directive::Transaction(
+            Transaction::new(day, "Purchase")
+                .with_posting(Posting::new("Assets:Wise", Amount::new(amount, currency)))
+                .with_posting(Posting::auto("Expenses:Unknown")),
+        )
+    }
+
+    fn prices() -> FxRates {
+        FxRates::from_directives(&[
+            Directive::Price(Price::new(
+                date(2024, 3, 1),
+                "EUR",
+                Amount::new(dec("1.08"), "USD"),
+            )),
+            Directive::Price(Price::new(
+                date(2024, 3, 4),
+                "EUR",
+                Amount::new(dec("1.09"), "USD"),
+            )),
+            Directive::Price(Price::new(
+                date(2024, 3, 1),
+                "USD",
+                Amount::new(dec("0.80"), "GBP"),
+            )),
+        ])
+    }
+
+    #[test]
+    fn test_rate_lookup() {
+        let rates = prices();
+        assert_eq!(
+            rates.rate(date(2024, 3, 3), "EUR", "USD"),
+            Some(dec("1.08"))
+        );
+        assert_eq!(
+            rates.rate(date(2024, 3, 4), "EUR", "USD"),
+            Some(dec("1.09"))
+        );
+        assert_eq!(rates.rate(date(2024, 2, 28), "EUR", "USD"), None);
+        // Inverse of USD/GBP
+        assert_eq!(
+            rates.rate(date(2024, 3, 2), "GBP", "USD"),
+            Some(dec("1.25"))
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        let mut priced = purchase(date(2024, 3, 5), dec("-10"), "EUR");
+        if let Directive::Transaction(txn) = &mut priced {
+            txn.postings[0].cost = Some(CostSpec::empty());
+        }
+        let mut result = ImportResult::new(vec![
+            purchase(date(2024, 3, 5), dec("-120.00"), "EUR"),
+            purchase(date(2024, 3, 5), dec("-5.00"), "USD"),
+            purchase(date(2024, 2, 1), dec("-7.00"), "EUR"),
+            priced,
+        ]);
+
+        assert_eq!(prices().annotate(&mut result, "USD"), 1);
+
+        let Directive::Transaction(txn) = &result.directives[0] else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(
+            txn.postings[0].price,
+            Some(PriceAnnotation::Unit(Amount::new(dec("1.09"), "USD")))
+        );
+        for directive in &result.directives[1..] {
+            let Directive::Transaction(txn) = directive else {
+                panic!("expected a transaction");
+            };
+            assert_eq!(txn.postings[0].price, None);
+        }
+        assert_eq!(
+            result.warnings,
+            ["no EUR/USD rate on or before 2024-02-01 for \"Purchase\""]
+        );
+    }
+}
@@ -30,18 +30,38 @@
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
+pub mod archive;
+pub mod categorize;
 pub mod config;
 pub mod csv_importer;
+pub mod dedup;
+pub mod filing;
+pub mod fx;
 pub mod ofx_importer;
+#[cfg(feature = "open-banking")]
+pub mod open_banking;
+pub mod post_process;
 pub mod registry;
+pub mod registry_config;
+pub mod session;
 
 use anyhow::Result;
+use chrono::NaiveDate;
 use rustledger_core::Directive;
 use std::path::Path;
 
+pub use archive::SourceFiles;
+pub use categorize::Categorizer;
 pub use config::ImporterConfig;
+pub use dedup::Deduplicator;
+pub use fx::FxRates;
 pub use ofx_importer::OfxImporter;
+#[cfg(feature = "open-banking")]
+pub use open_banking::{OpenBankingClient, OpenBankingConfig};
+pub use post_process::{PostProcessStep, PostProcessor};
 pub use registry::ImporterRegistry;
+pub use registry_config::RegistryConfig;
+pub use session::{ImportChange, ImportSession};
 
 /// Result of an import operation.
 #[derive(Debug, Clone)]
@@ -97,6 +117,29 @@ pub trait Importer: Send + Sync {
     fn description(&self) -> &str {
         self.name()
     }
+
+    /// The account the given file belongs to, used to file it away.
+    ///
+    /// Importers that do not know their account return `None` and cannot
+    /// be used for filing.
+    fn account(&self, _path: &Path) -> Option<&str> {
+        None
+    }
+
+    /// The date of the statement in the given file.
+    ///
+    /// When `None`, filing falls back to the latest transaction date and
+    /// then to the file's modification time.
+    fn date(&self, _path: &Path) -> Option<NaiveDate> {
+        None
+    }
+
+    /// The currency postings in other currencies are priced in, if any.
+    ///
+    /// See [`FxRates::annotate`].
+    fn fx_currency(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// Extract transactions from a file using the given configuration.
@@ -2,7 +2,10 @@
 //!
 //! This module implements importing transactions from OFX (Open Financial Exchange)
 //! and QFX (Quicken Financial Exchange) files commonly exported by banks.
+//! Each transaction's `FITID` is kept as its import id so that overlapping
+//! statements can be deduplicated.
 
+use crate::dedup::set_import_id;
 use crate::{ImportResult, Importer};
 use anyhow::{Context, Result};
 use chrono::{Datelike, NaiveDate};
@@ -135,6 +138,8 @@ impl OfxImporter {
             txn_builder = txn_builder.with_payee(name);
         }
 
+        set_import_id(&mut txn_builder, &txn.id);
+
         Ok(txn_builder)
     }
 }
@@ -155,6 +160,10 @@ impl Importer for OfxImporter {
         self.extract_from_string(&content)
     }
 
+    fn account(&self, _path: &Path) -> Option<&str> {
+        Some(&self.account)
+    }
+
     fn description(&self) -> &'static str {
         "Open Financial Exchange (OFX/QFX) file importer"
     }
@@ -278,6 +287,10 @@ NEWFILEUID:NONE
             Ok(import_result) => {
                 assert_eq!(import_result.directives.len(), 2);
                 assert!(import_result.warnings.is_empty());
+                let Directive::Transaction(first) = &import_result.directives[0] else {
+                    panic!("expected transaction");
+                };
+                assert_eq!(crate::dedup::import_id(first), Some("2024011501"));
             }
             Err(e) => {
                 // Some OFX parsers may be strict about format
@@ -287,6 +300,46 @@ NEWFILEUID:NONE
         }
     }
 
+    #[test]
+    fn test_ofx_importer_import_id_from_fitid() {
+        let ofx_content = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+SECURITY:NONE
+ENCODING:USASCII
+CHARSET:1252
+COMPRESSION:NONE
+OLDFILEUID:NONE
+NEWFILEUID:NONE
+
+<OFX><SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS>\
+<DTSERVER>20240430120000[0:GMT]<LANGUAGE>ENG</SONRS></SIGNONMSGSRSV1>\
+<CREDITCARDMSGSRSV1><CCSTMTTRNRS><TRNUID>0<STATUS><CODE>0<SEVERITY>INFO</STATUS>\
+<CCSTMTRS><CURDEF>USD<CCACCTFROM><ACCTID>abc123</CCACCTFROM><BANKTRANLIST>\
+<DTSTART>20240401120000[0:GMT]<DTEND>20240430120000[0:GMT]\
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240429120000[0:GMT]<TRNAMT>-46.05\
+<FITID>abcde-12345<NAME>AN AWESOME RESTAURANT</STMTTRN>\
+<STMTTRN><TRNTYPE>PAYMENT<DTPOSTED>20240430120000[0:GMT]<TRNAMT>3220.56\
+<FITID>abcd-1234<NAME>ACH DEPOSIT</STMTTRN>\
+</BANKTRANLIST><LEDGERBAL><BALAMT>-2749.00<DTASOF>20240430120000[0:GMT]</LEDGERBAL>\
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>";
+
+        let importer = OfxImporter::new("Liabilities:CreditCard", "USD");
+        let result = importer.extract_from_string(ofx_content).unwrap();
+
+        let ids: Vec<_> = result
+            .directives
+            .iter()
+            .map(|directive| {
+                let Directive::Transaction(txn) = directive else {
+                    panic!("expected transaction");
+                };
+                crate::dedup::import_id(txn)
+            })
+            .collect();
+        assert_eq!(ids, [Some("abcde-12345"), Some("abcd-1234")]);
+    }
+
     #[test]
     fn test_ofx_importer_credit_card() {
         // Credit card OFX content
@@ -0,0 +1,449 @@
+//! Importing transactions directly from an Open Banking API.
+//!
+//! The [`OpenBankingClient`] talks to a GoCardless Bank Account Data
+//! (formerly Nordigen) compatible API. Account requisitions are set up once
+//! through the provider; afterwards a TOML configuration maps each API
+//! account id to a ledger account:
+//!
+//! ```toml
+//! secret_id = "..."
+//! secret_key = "..."
+//!
+//! [[account]]
+//! id = "7e944232-bda9-40bc-b784-660c7ab5fe78"
+//! account = "Assets:Bank:Checking"
+//! ```
+//!
+//! An `access_token` may be given instead of `secret_id`/`secret_key`, and
+//! `base_url` overrides the default GoCardless endpoint.
+//!
+//! Booked transactions become directives in the same shape as the file
+//! importers produce: the mapped account on one side and
+//! `Expenses:Unknown`/`Income:Unknown` on the other, ready for the
+//! [`Categorizer`](crate::Categorizer). The bank's transaction id is kept in
+//! the [`IMPORT_ID_KEY`](crate::dedup::IMPORT_ID_KEY) metadata so the
+//! [`Deduplicator`](crate::Deduplicator) can skip transactions that were
+//! imported before. Pending transactions are ignored.
+
+use crate::ImportResult;
+use crate::dedup::set_import_id;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rustledger_core::{Amount, Directive, Posting, Transaction};
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Default API endpoint (GoCardless Bank Account Data, v2).
+pub const DEFAULT_BASE_URL: &str = "https://bankaccountdata.gocardless.com/api/v2";
+
+/// Configuration for importing from an Open Banking API.
+///
+/// `Debug` output redacts the secrets and the access token.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenBankingConfig {
+    /// The API endpoint, without a trailing slash.
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    /// The secret id used to request an access token.
+    #[serde(default)]
+    pub secret_id: Option<String>,
+    /// The secret key used to request an access token.
+    #[serde(default)]
+    pub secret_key: Option<String>,
+    /// A ready-made access token, used instead of the secrets.
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// The API accounts to import, in output order.
+    #[serde(default, rename = "account")]
+    pub accounts: Vec<AccountMapping>,
+}
+
+/// Maps an API account to a ledger account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountMapping {
+    /// The account id assigned by the API.
+    pub id: String,
+    /// The ledger account transactions are booked to.
+    pub account: String,
+}
+
+/// Placeholder shown by `Debug` for credentials that are set.
+fn redacted(secret: Option<&String>) -> Option<&'static str> {
+    secret.map(|_| "<redacted>")
+}
+
+impl std::fmt::Debug for OpenBankingConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenBankingConfig")
+            .field("base_url", &self.base_url)
+            .field("secret_id", &self.secret_id)
+            .field("secret_key", &redacted(self.secret_key.as_ref()))
+            .field("access_token", &redacted(self.access_token.as_ref()))
+            .field("accounts", &self.accounts)
+            .finish()
+    }
+}
+
+fn default_base_url() -> String {
+    DEFAULT_BASE_URL.to_string()
+}
+
+impl OpenBankingConfig {
+    /// Load a configuration from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Open Banking config: {}", path.display()))?;
+        Self::from_toml(&content)
+            .with_context(|| format!("Invalid Open Banking config: {}", path.display()))
+    }
+
+    /// Parse a configuration from TOML content.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let has_secrets = self.secret_id.is_some() && self.secret_key.is_some();
+        if self.access_token.is_none() && !has_secrets {
+            bail!("either access_token or both secret_id and secret_key are required");
+        }
+        if self.accounts.is_empty() {
+            bail!("no accounts configured");
+        }
+        Ok(())
+    }
+}
+
+/// The transactions endpoint's response.
+#[derive(Debug, Deserialize)]
+struct TransactionsResponse {
+    transactions: TransactionLists,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionLists {
+    #[serde(default)]
+    booked: Vec<ApiTransaction>,
+}
+
+/// A single transaction as returned by the API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiTransaction {
+    transaction_id: Option<String>,
+    internal_transaction_id: Option<String>,
+    booking_date: Option<String>,
+    value_date: Option<String>,
+    transaction_amount: ApiAmount,
+    creditor_name: Option<String>,
+    debtor_name: Option<String>,
+    remittance_information_unstructured: Option<String>,
+    #[serde(default)]
+    remittance_information_unstructured_array: Vec<String>,
+    additional_information: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiAmount {
+    amount: String,
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access: String,
+}
+
+/// Client for a GoCardless/Nordigen-compatible Open Banking API.
+#[derive(Clone)]
+pub struct OpenBankingClient {
+    config: OpenBankingConfig,
+    token: String,
+}
+
+impl std::fmt::Debug for OpenBankingClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenBankingClient")
+            .field("config", &self.config)
+            .field("token", &redacted(Some(&self.token)))
+            .finish()
+    }
+}
+
+impl OpenBankingClient {
+    /// Connect to the API, requesting an access token if none is configured.
+    pub fn connect(config: OpenBankingConfig) -> Result<Self> {
+        let token = match (&config.access_token, &config.secret_id, &config.secret_key) {
+            (Some(token), _, _) => token.clone(),
+            (None, Some(secret_id), Some(secret_key)) => {
+                let url = format!("{}/token/new/", config.base_url);
+                let response: TokenResponse = ureq::post(&url)
+                    .header("Accept", "application/json")
+                    .send_json(serde_json::json!({
+                        "secret_id": secret_id,
+                        "secret_key": secret_key,
+                    }))
+                    .context("Failed to request an access token")?
+                    .body_mut()
+                    .read_json()
+                    .context("Failed to parse access token response")?;
+                response.access
+            }
+            _ => bail!("either access_token or both secret_id and secret_key are required"),
+        };
+        Ok(Self { config, token })
+    }
+
+    /// The configuration this client was created with.
+    pub const fn config(&self) -> &OpenBankingConfig {
+        &self.config
+    }
+
+    /// Fetch the booked transactions of one mapped account.
+    ///
+    /// With `since`, only transactions booked on or after that date are
+    /// requested.
+    pub fn extract_account(
+        &self,
+        mapping: &AccountMapping,
+        since: Option<NaiveDate>,
+    ) -> Result<ImportResult> {
+        let url = format!(
+            "{}/accounts/{}/transactions/",
+            self.config.base_url, mapping.id
+        );
+        let mut request = ureq::get(&url)
+            .header("Accept", "application/json")
+            .header("Authorization", format!("Bearer {}", self.token));
+        if let Some(since) = since {
+            request = request.query("date_from", since.format("%Y-%m-%d").to_string());
+        }
+
+        let body = request
+            .call()
+            .with_context(|| format!("Failed to fetch transactions for {}", mapping.account))?
+            .body_mut()
+            .read_to_string()
+            .with_context(|| format!("Failed to read transactions for {}", mapping.account))?;
+
+        extract_from_json(&body, &mapping.account)
+    }
+
+    /// Fetch the booked transactions of every mapped account.
+    pub fn extract(
+        &self,
+        since: Option<NaiveDate>,
+    ) -> Result<Vec<(&AccountMapping, ImportResult)>> {
+        self.config
+            .accounts
+            .iter()
+            .map(|mapping| Ok((mapping, self.extract_account(mapping, since)?)))
+            .collect()
+    }
+}
+
+/// Convert a transactions response body into directives for `account`.
+///
+/// Transactions that cannot be converted are skipped with a warning.
+pub fn extract_from_json(content: &str, account: &str) -> Result<ImportResult> {
+    let response: TransactionsResponse =
+        serde_json::from_str(content).context("Failed to parse transactions response")?;
+
+    let mut directives = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, txn) in response.transactions.booked.iter().enumerate() {
+        match convert_transaction(txn, account) {
+            Ok(txn) => directives.push(Directive::Transaction(txn)),
+            Err(e) => warnings.push(format!("transaction {}: {e}", index + 1)),
+        }
+    }
+
+    directives.sort_by_key(Directive::date);
+
+    let mut result = ImportResult::new(directives);
+    for warning in warnings {
+        result = result.with_warning(warning);
+    }
+    Ok(result)
+}
+
+fn convert_transaction(txn: &ApiTransaction, account: &str) -> Result<Transaction> {
+    let date = txn
+        .booking_date
+        .as_deref()
+        .or(txn.value_date.as_deref())
+        .context("missing booking date")?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("invalid date '{date}'"))?;
+
+    let amount = Decimal::from_str(&txn.transaction_amount.amount)
+        .with_context(|| format!("invalid amount '{}'", txn.transaction_amount.amount))?;
+
+    // The counterparty is the creditor for payments and the debtor for receipts
+    let payee = if amount < Decimal::ZERO {
+        txn.creditor_name.as_deref()
+    } else {
+        txn.debtor_name.as_deref()
+    };
+
+    let narration = txn
+        .remittance_information_unstructured
+        .clone()
+        .or_else(|| {
+            (!txn.remittance_information_unstructured_array.is_empty())
+                .then(|| txn.remittance_information_unstructured_array.join(" "))
+        })
+        .or_else(|| txn.additional_information.clone())
+        .unwrap_or_default();
+
+    let contra_account = if amount < Decimal::ZERO {
+        "Expenses:Unknown"
+    } else {
+        "Income:Unknown"
+    };
+
+    let units = Amount::new(amount, &txn.transaction_amount.currency);
+    let mut result = Transaction::new(date, narration.trim())
+        .with_flag('*')
+        .with_posting(Posting::new(account, units))
+        .with_posting(Posting::auto(contra_account));
+
+    if let Some(payee) = payee.map(str::trim).filter(|p| !p.is_empty()) {
+        result = result.with_payee(payee);
+    }
+
+    if let Some(id) = txn
+        .transaction_id
+        .as_ref()
+        .or(txn.internal_transaction_id.as_ref())
+    {
+        set_import_id(&mut result, id);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup::import_id;
+
+    const RESPONSE: &str = r#"{
+        "transactions": {
+            "booked": [
+                {
+                    "transactionId": "tx-2",
+                    "bookingDate": "2024-01-05",
+                    "transactionAmount": {"amount": "2500.00", "currency": "EUR"},
+                    "debtorName": "ACME Corp",
+                    "remittanceInformationUnstructuredArray": ["Salary", "January"]
+                },
+                {
+                    "internalTransactionId": "int-1",
+                    "bookingDate": "2024-01-02",
+                    "valueDate": "2024-01-03",
+                    "transactionAmount": {"amount": "-12.50", "currency": "EUR"},
+                    "creditorName": "Corner Cafe",
+                    "remittanceInformationUnstructured": "Card payment"
+                },
+                {
+                    "transactionAmount": {"amount": "abc", "currency": "EUR"},
+                    "bookingDate": "2024-01-04"
+                }
+            ],
+            "pending": [
+                {
+                    "transactionAmount": {"amount": "-1.00", "currency": "EUR"},
+                    "valueDate": "2024-01-06"
+                }
+            ]
+        }
+    }"#;
+
+    fn transactions(result: &ImportResult) -> Vec<&Transaction> {
+        result
+            .directives
+            .iter()
+            .filter_map(|d| match d {
+                Directive::Transaction(txn) => Some(txn),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_from_json() {
+        let result = extract_from_json(RESPONSE, "Assets:Bank:Checking").unwrap();
+        let txns = transactions(&result);
+        assert_eq!(txns.len(), 2);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("invalid amount"));
+
+        let payment = txns[0];
+        assert_eq!(payment.date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(payment.payee.as_deref(), Some("Corner Cafe"));
+        assert_eq!(payment.narration.as_str(), "Card payment");
+        assert_eq!(import_id(payment), Some("int-1"));
+        assert_eq!(payment.postings[0].account.as_str(), "Assets:Bank:Checking");
+        let units = payment.postings[0].amount().unwrap();
+        assert_eq!(units.number, Decimal::from_str("-12.50").unwrap());
+        assert_eq!(units.currency.as_str(), "EUR");
+        assert_eq!(payment.postings[1].account.as_str(), "Expenses:Unknown");
+
+        let salary = txns[1];
+        assert_eq!(salary.payee.as_deref(), Some("ACME Corp"));
+        assert_eq!(salary.narration.as_str(), "Salary January");
+        assert_eq!(import_id(salary), Some("tx-2"));
+        assert_eq!(salary.postings[1].account.as_str(), "Income:Unknown");
+    }
+
+    #[test]
+    fn test_extract_from_invalid_json() {
+        assert!(extract_from_json("{}", "Assets:Bank").is_err());
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = OpenBankingConfig::from_toml(
+            r#"
+            secret_id = "id"
+            secret_key = "key"
+
+            [[account]]
+            id = "abc"
+            account = "Assets:Bank:Checking"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert_eq!(config.accounts.len(), 1);
+        assert_eq!(config.accounts[0].account, "Assets:Bank:Checking");
+
+        let debug = format!("{config:?}");
+        assert!(debug.contains("\"id\""));
+        assert!(!debug.contains("\"key\""));
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_config_requires_credentials() {
+        let err = OpenBankingConfig::from_toml(
+            r#"
+            [[account]]
+            id = "abc"
+            account = "Assets:Bank"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("access_token"));
+
+        let err = OpenBankingConfig::from_toml(r#"access_token = "t""#).unwrap_err();
+        assert!(err.to_string().contains("no accounts"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Post-processing of extracted directives with plugins.
+//!
+//! An importer declared in `importers.toml` can run plugins over the
+//! directives it extracts before they are returned, to normalize payees,
+//! split out fees or tag transactions. Plugins are either native plugins,
+//! named as in a ledger's `plugin` directive, or WASM plugins given by the
+//! path to their `.wasm` file, and run in declaration order:
+//!
+//! ```toml
+//! [[importer.post_process]]
+//! plugin = "auto_tag"
+//!
+//! [[importer.post_process]]
+//! plugin = "plugins/normalize_payees.wasm"
+//! config = "strip_store_numbers"
+//! ```
+//!
+//! Relative `.wasm` paths are resolved against the directory of the config
+//! file. WASM plugins need the `wasm-plugins` feature. Errors reported by a
+//! plugin become warnings of the import, prefixed with the plugin name.
+
+use crate::ImportResult;
+use anyhow::{Context, Result, bail};
+use rustledger_plugin::{
+    NativePluginRegistry, PluginInput, PluginOptions, PluginOutput, directives_to_wrappers,
+    wrappers_to_directives,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+/// A plugin run over the directives an importer extracted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostProcessStep {
+    /// Name of a native plugin, or path to a `.wasm` plugin.
+    pub plugin: String,
+    /// Configuration string passed to the plugin.
+    #[serde(default)]
+    pub config: Option<String>,
+}
+
+impl PostProcessStep {
+    /// Whether this step runs a WASM plugin rather than a native one.
+    pub fn is_wasm(&self) -> bool {
+        Path::new(&self.plugin)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wasm"))
+    }
+}
+
+/// A loaded plugin.
+enum StagePlugin {
+    /// Native plugin, looked up by name in the registry.
+    Native,
+    /// Compiled WASM plugin.
+    #[cfg(feature = "wasm-plugins")]
+    Wasm(rustledger_plugin::Plugin),
+}
+
+/// A loaded post-processing step.
+struct Stage {
+    name: String,
+    plugin: StagePlugin,
+    config: Option<String>,
+}
+
+/// Runs an importer's post-processing plugins over extracted directives.
+pub struct PostProcessor {
+    registry: NativePluginRegistry,
+    stages: Vec<Stage>,
+}
+
+impl PostProcessor {
+    /// Load the plugins of the given steps.
+    ///
+    /// Fails if a native plugin is unknown or a WASM plugin cannot be
+    /// loaded.
+    pub fn load(steps: &[PostProcessStep]) -> Result<Self> {
+        let registry = NativePluginRegistry::new();
+
+        let mut stages = Vec::with_capacity(steps.len());
+        for step in steps {
+            let plugin = if step.is_wasm() {
+                load_wasm(step)?
+            } else if registry.contains(&step.plugin) {
+                StagePlugin::Native
+            } else {
+                bail!("unknown native plugin '{}'", step.plugin);
+            };
+            stages.push(Stage {
+                name: step.plugin.clone(),
+                plugin,
+                config: step.config.clone(),
+            });
+        }
+
+        Ok(Self { registry, stages })
+    }
+
+    /// Whether there are no plugins to run.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Run the plugins over the directives of `result`, in order.
+    pub fn apply(&self, mut result: ImportResult) -> Result<ImportResult> {
+        if self.is_empty() {
+            return Ok(result);
+        }
+
+        let mut directives = directives_to_wrappers(&result.directives);
+        for stage in &self.stages {
+            let input = PluginInput {
+                directives,
+                options: PluginOptions::default(),
+                config: stage.config.clone(),
+            };
+            let output = self.run(stage, input)?;
+            result.warnings.extend(
+                output
+                    .errors
+                    .into_iter()
+                    .map(|error| format!("{}: {}", stage.name, error.message)),
+            );
+            directives = output.directives;
+        }
+
+        result.directives = wrappers_to_directives(&directives)
+            .context("post-processing plugins returned invalid directives")?;
+        Ok(result)
+    }
+
+    fn run(&self, stage: &Stage, input: PluginInput) -> Result<PluginOutput> {
+        match &stage.plugin {
+            StagePlugin::Native => {
+                let Some(plugin) = self.registry.find(&stage.name) else {
+                    bail!("unknown native plugin '{}'", stage.name);
+                };
+                Ok(plugin.process(input))
+            }
+            #[cfg(feature = "wasm-plugins")]
+            StagePlugin::Wasm(plugin) => plugin
+                .execute(&input, &rustledger_plugin::RuntimeConfig::default())
+                .with_context(|| format!("plugin {} failed", stage.name)),
+        }
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+fn load_wasm(step: &PostProcessStep) -> Result<StagePlugin> {
+    let runtime = rustledger_plugin::RuntimeConfig::default();
+    rustledger_plugin::Plugin::load(Path::new(&step.plugin), &runtime)
+        .map(StagePlugin::Wasm)
+        .with_context(|| format!("failed to load plugin {}", step.plugin))
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+fn load_wasm(step: &PostProcessStep) -> Result<StagePlugin> {
+    bail!(
+        "plugin {} needs WASM support (the `wasm-plugins` feature)",
+        step.plugin
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_core::{Amount, Directive, Posting, Transaction};
+
+    #[test]
+    fn test_native_plugin_post_processing() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let txn = Transaction::new(date, "Lunch")
+            .with_posting(Posting::new(
+                "Assets:Bank",
+                Amount::new((-12).into(), "USD"),
+            ))
+            .with_posting(Posting::auto("Expenses:Food"));
+        let result = ImportResult::new(vec![Directive::Transaction(txn)]);
+
+        let steps = [PostProcessStep {
+            plugin: "auto_tag".to_string(),
+            config: None,
+        }];
+        let result = PostProcessor::load(&steps).unwrap().apply(result).unwrap();
+        let Directive::Transaction(txn) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert!(txn.tags.contains("food"));
+        assert!(txn.postings[1].units.is_none());
+    }
+
+    #[test]
+    fn test_unknown_plugin_rejected() {
+        let steps = [PostProcessStep {
+            plugin: "no_such_plugin".to_string(),
+            config: None,
+        }];
+        let err = PostProcessor::load(&steps).err().unwrap();
+        assert!(err.to_string().contains("unknown native plugin"));
+        assert!(
+            PostProcessStep {
+                plugin: "plugins/Payees.WASM".to_string(),
+                config: None,
+            }
+            .is_wasm()
+        );
+    }
+}
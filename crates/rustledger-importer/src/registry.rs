@@ -1,5 +1,7 @@
 //! Registry for importers.
 
+use crate::archive::SourceFiles;
+use crate::registry_config::{ConfiguredImporter, RegistryConfig};
 use crate::{ImportResult, Importer};
 use anyhow::{Context, Result};
 use std::path::Path;
@@ -21,6 +23,22 @@ impl ImporterRegistry {
         }
     }
 
+    /// Create a registry from an importer configuration file (`importers.toml`).
+    ///
+    /// See [`crate::registry_config`] for the file format.
+    pub fn from_config(path: &Path) -> Result<Self> {
+        RegistryConfig::from_file(path).map(Self::from_registry_config)
+    }
+
+    /// Create a registry from an already-parsed configuration.
+    pub fn from_registry_config(config: RegistryConfig) -> Self {
+        let mut registry = Self::new();
+        for entry in config.importers {
+            registry.register(ConfiguredImporter::new(entry));
+        }
+        registry
+    }
+
     /// Register a new importer.
     pub fn register(&mut self, importer: impl Importer + 'static) {
         self.importers.push(Arc::new(importer));
@@ -47,6 +65,46 @@ impl ImporterRegistry {
             .with_context(|| format!("Failed to extract from: {}", path.display()))
     }
 
+    /// Extract transactions from a file, directory or zip archive.
+    ///
+    /// Directories and archives are expanded with [`SourceFiles::collect`]
+    /// and each contained file is routed to the first importer that
+    /// identifies it. Directives from all files are combined; warnings are
+    /// prefixed with the file they came from, and files that no importer
+    /// accepts or that fail to extract are reported as warnings rather than
+    /// aborting the whole run.
+    pub fn extract_all(&self, path: &Path) -> Result<ImportResult> {
+        let files = SourceFiles::collect(path)?;
+        let mut combined = ImportResult::empty();
+
+        for file in &files {
+            let Some(importer) = self.identify(&file.path) else {
+                combined
+                    .warnings
+                    .push(format!("{}: no matching importer", file.origin));
+                continue;
+            };
+            match importer.extract(&file.path) {
+                Ok(result) => {
+                    combined.directives.extend(result.directives);
+                    combined.warnings.extend(
+                        result
+                            .warnings
+                            .into_iter()
+                            .map(|warning| format!("{}: {warning}", file.origin)),
+                    );
+                }
+                Err(err) => combined.warnings.push(format!(
+                    "{}: {} importer failed: {err:#}",
+                    file.origin,
+                    importer.name()
+                )),
+            }
+        }
+
+        Ok(combined)
+    }
+
     /// List all registered importers.
     pub fn list_importers(&self) -> Vec<(&str, &str)> {
         self.importers
@@ -210,4 +268,61 @@ mod tests {
         let list = registry.list_importers();
         assert!(list.is_empty());
     }
+
+    /// Reports each file's contents as a warning, or fails on "boom".
+    struct EchoImporter;
+
+    impl Importer for EchoImporter {
+        fn name(&self) -> &'static str {
+            "Echo"
+        }
+
+        fn identify(&self, path: &Path) -> bool {
+            path.extension().is_some_and(|ext| ext == "csv")
+        }
+
+        fn extract(&self, path: &Path) -> Result<ImportResult> {
+            let contents = std::fs::read_to_string(path)?;
+            anyhow::ensure!(contents != "boom", "bad statement");
+            Ok(ImportResult::empty().with_warning(contents))
+        }
+
+        fn description(&self) -> &'static str {
+            "Echoes file contents"
+        }
+    }
+
+    #[test]
+    fn test_registry_extract_all_archive() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("statements.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        for (name, contents) in [
+            ("jan.csv", "january"),
+            ("feb.csv", "boom"),
+            ("notes.txt", ""),
+        ] {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut registry = ImporterRegistry::new();
+        registry.register(EchoImporter);
+        let result = registry.extract_all(&zip_path).unwrap();
+
+        let origin = zip_path.display();
+        assert_eq!(
+            result.warnings,
+            vec![
+                format!("{origin}!feb.csv: Echo importer failed: bad statement"),
+                format!("{origin}!jan.csv: january"),
+                format!("{origin}!notes.txt: no matching importer"),
+            ]
+        );
+    }
 }
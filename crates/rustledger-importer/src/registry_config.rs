@@ -0,0 +1,782 @@
+//! Configuration file format for a whole importer registry.
+//!
+//! An `importers.toml` file declares every importer used for a set of
+//! downloads, so that `rledger-extract downloads/` can route each file to the
+//! right importer automatically:
+//!
+//! ```toml
+//! [[importer]]
+//! name = "chase-checking"
+//! type = "csv"
+//! match = ["*chase*.csv"]
+//! account = "Assets:Bank:Chase"
+//! currency = "USD"
+//!
+//! [importer.options]
+//! date_column = "Posting Date"
+//! date_format = "%m/%d/%Y"
+//! amount_column = "Amount"
+//!
+//! [[importer.rules]]
+//! pattern = "STARBUCKS"
+//! account = "Expenses:Food:Coffee"
+//!
+//! [[importer]]
+//! name = "amex"
+//! type = "ofx"
+//! match = ["*.qfx"]
+//! account = "Liabilities:CreditCard:Amex"
+//! ```
+//!
+//! Importers are tried in declaration order; the first one whose `match`
+//! patterns accept the file name wins.
+//!
+//! Foreign-currency accounts can have their postings priced in the ledger's
+//! currency at the rate of the transaction date, taken from the `price`
+//! directives of the ledger given to `rledger-extract --ledger`:
+//!
+//! ```toml
+//! [[importer]]
+//! name = "wise-eur"
+//! type = "csv"
+//! match = ["wise*eur*.csv"]
+//! account = "Assets:Wise:EUR"
+//! currency = "EUR"
+//! fx_currency = "USD"
+//! ```
+//!
+//! European bank exports usually need the locale options:
+//!
+//! ```toml
+//! [importer.options]
+//! delimiter = ";"
+//! decimal_comma = true
+//! encoding = "windows-1252"
+//! ```
+//!
+//! Budgeting app exports that split one transaction over several categories
+//! can list the splits in one column (`Groceries: 20.00; Household: 5.00`)
+//! or use one amount column per category:
+//!
+//! ```toml
+//! [importer.options]
+//! split_column = "Categories"
+//! split_account_prefix = "Expenses"
+//!
+//! [[importer.options.category_columns]]
+//! column = "Household"
+//! account = "Expenses:Household"
+//! ```
+//!
+//! When the export has a unique reference per transaction, naming it in
+//! `id_column` lets overlapping downloads be re-imported without
+//! duplicates:
+//!
+//! ```toml
+//! [importer.options]
+//! id_column = "Reference"
+//! ```
+//!
+//! `source_metadata` keeps each row's raw text and line number on the
+//! transaction (`source_desc` and `source_line`), for auditing:
+//!
+//! ```toml
+//! [importer.options]
+//! source_metadata = true
+//! ```
+//!
+//! Extracted directives can be run through plugins before they are
+//! returned, in declaration order (see [`crate::post_process`]):
+//!
+//! ```toml
+//! [[importer.post_process]]
+//! plugin = "auto_tag"
+//!
+//! [[importer.post_process]]
+//! plugin = "plugins/normalize_payees.wasm"
+//! ```
+
+use crate::config::{CategoryColumn, ColumnSpec, CsvConfig, ImporterType};
+use crate::post_process::{PostProcessStep, PostProcessor};
+use crate::{ImportResult, Importer, ImporterConfig, OfxImporter};
+use anyhow::{Context, Result, bail};
+use rustledger_core::Directive;
+use rustledger_plugin::NativePluginRegistry;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Top-level structure of an importer registry configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// The declared importers, in priority order.
+    #[serde(default, rename = "importer")]
+    pub importers: Vec<ImporterEntry>,
+}
+
+/// A single importer declaration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImporterEntry {
+    /// Unique name of the importer (shown in diagnostics).
+    pub name: String,
+    /// The file format handled by this importer.
+    #[serde(rename = "type")]
+    pub kind: ImporterKind,
+    /// File name glob patterns (`*` and `?`) accepted by this importer.
+    #[serde(rename = "match")]
+    pub patterns: Vec<String>,
+    /// The target account for imported transactions.
+    pub account: String,
+    /// The currency for amounts (if not specified in the file).
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Currency to price postings in other currencies in, using the
+    /// historical rates of the ledger extracted against.
+    #[serde(default)]
+    pub fx_currency: Option<String>,
+    /// Format-specific options.
+    #[serde(default)]
+    pub options: ImporterOptions,
+    /// Categorization rules for the balancing posting.
+    #[serde(default)]
+    pub rules: Vec<CategorizationRule>,
+    /// Plugins run over the extracted directives, in order.
+    #[serde(default)]
+    pub post_process: Vec<PostProcessStep>,
+}
+
+/// File formats that can be declared in a registry configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImporterKind {
+    /// CSV file importer.
+    Csv,
+    /// OFX/QFX file importer.
+    Ofx,
+}
+
+/// Format-specific importer options.
+///
+/// Every field is optional; unset fields keep the [`CsvConfig`] defaults.
+/// Options that do not apply to the declared format are ignored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImporterOptions {
+    /// The column name or index for the date.
+    pub date_column: Option<ColumnRef>,
+    /// The date format (strftime-style).
+    pub date_format: Option<String>,
+    /// The column name or index for the narration/description.
+    pub narration_column: Option<ColumnRef>,
+    /// The column name or index for the payee.
+    pub payee_column: Option<ColumnRef>,
+    /// The column name or index for the amount.
+    pub amount_column: Option<ColumnRef>,
+    /// The column name or index for the transaction reference.
+    pub id_column: Option<ColumnRef>,
+    /// The column name or index for debit amounts.
+    pub debit_column: Option<ColumnRef>,
+    /// The column name or index for credit amounts.
+    pub credit_column: Option<ColumnRef>,
+    /// Whether the CSV has a header row.
+    pub has_header: Option<bool>,
+    /// The field delimiter.
+    pub delimiter: Option<char>,
+    /// Number of rows to skip at the beginning.
+    pub skip_rows: Option<usize>,
+    /// Whether to invert the sign of amounts.
+    pub invert_sign: Option<bool>,
+    /// Whether amounts use a comma as the decimal separator.
+    pub decimal_comma: Option<bool>,
+    /// The file's character encoding label (e.g. `windows-1252`).
+    pub encoding: Option<String>,
+    /// The column name or index for the split list.
+    pub split_column: Option<ColumnRef>,
+    /// The parent account for split categories.
+    pub split_account_prefix: Option<String>,
+    /// Amount columns booked to fixed accounts.
+    #[serde(default)]
+    pub category_columns: Vec<CategoryColumnRef>,
+    /// Whether to keep each row's raw text and line number as metadata.
+    pub source_metadata: Option<bool>,
+}
+
+/// An amount column booked to a fixed account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryColumnRef {
+    /// The column name or index holding the amount.
+    pub column: ColumnRef,
+    /// The account the amount is booked to.
+    pub account: String,
+}
+
+/// A column given either by header name or by zero-based index.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    /// Column specified by zero-based index.
+    Index(usize),
+    /// Column specified by name (from header).
+    Name(String),
+}
+
+impl From<ColumnRef> for ColumnSpec {
+    fn from(column: ColumnRef) -> Self {
+        match column {
+            ColumnRef::Index(i) => Self::Index(i),
+            ColumnRef::Name(name) => Self::Name(name),
+        }
+    }
+}
+
+/// Assigns the balancing posting of matching transactions to an account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategorizationRule {
+    /// Case-insensitive text searched for in the payee and narration.
+    pub pattern: String,
+    /// The account to use for the balancing posting.
+    pub account: String,
+}
+
+impl RegistryConfig {
+    /// Load a registry configuration from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read importer config: {}", path.display()))?;
+        let mut config = Self::from_toml(&content)
+            .with_context(|| format!("Invalid importer config: {}", path.display()))?;
+
+        // WASM plugin paths are relative to the config file
+        if let Some(base) = path.parent() {
+            for step in config
+                .importers
+                .iter_mut()
+                .flat_map(|entry| entry.post_process.iter_mut())
+                .filter(|step| step.is_wasm() && Path::new(&step.plugin).is_relative())
+            {
+                step.plugin = base.join(&step.plugin).to_string_lossy().into_owned();
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse a registry configuration from TOML content.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let plugins = NativePluginRegistry::new();
+        let mut seen = std::collections::HashSet::new();
+        for entry in &self.importers {
+            if !seen.insert(entry.name.as_str()) {
+                bail!("duplicate importer name '{}'", entry.name);
+            }
+            if entry.patterns.is_empty() {
+                bail!("importer '{}' has no match patterns", entry.name);
+            }
+            if let Some(step) = entry
+                .post_process
+                .iter()
+                .find(|step| !step.is_wasm() && !plugins.contains(&step.plugin))
+            {
+                bail!(
+                    "importer '{}' uses unknown plugin '{}'",
+                    entry.name,
+                    step.plugin
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An importer built from an [`ImporterEntry`].
+pub struct ConfiguredImporter {
+    entry: ImporterEntry,
+}
+
+impl ConfiguredImporter {
+    /// Create an importer from its configuration entry.
+    pub const fn new(entry: ImporterEntry) -> Self {
+        Self { entry }
+    }
+
+    /// The configuration entry this importer was built from.
+    pub const fn entry(&self) -> &ImporterEntry {
+        &self.entry
+    }
+
+    fn csv_config(&self) -> ImporterConfig {
+        let options = self.entry.options.clone();
+        let mut csv = CsvConfig::default();
+
+        if let Some(column) = options.date_column {
+            csv.date_column = column.into();
+        }
+        if let Some(format) = options.date_format {
+            csv.date_format = format;
+        }
+        if let Some(column) = options.narration_column {
+            csv.narration_column = Some(column.into());
+        }
+        if let Some(column) = options.payee_column {
+            csv.payee_column = Some(column.into());
+        }
+        if let Some(column) = options.amount_column {
+            csv.amount_column = Some(column.into());
+        }
+        if let Some(column) = options.id_column {
+            csv.id_column = Some(column.into());
+        }
+        if let Some(column) = options.debit_column {
+            csv.debit_column = Some(column.into());
+        }
+        if let Some(column) = options.credit_column {
+            csv.credit_column = Some(column.into());
+        }
+        if let Some(has_header) = options.has_header {
+            csv.has_header = has_header;
+        }
+        if let Some(delimiter) = options.delimiter {
+            csv.delimiter = delimiter;
+        }
+        if let Some(skip_rows) = options.skip_rows {
+            csv.skip_rows = skip_rows;
+        }
+        if let Some(invert_sign) = options.invert_sign {
+            csv.invert_sign = invert_sign;
+        }
+        if let Some(decimal_comma) = options.decimal_comma {
+            csv.decimal_comma = decimal_comma;
+        }
+        if options.encoding.is_some() {
+            csv.encoding = options.encoding;
+        }
+        if let Some(column) = options.split_column {
+            csv.split_column = Some(column.into());
+        }
+        if let Some(prefix) = options.split_account_prefix {
+            csv.split_account_prefix = prefix;
+        }
+        if let Some(source_metadata) = options.source_metadata {
+            csv.source_metadata = source_metadata;
+        }
+        csv.category_columns = options
+            .category_columns
+            .into_iter()
+            .map(|category| CategoryColumn {
+                column: category.column.into(),
+                account: category.account,
+            })
+            .collect();
+
+        ImporterConfig {
+            account: self.entry.account.clone(),
+            currency: self.entry.currency.clone(),
+            importer_type: ImporterType::Csv(csv),
+        }
+    }
+
+    /// Apply categorization rules to the balancing postings.
+    fn categorize(&self, result: &mut ImportResult) {
+        if self.entry.rules.is_empty() {
+            return;
+        }
+
+        for directive in &mut result.directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+
+            let haystack = format!(
+                "{} {}",
+                txn.payee.as_deref().unwrap_or_default(),
+                txn.narration
+            )
+            .to_lowercase();
+
+            let Some(rule) = self
+                .entry
+                .rules
+                .iter()
+                .find(|rule| haystack.contains(&rule.pattern.to_lowercase()))
+            else {
+                continue;
+            };
+
+            for posting in &mut txn.postings {
+                if posting.units.is_none() {
+                    posting.account = rule.account.as_str().into();
+                }
+            }
+        }
+    }
+}
+
+impl Importer for ConfiguredImporter {
+    fn name(&self) -> &str {
+        &self.entry.name
+    }
+
+    fn identify(&self, path: &Path) -> bool {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        self.entry
+            .patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, file_name))
+    }
+
+    fn account(&self, _path: &Path) -> Option<&str> {
+        Some(&self.entry.account)
+    }
+
+    fn fx_currency(&self) -> Option<&str> {
+        self.entry.fx_currency.as_deref()
+    }
+
+    fn extract(&self, path: &Path) -> Result<ImportResult> {
+        let mut result = match self.entry.kind {
+            ImporterKind::Csv => self.csv_config().extract(path)?,
+            ImporterKind::Ofx => OfxImporter::new(
+                self.entry.account.clone(),
+                self.entry
+                    .currency
+                    .clone()
+                    .unwrap_or_else(|| "USD".to_string()),
+            )
+            .extract(path)?,
+        };
+        self.categorize(&mut result);
+        if self.entry.post_process.is_empty() {
+            return Ok(result);
+        }
+        PostProcessor::load(&self.entry.post_process)?
+            .apply(result)
+            .with_context(|| format!("post-processing failed for importer '{}'", self.entry.name))
+    }
+
+    fn description(&self) -> &str {
+        match self.entry.kind {
+            ImporterKind::Csv => "CSV importer (from config)",
+            ImporterKind::Ofx => "OFX/QFX importer (from config)",
+        }
+    }
+}
+
+/// Case-insensitive file name matching supporting `*` and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const CONFIG: &str = r#"
+[[importer]]
+name = "checking"
+type = "csv"
+match = ["checking*.csv"]
+account = "Assets:Bank:Checking"
+currency = "EUR"
+fx_currency = "USD"
+
+[importer.options]
+date_column = 0
+narration_column = "Memo"
+amount_column = "Value"
+date_format = "%d/%m/%Y"
+
+[[importer.rules]]
+pattern = "coffee"
+account = "Expenses:Coffee"
+
+[[importer]]
+name = "card"
+type = "ofx"
+match = ["*.ofx", "*.qfx"]
+account = "Liabilities:Card"
+"#;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.csv", "bank.csv"));
+        assert!(glob_match("*.CSV", "bank.csv"));
+        assert!(glob_match("checking*.csv", "checking-2024-01.csv"));
+        assert!(glob_match("stmt-??.ofx", "stmt-01.ofx"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("*.csv", "bank.ofx"));
+        assert!(!glob_match("stmt-??.ofx", "stmt-001.ofx"));
+    }
+
+    #[test]
+    fn test_parse_registry_config() {
+        let config = RegistryConfig::from_toml(CONFIG).unwrap();
+        assert_eq!(config.importers.len(), 2);
+
+        let checking = &config.importers[0];
+        assert_eq!(checking.kind, ImporterKind::Csv);
+        assert_eq!(checking.currency.as_deref(), Some("EUR"));
+        assert!(matches!(
+            checking.options.date_column,
+            Some(ColumnRef::Index(0))
+        ));
+        assert_eq!(checking.rules.len(), 1);
+        let importer = ConfiguredImporter::new(checking.clone());
+        assert_eq!(importer.fx_currency(), Some("USD"));
+
+        assert_eq!(config.importers[1].kind, ImporterKind::Ofx);
+        assert_eq!(config.importers[1].fx_currency, None);
+    }
+
+    #[test]
+    fn test_locale_options() {
+        let content = r#"
+[[importer]]
+name = "giro"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:Bank:Giro"
+
+[importer.options]
+delimiter = ";"
+decimal_comma = true
+encoding = "windows-1252"
+id_column = 4
+"#;
+        let config = RegistryConfig::from_toml(content).unwrap();
+        let importer = ConfiguredImporter::new(config.importers[0].clone());
+        let ImporterType::Csv(csv) = importer.csv_config().importer_type;
+        assert_eq!(csv.delimiter, ';');
+        assert!(csv.decimal_comma);
+        assert_eq!(csv.encoding.as_deref(), Some("windows-1252"));
+        assert!(matches!(csv.id_column, Some(ColumnSpec::Index(4))));
+    }
+
+    #[test]
+    fn test_split_options() {
+        let content = r#"
+[[importer]]
+name = "budget"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:Bank:Checking"
+
+[importer.options]
+split_column = "Categories"
+split_account_prefix = "Expenses:Budget"
+
+[[importer.options.category_columns]]
+column = "Household"
+account = "Expenses:Household"
+
+[[importer.options.category_columns]]
+column = 5
+account = "Expenses:Bank:Fees"
+"#;
+        let config = RegistryConfig::from_toml(content).unwrap();
+        let importer = ConfiguredImporter::new(config.importers[0].clone());
+        let ImporterType::Csv(csv) = importer.csv_config().importer_type;
+        assert!(matches!(csv.split_column, Some(ColumnSpec::Name(ref s)) if s == "Categories"));
+        assert_eq!(csv.split_account_prefix, "Expenses:Budget");
+        assert_eq!(csv.category_columns.len(), 2);
+        assert!(matches!(
+            csv.category_columns[1].column,
+            ColumnSpec::Index(5)
+        ));
+        assert_eq!(csv.category_columns[1].account, "Expenses:Bank:Fees");
+    }
+
+    #[test]
+    fn test_duplicate_names_rejected() {
+        let content = r#"
+[[importer]]
+name = "a"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:A"
+
+[[importer]]
+name = "a"
+type = "ofx"
+match = ["*.ofx"]
+account = "Assets:B"
+"#;
+        let err = RegistryConfig::from_toml(content).unwrap_err();
+        assert!(err.to_string().contains("duplicate importer name"));
+    }
+
+    #[test]
+    fn test_unknown_field_rejected() {
+        let content = r#"
+[[importer]]
+name = "a"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:A"
+acount = "typo"
+"#;
+        assert!(RegistryConfig::from_toml(content).is_err());
+    }
+
+    #[test]
+    fn test_unknown_post_process_plugin_rejected() {
+        let content = r#"
+[[importer]]
+name = "a"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:A"
+
+[[importer.post_process]]
+plugin = "no_such_plugin"
+"#;
+        let err = RegistryConfig::from_toml(content).unwrap_err();
+        assert!(err.to_string().contains("unknown plugin 'no_such_plugin'"));
+    }
+
+    #[test]
+    fn test_wasm_plugin_path_relative_to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("importers.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[importer]]
+name = "a"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:A"
+
+[[importer.post_process]]
+plugin = "plugins/payees.wasm"
+config = "strict"
+"#,
+        )
+        .unwrap();
+
+        let config = RegistryConfig::from_file(&path).unwrap();
+        let step = &config.importers[0].post_process[0];
+        assert_eq!(
+            Path::new(&step.plugin),
+            dir.path().join("plugins/payees.wasm")
+        );
+        assert_eq!(step.config.as_deref(), Some("strict"));
+    }
+
+    #[test]
+    fn test_configured_importer_post_process() {
+        let content = r#"
+[[importer]]
+name = "checking"
+type = "csv"
+match = ["*.csv"]
+account = "Assets:Bank:Checking"
+currency = "USD"
+
+[importer.options]
+narration_column = "Memo"
+amount_column = "Value"
+date_format = "%Y-%m-%d"
+
+[[importer.rules]]
+pattern = "bakery"
+account = "Expenses:Food:Bakery"
+
+[[importer.post_process]]
+plugin = "auto_tag"
+"#;
+        let config = RegistryConfig::from_toml(content).unwrap();
+        let importer = ConfiguredImporter::new(config.importers[0].clone());
+
+        let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(file, "Date,Memo,Value").unwrap();
+        writeln!(file, "2024-01-15,Corner Bakery,-6.00").unwrap();
+        writeln!(file, "2024-01-16,Phone Bill,-30.00").unwrap();
+
+        let result = importer.extract(file.path()).unwrap();
+        assert_eq!(result.directives.len(), 2);
+
+        let Directive::Transaction(bakery) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert!(bakery.tags.contains("food"));
+        assert_eq!(bakery.postings[1].account.as_ref(), "Expenses:Food:Bakery");
+
+        let Directive::Transaction(phone) = &result.directives[1] else {
+            panic!("expected transaction");
+        };
+        assert!(phone.tags.is_empty());
+    }
+
+    #[test]
+    fn test_configured_importer_extract_with_rules() {
+        let config = RegistryConfig::from_toml(CONFIG).unwrap();
+        let importer = ConfiguredImporter::new(config.importers[0].clone());
+
+        let mut file = tempfile::Builder::new()
+            .prefix("checking")
+            .suffix(".csv")
+            .tempfile()
+            .unwrap();
+        writeln!(file, "Date,Memo,Value").unwrap();
+        writeln!(file, "15/01/2024,Morning Coffee,-4.50").unwrap();
+        writeln!(file, "16/01/2024,Groceries,-20.00").unwrap();
+
+        assert!(importer.identify(file.path()));
+
+        let result = importer.extract(file.path()).unwrap();
+        assert_eq!(result.directives.len(), 2);
+
+        let Directive::Transaction(coffee) = &result.directives[0] else {
+            panic!("expected transaction");
+        };
+        assert_eq!(coffee.postings[0].account.as_ref(), "Assets:Bank:Checking");
+        assert_eq!(coffee.postings[1].account.as_ref(), "Expenses:Coffee");
+        assert_eq!(
+            coffee.postings[0].amount().unwrap().currency.as_ref(),
+            "EUR"
+        );
+
+        let Directive::Transaction(groceries) = &result.directives[1] else {
+            panic!("expected transaction");
+        };
+        assert_ne!(groceries.postings[1].account.as_ref(), "Expenses:Coffee");
+    }
+}
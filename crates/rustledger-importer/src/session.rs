@@ -0,0 +1,349 @@
+//! Previewing an import before writing it to a ledger.
+//!
+//! An [`ImportSession`] compares extracted directives against an existing
+//! ledger and sorts them into [`ImportChange`]s: transactions that are new,
+//! ones that are already in the ledger, balance assertions from the
+//! statement, and `open` directives for accounts the ledger does not have
+//! yet. A front end shows the [preview](ImportSession::preview), lets the
+//! user accept or reject each change, and then
+//! [commits](ImportSession::commit) the accepted ones to a file:
+//!
+//! ```rust,no_run
+//! use rustledger_importer::{ImportChange, ImportSession, ImporterConfig};
+//! use std::path::Path;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! # let config = ImporterConfig::csv().account("Assets:Bank").build();
+//! # let ledger = Vec::new();
+//! let result = config.extract(Path::new("bank.csv"))?;
+//! let mut session = ImportSession::new(&ledger, result);
+//! for (index, change) in session.preview().iter().enumerate() {
+//!     if let ImportChange::OpenAccount(open) = change {
+//!         println!("{index}: would open {}", open.account);
+//!     }
+//! }
+//! session.set_accepted(0, false);
+//! session.commit(Path::new("ledger.beancount"))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Categorizing and pricing are left to the caller, who applies them to the
+//! [`ImportResult`] before starting the session.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rustledger_core::{
+    Balance, Directive, FormatConfig, InternedStr, Open, Transaction, format_directive,
+};
+
+use crate::{Deduplicator, ImportResult};
+
+/// A change an import would make to the ledger.
+#[derive(Debug, Clone)]
+pub enum ImportChange {
+    /// A transaction not yet in the ledger.
+    NewTransaction(Transaction),
+    /// An extracted directive the ledger already has.
+    Duplicate(Directive),
+    /// A balance assertion taken from the statement.
+    BalanceAssertion(Balance),
+    /// An account used by the import that the ledger never opens, dated at
+    /// its first use.
+    OpenAccount(Open),
+    /// Any other extracted directive, such as a price.
+    Other(Directive),
+}
+
+impl ImportChange {
+    /// The directive this change would write.
+    pub fn to_directive(&self) -> Directive {
+        match self {
+            Self::NewTransaction(txn) => Directive::Transaction(txn.clone()),
+            Self::BalanceAssertion(bal) => Directive::Balance(bal.clone()),
+            Self::OpenAccount(open) => Directive::Open(open.clone()),
+            Self::Duplicate(directive) | Self::Other(directive) => directive.clone(),
+        }
+    }
+
+    /// Whether the change is accepted until the user decides otherwise.
+    ///
+    /// Everything but duplicates is.
+    pub const fn accepted_by_default(&self) -> bool {
+        !matches!(self, Self::Duplicate(_))
+    }
+}
+
+/// An import awaiting review.
+#[derive(Debug, Clone)]
+pub struct ImportSession {
+    changes: Vec<ImportChange>,
+    accepted: Vec<bool>,
+    warnings: Vec<String>,
+}
+
+impl ImportSession {
+    /// Compare the result of an import against the directives of an
+    /// existing ledger.
+    ///
+    /// Account openings come first, followed by the extracted directives in
+    /// statement order.
+    pub fn new(ledger: &[Directive], result: ImportResult) -> Self {
+        let duplicates = Deduplicator::from_directives(ledger).duplicates(&result.directives);
+        let assertions: HashSet<_> = ledger
+            .iter()
+            .filter_map(|d| match d {
+                Directive::Balance(bal) => Some(balance_key(bal)),
+                _ => None,
+            })
+            .collect();
+
+        let mut extracted = Vec::with_capacity(result.directives.len());
+        for (directive, duplicate) in result.directives.into_iter().zip(duplicates) {
+            extracted.push(if duplicate {
+                ImportChange::Duplicate(directive)
+            } else {
+                match directive {
+                    Directive::Balance(bal) if assertions.contains(&balance_key(&bal)) => {
+                        ImportChange::Duplicate(Directive::Balance(bal))
+                    }
+                    Directive::Transaction(txn) => ImportChange::NewTransaction(txn),
+                    Directive::Balance(bal) => ImportChange::BalanceAssertion(bal),
+                    other => ImportChange::Other(other),
+                }
+            });
+        }
+
+        let mut changes = account_openings(ledger, &extracted);
+        changes.extend(extracted);
+        let accepted = changes
+            .iter()
+            .map(ImportChange::accepted_by_default)
+            .collect();
+
+        Self {
+            changes,
+            accepted,
+            warnings: result.warnings,
+        }
+    }
+
+    /// The changes the import would make.
+    pub fn preview(&self) -> &[ImportChange] {
+        &self.changes
+    }
+
+    /// Warnings the importer reported.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Whether the change at `index` in the preview will be written.
+    pub fn is_accepted(&self, index: usize) -> bool {
+        self.accepted.get(index).copied().unwrap_or(false)
+    }
+
+    /// Accept or reject the change at `index` in the preview.
+    ///
+    /// Indices past the end of the preview are ignored.
+    pub fn set_accepted(&mut self, index: usize, accepted: bool) {
+        if let Some(slot) = self.accepted.get_mut(index) {
+            *slot = accepted;
+        }
+    }
+
+    /// The directives of the accepted changes, in preview order.
+    pub fn accepted(&self) -> Vec<Directive> {
+        self.changes
+            .iter()
+            .zip(&self.accepted)
+            .filter(|(_, accepted)| **accepted)
+            .map(|(change, _)| change.to_directive())
+            .collect()
+    }
+
+    /// Append the accepted changes to `target_file`, creating it if needed.
+    ///
+    /// Returns the number of directives written.
+    pub fn commit(&self, target_file: &Path) -> Result<usize> {
+        let directives = self.accepted();
+        if directives.is_empty() {
+            return Ok(0);
+        }
+
+        let existing = std::fs::read(target_file).unwrap_or_default();
+        let mut text = String::new();
+        if existing.last().is_some_and(|&byte| byte != b'\n') {
+            text.push('\n');
+        }
+        let config = FormatConfig::default();
+        for directive in &directives {
+            if !existing.is_empty() || !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(format_directive(directive, &config).trim_end());
+            text.push('\n');
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(target_file)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .with_context(|| format!("failed to write to {}", target_file.display()))?;
+
+        Ok(directives.len())
+    }
+}
+
+/// What identifies a balance assertion: its date, account and currency.
+fn balance_key(bal: &Balance) -> (NaiveDate, InternedStr, InternedStr) {
+    (bal.date, bal.account.clone(), bal.amount.currency.clone())
+}
+
+/// `open` directives for the accounts new changes use but neither the
+/// ledger nor the import opens, dated at their first use.
+fn account_openings(ledger: &[Directive], changes: &[ImportChange]) -> Vec<ImportChange> {
+    let opened: HashSet<&InternedStr> = ledger
+        .iter()
+        .chain(changes.iter().filter_map(|change| match change {
+            ImportChange::Other(directive) => Some(directive),
+            _ => None,
+        }))
+        .filter_map(|d| match d {
+            Directive::Open(open) => Some(&open.account),
+            _ => None,
+        })
+        .collect();
+
+    let mut first_use: BTreeMap<&InternedStr, NaiveDate> = BTreeMap::new();
+    for change in changes {
+        let (date, accounts): (_, Vec<_>) = match change {
+            ImportChange::NewTransaction(txn) => {
+                (txn.date, txn.postings.iter().map(|p| &p.account).collect())
+            }
+            ImportChange::BalanceAssertion(bal) => (bal.date, vec![&bal.account]),
+            _ => continue,
+        };
+        for account in accounts {
+            if !opened.contains(account) {
+                first_use
+                    .entry(account)
+                    .and_modify(|first| *first = (*first).min(date))
+                    .or_insert(date);
+            }
+        }
+    }
+
+    first_use
+        .into_iter()
+        .map(|(account, date)| ImportChange::OpenAccount(Open::new(date, account.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup::set_import_id;
+    use rust_decimal::Decimal;
+    use rustledger_core::{Amount, Posting};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn purchase(day: u32, id: &str, expense: &str) -> Directive {
+        let mut txn = Transaction::new(date(day), "Purchase")
+            .with_posting(Posting::new(
+                "Assets:Bank",
+                Amount::new(Decimal::from(-10), "USD"),
+            ))
+            .with_posting(Posting::new(expense, Amount::new(Decimal::from(10), "USD")));
+        set_import_id(&mut txn, id);
+        Directive::Transaction(txn)
+    }
+
+    fn ledger() -> Vec<Directive> {
+        vec![
+            Directive::Open(Open::new(date(1), "Assets:Bank")),
+            Directive::Open(Open::new(date(1), "Expenses:Food")),
+            purchase(2, "a", "Expenses:Food"),
+        ]
+    }
+
+    #[test]
+    fn test_preview_classifies_changes() {
+        let result = ImportResult::new(vec![
+            purchase(2, "a", "Expenses:Food"),
+            purchase(5, "b", "Expenses:Books"),
+            purchase(3, "c", "Expenses:Books"),
+            Directive::Balance(Balance::new(
+                date(6),
+                "Assets:Bank",
+                Amount::new(Decimal::from(80), "USD"),
+            )),
+        ])
+        .with_warning("skipped a row");
+        let session = ImportSession::new(&ledger(), result);
+
+        let preview = session.preview();
+        assert_eq!(preview.len(), 5);
+        let ImportChange::OpenAccount(open) = &preview[0] else {
+            panic!("expected an account opening, got {:?}", preview[0]);
+        };
+        assert_eq!(open.account.as_str(), "Expenses:Books");
+        assert_eq!(open.date, date(3));
+        assert!(matches!(preview[1], ImportChange::Duplicate(_)));
+        assert!(matches!(preview[2], ImportChange::NewTransaction(_)));
+        assert!(matches!(preview[3], ImportChange::NewTransaction(_)));
+        assert!(matches!(preview[4], ImportChange::BalanceAssertion(_)));
+
+        assert!(!session.is_accepted(1));
+        assert_eq!(session.accepted().len(), 4);
+        assert_eq!(session.warnings(), ["skipped a row"]);
+    }
+
+    #[test]
+    fn test_known_balance_assertion_is_duplicate() {
+        let bal = Balance::new(
+            date(6),
+            "Assets:Bank",
+            Amount::new(Decimal::from(80), "USD"),
+        );
+        let mut ledger = ledger();
+        ledger.push(Directive::Balance(bal.clone()));
+
+        let session = ImportSession::new(&ledger, ImportResult::new(vec![Directive::Balance(bal)]));
+        assert!(matches!(session.preview(), [ImportChange::Duplicate(_)]));
+        assert!(session.accepted().is_empty());
+    }
+
+    #[test]
+    fn test_commit_appends_accepted_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("ledger.beancount");
+        std::fs::write(&target, "2024-01-01 open Assets:Bank").unwrap();
+
+        let result = ImportResult::new(vec![
+            purchase(5, "b", "Expenses:Books"),
+            purchase(6, "c", "Expenses:Food"),
+        ]);
+        let mut session = ImportSession::new(&ledger(), result);
+        session.set_accepted(2, false);
+        session.set_accepted(99, true);
+
+        assert_eq!(session.commit(&target).unwrap(), 2);
+        let text = std::fs::read_to_string(&target).unwrap();
+        assert!(
+            text.starts_with("2024-01-01 open Assets:Bank\n\n2024-01-05 open Expenses:Books\n\n")
+        );
+        assert!(text.contains("2024-01-05 * \"Purchase\""));
+        assert!(!text.contains("2024-01-06"));
+        assert!(text.ends_with('\n'));
+    }
+}
@@ -18,8 +18,8 @@
 
 use crate::Options;
 use rust_decimal::Decimal;
-use rustledger_core::Directive;
 use rustledger_core::intern::StringInterner;
+use rustledger_core::{Directive, NaiveDate};
 use rustledger_parser::Spanned;
 use sha2::{Digest, Sha256};
 use std::fs;
@@ -70,6 +70,12 @@ pub struct CachedOptions {
     pub allow_pipe_separator: bool,
     pub long_string_maxlines: u32,
     pub documents: Vec<String>,
+    /// Stored as a `YYYY-MM-DD` string
+    pub first_date: Option<String>,
+    /// Stored as the option value (`default` or `raw`)
+    pub plugin_processing_mode: String,
+    pub disabled_plugins: Vec<String>,
+    pub plugin_order: Vec<String>,
     pub custom: Vec<(String, String)>,
 }
 
@@ -106,6 +112,10 @@ impl From<&Options> for CachedOptions {
             allow_pipe_separator: opts.allow_pipe_separator,
             long_string_maxlines: opts.long_string_maxlines,
             documents: opts.documents.clone(),
+            first_date: opts.first_date.map(|date| date.to_string()),
+            plugin_processing_mode: opts.plugin_processing_mode.as_str().to_string(),
+            disabled_plugins: opts.disabled_plugins.clone(),
+            plugin_order: opts.plugin_order.clone(),
             custom: opts
                 .custom
                 .iter()
@@ -150,6 +160,12 @@ impl From<CachedOptions> for Options {
         opts.allow_pipe_separator = cached.allow_pipe_separator;
         opts.long_string_maxlines = cached.long_string_maxlines;
         opts.documents = cached.documents;
+        opts.first_date = cached
+            .first_date
+            .and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok());
+        opts.plugin_processing_mode = cached.plugin_processing_mode.parse().unwrap_or_default();
+        opts.disabled_plugins = cached.disabled_plugins;
+        opts.plugin_order = cached.plugin_order;
         opts.custom = cached.custom.into_iter().collect();
         opts
     }
@@ -181,7 +197,12 @@ const CACHE_MAGIC: &[u8; 8] = b"RLEDGER\0";
 /// Cache version - increment when format changes.
 /// v1: Initial release with string-based Decimal/NaiveDate
 /// v2: Binary Decimal (16 bytes) and `NaiveDate` (i32 days)
-const CACHE_VERSION: u32 = 2;
+/// v3: Posting comments
+/// v4: `first_date` option
+/// v5: `plugin_processing_mode` and `disable_plugin` options
+/// v6: `plugin_order` option
+/// v7: Sorted, deduplicated tags and links
+const CACHE_VERSION: u32 = 7;
 
 /// Cache header stored at the start of cache files.
 #[derive(Debug, Clone)]
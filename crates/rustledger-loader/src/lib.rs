@@ -10,6 +10,7 @@
 //! - Options collection and parsing
 //! - Plugin directive collection
 //! - Source map for error reporting
+//! - Per-file statistics (directive counts, parse time, errors)
 //! - Push/pop tag and metadata handling
 //! - Automatic GPG decryption for encrypted files (`.gpg`, `.asc`)
 //!
@@ -32,14 +33,16 @@
 pub mod cache;
 mod options;
 mod source_map;
+mod stats;
 
 #[cfg(feature = "cache")]
 pub use cache::{
     CacheEntry, CachedOptions, CachedPlugin, invalidate_cache, load_cache_entry,
     reintern_directives, save_cache_entry,
 };
-pub use options::Options;
+pub use options::{Options, PluginProcessingMode};
 pub use source_map::{SourceFile, SourceMap};
+pub use stats::{FileStats, LoadSummary};
 
 use rustledger_core::Directive;
 use rustledger_parser::{ParseError, Span, Spanned};
@@ -47,6 +50,7 @@ use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Instant;
 use thiserror::Error;
 
 /// Errors that can occur during loading.
@@ -112,6 +116,31 @@ pub struct LoadResult {
     pub source_map: SourceMap,
     /// All errors encountered during loading.
     pub errors: Vec<LoadError>,
+    /// Statistics for each loaded file, in the order files were read.
+    pub file_stats: Vec<FileStats>,
+}
+
+impl LoadResult {
+    /// Intra-day sequence number of each directive (parallel to `directives`).
+    ///
+    /// Numbers count directives sharing a date in load order, so they are
+    /// stable across runs and break ties between entries with the same
+    /// `time` metadata.
+    #[must_use]
+    pub fn sequence_numbers(&self) -> Vec<u32> {
+        rustledger_core::intraday_sequence(self.directives.iter().map(|d| &d.value))
+    }
+
+    /// Summarize the load for reporting.
+    #[must_use]
+    pub fn summary(&self) -> LoadSummary {
+        let load_errors = self
+            .errors
+            .iter()
+            .filter(|e| !matches!(e, LoadError::ParseErrors { .. }))
+            .count();
+        LoadSummary::new(&self.file_stats, load_errors)
+    }
 }
 
 /// A plugin directive.
@@ -187,6 +216,8 @@ pub struct Loader {
     root_dir: Option<PathBuf>,
     /// Whether to enforce path traversal protection.
     enforce_path_security: bool,
+    /// Statistics for files read by the current load.
+    file_stats: Vec<FileStats>,
 }
 
 impl Loader {
@@ -248,6 +279,7 @@ impl Loader {
         let mut plugins = Vec::new();
         let mut source_map = SourceMap::new();
         let mut errors = Vec::new();
+        self.file_stats.clear();
 
         // Get canonical path
         let canonical = path.canonicalize().map_err(|e| LoadError::Io {
@@ -277,6 +309,48 @@ impl Loader {
             plugins,
             source_map,
             errors,
+            file_stats: std::mem::take(&mut self.file_stats),
+        })
+    }
+
+    /// Load beancount source that is not read from disk.
+    ///
+    /// `path` names the source in the result and anchors relative includes,
+    /// which are still read from disk. Used for generated ledgers such as the
+    /// web UI's demo mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LoadError::IncludeCycle`] if an included file includes
+    /// `path`. Other errors are collected in [`LoadResult::errors`].
+    pub fn load_source(&mut self, path: &Path, source: &str) -> Result<LoadResult, LoadError> {
+        let mut directives = Vec::new();
+        let mut directive_sources = Vec::new();
+        let mut options = Options::default();
+        let mut plugins = Vec::new();
+        let mut source_map = SourceMap::new();
+        let mut errors = Vec::new();
+        self.file_stats.clear();
+
+        self.load_parsed(
+            path,
+            source.into(),
+            &mut directives,
+            &mut directive_sources,
+            &mut options,
+            &mut plugins,
+            &mut source_map,
+            &mut errors,
+        )?;
+
+        Ok(LoadResult {
+            directives,
+            directive_sources,
+            options,
+            plugins,
+            source_map,
+            errors,
+            file_stats: std::mem::take(&mut self.file_stats),
         })
     }
 
@@ -319,15 +393,49 @@ impl Loader {
                 .into()
         };
 
-        // Add to source map (Arc::clone is cheap - just increments refcount)
-        let file_id = source_map.add_file(path.to_path_buf(), std::sync::Arc::clone(&source));
+        self.load_parsed(
+            path,
+            source,
+            directives,
+            directive_sources,
+            options,
+            plugins,
+            source_map,
+            errors,
+        )
+    }
 
+    /// Parse `source`, read from `path`, and load the files it includes.
+    #[allow(clippy::too_many_arguments)]
+    fn load_parsed(
+        &mut self,
+        path: &Path,
+        source: std::sync::Arc<str>,
+        directives: &mut Vec<Spanned<Directive>>,
+        directive_sources: &mut Vec<PathBuf>,
+        options: &mut Options,
+        plugins: &mut Vec<Plugin>,
+        source_map: &mut SourceMap,
+        errors: &mut Vec<LoadError>,
+    ) -> Result<(), LoadError> {
         // Mark as loading
         self.include_stack.push(path.to_path_buf());
         self.loaded_files.insert(path.to_path_buf());
 
         // Parse (borrows from Arc, no allocation)
+        let start = Instant::now();
         let result = rustledger_parser::parse(&source);
+        self.file_stats.push(FileStats::new(
+            path.to_path_buf(),
+            source.len(),
+            &result.directives,
+            result.includes.len(),
+            result.errors.len(),
+            start.elapsed(),
+        ));
+
+        // Add to source map
+        let file_id = source_map.add_file(path.to_path_buf(), source);
 
         // Collect parse errors
         if !result.errors.is_empty() {
@@ -1,6 +1,7 @@
 //! Beancount options parsing and storage.
 
 use rust_decimal::Decimal;
+use rustledger_core::NaiveDate;
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
@@ -34,10 +35,58 @@ const KNOWN_OPTIONS: &[&str] = &[
     "documents",
     "insert_pythonpath",
     "plugin_processing_mode",
+    "disable_plugin",
+    "plugin_order",
+    "first_date",
 ];
 
 /// Options that can be specified multiple times.
-const REPEATABLE_OPTIONS: &[&str] = &["operating_currency", "insert_pythonpath", "documents"];
+const REPEATABLE_OPTIONS: &[&str] = &[
+    "operating_currency",
+    "insert_pythonpath",
+    "documents",
+    "disable_plugin",
+    "plugin_order",
+];
+
+/// Strip the `beancount.plugins.` prefix from a plugin name.
+fn short_plugin_name(name: &str) -> &str {
+    name.strip_prefix("beancount.plugins.").unwrap_or(name)
+}
+
+/// Which plugins run on a ledger (`option "plugin_processing_mode"`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PluginProcessingMode {
+    /// Run the implicit plugins (such as `auto_accounts` with `--auto`)
+    /// before the requested ones.
+    #[default]
+    Default,
+    /// Run only the plugins that were asked for explicitly.
+    Raw,
+}
+
+impl PluginProcessingMode {
+    /// The option value for this mode.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Raw => "raw",
+        }
+    }
+}
+
+impl FromStr for PluginProcessingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("unknown plugin processing mode: {s}")),
+        }
+    }
+}
 
 /// Option validation warning.
 #[derive(Debug, Clone)]
@@ -81,7 +130,9 @@ pub struct Options {
     /// Name prefix for Expenses accounts.
     pub name_expenses: String,
 
-    /// Account for rounding errors.
+    /// Account that rounding residuals within tolerance are booked to.
+    ///
+    /// Residuals are left in place when unset.
     pub account_rounding: Option<String>,
 
     /// Account for previous balances (opening balances).
@@ -135,6 +186,18 @@ pub struct Options {
     /// Directories to scan for document files.
     pub documents: Vec<String>,
 
+    /// Date the ledger starts; earlier entries are reported by validation.
+    pub first_date: Option<NaiveDate>,
+
+    /// Whether implicit plugins run.
+    pub plugin_processing_mode: PluginProcessingMode,
+
+    /// Plugins that must not run, even when requested.
+    pub disabled_plugins: Vec<String>,
+
+    /// Plugins to run first, in this order, ahead of the declared order.
+    pub plugin_order: Vec<String>,
+
     /// Any other custom options.
     pub custom: HashMap<String, String>,
 
@@ -177,6 +240,10 @@ impl Options {
             allow_pipe_separator: false,
             long_string_maxlines: 64,
             documents: Vec::new(),
+            first_date: None,
+            plugin_processing_mode: PluginProcessingMode::Default,
+            disabled_plugins: Vec::new(),
+            plugin_order: Vec::new(),
             custom: HashMap::new(),
             set_options: HashSet::new(),
             warnings: Vec::new(),
@@ -221,7 +288,20 @@ impl Options {
             "name_equity" => self.name_equity = value.to_string(),
             "name_income" => self.name_income = value.to_string(),
             "name_expenses" => self.name_expenses = value.to_string(),
-            "account_rounding" => self.account_rounding = Some(value.to_string()),
+            "account_rounding" => {
+                if value.split(':').count() < 2 || value.split(':').any(str::is_empty) {
+                    self.warnings.push(OptionWarning {
+                        code: "E7002",
+                        message: format!(
+                            "Invalid value \"{value}\" for option \"{key}\": expected an account name"
+                        ),
+                        option: key.to_string(),
+                        value: value.to_string(),
+                    });
+                } else {
+                    self.account_rounding = Some(value.to_string());
+                }
+            }
             "account_current_conversions" => {
                 self.account_current_conversions = Some(value.to_string());
             }
@@ -350,6 +430,36 @@ impl Options {
                 }
             }
             "documents" => self.documents.push(value.to_string()),
+            "first_date" => {
+                if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                    self.first_date = Some(date);
+                } else {
+                    self.warnings.push(OptionWarning {
+                        code: "E7002",
+                        message: format!(
+                            "Invalid value \"{value}\" for option \"{key}\": expected a YYYY-MM-DD date"
+                        ),
+                        option: key.to_string(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+            "plugin_processing_mode" => {
+                if let Ok(mode) = value.parse() {
+                    self.plugin_processing_mode = mode;
+                } else {
+                    self.warnings.push(OptionWarning {
+                        code: "E7002",
+                        message: format!(
+                            "Invalid value \"{value}\" for option \"{key}\": expected \"default\" or \"raw\""
+                        ),
+                        option: key.to_string(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+            "disable_plugin" => self.disabled_plugins.push(value.to_string()),
+            "plugin_order" => self.plugin_order.push(value.to_string()),
             _ => {
                 // Unknown options go to custom map
                 self.custom.insert(key.to_string(), value.to_string());
@@ -363,6 +473,186 @@ impl Options {
         self.custom.get(key).map(String::as_str)
     }
 
+    /// Whether the plugin `name` was turned off with `disable_plugin`.
+    ///
+    /// The `beancount.plugins.` prefix is ignored on both sides, so
+    /// `auto_tag` and `beancount.plugins.auto_tag` name the same plugin.
+    #[must_use]
+    pub fn is_plugin_disabled(&self, name: &str) -> bool {
+        let name = short_plugin_name(name);
+        self.disabled_plugins
+            .iter()
+            .any(|disabled| short_plugin_name(disabled) == name)
+    }
+
+    /// Reorder plugin `names` by `option "plugin_order"`.
+    ///
+    /// Listed plugins come first, in the listed order; the rest keep their
+    /// relative order after them. Names match as in
+    /// [`is_plugin_disabled`](Self::is_plugin_disabled).
+    pub fn order_plugins<T: AsRef<str>>(&self, names: &mut [T]) {
+        names.sort_by_key(|name| {
+            let name = short_plugin_name(name.as_ref());
+            self.plugin_order
+                .iter()
+                .position(|ordered| short_plugin_name(ordered) == name)
+                .unwrap_or(self.plugin_order.len())
+        });
+    }
+
+    /// The options that differ from their defaults, as `option` directive
+    /// key/value pairs.
+    ///
+    /// Setting the returned pairs on [`Options::default`], which the loader
+    /// starts from, reproduces these options. Known options come in [`KNOWN_OPTIONS`] order, custom ones
+    /// after them sorted by key; the auto-set `filename` is left out.
+    #[must_use]
+    pub fn entries(&self) -> Vec<(String, String)> {
+        fn flag(value: bool) -> String {
+            if value { "TRUE" } else { "FALSE" }.to_string()
+        }
+
+        let defaults = Self::default();
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut push = |key: &str, value: String| entries.push((key.to_string(), value));
+
+        if let Some(title) = &self.title {
+            push("title", title.clone());
+        }
+        for currency in &self.operating_currency {
+            push("operating_currency", currency.clone());
+        }
+        for (key, value, default) in [
+            ("name_assets", &self.name_assets, &defaults.name_assets),
+            (
+                "name_liabilities",
+                &self.name_liabilities,
+                &defaults.name_liabilities,
+            ),
+            ("name_equity", &self.name_equity, &defaults.name_equity),
+            ("name_income", &self.name_income, &defaults.name_income),
+            (
+                "name_expenses",
+                &self.name_expenses,
+                &defaults.name_expenses,
+            ),
+            (
+                "account_previous_balances",
+                &self.account_previous_balances,
+                &defaults.account_previous_balances,
+            ),
+            (
+                "account_previous_earnings",
+                &self.account_previous_earnings,
+                &defaults.account_previous_earnings,
+            ),
+            (
+                "account_previous_conversions",
+                &self.account_previous_conversions,
+                &defaults.account_previous_conversions,
+            ),
+            (
+                "account_current_earnings",
+                &self.account_current_earnings,
+                &defaults.account_current_earnings,
+            ),
+        ] {
+            if value != default {
+                push(key, value.clone());
+            }
+        }
+        for (key, value) in [
+            ("account_rounding", &self.account_rounding),
+            (
+                "account_current_conversions",
+                &self.account_current_conversions,
+            ),
+            ("account_unrealized_gains", &self.account_unrealized_gains),
+            ("conversion_currency", &self.conversion_currency),
+        ] {
+            if let Some(value) = value {
+                push(key, value.clone());
+            }
+        }
+
+        let mut tolerances: Vec<_> = self.inferred_tolerance_default.iter().collect();
+        tolerances.sort();
+        for (currency, tolerance) in tolerances {
+            push(
+                "inferred_tolerance_default",
+                format!("{currency}:{tolerance}"),
+            );
+        }
+        if self.inferred_tolerance_multiplier != defaults.inferred_tolerance_multiplier {
+            push(
+                "inferred_tolerance_multiplier",
+                self.inferred_tolerance_multiplier.to_string(),
+            );
+        }
+        for (key, value, default) in [
+            (
+                "infer_tolerance_from_cost",
+                self.infer_tolerance_from_cost,
+                defaults.infer_tolerance_from_cost,
+            ),
+            (
+                "use_legacy_fixed_tolerances",
+                self.use_legacy_fixed_tolerances,
+                defaults.use_legacy_fixed_tolerances,
+            ),
+            (
+                "experiment_explicit_tolerances",
+                self.experiment_explicit_tolerances,
+                defaults.experiment_explicit_tolerances,
+            ),
+            ("render_commas", self.render_commas, defaults.render_commas),
+            (
+                "allow_pipe_separator",
+                self.allow_pipe_separator,
+                defaults.allow_pipe_separator,
+            ),
+        ] {
+            if value != default {
+                push(key, flag(value));
+            }
+        }
+
+        if self.booking_method != defaults.booking_method {
+            push("booking_method", self.booking_method.clone());
+        }
+        if self.long_string_maxlines != defaults.long_string_maxlines {
+            push(
+                "long_string_maxlines",
+                self.long_string_maxlines.to_string(),
+            );
+        }
+        for dir in &self.documents {
+            push("documents", dir.clone());
+        }
+        if let Some(date) = self.first_date {
+            push("first_date", date.format("%Y-%m-%d").to_string());
+        }
+        if self.plugin_processing_mode != defaults.plugin_processing_mode {
+            push(
+                "plugin_processing_mode",
+                self.plugin_processing_mode.as_str().to_string(),
+            );
+        }
+        for name in &self.disabled_plugins {
+            push("disable_plugin", name.clone());
+        }
+        for name in &self.plugin_order {
+            push("plugin_order", name.clone());
+        }
+
+        let mut custom: Vec<_> = self.custom.iter().collect();
+        custom.sort();
+        for (key, value) in custom {
+            push(key, value.clone());
+        }
+        entries
+    }
+
     /// Get all account type prefixes.
     #[must_use]
     pub fn account_types(&self) -> [&str; 5] {
@@ -466,6 +756,106 @@ mod tests {
         assert!(opts.warnings[0].message.contains("TRUE or FALSE"));
     }
 
+    #[test]
+    fn test_account_rounding() {
+        let mut opts = Options::new();
+        opts.set("account_rounding", "Rounding");
+        assert_eq!(opts.warnings.len(), 1);
+        assert_eq!(opts.warnings[0].code, "E7002");
+        assert_eq!(opts.account_rounding, None);
+
+        let mut opts = Options::new();
+        opts.set("account_rounding", "Equity:Rounding");
+        assert!(opts.warnings.is_empty());
+        assert_eq!(opts.account_rounding.as_deref(), Some("Equity:Rounding"));
+    }
+
+    #[test]
+    fn test_first_date() {
+        let mut opts = Options::new();
+        opts.set("first_date", "2020-01-01");
+        assert!(opts.warnings.is_empty());
+        assert_eq!(opts.first_date, NaiveDate::from_ymd_opt(2020, 1, 1));
+
+        let mut opts = Options::new();
+        opts.set("first_date", "January 2020");
+        assert_eq!(opts.warnings.len(), 1);
+        assert_eq!(opts.warnings[0].code, "E7002");
+        assert_eq!(opts.first_date, None);
+    }
+
+    #[test]
+    fn test_plugin_options() {
+        let mut opts = Options::new();
+        assert_eq!(opts.plugin_processing_mode, PluginProcessingMode::Default);
+        opts.set("plugin_processing_mode", "RAW");
+        opts.set("disable_plugin", "beancount.plugins.auto_tag");
+        opts.set("disable_plugin", "leafonly");
+        assert!(opts.warnings.is_empty());
+        assert_eq!(opts.plugin_processing_mode, PluginProcessingMode::Raw);
+        assert!(opts.is_plugin_disabled("auto_tag"));
+        assert!(opts.is_plugin_disabled("beancount.plugins.leafonly"));
+        assert!(!opts.is_plugin_disabled("auto_accounts"));
+
+        opts.set("plugin_order", "beancount.plugins.implicit_prices");
+        opts.set("plugin_order", "auto_accounts");
+        assert!(opts.warnings.is_empty());
+        let mut names = ["auto_tag", "auto_accounts", "leafonly", "implicit_prices"];
+        opts.order_plugins(&mut names);
+        assert_eq!(
+            names,
+            ["implicit_prices", "auto_accounts", "auto_tag", "leafonly"]
+        );
+
+        let mut opts = Options::new();
+        opts.set("plugin_processing_mode", "fast");
+        assert_eq!(opts.warnings.len(), 1);
+        assert_eq!(opts.warnings[0].code, "E7002");
+        assert_eq!(opts.plugin_processing_mode, PluginProcessingMode::Default);
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let mut opts = Options::default();
+        assert!(opts.entries().is_empty());
+
+        let set = [
+            ("title", "My \"Books\""),
+            ("operating_currency", "USD"),
+            ("operating_currency", "EUR"),
+            ("name_assets", "Actifs"),
+            ("account_rounding", "Equity:Rounding"),
+            ("inferred_tolerance_default", "USD:0.01"),
+            ("inferred_tolerance_default", "*:0.001"),
+            ("infer_tolerance_from_cost", "FALSE"),
+            ("booking_method", "FIFO"),
+            ("documents", "docs"),
+            ("first_date", "2020-01-01"),
+            ("plugin_processing_mode", "raw"),
+            ("disable_plugin", "auto_tag"),
+            ("fava-option", "x"),
+        ];
+        for (key, value) in set {
+            opts.set(key, value);
+        }
+
+        let mut copy = Options::default();
+        for (key, value) in opts.entries() {
+            copy.set(&key, &value);
+        }
+        assert_eq!(copy.entries(), opts.entries());
+        assert_eq!(copy.title, opts.title);
+        assert_eq!(copy.operating_currency, ["USD", "EUR"]);
+        assert_eq!(
+            copy.inferred_tolerance_default,
+            opts.inferred_tolerance_default
+        );
+        assert!(!copy.infer_tolerance_from_cost);
+        assert_eq!(copy.first_date, opts.first_date);
+        assert_eq!(copy.plugin_processing_mode, PluginProcessingMode::Raw);
+        assert_eq!(copy.get("fava-option"), Some("x"));
+    }
+
     #[test]
     fn test_invalid_booking_method() {
         let mut opts = Options::new();
@@ -0,0 +1,100 @@
+//! Per-file load statistics.
+//!
+//! The loader records, for every file it reads, how many directives of each
+//! type the file holds, how long it took to parse and how many parse errors
+//! it has. [`LoadSummary`] aggregates these into a serializable report for
+//! tooling such as `rledger-doctor stats` and the web dashboard.
+
+use rustledger_core::Directive;
+use rustledger_parser::Spanned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Statistics for a single loaded file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileStats {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Size of the (decrypted) source in bytes.
+    pub bytes: usize,
+    /// Number of directives.
+    pub directive_count: usize,
+    /// Directive counts keyed by [`Directive::type_name`].
+    pub directives: BTreeMap<&'static str, usize>,
+    /// Number of `include` directives.
+    pub includes: usize,
+    /// Number of parse errors.
+    pub parse_errors: usize,
+    /// Time spent parsing, in milliseconds.
+    pub parse_ms: f64,
+}
+
+impl FileStats {
+    /// Collect statistics for a parsed file.
+    pub(crate) fn new(
+        path: PathBuf,
+        bytes: usize,
+        directives: &[Spanned<Directive>],
+        includes: usize,
+        parse_errors: usize,
+        parse_duration: Duration,
+    ) -> Self {
+        let mut counts = BTreeMap::new();
+        for directive in directives {
+            *counts.entry(directive.value.type_name()).or_insert(0) += 1;
+        }
+        Self {
+            path,
+            bytes,
+            directive_count: directives.len(),
+            directives: counts,
+            includes,
+            parse_errors,
+            parse_ms: parse_duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Machine-readable summary of a load.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadSummary {
+    /// Number of files loaded.
+    pub file_count: usize,
+    /// Total number of directives.
+    pub directive_count: usize,
+    /// Directive counts across all files, keyed by type name.
+    pub directives: BTreeMap<&'static str, usize>,
+    /// Total number of parse errors.
+    pub parse_errors: usize,
+    /// Number of load errors not tied to a parsed file, such as
+    /// unreadable includes.
+    pub load_errors: usize,
+    /// Total time spent parsing, in milliseconds.
+    pub parse_ms: f64,
+    /// Statistics for each file, in load order.
+    pub files: Vec<FileStats>,
+}
+
+impl LoadSummary {
+    /// Aggregate per-file statistics.
+    #[must_use]
+    pub fn new(files: &[FileStats], load_errors: usize) -> Self {
+        let mut summary = Self {
+            file_count: files.len(),
+            load_errors,
+            files: files.to_vec(),
+            ..Self::default()
+        };
+        for file in files {
+            for (kind, count) in &file.directives {
+                *summary.directives.entry(kind).or_insert(0) += count;
+            }
+            summary.directive_count += file.directive_count;
+            summary.parse_errors += file.parse_errors;
+            summary.parse_ms += file.parse_ms;
+        }
+        summary
+    }
+}
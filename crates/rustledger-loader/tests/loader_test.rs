@@ -75,6 +75,26 @@ fn test_load_with_include() {
     assert!(result.errors.is_empty(), "expected no errors");
 }
 
+#[test]
+fn test_load_source_in_memory() {
+    let source = "option \"title\" \"Generated\"\n\
+                  include \"accounts.beancount\"\n\
+                  2024-01-15 * \"Coffee\"\n  Expenses:Food  4.00 USD\n  Assets:Cash\n";
+    let result = Loader::new()
+        .load_source(&fixtures_path("generated.beancount"), source)
+        .expect("should load source");
+
+    assert_eq!(result.options.title, Some("Generated".to_string()));
+    // 3 open directives from the include, 1 transaction from the source
+    assert_eq!(result.directives.len(), 4);
+    assert_eq!(
+        result.directive_sources.last(),
+        Some(&fixtures_path("generated.beancount"))
+    );
+    assert_eq!(result.file_stats.len(), 2);
+    assert!(result.errors.is_empty(), "expected no errors");
+}
+
 #[test]
 fn test_load_include_cycle_detection() {
     let path = fixtures_path("cycle_a.beancount");
@@ -169,6 +189,55 @@ fn test_load_with_parse_errors() {
     );
 }
 
+#[test]
+fn test_load_file_stats() {
+    let path = fixtures_path("main_with_include.beancount");
+    let result = load(&path).expect("should load file with include");
+
+    // Files are listed in the order they were read
+    assert_eq!(result.file_stats.len(), 2);
+    let main = &result.file_stats[0];
+    assert!(main.path.ends_with("main_with_include.beancount"));
+    assert_eq!(main.includes, 1);
+    assert_eq!(main.directive_count, 1);
+    assert_eq!(main.directives.get("transaction"), Some(&1));
+
+    let accounts = &result.file_stats[1];
+    assert!(accounts.path.ends_with("accounts.beancount"));
+    assert_eq!(accounts.directives.get("open"), Some(&3));
+    assert_eq!(accounts.parse_errors, 0);
+
+    let summary = result.summary();
+    assert_eq!(summary.file_count, 2);
+    assert_eq!(summary.directive_count, result.directives.len());
+    assert_eq!(summary.directives.get("open"), Some(&3));
+    assert_eq!(summary.parse_errors, 0);
+    assert_eq!(summary.load_errors, 0);
+}
+
+#[test]
+fn test_load_file_stats_with_parse_errors() {
+    let path = fixtures_path("parse_error.beancount");
+    let result = load(&path).expect("should load file even with parse errors");
+
+    let summary = result.summary();
+    assert!(summary.parse_errors >= 1);
+    assert_eq!(summary.parse_errors, result.file_stats[0].parse_errors);
+    assert_eq!(summary.load_errors, 0);
+    assert_eq!(summary.directive_count, result.directives.len());
+}
+
+#[test]
+fn test_load_file_stats_with_missing_include() {
+    let path = fixtures_path("missing_include.beancount");
+    let result = load(&path).expect("should load file with missing include");
+
+    let summary = result.summary();
+    assert_eq!(summary.file_count, 1);
+    assert_eq!(summary.parse_errors, 0);
+    assert_eq!(summary.load_errors, 1);
+}
+
 #[test]
 fn test_load_nonexistent_file() {
     let path = fixtures_path("does_not_exist.beancount");
@@ -0,0 +1,165 @@
+//! Request cancellation for the synchronous main loop.
+//!
+//! Requests are handled one at a time, so a `$/cancelRequest` for the
+//! request being handled (or one queued behind it) only arrives while the
+//! main loop is busy. Long operations call [`Inbox::is_request_cancelled`]
+//! or [`Inbox::is_progress_cancelled`] between steps; these drain the
+//! channel, record cancellations, and queue every other message so the main
+//! loop handles it afterwards in order.
+
+use crossbeam_channel::Receiver;
+use lsp_server::RequestId;
+use lsp_types::notification::{Cancel, Notification, WorkDoneProgressCancel};
+use lsp_types::{CancelParams, ProgressToken, WorkDoneProgressCancelParams};
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+
+/// Incoming messages, with cancellations pulled out.
+pub struct Inbox {
+    /// Channel of messages from the client.
+    receiver: Receiver<lsp_server::Message>,
+    /// Messages received while checking for cancellation.
+    pending: RefCell<VecDeque<lsp_server::Message>>,
+    /// Requests the client cancelled.
+    cancelled_requests: RefCell<HashSet<RequestId>>,
+    /// Server-created progress the user cancelled.
+    cancelled_progress: RefCell<HashSet<ProgressToken>>,
+}
+
+impl Inbox {
+    /// Create an inbox reading from `receiver`.
+    pub fn new(receiver: Receiver<lsp_server::Message>) -> Self {
+        Self {
+            receiver,
+            pending: RefCell::new(VecDeque::new()),
+            cancelled_requests: RefCell::new(HashSet::new()),
+            cancelled_progress: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Next message to handle, blocking until one arrives.
+    ///
+    /// Returns `None` once the channel is closed and nothing is queued.
+    pub fn next(&self) -> Option<lsp_server::Message> {
+        if let Some(msg) = self.pending.borrow_mut().pop_front() {
+            return Some(msg);
+        }
+        self.receiver.recv().ok()
+    }
+
+    /// Check whether the client cancelled request `id`.
+    pub fn is_request_cancelled(&self, id: &RequestId) -> bool {
+        self.poll();
+        self.cancelled_requests.borrow().contains(id)
+    }
+
+    /// Check whether the user cancelled the progress with `token`.
+    pub fn is_progress_cancelled(&self, token: &ProgressToken) -> bool {
+        self.poll();
+        self.cancelled_progress.borrow().contains(token)
+    }
+
+    /// Forget a cancellation once its request has been answered.
+    pub fn complete_request(&self, id: &RequestId) {
+        self.cancelled_requests.borrow_mut().remove(id);
+    }
+
+    /// Forget a cancellation once its progress has ended.
+    pub fn complete_progress(&self, token: &ProgressToken) {
+        self.cancelled_progress.borrow_mut().remove(token);
+    }
+
+    /// Drain the channel without blocking.
+    fn poll(&self) {
+        for msg in self.receiver.try_iter() {
+            match msg {
+                lsp_server::Message::Notification(notif) if notif.method == Cancel::METHOD => {
+                    if let Ok(params) = serde_json::from_value::<CancelParams>(notif.params) {
+                        let id = match params.id {
+                            lsp_types::NumberOrString::Number(n) => RequestId::from(n),
+                            lsp_types::NumberOrString::String(s) => RequestId::from(s),
+                        };
+                        tracing::debug!("Request {} cancelled", id);
+                        self.cancelled_requests.borrow_mut().insert(id);
+                    }
+                }
+                lsp_server::Message::Notification(notif)
+                    if notif.method == WorkDoneProgressCancel::METHOD =>
+                {
+                    if let Ok(params) =
+                        serde_json::from_value::<WorkDoneProgressCancelParams>(notif.params)
+                    {
+                        self.cancelled_progress.borrow_mut().insert(params.token);
+                    }
+                }
+                msg => self.pending.borrow_mut().push_back(msg),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::NumberOrString;
+
+    fn cancel(id: i32) -> lsp_server::Message {
+        lsp_server::Message::Notification(lsp_server::Notification::new(
+            Cancel::METHOD.to_string(),
+            CancelParams {
+                id: NumberOrString::Number(id),
+            },
+        ))
+    }
+
+    fn request(id: i32) -> lsp_server::Message {
+        lsp_server::Message::Request(lsp_server::Request::new(
+            RequestId::from(id),
+            "textDocument/hover".to_string(),
+            serde_json::Value::Null,
+        ))
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let inbox = Inbox::new(receiver);
+
+        sender.send(request(2)).unwrap();
+        sender.send(cancel(1)).unwrap();
+
+        assert!(inbox.is_request_cancelled(&RequestId::from(1)));
+        assert!(!inbox.is_request_cancelled(&RequestId::from(2)));
+
+        // Other messages are kept, in order
+        match inbox.next() {
+            Some(lsp_server::Message::Request(req)) => assert_eq!(req.id, RequestId::from(2)),
+            other => panic!("expected request, got {other:?}"),
+        }
+
+        inbox.complete_request(&RequestId::from(1));
+        assert!(!inbox.is_request_cancelled(&RequestId::from(1)));
+    }
+
+    #[test]
+    fn test_cancel_progress() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let inbox = Inbox::new(receiver);
+        let token = NumberOrString::String("rledger/validate/0".to_string());
+
+        sender
+            .send(lsp_server::Message::Notification(
+                lsp_server::Notification::new(
+                    WorkDoneProgressCancel::METHOD.to_string(),
+                    WorkDoneProgressCancelParams {
+                        token: token.clone(),
+                    },
+                ),
+            ))
+            .unwrap();
+
+        assert!(inbox.is_progress_cancelled(&token));
+        inbox.complete_progress(&token);
+        assert!(!inbox.is_progress_cancelled(&token));
+    }
+}
@@ -0,0 +1,415 @@
+//! BQL support inside `query` directives and `.bql` files.
+//!
+//! The query string of a `query` directive, and each `;`-separated
+//! statement of a `.bql` file, is edited as BQL:
+//! - Completion of keywords, columns and functions, and of account and tag
+//!   literals from the ledger inside string literals
+//! - Hover documentation for keywords, columns and functions
+//! - Syntax diagnostics from the query parser
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionResponse, Diagnostic, DiagnosticSeverity, Hover,
+    HoverContents, MarkupContent, MarkupKind, Position, Range, Uri,
+};
+use rustledger_core::Directive;
+use rustledger_parser::ParseResult;
+use rustledger_query::completions::{self, BqlContext, CompletionCategory};
+use rustledger_query::error::ParseErrorKind;
+use rustledger_query::parse as parse_query;
+use std::collections::BTreeSet;
+
+use super::utils::LineIndex;
+
+/// A BQL query embedded in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryRegion {
+    /// The query text, with string escapes resolved.
+    pub text: String,
+    /// Source byte offset of each byte of `text`, plus one past the end.
+    offsets: Vec<usize>,
+}
+
+impl QueryRegion {
+    /// Source byte range the query occupies.
+    fn source_range(&self) -> (usize, usize) {
+        (self.offsets[0], self.offsets[self.offsets.len() - 1])
+    }
+
+    /// Whether a source offset falls within the query, ends included.
+    fn contains(&self, offset: usize) -> bool {
+        let (start, end) = self.source_range();
+        (start..=end).contains(&offset)
+    }
+
+    /// Offset into `text` of a source offset within the query.
+    fn text_offset(&self, offset: usize) -> usize {
+        self.offsets.partition_point(|&o| o < offset)
+    }
+
+    /// Source offset of an offset into `text`.
+    fn source_offset(&self, offset: usize) -> usize {
+        self.offsets[offset.min(self.offsets.len() - 1)]
+    }
+}
+
+/// Whether a document is a BQL file.
+pub fn is_bql_file(uri: &Uri) -> bool {
+    uri.as_str().ends_with(".bql")
+}
+
+/// The BQL queries of a document: every statement of a `.bql` file, or the
+/// query strings of a ledger's `query` directives.
+pub fn query_regions(uri: &Uri, source: &str, parse_result: &ParseResult) -> Vec<QueryRegion> {
+    if is_bql_file(uri) {
+        bql_statements(source)
+    } else {
+        query_directive_regions(source, parse_result)
+    }
+}
+
+/// Split a `.bql` file into statements at `;` outside string literals.
+fn bql_statements(source: &str) -> Vec<QueryRegion> {
+    let mut regions = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    for (i, c) in source.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => {
+                regions.push(plain_region(source, start, i));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    regions.push(plain_region(source, start, source.len()));
+    regions.retain(|region| !region.text.trim().is_empty());
+    regions
+}
+
+/// A region covering `source[start..end]` verbatim.
+fn plain_region(source: &str, start: usize, end: usize) -> QueryRegion {
+    QueryRegion {
+        text: source[start..end].to_string(),
+        offsets: (start..=end).collect(),
+    }
+}
+
+/// The query strings of the `query` directives in a ledger.
+fn query_directive_regions(source: &str, parse_result: &ParseResult) -> Vec<QueryRegion> {
+    parse_result
+        .directives
+        .iter()
+        .filter(|spanned| matches!(spanned.value, Directive::Query(_)))
+        .filter_map(|spanned| {
+            // The query is the second string, after the query's name
+            let text = source.get(spanned.span.start..spanned.span.end)?;
+            let (start, end) = string_literals(text).nth(1)?;
+            Some(unescaped_region(
+                source,
+                spanned.span.start + start,
+                spanned.span.start + end,
+            ))
+        })
+        .collect()
+}
+
+/// Byte ranges of the contents of the string literals in `text`.
+fn string_literals(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = text.char_indices();
+    std::iter::from_fn(move || {
+        let start = chars.by_ref().find(|&(_, c)| c == '"')?.0 + 1;
+        let mut escaped = false;
+        for (i, c) in chars.by_ref() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => return Some((start, i)),
+                _ => escaped = false,
+            }
+        }
+        None
+    })
+}
+
+/// A region for the contents of a string literal at `source[start..end]`,
+/// resolving backslash escapes.
+fn unescaped_region(source: &str, start: usize, end: usize) -> QueryRegion {
+    let mut text = String::new();
+    let mut offsets = Vec::new();
+    let mut chars = source[start..end].char_indices();
+    while let Some((i, c)) = chars.next() {
+        let c = if c == '\\' {
+            match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 't')) => '\t',
+                Some((_, escaped)) => escaped,
+                None => c,
+            }
+        } else {
+            c
+        };
+        offsets.extend(std::iter::repeat(start + i).take(c.len_utf8()));
+        text.push(c);
+    }
+    offsets.push(end);
+    QueryRegion { text, offsets }
+}
+
+/// The query containing a position, if any.
+pub fn region_at(regions: &[QueryRegion], source: &str, position: Position) -> Option<usize> {
+    let offset = LineIndex::new(source).position_to_offset(position.line, position.character)?;
+    regions.iter().position(|region| region.contains(offset))
+}
+
+/// Complete BQL at a position inside a query.
+///
+/// Inside string literals, accounts and tags of the ledger are offered.
+pub fn handle_bql_completion(
+    region: &QueryRegion,
+    source: &str,
+    position: Position,
+    ledger: &[Directive],
+) -> Option<CompletionResponse> {
+    let offset = LineIndex::new(source).position_to_offset(position.line, position.character)?;
+    let result = completions::complete(&region.text, region.text_offset(offset));
+
+    let items: Vec<CompletionItem> = if result.context == BqlContext::InString {
+        literal_completions(ledger)
+    } else {
+        result
+            .completions
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.text.clone(),
+                kind: Some(match completion.category {
+                    CompletionCategory::Keyword => CompletionItemKind::KEYWORD,
+                    CompletionCategory::Function => CompletionItemKind::FUNCTION,
+                    CompletionCategory::Column => CompletionItemKind::FIELD,
+                    CompletionCategory::Operator => CompletionItemKind::OPERATOR,
+                    CompletionCategory::Literal => CompletionItemKind::VALUE,
+                }),
+                detail: completion.description,
+                ..Default::default()
+            })
+            .collect()
+    };
+
+    if items.is_empty() {
+        None
+    } else {
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+/// Account and tag names of the ledger, for string literals.
+fn literal_completions(ledger: &[Directive]) -> Vec<CompletionItem> {
+    let mut accounts = BTreeSet::new();
+    let mut tags = BTreeSet::new();
+    for directive in ledger {
+        match directive {
+            Directive::Open(open) => {
+                accounts.insert(open.account.to_string());
+            }
+            Directive::Transaction(txn) => {
+                accounts.extend(txn.postings.iter().map(|p| p.account.to_string()));
+                tags.extend(txn.tags.iter().map(ToString::to_string));
+            }
+            _ => {}
+        }
+    }
+
+    let accounts = accounts.into_iter().map(|account| CompletionItem {
+        label: account,
+        kind: Some(CompletionItemKind::VARIABLE),
+        detail: Some("Account".to_string()),
+        ..Default::default()
+    });
+    let tags = tags.into_iter().map(|tag| CompletionItem {
+        label: tag,
+        kind: Some(CompletionItemKind::CONSTANT),
+        detail: Some("Tag".to_string()),
+        ..Default::default()
+    });
+    accounts.chain(tags).collect()
+}
+
+/// Document the BQL keyword, column or function under the cursor.
+pub fn handle_bql_hover(region: &QueryRegion, source: &str, position: Position) -> Option<Hover> {
+    let line_index = LineIndex::new(source);
+    let offset = line_index.position_to_offset(position.line, position.character)?;
+    let cursor = region.text_offset(offset);
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let text = &region.text;
+    let start = text[..cursor]
+        .rfind(|c: char| !is_word(c))
+        .map_or(0, |i| i + 1);
+    let end = text[cursor..]
+        .find(|c: char| !is_word(c))
+        .map_or(text.len(), |i| cursor + i);
+    let word = &text[start..end];
+    if word.is_empty() {
+        return None;
+    }
+
+    let completion = completions::describe(word)?;
+    let (start_line, start_col) = line_index.offset_to_position(region.source_offset(start));
+    let (end_line, end_col) = line_index.offset_to_position(region.source_offset(end));
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!(
+                "## BQL {}: `{}`\n\n{}",
+                completion.category.as_str(),
+                completion.text,
+                completion.description.unwrap_or_default()
+            ),
+        }),
+        range: Some(Range::new(
+            Position::new(start_line, start_col),
+            Position::new(end_line, end_col),
+        )),
+    })
+}
+
+/// Syntax errors in the queries of a document.
+pub fn bql_diagnostics(regions: &[QueryRegion], source: &str) -> Vec<Diagnostic> {
+    let line_index = LineIndex::new(source);
+    regions
+        .iter()
+        .filter_map(|region| {
+            let error = parse_query(&region.text).err()?;
+            let (start, end) = match error.kind {
+                ParseErrorKind::UnexpectedEof => {
+                    let end = region.source_range().1;
+                    (end, end)
+                }
+                ParseErrorKind::SyntaxError(_) => (
+                    region.source_offset(error.position),
+                    region.source_offset(error.position + 1),
+                ),
+            };
+            let (start_line, start_col) = line_index.offset_to_position(start);
+            let (end_line, end_col) = line_index.offset_to_position(end);
+            Some(Diagnostic {
+                range: Range::new(
+                    Position::new(start_line, start_col),
+                    Position::new(end_line, end_col),
+                ),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                source: Some("rustledger-bql".to_string()),
+                message: format!("BQL syntax error: {}", error.kind),
+                related_information: None,
+                tags: None,
+                code_description: None,
+                data: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_parser::parse;
+
+    const LEDGER: &str = r#"2024-01-01 open Assets:Bank USD
+2024-01-01 open Expenses:Food
+2024-01-15 * "Lunch" #work
+  Assets:Bank  -12.00 USD
+  Expenses:Food
+2024-02-01 query "food" "SELECT account WHERE account ~ \"Expenses\" AND "
+"#;
+
+    fn ledger_regions() -> Vec<QueryRegion> {
+        let uri: Uri = "file:///ledger.beancount".parse().unwrap();
+        query_regions(&uri, LEDGER, &parse(LEDGER))
+    }
+
+    #[test]
+    fn test_query_directive_region_unescapes() {
+        let regions = ledger_regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(
+            regions[0].text,
+            r#"SELECT account WHERE account ~ "Expenses" AND "#
+        );
+        let start = LEDGER.find("SELECT").unwrap();
+        assert_eq!(regions[0].source_range().0, start);
+        // `"Expenses"` is escaped, so text offsets lag behind the source
+        let quote = regions[0].text.find('"').unwrap();
+        assert_eq!(regions[0].source_offset(quote), start + quote);
+        assert_eq!(regions[0].source_offset(quote + 1), start + quote + 2);
+    }
+
+    #[test]
+    fn test_bql_statements() {
+        let source = "SELECT account;\n\nBALANCES; \"a;b\"";
+        let regions = bql_statements(source);
+        let texts: Vec<_> = regions.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(texts, vec!["SELECT account", "\n\nBALANCES", " \"a;b\""]);
+        assert_eq!(regions[1].source_range(), (15, 25));
+    }
+
+    #[test]
+    fn test_completion_columns_and_literals() {
+        let regions = ledger_regions();
+        let line = LEDGER.lines().count() as u32 - 1;
+
+        // After SELECT: columns and functions
+        let col = LEDGER.lines().last().unwrap().find("SELECT").unwrap() as u32 + 7;
+        let Some(CompletionResponse::Array(items)) =
+            handle_bql_completion(&regions[0], LEDGER, Position::new(line, col), &[])
+        else {
+            panic!("expected completions");
+        };
+        assert!(items.iter().any(|item| item.label == "account"
+            && item.kind == Some(CompletionItemKind::FIELD)));
+
+        // Inside a string literal: accounts and tags
+        let source = "SELECT account WHERE \"";
+        let region = plain_region(source, 0, source.len());
+        let ledger: Vec<_> = parse(LEDGER)
+            .directives
+            .into_iter()
+            .map(|d| d.value)
+            .collect();
+        let Some(CompletionResponse::Array(items)) =
+            handle_bql_completion(&region, source, Position::new(0, 22), &ledger)
+        else {
+            panic!("expected completions");
+        };
+        let labels: Vec<_> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["Assets:Bank", "Expenses:Food", "work"]);
+    }
+
+    #[test]
+    fn test_hover_column() {
+        let source = "SELECT narration";
+        let region = plain_region(source, 0, source.len());
+        let hover = handle_bql_hover(&region, source, Position::new(0, 9)).unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markup");
+        };
+        assert!(markup.value.contains("BQL column: `narration`"));
+        assert_eq!(
+            hover.range,
+            Some(Range::new(Position::new(0, 7), Position::new(0, 16)))
+        );
+    }
+
+    #[test]
+    fn test_bql_diagnostics() {
+        let regions = ledger_regions();
+        let diagnostics = bql_diagnostics(&regions, LEDGER);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.starts_with("BQL syntax error"));
+        let line = LEDGER.lines().count() as u32 - 1;
+        assert_eq!(diagnostics[0].range.start.line, line);
+
+        let valid = "SELECT account;\nBALANCES;\n";
+        assert!(bql_diagnostics(&bql_statements(valid), valid).is_empty());
+    }
+}
@@ -2,20 +2,29 @@
 //!
 //! Provides code actions for:
 //! - Adding missing account open directives
+//! - Replacing a likely misspelled account with the opened one it resembles
 //! - Balancing transaction postings
 //! - Formatting amounts consistently
+//! - Moving the selected transactions to an included file
 //!
 //! Supports resolve for lazy-loading workspace edits.
 
+use chrono::Datelike;
 use lsp_types::{
-    CodeAction, CodeActionKind, CodeActionParams, CodeActionResponse, Position, Range, TextEdit,
-    Uri, WorkspaceEdit,
+    CodeAction, CodeActionKind, CodeActionParams, CodeActionResponse, CreateFile,
+    CreateFileOptions, Diagnostic, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, Range, ResourceOp, TextDocumentEdit,
+    TextEdit, Uri, WorkspaceEdit,
 };
-use rustledger_core::Directive;
+use rustledger_core::{Directive, NaiveDate};
 use rustledger_parser::ParseResult;
 use std::collections::{HashMap, HashSet};
 
-use super::utils::byte_offset_to_position;
+use super::diagnostics::SIMILAR_ACCOUNT;
+use super::utils::{LineIndex, byte_offset_to_position};
+
+/// Code action data kind for moving transactions to an included file.
+const EXTRACT_TO_INCLUDE: &str = "extract_to_include";
 
 /// Handle a code action request.
 pub fn handle_code_actions(
@@ -49,11 +58,24 @@ pub fn handle_code_actions(
         }
     }
 
+    // Offer the suggested account for likely typos
+    actions.extend(
+        params
+            .context
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| create_similar_account_action(&uri, diagnostic)),
+    );
+
     // Check for unbalanced transactions in range
     if let Some(action) = check_unbalanced_transactions(params, source, parse_result) {
         actions.push(action);
     }
 
+    if let Some(action) = create_extract_to_include_action(&uri, source, parse_result, range) {
+        actions.push(action);
+    }
+
     if actions.is_empty() {
         None
     } else {
@@ -170,33 +192,322 @@ fn create_open_directive_action(uri: &Uri, account: &str) -> CodeAction {
     }
 }
 
+/// Create a quick fix replacing a misspelled account with the suggested one.
+#[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
+fn create_similar_account_action(uri: &Uri, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let data = diagnostic.data.as_ref()?;
+    if data.get("kind").and_then(|v| v.as_str()) != Some(SIMILAR_ACCOUNT) {
+        return None;
+    }
+    let suggestion = data.get("suggestion").and_then(|v| v.as_str())?;
+
+    let mut changes = HashMap::new();
+    changes.insert(
+        uri.clone(),
+        vec![TextEdit {
+            range: diagnostic.range,
+            new_text: suggestion.to_string(),
+        }],
+    );
+
+    Some(CodeAction {
+        title: format!("Change to '{}'", suggestion),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        }),
+        command: None,
+        is_preferred: Some(true),
+        disabled: None,
+        data: None,
+    })
+}
+
+/// Offer to move the transactions starting in a selection to an included
+/// file.
+///
+/// The file sits next to the document and is named after the years of the
+/// transactions (`2024.beancount`, `2023-2024.beancount`). The edit is
+/// deferred to the resolve phase.
+fn create_extract_to_include_action(
+    uri: &Uri,
+    source: &str,
+    parse_result: &ParseResult,
+    range: Range,
+) -> Option<CodeAction> {
+    if range.start == range.end {
+        return None;
+    }
+    let selected = selected_transactions(source, parse_result, range);
+    let target = include_file_name(&selected)?;
+    if sibling_uri(uri, &target)? == *uri {
+        return None;
+    }
+
+    let data = serde_json::json!({
+        "kind": EXTRACT_TO_INCLUDE,
+        "uri": uri.as_str(),
+        "target": target,
+        "range": range,
+    });
+
+    let count = selected.len();
+    Some(CodeAction {
+        title: format!(
+            "Move {count} transaction{} to include file '{target}'",
+            if count == 1 { "" } else { "s" }
+        ),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        diagnostics: None,
+        edit: None, // Resolved lazily
+        command: None,
+        is_preferred: None,
+        disabled: None,
+        data: Some(data),
+    })
+}
+
+/// Transactions whose first line lies in `range`, as `(date, first line,
+/// last line)`.
+fn selected_transactions(
+    source: &str,
+    parse_result: &ParseResult,
+    range: Range,
+) -> Vec<(NaiveDate, u32, u32)> {
+    let index = LineIndex::new(source);
+    // A selection of whole lines ends at the start of the next line
+    let last_line = if range.end.character == 0 && range.end.line > range.start.line {
+        range.end.line - 1
+    } else {
+        range.end.line
+    };
+
+    parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned| {
+            let Directive::Transaction(txn) = &spanned.value else {
+                return None;
+            };
+            let text = source.get(spanned.span.start..spanned.span.end)?.trim_end();
+            let (start, _) = index.offset_to_position(spanned.span.start);
+            let (end, _) = index.offset_to_position(spanned.span.start + text.len());
+            (range.start.line <= start && start <= last_line).then_some((txn.date, start, end))
+        })
+        .collect()
+}
+
+/// The include file for a set of transactions, named after their years.
+fn include_file_name(selected: &[(NaiveDate, u32, u32)]) -> Option<String> {
+    let first = selected.iter().map(|(date, _, _)| date.year()).min()?;
+    let last = selected.iter().map(|(date, _, _)| date.year()).max()?;
+    Some(if first == last {
+        format!("{first}.beancount")
+    } else {
+        format!("{first}-{last}.beancount")
+    })
+}
+
+/// The URI of `name` in the same directory as `uri`.
+fn sibling_uri(uri: &Uri, name: &str) -> Option<Uri> {
+    let (dir, _) = uri.as_str().rsplit_once('/')?;
+    format!("{dir}/{name}").parse().ok()
+}
+
 /// Handle a code action resolve request.
 /// Computes the workspace edit for a code action.
+///
+/// `read_document` returns the current text of another document, or `None`
+/// if it does not exist; it is used to append to an existing include file.
 #[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
 pub fn handle_code_action_resolve(
     action: CodeAction,
     source: &str,
     parse_result: &ParseResult,
     uri: &Uri,
+    read_document: impl Fn(&Uri) -> Option<String>,
 ) -> CodeAction {
     let mut resolved = action.clone();
 
     if let Some(data) = &action.data {
-        if data.get("kind").and_then(|v| v.as_str()) == Some("add_open_directive") {
-            if let Some(account) = data.get("account").and_then(|v| v.as_str()) {
-                resolved.edit = Some(compute_open_directive_edit(
-                    uri,
-                    source,
-                    account,
-                    parse_result,
-                ));
+        match data.get("kind").and_then(|v| v.as_str()) {
+            Some("add_open_directive") => {
+                if let Some(account) = data.get("account").and_then(|v| v.as_str()) {
+                    resolved.edit = Some(compute_open_directive_edit(
+                        uri,
+                        source,
+                        account,
+                        parse_result,
+                    ));
+                }
             }
+            Some(EXTRACT_TO_INCLUDE) => {
+                let target = data.get("target").and_then(|v| v.as_str());
+                let range = data
+                    .get("range")
+                    .and_then(|v| serde_json::from_value::<Range>(v.clone()).ok());
+                if let (Some(target), Some(range)) = (target, range) {
+                    resolved.edit = sibling_uri(uri, target).and_then(|target_uri| {
+                        let target_text = read_document(&target_uri);
+                        compute_extract_to_include_edit(
+                            uri,
+                            source,
+                            parse_result,
+                            range,
+                            &target_uri,
+                            target,
+                            target_text.as_deref(),
+                        )
+                    });
+                }
+            }
+            _ => {}
         }
     }
 
     resolved
 }
 
+/// Compute the workspace edit moving the selected transactions to an
+/// included file.
+///
+/// The file is created when `target_text` is `None`, and appended to
+/// otherwise. The `include` line is added to the document unless it is
+/// already there.
+fn compute_extract_to_include_edit(
+    uri: &Uri,
+    source: &str,
+    parse_result: &ParseResult,
+    range: Range,
+    target_uri: &Uri,
+    target: &str,
+    target_text: Option<&str>,
+) -> Option<WorkspaceEdit> {
+    let selected = selected_transactions(source, parse_result, range);
+    if selected.is_empty() {
+        return None;
+    }
+
+    let index = LineIndex::new(source);
+    let lines: Vec<&str> = source.lines().collect();
+    let line_start = |line: u32| {
+        if (line as usize) < lines.len() {
+            Position::new(line, 0)
+        } else {
+            let (line, character) = index.offset_to_position(source.len());
+            Position::new(line, character)
+        }
+    };
+
+    let mut moved = Vec::new();
+    let mut source_edits = Vec::new();
+
+    if !parse_result.includes.iter().any(|(path, _)| path == target) {
+        let position = include_position(&index, parse_result);
+        source_edits.push(TextEdit {
+            range: Range::new(position, position),
+            new_text: format!("include \"{target}\"\n"),
+        });
+    }
+
+    for &(_, start, end) in &selected {
+        let from = index.position_to_offset(start, 0)?;
+        let to = index.position_to_offset(end + 1, 0).unwrap_or(source.len());
+        moved.push(source[from..to].trim_end());
+
+        // Take a blank line after the transaction along with it
+        let mut next = end + 1;
+        if lines
+            .get(next as usize)
+            .is_some_and(|l| l.trim().is_empty())
+        {
+            next += 1;
+        }
+        source_edits.push(TextEdit {
+            range: Range::new(Position::new(start, 0), line_start(next)),
+            new_text: String::new(),
+        });
+    }
+
+    let mut new_text = moved.join("\n\n");
+    new_text.push('\n');
+
+    let mut operations = Vec::new();
+    let insert_at = match target_text {
+        Some(text) => {
+            if !text.trim().is_empty() {
+                let separator = if text.ends_with('\n') { "\n" } else { "\n\n" };
+                new_text.insert_str(0, separator);
+            }
+            let (line, character) = LineIndex::new(text).offset_to_position(text.len());
+            Position::new(line, character)
+        }
+        None => {
+            operations.push(DocumentChangeOperation::Op(ResourceOp::Create(
+                CreateFile {
+                    uri: target_uri.clone(),
+                    options: Some(CreateFileOptions {
+                        overwrite: Some(false),
+                        ignore_if_exists: Some(true),
+                    }),
+                    annotation_id: None,
+                },
+            )));
+            Position::new(0, 0)
+        }
+    };
+
+    let text_edit = |uri: &Uri, edits: Vec<TextEdit>| {
+        DocumentChangeOperation::Edit(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: None,
+            },
+            edits: edits.into_iter().map(OneOf::Left).collect(),
+        })
+    };
+    operations.push(text_edit(
+        target_uri,
+        vec![TextEdit {
+            range: Range::new(insert_at, insert_at),
+            new_text,
+        }],
+    ));
+    operations.push(text_edit(uri, source_edits));
+
+    Some(WorkspaceEdit {
+        changes: None,
+        document_changes: Some(DocumentChanges::Operations(operations)),
+        change_annotations: None,
+    })
+}
+
+/// Where to add an `include` line: after the last include, option or
+/// plugin, or at the top of the document.
+fn include_position(index: &LineIndex, parse_result: &ParseResult) -> Position {
+    let last = parse_result
+        .includes
+        .iter()
+        .map(|(_, span)| span.end)
+        .max()
+        .or_else(|| {
+            let options = parse_result.options.iter().map(|(_, _, span)| span.end);
+            let plugins = parse_result.plugins.iter().map(|(_, _, span)| span.end);
+            options.chain(plugins).max()
+        });
+
+    match last {
+        Some(offset) => {
+            let (line, _) = index.offset_to_position(offset.saturating_sub(1));
+            Position::new(line + 1, 0)
+        }
+        None => Position::new(0, 0),
+    }
+}
+
 /// Compute the workspace edit for adding an open directive.
 #[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
 fn compute_open_directive_edit(
@@ -389,7 +700,7 @@ mod tests {
             })),
         };
 
-        let resolved = handle_code_action_resolve(action, source, &result, &uri);
+        let resolved = handle_code_action_resolve(action, source, &result, &uri, |_| None);
 
         // Should now have an edit
         assert!(resolved.edit.is_some());
@@ -403,4 +714,189 @@ mod tests {
         assert!(edits[0].new_text.contains("open Expenses:Food"));
         assert!(edits[0].new_text.contains("2024-01-01")); // Earliest date
     }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)] // Uri has interior mutability but is safe in tests
+    fn test_similar_account_action() {
+        use super::super::diagnostics::{opened_accounts, similar_account_diagnostics};
+
+        let source = r#"2024-01-01 open Assets:Bank USD
+2024-01-01 open Expenses:Restaurant
+2024-01-15 * "Dinner"
+  Assets:Bank  -40.00 USD
+  Expenses:Restuarant
+"#;
+        let result = parse(source);
+        let diagnostics = similar_account_diagnostics(source, &result, &opened_accounts(&result));
+        let uri: Uri = "file:///test.beancount".parse().unwrap();
+        let params = CodeActionParams {
+            text_document: lsp_types::TextDocumentIdentifier { uri: uri.clone() },
+            range: diagnostics[0].range,
+            context: lsp_types::CodeActionContext {
+                diagnostics: diagnostics.clone(),
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let actions = handle_code_actions(&params, source, &result).unwrap();
+        let action = actions
+            .into_iter()
+            .find_map(|action| match action {
+                lsp_types::CodeActionOrCommand::CodeAction(action)
+                    if action.title == "Change to 'Expenses:Restaurant'" =>
+                {
+                    Some(action)
+                }
+                _ => None,
+            })
+            .expect("quick fix offered");
+
+        let changes = action.edit.unwrap().changes.unwrap();
+        let edits = changes.get(&uri).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, diagnostics[0].range);
+        assert_eq!(edits[0].new_text, "Expenses:Restaurant");
+    }
+
+    /// The edits of an extract-to-include action, as (created files,
+    /// per-document text edits).
+    #[allow(clippy::type_complexity)]
+    fn extract_edits(
+        source: &str,
+        range: Range,
+        target_text: Option<&str>,
+    ) -> (Vec<String>, Vec<(String, Vec<TextEdit>)>) {
+        let result = parse(source);
+        let uri: Uri = "file:///ledger/main.beancount".parse().unwrap();
+        let action =
+            create_extract_to_include_action(&uri, source, &result, range).expect("action offered");
+        assert_eq!(
+            action.title,
+            "Move 2 transactions to include file '2024.beancount'"
+        );
+
+        let target_text = target_text.map(str::to_string);
+        let resolved =
+            handle_code_action_resolve(action, source, &result, &uri, |_| target_text.clone());
+        let Some(DocumentChanges::Operations(operations)) = resolved.edit.unwrap().document_changes
+        else {
+            panic!("expected document operations");
+        };
+
+        let mut created = Vec::new();
+        let mut edits = Vec::new();
+        for operation in operations {
+            match operation {
+                DocumentChangeOperation::Op(ResourceOp::Create(create)) => {
+                    created.push(create.uri.as_str().to_string());
+                }
+                DocumentChangeOperation::Edit(edit) => edits.push((
+                    edit.text_document.uri.as_str().to_string(),
+                    edit.edits
+                        .into_iter()
+                        .map(|edit| match edit {
+                            OneOf::Left(edit) => edit,
+                            OneOf::Right(annotated) => annotated.text_edit,
+                        })
+                        .collect(),
+                )),
+                DocumentChangeOperation::Op(_) => panic!("unexpected operation"),
+            }
+        }
+        (created, edits)
+    }
+
+    #[test]
+    fn test_extract_to_new_include_file() {
+        let source = r#"option "title" "Test"
+2024-01-01 open Assets:Bank USD
+
+2023-12-30 * "Old"
+  Assets:Bank  -1.00 USD
+  Expenses:Food
+
+2024-01-15 * "Coffee"
+  Assets:Bank  -5.00 USD
+  Expenses:Food
+
+2024-02-01 * "Lunch"
+  Assets:Bank  -12.00 USD
+  Expenses:Food
+"#;
+        let range = Range::new(Position::new(7, 0), Position::new(14, 0));
+        let (created, edits) = extract_edits(source, range, None);
+
+        assert_eq!(created, vec!["file:///ledger/2024.beancount"]);
+        assert_eq!(edits.len(), 2);
+
+        let (target, target_edits) = &edits[0];
+        assert_eq!(target, "file:///ledger/2024.beancount");
+        assert_eq!(target_edits[0].range.start, Position::new(0, 0));
+        assert_eq!(
+            target_edits[0].new_text,
+            "2024-01-15 * \"Coffee\"\n  Assets:Bank  -5.00 USD\n  Expenses:Food\n\n\
+             2024-02-01 * \"Lunch\"\n  Assets:Bank  -12.00 USD\n  Expenses:Food\n"
+        );
+
+        let (document, document_edits) = &edits[1];
+        assert_eq!(document, "file:///ledger/main.beancount");
+        let ranges: Vec<_> = document_edits.iter().map(|e| e.range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(1, 0), Position::new(1, 0)),
+                Range::new(Position::new(7, 0), Position::new(11, 0)),
+                Range::new(Position::new(11, 0), Position::new(14, 0)),
+            ]
+        );
+        assert_eq!(document_edits[0].new_text, "include \"2024.beancount\"\n");
+    }
+
+    #[test]
+    fn test_extract_to_existing_include_file() {
+        let source = r#"include "2024.beancount"
+
+2024-01-15 * "Coffee"
+  Assets:Bank  -5.00 USD
+  Expenses:Food
+
+2024-02-01 * "Lunch"
+  Assets:Bank  -12.00 USD
+  Expenses:Food
+"#;
+        let range = Range::new(Position::new(2, 0), Position::new(8, 15));
+        let (created, edits) =
+            extract_edits(source, range, Some("2024-01-01 open Expenses:Food\n"));
+
+        assert!(created.is_empty());
+        let (_, target_edits) = &edits[0];
+        assert_eq!(target_edits[0].range.start, Position::new(1, 0));
+        assert!(
+            target_edits[0]
+                .new_text
+                .starts_with("\n2024-01-15 * \"Coffee\"")
+        );
+
+        // The include is already there; only the transactions are removed
+        let (_, document_edits) = &edits[1];
+        assert_eq!(document_edits.len(), 2);
+        assert!(document_edits.iter().all(|e| e.new_text.is_empty()));
+    }
+
+    #[test]
+    fn test_extract_to_include_needs_selection() {
+        let source = "2024-01-15 * \"Coffee\"\n  Assets:Bank  -5.00 USD\n  Expenses:Food\n";
+        let result = parse(source);
+        let uri: Uri = "file:///ledger/main.beancount".parse().unwrap();
+
+        let empty = Range::new(Position::new(0, 0), Position::new(0, 0));
+        assert!(create_extract_to_include_action(&uri, source, &result, empty).is_none());
+
+        // Selecting only postings does not select the transaction
+        let postings = Range::new(Position::new(1, 0), Position::new(2, 10));
+        assert!(create_extract_to_include_action(&uri, source, &result, postings).is_none());
+    }
 }
@@ -1,41 +1,154 @@
 //! Go to declaration handler.
 //!
-//! In Beancount, "declaration" and "definition" are the same concept:
+//! Declarations are the directives that introduce a symbol:
 //! - For accounts: the `open` directive
 //! - For currencies: the `commodity` directive
 //!
-//! This module simply re-exports the definition handler.
+//! Unlike go-to-definition, which can be configured to jump to balance
+//! assertions or transactions, go-to-declaration always resolves here.
 
-pub use super::definition::handle_goto_definition as handle_goto_declaration;
+use lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location, Position, Range, Uri};
+use rustledger_core::Directive;
+use rustledger_parser::{ParseResult, Span};
+
+use super::utils::{
+    byte_offset_to_position, get_word_at_source_position, is_account_type, is_currency_like_simple,
+};
+
+/// Handle a go-to-declaration request.
+pub fn handle_goto_declaration(
+    params: &GotoDefinitionParams,
+    source: &str,
+    parse_result: &ParseResult,
+    uri: &Uri,
+) -> Option<GotoDefinitionResponse> {
+    let position = params.text_document_position_params.position;
+
+    // Get the word at the cursor position
+    let word = get_word_at_source_position(source, position)?;
+
+    tracing::debug!("Go-to-declaration for word: {:?}", word);
+
+    // Check if it's an account name
+    if word.contains(':') || is_account_type(&word) {
+        if let Some(location) = find_account_declaration(&word, parse_result, source, uri) {
+            return Some(GotoDefinitionResponse::Scalar(location));
+        }
+    }
+
+    // Check if it's a currency
+    if is_currency_like_simple(&word) {
+        if let Some(location) = find_currency_declaration(&word, parse_result, source, uri) {
+            return Some(GotoDefinitionResponse::Scalar(location));
+        }
+    }
+
+    None
+}
+
+/// Find the declaration of an account (the Open directive).
+pub(crate) fn find_account_declaration(
+    account: &str,
+    parse_result: &ParseResult,
+    source: &str,
+    uri: &Uri,
+) -> Option<Location> {
+    for spanned_directive in &parse_result.directives {
+        if let Directive::Open(open) = &spanned_directive.value {
+            let open_account = open.account.to_string();
+            // Match exact account or account prefix
+            if open_account == account || account.starts_with(&format!("{}:", open_account)) {
+                return Some(span_to_location(source, spanned_directive.span, uri));
+            }
+        }
+    }
+    None
+}
+
+/// Find the declaration of a currency (the Commodity directive).
+pub(crate) fn find_currency_declaration(
+    currency: &str,
+    parse_result: &ParseResult,
+    source: &str,
+    uri: &Uri,
+) -> Option<Location> {
+    for spanned_directive in &parse_result.directives {
+        if let Directive::Commodity(comm) = &spanned_directive.value {
+            if comm.currency.as_ref() == currency {
+                return Some(span_to_location(source, spanned_directive.span, uri));
+            }
+        }
+    }
+    None
+}
+
+/// Convert a directive span to an LSP location.
+pub(crate) fn span_to_location(source: &str, span: Span, uri: &Uri) -> Location {
+    let (start_line, start_col) = byte_offset_to_position(source, span.start);
+    let (end_line, end_col) = byte_offset_to_position(source, span.end);
+
+    Location {
+        uri: uri.clone(),
+        range: Range {
+            start: Position::new(start_line, start_col),
+            end: Position::new(end_line, end_col),
+        },
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use lsp_types::{
-        GotoDefinitionParams, Position, TextDocumentIdentifier, TextDocumentPositionParams,
-    };
+    use lsp_types::{TextDocumentIdentifier, TextDocumentPositionParams};
     use rustledger_parser::parse;
 
+    fn params_at(uri: &Uri, line: u32, col: u32) -> GotoDefinitionParams {
+        GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position::new(line, col),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
     #[test]
-    fn test_goto_declaration_is_goto_definition() {
+    fn test_goto_declaration_jumps_to_open() {
         let source = r#"2024-01-01 open Assets:Bank USD
+2024-01-10 balance Assets:Bank 100.00 USD
 2024-01-15 * "Coffee"
   Assets:Bank  -5.00 USD
   Expenses:Food
 "#;
         let result = parse(source);
-        let uri: lsp_types::Uri = "file:///test.beancount".parse().unwrap();
+        let uri: Uri = "file:///test.beancount".parse().unwrap();
 
-        let params = GotoDefinitionParams {
-            text_document_position_params: TextDocumentPositionParams {
-                text_document: TextDocumentIdentifier { uri: uri.clone() },
-                position: Position::new(2, 5), // On "Assets:Bank" in posting
-            },
-            work_done_progress_params: Default::default(),
-            partial_result_params: Default::default(),
+        // On "Assets:Bank" in posting
+        let params = params_at(&uri, 3, 5);
+        let response = handle_goto_declaration(&params, source, &result, &uri);
+
+        let Some(GotoDefinitionResponse::Scalar(location)) = response else {
+            panic!("expected a scalar location");
         };
+        assert_eq!(location.range.start.line, 0);
+    }
+
+    #[test]
+    fn test_goto_declaration_currency() {
+        let source = r#"2024-01-01 commodity USD
+2024-01-01 open Assets:Bank USD
+"#;
+        let result = parse(source);
+        let uri: Uri = "file:///test.beancount".parse().unwrap();
 
-        let result = handle_goto_declaration(&params, source, &result, &uri);
-        assert!(result.is_some());
+        // On "USD" in the open directive
+        let params = params_at(&uri, 1, 29);
+        let response = handle_goto_declaration(&params, source, &result, &uri);
+
+        let Some(GotoDefinitionResponse::Scalar(location)) = response else {
+            panic!("expected a scalar location");
+        };
+        assert_eq!(location.range.start.line, 0);
     }
 }
@@ -1,16 +1,34 @@
 //! Go-to-definition handler.
 //!
 //! Provides navigation to symbol definitions:
-//! - Account → Open directive
+//! - Account → Open directive, latest balance/pad, or first transaction
+//!   (see [`DefinitionTarget`])
 //! - Currency → Commodity directive
 
-use lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location, Position, Range, Uri};
+use lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location, Uri};
 use rustledger_core::Directive;
 use rustledger_parser::ParseResult;
+use serde::{Deserialize, Serialize};
 
-use super::utils::{
-    byte_offset_to_position, get_word_at_source_position, is_account_type, is_currency_like_simple,
-};
+use super::declaration::{find_account_declaration, find_currency_declaration, span_to_location};
+use super::utils::{get_word_at_source_position, is_account_type, is_currency_like_simple};
+
+/// Where go-to-definition should jump for accounts.
+///
+/// Go-to-declaration always jumps to the `open` directive; this setting
+/// lets users make go-to-definition more useful for day-to-day editing.
+/// If no matching directive exists, the `open` directive is used instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionTarget {
+    /// The `open` directive (same as go-to-declaration).
+    #[default]
+    Open,
+    /// The most recent `balance` or `pad` directive for the account.
+    LatestBalance,
+    /// The first transaction posting to the account.
+    FirstTransaction,
+}
 
 /// Handle a go-to-definition request.
 pub fn handle_goto_definition(
@@ -18,24 +36,36 @@ pub fn handle_goto_definition(
     source: &str,
     parse_result: &ParseResult,
     uri: &Uri,
+    target: DefinitionTarget,
 ) -> Option<GotoDefinitionResponse> {
     let position = params.text_document_position_params.position;
 
     // Get the word at the cursor position
     let word = get_word_at_source_position(source, position)?;
 
-    tracing::debug!("Go-to-definition for word: {:?}", word);
+    tracing::debug!("Go-to-definition for word: {:?} ({:?})", word, target);
 
     // Check if it's an account name
     if word.contains(':') || is_account_type(&word) {
-        if let Some(location) = find_account_definition(&word, parse_result, source, uri) {
+        let location = match target {
+            DefinitionTarget::Open => None,
+            DefinitionTarget::LatestBalance => {
+                find_latest_balance(&word, parse_result, source, uri)
+            }
+            DefinitionTarget::FirstTransaction => {
+                find_first_transaction(&word, parse_result, source, uri)
+            }
+        }
+        .or_else(|| find_account_declaration(&word, parse_result, source, uri));
+
+        if let Some(location) = location {
             return Some(GotoDefinitionResponse::Scalar(location));
         }
     }
 
     // Check if it's a currency
     if is_currency_like_simple(&word) {
-        if let Some(location) = find_currency_definition(&word, parse_result, source, uri) {
+        if let Some(location) = find_currency_declaration(&word, parse_result, source, uri) {
             return Some(GotoDefinitionResponse::Scalar(location));
         }
     }
@@ -43,60 +73,136 @@ pub fn handle_goto_definition(
     None
 }
 
-/// Find the definition of an account (the Open directive).
-fn find_account_definition(
+/// Find the most recent `balance` or `pad` directive for an account.
+///
+/// Directives on the same date resolve to the one appearing last in the file.
+fn find_latest_balance(
     account: &str,
     parse_result: &ParseResult,
     source: &str,
     uri: &Uri,
 ) -> Option<Location> {
-    for spanned_directive in &parse_result.directives {
-        if let Directive::Open(open) = &spanned_directive.value {
-            let open_account = open.account.to_string();
-            // Match exact account or account prefix
-            if open_account == account || account.starts_with(&format!("{}:", open_account)) {
-                let (start_line, start_col) =
-                    byte_offset_to_position(source, spanned_directive.span.start);
-                let (end_line, end_col) =
-                    byte_offset_to_position(source, spanned_directive.span.end);
-
-                return Some(Location {
-                    uri: uri.clone(),
-                    range: Range {
-                        start: Position::new(start_line, start_col),
-                        end: Position::new(end_line, end_col),
-                    },
-                });
+    let (_, span) = parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned_directive| match &spanned_directive.value {
+            Directive::Balance(bal) if bal.account.as_ref() == account => {
+                Some((bal.date, spanned_directive.span))
             }
-        }
-    }
-    None
+            Directive::Pad(pad) if pad.account.as_ref() == account => {
+                Some((pad.date, spanned_directive.span))
+            }
+            _ => None,
+        })
+        .max_by_key(|(date, _)| *date)?;
+
+    Some(span_to_location(source, span, uri))
 }
 
-/// Find the definition of a currency (the Commodity directive).
-fn find_currency_definition(
-    currency: &str,
+/// Find the earliest transaction with a posting to an account.
+fn find_first_transaction(
+    account: &str,
     parse_result: &ParseResult,
     source: &str,
     uri: &Uri,
 ) -> Option<Location> {
-    for spanned_directive in &parse_result.directives {
-        if let Directive::Commodity(comm) = &spanned_directive.value {
-            if comm.currency.as_ref() == currency {
-                let (start_line, start_col) =
-                    byte_offset_to_position(source, spanned_directive.span.start);
-                let (end_line, end_col) =
-                    byte_offset_to_position(source, spanned_directive.span.end);
-
-                return Some(Location {
-                    uri: uri.clone(),
-                    range: Range {
-                        start: Position::new(start_line, start_col),
-                        end: Position::new(end_line, end_col),
-                    },
-                });
+    let (_, span) = parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned_directive| match &spanned_directive.value {
+            Directive::Transaction(txn)
+                if txn.postings.iter().any(|p| p.account.as_ref() == account) =>
+            {
+                Some((txn.date, spanned_directive.span))
             }
+            _ => None,
+        })
+        .min_by_key(|(date, _)| *date)?;
+
+    Some(span_to_location(source, span, uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams};
+    use rustledger_parser::parse;
+
+    const SOURCE: &str = r#"2024-01-01 open Assets:Bank USD
+2024-01-01 open Expenses:Food
+2024-03-01 * "Groceries"
+  Assets:Bank  -20.00 USD
+  Expenses:Food
+2024-02-01 * "Coffee"
+  Assets:Bank  -5.00 USD
+  Expenses:Food
+2024-02-15 pad Assets:Bank Equity:Opening-Balances
+2024-04-01 balance Assets:Bank 100.00 USD
+2024-03-15 balance Assets:Bank 80.00 USD
+"#;
+
+    fn definition_line(target: DefinitionTarget) -> u32 {
+        let result = parse(SOURCE);
+        let uri: Uri = "file:///test.beancount".parse().unwrap();
+
+        let params = GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position::new(3, 5), // On "Assets:Bank" in posting
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        match handle_goto_definition(&params, SOURCE, &result, &uri, target) {
+            Some(GotoDefinitionResponse::Scalar(location)) => location.range.start.line,
+            other => panic!("expected a scalar location, got {other:?}"),
         }
     }
-    None
+
+    #[test]
+    fn test_definition_open() {
+        assert_eq!(definition_line(DefinitionTarget::Open), 0);
+    }
+
+    #[test]
+    fn test_definition_latest_balance() {
+        assert_eq!(definition_line(DefinitionTarget::LatestBalance), 9);
+    }
+
+    #[test]
+    fn test_definition_first_transaction() {
+        assert_eq!(definition_line(DefinitionTarget::FirstTransaction), 5);
+    }
+
+    #[test]
+    fn test_definition_falls_back_to_open() {
+        let source = "2024-01-01 open Assets:Bank USD\n";
+        let result = parse(source);
+        let uri: Uri = "file:///test.beancount".parse().unwrap();
+
+        let params = GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position::new(0, 20),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let response = handle_goto_definition(
+            &params,
+            source,
+            &result,
+            &uri,
+            DefinitionTarget::LatestBalance,
+        );
+        assert!(matches!(response, Some(GotoDefinitionResponse::Scalar(_))));
+    }
+
+    #[test]
+    fn test_definition_target_deserialize() {
+        let target: DefinitionTarget = serde_json::from_str("\"first_transaction\"").unwrap();
+        assert_eq!(target, DefinitionTarget::FirstTransaction);
+    }
 }
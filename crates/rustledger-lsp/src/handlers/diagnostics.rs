@@ -1,7 +1,26 @@
 //! Diagnostics handler for publishing parse errors.
+//!
+//! Also flags accounts that are never opened but are a likely typo of an
+//! opened one (e.g. `Expenses:Restuarant` for `Expenses:Restaurant`).
+//!
+//! Clients supporting LSP 3.17 pull diagnostics get the same diagnostics
+//! through `textDocument/diagnostic` and `workspace/diagnostic` reports.
+//! Each report carries a result ID derived from its diagnostics, so files
+//! whose diagnostics did not change since the client's last pull are
+//! reported as unchanged instead of being sent again.
 
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentDiagnosticReport, DocumentDiagnosticReportResult,
+    FullDocumentDiagnosticReport, Position, Range, RelatedFullDocumentDiagnosticReport,
+    RelatedUnchangedDocumentDiagnosticReport, UnchangedDocumentDiagnosticReport, Uri,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceUnchangedDocumentDiagnosticReport,
+};
+use rustledger_core::Directive;
 use rustledger_parser::{ParseError, ParseResult};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
 
 use super::utils::LineIndex;
 
@@ -39,9 +58,209 @@ pub fn parse_error_to_diagnostic(error: &ParseError, line_index: &LineIndex) ->
     }
 }
 
+/// Result ID of a diagnostics report, derived from its diagnostics.
+pub fn diagnostics_result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Build the `textDocument/diagnostic` report of a document.
+///
+/// The report is unchanged when `previous_result_id` is still the result
+/// ID of `diagnostics`.
+pub fn document_diagnostic_report(
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReportResult {
+    let result_id = diagnostics_result_id(&diagnostics);
+    let report = if previous_result_id == Some(result_id.as_str()) {
+        DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+            related_documents: None,
+            unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport { result_id },
+        })
+    } else {
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items: diagnostics,
+            },
+        })
+    };
+    DocumentDiagnosticReportResult::Report(report)
+}
+
+/// Build the `workspace/diagnostic` report item of one file.
+///
+/// `version` is the version of the document if the client has it open.
+pub fn workspace_document_report(
+    uri: Uri,
+    version: Option<i64>,
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> WorkspaceDocumentDiagnosticReport {
+    let result_id = diagnostics_result_id(&diagnostics);
+    if previous_result_id == Some(result_id.as_str()) {
+        WorkspaceDocumentDiagnosticReport::Unchanged(WorkspaceUnchangedDocumentDiagnosticReport {
+            uri,
+            version,
+            unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport { result_id },
+        })
+    } else {
+        WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+            uri,
+            version,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items: diagnostics,
+            },
+        })
+    }
+}
+
+/// Code action data kind of similar-account diagnostics.
+pub const SIMILAR_ACCOUNT: &str = "similar_account";
+
+/// Collect the accounts opened in a document.
+pub fn opened_accounts(parse_result: &ParseResult) -> HashSet<String> {
+    parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned| match &spanned.value {
+            Directive::Open(open) => Some(open.account.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Warn about unopened accounts within a small edit distance of an opened
+/// account.
+///
+/// Each diagnostic carries the suggested account in its `data`, for the
+/// quick fix in the code actions handler.
+pub fn similar_account_diagnostics(
+    source: &str,
+    parse_result: &ParseResult,
+    opened: &HashSet<String>,
+) -> Vec<Diagnostic> {
+    let line_index = LineIndex::new(source);
+    let mut candidates: Vec<&String> = opened.iter().collect();
+    candidates.sort();
+
+    let mut diagnostics = Vec::new();
+    for spanned in &parse_result.directives {
+        for account in directive_accounts(&spanned.value) {
+            if opened.contains(account) {
+                continue;
+            }
+            let Some(suggestion) = similar_account(account, &candidates) else {
+                continue;
+            };
+            let text = &source[spanned.span.start..spanned.span.end];
+            for offset in account_occurrences(text, account) {
+                let start = spanned.span.start + offset;
+                let (start_line, start_col) = line_index.offset_to_position(start);
+                let (end_line, end_col) = line_index.offset_to_position(start + account.len());
+                diagnostics.push(Diagnostic {
+                    range: Range {
+                        start: Position::new(start_line, start_col),
+                        end: Position::new(end_line, end_col),
+                    },
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: None,
+                    source: Some("rustledger".to_string()),
+                    message: format!(
+                        "Account {account} is never opened; did you mean {suggestion}?"
+                    ),
+                    related_information: None,
+                    tags: None,
+                    code_description: None,
+                    data: Some(serde_json::json!({
+                        "kind": SIMILAR_ACCOUNT,
+                        "account": account,
+                        "suggestion": suggestion,
+                    })),
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Accounts a directive refers to, other than the one it opens.
+fn directive_accounts(directive: &Directive) -> BTreeSet<&str> {
+    match directive {
+        Directive::Transaction(txn) => txn.postings.iter().map(|p| p.account.as_ref()).collect(),
+        Directive::Balance(bal) => BTreeSet::from([bal.account.as_ref()]),
+        Directive::Pad(pad) => BTreeSet::from([pad.account.as_ref(), pad.source_account.as_ref()]),
+        Directive::Note(note) => BTreeSet::from([note.account.as_ref()]),
+        Directive::Document(doc) => BTreeSet::from([doc.account.as_ref()]),
+        Directive::Close(close) => BTreeSet::from([close.account.as_ref()]),
+        _ => BTreeSet::new(),
+    }
+}
+
+/// The closest opened account `account` is likely a typo of.
+///
+/// One edit is allowed for short names and two for names of 12 or more
+/// characters; adjacent transpositions count as one edit.
+fn similar_account<'a>(account: &str, candidates: &[&'a String]) -> Option<&'a str> {
+    let max_distance = if account.chars().count() >= 12 { 2 } else { 1 };
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(account, candidate), candidate.as_str()))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Optimal string alignment distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+/// Byte offsets of `account` in `text` that are not part of a longer name.
+fn account_occurrences(text: &str, account: &str) -> Vec<usize> {
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, ':' | '-' | '_');
+    text.match_indices(account)
+        .filter(|(offset, _)| {
+            let before = text[..*offset].chars().next_back();
+            let after = text[offset + account.len()..].chars().next();
+            !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+        })
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rustledger_parser::parse;
 
     #[test]
     fn test_line_index_offset_to_position() {
@@ -53,4 +272,94 @@ mod tests {
         assert_eq!(line_index.offset_to_position(6), (1, 0));
         assert_eq!(line_index.offset_to_position(12), (2, 0));
     }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("Restaurant", "Restaurant"), 0);
+        assert_eq!(edit_distance("Restuarant", "Restaurant"), 1);
+        assert_eq!(edit_distance("Restaurnt", "Restaurant"), 1);
+        assert_eq!(edit_distance("Food", "Fuel"), 3);
+    }
+
+    #[test]
+    fn test_similar_account_diagnostics() {
+        let source = r#"2024-01-01 open Assets:Bank USD
+2024-01-01 open Expenses:Restaurant
+2024-01-15 * "Dinner"
+  Assets:Bank  -40.00 USD
+  Expenses:Restuarant
+2024-01-16 * "Groceries"
+  Assets:Bank  -20.00 USD
+  Expenses:Groceries
+2024-02-01 balance Assets:Bnak  -60.00 USD
+"#;
+        let result = parse(source);
+        let diagnostics = similar_account_diagnostics(source, &result, &opened_accounts(&result));
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Account Expenses:Restuarant is never opened; did you mean Expenses:Restaurant?"
+        );
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(4, 2), Position::new(4, 21))
+        );
+        assert_eq!(
+            diagnostics[0].data.as_ref().unwrap()["suggestion"],
+            "Expenses:Restaurant"
+        );
+        assert_eq!(
+            diagnostics[1].range,
+            Range::new(Position::new(8, 19), Position::new(8, 30))
+        );
+    }
+
+    #[test]
+    fn test_document_diagnostic_report_result_ids() {
+        let source = "2024-01-01 open\n";
+        let diagnostics = parse_errors_to_diagnostics(&parse(source), source);
+        assert!(!diagnostics.is_empty());
+
+        let DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(full)) =
+            document_diagnostic_report(diagnostics.clone(), None)
+        else {
+            panic!("expected a full report");
+        };
+        let report = full.full_document_diagnostic_report;
+        assert_eq!(report.items, diagnostics);
+        let result_id = report.result_id.unwrap();
+
+        // Asking again with the same result ID gets an unchanged report
+        assert!(matches!(
+            document_diagnostic_report(diagnostics, Some(&result_id)),
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Unchanged(_))
+        ));
+
+        // Fixing the error changes the result ID
+        assert!(matches!(
+            document_diagnostic_report(Vec::new(), Some(&result_id)),
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(_))
+        ));
+        assert_ne!(diagnostics_result_id(&[]), result_id);
+    }
+
+    #[test]
+    fn test_workspace_document_report() {
+        let uri: Uri = "file:///ledger/accounts.beancount".parse().unwrap();
+        let result_id = diagnostics_result_id(&[]);
+
+        let WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) =
+            workspace_document_report(uri.clone(), Some(3), Vec::new(), Some(&result_id))
+        else {
+            panic!("expected an unchanged report");
+        };
+        assert_eq!(unchanged.uri, uri);
+        assert_eq!(unchanged.version, Some(3));
+
+        assert!(matches!(
+            workspace_document_report(uri, None, Vec::new(), Some("stale")),
+            WorkspaceDocumentDiagnosticReport::Full(_)
+        ));
+    }
 }
@@ -4,12 +4,19 @@
 //! - Negative amounts: red
 //! - Positive amounts: green
 //! - Zero amounts: gray
+//! - `color: "#rrggbb"` metadata on `commodity` and `open` directives: the
+//!   color itself, editable through the client's color picker
+//!
+//! The per-commodity and per-account colors are also available through
+//! [`commodity_colors`] and [`account_colors`] so other views can reuse them.
+
+use std::collections::HashMap;
 
 use lsp_types::{
     Color, ColorInformation, ColorPresentation, ColorPresentationParams, DocumentColorParams,
-    Position, Range,
+    Position, Range, TextEdit,
 };
-use rustledger_core::Directive;
+use rustledger_core::{Directive, MetaValue, Metadata};
 use rustledger_parser::ParseResult;
 
 use super::utils::byte_offset_to_position;
@@ -38,6 +45,9 @@ const COLOR_ZERO: Color = Color {
     alpha: 1.0,
 };
 
+/// Metadata key holding a commodity's or account's display color.
+pub const COLOR_META_KEY: &str = "color";
+
 /// Handle a document color request.
 pub fn handle_document_color(
     _params: &DocumentColorParams,
@@ -93,6 +103,20 @@ pub fn handle_document_color(
                     colors.push(ColorInformation { range, color });
                 }
             }
+            Directive::Commodity(comm) => {
+                colors.extend(meta_color_information(
+                    source,
+                    spanned.span.start,
+                    &comm.meta,
+                ));
+            }
+            Directive::Open(open) => {
+                colors.extend(meta_color_information(
+                    source,
+                    spanned.span.start,
+                    &open.meta,
+                ));
+            }
             Directive::Price(price) => {
                 let (line, _) = byte_offset_to_position(source, spanned.span.start);
                 let line_text = source.lines().nth(line as usize).unwrap_or("");
@@ -117,10 +141,32 @@ pub fn handle_document_color(
 }
 
 /// Handle a color presentation request.
-/// This is called when the user wants to change a color (not really applicable for amounts).
-pub fn handle_color_presentation(params: &ColorPresentationParams) -> Vec<ColorPresentation> {
-    // We don't support changing colors - amounts are data, not colors
-    // Just return the current representation
+///
+/// This is called when the user picks a new color. Hex colors in metadata are
+/// rewritten to the picked color; amount colors cannot be changed (amounts are
+/// data, not colors), so only a label is returned for them.
+pub fn handle_color_presentation(
+    params: &ColorPresentationParams,
+    source: &str,
+) -> Vec<ColorPresentation> {
+    let current = source
+        .lines()
+        .nth(params.range.start.line as usize)
+        .and_then(|line| {
+            line.get(params.range.start.character as usize..params.range.end.character as usize)
+        });
+    if current.is_some_and(|text| parse_hex_color(text).is_some()) {
+        let hex = format_hex_color(&params.color);
+        return vec![ColorPresentation {
+            label: hex.clone(),
+            text_edit: Some(TextEdit {
+                range: params.range,
+                new_text: hex,
+            }),
+            additional_text_edits: None,
+        }];
+    }
+
     let label = if params.color.red > 0.5 && params.color.green < 0.5 {
         "Negative amount"
     } else if params.color.green > 0.5 {
@@ -136,6 +182,115 @@ pub fn handle_color_presentation(params: &ColorPresentationParams) -> Vec<ColorP
     }]
 }
 
+/// Colors assigned to commodities through `color` metadata.
+pub fn commodity_colors(parse_result: &ParseResult) -> HashMap<String, Color> {
+    parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned| match &spanned.value {
+            Directive::Commodity(comm) => {
+                Some((comm.currency.to_string(), meta_color(&comm.meta)?))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Colors assigned to accounts through `color` metadata on `open` directives.
+pub fn account_colors(parse_result: &ParseResult) -> HashMap<String, Color> {
+    parse_result
+        .directives
+        .iter()
+        .filter_map(|spanned| match &spanned.value {
+            Directive::Open(open) => Some((open.account.to_string(), meta_color(&open.meta)?)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parse a `#rgb`, `#rrggbb` or `#rrggbbaa` hex color.
+pub fn parse_hex_color(text: &str) -> Option<Color> {
+    let hex = text.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize, width: usize| -> Option<f32> {
+        let value = u8::from_str_radix(hex.get(i * width..(i + 1) * width)?, 16).ok()?;
+        // Short form repeats each digit (#f80 is #ff8800)
+        let value = if width == 1 { value * 17 } else { value };
+        Some(f32::from(value) / 255.0)
+    };
+
+    let (width, has_alpha) = match hex.len() {
+        3 => (1, false),
+        6 => (2, false),
+        8 => (2, true),
+        _ => return None,
+    };
+    Some(Color {
+        red: channel(0, width)?,
+        green: channel(1, width)?,
+        blue: channel(2, width)?,
+        alpha: if has_alpha { channel(3, width)? } else { 1.0 },
+    })
+}
+
+/// Format a color as `#rrggbb`, or `#rrggbbaa` when not fully opaque.
+pub fn format_hex_color(color: &Color) -> String {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut hex = format!(
+        "#{:02x}{:02x}{:02x}",
+        byte(color.red),
+        byte(color.green),
+        byte(color.blue)
+    );
+    if byte(color.alpha) != 255 {
+        hex.push_str(&format!("{:02x}", byte(color.alpha)));
+    }
+    hex
+}
+
+/// The hex color in a directive's `color` metadata, if any.
+fn meta_color(meta: &Metadata) -> Option<Color> {
+    match meta.get(COLOR_META_KEY)? {
+        MetaValue::String(value) => parse_hex_color(value),
+        _ => None,
+    }
+}
+
+/// Color information for the `color` metadata of a directive starting at
+/// `start` (a byte offset).
+fn meta_color_information(source: &str, start: usize, meta: &Metadata) -> Option<ColorInformation> {
+    let color = meta_color(meta)?;
+    let (start_line, _) = byte_offset_to_position(source, start);
+
+    // Metadata lines follow the directive line and are indented
+    for (offset, line_text) in source.lines().skip(start_line as usize + 1).enumerate() {
+        if !line_text.starts_with([' ', '\t']) {
+            break;
+        }
+        let trimmed = line_text.trim_start();
+        let Some(value) = trimmed
+            .strip_prefix(COLOR_META_KEY)
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        else {
+            continue;
+        };
+        let quote = line_text.len() - value.len() + value.find('"')?;
+        let end = quote + 1 + line_text[quote + 1..].find('"')?;
+        let line = start_line + 1 + offset as u32;
+        return Some(ColorInformation {
+            range: Range {
+                start: Position::new(line, (quote + 1) as u32),
+                end: Position::new(line, end as u32),
+            },
+            color,
+        });
+    }
+
+    None
+}
+
 /// Find the range of an amount in a line.
 fn find_amount_range(line: &str, amount_str: &str, line_num: u32) -> Option<Range> {
     // Look for the amount pattern (may have negative sign)
@@ -204,6 +359,68 @@ mod tests {
         assert!(colors[1].color.red < 0.5);
     }
 
+    #[test]
+    fn test_document_color_metadata() {
+        let source = r##"2024-01-01 commodity USD
+  name: "US Dollar"
+  color: "#ff8000"
+2024-01-01 open Assets:Bank
+  color: "#0f0"
+2024-01-01 open Assets:Cash
+  color: "not a color"
+"##;
+        let result = parse(source);
+        let params = DocumentColorParams {
+            text_document: lsp_types::TextDocumentIdentifier {
+                uri: "file:///test.beancount".parse().unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let colors = handle_document_color(&params, source, &result).unwrap();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].range.start, Position::new(2, 10));
+        assert_eq!(colors[0].range.end, Position::new(2, 17));
+        assert_eq!(format_hex_color(&colors[0].color), "#ff8000");
+        assert_eq!(colors[1].range.start, Position::new(4, 10));
+
+        let commodities = commodity_colors(&result);
+        assert_eq!(format_hex_color(&commodities["USD"]), "#ff8000");
+        let accounts = account_colors(&result);
+        assert_eq!(format_hex_color(&accounts["Assets:Bank"]), "#00ff00");
+        assert!(!accounts.contains_key("Assets:Cash"));
+
+        // Picking a new color rewrites the hex value
+        let presentation = handle_color_presentation(
+            &ColorPresentationParams {
+                text_document: params.text_document.clone(),
+                color: Color {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 1.0,
+                    alpha: 1.0,
+                },
+                range: colors[0].range,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+            source,
+        );
+        let edit = presentation[0].text_edit.as_ref().unwrap();
+        assert_eq!(edit.new_text, "#0000ff");
+        assert_eq!(edit.range, colors[0].range);
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert!(parse_hex_color("#12345").is_none());
+        assert!(parse_hex_color("ff0000").is_none());
+        assert!(parse_hex_color("#gg0000").is_none());
+        let color = parse_hex_color("#ff000080").unwrap();
+        assert_eq!(format_hex_color(&color), "#ff000080");
+    }
+
     #[test]
     fn test_document_color_balance() {
         let source = r#"2024-01-31 balance Assets:Bank 100 USD
@@ -4,6 +4,8 @@
 //! - rledger.insertDate: Insert today's date
 //! - rledger.sortTransactions: Sort transactions by date
 //! - rledger.alignAmounts: Align amounts in a region
+//!
+//! Report commands are in [`super::virtual_documents`].
 
 use chrono::Local;
 use lsp_types::{ExecuteCommandParams, TextEdit, Uri, WorkspaceEdit};
@@ -0,0 +1,381 @@
+//! Workspace file operation handlers.
+//!
+//! Keeps `include` directives pointing at the right files when ledger files
+//! are moved around from the editor:
+//! - `workspace/willRenameFiles`: rewrites the paths of includes that
+//!   resolve to a renamed file or folder (or that are relative to a moved
+//!   document)
+//! - `workspace/didDeleteFiles`: the including documents are re-validated,
+//!   and [`missing_include_diagnostics`] flags includes whose file is gone
+
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, FileOperationFilter, FileOperationPattern,
+    FileOperationPatternKind, FileOperationRegistrationOptions, Position, Range, RenameFilesParams,
+    TextEdit, Uri, WorkspaceEdit,
+};
+use rustledger_parser::{ParseResult, Span};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use super::utils::LineIndex;
+
+/// Glob matching ledger files.
+const LEDGER_GLOB: &str = "**/*.{beancount,bean}";
+
+/// The file operations the server wants to hear about: ledger files, and
+/// folders (which may contain included files).
+pub fn registration_options() -> FileOperationRegistrationOptions {
+    let filter = |glob: &str, matches| FileOperationFilter {
+        scheme: Some("file".to_string()),
+        pattern: FileOperationPattern {
+            glob: glob.to_string(),
+            matches: Some(matches),
+            options: None,
+        },
+    };
+    FileOperationRegistrationOptions {
+        filters: vec![
+            filter(LEDGER_GLOB, FileOperationPatternKind::File),
+            filter("**/*", FileOperationPatternKind::Folder),
+        ],
+    }
+}
+
+/// Handle a workspace/willRenameFiles request.
+///
+/// `documents` are the ledger files whose includes should be kept up to
+/// date, as `(path, text)` pairs. Relative includes stay relative and
+/// absolute ones stay absolute.
+#[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
+pub fn handle_will_rename_files(
+    params: &RenameFilesParams,
+    documents: &[(PathBuf, String)],
+) -> Option<WorkspaceEdit> {
+    let renames: Vec<(PathBuf, PathBuf)> = params
+        .files
+        .iter()
+        .filter_map(|file| {
+            Some((
+                file_uri_to_path(&file.old_uri)?,
+                file_uri_to_path(&file.new_uri)?,
+            ))
+        })
+        .collect();
+    if renames.is_empty() {
+        return None;
+    }
+
+    let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+    for (path, source) in documents {
+        let Some(base_dir) = path.parent() else {
+            continue;
+        };
+        let new_path = apply_renames(path, &renames);
+        let new_base_dir = new_path
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(base_dir);
+
+        let parse_result = rustledger_parser::parse(source);
+        let line_index = LineIndex::new(source);
+        let mut edits = Vec::new();
+
+        for (include, span) in &parse_result.includes {
+            if is_glob(include) {
+                continue;
+            }
+            let target = normalize(&base_dir.join(include));
+            let new_target = apply_renames(&target, &renames);
+            if new_target.is_none() && new_path.is_none() {
+                continue;
+            }
+            let new_target = new_target.unwrap_or(target);
+
+            let new_include = if Path::new(include).is_absolute() {
+                new_target.to_string_lossy().into_owned()
+            } else {
+                relative_path(new_base_dir, &new_target)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            if new_include == *include {
+                continue;
+            }
+
+            if let Some((start, end)) = include_value_range(source, span) {
+                edits.push(TextEdit {
+                    range: Range {
+                        start: to_position(&line_index, start),
+                        end: to_position(&line_index, end),
+                    },
+                    new_text: new_include,
+                });
+            }
+        }
+
+        if !edits.is_empty() {
+            if let Some(uri) = path_to_uri(path) {
+                changes.insert(uri, edits);
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        return None;
+    }
+
+    Some(WorkspaceEdit {
+        changes: Some(changes),
+        document_changes: None,
+        change_annotations: None,
+    })
+}
+
+/// Diagnostics for `include` directives whose file does not exist.
+///
+/// `path` is the document's location; glob includes are not checked.
+pub fn missing_include_diagnostics(
+    path: &Path,
+    source: &str,
+    parse_result: &ParseResult,
+) -> Vec<Diagnostic> {
+    let Some(base_dir) = path.parent() else {
+        return Vec::new();
+    };
+    let line_index = LineIndex::new(source);
+
+    parse_result
+        .includes
+        .iter()
+        .filter(|(include, _)| !is_glob(include) && !base_dir.join(include).exists())
+        .map(|(include, span)| {
+            let (start, end) = include_value_range(source, span).unwrap_or((span.start, span.end));
+            Diagnostic {
+                range: Range {
+                    start: to_position(&line_index, start),
+                    end: to_position(&line_index, end),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                source: Some("rustledger".to_string()),
+                message: format!("Included file not found: {include}"),
+                related_information: None,
+                tags: None,
+                code_description: None,
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// The files included by a document, resolved against its directory.
+///
+/// Glob includes are skipped.
+pub fn resolved_includes(path: &Path, parse_result: &ParseResult) -> Vec<PathBuf> {
+    let Some(base_dir) = path.parent() else {
+        return Vec::new();
+    };
+    parse_result
+        .includes
+        .iter()
+        .filter(|(include, _)| !is_glob(include))
+        .map(|(include, _)| normalize(&base_dir.join(include)))
+        .collect()
+}
+
+/// Where `path` ends up after the renames, if any of them affects it.
+///
+/// A folder rename moves everything below the folder.
+fn apply_renames(path: &Path, renames: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    renames.iter().find_map(|(old, new)| {
+        let rest = path.strip_prefix(old).ok()?;
+        Some(if rest.as_os_str().is_empty() {
+            new.clone()
+        } else {
+            new.join(rest)
+        })
+    })
+}
+
+/// Byte range of the path between the quotes of an include directive.
+fn include_value_range(source: &str, span: &Span) -> Option<(usize, usize)> {
+    let text = source.get(span.start..span.end)?;
+    let open = text.find('"')? + 1;
+    let close = open + text[open..].find('"')?;
+    Some((span.start + open, span.start + close))
+}
+
+fn to_position(line_index: &LineIndex, offset: usize) -> Position {
+    let (line, col) = line_index.offset_to_position(offset);
+    Position::new(line, col)
+}
+
+/// Whether an include path is a glob pattern.
+fn is_glob(include: &str) -> bool {
+    include.contains(['*', '?', '['])
+}
+
+/// Lexically resolve `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The path of `target` relative to the directory `base`.
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base = normalize(base);
+    let base: Vec<_> = base.components().collect();
+    let target = normalize(target);
+    let target: Vec<_> = target.components().collect();
+
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Convert a `file://` URI string to a path.
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    uri.strip_prefix("file://").map(PathBuf::from)
+}
+
+/// Convert a path to a `file://` URI.
+fn path_to_uri(path: &Path) -> Option<Uri> {
+    format!("file://{}", path.display()).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::FileRename;
+
+    fn rename(old: &str, new: &str) -> RenameFilesParams {
+        RenameFilesParams {
+            files: vec![FileRename {
+                old_uri: format!("file://{old}"),
+                new_uri: format!("file://{new}"),
+            }],
+        }
+    }
+
+    /// New texts of the edits for `path`, in order.
+    #[allow(clippy::mutable_key_type)]
+    fn edits_for(edit: &WorkspaceEdit, path: &str) -> Vec<String> {
+        let uri: Uri = format!("file://{path}").parse().unwrap();
+        edit.changes.as_ref().unwrap()[&uri]
+            .iter()
+            .map(|e| e.new_text.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_included_file() {
+        let main = "include \"accounts.beancount\"\ninclude \"2024/txns.beancount\"\n";
+        let documents = vec![(PathBuf::from("/ledger/main.beancount"), main.to_string())];
+
+        let edit = handle_will_rename_files(
+            &rename(
+                "/ledger/2024/txns.beancount",
+                "/ledger/archive/2024.beancount",
+            ),
+            &documents,
+        )
+        .unwrap();
+
+        assert_eq!(
+            edits_for(&edit, "/ledger/main.beancount"),
+            ["archive/2024.beancount"]
+        );
+        let uri: Uri = "file:///ledger/main.beancount".parse().unwrap();
+        let range = edit.changes.unwrap()[&uri][0].range;
+        assert_eq!(range.start, Position::new(1, 9));
+        assert_eq!(range.end, Position::new(1, 28));
+    }
+
+    #[test]
+    fn test_rename_folder_and_unrelated_file() {
+        let main = "include \"2024/txns.beancount\"\n";
+        let documents = vec![(PathBuf::from("/ledger/main.beancount"), main.to_string())];
+
+        let edit =
+            handle_will_rename_files(&rename("/ledger/2024", "/ledger/years/2024"), &documents)
+                .unwrap();
+        assert_eq!(
+            edits_for(&edit, "/ledger/main.beancount"),
+            ["years/2024/txns.beancount"]
+        );
+
+        assert!(
+            handle_will_rename_files(
+                &rename("/ledger/other.beancount", "/x.beancount"),
+                &documents
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rename_including_document() {
+        // Moving the including file keeps its relative includes pointing at
+        // the same files
+        let main = "include \"accounts.beancount\"\ninclude \"/abs/prices.beancount\"\n";
+        let documents = vec![(PathBuf::from("/ledger/main.beancount"), main.to_string())];
+
+        let edit = handle_will_rename_files(
+            &rename("/ledger/main.beancount", "/ledger/books/main.beancount"),
+            &documents,
+        )
+        .unwrap();
+        assert_eq!(
+            edits_for(&edit, "/ledger/main.beancount"),
+            ["../accounts.beancount"]
+        );
+    }
+
+    #[test]
+    fn test_missing_include_diagnostics() {
+        let dir = std::env::temp_dir().join(format!("rledger-lsp-includes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("present.beancount"), "").unwrap();
+
+        let source =
+            "include \"present.beancount\"\ninclude \"deleted.beancount\"\ninclude \"*.bean\"\n";
+        let parse_result = rustledger_parser::parse(source);
+        let diagnostics =
+            missing_include_diagnostics(&dir.join("main.beancount"), source, &parse_result);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 9));
+        assert!(diagnostics[0].message.contains("deleted.beancount"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/a/b"), Path::new("/a/c/d.bean")),
+            PathBuf::from("../c/d.bean")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a"), Path::new("/a/./b/../c.bean")),
+            PathBuf::from("c.bean")
+        );
+    }
+}
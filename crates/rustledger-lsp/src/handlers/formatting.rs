@@ -10,15 +10,14 @@ use rustledger_core::Directive;
 use rustledger_parser::ParseResult;
 
 use super::utils::byte_offset_to_position;
-
-/// Default column for amount alignment.
-const AMOUNT_COLUMN: usize = 50;
+use crate::settings::FormattingSettings;
 
 /// Handle a document formatting request.
 pub fn handle_formatting(
     _params: &DocumentFormattingParams,
     source: &str,
     parse_result: &ParseResult,
+    style: &FormattingSettings,
 ) -> Option<Vec<TextEdit>> {
     let mut edits = Vec::new();
     let lines: Vec<&str> = source.lines().collect();
@@ -32,7 +31,7 @@ pub fn handle_formatting(
                 let posting_line = start_line + 1 + i as u32;
 
                 if let Some(line) = lines.get(posting_line as usize) {
-                    if let Some(edit) = format_posting_line(line, posting_line, posting) {
+                    if let Some(edit) = format_posting_line(line, posting_line, posting, style) {
                         edits.push(edit);
                     }
                 }
@@ -87,6 +86,7 @@ fn format_posting_line(
     line: &str,
     line_num: u32,
     posting: &rustledger_core::Posting,
+    style: &FormattingSettings,
 ) -> Option<TextEdit> {
     let trimmed = line.trim();
 
@@ -100,7 +100,7 @@ fn format_posting_line(
 
     // Check if line starts with proper indentation
     let current_indent = line.len() - line.trim_start().len();
-    let expected_indent = 2;
+    let expected_indent = style.indent;
 
     // Build the formatted line
     let mut formatted = String::new();
@@ -118,10 +118,11 @@ fn format_posting_line(
             let curr_str = curr.to_string();
             let amount_str = format!("{} {}", num_str, curr_str);
 
-            // Calculate padding to align amount at AMOUNT_COLUMN
+            // Calculate padding to align amount at the configured column
             let current_len = expected_indent + account.len();
-            let padding = if current_len < AMOUNT_COLUMN - amount_str.len() {
-                AMOUNT_COLUMN - amount_str.len() - current_len
+            let amount_start = style.amount_column.saturating_sub(amount_str.len());
+            let padding = if current_len < amount_start {
+                amount_start - current_len
             } else {
                 2 // Minimum 2 spaces
             };
@@ -175,7 +176,7 @@ mod tests {
             work_done_progress_params: Default::default(),
         };
 
-        let edits = handle_formatting(&params, source, &result);
+        let edits = handle_formatting(&params, source, &result, &FormattingSettings::default());
         assert!(edits.is_some());
     }
 
@@ -191,11 +192,33 @@ mod tests {
             work_done_progress_params: Default::default(),
         };
 
-        let edits = handle_formatting(&params, source, &result);
+        let edits = handle_formatting(&params, source, &result, &FormattingSettings::default());
         assert!(edits.is_some());
 
         let edits = edits.unwrap();
         // Should have edit to replace tab
         assert!(edits.iter().any(|e| e.new_text.contains("  ")));
     }
+
+    #[test]
+    fn test_formatting_uses_configured_style() {
+        let source = "2024-01-01 * \"Test\"\n  Assets:Bank  100 USD\n  Income:Salary\n";
+        let result = parse(source);
+        let params = DocumentFormattingParams {
+            text_document: lsp_types::TextDocumentIdentifier {
+                uri: "file:///test.beancount".parse().unwrap(),
+            },
+            options: Default::default(),
+            work_done_progress_params: Default::default(),
+        };
+        let style = FormattingSettings {
+            amount_column: 30,
+            indent: 4,
+        };
+
+        let edits = handle_formatting(&params, source, &result, &style).unwrap();
+        let posting = edits.iter().find(|e| e.range.start.line == 1).unwrap();
+        assert!(posting.new_text.starts_with("    Assets:Bank"));
+        assert_eq!(posting.new_text.len(), 30);
+    }
 }
@@ -5,6 +5,7 @@
 
 pub mod utils;
 
+pub mod bql;
 pub mod call_hierarchy;
 pub mod code_actions;
 pub mod code_lens;
@@ -17,6 +18,7 @@ pub mod document_color;
 pub mod document_highlight;
 pub mod document_links;
 pub mod execute_command;
+pub mod file_operations;
 pub mod folding;
 pub mod formatting;
 pub mod hover;
@@ -31,4 +33,5 @@ pub mod semantic_tokens;
 pub mod signature_help;
 pub mod symbols;
 pub mod type_hierarchy;
+pub mod virtual_documents;
 pub mod workspace_symbols;
@@ -7,12 +7,14 @@ use rustledger_core::Directive;
 use rustledger_parser::ParseResult;
 
 use super::utils::byte_offset_to_position;
+use crate::settings::FormattingSettings;
 
 /// Handle a range formatting request.
 pub fn handle_range_formatting(
     params: &DocumentRangeFormattingParams,
     source: &str,
     parse_result: &ParseResult,
+    style: &FormattingSettings,
 ) -> Option<Vec<TextEdit>> {
     let range = params.range;
     let mut edits = Vec::new();
@@ -66,7 +68,9 @@ pub fn handle_range_formatting(
                 // Check if posting is within range
                 if posting_line >= range.start.line && posting_line <= range.end.line {
                     if let Some(line) = lines.get(posting_line as usize) {
-                        if let Some(edit) = format_posting_line(line, posting_line, posting) {
+                        if let Some(edit) =
+                            format_posting_line(line, posting_line, posting, style.indent)
+                        {
                             // Don't duplicate edits
                             if !edits.iter().any(|e| e.range.start.line == posting_line) {
                                 edits.push(edit);
@@ -96,6 +100,7 @@ fn format_posting_line(
     line: &str,
     line_num: u32,
     posting: &rustledger_core::Posting,
+    expected_indent: usize,
 ) -> Option<TextEdit> {
     let trimmed = line.trim();
 
@@ -106,7 +111,6 @@ fn format_posting_line(
 
     let account = posting.account.to_string();
     let current_indent = line.len() - line.trim_start().len();
-    let expected_indent = 2;
 
     // Only fix indentation issues
     if current_indent != expected_indent {
@@ -148,7 +152,8 @@ mod tests {
             work_done_progress_params: Default::default(),
         };
 
-        let edits = handle_range_formatting(&params, source, &result);
+        let edits =
+            handle_range_formatting(&params, source, &result, &FormattingSettings::default());
         assert!(edits.is_some());
     }
 }
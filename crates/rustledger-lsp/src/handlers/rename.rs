@@ -1,8 +1,8 @@
 //! Rename handler for refactoring accounts and currencies.
 //!
 //! Supports renaming:
-//! - Account names (updates all usages in the file)
-//! - Currency names (updates all usages in the file)
+//! - Account names (updates all usages in every open document)
+//! - Currency names (updates all usages in every open document)
 
 use lsp_types::{
     Position, PrepareRenameResponse, Range, RenameParams, TextDocumentPositionParams, TextEdit,
@@ -42,16 +42,22 @@ pub fn handle_prepare_rename(
     }
 }
 
-/// Handle a rename request.
-#[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
-pub fn handle_rename(
+/// A symbol being renamed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameTarget {
+    /// An account name.
+    Account(String),
+    /// A currency name.
+    Currency(String),
+}
+
+/// Find the account or currency a rename request points at.
+pub fn rename_target(
     params: &RenameParams,
     source: &str,
     parse_result: &ParseResult,
-) -> Option<WorkspaceEdit> {
+) -> Option<RenameTarget> {
     let position = params.text_document_position.position;
-    let new_name = &params.new_name;
-    let uri = params.text_document_position.text_document.uri.clone();
 
     let line_idx = position.line as usize;
     let lines: Vec<&str> = source.lines().collect();
@@ -60,16 +66,44 @@ pub fn handle_rename(
     // Get the word at the cursor position
     let (old_name, _, _) = get_word_at_position(line, position.character as usize)?;
 
-    // Collect all edits
-    let mut edits = Vec::new();
-
     if is_account_like(&old_name) {
-        // Rename account
-        collect_account_rename_edits(source, parse_result, &old_name, new_name, &mut edits);
+        Some(RenameTarget::Account(old_name))
     } else if is_currency_like(&old_name, parse_result) {
-        // Rename currency
-        collect_currency_rename_edits(source, parse_result, &old_name, new_name, &mut edits);
+        Some(RenameTarget::Currency(old_name))
+    } else {
+        None
+    }
+}
+
+/// Collect the edits renaming `target` to `new_name` in one document.
+pub fn rename_edits(
+    target: &RenameTarget,
+    new_name: &str,
+    source: &str,
+    parse_result: &ParseResult,
+) -> Vec<TextEdit> {
+    let mut edits = Vec::new();
+    match target {
+        RenameTarget::Account(old_name) => {
+            collect_account_rename_edits(source, parse_result, old_name, new_name, &mut edits);
+        }
+        RenameTarget::Currency(old_name) => {
+            collect_currency_rename_edits(source, parse_result, old_name, new_name, &mut edits);
+        }
     }
+    edits
+}
+
+/// Handle a rename request within a single document.
+#[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
+pub fn handle_rename(
+    params: &RenameParams,
+    source: &str,
+    parse_result: &ParseResult,
+) -> Option<WorkspaceEdit> {
+    let uri = params.text_document_position.text_document.uri.clone();
+    let target = rename_target(params, source, parse_result)?;
+    let edits = rename_edits(&target, &params.new_name, source, parse_result);
 
     if edits.is_empty() {
         return None;
@@ -360,4 +394,31 @@ mod tests {
         // Should have 2 edits: one for open, one for posting
         assert_eq!(edits.len(), 2);
     }
+
+    #[test]
+    fn test_rename_edits_in_other_document() {
+        let source = "2024-01-01 open Assets:Bank USD\n";
+        let result = parse(source);
+        let uri: lsp_types::Uri = "file:///main.beancount".parse().unwrap();
+        let params = RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: lsp_types::TextDocumentIdentifier { uri },
+                position: Position::new(0, 16), // On "Assets:Bank"
+            },
+            new_name: "Assets:Checking".to_string(),
+            work_done_progress_params: Default::default(),
+        };
+        let target = rename_target(&params, source, &result).unwrap();
+        assert_eq!(target, RenameTarget::Account("Assets:Bank".to_string()));
+
+        // The target found in one document applies to the others
+        let other = r#"2024-01-15 * "Coffee"
+  Assets:Bank  -5.00 USD
+  Expenses:Food
+"#;
+        let edits = rename_edits(&target, "Assets:Checking", other, &parse(other));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position::new(1, 2));
+        assert_eq!(edits[0].new_text, "Assets:Checking");
+    }
 }
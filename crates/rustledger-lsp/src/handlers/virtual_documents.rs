@@ -0,0 +1,443 @@
+//! Read-only virtual documents for registers and query results.
+//!
+//! Reports are addressed with custom URI schemes so editors can show them
+//! as ordinary (read-only) buffers:
+//! - `rustledger-register://Assets/Bank` - register of `Assets:Bank`
+//! - `rustledger-query:SELECT%20account` - result table of a BQL query
+//!
+//! The `rledger.openRegister` and `rledger.openQuery` commands return the
+//! URI and rendered text of a report. Clients that fetch content lazily can
+//! ask for it with the `rledger/virtualDocument` request, and are sent
+//! `rledger/virtualDocumentChanged` when an open report goes stale.
+
+use lsp_types::{ExecuteCommandParams, Uri};
+use rustledger_booking::interpolate;
+use rustledger_core::{Decimal, Directive, sort_directives};
+use rustledger_parser::ParseResult;
+use rustledger_query::{Executor, QueryResult, Value, parse as parse_query};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// URI scheme for account registers.
+pub const REGISTER_SCHEME: &str = "rustledger-register";
+
+/// URI scheme for BQL query results.
+pub const QUERY_SCHEME: &str = "rustledger-query";
+
+/// Request for the text of a virtual document.
+pub const VIRTUAL_DOCUMENT_REQUEST: &str = "rledger/virtualDocument";
+
+/// Notification carrying the re-rendered text of an open virtual document.
+pub const VIRTUAL_DOCUMENT_CHANGED: &str = "rledger/virtualDocumentChanged";
+
+/// Commands that open virtual documents.
+pub const COMMANDS: &[&str] = &["rledger.openRegister", "rledger.openQuery"];
+
+/// Width of the description column in registers.
+const DESCRIPTION_WIDTH: usize = 40;
+
+/// A report served as a virtual document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VirtualDocument {
+    /// Register of an account and its sub-accounts.
+    Register(String),
+    /// Result of a BQL query.
+    Query(String),
+}
+
+impl VirtualDocument {
+    /// Parse a virtual document URI.
+    pub fn from_uri(uri: &Uri) -> Option<Self> {
+        let uri = uri.as_str();
+        if let Some(rest) = uri
+            .strip_prefix(REGISTER_SCHEME)
+            .and_then(|r| r.strip_prefix("://"))
+        {
+            let account: Vec<String> = rest
+                .trim_end_matches('/')
+                .split('/')
+                .map(percent_decode)
+                .collect::<Option<_>>()?;
+            if account.iter().any(String::is_empty) {
+                return None;
+            }
+            return Some(Self::Register(account.join(":")));
+        }
+        if let Some(rest) = uri
+            .strip_prefix(QUERY_SCHEME)
+            .and_then(|r| r.strip_prefix(':'))
+        {
+            let query = percent_decode(rest)?;
+            if query.trim().is_empty() {
+                return None;
+            }
+            return Some(Self::Query(query));
+        }
+        None
+    }
+
+    /// The URI addressing this document.
+    pub fn uri(&self) -> Uri {
+        let uri = match self {
+            Self::Register(account) => {
+                let path: Vec<String> = account.split(':').map(percent_encode).collect();
+                format!("{REGISTER_SCHEME}://{}", path.join("/"))
+            }
+            Self::Query(query) => format!("{QUERY_SCHEME}:{}", percent_encode(query)),
+        };
+        uri.parse().expect("percent-encoded URI is valid")
+    }
+
+    /// Render the document against the ledger.
+    pub fn render(&self, directives: &[Directive]) -> String {
+        match self {
+            Self::Register(account) => render_register(account, directives),
+            Self::Query(query) => render_query(query, directives),
+        }
+    }
+}
+
+/// Check whether a URI addresses a virtual document.
+pub fn is_virtual(uri: &Uri) -> bool {
+    let uri = uri.as_str();
+    uri.starts_with(&format!("{REGISTER_SCHEME}:")) || uri.starts_with(&format!("{QUERY_SCHEME}:"))
+}
+
+/// Collect the ledger's directives from all open documents.
+///
+/// Directives are sorted by date and transactions are interpolated where
+/// possible, so registers can show elided amounts.
+pub fn ledger_directives<'a>(
+    documents: impl IntoIterator<Item = &'a ParseResult>,
+) -> Vec<Directive> {
+    let mut directives: Vec<Directive> = documents
+        .into_iter()
+        .flat_map(|result| result.directives.iter().map(|s| s.value.clone()))
+        .collect();
+    for directive in &mut directives {
+        if let Directive::Transaction(txn) = directive {
+            if let Ok(result) = interpolate(txn) {
+                *txn = result.transaction;
+            }
+        }
+    }
+    sort_directives(&mut directives);
+    directives
+}
+
+/// Handle `rledger.openRegister` and `rledger.openQuery`.
+///
+/// Returns the document's URI and rendered text.
+pub fn handle_virtual_document_command(
+    params: &ExecuteCommandParams,
+    directives: &[Directive],
+) -> Option<serde_json::Value> {
+    let argument = params.arguments.first()?;
+    let document = match params.command.as_str() {
+        "rledger.openRegister" => {
+            let account = argument
+                .get("account")
+                .and_then(|v| v.as_str())
+                .or_else(|| argument.as_str())?;
+            VirtualDocument::Register(account.to_string())
+        }
+        "rledger.openQuery" => {
+            let query = argument
+                .get("query")
+                .and_then(|v| v.as_str())
+                .or_else(|| argument.as_str())?;
+            VirtualDocument::Query(query.to_string())
+        }
+        _ => return None,
+    };
+
+    Some(serde_json::json!({
+        "uri": document.uri().as_str(),
+        "text": document.render(directives),
+    }))
+}
+
+/// Render the register of an account and its sub-accounts.
+fn render_register(account: &str, directives: &[Directive]) -> String {
+    let prefix = format!("{account}:");
+    let mut balance: BTreeMap<String, Decimal> = BTreeMap::new();
+    let mut rows: Vec<[String; 4]> = Vec::new();
+
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        for posting in &txn.postings {
+            let name = posting.account.as_str();
+            if name != account && !name.starts_with(&prefix) {
+                continue;
+            }
+
+            let description = match &txn.payee {
+                Some(payee) => format!("{payee} | {}", txn.narration),
+                None => txn.narration.to_string(),
+            };
+            let (change, total) = match posting.units.as_ref().and_then(|u| u.as_amount()) {
+                Some(units) => {
+                    let total = balance.entry(units.currency.to_string()).or_default();
+                    *total += units.number;
+                    (units.to_string(), format!("{total} {}", units.currency))
+                }
+                None => (String::new(), String::new()),
+            };
+            rows.push([
+                format!("{} {}", txn.date, txn.flag),
+                truncate(&description, DESCRIPTION_WIDTH),
+                change,
+                total,
+            ]);
+        }
+    }
+
+    let mut out =
+        format!("; Register: {account}\n; Read-only view, regenerated when the ledger changes\n\n");
+    if rows.is_empty() {
+        out.push_str("; No postings\n");
+        return out;
+    }
+
+    let change_width = rows.iter().map(|r| r[2].chars().count()).max().unwrap_or(0);
+    let total_width = rows.iter().map(|r| r[3].chars().count()).max().unwrap_or(0);
+    for [date, description, change, total] in &rows {
+        let line = format!(
+            "{date}  {description:<DESCRIPTION_WIDTH$}  {change:>change_width$}  {total:>total_width$}"
+        );
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+
+    let _ = writeln!(out);
+    let balances: Vec<String> = balance
+        .iter()
+        .map(|(currency, number)| format!("{number} {currency}"))
+        .collect();
+    let _ = writeln!(out, "; Balance: {}", balances.join(", "));
+    out
+}
+
+/// Render the result of a BQL query as a text table.
+fn render_query(query: &str, directives: &[Directive]) -> String {
+    let mut out = String::new();
+    for line in query.trim().lines() {
+        let _ = writeln!(out, "; {line}");
+    }
+    out.push('\n');
+
+    let result = parse_query(query)
+        .map_err(|e| format!("Query parse error: {e}"))
+        .and_then(|q| {
+            Executor::new(directives)
+                .execute(&q)
+                .map_err(|e| format!("Query execution error: {e}"))
+        });
+    match result {
+        Ok(result) => out.push_str(&format_table(&result)),
+        Err(e) => {
+            let _ = writeln!(out, "; {e}");
+        }
+    }
+    out
+}
+
+/// Format a query result with aligned columns.
+fn format_table(result: &QueryResult) -> String {
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(format_value).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = result.columns.iter().map(|c| c.chars().count()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, width)| format!("{column:<width$}"))
+        .collect();
+    let _ = writeln!(out, "{}", header.join("  ").trim_end());
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    let _ = writeln!(out, "{}", rule.join("  "));
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        let _ = writeln!(out, "{}", line.join("  ").trim_end());
+    }
+    let _ = writeln!(out, "\n; {} rows", cells.len());
+    out
+}
+
+/// Format a query value for display.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Date(d) => d.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Amount(a) => a.to_string(),
+        Value::Position(p) => match &p.cost {
+            Some(cost) => format!("{} {{{} {}}}", p.units, cost.number, cost.currency),
+            None => p.units.to_string(),
+        },
+        Value::Inventory(inv) => inv
+            .positions()
+            .iter()
+            .map(|p| p.units.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::StringSet(set) => set.join(", "),
+        Value::Null => String::new(),
+    }
+}
+
+/// Truncate text to `width` characters, marking the cut with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Percent-encode everything but unreserved URI characters.
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+/// Decode percent escapes, failing on malformed escapes or invalid UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_parser::parse;
+
+    const LEDGER: &str = r#"2024-01-01 open Assets:Bank:Checking USD
+2024-01-01 open Expenses:Food USD
+2024-01-01 open Income:Salary USD
+
+2024-01-20 * "Cafe" "Coffee"
+  Assets:Bank:Checking  -5.00 USD
+  Expenses:Food
+
+2024-01-15 * "Salary"
+  Assets:Bank:Checking  100.00 USD
+  Income:Salary
+"#;
+
+    fn directives() -> Vec<Directive> {
+        let result = parse(LEDGER);
+        ledger_directives([&result])
+    }
+
+    #[test]
+    fn test_register_uri_round_trip() {
+        let document = VirtualDocument::Register("Assets:Bank".to_string());
+        let uri = document.uri();
+        assert_eq!(uri.as_str(), "rustledger-register://Assets/Bank");
+        assert!(is_virtual(&uri));
+        assert_eq!(VirtualDocument::from_uri(&uri), Some(document));
+    }
+
+    #[test]
+    fn test_query_uri_round_trip() {
+        let document = VirtualDocument::Query("SELECT account WHERE number > 0".to_string());
+        let uri = document.uri();
+        assert!(
+            uri.as_str()
+                .starts_with("rustledger-query:SELECT%20account")
+        );
+        assert_eq!(VirtualDocument::from_uri(&uri), Some(document));
+
+        let file: Uri = "file:///ledger.beancount".parse().unwrap();
+        assert!(!is_virtual(&file));
+        assert_eq!(VirtualDocument::from_uri(&file), None);
+    }
+
+    #[test]
+    fn test_render_register() {
+        let text = VirtualDocument::Register("Assets:Bank".to_string()).render(&directives());
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "; Register: Assets:Bank");
+        // Sorted by date with a running balance
+        assert!(lines[3].starts_with("2024-01-15 *  Salary"));
+        assert!(lines[3].ends_with("100.00 USD  100.00 USD"));
+        assert!(lines[4].starts_with("2024-01-20 *  Cafe | Coffee"));
+        assert!(lines[4].ends_with("-5.00 USD   95.00 USD"));
+        assert!(text.ends_with("; Balance: 95.00 USD\n"));
+    }
+
+    #[test]
+    fn test_render_register_interpolates() {
+        let text = VirtualDocument::Register("Expenses:Food".to_string()).render(&directives());
+        assert!(text.contains("5.00 USD"));
+    }
+
+    #[test]
+    fn test_render_query() {
+        let query = r#"SELECT account, sum(position) WHERE account ~ "Assets:" GROUP BY account"#;
+        let text = VirtualDocument::Query(query.to_string()).render(&directives());
+
+        assert!(text.starts_with(&format!("; {query}\n")));
+        assert!(text.contains("Assets:Bank:Checking"));
+        assert!(text.contains("95.00 USD"));
+        assert!(text.contains("; 1 rows"));
+    }
+
+    #[test]
+    fn test_render_query_error() {
+        let text = VirtualDocument::Query("SELEKT".to_string()).render(&directives());
+        assert!(text.contains("; Query parse error"));
+    }
+
+    #[test]
+    fn test_open_register_command() {
+        let params = ExecuteCommandParams {
+            command: "rledger.openRegister".to_string(),
+            arguments: vec![serde_json::json!({ "account": "Assets:Bank" })],
+            work_done_progress_params: Default::default(),
+        };
+        let value = handle_virtual_document_command(&params, &directives()).unwrap();
+        assert_eq!(value["uri"], "rustledger-register://Assets/Bank");
+        assert!(value["text"].as_str().unwrap().contains("Salary"));
+    }
+}
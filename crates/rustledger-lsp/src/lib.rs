@@ -32,14 +32,21 @@ pub mod db;
 pub mod handlers;
 pub mod main_loop;
 
+mod cancellation;
+mod progress;
 mod server;
+mod settings;
 mod snapshot;
+mod status;
 mod vfs;
+mod workspace;
 
 pub use main_loop::run_main_loop;
 pub use server::{Server, start_stdio};
+pub use settings::{FormattingSettings, Settings, ValidationLevel};
 pub use snapshot::Snapshot;
 pub use vfs::Vfs;
+pub use workspace::{Workspace, WorkspaceRoot};
 
 /// LSP server version.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -4,7 +4,13 @@
 //! - Notifications handled synchronously (critical for correctness)
 //! - Requests dispatched to threadpool with immutable snapshots
 //! - Revision counter enables cancellation of stale requests
+//! - Long operations report `$/progress` and check for `$/cancelRequest`
+//!   between steps (see the `cancellation` module)
 
+use crate::cancellation::Inbox;
+use crate::handlers::bql::{
+    bql_diagnostics, handle_bql_completion, handle_bql_hover, is_bql_file, query_regions, region_at,
+};
 use crate::handlers::call_hierarchy::{
     handle_incoming_calls, handle_outgoing_calls, handle_prepare_call_hierarchy,
 };
@@ -14,11 +20,17 @@ use crate::handlers::completion::handle_completion;
 use crate::handlers::completion_resolve::handle_completion_resolve;
 use crate::handlers::declaration::handle_goto_declaration;
 use crate::handlers::definition::handle_goto_definition;
-use crate::handlers::diagnostics::parse_errors_to_diagnostics;
+use crate::handlers::diagnostics::{
+    document_diagnostic_report, opened_accounts, parse_errors_to_diagnostics,
+    similar_account_diagnostics, workspace_document_report,
+};
 use crate::handlers::document_color::{handle_color_presentation, handle_document_color};
 use crate::handlers::document_highlight::handle_document_highlight;
 use crate::handlers::document_links::{handle_document_link_resolve, handle_document_links};
 use crate::handlers::execute_command::handle_execute_command;
+use crate::handlers::file_operations::{
+    handle_will_rename_files, missing_include_diagnostics, resolved_includes,
+};
 use crate::handlers::folding::handle_folding_ranges;
 use crate::handlers::formatting::handle_formatting;
 use crate::handlers::hover::handle_hover;
@@ -27,7 +39,7 @@ use crate::handlers::linked_editing::handle_linked_editing_range;
 use crate::handlers::on_type_formatting::handle_on_type_formatting;
 use crate::handlers::range_formatting::handle_range_formatting;
 use crate::handlers::references::handle_references;
-use crate::handlers::rename::{handle_prepare_rename, handle_rename};
+use crate::handlers::rename::{handle_prepare_rename, rename_edits, rename_target};
 use crate::handlers::selection_range::handle_selection_range;
 use crate::handlers::semantic_tokens::{
     handle_semantic_tokens, handle_semantic_tokens_delta, handle_semantic_tokens_range,
@@ -37,46 +49,59 @@ use crate::handlers::symbols::handle_document_symbols;
 use crate::handlers::type_hierarchy::{
     handle_prepare_type_hierarchy, handle_subtypes, handle_supertypes,
 };
+use crate::handlers::virtual_documents::{
+    COMMANDS as VIRTUAL_DOCUMENT_COMMANDS, VIRTUAL_DOCUMENT_CHANGED, VIRTUAL_DOCUMENT_REQUEST,
+    VirtualDocument, handle_virtual_document_command, is_virtual, ledger_directives,
+};
 use crate::handlers::workspace_symbols::handle_workspace_symbols;
+use crate::progress::{Progress, create_token};
+use crate::settings::{Settings, ValidationLevel};
 use crate::snapshot::bump_revision;
+use crate::status::LedgerStatus;
 use crate::vfs::Vfs;
+use crate::workspace::Workspace;
 use crossbeam_channel::{Receiver, Sender};
 use lsp_types::notification::{
-    DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
-    Notification, PublishDiagnostics,
+    Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles,
+    DidChangeWorkspaceFolders, DidCloseTextDocument, DidDeleteFiles, DidOpenTextDocument,
+    Notification, PublishDiagnostics, ShowMessage, WorkDoneProgressCancel,
 };
 use lsp_types::request::{
     CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare,
     CodeActionRequest, CodeActionResolveRequest, CodeLensRequest, CodeLensResolve,
-    ColorPresentationRequest, Completion, DocumentColor, DocumentHighlightRequest,
-    DocumentLinkRequest, DocumentLinkResolve, DocumentSymbolRequest, ExecuteCommand,
-    FoldingRangeRequest, Formatting, GotoDeclaration, GotoDefinition, HoverRequest, Initialize,
-    InlayHintRequest, InlayHintResolveRequest, LinkedEditingRange, OnTypeFormatting,
+    ColorPresentationRequest, Completion, DocumentColor, DocumentDiagnosticRequest,
+    DocumentHighlightRequest, DocumentLinkRequest, DocumentLinkResolve, DocumentSymbolRequest,
+    ExecuteCommand, FoldingRangeRequest, Formatting, GotoDeclaration, GotoDefinition, HoverRequest,
+    Initialize, InlayHintRequest, InlayHintResolveRequest, LinkedEditingRange, OnTypeFormatting,
     PrepareRenameRequest, RangeFormatting, References, Rename, Request, ResolveCompletionItem,
     SelectionRangeRequest, SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
     SemanticTokensRangeRequest, Shutdown, SignatureHelpRequest, TypeHierarchyPrepare,
-    TypeHierarchySubtypes, TypeHierarchySupertypes, WorkspaceSymbolRequest,
+    TypeHierarchySubtypes, TypeHierarchySupertypes, WillRenameFiles, WorkspaceDiagnosticRefresh,
+    WorkspaceDiagnosticRequest, WorkspaceSymbolRequest,
 };
 use lsp_types::{
     CallHierarchyIncomingCallsParams, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
-    CodeAction, CodeActionParams, CodeLens, CodeLensParams, ColorPresentationParams,
-    CompletionItem, CompletionParams, DiagnosticOptions, DiagnosticServerCapabilities,
-    DocumentColorParams, DocumentFormattingParams, DocumentHighlightParams, DocumentLink,
-    DocumentLinkParams, DocumentOnTypeFormattingParams, DocumentRangeFormattingParams,
-    DocumentSymbolParams, ExecuteCommandParams, FoldingRangeParams, GotoDefinitionParams,
-    HoverParams, InitializeParams, InitializeResult, InlayHint, InlayHintParams,
-    LinkedEditingRangeParams, PublishDiagnosticsParams, ReferenceParams, RenameParams,
+    ClientCapabilities, CodeAction, CodeActionParams, CodeLens, CodeLensParams,
+    ColorPresentationParams, CompletionItem, CompletionParams, DiagnosticOptions,
+    DiagnosticServerCapabilities, DocumentColorParams, DocumentDiagnosticParams,
+    DocumentFormattingParams, DocumentHighlightParams, DocumentLink, DocumentLinkParams,
+    DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, DocumentSymbolParams,
+    ExecuteCommandParams, FoldingRangeParams, GotoDefinitionParams, HoverParams, InitializeParams,
+    InitializeResult, InlayHint, InlayHintParams, LinkedEditingRangeParams, ProgressToken,
+    PublishDiagnosticsParams, ReferenceParams, RenameFilesParams, RenameParams,
     SelectionRangeParams, SemanticTokensDeltaParams, SemanticTokensParams,
     SemanticTokensRangeParams, ServerCapabilities, ServerInfo, SignatureHelpParams,
     TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind,
     TypeHierarchyPrepareParams, TypeHierarchySubtypesParams, TypeHierarchySupertypesParams, Uri,
-    WorkspaceSymbolParams,
+    WorkDoneProgressOptions, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+    WorkspaceDiagnosticReportResult, WorkspaceEdit, WorkspaceFolder, WorkspaceSymbolParams,
 };
 use parking_lot::RwLock;
 use rustledger_parser::{ParseResult, parse};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Convert a URI to a file path.
 #[cfg(not(windows))]
@@ -94,6 +119,9 @@ fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
         .map(PathBuf::from)
 }
 
+/// Error message for requests the client cancelled.
+const REQUEST_CANCELLED: &str = "Request cancelled";
+
 /// Events processed by the main loop.
 #[derive(Debug)]
 pub enum Event {
@@ -135,6 +163,24 @@ pub struct MainLoopState {
     pub diagnostics: HashMap<Uri, Vec<lsp_types::Diagnostic>>,
     /// Whether shutdown was requested.
     pub shutdown_requested: bool,
+    /// Client-configurable settings.
+    pub settings: Settings,
+    /// Workspace folders and their main ledgers.
+    pub workspace: Workspace,
+    /// Incoming messages and pending cancellations.
+    inbox: Inbox,
+    /// Whether the client accepts server-created progress tokens.
+    pub work_done_progress: bool,
+    /// Whether the client pulls diagnostics instead of receiving them.
+    pub pull_diagnostics: bool,
+    /// Whether the client can be asked to pull diagnostics again.
+    pub diagnostic_refresh: bool,
+    /// The request being handled, if any.
+    current_request: Option<lsp_server::RequestId>,
+    /// Virtual documents the client has open.
+    virtual_documents: HashMap<Uri, VirtualDocument>,
+    /// How long the last full re-validation took.
+    last_check: Option<Duration>,
 }
 
 /// Default empty parse result for missing documents.
@@ -144,13 +190,54 @@ fn empty_parse_result() -> Arc<ParseResult> {
 
 impl MainLoopState {
     /// Create a new main loop state.
-    pub fn new(sender: Sender<lsp_server::Message>) -> Self {
+    pub fn new(
+        receiver: Receiver<lsp_server::Message>,
+        sender: Sender<lsp_server::Message>,
+        settings: Settings,
+        workspace_folders: &[WorkspaceFolder],
+    ) -> Self {
         Self {
             vfs: Arc::new(RwLock::new(Vfs::new())),
             sender,
             diagnostics: HashMap::new(),
             shutdown_requested: false,
+            workspace: Workspace::new(workspace_folders, &settings),
+            settings,
+            inbox: Inbox::new(receiver),
+            work_done_progress: false,
+            pull_diagnostics: false,
+            diagnostic_refresh: false,
+            current_request: None,
+            virtual_documents: HashMap::new(),
+            last_check: None,
+        }
+    }
+
+    /// Start reporting progress, on `token` or a server-created token.
+    fn start_progress(&self, token: Option<ProgressToken>, name: &str, title: &str) -> Progress {
+        let token = token.or_else(|| {
+            self.work_done_progress
+                .then(|| create_token(&self.sender, name))
+        });
+        Progress::begin(&self.sender, token, title, true)
+    }
+
+    /// Check whether the current request or the progress was cancelled.
+    fn is_cancelled(&self, progress: &Progress) -> bool {
+        self.current_request
+            .as_ref()
+            .is_some_and(|id| self.inbox.is_request_cancelled(id))
+            || progress
+                .token()
+                .is_some_and(|token| self.inbox.is_progress_cancelled(token))
+    }
+
+    /// End progress reporting with a final message.
+    fn finish_progress(&self, progress: Progress, message: impl Into<String>) {
+        if let Some(token) = progress.token() {
+            self.inbox.complete_progress(token);
         }
+        progress.finish(message);
     }
 
     /// Get document text and cached parse result for a URI.
@@ -189,6 +276,14 @@ impl MainLoopState {
     fn handle_request(&mut self, req: lsp_server::Request) {
         let id = req.id.clone();
 
+        // Skip requests cancelled while they were queued
+        if self.inbox.is_request_cancelled(&id) {
+            self.inbox.complete_request(&id);
+            self.send_cancelled(id);
+            return;
+        }
+        self.current_request = Some(id.clone());
+
         // Dispatch based on method
         let result = match req.method.as_str() {
             Initialize::METHOD => self.handle_initialize(req),
@@ -236,11 +331,24 @@ impl MainLoopState {
             SignatureHelpRequest::METHOD => self.handle_signature_help_request(req),
             ExecuteCommand::METHOD => self.handle_execute_command_request(req),
             ResolveCompletionItem::METHOD => self.handle_completion_resolve_request(req),
+            WillRenameFiles::METHOD => self.handle_will_rename_files_request(req),
+            DocumentDiagnosticRequest::METHOD => self.handle_document_diagnostic_request(req),
+            WorkspaceDiagnosticRequest::METHOD => self.handle_workspace_diagnostic_request(req),
+            VIRTUAL_DOCUMENT_REQUEST => self.handle_virtual_document_request(req),
             _ => {
                 tracing::warn!("Unhandled request: {}", req.method);
                 Err(format!("Unhandled request: {}", req.method))
             }
         };
+        self.current_request = None;
+
+        // Don't send results the client no longer wants
+        let cancelled = self.inbox.is_request_cancelled(&id);
+        self.inbox.complete_request(&id);
+        if cancelled || result.as_ref().is_err_and(|msg| msg == REQUEST_CANCELLED) {
+            self.send_cancelled(id);
+            return;
+        }
 
         // Send response
         let response = match result {
@@ -267,9 +375,7 @@ impl MainLoopState {
 
         let capabilities = ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
-            diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
-                ..Default::default()
-            })),
+            diagnostic_provider: Some(diagnostic_capabilities()),
             ..Default::default()
         };
 
@@ -295,6 +401,17 @@ impl MainLoopState {
         let uri = &params.text_document_position.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
+        let position = params.text_document_position.position;
+        let regions = query_regions(uri, &text, &parse_result);
+        if let Some(index) = region_at(&regions, &text, position) {
+            let response =
+                handle_bql_completion(&regions[index], &text, position, &self.ledger_directives());
+            return serde_json::to_value(response).map_err(|e| e.to_string());
+        }
+        if is_bql_file(uri) {
+            return Ok(serde_json::Value::Null);
+        }
+
         let response = handle_completion(&params, &text, &parse_result);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
@@ -311,7 +428,13 @@ impl MainLoopState {
         let uri = &params.text_document_position_params.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
-        let response = handle_goto_definition(&params, &text, &parse_result, uri);
+        let response = handle_goto_definition(
+            &params,
+            &text,
+            &parse_result,
+            uri,
+            self.settings.definition_target,
+        );
 
         serde_json::to_value(response).map_err(|e| e.to_string())
     }
@@ -339,6 +462,13 @@ impl MainLoopState {
         let uri = &params.text_document_position_params.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
+        let position = params.text_document_position_params.position;
+        let regions = query_regions(uri, &text, &parse_result);
+        if let Some(index) = region_at(&regions, &text, position) {
+            let response = handle_bql_hover(&regions[index], &text, position);
+            return serde_json::to_value(response).map_err(|e| e.to_string());
+        }
+
         let response = handle_hover(&params, &text, &parse_result);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
@@ -446,7 +576,17 @@ impl MainLoopState {
 
         let (text, parse_result) = self.get_document_data(&uri);
 
-        let resolved = handle_code_action_resolve(action, &text, &parse_result, &uri);
+        // Other documents (such as an include file to move transactions
+        // to) are read from the editor if open, else from disk
+        let read_document = |uri: &Uri| {
+            let path = uri_to_path(uri)?;
+            self.vfs
+                .read()
+                .get_content(&path)
+                .or_else(|| std::fs::read_to_string(&path).ok())
+        };
+        let resolved =
+            handle_code_action_resolve(action, &text, &parse_result, &uri, read_document);
 
         serde_json::to_value(resolved).map_err(|e| e.to_string())
     }
@@ -459,18 +599,31 @@ impl MainLoopState {
         let params: WorkspaceSymbolParams =
             serde_json::from_value(req.params).map_err(|e| e.to_string())?;
 
-        // Collect all open documents with cached parse results
-        let mut vfs = self.vfs.write();
-        let documents: Vec<_> = vfs
-            .iter_with_parse()
-            .map(|(path, content, parse_result)| {
-                let uri_str = format!("file://{}", path.display());
-                let uri: Uri = uri_str
-                    .parse()
-                    .unwrap_or_else(|_| "file:///".parse().unwrap());
-                (uri, content, parse_result)
-            })
-            .collect();
+        // Collect all open documents, parsing any not parsed yet
+        let paths: Vec<_> = self.vfs.read().paths().cloned().collect();
+        let progress = self.start_progress(
+            params.work_done_progress_params.work_done_token.clone(),
+            "index",
+            "Indexing ledger",
+        );
+        let mut documents = Vec::with_capacity(paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            if self.is_cancelled(&progress) {
+                self.finish_progress(progress, "Cancelled");
+                return Err(REQUEST_CANCELLED.to_string());
+            }
+            progress.report(i, paths.len(), path.display().to_string());
+
+            let Some((content, parse_result)) = self.vfs.write().get_document_data(path) else {
+                continue;
+            };
+            let uri_str = format!("file://{}", path.display());
+            let uri: Uri = uri_str
+                .parse()
+                .unwrap_or_else(|_| "file:///".parse().unwrap());
+            documents.push((uri, content, parse_result));
+        }
+        self.finish_progress(progress, format!("Indexed {} files", documents.len()));
 
         let response = handle_workspace_symbols(&params, &documents);
 
@@ -494,14 +647,66 @@ impl MainLoopState {
     }
 
     /// Handle the textDocument/rename request.
+    #[allow(clippy::mutable_key_type)] // Uri is required as key by LSP WorkspaceEdit API
     fn handle_rename_request(&self, req: lsp_server::Request) -> Result<serde_json::Value, String> {
         let params: RenameParams = serde_json::from_value(req.params).map_err(|e| e.to_string())?;
 
         let uri = &params.text_document_position.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
+        let Some(target) = rename_target(&params, &text, &parse_result) else {
+            return Ok(serde_json::Value::Null);
+        };
 
-        let response = handle_rename(&params, &text, &parse_result);
+        let mut changes = HashMap::new();
+        let edits = rename_edits(&target, &params.new_name, &text, &parse_result);
+        if !edits.is_empty() {
+            changes.insert(uri.clone(), edits);
+        }
+
+        // Rename in every other open document, checking for cancellation
+        // between documents
+        let current = uri_to_path(uri);
+        let paths: Vec<_> = self
+            .vfs
+            .read()
+            .paths()
+            .filter(|path| current.as_ref() != Some(*path))
+            .cloned()
+            .collect();
+        let progress = self.start_progress(
+            params.work_done_progress_params.work_done_token.clone(),
+            "rename",
+            "Renaming",
+        );
+        for (i, path) in paths.iter().enumerate() {
+            if self.is_cancelled(&progress) {
+                self.finish_progress(progress, "Cancelled");
+                return Err(REQUEST_CANCELLED.to_string());
+            }
+            progress.report(i, paths.len(), path.display().to_string());
+
+            let Some((content, parse_result)) = self.vfs.write().get_document_data(path) else {
+                continue;
+            };
+            let edits = rename_edits(&target, &params.new_name, &content, &parse_result);
+            if edits.is_empty() {
+                continue;
+            }
+            let Ok(doc_uri) = format!("file://{}", path.display()).parse::<Uri>() else {
+                continue;
+            };
+            changes.insert(doc_uri, edits);
+        }
+        self.finish_progress(progress, format!("Renamed in {} files", changes.len()));
 
+        if changes.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        let response = WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        };
         serde_json::to_value(response).map_err(|e| e.to_string())
     }
 
@@ -516,7 +721,7 @@ impl MainLoopState {
         let uri = &params.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
-        let response = handle_formatting(&params, &text, &parse_result);
+        let response = handle_formatting(&params, &text, &parse_result, &self.settings.formatting);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
     }
@@ -548,7 +753,8 @@ impl MainLoopState {
         let uri = &params.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
-        let response = handle_range_formatting(&params, &text, &parse_result);
+        let response =
+            handle_range_formatting(&params, &text, &parse_result, &self.settings.formatting);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
     }
@@ -801,8 +1007,8 @@ impl MainLoopState {
         let params: ColorPresentationParams =
             serde_json::from_value(req.params).map_err(|e| e.to_string())?;
 
-        // Handle color presentation
-        let response = handle_color_presentation(&params);
+        let (text, _parse_result) = self.get_document_data(&params.text_document.uri);
+        let response = handle_color_presentation(&params, &text);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
     }
@@ -818,7 +1024,7 @@ impl MainLoopState {
         let uri = &params.text_document_position_params.text_document.uri;
         let (text, parse_result) = self.get_document_data(uri);
 
-        // Handle go-to-declaration (same as definition for Beancount)
+        // Declarations always resolve to the open/commodity directive
         let response = handle_goto_declaration(&params, &text, &parse_result, uri);
 
         serde_json::to_value(response).map_err(|e| e.to_string())
@@ -903,6 +1109,12 @@ impl MainLoopState {
         let params: ExecuteCommandParams =
             serde_json::from_value(req.params).map_err(|e| e.to_string())?;
 
+        // Reports are rendered against the whole ledger
+        if VIRTUAL_DOCUMENT_COMMANDS.contains(&params.command.as_str()) {
+            let response = handle_virtual_document_command(&params, &self.ledger_directives());
+            return Ok(response.unwrap_or(serde_json::Value::Null));
+        }
+
         // Try to get URI from command arguments first
         let uri_from_args: Option<Uri> = params
             .arguments
@@ -944,6 +1156,181 @@ impl MainLoopState {
         Ok(response.unwrap_or(serde_json::Value::Null))
     }
 
+    /// Handle the rledger/virtualDocument request.
+    fn handle_virtual_document_request(
+        &self,
+        req: lsp_server::Request,
+    ) -> Result<serde_json::Value, String> {
+        let uri: Uri = req
+            .params
+            .get("uri")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse().ok())
+            .ok_or("Missing uri")?;
+        let document = VirtualDocument::from_uri(&uri)
+            .ok_or_else(|| format!("Not a virtual document: {}", uri.as_str()))?;
+
+        Ok(serde_json::json!({
+            "text": document.render(&self.ledger_directives()),
+        }))
+    }
+
+    /// Directives of all open documents, for rendering reports.
+    fn ledger_directives(&self) -> Vec<rustledger_core::Directive> {
+        let paths: Vec<_> = self.vfs.read().paths().cloned().collect();
+        let parse_results: Vec<_> = paths
+            .iter()
+            .filter_map(|path| self.vfs.write().get_document_data(path))
+            .map(|(_, parse_result)| parse_result)
+            .collect();
+        ledger_directives(parse_results.iter().map(AsRef::as_ref))
+    }
+
+    /// Text of every ledger file the server knows of.
+    ///
+    /// These are the open documents plus the main ledger of every
+    /// workspace root and the files it includes, read from disk unless open.
+    fn ledger_files(&self) -> Vec<(PathBuf, String)> {
+        let vfs = self.vfs.read();
+        let mut files: Vec<(PathBuf, String)> = vfs
+            .paths()
+            .filter_map(|path| Some((path.clone(), vfs.get_content(path)?)))
+            .collect();
+        drop(vfs);
+
+        let mut seen: HashSet<PathBuf> = files.iter().map(|(path, _)| path.clone()).collect();
+        let mut pending: Vec<PathBuf> = self.workspace.journal_files(&self.settings);
+        for (path, text) in &files {
+            pending.extend(resolved_includes(path, &parse(text)));
+        }
+        while let Some(path) = pending.pop() {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            pending.extend(resolved_includes(&path, &parse(&text)));
+            files.push((path, text));
+        }
+
+        files
+    }
+
+    /// Handle the textDocument/diagnostic request.
+    fn handle_document_diagnostic_request(
+        &mut self,
+        req: lsp_server::Request,
+    ) -> Result<serde_json::Value, String> {
+        let params: DocumentDiagnosticParams =
+            serde_json::from_value(req.params).map_err(|e| e.to_string())?;
+        let uri = params.text_document.uri;
+
+        // Closed files are checked as they are on disk
+        let text = uri_to_path(&uri)
+            .and_then(|path| {
+                let content = self.vfs.read().get_content(&path);
+                content.or_else(|| std::fs::read_to_string(&path).ok())
+            })
+            .unwrap_or_default();
+        let diagnostics = self.compute_diagnostics(&uri, &text);
+        self.diagnostics.insert(uri, diagnostics.clone());
+
+        let report = document_diagnostic_report(diagnostics, params.previous_result_id.as_deref());
+
+        serde_json::to_value(report).map_err(|e| e.to_string())
+    }
+
+    /// Handle the workspace/diagnostic request.
+    ///
+    /// Reports every ledger file, open or not; files whose diagnostics did
+    /// not change since the client's previous pull are reported unchanged.
+    #[allow(clippy::mutable_key_type)] // Uri has interior mutability but is only compared here
+    fn handle_workspace_diagnostic_request(
+        &mut self,
+        req: lsp_server::Request,
+    ) -> Result<serde_json::Value, String> {
+        let params: WorkspaceDiagnosticParams =
+            serde_json::from_value(req.params).map_err(|e| e.to_string())?;
+        let previous: HashMap<Uri, String> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
+
+        let files = self.ledger_files();
+        // Clients pull repeatedly, so only report progress when asked to
+        let progress = Progress::begin(
+            &self.sender,
+            params.work_done_progress_params.work_done_token,
+            "Checking ledger",
+            true,
+        );
+        let total = files.len();
+        let mut items = Vec::with_capacity(total);
+        for (i, (path, text)) in files.into_iter().enumerate() {
+            if self.is_cancelled(&progress) {
+                self.finish_progress(progress, "Cancelled");
+                return Err(REQUEST_CANCELLED.to_string());
+            }
+            progress.report(i, total, path.display().to_string());
+
+            let Ok(uri) = format!("file://{}", path.display()).parse::<Uri>() else {
+                continue;
+            };
+            let version = self
+                .vfs
+                .read()
+                .get(&path)
+                .map(|doc| i64::from(doc.version()));
+            let diagnostics = self.compute_diagnostics(&uri, &text);
+            let previous_result_id = previous.get(&uri).map(String::as_str);
+            self.diagnostics.insert(uri.clone(), diagnostics.clone());
+            items.push(workspace_document_report(
+                uri,
+                version,
+                diagnostics,
+                previous_result_id,
+            ));
+        }
+        self.finish_progress(progress, format!("Checked {total} files"));
+
+        let report = WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items });
+
+        serde_json::to_value(report).map_err(|e| e.to_string())
+    }
+
+    /// Handle the workspace/willRenameFiles request.
+    fn handle_will_rename_files_request(
+        &self,
+        req: lsp_server::Request,
+    ) -> Result<serde_json::Value, String> {
+        let params: RenameFilesParams =
+            serde_json::from_value(req.params).map_err(|e| e.to_string())?;
+
+        let response = handle_will_rename_files(&params, &self.ledger_files());
+
+        serde_json::to_value(response).map_err(|e| e.to_string())
+    }
+
+    /// Send re-rendered text of open virtual documents to the client.
+    fn refresh_virtual_documents(&self) {
+        if self.virtual_documents.is_empty() {
+            return;
+        }
+        let directives = self.ledger_directives();
+        for (uri, document) in &self.virtual_documents {
+            let notif = lsp_server::Notification::new(
+                VIRTUAL_DOCUMENT_CHANGED.to_string(),
+                serde_json::json!({
+                    "uri": uri.as_str(),
+                    "text": document.render(&directives),
+                }),
+            );
+            self.send(lsp_server::Message::Notification(notif));
+        }
+    }
+
     /// Handle the completionItem/resolve request.
     fn handle_completion_resolve_request(
         &self,
@@ -999,6 +1386,32 @@ impl MainLoopState {
                     self.on_did_change_watched_files(params);
                 }
             }
+            DidDeleteFiles::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<lsp_types::DeleteFilesParams>(notif.params)
+                {
+                    self.on_did_delete_files(params);
+                }
+            }
+            DidChangeWorkspaceFolders::METHOD => {
+                if let Ok(params) = serde_json::from_value::<
+                    lsp_types::DidChangeWorkspaceFoldersParams,
+                >(notif.params)
+                {
+                    self.on_did_change_workspace_folders(params);
+                }
+            }
+            DidChangeConfiguration::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<lsp_types::DidChangeConfigurationParams>(notif.params)
+                {
+                    self.on_did_change_configuration(params);
+                }
+            }
+            Cancel::METHOD | WorkDoneProgressCancel::METHOD => {
+                // The request or progress already finished; cancellations
+                // that matter are picked up by the inbox while busy
+            }
             "initialized" => {
                 tracing::info!("Client initialized");
                 // Register for file watching after initialization
@@ -1022,6 +1435,14 @@ impl MainLoopState {
 
         tracing::info!("Document opened: {}", uri.as_str());
 
+        // Reports are read-only and not Beancount, so skip the VFS and diagnostics
+        if is_virtual(&uri) {
+            if let Some(document) = VirtualDocument::from_uri(&uri) {
+                self.virtual_documents.insert(uri, document);
+            }
+            return;
+        }
+
         // Store in VFS
         if let Some(path) = uri_to_path(&uri) {
             self.vfs.write().open(path, text.clone(), version);
@@ -1032,6 +1453,7 @@ impl MainLoopState {
 
         // Compute and publish diagnostics
         self.publish_diagnostics(&uri, &text);
+        self.send_status();
     }
 
     /// Handle textDocument/didChange notification.
@@ -1039,6 +1461,10 @@ impl MainLoopState {
         let uri = params.text_document.uri;
         let version = params.text_document.version;
 
+        if is_virtual(&uri) {
+            return;
+        }
+
         // For full sync, take the last change (which is the full content)
         if let Some(change) = params.content_changes.into_iter().last() {
             let text = change.text;
@@ -1055,6 +1481,10 @@ impl MainLoopState {
 
             // Recompute diagnostics
             self.publish_diagnostics(&uri, &text);
+
+            // Reports depend on the whole ledger
+            self.refresh_virtual_documents();
+            self.send_status();
         }
     }
 
@@ -1064,6 +1494,11 @@ impl MainLoopState {
 
         tracing::info!("Document closed: {}", uri.as_str());
 
+        if is_virtual(&uri) {
+            self.virtual_documents.remove(&uri);
+            return;
+        }
+
         // Remove from VFS
         if let Some(path) = uri_to_path(&uri) {
             self.vfs.write().close(&path);
@@ -1072,6 +1507,7 @@ impl MainLoopState {
         // Clear diagnostics
         self.diagnostics.remove(&uri);
         self.send_diagnostics(&uri, vec![]);
+        self.send_status();
     }
 
     /// Handle workspace/didChangeWatchedFiles notification.
@@ -1090,6 +1526,96 @@ impl MainLoopState {
         }
     }
 
+    /// Handle workspace/didDeleteFiles notification.
+    ///
+    /// Documents that included a deleted file get a diagnostic on the
+    /// dangling include.
+    fn on_did_delete_files(&mut self, params: lsp_types::DeleteFilesParams) {
+        tracing::info!("Files deleted: {} files", params.files.len());
+
+        bump_revision();
+        self.revalidate_open_documents();
+
+        // The journals usually hold the includes but need not be open
+        for journal in self.workspace.journal_files(&self.settings) {
+            if self.vfs.read().get(&journal).is_none() {
+                if let (Ok(text), Ok(uri)) = (
+                    std::fs::read_to_string(&journal),
+                    format!("file://{}", journal.display()).parse::<Uri>(),
+                ) {
+                    self.publish_diagnostics(&uri, &text);
+                }
+            }
+        }
+    }
+
+    /// Handle workspace/didChangeConfiguration notification.
+    ///
+    /// Invalid settings are reported to the user and the previous settings
+    /// are kept.
+    fn on_did_change_configuration(&mut self, params: lsp_types::DidChangeConfigurationParams) {
+        match Settings::from_configuration_change(&params.settings) {
+            Ok(Some(settings)) => {
+                tracing::info!("Settings changed");
+                tracing::debug!("Server settings: {:?}", settings);
+                let revalidate = settings.validation != self.settings.validation;
+                let journals_changed = !settings.journals().eq(self.settings.journals());
+                self.settings = settings;
+                if journals_changed {
+                    self.workspace.refresh(&self.settings);
+                }
+                if revalidate || journals_changed {
+                    self.revalidate_open_documents();
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("Invalid settings, keeping previous: {}", e);
+                self.send(show_message(
+                    lsp_types::MessageType::WARNING,
+                    format!("Invalid rledger settings, keeping previous settings: {e}"),
+                ));
+            }
+        }
+    }
+
+    /// Handle workspace/didChangeWorkspaceFolders notification.
+    ///
+    /// Diagnostics of documents in removed folders are cleared unless the
+    /// documents are still open.
+    fn on_did_change_workspace_folders(
+        &mut self,
+        params: lsp_types::DidChangeWorkspaceFoldersParams,
+    ) {
+        for folder in &params.event.removed {
+            let Some(root) = self.workspace.remove_folder(folder) else {
+                continue;
+            };
+            tracing::info!("Workspace root removed: {}", root.path.display());
+            let stale: Vec<Uri> = self
+                .diagnostics
+                .keys()
+                .filter(|uri| {
+                    uri_to_path(uri).is_some_and(|path| {
+                        path.starts_with(&root.path) && self.vfs.read().get(&path).is_none()
+                    })
+                })
+                .cloned()
+                .collect();
+            for uri in stale {
+                self.diagnostics.remove(&uri);
+                self.send_diagnostics(&uri, vec![]);
+            }
+        }
+        for folder in &params.event.added {
+            tracing::info!("Workspace root added: {}", folder.uri.as_str());
+            self.workspace.add_folder(folder, &self.settings);
+        }
+
+        bump_revision();
+        self.revalidate_open_documents();
+    }
+
     /// Re-validate all open documents (e.g., after an included file changes).
     fn revalidate_open_documents(&mut self) {
         let paths: Vec<_> = self.vfs.read().paths().cloned().collect();
@@ -1106,10 +1632,32 @@ impl MainLoopState {
             .collect();
 
         // Now publish diagnostics
-        for (uri, content) in documents {
+        let started = Instant::now();
+        let progress = self.start_progress(None, "validate", "Validating ledger");
+        let total = documents.len();
+        for (i, (uri, content)) in documents.into_iter().enumerate() {
+            if self.is_cancelled(&progress) {
+                tracing::info!("Revalidation cancelled after {} of {} files", i, total);
+                self.finish_progress(progress, "Cancelled");
+                return;
+            }
+            progress.report(i, total, uri.as_str());
+
             tracing::debug!("Revalidating: {}", uri.as_str());
             self.publish_diagnostics(&uri, &content);
         }
+        self.finish_progress(progress, format!("Validated {total} files"));
+        self.last_check = Some(started.elapsed());
+        self.send_status();
+
+        // Files that are not open may be affected too
+        if self.pull_diagnostics && self.diagnostic_refresh {
+            self.send(lsp_server::Message::Request(lsp_server::Request::new(
+                lsp_server::RequestId::from("workspace-diagnostic-refresh".to_string()),
+                WorkspaceDiagnosticRefresh::METHOD.to_string(),
+                serde_json::Value::Null,
+            )));
+        }
     }
 
     /// Register file watchers with the client.
@@ -1154,11 +1702,7 @@ impl MainLoopState {
 
     /// Parse document and publish diagnostics.
     fn publish_diagnostics(&mut self, uri: &Uri, text: &str) {
-        // Parse the document
-        let result = parse(text);
-
-        // Convert errors to LSP diagnostics
-        let diagnostics = parse_errors_to_diagnostics(&result, text);
+        let diagnostics = self.compute_diagnostics(uri, text);
 
         tracing::debug!(
             "Publishing {} diagnostics for {}",
@@ -1171,8 +1715,73 @@ impl MainLoopState {
         self.send_diagnostics(uri, diagnostics);
     }
 
-    /// Send diagnostics to the client.
+    /// Parse a document and compute its diagnostics.
+    fn compute_diagnostics(&self, uri: &Uri, text: &str) -> Vec<lsp_types::Diagnostic> {
+        // Parse the document
+        let result = parse(text);
+
+        // Convert errors to LSP diagnostics
+        match self.settings.validation {
+            ValidationLevel::Off => Vec::new(),
+            // BQL files hold queries, not Beancount
+            ValidationLevel::Syntax if is_bql_file(uri) => {
+                bql_diagnostics(&query_regions(uri, text, &result), text)
+            }
+            ValidationLevel::Syntax => {
+                let mut diagnostics = parse_errors_to_diagnostics(&result, text);
+                if let Some(path) = uri_to_path(uri) {
+                    diagnostics.extend(missing_include_diagnostics(&path, text, &result));
+                }
+                let opened = self.opened_accounts(uri_to_path(uri).as_deref(), &result);
+                diagnostics.extend(similar_account_diagnostics(text, &result, &opened));
+                diagnostics.extend(bql_diagnostics(&query_regions(uri, text, &result), text));
+                diagnostics
+            }
+        }
+    }
+
+    /// Accounts opened in a document or any other open document of the
+    /// same workspace root.
+    fn opened_accounts(&self, path: Option<&Path>, parse_result: &ParseResult) -> HashSet<String> {
+        let mut opened = opened_accounts(parse_result);
+        let paths: Vec<_> = self
+            .vfs
+            .read()
+            .paths()
+            .filter(|other| path.map_or(true, |path| self.workspace.same_root(path, other)))
+            .cloned()
+            .collect();
+        for path in &paths {
+            if let Some((_, other)) = self.vfs.write().get_document_data(path) {
+                opened.extend(opened_accounts(&other));
+            }
+        }
+        opened
+    }
+
+    /// Send the `rustledger/status` summary of the ledger.
+    fn send_status(&self) {
+        let mut status = LedgerStatus {
+            main_ledger: self
+                .workspace
+                .journal_files(&self.settings)
+                .into_iter()
+                .next(),
+            directives: self.ledger_directives().len(),
+            ..LedgerStatus::default()
+        };
+        status.count(self.diagnostics.values().flatten());
+        if let Some(duration) = self.last_check {
+            status.set_last_check(duration);
+        }
+        self.send(lsp_server::Message::Notification(status.notification()));
+    }
+
+    /// Send diagnostics to the client, unless it pulls them.
     fn send_diagnostics(&self, uri: &Uri, diagnostics: Vec<lsp_types::Diagnostic>) {
+        if self.pull_diagnostics {
+            return;
+        }
         let params = PublishDiagnosticsParams {
             uri: uri.clone(),
             diagnostics,
@@ -1184,6 +1793,16 @@ impl MainLoopState {
         self.send(lsp_server::Message::Notification(notif));
     }
 
+    /// Answer a cancelled request.
+    fn send_cancelled(&self, id: lsp_server::RequestId) {
+        let response = lsp_server::Response::new_err(
+            id,
+            lsp_server::ErrorCode::RequestCanceled as i32,
+            REQUEST_CANCELLED.to_string(),
+        );
+        self.send(lsp_server::Message::Response(response));
+    }
+
     /// Send a message to the client.
     fn send(&self, msg: lsp_server::Message) {
         if let Err(e) = self.sender.send(msg) {
@@ -1192,13 +1811,56 @@ impl MainLoopState {
     }
 }
 
+/// Diagnostic capabilities: pull diagnostics for documents and the whole
+/// workspace, which depend on each other through includes and opened accounts.
+pub fn diagnostic_capabilities() -> DiagnosticServerCapabilities {
+    DiagnosticServerCapabilities::Options(DiagnosticOptions {
+        identifier: Some("rustledger".to_string()),
+        inter_file_dependencies: true,
+        workspace_diagnostics: true,
+        work_done_progress_options: WorkDoneProgressOptions {
+            work_done_progress: Some(true),
+        },
+    })
+}
+
+/// Build a window/showMessage notification.
+pub fn show_message(typ: lsp_types::MessageType, message: String) -> lsp_server::Message {
+    let params = lsp_types::ShowMessageParams { typ, message };
+    lsp_server::Message::Notification(lsp_server::Notification::new(
+        ShowMessage::METHOD.to_string(),
+        params,
+    ))
+}
+
 /// Run the main event loop.
-pub fn run_main_loop(receiver: Receiver<lsp_server::Message>, sender: Sender<lsp_server::Message>) {
-    let mut state = MainLoopState::new(sender);
+pub fn run_main_loop(
+    receiver: Receiver<lsp_server::Message>,
+    sender: Sender<lsp_server::Message>,
+    settings: Settings,
+    client_capabilities: &ClientCapabilities,
+    workspace_folders: &[WorkspaceFolder],
+) {
+    let mut state = MainLoopState::new(receiver, sender, settings, workspace_folders);
+    state.work_done_progress = client_capabilities
+        .window
+        .as_ref()
+        .and_then(|window| window.work_done_progress)
+        .unwrap_or(false);
+    state.pull_diagnostics = client_capabilities
+        .text_document
+        .as_ref()
+        .is_some_and(|text_document| text_document.diagnostic.is_some());
+    state.diagnostic_refresh = client_capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.diagnostic.as_ref())
+        .and_then(|diagnostic| diagnostic.refresh_support)
+        .unwrap_or(false);
 
     tracing::info!("Main loop started");
 
-    for msg in receiver {
+    while let Some(msg) = state.inbox.next() {
         let event = match msg {
             lsp_server::Message::Request(req) => Event::Message(Message::Request(req)),
             lsp_server::Message::Notification(notif) => {
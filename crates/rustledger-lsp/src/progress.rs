@@ -0,0 +1,159 @@
+//! Work done progress reporting (`$/progress`).
+//!
+//! Long operations report progress either on a token supplied by the client
+//! with the request (`workDoneToken`) or on a token the server creates with
+//! `window/workDoneProgress/create` when the client supports it. Without a
+//! token, reporting is a no-op.
+
+use crossbeam_channel::Sender;
+use lsp_types::notification::{Notification, Progress as ProgressNotification};
+use lsp_types::request::{Request, WorkDoneProgressCreate};
+use lsp_types::{
+    NumberOrString, ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress,
+    WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+    WorkDoneProgressReport,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counter for server-created progress tokens.
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+/// Ask the client to create a new progress token.
+///
+/// Following rust-analyzer, the token is used right away without waiting for
+/// the client's response; clients queue progress for tokens being created.
+pub fn create_token(sender: &Sender<lsp_server::Message>, name: &str) -> ProgressToken {
+    let n = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    let token = NumberOrString::String(format!("rledger/{name}/{n}"));
+
+    let request = lsp_server::Request::new(
+        lsp_server::RequestId::from(format!("progress-{name}-{n}")),
+        WorkDoneProgressCreate::METHOD.to_string(),
+        WorkDoneProgressCreateParams {
+            token: token.clone(),
+        },
+    );
+    if let Err(e) = sender.send(lsp_server::Message::Request(request)) {
+        tracing::error!("Failed to send message: {}", e);
+    }
+
+    token
+}
+
+/// An in-flight progress report, ended when dropped.
+pub struct Progress {
+    /// Sender for outgoing LSP messages.
+    sender: Sender<lsp_server::Message>,
+    /// Token to report on, if any.
+    token: Option<ProgressToken>,
+    /// Message sent with the end notification.
+    end_message: Option<String>,
+}
+
+impl Progress {
+    /// Start reporting progress on `token`.
+    pub fn begin(
+        sender: &Sender<lsp_server::Message>,
+        token: Option<ProgressToken>,
+        title: &str,
+        cancellable: bool,
+    ) -> Self {
+        let progress = Self {
+            sender: sender.clone(),
+            token,
+            end_message: None,
+        };
+        progress.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(cancellable),
+            message: None,
+            percentage: Some(0),
+        }));
+        progress
+    }
+
+    /// The token progress is reported on.
+    pub fn token(&self) -> Option<&ProgressToken> {
+        self.token.as_ref()
+    }
+
+    /// Report that `done` of `total` steps are complete.
+    pub fn report(&self, done: usize, total: usize, message: impl Into<String>) {
+        let percentage = (done.min(total) * 100)
+            .checked_div(total)
+            .map_or(100, |p| p as u32);
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: None,
+            message: Some(message.into()),
+            percentage: Some(percentage),
+        }));
+    }
+
+    /// Set the message shown when progress ends.
+    pub fn finish(mut self, message: impl Into<String>) {
+        self.end_message = Some(message.into());
+    }
+
+    /// Send a progress notification, if there is a token.
+    fn send(&self, value: WorkDoneProgress) {
+        let Some(token) = self.token.clone() else {
+            return;
+        };
+        let params = ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(value),
+        };
+        let notif = lsp_server::Notification::new(ProgressNotification::METHOD.to_string(), params);
+        if let Err(e) = self.sender.send(lsp_server::Message::Notification(notif)) {
+            tracing::error!("Failed to send message: {}", e);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        let message = self.end_message.take();
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress_values(receiver: &crossbeam_channel::Receiver<lsp_server::Message>) -> Vec<String> {
+        receiver
+            .try_iter()
+            .filter_map(|msg| match msg {
+                lsp_server::Message::Notification(n) => Some(n.params["value"]["kind"].to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_progress_begin_report_end() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let token = NumberOrString::String("test".to_string());
+
+        let progress = Progress::begin(&sender, Some(token), "Validating", true);
+        progress.report(1, 2, "a.beancount");
+        progress.finish("done");
+
+        assert_eq!(
+            progress_values(&receiver),
+            vec!["\"begin\"", "\"report\"", "\"end\""]
+        );
+    }
+
+    #[test]
+    fn test_progress_without_token_is_silent() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        let progress = Progress::begin(&sender, None, "Validating", true);
+        progress.report(1, 2, "a.beancount");
+        drop(progress);
+
+        assert!(receiver.try_recv().is_err());
+    }
+}
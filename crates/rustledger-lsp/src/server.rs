@@ -1,10 +1,13 @@
 //! Main LSP server implementation.
 
 use crate::handlers::execute_command::COMMANDS;
+use crate::handlers::file_operations;
 use crate::handlers::on_type_formatting::{FIRST_TRIGGER_CHARACTER, MORE_TRIGGER_CHARACTERS};
 use crate::handlers::semantic_tokens::get_capabilities as get_semantic_tokens_capabilities;
 use crate::handlers::signature_help::TRIGGER_CHARACTERS as SIGNATURE_TRIGGER_CHARACTERS;
-use crate::main_loop::run_main_loop;
+use crate::handlers::virtual_documents::COMMANDS as VIRTUAL_DOCUMENT_COMMANDS;
+use crate::main_loop::{diagnostic_capabilities, run_main_loop, show_message};
+use crate::settings::Settings;
 use lsp_server::Connection;
 use lsp_types::InitializeParams;
 
@@ -29,15 +32,40 @@ impl Server {
     pub fn run(self) {
         tracing::info!("Starting Beancount Language Server v{}", crate::VERSION);
 
-        if let Some(folders) = &self.init_params.workspace_folders {
-            if let Some(folder) = folders.first() {
-                tracing::info!("Workspace root: {}", folder.uri.as_str());
-            }
+        let workspace_folders = self
+            .init_params
+            .workspace_folders
+            .clone()
+            .unwrap_or_default();
+        for folder in &workspace_folders {
+            tracing::info!("Workspace root: {}", folder.uri.as_str());
         }
 
+        let settings =
+            Settings::from_initialization_options(self.init_params.initialization_options.as_ref())
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Invalid initializationOptions, using defaults: {}", e);
+                    let message = format!("Invalid rledger settings, using defaults: {e}");
+                    if let Err(e) = self
+                        .connection
+                        .sender
+                        .send(show_message(lsp_types::MessageType::WARNING, message))
+                    {
+                        tracing::error!("Failed to send message: {}", e);
+                    }
+                    Settings::default()
+                });
+        tracing::debug!("Server settings: {:?}", settings);
+
         // Run the main event loop
         let (sender, receiver) = (self.connection.sender, self.connection.receiver);
-        run_main_loop(receiver, sender);
+        run_main_loop(
+            receiver,
+            sender,
+            settings,
+            &self.init_params.capabilities,
+            &workspace_folders,
+        );
 
         tracing::info!("Server shutdown complete");
     }
@@ -83,10 +111,19 @@ pub fn start_stdio() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 work_done_progress_options: Default::default(),
             },
         )),
-        workspace_symbol_provider: Some(lsp_types::OneOf::Left(true)),
+        workspace_symbol_provider: Some(lsp_types::OneOf::Right(
+            lsp_types::WorkspaceSymbolOptions {
+                work_done_progress_options: lsp_types::WorkDoneProgressOptions {
+                    work_done_progress: Some(true),
+                },
+                resolve_provider: None,
+            },
+        )),
         rename_provider: Some(lsp_types::OneOf::Right(lsp_types::RenameOptions {
             prepare_provider: Some(true),
-            work_done_progress_options: Default::default(),
+            work_done_progress_options: lsp_types::WorkDoneProgressOptions {
+                work_done_progress: Some(true),
+            },
         })),
         document_formatting_provider: Some(lsp_types::OneOf::Left(true)),
         document_range_formatting_provider: Some(lsp_types::OneOf::Left(true)),
@@ -119,6 +156,7 @@ pub fn start_stdio() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             resolve_provider: Some(true), // Enable resolve for lazy-loading balance verification
         }),
         color_provider: Some(lsp_types::ColorProviderCapability::Simple(true)),
+        diagnostic_provider: Some(diagnostic_capabilities()),
         declaration_provider: Some(lsp_types::DeclarationCapability::Simple(true)),
         call_hierarchy_provider: Some(lsp_types::CallHierarchyServerCapability::Simple(true)),
         signature_help_provider: Some(lsp_types::SignatureHelpOptions {
@@ -132,7 +170,11 @@ pub fn start_stdio() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             work_done_progress_options: Default::default(),
         }),
         execute_command_provider: Some(lsp_types::ExecuteCommandOptions {
-            commands: COMMANDS.iter().map(|s| s.to_string()).collect(),
+            commands: COMMANDS
+                .iter()
+                .chain(VIRTUAL_DOCUMENT_COMMANDS)
+                .map(|s| s.to_string())
+                .collect(),
             work_done_progress_options: Default::default(),
         }),
         // Type hierarchy: advertised via experimental until lsp-types adds native support
@@ -145,7 +187,12 @@ pub fn start_stdio() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 supported: Some(true),
                 change_notifications: Some(lsp_types::OneOf::Left(true)),
             }),
-            file_operations: None, // File operations (create/rename/delete) not needed for Beancount
+            // Keep include paths in sync when ledger files are renamed or deleted
+            file_operations: Some(lsp_types::WorkspaceFileOperationsServerCapabilities {
+                will_rename: Some(file_operations::registration_options()),
+                did_delete: Some(file_operations::registration_options()),
+                ..Default::default()
+            }),
         }),
         ..Default::default()
     };
@@ -0,0 +1,173 @@
+//! Server settings supplied by the client.
+//!
+//! Settings are read from the `initializationOptions` field of the
+//! initialize request and replaced whenever the client sends
+//! `workspace/didChangeConfiguration`. Unknown keys are ignored and missing
+//! keys fall back to their defaults, so clients only need to send what they
+//! change.
+//!
+//! ```json
+//! {
+//!   "journalFile": "/home/me/ledger/main.beancount",
+//!   "journalFiles": ["/home/me/business/books.beancount"],
+//!   "formatting": { "amountColumn": 60, "indent": 4 },
+//!   "validation": "syntax"
+//! }
+//! ```
+
+use crate::handlers::definition::DefinitionTarget;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Section name clients may nest the settings under in
+/// `workspace/didChangeConfiguration` (e.g. `{ "rledger": { ... } }`).
+pub const SETTINGS_SECTION: &str = "rledger";
+
+/// Client-configurable server settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Where go-to-definition jumps for accounts.
+    pub definition_target: DefinitionTarget,
+    /// Main ledger file that includes the rest of the journal.
+    pub journal_file: Option<PathBuf>,
+    /// Main ledger files of the other workspace folders, for multi-root
+    /// workspaces; each applies to the folder containing it.
+    pub journal_files: Vec<PathBuf>,
+    /// Formatting style.
+    pub formatting: FormattingSettings,
+    /// How much checking is reported as diagnostics.
+    pub validation: ValidationLevel,
+}
+
+/// Formatting style used by the formatting handlers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormattingSettings {
+    /// Column at which posting amounts end.
+    pub amount_column: usize,
+    /// Number of spaces postings are indented by.
+    pub indent: usize,
+}
+
+impl Default for FormattingSettings {
+    fn default() -> Self {
+        Self {
+            amount_column: 50,
+            indent: 2,
+        }
+    }
+}
+
+/// How strictly documents are checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationLevel {
+    /// Publish no diagnostics.
+    Off,
+    /// Report syntax errors.
+    #[default]
+    Syntax,
+}
+
+impl Settings {
+    /// Every configured main ledger file.
+    pub fn journals(&self) -> impl Iterator<Item = &PathBuf> {
+        self.journal_file.iter().chain(&self.journal_files)
+    }
+
+    /// Build settings from the client's `initializationOptions`.
+    ///
+    /// Missing options yield the defaults; invalid options are an error so
+    /// the server can report them to the user.
+    pub fn from_initialization_options(
+        options: Option<&serde_json::Value>,
+    ) -> Result<Self, serde_json::Error> {
+        match options {
+            None | Some(serde_json::Value::Null) => Ok(Self::default()),
+            Some(options) => Self::deserialize(options),
+        }
+    }
+
+    /// Build settings from a `workspace/didChangeConfiguration` payload.
+    ///
+    /// Accepts the settings either at the top level or nested under
+    /// [`SETTINGS_SECTION`]. Returns `Ok(None)` when the client sent no
+    /// settings (pull-model clients send `null`).
+    pub fn from_configuration_change(
+        settings: &serde_json::Value,
+    ) -> Result<Option<Self>, serde_json::Error> {
+        let settings = settings.get(SETTINGS_SECTION).unwrap_or(settings);
+        if settings.is_null() {
+            return Ok(None);
+        }
+        Self::deserialize(settings).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_defaults() {
+        assert_eq!(
+            Settings::from_initialization_options(None).unwrap(),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn test_settings_definition_target() {
+        let options = serde_json::json!({ "definitionTarget": "latest_balance" });
+        let settings = Settings::from_initialization_options(Some(&options)).unwrap();
+        assert_eq!(settings.definition_target, DefinitionTarget::LatestBalance);
+    }
+
+    #[test]
+    fn test_settings_invalid_is_error() {
+        let options = serde_json::json!({ "definitionTarget": "nowhere" });
+        assert!(Settings::from_initialization_options(Some(&options)).is_err());
+
+        let options = serde_json::json!({ "formatting": { "indent": "four" } });
+        assert!(Settings::from_initialization_options(Some(&options)).is_err());
+    }
+
+    #[test]
+    fn test_settings_full() {
+        let options = serde_json::json!({
+            "journalFile": "/ledger/main.beancount",
+            "journalFiles": ["/business/books.beancount"],
+            "formatting": { "amountColumn": 60 },
+            "validation": "off"
+        });
+        let settings = Settings::from_initialization_options(Some(&options)).unwrap();
+        assert_eq!(
+            settings.journal_file,
+            Some(PathBuf::from("/ledger/main.beancount"))
+        );
+        assert_eq!(settings.journals().count(), 2);
+        assert_eq!(settings.formatting.amount_column, 60);
+        assert_eq!(settings.formatting.indent, 2);
+        assert_eq!(settings.validation, ValidationLevel::Off);
+    }
+
+    #[test]
+    fn test_settings_configuration_change() {
+        let nested = serde_json::json!({ "rledger": { "validation": "off" } });
+        let settings = Settings::from_configuration_change(&nested)
+            .unwrap()
+            .unwrap();
+        assert_eq!(settings.validation, ValidationLevel::Off);
+
+        let flat = serde_json::json!({ "formatting": { "indent": 4 } });
+        let settings = Settings::from_configuration_change(&flat).unwrap().unwrap();
+        assert_eq!(settings.formatting.indent, 4);
+
+        assert!(
+            Settings::from_configuration_change(&serde_json::Value::Null)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Ledger health summary (`rustledger/status`).
+//!
+//! After every re-validation the server sends a `rustledger/status`
+//! notification that editor extensions can render in their status bar:
+//!
+//! ```json
+//! {
+//!   "mainLedger": "/home/me/ledger.beancount",
+//!   "directives": 1234,
+//!   "errors": 0,
+//!   "warnings": 2,
+//!   "lastCheckMs": 41
+//! }
+//! ```
+
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Method of the status notification.
+pub const STATUS_NOTIFICATION: &str = "rustledger/status";
+
+/// Summary of the ledger's health.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerStatus {
+    /// Main ledger of the first workspace root, if one is known.
+    pub main_ledger: Option<PathBuf>,
+    /// Number of directives in the open documents.
+    pub directives: usize,
+    /// Number of error diagnostics across all documents.
+    pub errors: usize,
+    /// Number of warning diagnostics across all documents.
+    pub warnings: usize,
+    /// How long the last full re-validation took, in milliseconds.
+    pub last_check_ms: Option<u64>,
+}
+
+impl LedgerStatus {
+    /// Count the errors and warnings among published diagnostics.
+    ///
+    /// Diagnostics without a severity count as errors, as most clients show
+    /// them that way.
+    pub fn count<'a>(&mut self, diagnostics: impl IntoIterator<Item = &'a Diagnostic>) {
+        for diagnostic in diagnostics {
+            match diagnostic.severity {
+                Some(DiagnosticSeverity::ERROR) | None => self.errors += 1,
+                Some(DiagnosticSeverity::WARNING) => self.warnings += 1,
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Record the duration of a full re-validation.
+    pub fn set_last_check(&mut self, duration: Duration) {
+        self.last_check_ms = Some(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX));
+    }
+
+    /// The `rustledger/status` notification carrying this summary.
+    pub fn notification(&self) -> lsp_server::Notification {
+        lsp_server::Notification::new(STATUS_NOTIFICATION.to_string(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(severity: Option<DiagnosticSeverity>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            message: "test".to_string(),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn test_status_counts_and_serializes() {
+        let mut status = LedgerStatus {
+            main_ledger: Some(PathBuf::from("/ledger/main.beancount")),
+            directives: 12,
+            ..LedgerStatus::default()
+        };
+        status.count(&[
+            diagnostic(Some(DiagnosticSeverity::ERROR)),
+            diagnostic(None),
+            diagnostic(Some(DiagnosticSeverity::WARNING)),
+            diagnostic(Some(DiagnosticSeverity::HINT)),
+        ]);
+        status.set_last_check(Duration::from_micros(41_900));
+
+        let notification = status.notification();
+        assert_eq!(notification.method, "rustledger/status");
+        assert_eq!(
+            notification.params,
+            serde_json::json!({
+                "mainLedger": "/ledger/main.beancount",
+                "directives": 12,
+                "errors": 2,
+                "warnings": 1,
+                "lastCheckMs": 41,
+            })
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Workspace folders and the main ledger of each.
+//!
+//! Clients may open several workspace folders at once, each holding its own
+//! ledger. Every folder gets a [`WorkspaceRoot`] whose main ledger comes
+//! from the settings (`journalFile` or `journalFiles` inside the folder) or,
+//! failing that, is detected from the files in the folder:
+//!
+//! 1. the only file containing an `option "title"` directive,
+//! 2. otherwise the only file that no other file includes,
+//! 3. otherwise `main.beancount` if it exists.
+//!
+//! Documents belong to the root whose folder contains them (the innermost
+//! one for nested folders); documents outside every folder belong to none.
+
+use crate::handlers::file_operations::resolved_includes;
+use crate::settings::Settings;
+use lsp_types::{Uri, WorkspaceFolder};
+use rustledger_parser::parse;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// How many directory levels below a folder are searched for ledger files.
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// A workspace folder and the ledger it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// The folder's URI as sent by the client.
+    pub uri: Uri,
+    /// The folder on disk.
+    pub path: PathBuf,
+    /// The main ledger file, if one was configured or detected.
+    pub journal_file: Option<PathBuf>,
+}
+
+/// The workspace folders the client has open.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    roots: Vec<WorkspaceRoot>,
+}
+
+impl Workspace {
+    /// Create a workspace from the client's folders.
+    pub fn new(folders: &[WorkspaceFolder], settings: &Settings) -> Self {
+        let mut workspace = Self::default();
+        for folder in folders {
+            workspace.add_folder(folder, settings);
+        }
+        workspace
+    }
+
+    /// The open roots, in the order they were added.
+    pub fn roots(&self) -> &[WorkspaceRoot] {
+        &self.roots
+    }
+
+    /// Add a folder, detecting its main ledger.
+    pub fn add_folder(&mut self, folder: &WorkspaceFolder, settings: &Settings) {
+        let Some(path) = uri_to_path(&folder.uri) else {
+            return;
+        };
+        if self.roots.iter().any(|root| root.path == path) {
+            return;
+        }
+        let journal_file = configured_journal(&path, settings).or_else(|| detect_journal(&path));
+        tracing::info!(
+            "Workspace root {} uses main ledger {:?}",
+            path.display(),
+            journal_file
+        );
+        self.roots.push(WorkspaceRoot {
+            uri: folder.uri.clone(),
+            path,
+            journal_file,
+        });
+    }
+
+    /// Remove a folder, returning its root if it was open.
+    pub fn remove_folder(&mut self, folder: &WorkspaceFolder) -> Option<WorkspaceRoot> {
+        let index = self.roots.iter().position(|root| root.uri == folder.uri)?;
+        Some(self.roots.remove(index))
+    }
+
+    /// Re-resolve every root's main ledger after the settings changed.
+    pub fn refresh(&mut self, settings: &Settings) {
+        for root in &mut self.roots {
+            root.journal_file =
+                configured_journal(&root.path, settings).or_else(|| detect_journal(&root.path));
+        }
+    }
+
+    /// The root a file belongs to.
+    pub fn root_for(&self, path: &Path) -> Option<&WorkspaceRoot> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count())
+    }
+
+    /// Whether two files belong to the same root (or both to none).
+    pub fn same_root(&self, a: &Path, b: &Path) -> bool {
+        self.root_for(a).map(|root| &root.path) == self.root_for(b).map(|root| &root.path)
+    }
+
+    /// The main ledger files of every root, plus any configured journal
+    /// file outside all roots.
+    pub fn journal_files(&self, settings: &Settings) -> Vec<PathBuf> {
+        let mut journals: Vec<PathBuf> = self
+            .roots
+            .iter()
+            .filter_map(|root| root.journal_file.clone())
+            .collect();
+        for journal in settings.journals() {
+            if !journals.contains(journal) {
+                journals.push(journal.clone());
+            }
+        }
+        journals
+    }
+}
+
+/// The configured journal file inside a folder, if any.
+fn configured_journal(folder: &Path, settings: &Settings) -> Option<PathBuf> {
+    settings
+        .journals()
+        .find(|journal| journal.starts_with(folder))
+        .cloned()
+}
+
+/// Guess the main ledger of a folder from its files.
+pub fn detect_journal(folder: &Path) -> Option<PathBuf> {
+    let mut files = Vec::new();
+    collect_ledger_files(folder, 0, &mut files);
+    files.sort();
+
+    let texts: Vec<(PathBuf, String)> = files
+        .into_iter()
+        .filter_map(|path| Some((path.clone(), std::fs::read_to_string(&path).ok()?)))
+        .collect();
+
+    let titled: Vec<&PathBuf> = texts
+        .iter()
+        .filter(|(_, text)| has_title_option(text))
+        .map(|(path, _)| path)
+        .collect();
+    if let [only] = titled[..] {
+        return Some(only.clone());
+    }
+
+    let included: HashSet<PathBuf> = texts
+        .iter()
+        .flat_map(|(path, text)| resolved_includes(path, &parse(text)))
+        .collect();
+    let top_level: Vec<&PathBuf> = texts
+        .iter()
+        .map(|(path, _)| path)
+        .filter(|path| !included.contains(*path))
+        .collect();
+    if let [only] = top_level[..] {
+        return Some(only.clone());
+    }
+
+    let main = folder.join("main.beancount");
+    main.is_file().then_some(main)
+}
+
+/// Collect `.beancount` and `.bean` files, skipping hidden directories.
+fn collect_ledger_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        if path.is_dir() {
+            if !hidden && depth < MAX_SCAN_DEPTH {
+                collect_ledger_files(&path, depth + 1, files);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "beancount" || ext == "bean")
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Whether a ledger sets `option "title"`.
+fn has_title_option(text: &str) -> bool {
+    text.lines().any(|line| {
+        let mut words = line.split_whitespace();
+        words.next() == Some("option") && words.next() == Some("\"title\"")
+    })
+}
+
+/// Convert a folder URI to a path.
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    uri.as_str()
+        .strip_prefix("file://")
+        .map(|path| PathBuf::from(path.trim_end_matches('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rledger-lsp-workspace-{name}-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn folder(path: &Path) -> WorkspaceFolder {
+        WorkspaceFolder {
+            uri: format!("file://{}", path.display()).parse().unwrap(),
+            name: path.display().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_detect_journal_by_title() {
+        let dir = temp_dir("title");
+        std::fs::write(dir.join("a.beancount"), "2024-01-01 open Assets:A\n").unwrap();
+        std::fs::write(
+            dir.join("ledger.beancount"),
+            "option \"title\" \"Home\"\ninclude \"a.beancount\"\n",
+        )
+        .unwrap();
+        let detected = detect_journal(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(detected, Some(dir.join("ledger.beancount")));
+    }
+
+    #[test]
+    fn test_detect_journal_by_includes() {
+        let dir = temp_dir("includes");
+        std::fs::create_dir_all(dir.join("years")).unwrap();
+        std::fs::write(dir.join("years/2024.bean"), "").unwrap();
+        std::fs::write(dir.join("top.bean"), "include \"years/2024.bean\"\n").unwrap();
+        let detected = detect_journal(&dir);
+
+        std::fs::write(dir.join("other.bean"), "").unwrap();
+        let ambiguous = detect_journal(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(detected, Some(dir.join("top.bean")));
+        assert_eq!(ambiguous, None);
+    }
+
+    #[test]
+    fn test_roots_by_folder() {
+        let personal = temp_dir("personal");
+        let business = temp_dir("business");
+        std::fs::write(personal.join("main.beancount"), "").unwrap();
+        std::fs::write(business.join("books.beancount"), "").unwrap();
+        std::fs::write(business.join("2024.beancount"), "").unwrap();
+
+        let settings = Settings {
+            journal_files: vec![business.join("books.beancount")],
+            ..Settings::default()
+        };
+        let workspace = Workspace::new(&[folder(&personal), folder(&business)], &settings);
+        std::fs::remove_dir_all(&personal).unwrap();
+        std::fs::remove_dir_all(&business).unwrap();
+
+        let root = workspace
+            .root_for(&business.join("2024.beancount"))
+            .unwrap();
+        assert_eq!(root.journal_file, Some(business.join("books.beancount")));
+        let root = workspace
+            .root_for(&personal.join("main.beancount"))
+            .unwrap();
+        assert_eq!(root.journal_file, Some(personal.join("main.beancount")));
+        assert!(!workspace.same_root(
+            &personal.join("main.beancount"),
+            &business.join("books.beancount"),
+        ));
+        assert_eq!(workspace.journal_files(&settings).len(), 2);
+    }
+}
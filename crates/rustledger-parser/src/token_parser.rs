@@ -387,6 +387,17 @@ fn tok_comment<'src>() -> impl Parser<'src, &'src [SpannedToken<'src>], (), TokE
         .to(())
 }
 
+/// Match a comment token, yielding its text without the leading `;`.
+fn tok_comment_text<'src>()
+-> impl Parser<'src, &'src [SpannedToken<'src>], String, TokExtra<'src>> + Clone {
+    any()
+        .filter(|t: &SpannedToken<'_>| matches!(t.token, Token::Comment(_)))
+        .map(|t: SpannedToken<'src>| match t.token {
+            Token::Comment(c) => c.trim_start_matches(';').trim().to_string(),
+            _ => unreachable!(),
+        })
+}
+
 /// Match a star token (*).
 fn tok_star<'src>() -> impl Parser<'src, &'src [SpannedToken<'src>], (), TokExtra<'src>> + Clone {
     any()
@@ -851,7 +862,7 @@ enum TxnHeaderItem {
 /// Posting, metadata, or tag/link continuation.
 #[derive(Debug, Clone)]
 enum PostingOrMeta {
-    Posting(Posting),
+    Posting(Box<Posting>),
     Meta(String, MetaValue),
     TagsLinks(Vec<String>, Vec<String>),
 }
@@ -889,30 +900,33 @@ fn tok_posting_with_meta<'src>()
         .then(amount)
         .then(cost)
         .then(price)
-        .then_ignore(tok_comment().or_not())
+        .then(tok_comment_text().or_not())
         .then(tok_posting_meta().repeated().collect::<Vec<_>>())
-        .map(|(((((flag, account), amount), cost), price), metadata)| {
-            // Create posting based on whether we have an amount
-            let mut posting = if let Some(a) = amount {
-                Posting::with_incomplete(account, a)
-            } else {
-                Posting::auto(account)
-            };
-            if let Some(f) = flag {
-                posting = posting.with_flag(f);
-            }
-            if let Some(c) = cost {
-                posting = posting.with_cost(c);
-            }
-            if let Some(p) = price {
-                posting = posting.with_price(p);
-            }
-            // Add posting-level metadata
-            for (key, value) in metadata {
-                posting.meta.insert(key, value);
-            }
-            posting
-        })
+        .map(
+            |((((((flag, account), amount), cost), price), comment), metadata)| {
+                // Create posting based on whether we have an amount
+                let mut posting = if let Some(a) = amount {
+                    Posting::with_incomplete(account, a)
+                } else {
+                    Posting::auto(account)
+                };
+                if let Some(f) = flag {
+                    posting = posting.with_flag(f);
+                }
+                if let Some(c) = cost {
+                    posting = posting.with_cost(c);
+                }
+                if let Some(p) = price {
+                    posting = posting.with_price(p);
+                }
+                posting.comment = comment.filter(|c| !c.is_empty());
+                // Add posting-level metadata
+                for (key, value) in metadata {
+                    posting.meta.insert(key, value);
+                }
+                posting
+            },
+        )
 }
 
 /// Parse a metadata line inside a directive, returning None for comment-only lines.
@@ -986,7 +1000,7 @@ fn tok_posting_or_meta<'src>()
     let posting_line = tok_newline()
         .ignore_then(tok_indent())
         .ignore_then(tok_posting_with_meta())
-        .map(|p| Some(PostingOrMeta::Posting(p)));
+        .map(|p| Some(PostingOrMeta::Posting(Box::new(p))));
 
     // Comment with indentation (within posting block)
     let comment_line = tok_newline()
@@ -1054,7 +1068,7 @@ fn tok_transaction_directive<'src>()
             for item in items.into_iter().flatten() {
                 match item {
                     PostingOrMeta::Posting(p) => {
-                        txn = txn.with_posting(p);
+                        txn = txn.with_posting(*p);
                     }
                     PostingOrMeta::Meta(k, v) => {
                         txn.meta.insert(k, v);
@@ -1793,11 +1807,7 @@ fn apply_pushed_tags(directive: Directive, tag_stack: &[InternedStr]) -> Directi
 
     match directive {
         Directive::Transaction(mut txn) => {
-            for tag in tag_stack {
-                if !txn.tags.contains(tag) {
-                    txn.tags.push(tag.clone());
-                }
-            }
+            txn.tags.extend(tag_stack);
             Directive::Transaction(txn)
         }
         other => other,
@@ -0,0 +1,64 @@
+//! Golden-file tests for the directive formatter.
+//!
+//! Each `tests/golden/<name>.input.beancount` is parsed and formatted with
+//! the default configuration; the result must match
+//! `tests/golden/<name>.expected.beancount`. The expected files are also
+//! formatted again to check that formatting is idempotent.
+//!
+//! The expected files are maintained by hand and must not be regenerated
+//! from this formatter's output. They follow beancount's printer
+//! (`bean-format`) for directive layout, `*` for `txn`, sorted tags before
+//! links and four-space posting metadata, with three deliberate
+//! differences: metadata keys are sorted (beancount keeps insertion
+//! order), amounts are aligned to a fixed column, and comments are kept.
+//! When changing a file, compare it with `bean-format` output for the same
+//! input and record any new difference here.
+
+use rustledger_core::format::{FormatConfig, format_directive};
+use rustledger_parser::parse;
+use std::path::{Path, PathBuf};
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Parse `source` and format every directive, separated by blank lines.
+fn format_source(source: &str) -> String {
+    let result = parse(source);
+    assert!(
+        result.errors.is_empty(),
+        "expected no errors, got: {:?}",
+        result.errors
+    );
+
+    let config = FormatConfig::default();
+    result
+        .directives
+        .iter()
+        .map(|d| format_directive(&d.value, &config))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn check_golden(name: &str) {
+    let dir = golden_dir();
+    let input = std::fs::read_to_string(dir.join(format!("{name}.input.beancount"))).unwrap();
+    let expected_path = dir.join(format!("{name}.expected.beancount"));
+
+    let expected = std::fs::read_to_string(&expected_path).unwrap();
+    assert_eq!(
+        format_source(&input),
+        expected,
+        "formatted output differs for {name}"
+    );
+    assert_eq!(
+        format_source(&expected),
+        expected,
+        "formatting is not idempotent for {name}"
+    );
+}
+
+#[test]
+fn test_golden_all_directives() {
+    check_golden("all-directives");
+}
@@ -101,9 +101,9 @@ fn test_parse_transaction_with_tags_and_links() {
     assert_eq!(count_directive_type(&result, "transaction"), 1);
 
     if let Directive::Transaction(txn) = &result.directives[0].value {
-        assert!(txn.tags.iter().any(|t| t.as_str() == "food"));
-        assert!(txn.tags.iter().any(|t| t.as_str() == "restaurant"));
-        assert!(txn.links.iter().any(|l| l.as_str() == "receipt-123"));
+        assert!(txn.tags.contains("food"));
+        assert!(txn.tags.contains("restaurant"));
+        assert!(txn.links.contains("receipt-123"));
     } else {
         panic!("expected transaction");
     }
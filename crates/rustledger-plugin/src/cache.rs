@@ -0,0 +1,226 @@
+//! On-disk cache of WASM plugin results.
+//!
+//! Plugins are pure functions of their input: the same module run on the
+//! same directives, options and config, with the same virtual date and
+//! random seed, always produces the same output. The cache exploits this by
+//! keying each plugin's output on a hash of the module bytes, the serialized
+//! input and the date and seed, so an unchanged ledger skips plugin
+//! execution entirely.
+//!
+//! # Cache layout
+//!
+//! Entries live in a directory, one file per plugin name. Each run
+//! overwrites the plugin's entry, so the cache never grows beyond one
+//! result per plugin:
+//!
+//! ```text
+//! magic (8) | version (4) | key (32) | MessagePack-encoded PluginOutput
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::runtime::RuntimeConfig;
+use crate::types::{PluginInput, PluginOutput};
+
+/// Magic bytes identifying a plugin cache entry.
+const CACHE_MAGIC: &[u8; 8] = b"RLPLUGIN";
+
+/// Entry format version; bump when the layout or `PluginOutput` changes.
+const CACHE_VERSION: u32 = 1;
+
+/// Length of the entry header.
+const HEADER_LEN: usize = 8 + 4 + 32;
+
+/// Cache key: SHA-256 over the module hash, the serialized input and the
+/// sandbox's date and seed.
+pub type CacheKey = [u8; 32];
+
+/// Hash plugin module bytes.
+pub fn module_hash(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// Compute the cache key for running a module on an input.
+///
+/// The input includes the directives, options and plugin config, so a
+/// change to any of them, or to the date or seed in `config`, is a cache
+/// miss.
+pub fn cache_key(
+    module_hash: &[u8; 32],
+    input: &PluginInput,
+    config: &RuntimeConfig,
+) -> Option<CacheKey> {
+    let input_bytes = rmp_serde::to_vec(input).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_VERSION.to_le_bytes());
+    hasher.update(module_hash);
+    hasher.update(&input_bytes);
+    hasher.update(config.clock_nanos().to_le_bytes());
+    hasher.update(config.seed.to_le_bytes());
+    Some(hasher.finalize().into())
+}
+
+/// Directory-backed cache of plugin outputs.
+#[derive(Debug, Clone)]
+pub struct PluginCache {
+    dir: PathBuf,
+}
+
+impl PluginCache {
+    /// Use `dir` as the cache directory; it is created on first store.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The conventional cache directory for a ledger file
+    /// (`ledger.beancount` caches in `ledger.beancount.plugin-cache/`).
+    pub fn for_ledger(main_file: &Path) -> Self {
+        let name = main_file.file_name().map_or_else(
+            || "ledger.plugin-cache".to_string(),
+            |n| format!("{}.plugin-cache", n.to_string_lossy()),
+        );
+        Self::new(main_file.with_file_name(name))
+    }
+
+    /// Get the cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Look up a plugin's cached output.
+    ///
+    /// Returns `None` when there is no entry, it was stored under another
+    /// key, or it cannot be read.
+    pub fn load(&self, plugin: &str, key: &CacheKey) -> Option<PluginOutput> {
+        let data = fs::read(self.entry_path(plugin)).ok()?;
+        if data.len() < HEADER_LEN
+            || &data[..8] != CACHE_MAGIC
+            || data[8..12] != CACHE_VERSION.to_le_bytes()
+            || data[12..HEADER_LEN] != key[..]
+        {
+            return None;
+        }
+        rmp_serde::from_slice(&data[HEADER_LEN..]).ok()
+    }
+
+    /// Store a plugin's output under a key, replacing any previous entry.
+    pub fn store(
+        &self,
+        plugin: &str,
+        key: &CacheKey,
+        output: &PluginOutput,
+    ) -> std::io::Result<()> {
+        let encoded = rmp_serde::to_vec(output).map_err(std::io::Error::other)?;
+        let mut data = Vec::with_capacity(HEADER_LEN + encoded.len());
+        data.extend_from_slice(CACHE_MAGIC);
+        data.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        data.extend_from_slice(key);
+        data.extend_from_slice(&encoded);
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(plugin), data)
+    }
+
+    /// Remove all cached entries.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path(&self, plugin: &str) -> PathBuf {
+        // Plugin names come from file stems; keep them filesystem-safe
+        let name: String = plugin
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{name}.bin"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PluginOptions;
+    use chrono::NaiveDate;
+
+    fn input(config: Option<&str>) -> PluginInput {
+        PluginInput {
+            directives: Vec::new(),
+            options: PluginOptions {
+                operating_currencies: vec!["USD".to_string()],
+                title: None,
+            },
+            config: config.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_module_and_input() {
+        let module_a = module_hash(b"module a");
+        let module_b = module_hash(b"module b");
+        let config = RuntimeConfig::default();
+
+        let key = cache_key(&module_a, &input(None), &config).unwrap();
+        assert_eq!(key, cache_key(&module_a, &input(None), &config).unwrap());
+        assert_ne!(key, cache_key(&module_b, &input(None), &config).unwrap());
+        assert_ne!(
+            key,
+            cache_key(&module_a, &input(Some("x")), &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_runtime_config() {
+        let module = module_hash(b"module");
+        let config = RuntimeConfig {
+            today: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+            seed: 0,
+            ..RuntimeConfig::default()
+        };
+        let key = cache_key(&module, &input(None), &config).unwrap();
+
+        let tomorrow = RuntimeConfig {
+            today: NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            ..config
+        };
+        assert_ne!(key, cache_key(&module, &input(None), &tomorrow).unwrap());
+
+        let reseeded = RuntimeConfig { seed: 1, ..config };
+        assert_ne!(key, cache_key(&module, &input(None), &reseeded).unwrap());
+    }
+
+    #[test]
+    fn test_cache_store_and_load() {
+        let dir = std::env::temp_dir().join(format!("rledger-plugin-cache-{}", std::process::id()));
+        let cache = PluginCache::new(&dir);
+        let config = RuntimeConfig::default();
+        let key = cache_key(&module_hash(b"module"), &input(None), &config).unwrap();
+        let other = cache_key(&module_hash(b"module"), &input(Some("x")), &config).unwrap();
+
+        assert!(cache.load("my plugin", &key).is_none());
+
+        let output = PluginOutput {
+            directives: Vec::new(),
+            errors: Vec::new(),
+            logs: Vec::new(),
+        };
+        cache.store("my plugin", &key, &output).unwrap();
+        assert!(cache.load("my plugin", &key).is_some());
+        assert!(cache.load("my plugin", &other).is_none());
+        assert!(cache.load("another", &key).is_none());
+
+        cache.clear().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -1,9 +1,9 @@
 //! Conversion between core types and plugin serialization types.
 
 use rustledger_core::{
-    Amount, Balance, Close, Commodity, CostSpec, Custom, Decimal, Directive, Document, Event,
-    IncompleteAmount, MetaValue, NaiveDate, Note, Open, Pad, Posting, Price, PriceAnnotation,
-    Query, Transaction,
+    Amount, Balance, Close, Commodity, CostSpec, Custom, Decimal, Directive, DirectivePriority,
+    Document, Event, IncompleteAmount, InternedSet, MetaValue, NaiveDate, Note, Open, Pad, Posting,
+    Price, PriceAnnotation, Query, Transaction,
 };
 
 use crate::types::{
@@ -210,6 +210,11 @@ fn open_to_data(open: &Open) -> OpenData {
         account: open.account.to_string(),
         currencies: open.currencies.iter().map(ToString::to_string).collect(),
         booking: open.booking.clone(),
+        metadata: open
+            .meta
+            .iter()
+            .map(|(k, v)| (k.clone(), meta_value_to_data(v)))
+            .collect(),
     }
 }
 
@@ -358,8 +363,8 @@ fn data_to_transaction(
         flag,
         payee: data.payee.as_ref().map(|p| p.as_str().into()),
         narration: data.narration.as_str().into(),
-        tags: data.tags.iter().map(|t| t.as_str().into()).collect(),
-        links: data.links.iter().map(|l| l.as_str().into()).collect(),
+        tags: data.tags.iter().map(String::as_str).collect(),
+        links: data.links.iter().map(String::as_str).collect(),
         meta,
         postings,
     })
@@ -392,6 +397,7 @@ fn data_to_posting(data: &PostingData) -> Result<Posting, ConversionError> {
         price,
         flag,
         meta,
+        comment: None,
     })
 }
 
@@ -544,7 +550,11 @@ fn data_to_open(data: &OpenData, date: NaiveDate) -> Open {
         account: data.account.clone().into(),
         currencies: data.currencies.iter().map(|c| c.clone().into()).collect(),
         booking: data.booking.clone(),
-        meta: Default::default(),
+        meta: data
+            .metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), data_to_meta_value(v)))
+            .collect(),
     }
 }
 
@@ -600,8 +610,8 @@ fn data_to_document(data: &DocumentData, date: NaiveDate) -> Document {
         date,
         account: data.account.clone().into(),
         path: data.path.clone(),
-        tags: Vec::new(),
-        links: Vec::new(),
+        tags: InternedSet::new(),
+        links: InternedSet::new(),
         meta: Default::default(),
     }
 }
@@ -639,12 +649,48 @@ fn data_to_custom(data: &CustomData, date: NaiveDate) -> Custom {
 }
 
 /// Convert a list of serializable wrappers back to directives.
+///
+/// The input order is preserved; see [`sort_wrappers`] for the order
+/// plugins are expected to return.
 pub fn wrappers_to_directives(
     wrappers: &[DirectiveWrapper],
 ) -> Result<Vec<Directive>, ConversionError> {
     wrappers.iter().map(wrapper_to_directive).collect()
 }
 
+/// The sorting priority of a wrapped directive, as for [`Directive::priority`].
+#[must_use]
+pub const fn wrapper_priority(wrapper: &DirectiveWrapper) -> DirectivePriority {
+    match &wrapper.data {
+        DirectiveData::Open(_) => DirectivePriority::Open,
+        DirectiveData::Commodity(_) => DirectivePriority::Commodity,
+        DirectiveData::Pad(_) => DirectivePriority::Pad,
+        DirectiveData::Balance(_) => DirectivePriority::Balance,
+        DirectiveData::Transaction(_) => DirectivePriority::Transaction,
+        DirectiveData::Note(_) => DirectivePriority::Note,
+        DirectiveData::Document(_) => DirectivePriority::Document,
+        DirectiveData::Event(_) => DirectivePriority::Event,
+        DirectiveData::Query(_) => DirectivePriority::Query,
+        DirectiveData::Price(_) => DirectivePriority::Price,
+        DirectiveData::Close(_) => DirectivePriority::Close,
+        DirectiveData::Custom(_) => DirectivePriority::Custom,
+    }
+}
+
+/// Sort wrappers into ledger order: by date, then by [`DirectivePriority`].
+///
+/// The sort is stable, so directives with the same date and type keep their
+/// relative order. Plugins that add directives sort their output with this,
+/// which guarantees that a generated `open` precedes every use of the
+/// account on its date and a generated `close` follows them.
+pub fn sort_wrappers(wrappers: &mut [DirectiveWrapper]) {
+    wrappers.sort_by(|a, b| {
+        a.date
+            .cmp(&b.date)
+            .then_with(|| wrapper_priority(a).cmp(&wrapper_priority(b)))
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -663,8 +709,8 @@ mod tests {
             flag: '*',
             payee: Some("Grocery Store".into()),
             narration: "Weekly groceries".into(),
-            tags: vec!["food".into()],
-            links: vec!["grocery-2024".into()],
+            tags: std::iter::once("food").collect(),
+            links: std::iter::once("grocery-2024").collect(),
             meta: HashMap::new(),
             postings: vec![
                 Posting {
@@ -674,6 +720,7 @@ mod tests {
                     price: None,
                     flag: None,
                     meta: HashMap::new(),
+                    comment: None,
                 },
                 Posting {
                     account: "Assets:Checking".into(),
@@ -682,6 +729,7 @@ mod tests {
                     price: None,
                     flag: None,
                     meta: HashMap::new(),
+                    comment: None,
                 },
             ],
         };
@@ -821,8 +869,8 @@ mod tests {
                 date,
                 account: "Assets:Test".into(),
                 path: "/path/to/doc.pdf".to_string(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 meta: HashMap::new(),
             }),
             Directive::Query(Query {
@@ -844,4 +892,28 @@ mod tests {
 
         assert_eq!(directives.len(), roundtrip.len());
     }
+
+    #[test]
+    fn test_sort_wrappers_is_stable_by_date_and_priority() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let directives = vec![
+            Directive::Close(Close::new(date(2), "Assets:A")),
+            Directive::Transaction(Transaction::new(date(2), "first")),
+            Directive::Open(Open::new(date(2), "Assets:A")),
+            Directive::Transaction(Transaction::new(date(2), "second")),
+            Directive::Open(Open::new(date(1), "Assets:B")),
+        ];
+        let mut wrappers = directives_to_wrappers(&directives);
+        sort_wrappers(&mut wrappers);
+
+        let order: Vec<_> = wrappers
+            .iter()
+            .map(|w| match &w.data {
+                DirectiveData::Transaction(txn) => txn.narration.clone(),
+                _ => w.directive_type.clone(),
+            })
+            .collect();
+        assert_eq!(order, ["open", "open", "first", "second", "close"]);
+        assert_eq!(wrappers[0].date, "2024-01-01");
+    }
 }
@@ -14,6 +14,21 @@
 //! - **WASM Plugins**: Sandboxed plugins loaded from `.wasm` files
 //! - **Native Plugins**: Built-in plugins implemented in Rust
 //!
+//! WASM plugins export `process` to transform the directive stream, and
+//! may export `extract` to act as importers that turn a file's bytes into
+//! directives (see [`ExtractInput`]). Plugins with the
+//! [`PluginCapabilities::FUNCTIONS`] capability also provide scalar
+//! functions that BQL queries call as `plugin.function(...)` (see
+//! [`FunctionInput`]).
+//!
+//! # Versioning
+//!
+//! Plugins may export `plugin_api_version() -> u32` and
+//! `plugin_capabilities() -> u32` (bit flags of [`PluginCapabilities`]).
+//! Both are checked when the plugin is loaded, so a plugin built for a
+//! different interface version fails with a clear message instead of a
+//! `MessagePack` decoding error on its first call.
+//!
 //! # Built-in Plugins (14)
 //!
 //! - `implicit_prices`: Generates price entries from transaction costs/prices
@@ -21,16 +36,32 @@
 //! - `auto_accounts`: Auto-generates Open directives for used accounts
 //! - `auto_tag`: Auto-tag transactions by account patterns
 //! - `leafonly`: Errors on postings to non-leaf accounts
-//! - `noduplicates`: Hash-based duplicate transaction detection
-//! - `onecommodity`: Enforces single commodity per account
+//! - `noduplicates`: Hash-based duplicate transaction detection (hash stored as `txn_hash` metadata)
+//! - `onecommodity`: Enforces single commodity per account (optionally filtered by account pattern)
 //! - `unique_prices`: One price per day per currency pair
 //! - `check_closing`: Zero balance assertion on account closing
 //! - `close_tree`: Closes descendant accounts automatically
-//! - `coherent_cost`: Enforces cost OR price (not both) consistency
+//! - `coherent_cost`: Currencies held at cost are never used without cost
 //! - `sellgains`: Cross-checks capital gains against sales
 //! - `pedantic`: Enables all strict validation rules
 //! - `unrealized`: Calculates unrealized gains/losses
 //!
+//! # Custom Native Plugins
+//!
+//! Applications embedding rustledger can add their own native plugins by
+//! implementing [`NativePlugin`] and either passing them to
+//! [`NativePluginRegistry::register`] or registering them process-wide with
+//! [`register_native_plugin`], which makes them available to every registry
+//! created with [`NativePluginRegistry::new`].
+//!
+//! # Directive Ordering
+//!
+//! Plugins return directives in ledger order: by date, then by
+//! [`DirectivePriority`](rustledger_core::DirectivePriority), keeping the
+//! input order otherwise ([`sort_wrappers`]). Generated `open` directives
+//! therefore come before any use of the account on the same day, and
+//! generated `close` directives after all activity.
+//!
 //! # Example
 //!
 //! ```ignore
@@ -52,6 +83,8 @@
 #![deny(unsafe_code)]
 #![warn(missing_docs)]
 
+#[cfg(feature = "wasm-runtime")]
+pub mod cache;
 pub mod convert;
 pub mod native;
 #[cfg(feature = "python-plugins")]
@@ -60,13 +93,19 @@ pub mod python;
 pub mod runtime;
 pub mod types;
 
+#[cfg(feature = "wasm-runtime")]
+pub use cache::PluginCache;
 pub use convert::{
-    ConversionError, directive_to_wrapper, directives_to_wrappers, wrapper_to_directive,
-    wrappers_to_directives,
+    ConversionError, directive_to_wrapper, directives_to_wrappers, sort_wrappers, wrapper_priority,
+    wrapper_to_directive, wrappers_to_directives,
 };
-pub use native::{NativePlugin, NativePluginRegistry};
+pub use native::{NativePlugin, NativePluginRegistry, register_native_plugin};
 #[cfg(feature = "wasm-runtime")]
 pub use runtime::{
     Plugin, PluginManager, RuntimeConfig, WatchingPluginManager, validate_plugin_module,
 };
-pub use types::{PluginError, PluginErrorSeverity, PluginInput, PluginOptions, PluginOutput};
+pub use types::{
+    ExtractInput, ExtractOutput, FunctionInput, FunctionOutput, FunctionValue, PLUGIN_API_VERSION,
+    PluginCapabilities, PluginError, PluginErrorSeverity, PluginInput, PluginLog, PluginLogStream,
+    PluginOptions, PluginOutput,
+};
@@ -3,9 +3,13 @@
 //! These plugins run as native Rust code for maximum performance.
 //! They implement the same interface as WASM plugins.
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Mutex, PoisonError};
+
+use crate::convert::sort_wrappers;
 use crate::types::{
-    DirectiveData, DirectiveWrapper, DocumentData, OpenData, PluginError, PluginInput,
-    PluginOutput, TransactionData,
+    DirectiveData, DirectiveWrapper, DocumentData, MetaValueData, OpenData, PluginError,
+    PluginInput, PluginOutput, TransactionData,
 };
 
 /// Trait for native plugins.
@@ -20,14 +24,81 @@ pub trait NativePlugin: Send + Sync {
     fn process(&self, input: PluginInput) -> PluginOutput;
 }
 
-/// Registry of built-in native plugins.
+/// Constructor for a plugin registered with [`register_native_plugin`].
+pub type NativePluginFactory = fn() -> Box<dyn NativePlugin>;
+
+/// Plugins registered process-wide by the embedding application.
+static REGISTERED_PLUGINS: Mutex<Vec<NativePluginFactory>> = Mutex::new(Vec::new());
+
+/// Register a native plugin for every registry created afterwards with
+/// [`NativePluginRegistry::new`].
+///
+/// Applications embedding rustledger call this at startup to make their own
+/// plugins available to `plugin "name"` directives and to the CLI commands,
+/// without threading a registry through. A registered plugin replaces a
+/// built-in plugin with the same name.
+///
+/// ```
+/// use rustledger_plugin::{
+///     NativePlugin, NativePluginRegistry, PluginInput, PluginOutput, register_native_plugin,
+/// };
+///
+/// struct Audit;
+///
+/// impl NativePlugin for Audit {
+///     fn name(&self) -> &'static str {
+///         "acme_audit"
+///     }
+///     fn description(&self) -> &'static str {
+///         "ACME audit rules"
+///     }
+///     fn process(&self, input: PluginInput) -> PluginOutput {
+///         PluginOutput { directives: input.directives, errors: Vec::new(), logs: Vec::new() }
+///     }
+/// }
+///
+/// register_native_plugin(|| Box::new(Audit));
+/// assert!(NativePluginRegistry::new().contains("acme_audit"));
+/// ```
+pub fn register_native_plugin(factory: NativePluginFactory) {
+    REGISTERED_PLUGINS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(factory);
+}
+
+/// Registry of native plugins.
+///
+/// Holds the built-in plugins plus any added with [`register_native_plugin`]
+/// or [`NativePluginRegistry::register`].
 pub struct NativePluginRegistry {
     plugins: Vec<Box<dyn NativePlugin>>,
 }
 
 impl NativePluginRegistry {
-    /// Create a new registry with all built-in plugins.
+    /// Create a new registry with all built-in plugins and the plugins
+    /// registered with [`register_native_plugin`].
     pub fn new() -> Self {
+        let mut registry = Self::builtin();
+        let factories = REGISTERED_PLUGINS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for factory in factories {
+            registry.register(factory());
+        }
+        registry
+    }
+
+    /// Create a registry with no plugins.
+    pub fn empty() -> Self {
+        Self {
+            plugins: Vec::new(),
+        }
+    }
+
+    /// Create a registry with only the built-in plugins.
+    pub fn builtin() -> Self {
         Self {
             plugins: vec![
                 Box::new(ImplicitPricesPlugin),
@@ -49,10 +120,31 @@ impl NativePluginRegistry {
                 Box::new(CommodityAttrPlugin::new()),
                 Box::new(CheckAverageCostPlugin::new()),
                 Box::new(CurrencyAccountsPlugin::new()),
+                Box::new(ForecastPlugin),
             ],
         }
     }
 
+    /// Add a plugin, replacing any plugin with the same name.
+    pub fn register(&mut self, plugin: Box<dyn NativePlugin>) {
+        match self.plugins.iter().position(|p| p.name() == plugin.name()) {
+            Some(index) => self.plugins[index] = plugin,
+            None => self.plugins.push(plugin),
+        }
+    }
+
+    /// Builder-style variant of [`register`](Self::register).
+    #[must_use]
+    pub fn with_plugin(mut self, plugin: impl NativePlugin + 'static) -> Self {
+        self.register(Box::new(plugin));
+        self
+    }
+
+    /// Check if a plugin with this name is available in this registry.
+    pub fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
     /// Find a plugin by name.
     pub fn find(&self, name: &str) -> Option<&dyn NativePlugin> {
         // Check for beancount.plugins.* prefix
@@ -94,6 +186,7 @@ impl NativePluginRegistry {
                 | "commodity_attr"
                 | "check_average_cost"
                 | "currency_accounts"
+                | "forecast"
         )
     }
 }
@@ -185,6 +278,7 @@ impl NativePlugin for ImplicitPricesPlugin {
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -255,6 +349,7 @@ impl NativePlugin for CheckCommodityPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -281,6 +376,60 @@ mod tests {
         ));
         assert!(!NativePluginRegistry::is_builtin("my_custom_plugin"));
     }
+
+    struct CustomPlugin(&'static str);
+
+    impl NativePlugin for CustomPlugin {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn description(&self) -> &'static str {
+            "Custom test plugin"
+        }
+
+        fn process(&self, input: PluginInput) -> PluginOutput {
+            PluginOutput {
+                directives: Vec::new(),
+                errors: vec![PluginError::warning(format!(
+                    "{} saw {} directives",
+                    self.0,
+                    input.directives.len()
+                ))],
+                logs: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_custom_plugin() {
+        let builtin_count = NativePluginRegistry::builtin().list().len();
+        let registry = NativePluginRegistry::builtin().with_plugin(CustomPlugin("org_rules"));
+
+        assert_eq!(registry.list().len(), builtin_count + 1);
+        assert!(registry.contains("org_rules"));
+        assert!(registry.contains("beancount.plugins.org_rules"));
+        assert!(!NativePluginRegistry::empty().contains("org_rules"));
+    }
+
+    #[test]
+    fn test_register_replaces_builtin() {
+        let mut registry = NativePluginRegistry::builtin();
+        let builtin_count = registry.list().len();
+        registry.register(Box::new(CustomPlugin("leafonly")));
+
+        assert_eq!(registry.list().len(), builtin_count);
+        let plugin = registry.find("leafonly").unwrap();
+        assert_eq!(plugin.description(), "Custom test plugin");
+    }
+
+    #[test]
+    fn test_register_native_plugin_global() {
+        register_native_plugin(|| Box::new(CustomPlugin("global_rules")));
+
+        assert!(NativePluginRegistry::new().contains("global_rules"));
+        assert!(!NativePluginRegistry::builtin().contains("global_rules"));
+    }
 }
 
 /// Plugin that automatically adds tags based on account patterns.
@@ -356,6 +505,7 @@ impl NativePlugin for AutoTagPlugin {
         PluginOutput {
             directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -442,66 +592,60 @@ impl NativePlugin for AutoAccountsPlugin {
     }
 
     fn process(&self, input: PluginInput) -> PluginOutput {
-        use std::collections::{HashMap, HashSet};
+        use std::collections::{BTreeMap, HashSet};
 
-        let mut opened_accounts: HashSet<String> = HashSet::new();
-        let mut account_first_use: HashMap<String, String> = HashMap::new(); // account -> date
+        let mut opened_accounts: HashSet<&str> = HashSet::new();
+        // account -> earliest date it is used on
+        let mut account_first_use: BTreeMap<&str, &str> = BTreeMap::new();
 
         // First pass: find all open directives and first use of each account
         for wrapper in &input.directives {
-            match &wrapper.data {
+            let used: Vec<&str> = match &wrapper.data {
                 DirectiveData::Open(data) => {
-                    opened_accounts.insert(data.account.clone());
+                    opened_accounts.insert(&data.account);
+                    continue;
                 }
                 DirectiveData::Transaction(txn) => {
-                    for posting in &txn.postings {
-                        account_first_use
-                            .entry(posting.account.clone())
-                            .or_insert_with(|| wrapper.date.clone());
-                    }
-                }
-                DirectiveData::Balance(data) => {
-                    account_first_use
-                        .entry(data.account.clone())
-                        .or_insert_with(|| wrapper.date.clone());
+                    txn.postings.iter().map(|p| p.account.as_str()).collect()
                 }
-                DirectiveData::Pad(data) => {
-                    account_first_use
-                        .entry(data.account.clone())
-                        .or_insert_with(|| wrapper.date.clone());
-                    account_first_use
-                        .entry(data.source_account.clone())
-                        .or_insert_with(|| wrapper.date.clone());
+                DirectiveData::Balance(data) => vec![&data.account],
+                DirectiveData::Pad(data) => vec![&data.account, &data.source_account],
+                _ => continue,
+            };
+            for account in used {
+                let first = account_first_use.entry(account).or_insert(&wrapper.date);
+                if wrapper.date.as_str() < *first {
+                    *first = &wrapper.date;
                 }
-                _ => {}
             }
         }
 
-        // Generate open directives for accounts without explicit open
-        let mut new_directives: Vec<DirectiveWrapper> = Vec::new();
-        for (account, date) in &account_first_use {
-            if !opened_accounts.contains(account) {
-                new_directives.push(DirectiveWrapper {
-                    directive_type: "open".to_string(),
-                    date: date.clone(),
-                    data: DirectiveData::Open(OpenData {
-                        account: account.clone(),
-                        currencies: vec![],
-                        booking: None,
-                    }),
-                });
-            }
-        }
+        // Generate open directives for accounts without explicit open,
+        // dated at the first use so they precede every use
+        let mut new_directives: Vec<DirectiveWrapper> = account_first_use
+            .into_iter()
+            .filter(|(account, _)| !opened_accounts.contains(account))
+            .map(|(account, date)| DirectiveWrapper {
+                directive_type: "open".to_string(),
+                date: date.to_string(),
+                data: DirectiveData::Open(OpenData {
+                    account: account.to_string(),
+                    currencies: vec![],
+                    booking: None,
+                    metadata: vec![],
+                }),
+            })
+            .collect();
 
         // Add existing directives
         new_directives.extend(input.directives);
 
-        // Sort by date
-        new_directives.sort_by(|a, b| a.date.cmp(&b.date));
+        sort_wrappers(&mut new_directives);
 
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -559,11 +703,158 @@ impl NativePlugin for LeafOnlyPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
 
-/// Plugin that detects duplicate transactions based on hash.
+/// Metadata key under which [`NoDuplicatesPlugin`] stores each
+/// transaction's hash.
+pub const TRANSACTION_HASH_KEY: &str = "txn_hash";
+
+/// Which parts of a transaction [`transaction_hash`] covers.
+///
+/// The date, flag, payee, links and postings (accounts, units, costs and
+/// prices) are always included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashFields {
+    /// Include transaction and posting metadata (default: false).
+    pub meta: bool,
+    /// Include tags (default: true).
+    pub tags: bool,
+    /// Include the narration (default: true).
+    pub narration: bool,
+}
+
+impl Default for HashFields {
+    fn default() -> Self {
+        Self {
+            meta: false,
+            tags: true,
+            narration: true,
+        }
+    }
+}
+
+impl HashFields {
+    /// Parse a configuration string in Python dict-like format.
+    ///
+    /// Example: `"{'meta': True, 'narration': False}"`. Fields not given
+    /// keep their default.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem for unknown fields or values
+    /// other than booleans.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut fields = Self::default();
+
+        let trimmed = config.trim();
+        let content = trimmed
+            .strip_prefix('{')
+            .and_then(|c| c.strip_suffix('}'))
+            .unwrap_or(trimmed);
+
+        for entry in content.split(',').filter(|e| !e.trim().is_empty()) {
+            let (key, value) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("expected 'field': True/False, got '{}'", entry.trim()))?;
+            let key = key.trim().trim_matches('\'').trim_matches('"');
+            let value = match value.trim() {
+                "True" | "true" => true,
+                "False" | "false" => false,
+                other => return Err(format!("'{key}' must be True or False, got '{other}'")),
+            };
+            match key {
+                "meta" => fields.meta = value,
+                "tags" => fields.tags = value,
+                "narration" => fields.narration = value,
+                _ => {
+                    return Err(format!(
+                        "unknown field '{key}' (expected meta, tags or narration)"
+                    ));
+                }
+            }
+        }
+
+        Ok(fields)
+    }
+}
+
+/// Content hash of a transaction, as a 16-digit hex string.
+///
+/// The hash is stable across runs and versions, so tools can store it and
+/// compare later (e.g. to skip already imported transactions). Metadata
+/// ordering and the [`TRANSACTION_HASH_KEY`] entry itself do not affect it.
+pub fn transaction_hash(date: &str, txn: &TransactionData, fields: &HashFields) -> String {
+    use crate::types::MetaValueData;
+
+    /// FNV-1a, chosen over `DefaultHasher` for its fixed output.
+    struct Fnv(u64);
+
+    impl Fnv {
+        fn write(&mut self, part: &str) {
+            for byte in part.bytes().chain(std::iter::once(0)) {
+                self.0 ^= u64::from(byte);
+                self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+
+        fn write_json<T: serde::Serialize>(&mut self, value: &T) {
+            self.write(&serde_json::to_string(value).unwrap_or_default());
+        }
+
+        fn write_meta(&mut self, meta: &[(String, MetaValueData)]) {
+            let mut entries: Vec<_> = meta
+                .iter()
+                .filter(|(key, _)| key != TRANSACTION_HASH_KEY)
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, value) in entries {
+                self.write(key);
+                self.write_json(value);
+            }
+        }
+    }
+
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    hasher.write(date);
+    hasher.write(&txn.flag);
+    hasher.write(txn.payee.as_deref().unwrap_or_default());
+    if fields.narration {
+        hasher.write(&txn.narration);
+    }
+    if fields.tags {
+        let mut tags: Vec<_> = txn.tags.iter().collect();
+        tags.sort();
+        hasher.write_json(&tags);
+    }
+    let mut links: Vec<_> = txn.links.iter().collect();
+    links.sort();
+    hasher.write_json(&links);
+    if fields.meta {
+        hasher.write_meta(&txn.metadata);
+    }
+    for posting in &txn.postings {
+        hasher.write(&posting.account);
+        hasher.write(posting.flag.as_deref().unwrap_or_default());
+        hasher.write_json(&posting.units);
+        hasher.write_json(&posting.cost);
+        hasher.write_json(&posting.price);
+        if fields.meta {
+            hasher.write_meta(&posting.metadata);
+        }
+    }
+
+    format!("{:016x}", hasher.0)
+}
+
+/// Plugin that detects duplicate transactions based on a content hash.
+///
+/// Each transaction's hash is stored in its metadata under
+/// [`TRANSACTION_HASH_KEY`]; an error is reported for every transaction
+/// whose hash was already seen. The fields covered are configured with a
+/// [`HashFields`] string, e.g. `plugin "noduplicates" "{'meta': True}"`.
 pub struct NoDuplicatesPlugin;
 
 impl NativePlugin for NoDuplicatesPlugin {
@@ -576,48 +867,188 @@ impl NativePlugin for NoDuplicatesPlugin {
     }
 
     fn process(&self, input: PluginInput) -> PluginOutput {
+        use crate::types::MetaValueData;
         use std::collections::HashSet;
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        fn hash_transaction(date: &str, txn: &TransactionData) -> u64 {
-            let mut hasher = DefaultHasher::new();
-            date.hash(&mut hasher);
-            txn.narration.hash(&mut hasher);
-            txn.payee.hash(&mut hasher);
-            for posting in &txn.postings {
-                posting.account.hash(&mut hasher);
-                if let Some(units) = &posting.units {
-                    units.number.hash(&mut hasher);
-                    units.currency.hash(&mut hasher);
-                }
+
+        let fields = match input.config.as_deref().map(HashFields::parse) {
+            None => HashFields::default(),
+            Some(Ok(fields)) => fields,
+            Some(Err(e)) => {
+                return PluginOutput {
+                    directives: input.directives,
+                    errors: vec![PluginError::error(format!(
+                        "Invalid noduplicates config: {e}"
+                    ))],
+                    logs: Vec::new(),
+                };
             }
-            hasher.finish()
-        }
+        };
 
-        let mut seen: HashSet<u64> = HashSet::new();
+        let mut seen: HashSet<String> = HashSet::new();
         let mut errors = Vec::new();
+        let mut directives = input.directives;
 
-        for wrapper in &input.directives {
-            if let DirectiveData::Transaction(txn) = &wrapper.data {
-                let hash = hash_transaction(&wrapper.date, txn);
-                if !seen.insert(hash) {
-                    errors.push(PluginError::error(format!(
-                        "Duplicate transaction: {} \"{}\"",
-                        wrapper.date, txn.narration
-                    )));
-                }
+        for wrapper in &mut directives {
+            let DirectiveData::Transaction(txn) = &mut wrapper.data else {
+                continue;
+            };
+            let hash = transaction_hash(&wrapper.date, txn, &fields);
+            if !seen.insert(hash.clone()) {
+                errors.push(PluginError::error(format!(
+                    "Duplicate transaction: {} \"{}\" (hash {hash})",
+                    wrapper.date, txn.narration
+                )));
             }
+
+            txn.metadata.retain(|(key, _)| key != TRANSACTION_HASH_KEY);
+            txn.metadata.push((
+                TRANSACTION_HASH_KEY.to_string(),
+                MetaValueData::String(hash),
+            ));
         }
 
         PluginOutput {
-            directives: input.directives,
+            directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
 
+/// Accounts checked by [`OneCommodityPlugin`] and [`CoherentCostPlugin`].
+///
+/// Patterns are regular expressions matched at the start of account names,
+/// as beancount does.
+#[derive(Debug, Clone, Default)]
+pub struct AccountFilter {
+    /// Only accounts matching this pattern are checked.
+    include: Option<regex::Regex>,
+    /// Accounts matching this pattern are never checked.
+    exclude: Option<regex::Regex>,
+}
+
+impl AccountFilter {
+    /// Parse a plugin configuration string.
+    ///
+    /// A bare pattern selects the accounts to check, as in beancount:
+    /// `plugin "onecommodity" "Assets:.*"`. The dict-like form can also
+    /// exclude accounts: `"{'include': 'Assets:.*', 'exclude': 'Assets:Broker'}"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem for unknown keys, malformed
+    /// entries or invalid patterns.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let trimmed = config.trim();
+        let Some(content) = trimmed.strip_prefix('{').and_then(|c| c.strip_suffix('}')) else {
+            return Ok(Self {
+                include: Self::compile(trimmed)?,
+                exclude: None,
+            });
+        };
+
+        let strings = quoted_strings(content)?;
+        if strings.len() % 2 != 0 {
+            return Err(format!("expected 'key': 'pattern' pairs, got '{content}'"));
+        }
+
+        let mut filter = Self::default();
+        for pair in strings.chunks(2) {
+            let pattern = Self::compile(&pair[1])?;
+            match pair[0].as_str() {
+                "include" => filter.include = pattern,
+                "exclude" => filter.exclude = pattern,
+                other => return Err(format!("unknown field '{other}'")),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn compile(pattern: &str) -> Result<Option<regex::Regex>, String> {
+        if pattern.is_empty() {
+            return Ok(None);
+        }
+        regex::Regex::new(&format!("^(?:{pattern})"))
+            .map(Some)
+            .map_err(|e| format!("invalid pattern '{pattern}': {e}"))
+    }
+
+    /// Whether `account` should be checked.
+    pub fn matches(&self, account: &str) -> bool {
+        self.include
+            .as_ref()
+            .map_or(true, |re| re.is_match(account))
+            && !self.exclude.as_ref().is_some_and(|re| re.is_match(account))
+    }
+}
+
+/// Split `'a': 'b', "c": "d"` into its quoted strings.
+fn quoted_strings(content: &str) -> Result<Vec<String>, String> {
+    let mut strings = Vec::new();
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(d) if d == c => break,
+                        Some(d) => value.push(d),
+                        None => return Err(format!("unterminated string '{value}'")),
+                    }
+                }
+                strings.push(value);
+            }
+            c if c == ':' || c == ',' || c.is_whitespace() => {}
+            c => return Err(format!("unexpected '{c}' outside quotes")),
+        }
+    }
+    Ok(strings)
+}
+
+/// Whether an `open` directive turns a plugin's check off for its account
+/// with `<key>: FALSE` metadata.
+fn opted_out(open: &OpenData, key: &str) -> bool {
+    open.metadata
+        .iter()
+        .any(|(k, v)| k == key && matches!(v, MetaValueData::Bool(false)))
+}
+
+/// Metadata of a directive, for locating errors about it.
+fn directive_metadata(wrapper: &DirectiveWrapper) -> &[(String, MetaValueData)] {
+    match &wrapper.data {
+        DirectiveData::Transaction(txn) => &txn.metadata,
+        DirectiveData::Open(open) => &open.metadata,
+        DirectiveData::Commodity(comm) => &comm.metadata,
+        _ => &[],
+    }
+}
+
+/// Currencies seen per account, with the directive that first mixed them.
+type CurrencyUses<'a> = BTreeMap<&'a str, (BTreeSet<&'a str>, Option<&'a DirectiveWrapper>)>;
+
+fn record_currency<'a>(
+    uses: &mut CurrencyUses<'a>,
+    account: &'a str,
+    currency: &'a str,
+    wrapper: &'a DirectiveWrapper,
+) {
+    if currency.is_empty() {
+        return;
+    }
+    let (currencies, mixed_at) = uses.entry(account).or_default();
+    if currencies.insert(currency) && currencies.len() > 1 && mixed_at.is_none() {
+        *mixed_at = Some(wrapper);
+    }
+}
+
 /// Plugin that enforces single commodity per account.
+///
+/// Both the units and the cost currencies of postings are checked, along
+/// with the currencies of balance assertions. Accounts whose `open`
+/// directive allows several currencies, or sets `onecommodity: FALSE`, are
+/// skipped; the configuration string narrows the checked accounts further
+/// (see [`AccountFilter::parse`]).
 pub struct OneCommodityPlugin;
 
 impl NativePlugin for OneCommodityPlugin {
@@ -630,35 +1061,90 @@ impl NativePlugin for OneCommodityPlugin {
     }
 
     fn process(&self, input: PluginInput) -> PluginOutput {
-        use std::collections::HashMap;
+        use std::collections::HashSet;
 
-        // Track currencies used per account
-        let mut account_currencies: HashMap<String, String> = HashMap::new();
-        let mut errors = Vec::new();
+        let filter = match input.config.as_deref().map(AccountFilter::parse) {
+            None => AccountFilter::default(),
+            Some(Ok(filter)) => filter,
+            Some(Err(e)) => {
+                return PluginOutput {
+                    directives: input.directives,
+                    errors: vec![PluginError::error(format!(
+                        "Invalid onecommodity config: {e}"
+                    ))],
+                    logs: Vec::new(),
+                };
+            }
+        };
+
+        let mut skipped: HashSet<&str> = HashSet::new();
+        let mut units = CurrencyUses::new();
+        let mut costs = CurrencyUses::new();
 
         for wrapper in &input.directives {
-            if let DirectiveData::Transaction(txn) = &wrapper.data {
-                for posting in &txn.postings {
-                    if let Some(units) = &posting.units {
-                        if let Some(existing) = account_currencies.get(&posting.account) {
-                            if existing != &units.currency {
-                                errors.push(PluginError::error(format!(
-                                    "Account '{}' uses multiple currencies: {} and {}",
-                                    posting.account, existing, units.currency
-                                )));
-                            }
-                        } else {
-                            account_currencies
-                                .insert(posting.account.clone(), units.currency.clone());
+            match &wrapper.data {
+                DirectiveData::Open(open) => {
+                    if open.currencies.len() > 1 || opted_out(open, "onecommodity") {
+                        skipped.insert(&open.account);
+                    }
+                }
+                DirectiveData::Transaction(txn) => {
+                    for posting in &txn.postings {
+                        if !filter.matches(&posting.account) {
+                            continue;
+                        }
+                        if let Some(amount) = &posting.units {
+                            record_currency(
+                                &mut units,
+                                &posting.account,
+                                &amount.currency,
+                                wrapper,
+                            );
+                        }
+                        if let Some(currency) =
+                            posting.cost.as_ref().and_then(|c| c.currency.as_deref())
+                        {
+                            record_currency(&mut costs, &posting.account, currency, wrapper);
                         }
                     }
                 }
+                DirectiveData::Balance(balance) if filter.matches(&balance.account) => {
+                    record_currency(
+                        &mut units,
+                        &balance.account,
+                        &balance.amount.currency,
+                        wrapper,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        let mut errors = Vec::new();
+        for (kind, uses) in [("currency", &units), ("cost currency", &costs)] {
+            for (account, (currencies, mixed_at)) in uses {
+                let Some(wrapper) = mixed_at else {
+                    continue;
+                };
+                if skipped.contains(account) {
+                    continue;
+                }
+                let currencies: Vec<_> = currencies.iter().copied().collect();
+                errors.push(
+                    PluginError::error(format!(
+                        "More than one {kind} in account '{account}': {} (mixed on {})",
+                        currencies.join(", "),
+                        wrapper.date
+                    ))
+                    .at_directive(directive_metadata(wrapper)),
+                );
             }
         }
 
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -701,6 +1187,7 @@ impl NativePlugin for UniquePricesPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -771,12 +1258,12 @@ impl NativePlugin for DocumentDiscoveryPlugin {
         let mut all_directives = input.directives;
         all_directives.extend(new_directives);
 
-        // Sort by date
-        all_directives.sort_by(|a, b| a.date.cmp(&b.date));
+        sort_wrappers(&mut all_directives);
 
         PluginOutput {
             directives: all_directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -906,12 +1393,12 @@ impl NativePlugin for CheckClosingPlugin {
             }
         }
 
-        // Sort by date
-        new_directives.sort_by(|a, b| a.date.cmp(&b.date));
+        sort_wrappers(&mut new_directives);
 
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -969,72 +1456,84 @@ impl NativePlugin for CloseTreePlugin {
 
     fn process(&self, input: PluginInput) -> PluginOutput {
         use crate::types::CloseData;
-        use std::collections::HashSet;
+        use std::collections::{BTreeMap, BTreeSet, HashSet};
 
         // Collect all accounts that are used
-        let mut all_accounts: HashSet<String> = HashSet::new();
+        let mut all_accounts: BTreeSet<&str> = BTreeSet::new();
         for wrapper in &input.directives {
-            if let DirectiveData::Open(data) = &wrapper.data {
-                all_accounts.insert(data.account.clone());
-            }
-            if let DirectiveData::Transaction(txn) = &wrapper.data {
-                for posting in &txn.postings {
-                    all_accounts.insert(posting.account.clone());
+            match &wrapper.data {
+                DirectiveData::Open(data) => {
+                    all_accounts.insert(&data.account);
+                }
+                DirectiveData::Transaction(txn) => {
+                    all_accounts.extend(txn.postings.iter().map(|p| p.account.as_str()));
+                }
+                DirectiveData::Balance(data) => {
+                    all_accounts.insert(&data.account);
                 }
+                DirectiveData::Pad(data) => {
+                    all_accounts.insert(&data.account);
+                    all_accounts.insert(&data.source_account);
+                }
+                _ => {}
             }
         }
 
-        // Collect accounts that are explicitly closed
-        let mut closed_parents: Vec<(String, String)> = Vec::new(); // (account, date)
+        // Collect accounts that are explicitly closed; these are never closed again
+        let mut closed_parents: Vec<(&str, &str)> = Vec::new(); // (account, date)
+        let mut explicitly_closed: HashSet<&str> = HashSet::new();
         for wrapper in &input.directives {
             if let DirectiveData::Close(data) = &wrapper.data {
-                closed_parents.push((data.account.clone(), wrapper.date.clone()));
+                closed_parents.push((&data.account, &wrapper.date));
+                explicitly_closed.insert(&data.account);
             }
         }
 
-        // Find child accounts for each closed parent
-        let mut new_directives = input.directives;
-
+        // Close each open descendant at the earliest close date of its
+        // closed ancestors
+        let mut descendant_close: BTreeMap<&str, &str> = BTreeMap::new();
         for (parent, close_date) in &closed_parents {
             let prefix = format!("{parent}:");
-            for account in &all_accounts {
-                if account.starts_with(&prefix) {
-                    // Check if already closed
-                    let already_closed = new_directives.iter().any(|w| {
-                        if let DirectiveData::Close(data) = &w.data {
-                            &data.account == account
-                        } else {
-                            false
-                        }
-                    });
-
-                    if !already_closed {
-                        new_directives.push(DirectiveWrapper {
-                            directive_type: "close".to_string(),
-                            date: close_date.clone(),
-                            data: DirectiveData::Close(CloseData {
-                                account: account.clone(),
-                            }),
-                        });
-                    }
+            for account in all_accounts
+                .iter()
+                .filter(|a| a.starts_with(&prefix) && !explicitly_closed.contains(*a))
+            {
+                let date = descendant_close.entry(account).or_insert(close_date);
+                if close_date < date {
+                    *date = close_date;
                 }
             }
         }
 
-        // Sort by date
-        new_directives.sort_by(|a, b| a.date.cmp(&b.date));
+        let generated: Vec<DirectiveWrapper> = descendant_close
+            .into_iter()
+            .map(|(account, date)| DirectiveWrapper {
+                directive_type: "close".to_string(),
+                date: date.to_string(),
+                data: DirectiveData::Close(CloseData {
+                    account: account.to_string(),
+                }),
+            })
+            .collect();
+
+        let mut new_directives = input.directives;
+        new_directives.extend(generated);
+        sort_wrappers(&mut new_directives);
 
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
 
-/// Plugin that ensures currencies use cost OR price consistently, never both.
+/// Plugin that ensures currencies held at cost are never used without cost.
 ///
-/// If a currency is used with cost notation `{...}`, it should not also be used
-/// with price notation `@` in the same ledger, as this can lead to inconsistencies.
+/// Moving a commodity held at cost without its cost, for instance selling
+/// it at a price with no lot, is usually a mistake. Accounts whose `open`
+/// directive sets `coherent_cost: FALSE` are skipped, and the configuration
+/// string narrows the checked accounts (see [`AccountFilter::parse`]).
 pub struct CoherentCostPlugin;
 
 impl NativePlugin for CoherentCostPlugin {
@@ -1043,52 +1542,79 @@ impl NativePlugin for CoherentCostPlugin {
     }
 
     fn description(&self) -> &'static str {
-        "Enforce cost OR price (not both) consistency"
+        "Enforce consistent use of cost per currency"
     }
 
     fn process(&self, input: PluginInput) -> PluginOutput {
-        use std::collections::{HashMap, HashSet};
+        use std::collections::HashSet;
 
-        // Track which currencies are used with cost vs price
-        let mut currencies_with_cost: HashSet<String> = HashSet::new();
-        let mut currencies_with_price: HashSet<String> = HashSet::new();
-        let mut first_use: HashMap<String, (String, String)> = HashMap::new(); // currency -> (type, date)
+        let filter = match input.config.as_deref().map(AccountFilter::parse) {
+            None => AccountFilter::default(),
+            Some(Ok(filter)) => filter,
+            Some(Err(e)) => {
+                return PluginOutput {
+                    directives: input.directives,
+                    errors: vec![PluginError::error(format!(
+                        "Invalid coherent_cost config: {e}"
+                    ))],
+                    logs: Vec::new(),
+                };
+            }
+        };
 
-        for wrapper in &input.directives {
-            if let DirectiveData::Transaction(txn) = &wrapper.data {
-                for posting in &txn.postings {
-                    if let Some(units) = &posting.units {
-                        let currency = &units.currency;
+        let skipped: HashSet<&str> = input
+            .directives
+            .iter()
+            .filter_map(|wrapper| match &wrapper.data {
+                DirectiveData::Open(open) if opted_out(open, "coherent_cost") => {
+                    Some(open.account.as_str())
+                }
+                _ => None,
+            })
+            .collect();
 
-                        if posting.cost.is_some() && !currencies_with_cost.contains(currency) {
-                            currencies_with_cost.insert(currency.clone());
-                            first_use
-                                .entry(currency.clone())
-                                .or_insert(("cost".to_string(), wrapper.date.clone()));
-                        }
+        // First transaction using each currency with and without cost
+        let mut with_cost: BTreeMap<&str, &DirectiveWrapper> = BTreeMap::new();
+        let mut without_cost: BTreeMap<&str, &DirectiveWrapper> = BTreeMap::new();
 
-                        if posting.price.is_some() && !currencies_with_price.contains(currency) {
-                            currencies_with_price.insert(currency.clone());
-                            first_use
-                                .entry(currency.clone())
-                                .or_insert(("price".to_string(), wrapper.date.clone()));
-                        }
-                    }
+        for wrapper in &input.directives {
+            let DirectiveData::Transaction(txn) = &wrapper.data else {
+                continue;
+            };
+            for posting in &txn.postings {
+                if !filter.matches(&posting.account) || skipped.contains(posting.account.as_str()) {
+                    continue;
                 }
+                let Some(units) = posting.units.as_ref().filter(|u| !u.currency.is_empty()) else {
+                    continue;
+                };
+                let uses = if posting.cost.is_some() {
+                    &mut with_cost
+                } else {
+                    &mut without_cost
+                };
+                uses.entry(units.currency.as_str()).or_insert(wrapper);
             }
         }
 
-        // Find currencies used with both
-        let mut errors = Vec::new();
-        for currency in currencies_with_cost.intersection(&currencies_with_price) {
-            errors.push(PluginError::error(format!(
-                "Currency '{currency}' is used with both cost and price notation - this may cause inconsistencies"
-            )));
-        }
+        let errors = with_cost
+            .iter()
+            .filter_map(|(currency, held_at)| {
+                let wrapper = without_cost.get(currency)?;
+                Some(
+                    PluginError::error(format!(
+                        "Currency '{currency}' is used both with and without cost (at cost on {}, without on {})",
+                        held_at.date, wrapper.date
+                    ))
+                    .at_directive(directive_metadata(wrapper)),
+                )
+            })
+            .collect();
 
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -1097,6 +1623,8 @@ impl NativePlugin for CoherentCostPlugin {
 ///
 /// When selling a position at a price, this plugin verifies that any
 /// income/expense postings match the expected gain/loss from the sale.
+/// Lots are tracked per account, so the expected gain is computed from the
+/// lots the sale actually matches under the account's booking method.
 pub struct SellGainsPlugin;
 
 impl NativePlugin for SellGainsPlugin {
@@ -1109,57 +1637,87 @@ impl NativePlugin for SellGainsPlugin {
     }
 
     fn process(&self, input: PluginInput) -> PluginOutput {
-        use rust_decimal::Decimal;
-        use std::str::FromStr;
+        use rustledger_core::{Amount, BookingMethod, Directive, InternedStr, Inventory};
+        use std::collections::HashMap;
 
         let mut errors = Vec::new();
+        let mut booking: HashMap<InternedStr, BookingMethod> = HashMap::new();
+        let mut lots: HashMap<InternedStr, Inventory> = HashMap::new();
 
         for wrapper in &input.directives {
-            if let DirectiveData::Transaction(txn) = &wrapper.data {
-                // Find postings that are sales (negative units with cost and price)
-                for posting in &txn.postings {
-                    if let (Some(units), Some(cost), Some(price)) =
-                        (&posting.units, &posting.cost, &posting.price)
-                    {
-                        // Check if this is a sale (negative units)
-                        let units_num = Decimal::from_str(&units.number).unwrap_or_default();
-                        if units_num >= Decimal::ZERO {
-                            continue;
-                        }
-
-                        // Get cost basis
-                        let cost_per = cost
-                            .number_per
-                            .as_ref()
-                            .and_then(|s| Decimal::from_str(s).ok())
-                            .unwrap_or_default();
-
-                        // Get sale price
-                        let sale_price = price
-                            .amount
-                            .as_ref()
-                            .and_then(|a| Decimal::from_str(&a.number).ok())
-                            .unwrap_or_default();
-
-                        // Calculate expected gain/loss
-                        let expected_gain = (sale_price - cost_per) * units_num.abs();
+            let txn = match crate::convert::wrapper_to_directive(wrapper) {
+                Ok(Directive::Open(open)) => {
+                    let method = open
+                        .booking
+                        .as_deref()
+                        .and_then(|b| b.parse().ok())
+                        .unwrap_or_default();
+                    booking.insert(open.account, method);
+                    continue;
+                }
+                Ok(Directive::Transaction(txn)) => txn,
+                _ => continue,
+            };
 
-                        // Look for income/expense posting that should match
-                        let has_gain_posting = txn.postings.iter().any(|p| {
-                            p.account.starts_with("Income:") || p.account.starts_with("Expenses:")
-                        });
+            // Look for income/expense posting that should match
+            let has_gain_posting = txn
+                .postings
+                .iter()
+                .any(|p| p.account.starts_with("Income:") || p.account.starts_with("Expenses:"));
 
-                        if expected_gain != Decimal::ZERO && !has_gain_posting {
-                            errors.push(PluginError::warning(format!(
-                                "Sale of {} {} at {} (cost {}) has expected gain/loss of {} but no Income/Expenses posting",
-                                units_num.abs(),
-                                units.currency,
-                                sale_price,
-                                cost_per,
-                                expected_gain
-                            )));
-                        }
-                    }
+            for posting in &txn.postings {
+                let (Some(units), Some(cost_spec)) = (posting.amount(), &posting.cost) else {
+                    continue;
+                };
+                let method = booking.get(&posting.account).copied().unwrap_or_default();
+                let booked = lots
+                    .entry(posting.account.clone())
+                    .or_default()
+                    .book_at_cost(units, cost_spec, method, txn.date);
+
+                // Only sales (negative units) at a price realize a gain
+                let Some(price) = posting.price.as_ref().and_then(|p| p.amount()) else {
+                    continue;
+                };
+                if !units.number.is_sign_negative() {
+                    continue;
+                }
+                let sale_price = if posting.price.as_ref().is_some_and(|p| !p.is_unit()) {
+                    Amount::new(price.number / units.number.abs(), price.currency.clone())
+                } else {
+                    price.clone()
+                };
+
+                // Without matching lots, fall back to the posting's own cost
+                let expected_gain = match booked {
+                    Ok(Some(result)) => result.realized_gain(&sale_price),
+                    _ => cost_spec
+                        .resolve(units.number, txn.date)
+                        .filter(|cost| cost.currency == sale_price.currency)
+                        .map(|cost| {
+                            Amount::new(
+                                (sale_price.number - cost.number) * units.number.abs(),
+                                sale_price.currency.clone(),
+                            )
+                        }),
+                };
+                let Some(expected_gain) = expected_gain else {
+                    continue;
+                };
+
+                if !expected_gain.number.is_zero() && !has_gain_posting {
+                    let cost_per = cost_spec
+                        .number_per
+                        .map(|n| n.to_string())
+                        .unwrap_or_default();
+                    errors.push(PluginError::warning(format!(
+                        "Sale of {} {} at {} (cost {}) has expected gain/loss of {} but no Income/Expenses posting",
+                        units.number.abs(),
+                        units.currency,
+                        sale_price.number,
+                        cost_per,
+                        expected_gain.number
+                    )));
                 }
             }
         }
@@ -1167,6 +1725,7 @@ impl NativePlugin for SellGainsPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -1231,6 +1790,7 @@ impl NativePlugin for PedanticPlugin {
         PluginOutput {
             directives: input.directives,
             errors: all_errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -1360,6 +1920,7 @@ impl NativePlugin for UnrealizedPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -1448,6 +2009,7 @@ impl NativePlugin for NoUnusedPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -1470,6 +2032,7 @@ mod nounused_tests {
                         account: "Assets:Bank".to_string(),
                         currencies: vec![],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1479,6 +2042,7 @@ mod nounused_tests {
                         account: "Assets:Unused".to_string(),
                         currencies: vec![],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1531,6 +2095,7 @@ mod nounused_tests {
                         account: "Assets:Bank".to_string(),
                         currencies: vec![],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1581,6 +2146,7 @@ mod nounused_tests {
                         account: "Assets:OldAccount".to_string(),
                         currencies: vec![],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1705,12 +2271,12 @@ impl NativePlugin for CheckDrainedPlugin {
             }
         }
 
-        // Sort by date
-        new_directives.sort_by(|a, b| a.date.cmp(&b.date));
+        sort_wrappers(&mut new_directives);
 
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -1733,6 +2299,7 @@ mod check_drained_tests {
                         account: "Assets:Bank".to_string(),
                         currencies: vec!["USD".to_string()],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1809,6 +2376,7 @@ mod check_drained_tests {
                         account: "Income:Salary".to_string(),
                         currencies: vec!["USD".to_string()],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -1850,6 +2418,7 @@ mod check_drained_tests {
                         account: "Assets:Bank".to_string(),
                         currencies: vec![],
                         booking: None,
+                        metadata: vec![],
                     }),
                 },
                 DirectiveWrapper {
@@ -2055,6 +2624,7 @@ impl NativePlugin for CommodityAttrPlugin {
             return PluginOutput {
                 directives: input.directives,
                 errors: Vec::new(),
+                logs: Vec::new(),
             };
         }
 
@@ -2097,6 +2667,7 @@ impl NativePlugin for CommodityAttrPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -2369,6 +2940,7 @@ impl NativePlugin for CheckAverageCostPlugin {
         PluginOutput {
             directives: input.directives,
             errors,
+            logs: Vec::new(),
         }
     }
 }
@@ -2754,6 +3326,7 @@ impl NativePlugin for CurrencyAccountsPlugin {
         PluginOutput {
             directives: new_directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
@@ -2958,3 +3531,368 @@ mod currency_accounts_tests {
         }
     }
 }
+
+/// Plugin that expands recurring forecast transactions into future entries.
+///
+/// A transaction flagged `#` with a `forecast` metadata value such as
+/// `"MONTHLY UNTIL 2025-12-31"` is replaced by one copy per occurrence,
+/// starting on its own date. As in beancount's forecast plugin, the rule can
+/// instead be written at the end of the narration in brackets:
+/// `# "Rent [MONTHLY REPEAT 12 TIMES]"`.
+///
+/// A rule is a period (`DAILY`, `WEEKLY`, `MONTHLY` or `YEARLY`) optionally
+/// followed by `SKIP n TIMES` (occur every n+1 periods), `REPEAT n TIMES`
+/// and `UNTIL date`. Without `REPEAT` or `UNTIL`, occurrences run to the end
+/// of the current year.
+///
+/// Generated entries keep the `#` flag and the `forecast` metadata, which
+/// is how validation recognizes them and skips its future-date warning.
+pub struct ForecastPlugin;
+
+/// How often a forecast transaction recurs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForecastPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed forecast recurrence rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ForecastRule {
+    period: ForecastPeriod,
+    /// Number of periods between occurrences.
+    interval: u32,
+    /// Maximum number of occurrences.
+    repeat: Option<u32>,
+    /// Last date an occurrence may fall on.
+    until: Option<chrono::NaiveDate>,
+}
+
+impl ForecastRule {
+    /// Parse a rule such as `MONTHLY SKIP 1 TIME UNTIL 2025-12-31`.
+    fn parse(rule: &str) -> Result<Self, String> {
+        let words: Vec<String> = rule.split_whitespace().map(str::to_uppercase).collect();
+        let Some((period, mut rest)) = words.split_first() else {
+            return Err("empty forecast rule".to_string());
+        };
+        let period = match period.as_str() {
+            "DAILY" => ForecastPeriod::Daily,
+            "WEEKLY" => ForecastPeriod::Weekly,
+            "MONTHLY" => ForecastPeriod::Monthly,
+            "YEARLY" => ForecastPeriod::Yearly,
+            other => return Err(format!("unknown forecast period '{other}'")),
+        };
+
+        let mut parsed = Self {
+            period,
+            interval: 1,
+            repeat: None,
+            until: None,
+        };
+        let count = |words: &[String]| -> Result<u32, String> {
+            match words {
+                [n, unit, ..] if unit == "TIME" || unit == "TIMES" => n
+                    .parse()
+                    .map_err(|_| format!("invalid forecast count '{n}'")),
+                _ => Err("expected 'n TIMES' in forecast rule".to_string()),
+            }
+        };
+
+        while let Some((keyword, args)) = rest.split_first() {
+            match keyword.as_str() {
+                "SKIP" => {
+                    parsed.interval = count(args)? + 1;
+                    rest = &args[2..];
+                }
+                "REPEAT" => {
+                    parsed.repeat = Some(count(args)?);
+                    rest = &args[2..];
+                }
+                "UNTIL" => {
+                    let date = args
+                        .first()
+                        .ok_or_else(|| "expected a date after UNTIL".to_string())?;
+                    parsed.until = Some(
+                        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                            .map_err(|_| format!("invalid forecast date '{date}'"))?,
+                    );
+                    rest = &args[1..];
+                }
+                other => return Err(format!("unexpected '{other}' in forecast rule")),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Dates of every occurrence starting at `start`, ending at `default_until`
+    /// when the rule sets neither `REPEAT` nor `UNTIL`.
+    fn dates(
+        &self,
+        start: chrono::NaiveDate,
+        default_until: chrono::NaiveDate,
+    ) -> Vec<chrono::NaiveDate> {
+        let until = match (self.until, self.repeat) {
+            (Some(until), _) => until,
+            (None, Some(_)) => chrono::NaiveDate::MAX,
+            (None, None) => default_until,
+        };
+
+        let mut dates = Vec::new();
+        for index in 0.. {
+            if self.repeat.is_some_and(|repeat| index >= repeat) {
+                break;
+            }
+            let step = index * self.interval;
+            // Offsets are taken from the start date so month ends don't drift
+            let date = match self.period {
+                ForecastPeriod::Daily => start.checked_add_days(chrono::Days::new(step.into())),
+                ForecastPeriod::Weekly => {
+                    start.checked_add_days(chrono::Days::new(u64::from(step) * 7))
+                }
+                ForecastPeriod::Monthly => start.checked_add_months(chrono::Months::new(step)),
+                ForecastPeriod::Yearly => {
+                    start.checked_add_months(chrono::Months::new(step.saturating_mul(12)))
+                }
+            };
+            match date {
+                Some(date) if date <= until => dates.push(date),
+                _ => break,
+            }
+        }
+        dates
+    }
+}
+
+/// Split a forecast rule off a transaction, from its `forecast` metadata or
+/// a trailing `[RULE]` in the narration. Returns the rule and the narration
+/// to give the generated entries.
+fn forecast_rule(txn: &TransactionData) -> Option<(String, String)> {
+    use crate::types::MetaValueData;
+
+    if let Some((_, MetaValueData::String(rule))) =
+        txn.metadata.iter().find(|(key, _)| key == "forecast")
+    {
+        return Some((rule.clone(), txn.narration.clone()));
+    }
+
+    let narration = txn.narration.trim_end().strip_suffix(']')?;
+    let (text, rule) = narration.rsplit_once('[')?;
+    // Only brackets starting with a period are rules, not bracketed notes
+    let period = rule.split_whitespace().next()?.to_uppercase();
+    if !matches!(period.as_str(), "DAILY" | "WEEKLY" | "MONTHLY" | "YEARLY") {
+        return None;
+    }
+    Some((rule.trim().to_string(), text.trim_end().to_string()))
+}
+
+impl NativePlugin for ForecastPlugin {
+    fn name(&self) -> &'static str {
+        "forecast"
+    }
+
+    fn description(&self) -> &'static str {
+        "Expand recurring # transactions into future forecast entries"
+    }
+
+    fn process(&self, input: PluginInput) -> PluginOutput {
+        use crate::types::MetaValueData;
+        use chrono::{Datelike, NaiveDate};
+
+        let today = chrono::Local::now().date_naive();
+        let default_until = NaiveDate::from_ymd_opt(today.year(), 12, 31).unwrap_or(today);
+
+        let mut errors = Vec::new();
+        let mut new_directives = Vec::with_capacity(input.directives.len());
+        let mut expanded = false;
+
+        for wrapper in input.directives {
+            let DirectiveData::Transaction(txn) = &wrapper.data else {
+                new_directives.push(wrapper);
+                continue;
+            };
+            if txn.flag != "#" {
+                new_directives.push(wrapper);
+                continue;
+            }
+            let Some((rule_text, narration)) = forecast_rule(txn) else {
+                new_directives.push(wrapper);
+                continue;
+            };
+
+            let rule = match ForecastRule::parse(&rule_text) {
+                Ok(rule) => rule,
+                Err(message) => {
+                    errors.push(PluginError::error(format!(
+                        "Invalid forecast rule '{rule_text}' on {}: {message}",
+                        wrapper.date
+                    )));
+                    new_directives.push(wrapper);
+                    continue;
+                }
+            };
+            let Ok(start) = NaiveDate::parse_from_str(&wrapper.date, "%Y-%m-%d") else {
+                new_directives.push(wrapper);
+                continue;
+            };
+
+            let mut template = txn.clone();
+            template.narration = narration;
+            template.metadata.retain(|(key, _)| key != "forecast");
+            template
+                .metadata
+                .push(("forecast".to_string(), MetaValueData::String(rule_text)));
+
+            for date in rule.dates(start, default_until) {
+                new_directives.push(DirectiveWrapper {
+                    directive_type: wrapper.directive_type.clone(),
+                    date: date.format("%Y-%m-%d").to_string(),
+                    data: DirectiveData::Transaction(template.clone()),
+                });
+            }
+            expanded = true;
+        }
+
+        if expanded {
+            sort_wrappers(&mut new_directives);
+        }
+
+        PluginOutput {
+            directives: new_directives,
+            errors,
+            logs: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod forecast_tests {
+    use super::*;
+    use crate::types::*;
+
+    fn forecast_txn(date: &str, narration: &str, rule: Option<&str>) -> DirectiveWrapper {
+        DirectiveWrapper {
+            directive_type: "transaction".to_string(),
+            date: date.to_string(),
+            data: DirectiveData::Transaction(TransactionData {
+                flag: "#".to_string(),
+                payee: None,
+                narration: narration.to_string(),
+                tags: vec![],
+                links: vec![],
+                metadata: rule
+                    .map(|rule| {
+                        vec![(
+                            "forecast".to_string(),
+                            MetaValueData::String(rule.to_string()),
+                        )]
+                    })
+                    .unwrap_or_default(),
+                postings: vec![PostingData {
+                    account: "Expenses:Rent".to_string(),
+                    units: Some(AmountData {
+                        number: "1000".to_string(),
+                        currency: "USD".to_string(),
+                    }),
+                    cost: None,
+                    price: None,
+                    flag: None,
+                    metadata: vec![],
+                }],
+            }),
+        }
+    }
+
+    fn run(directives: Vec<DirectiveWrapper>) -> PluginOutput {
+        ForecastPlugin.process(PluginInput {
+            directives,
+            options: PluginOptions {
+                operating_currencies: vec!["USD".to_string()],
+                title: None,
+            },
+            config: None,
+        })
+    }
+
+    fn dates(output: &PluginOutput) -> Vec<&str> {
+        output.directives.iter().map(|d| d.date.as_str()).collect()
+    }
+
+    #[test]
+    fn test_forecast_metadata_until() {
+        let output = run(vec![forecast_txn(
+            "2025-01-31",
+            "Rent",
+            Some("MONTHLY UNTIL 2025-04-30"),
+        )]);
+        assert!(output.errors.is_empty());
+        assert_eq!(
+            dates(&output),
+            vec!["2025-01-31", "2025-02-28", "2025-03-31", "2025-04-30"]
+        );
+        let DirectiveData::Transaction(txn) = &output.directives[1].data else {
+            panic!("Expected transaction");
+        };
+        assert_eq!(txn.flag, "#");
+        assert_eq!(txn.narration, "Rent");
+        assert_eq!(txn.metadata.len(), 1);
+    }
+
+    #[test]
+    fn test_forecast_narration_repeat_and_skip() {
+        let output = run(vec![forecast_txn(
+            "2025-01-01",
+            "Insurance [WEEKLY SKIP 1 TIME REPEAT 3 TIMES]",
+            None,
+        )]);
+        assert_eq!(
+            dates(&output),
+            vec!["2025-01-01", "2025-01-15", "2025-01-29"]
+        );
+        let DirectiveData::Transaction(txn) = &output.directives[0].data else {
+            panic!("Expected transaction");
+        };
+        assert_eq!(txn.narration, "Insurance");
+        assert!(
+            matches!(&txn.metadata[..], [(key, MetaValueData::String(rule))]
+                if key == "forecast" && rule == "WEEKLY SKIP 1 TIME REPEAT 3 TIMES")
+        );
+    }
+
+    #[test]
+    fn test_forecast_ignores_other_transactions() {
+        let mut cleared = forecast_txn("2025-01-01", "Rent [MONTHLY REPEAT 3 TIMES]", None);
+        if let DirectiveData::Transaction(txn) = &mut cleared.data {
+            txn.flag = "*".to_string();
+        }
+        let bookmarked = forecast_txn("2025-01-02", "Look into this [ask bank]", None);
+
+        let output = run(vec![cleared, bookmarked]);
+        assert_eq!(dates(&output), vec!["2025-01-01", "2025-01-02"]);
+    }
+
+    #[test]
+    fn test_forecast_invalid_rule() {
+        let output = run(vec![forecast_txn(
+            "2025-01-01",
+            "Rent",
+            Some("FORTNIGHTLY"),
+        )]);
+        assert_eq!(output.errors.len(), 1);
+        assert!(output.errors[0].message.contains("unknown forecast period"));
+        assert_eq!(output.directives.len(), 1);
+    }
+
+    #[test]
+    fn test_forecast_rule_parse() {
+        let rule = ForecastRule::parse("yearly skip 2 times until 2030-06-30").unwrap();
+        assert_eq!(rule.period, ForecastPeriod::Yearly);
+        assert_eq!(rule.interval, 3);
+        assert_eq!(rule.repeat, None);
+        assert_eq!(rule.until, chrono::NaiveDate::from_ymd_opt(2030, 6, 30));
+        assert!(ForecastRule::parse("MONTHLY REPEAT").is_err());
+        assert!(ForecastRule::parse("MONTHLY UNTIL tomorrow").is_err());
+    }
+}
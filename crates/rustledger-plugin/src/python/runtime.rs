@@ -2,15 +2,22 @@
 //!
 //! This module provides the runtime for executing Python beancount plugins
 //! in a sandboxed WASM environment using `CPython` compiled to WASI.
+//!
+//! The interpreter's stdout and stderr are captured rather than inherited:
+//! each line is emitted as a `tracing` event tagged with the plugin name and
+//! returned in [`PluginOutput::logs`], so `print()` works for debugging.
 
 use super::PythonError;
 use super::compat::BEANCOUNT_COMPAT_PY;
 use super::download;
-use crate::types::{PluginError, PluginErrorSeverity, PluginInput, PluginOutput};
+use crate::types::{
+    PluginError, PluginErrorSeverity, PluginInput, PluginLog, PluginLogStream, PluginOutput,
+};
 use anyhow::Result;
 use std::sync::Arc;
 use wasmtime::{Config, Engine, Linker, Module, Store};
 use wasmtime_wasi::p1;
+use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
 use wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder};
 
 /// Python plugin runtime.
@@ -95,6 +102,17 @@ impl PythonRuntime {
         plugin_code: &str,
         plugin_func: &str,
         input: &PluginInput,
+    ) -> Result<PluginOutput, PythonError> {
+        self.execute_named(plugin_func, plugin_code, plugin_func, input)
+    }
+
+    /// Execute a Python plugin, tagging its captured output with `name`.
+    fn execute_named(
+        &self,
+        name: &str,
+        plugin_code: &str,
+        plugin_func: &str,
+        input: &PluginInput,
     ) -> Result<PluginOutput, PythonError> {
         // Serialize input to JSON
         let directives_json = serialize_directives_to_json(&input.directives)?;
@@ -141,10 +159,12 @@ with open('work/output.json', 'w') as f:
         );
 
         // Execute Python
-        let output = self.run_python(&script, BEANCOUNT_COMPAT_PY, plugin_code)?;
+        let (output, logs) = self.run_python(name, &script, BEANCOUNT_COMPAT_PY, plugin_code)?;
 
         // Parse output
-        parse_plugin_output(&output)
+        let mut output = parse_plugin_output(&output)?;
+        output.logs = logs;
+        Ok(output)
     }
 
     /// Execute a built-in beancount plugin by module name.
@@ -170,16 +190,18 @@ with open('work/output.json', 'w') as f:
             }
         };
 
-        self.execute_plugin(plugin_code, "plugin", input)
+        self.execute_named(module_name, plugin_code, "plugin", input)
     }
 
-    /// Run a Python script and return output via file.
+    /// Run a Python script and return output via file, along with the lines
+    /// it printed (already emitted as `tracing` events).
     fn run_python(
         &self,
+        name: &str,
         script: &str,
         compat_code: &str,
         plugin_code: &str,
-    ) -> Result<String, PythonError> {
+    ) -> Result<(String, Vec<PluginLog>), PythonError> {
         // Create a work directory for script and output
         let work_dir = tempfile::tempdir().map_err(PythonError::Io)?;
 
@@ -198,8 +220,11 @@ with open('work/output.json', 'w') as f:
         // Build WASI context
         let mut wasi_builder = WasiCtxBuilder::new();
 
-        // Inherit stderr for error messages
-        wasi_builder.inherit_stderr();
+        // Capture stdout and stderr so prints become plugin logs
+        let stdout = MemoryOutputPipe::new(MAX_CAPTURED_OUTPUT);
+        let stderr = MemoryOutputPipe::new(MAX_CAPTURED_OUTPUT);
+        wasi_builder.stdout(stdout.clone());
+        wasi_builder.stderr(stderr.clone());
 
         // Get the python-wasi root directory (parent of lib)
         let python_root = self.stdlib_path.parent().unwrap_or(&self.stdlib_path);
@@ -244,20 +269,47 @@ with open('work/output.json', 'w') as f:
             .map_err(PythonError::Wasm)?;
 
         // Run Python
-        start
-            .call(&mut store, ())
-            .map_err(|e| PythonError::Execution(format!("Python execution failed: {e}")))?;
+        let result = start.call(&mut store, ());
+
+        // Surface whatever the plugin printed, even if it then failed
+        let stderr = stderr.contents();
+        let mut logs = PluginLog::from_captured(name, PluginLogStream::Stdout, &stdout.contents());
+        logs.extend(PluginLog::from_captured(
+            name,
+            PluginLogStream::Stderr,
+            &stderr,
+        ));
+        for log in &logs {
+            log.emit();
+        }
+
+        // Tracebacks go to stderr, so include it in execution errors
+        let stderr = String::from_utf8_lossy(&stderr);
+        let stderr = stderr.trim_end();
+        let with_stderr = |message: String| {
+            if stderr.is_empty() {
+                PythonError::Execution(message)
+            } else {
+                PythonError::Execution(format!("{message}\n{stderr}"))
+            }
+        };
+
+        result.map_err(|e| with_stderr(format!("Python execution failed: {e}")))?;
 
         // Read output from file
         let output_path = work_dir.path().join("output.json");
-        std::fs::read_to_string(&output_path).map_err(|e| {
-            PythonError::Execution(format!(
+        let output = std::fs::read_to_string(&output_path).map_err(|e| {
+            with_stderr(format!(
                 "failed to read Python output: {e}. The plugin may have crashed."
             ))
-        })
+        })?;
+        Ok((output, logs))
     }
 }
 
+/// Maximum bytes captured from each of the interpreter's stdio streams.
+const MAX_CAPTURED_OUTPUT: usize = 1024 * 1024;
+
 /// Serialize directives to JSON for Python consumption.
 fn serialize_directives_to_json(
     directives: &[crate::types::DirectiveWrapper],
@@ -306,7 +358,11 @@ fn parse_plugin_output(output: &str) -> Result<PluginOutput, PythonError> {
         })
         .collect();
 
-    Ok(PluginOutput { directives, errors })
+    Ok(PluginOutput {
+        directives,
+        errors,
+        logs: Vec::new(),
+    })
 }
 
 // =============================================================================
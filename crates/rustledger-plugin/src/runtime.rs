@@ -9,12 +9,49 @@
 //! - **No filesystem access**: Plugins cannot read or write files
 //! - **No network access**: Plugins cannot make network connections
 //! - **No environment access**: Plugins cannot read environment variables
-//! - **No system calls**: No WASI or other system imports are provided
+//! - **No system calls**: The only imports provided are WASI `fd_write`,
+//!   and only for stdout and stderr, which the host captures (see below),
+//!   the virtual clock and random source described below, and stubs for
+//!   the rest of the WASI start-up interface (empty arguments and
+//!   environment, no open files, `proc_exit` traps). Modules importing
+//!   anything else are rejected when loaded
 //! - **Memory limits**: Configurable max memory (default 256MB)
 //! - **Execution limits**: Fuel-based execution time limits (default 30s)
 //!
-//! The only way for plugins to communicate is through the `process` function
-//! which receives serialized directive data and returns modified directives.
+//! The only way for plugins to communicate is through their entry points:
+//! `process` receives serialized directive data and returns modified
+//! directives, the optional `extract` receives a file's bytes and returns
+//! imported directives, and the optional `functions` and `call_function`
+//! list and evaluate query functions.
+//!
+//! # Version Handshake
+//!
+//! When a plugin is loaded, its optional `plugin_api_version` and
+//! `plugin_capabilities` exports are called and checked against
+//! [`PLUGIN_API_VERSION`] and [`PluginCapabilities::SUPPORTED`]. Plugins
+//! built for an interface this host cannot speak are rejected right away.
+//!
+//! # Plugin Output Capture
+//!
+//! Plugins may import `wasi_snapshot_preview1::fd_write` to print to stdout
+//! or stderr. Nothing reaches the host's own streams: each captured line
+//! is emitted as a `tracing` event tagged with the plugin name and returned
+//! in [`PluginOutput::logs`], so plugin authors can debug with plain prints.
+//!
+//! # Determinism
+//!
+//! Plugins never see the host's real clock or entropy. WASI
+//! `clock_time_get` reports midnight UTC of [`RuntimeConfig::today`] for
+//! every clock, and `random_get` draws from a generator seeded with
+//! [`RuntimeConfig::seed`] at the start of each call. A plugin computing
+//! something "as of today" thus gives the same output in CI as on the
+//! desk, and its results can be cached.
+//!
+//! # Result Caching
+//!
+//! A `PluginManager` given a [`PluginCache`] skips running a plugin when
+//! its module and input are unchanged since the last run, replaying the
+//! cached output instead.
 //!
 //! # Hot Reloading
 //!
@@ -22,15 +59,65 @@
 //! development workflows. It tracks plugin file modification times and
 //! reloads plugins when their source files change.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
 
 use anyhow::{Context, Result};
-use wasmtime::{Config, Engine, Linker, Module, Store};
-
-use crate::types::{PluginInput, PluginOutput};
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Module, Store};
+
+use crate::cache::{PluginCache, cache_key, module_hash};
+use crate::types::{
+    ExtractInput, ExtractOutput, FunctionInput, FunctionOutput, PLUGIN_API_VERSION,
+    PluginCapabilities, PluginInput, PluginLog, PluginLogStream, PluginOutput,
+};
+
+/// WASI module name plugins import `fd_write` from.
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+/// The WASI functions the host provides. Besides output, the clock and the
+/// random source, these are the calls a `wasm32-wasip1` standard library
+/// makes on start-up and exit.
+const WASI_IMPORTS: &[&str] = &[
+    "fd_write",
+    "clock_time_get",
+    "clock_res_get",
+    "random_get",
+    "args_get",
+    "args_sizes_get",
+    "environ_get",
+    "environ_sizes_get",
+    "fd_close",
+    "fd_fdstat_get",
+    "fd_prestat_get",
+    "fd_prestat_dir_name",
+    "fd_read",
+    "fd_seek",
+    "proc_exit",
+    "sched_yield",
+];
+
+/// Oldest plugin interface version this host still accepts.
+const OLDEST_PLUGIN_API_VERSION: u32 = 1;
+
+/// Fuel allowed for the version handshake calls.
+const HANDSHAKE_FUEL: u64 = 1_000_000;
+
+/// Maximum bytes captured per stream per call; further output is dropped.
+const MAX_CAPTURED_OUTPUT: usize = 1024 * 1024;
+
+/// WASI errno values returned by the host shims.
+const ERRNO_SUCCESS: i32 = 0;
+const ERRNO_BADF: i32 = 8;
+const ERRNO_FAULT: i32 = 21;
+const ERRNO_INVAL: i32 = 28;
+
+/// Highest WASI clock id (`thread_cputime_id`).
+const MAX_CLOCK_ID: i32 = 3;
 
 /// Configuration for the plugin runtime.
 #[derive(Debug, Clone)]
@@ -39,6 +126,23 @@ pub struct RuntimeConfig {
     pub max_memory: usize,
     /// Maximum execution time in seconds (default: 30).
     pub max_time_secs: u64,
+    /// The date plugins see as today (default: the current local date).
+    pub today: NaiveDate,
+    /// Seed for the random bytes plugins draw (default: 0).
+    pub seed: u64,
+}
+
+impl RuntimeConfig {
+    /// Nanoseconds since the Unix epoch at midnight UTC of [`today`](Self::today),
+    /// the time every WASI clock reports.
+    pub fn clock_nanos(&self) -> u64 {
+        self.today
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+            .timestamp_nanos_opt()
+            .and_then(|nanos| u64::try_from(nanos).ok())
+            .unwrap_or(0)
+    }
 }
 
 impl Default for RuntimeConfig {
@@ -46,6 +150,8 @@ impl Default for RuntimeConfig {
         Self {
             max_memory: 256 * 1024 * 1024, // 256MB
             max_time_secs: 30,
+            today: Local::now().date_naive(),
+            seed: 0,
         }
     }
 }
@@ -53,8 +159,10 @@ impl Default for RuntimeConfig {
 /// Validate that a WASM module doesn't have any forbidden imports.
 ///
 /// Beancount plugins should be self-contained and not require any
-/// external imports (WASI, env, etc.). This function checks that the
-/// module only has the expected exports and no unexpected imports.
+/// external imports (WASI, env, etc.), except the WASI functions the host
+/// provides: `fd_write` for printing to stdout and stderr, the virtual
+/// clock and random source, and the start-up stubs. This function checks
+/// that the module only has the expected exports and no unexpected imports.
 ///
 /// # Errors
 ///
@@ -64,14 +172,7 @@ pub fn validate_plugin_module(bytes: &[u8]) -> Result<()> {
     let engine = Engine::default();
     let module = Module::new(&engine, bytes)?;
 
-    // Check for forbidden imports (any imports are forbidden)
-    if let Some(import) = module.imports().next() {
-        anyhow::bail!(
-            "plugin has forbidden import: {}::{}",
-            import.module(),
-            import.name()
-        );
-    }
+    check_imports(&module)?;
 
     // Verify required exports exist
     let exports: Vec<_> = module.exports().map(|e| e.name()).collect();
@@ -82,13 +183,31 @@ pub fn validate_plugin_module(bytes: &[u8]) -> Result<()> {
     if !exports.contains(&"alloc") {
         anyhow::bail!("plugin must export 'alloc' function");
     }
-    if !exports.contains(&"process") {
-        anyhow::bail!("plugin must export 'process' function");
+    if !exports.contains(&"process") && !exports.contains(&"extract") {
+        anyhow::bail!("plugin must export a 'process' or 'extract' function");
     }
 
     Ok(())
 }
 
+/// Reject modules importing anything but the host shims in [`WASI_IMPORTS`].
+fn check_imports(module: &Module) -> Result<()> {
+    if let Some(import) = module
+        .imports()
+        .find(|i| !(i.module() == WASI_MODULE && WASI_IMPORTS.contains(&i.name())))
+    {
+        anyhow::bail!(
+            "plugin has forbidden import: {}::{} (plugins may only import these {} \
+             functions: {})",
+            import.module(),
+            import.name(),
+            WASI_MODULE,
+            WASI_IMPORTS.join(", ")
+        );
+    }
+    Ok(())
+}
+
 /// A loaded WASM plugin.
 pub struct Plugin {
     /// Plugin name (derived from filename).
@@ -97,11 +216,17 @@ pub struct Plugin {
     module: Module,
     /// Engine reference.
     engine: Arc<Engine>,
+    /// SHA-256 of the module bytes, for result caching.
+    hash: [u8; 32],
+    /// Plugin interface version the plugin was built against.
+    api_version: u32,
+    /// Host features the plugin relies on.
+    capabilities: PluginCapabilities,
 }
 
 impl Plugin {
     /// Load a plugin from a WASM file.
-    pub fn load(path: &Path, _config: &RuntimeConfig) -> Result<Self> {
+    pub fn load(path: &Path, config: &RuntimeConfig) -> Result<Self> {
         let name = path
             .file_stem()
             .and_then(|s| s.to_str())
@@ -120,11 +245,15 @@ impl Plugin {
 
         let module = Module::new(&engine, &wasm_bytes)
             .with_context(|| format!("failed to compile {}", path.display()))?;
+        let (api_version, capabilities) = handshake(&name, &engine, &module, config)?;
 
         Ok(Self {
             name,
             module,
             engine,
+            hash: module_hash(&wasm_bytes),
+            api_version,
+            capabilities,
         })
     }
 
@@ -132,7 +261,7 @@ impl Plugin {
     pub fn load_bytes(
         name: impl Into<String>,
         bytes: &[u8],
-        _config: &RuntimeConfig,
+        config: &RuntimeConfig,
     ) -> Result<Self> {
         let name = name.into();
 
@@ -141,11 +270,15 @@ impl Plugin {
 
         let engine = Arc::new(Engine::new(&engine_config)?);
         let module = Module::new(&engine, bytes)?;
+        let (api_version, capabilities) = handshake(&name, &engine, &module, config)?;
 
         Ok(Self {
             name,
             module,
             engine,
+            hash: module_hash(bytes),
+            api_version,
+            capabilities,
         })
     }
 
@@ -154,18 +287,98 @@ impl Plugin {
         &self.name
     }
 
+    /// SHA-256 hash of the plugin's module bytes.
+    pub const fn module_hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
+    /// Plugin interface version the plugin was built against.
+    pub const fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Host features the plugin declared it relies on.
+    pub const fn capabilities(&self) -> PluginCapabilities {
+        self.capabilities
+    }
+
+    /// Check whether the plugin can act as an importer (exports `extract`).
+    pub fn is_importer(&self) -> bool {
+        self.module.get_export("extract").is_some()
+    }
+
     /// Execute the plugin with the given input.
+    ///
+    /// Anything the plugin printed is returned in [`PluginOutput::logs`].
     pub fn execute(&self, input: &PluginInput, config: &RuntimeConfig) -> Result<PluginOutput> {
+        let (mut output, logs) = self.call::<_, PluginOutput>("process", input, config)?;
+        output.logs.extend(logs);
+        Ok(output)
+    }
+
+    /// Run the plugin's `extract` entry point on a file.
+    ///
+    /// Anything the plugin printed is only emitted as `tracing` events.
+    pub fn extract(&self, input: &ExtractInput, config: &RuntimeConfig) -> Result<ExtractOutput> {
+        self.call("extract", input, config)
+            .map(|(output, _)| output)
+    }
+
+    /// Names of the query functions the plugin provides.
+    ///
+    /// Empty unless the plugin declared [`PluginCapabilities::FUNCTIONS`].
+    pub fn functions(&self, config: &RuntimeConfig) -> Result<Vec<String>> {
+        if !self.capabilities.contains(PluginCapabilities::FUNCTIONS) {
+            return Ok(Vec::new());
+        }
+        self.call("functions", &(), config).map(|(names, _)| names)
+    }
+
+    /// Evaluate one of the plugin's query functions on a batch of rows.
+    ///
+    /// Fails if the plugin reports an error or does not return exactly one
+    /// value per row.
+    pub fn call_function(
+        &self,
+        input: &FunctionInput,
+        config: &RuntimeConfig,
+    ) -> Result<FunctionOutput> {
+        let (output, _): (FunctionOutput, _) = self.call("call_function", input, config)?;
+        if let Some(error) = &output.error {
+            anyhow::bail!("{}.{}: {error}", self.name, input.function);
+        }
+        if output.values.len() != input.rows.len() {
+            anyhow::bail!(
+                "{}.{} returned {} values for {} rows",
+                self.name,
+                input.function,
+                output.values.len(),
+                input.rows.len()
+            );
+        }
+        Ok(output)
+    }
+
+    /// Call an entry point with `MessagePack`-serialized input and output.
+    ///
+    /// Returns the output along with the lines the plugin printed, which
+    /// have already been emitted as `tracing` events.
+    fn call<I: Serialize, O: DeserializeOwned>(
+        &self,
+        entry_point: &str,
+        input: &I,
+        config: &RuntimeConfig,
+    ) -> Result<(O, Vec<PluginLog>)> {
         // Create a store with fuel limit
-        let mut store = Store::new(&self.engine, ());
+        let mut store = Store::new(&self.engine, HostState::new(config));
 
         // Set fuel limit based on time (rough approximation: 1M instructions per second)
         let fuel = config.max_time_secs * 1_000_000;
         store.set_fuel(fuel)?;
 
-        // Create linker with NO imports for full sandboxing
+        // Create linker with only the host shims for full sandboxing
         // Plugins have no access to filesystem, network, or any system calls
-        let linker = Linker::new(&self.engine);
+        let linker = host_linker(&self.engine)?;
 
         // Instantiate the module
         let instance = linker.instantiate(&mut store, &self.module)?;
@@ -189,12 +402,19 @@ impl Plugin {
         // Write input to WASM memory
         memory.write(&mut store, input_ptr as usize, &input_bytes)?;
 
-        // Call the process function
-        let process = instance
-            .get_typed_func::<(u32, u32), u64>(&mut store, "process")
-            .context("plugin must export 'process' function")?;
+        // Call the entry point
+        let entry = instance
+            .get_typed_func::<(u32, u32), u64>(&mut store, entry_point)
+            .with_context(|| format!("plugin must export '{entry_point}' function"))?;
 
-        let result = process.call(&mut store, (input_ptr, input_bytes.len() as u32))?;
+        let result = entry.call(&mut store, (input_ptr, input_bytes.len() as u32));
+
+        // Surface whatever the plugin printed, even if it then trapped
+        let logs = store.data().stdio.logs(&self.name);
+        for log in &logs {
+            log.emit();
+        }
+        let result = result?;
 
         // Parse result (packed as ptr << 32 | len)
         let output_ptr = (result >> 32) as u32;
@@ -205,9 +425,298 @@ impl Plugin {
         memory.read(&store, output_ptr as usize, &mut output_bytes)?;
 
         // Deserialize output
-        let output: PluginOutput = rmp_serde::from_slice(&output_bytes)?;
+        let output = rmp_serde::from_slice(&output_bytes)?;
 
-        Ok(output)
+        Ok((output, logs))
+    }
+}
+
+/// Read and check a plugin's declared interface version and capabilities.
+///
+/// Both exports are optional: a plugin without `plugin_api_version` is
+/// taken to target version 1, and one without `plugin_capabilities`
+/// declares none.
+fn handshake(
+    name: &str,
+    engine: &Engine,
+    module: &Module,
+    config: &RuntimeConfig,
+) -> Result<(u32, PluginCapabilities)> {
+    check_imports(module).with_context(|| format!("failed to load plugin '{name}'"))?;
+
+    let declares_version = module.get_export("plugin_api_version").is_some();
+    let declares_capabilities = module.get_export("plugin_capabilities").is_some();
+
+    let (api_version, capabilities) = if declares_version || declares_capabilities {
+        let mut store = Store::new(engine, HostState::new(config));
+        store.set_fuel(HANDSHAKE_FUEL)?;
+        let linker = host_linker(engine)?;
+        let instance = linker
+            .instantiate(&mut store, module)
+            .with_context(|| format!("failed to instantiate plugin '{name}'"))?;
+
+        let mut call = |export: &str| -> Result<u32> {
+            instance
+                .get_typed_func::<(), u32>(&mut store, export)
+                .with_context(|| {
+                    format!("plugin '{name}': '{export}' must take no arguments and return an i32")
+                })?
+                .call(&mut store, ())
+                .with_context(|| format!("plugin '{name}': '{export}' failed"))
+        };
+        let api_version = if declares_version {
+            call("plugin_api_version")?
+        } else {
+            1
+        };
+        let capabilities = if declares_capabilities {
+            PluginCapabilities::from_bits(call("plugin_capabilities")?)
+        } else {
+            PluginCapabilities::empty()
+        };
+        (api_version, capabilities)
+    } else {
+        (1, PluginCapabilities::empty())
+    };
+
+    if !(OLDEST_PLUGIN_API_VERSION..=PLUGIN_API_VERSION).contains(&api_version) {
+        anyhow::bail!(
+            "plugin '{name}' targets plugin API version {api_version}, but this host supports \
+             versions {OLDEST_PLUGIN_API_VERSION} to {PLUGIN_API_VERSION}; rebuild the plugin \
+             against a matching rustledger-plugin"
+        );
+    }
+    let unsupported = capabilities.unsupported();
+    if unsupported != PluginCapabilities::empty() {
+        anyhow::bail!(
+            "plugin '{name}' requires capabilities this host does not support (bits {:#x})",
+            unsupported.bits()
+        );
+    }
+    if capabilities.contains(PluginCapabilities::IMPORTER) && module.get_export("extract").is_none()
+    {
+        anyhow::bail!(
+            "plugin '{name}' declares the importer capability but does not export 'extract'"
+        );
+    }
+    if capabilities.contains(PluginCapabilities::FUNCTIONS) {
+        for export in ["functions", "call_function"] {
+            if module.get_export(export).is_none() {
+                anyhow::bail!(
+                    "plugin '{name}' declares the functions capability but does not export \
+                     '{export}'"
+                );
+            }
+        }
+    }
+
+    Ok((api_version, capabilities))
+}
+
+/// A linker providing the WASI functions in [`WASI_IMPORTS`].
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(WASI_MODULE, "fd_write", fd_write)?;
+    linker.func_wrap(WASI_MODULE, "clock_time_get", clock_time_get)?;
+    linker.func_wrap(WASI_MODULE, "clock_res_get", clock_res_get)?;
+    linker.func_wrap(WASI_MODULE, "random_get", random_get)?;
+
+    // Start-up stubs: no arguments, no environment, no preopened
+    // directories and no descriptors besides the captured stdio
+    linker.func_wrap(WASI_MODULE, "args_get", |_: i32, _: i32| ERRNO_SUCCESS)?;
+    linker.func_wrap(WASI_MODULE, "args_sizes_get", empty_sizes_get)?;
+    linker.func_wrap(WASI_MODULE, "environ_get", |_: i32, _: i32| ERRNO_SUCCESS)?;
+    linker.func_wrap(WASI_MODULE, "environ_sizes_get", empty_sizes_get)?;
+    linker.func_wrap(WASI_MODULE, "fd_close", |_: i32| ERRNO_BADF)?;
+    linker.func_wrap(WASI_MODULE, "fd_fdstat_get", |_: i32, _: i32| ERRNO_BADF)?;
+    linker.func_wrap(WASI_MODULE, "fd_prestat_get", |_: i32, _: i32| ERRNO_BADF)?;
+    linker.func_wrap(
+        WASI_MODULE,
+        "fd_prestat_dir_name",
+        |_: i32, _: i32, _: i32| ERRNO_BADF,
+    )?;
+    linker.func_wrap(WASI_MODULE, "fd_read", |_: i32, _: i32, _: i32, _: i32| {
+        ERRNO_BADF
+    })?;
+    linker.func_wrap(WASI_MODULE, "fd_seek", |_: i32, _: i64, _: i32, _: i32| {
+        ERRNO_BADF
+    })?;
+    linker.func_wrap(WASI_MODULE, "proc_exit", proc_exit)?;
+    linker.func_wrap(WASI_MODULE, "sched_yield", || ERRNO_SUCCESS)?;
+    Ok(linker)
+}
+
+/// What the host shims of one plugin call work with.
+#[derive(Debug)]
+struct HostState {
+    stdio: CapturedStdio,
+    /// The time every clock reports, in nanoseconds since the Unix epoch.
+    clock_nanos: u64,
+    rng: SplitMix64,
+}
+
+impl HostState {
+    fn new(config: &RuntimeConfig) -> Self {
+        Self {
+            stdio: CapturedStdio::default(),
+            clock_nanos: config.clock_nanos(),
+            rng: SplitMix64(config.seed),
+        }
+    }
+}
+
+/// The `SplitMix64` generator behind `random_get`: tiny, fast and fully
+/// determined by its seed, which is all plugins need.
+#[derive(Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// Stdout and stderr bytes a plugin wrote through `fd_write`.
+#[derive(Debug, Default)]
+struct CapturedStdio {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl CapturedStdio {
+    /// Split the captured streams into log lines tagged with `plugin`.
+    fn logs(&self, plugin: &str) -> Vec<PluginLog> {
+        let mut logs = PluginLog::from_captured(plugin, PluginLogStream::Stdout, &self.stdout);
+        logs.extend(PluginLog::from_captured(
+            plugin,
+            PluginLogStream::Stderr,
+            &self.stderr,
+        ));
+        logs
+    }
+}
+
+/// Host implementation of WASI `fd_write` that only accepts stdout (1) and
+/// stderr (2), appending the written bytes to the store's [`CapturedStdio`].
+fn fd_write(
+    mut caller: Caller<'_, HostState>,
+    fd: i32,
+    iovs: i32,
+    iovs_len: i32,
+    nwritten: i32,
+) -> i32 {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return ERRNO_FAULT;
+    };
+
+    // Gather the iovec buffers, each a (ptr: u32, len: u32) pair
+    let mut bytes = Vec::new();
+    {
+        let data = memory.data(&caller);
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+        for i in 0..iovs_len as u32 as usize {
+            let iov = iovs as u32 as usize + i * 8;
+            let (Some(ptr), Some(len)) = (read_u32(iov), read_u32(iov + 4)) else {
+                return ERRNO_FAULT;
+            };
+            let Some(buf) = data.get(ptr..ptr + len) else {
+                return ERRNO_FAULT;
+            };
+            bytes.extend_from_slice(buf);
+        }
+    }
+
+    let captured = &mut caller.data_mut().stdio;
+    let target = match fd {
+        1 => &mut captured.stdout,
+        2 => &mut captured.stderr,
+        _ => return ERRNO_BADF,
+    };
+    let room = MAX_CAPTURED_OUTPUT.saturating_sub(target.len());
+    target.extend_from_slice(&bytes[..bytes.len().min(room)]);
+
+    // Report everything as written so plugins don't retry dropped output
+    let written = (bytes.len() as u32).to_le_bytes();
+    if memory
+        .write(&mut caller, nwritten as u32 as usize, &written)
+        .is_err()
+    {
+        return ERRNO_FAULT;
+    }
+    ERRNO_SUCCESS
+}
+
+/// Host implementation of WASI `clock_time_get`: every clock reports the
+/// virtual time of [`RuntimeConfig::today`].
+fn clock_time_get(mut caller: Caller<'_, HostState>, id: i32, _precision: i64, time: i32) -> i32 {
+    if !(0..=MAX_CLOCK_ID).contains(&id) {
+        return ERRNO_INVAL;
+    }
+    let nanos = caller.data().clock_nanos.to_le_bytes();
+    write_guest(&mut caller, time, &nanos)
+}
+
+/// Host implementation of WASI `clock_res_get`. The clocks never move, so
+/// any resolution is honest; report one nanosecond.
+fn clock_res_get(mut caller: Caller<'_, HostState>, id: i32, resolution: i32) -> i32 {
+    if !(0..=MAX_CLOCK_ID).contains(&id) {
+        return ERRNO_INVAL;
+    }
+    write_guest(&mut caller, resolution, &1u64.to_le_bytes())
+}
+
+/// Host implementation of WASI `random_get`, filling the buffer from the
+/// seeded generator.
+fn random_get(mut caller: Caller<'_, HostState>, buf: i32, buf_len: i32) -> i32 {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return ERRNO_FAULT;
+    };
+    let (start, len) = (buf as u32 as usize, buf_len as u32 as usize);
+    if start.saturating_add(len) > memory.data_size(&caller) {
+        return ERRNO_FAULT;
+    }
+    let mut bytes = vec![0; len];
+    caller.data_mut().rng.fill(&mut bytes);
+    write_guest(&mut caller, buf, &bytes)
+}
+
+/// Host implementation of WASI `args_sizes_get` and `environ_sizes_get`:
+/// plugins get no arguments and no environment variables.
+fn empty_sizes_get(mut caller: Caller<'_, HostState>, count: i32, buf_size: i32) -> i32 {
+    match write_guest(&mut caller, count, &0u32.to_le_bytes()) {
+        ERRNO_SUCCESS => write_guest(&mut caller, buf_size, &0u32.to_le_bytes()),
+        errno => errno,
+    }
+}
+
+/// Host implementation of WASI `proc_exit`, which ends the call with an
+/// error: a plugin has no process to exit.
+fn proc_exit(code: i32) -> Result<()> {
+    anyhow::bail!("plugin called proc_exit with code {code}")
+}
+
+/// Write `bytes` to the plugin's memory at `ptr`, returning a WASI errno.
+fn write_guest(caller: &mut Caller<'_, HostState>, ptr: i32, bytes: &[u8]) -> i32 {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return ERRNO_FAULT;
+    };
+    match memory.write(caller, ptr as u32 as usize, bytes) {
+        Ok(()) => ERRNO_SUCCESS,
+        Err(_) => ERRNO_FAULT,
     }
 }
 
@@ -217,6 +726,12 @@ pub struct PluginManager {
     config: RuntimeConfig,
     /// Loaded plugins.
     plugins: Vec<Plugin>,
+    /// Cache of plugin outputs, if enabled.
+    cache: Option<PluginCache>,
+    /// Names of plugins skipped by [`execute_all`](Self::execute_all).
+    disabled: HashSet<String>,
+    /// Plugin names run first, in this order, by [`execute_all`](Self::execute_all).
+    order: Vec<String>,
 }
 
 impl PluginManager {
@@ -226,13 +741,55 @@ impl PluginManager {
     }
 
     /// Create a plugin manager with custom configuration.
-    pub const fn with_config(config: RuntimeConfig) -> Self {
+    pub fn with_config(config: RuntimeConfig) -> Self {
         Self {
             config,
             plugins: Vec::new(),
+            cache: None,
+            disabled: HashSet::new(),
+            order: Vec::new(),
         }
     }
 
+    /// Cache plugin outputs in `cache` for [`execute_all`](Self::execute_all).
+    pub fn set_cache(&mut self, cache: PluginCache) {
+        self.cache = Some(cache);
+    }
+
+    /// Skip the plugin named `name` in [`execute_all`](Self::execute_all),
+    /// e.g. for a ledger's `option "disable_plugin"`.
+    pub fn disable(&mut self, name: impl Into<String>) {
+        self.disabled.insert(name.into());
+    }
+
+    /// Whether the plugin named `name` has been disabled.
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.disabled.contains(name)
+    }
+
+    /// Run the plugins named in `names` first, in that order, e.g. for a
+    /// ledger's `option "plugin_order"`. Unlisted plugins keep their load
+    /// order after them.
+    pub fn set_order(&mut self, names: Vec<String>) {
+        self.order = names;
+    }
+
+    /// The enabled plugins in the order [`execute_all`](Self::execute_all) runs them.
+    fn plugins_to_run(&self) -> Vec<&Plugin> {
+        let mut plugins: Vec<&Plugin> = self
+            .plugins
+            .iter()
+            .filter(|p| !self.is_disabled(p.name()))
+            .collect();
+        plugins.sort_by_key(|p| {
+            self.order
+                .iter()
+                .position(|name| name == p.name())
+                .unwrap_or(self.order.len())
+        });
+        plugins
+    }
+
     /// Load a plugin from a file path.
     pub fn load(&mut self, path: &Path) -> Result<usize> {
         let plugin = Plugin::load(path, &self.config)?;
@@ -258,22 +815,61 @@ impl PluginManager {
         plugin.execute(input, &self.config)
     }
 
-    /// Execute all loaded plugins in sequence.
+    /// Execute all loaded plugins in sequence, skipping disabled ones and
+    /// running any set with [`set_order`](Self::set_order) first.
+    ///
+    /// With a cache set, each plugin whose module and input match its last
+    /// run is skipped and its cached output used instead. Cache failures
+    /// never fail execution; the plugin simply runs.
     pub fn execute_all(&self, mut input: PluginInput) -> Result<PluginOutput> {
         let mut all_errors = Vec::new();
+        let mut all_logs = Vec::new();
 
-        for plugin in &self.plugins {
-            let output = plugin.execute(&input, &self.config)?;
+        for plugin in self.plugins_to_run() {
+            let output = self.execute_cached(plugin, &input)?;
             all_errors.extend(output.errors);
+            all_logs.extend(output.logs);
             input.directives = output.directives;
         }
 
         Ok(PluginOutput {
             directives: input.directives,
             errors: all_errors,
+            logs: all_logs,
         })
     }
 
+    /// Execute a plugin, going through the cache if one is set.
+    fn execute_cached(&self, plugin: &Plugin, input: &PluginInput) -> Result<PluginOutput> {
+        let Some(cache) = &self.cache else {
+            return plugin.execute(input, &self.config);
+        };
+        let Some(key) = cache_key(plugin.module_hash(), input, &self.config) else {
+            return plugin.execute(input, &self.config);
+        };
+
+        if let Some(output) = cache.load(plugin.name(), &key) {
+            return Ok(output);
+        }
+        let output = plugin.execute(input, &self.config)?;
+        let _ = cache.store(plugin.name(), &key, &output);
+        Ok(output)
+    }
+
+    /// Offer a file to each importer plugin in load order.
+    ///
+    /// Returns the name of the first plugin that identifies the file along
+    /// with its output, or `None` if no plugin claims it.
+    pub fn extract(&self, input: &ExtractInput) -> Result<Option<(&str, ExtractOutput)>> {
+        for plugin in self.plugins.iter().filter(|p| p.is_importer()) {
+            let output = plugin.extract(input, &self.config)?;
+            if output.identified {
+                return Ok(Some((plugin.name(), output)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Get the number of loaded plugins.
     pub fn len(&self) -> usize {
         self.plugins.len()
@@ -466,16 +1062,19 @@ impl WatchingPluginManager {
     /// Execute all loaded plugins in sequence.
     pub fn execute_all(&self, mut input: PluginInput) -> Result<PluginOutput> {
         let mut all_errors = Vec::new();
+        let mut all_logs = Vec::new();
 
         for tracked in &self.plugins {
             let output = tracked.plugin.execute(&input, &self.config)?;
             all_errors.extend(output.errors);
+            all_logs.extend(output.logs);
             input.directives = output.directives;
         }
 
         Ok(PluginOutput {
             directives: input.directives,
             errors: all_errors,
+            logs: all_logs,
         })
     }
 
@@ -541,15 +1140,15 @@ mod tests {
         );
     }
 
-    /// Test that a module with WASI imports is rejected.
+    /// Test that a module with WASI imports other than `fd_write` is rejected.
     #[test]
     fn test_wasi_import_rejected() {
-        // A module that tries to import WASI fd_write
+        // A module that tries to import WASI path_open
         let wasm = wat::parse_str(
             r#"
             (module
-                (import "wasi_snapshot_preview1" "fd_write"
-                    (func $fd_write (param i32 i32 i32 i32) (result i32))
+                (import "wasi_snapshot_preview1" "path_open"
+                    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32))
                 )
                 (memory (export "memory") 1)
                 (func (export "alloc") (param i32) (result i32)
@@ -579,6 +1178,238 @@ mod tests {
         );
     }
 
+    /// Build a plugin whose `process` prints `stdout` to fd 1 and `stderr`
+    /// to fd 2 before returning an empty `PluginOutput`.
+    fn printing_plugin(stdout: &str, stderr: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32))
+                )
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\92\90\90")
+                (data (i32.const 64) "{stdout}")
+                (data (i32.const 256) "{stderr}")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    ;; iovec at 32: (ptr 64, len)
+                    (i32.store (i32.const 32) (i32.const 64))
+                    (i32.store (i32.const 36) (i32.const {stdout_len}))
+                    (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 1) (i32.const 48)))
+                    ;; iovec at 32: (ptr 256, len)
+                    (i32.store (i32.const 32) (i32.const 256))
+                    (i32.store (i32.const 36) (i32.const {stderr_len}))
+                    (drop (call $fd_write (i32.const 2) (i32.const 32) (i32.const 1) (i32.const 48)))
+                    ;; ptr 16 << 32 | len 3
+                    i64.const 68719476739
+                )
+            )
+            "#,
+            stdout = stdout.replace('\n', "\\n"),
+            stderr = stderr.replace('\n', "\\n"),
+            stdout_len = stdout.len(),
+            stderr_len = stderr.len(),
+        ))
+        .expect("valid wat")
+    }
+
+    /// Test that plugins may import WASI `fd_write` for printing.
+    #[test]
+    fn test_fd_write_import_allowed() {
+        let wasm = printing_plugin("hi\n", "");
+        let result = validate_plugin_module(&wasm);
+        assert!(
+            result.is_ok(),
+            "fd_write import should be allowed: {:?}",
+            result.err()
+        );
+    }
+
+    /// Test that plugin stdout and stderr are captured as tagged log lines.
+    #[test]
+    fn test_plugin_stdio_captured() {
+        let mut manager = PluginManager::new();
+        manager
+            .load_bytes("chatty", &printing_plugin("loaded\nchecked 0\n", "oops\n"))
+            .unwrap();
+        manager
+            .load_bytes("quiet", &printing_plugin("", ""))
+            .unwrap();
+
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions::default(),
+            config: None,
+        };
+        let output = manager.execute_all(input).unwrap();
+
+        let log = |stream, message: &str| PluginLog {
+            plugin: "chatty".to_string(),
+            stream,
+            message: message.to_string(),
+        };
+        assert_eq!(
+            output.logs,
+            vec![
+                log(PluginLogStream::Stdout, "loaded"),
+                log(PluginLogStream::Stdout, "checked 0"),
+                log(PluginLogStream::Stderr, "oops"),
+            ]
+        );
+    }
+
+    /// Build a plugin whose `process` traps unless the realtime clock reads
+    /// `nanos` and the first eight random bytes read `random`.
+    fn sandbox_plugin(nanos: u64, random: u64) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"
+            (module
+                (import "wasi_snapshot_preview1" "clock_time_get"
+                    (func $clock_time_get (param i32 i64 i32) (result i32))
+                )
+                (import "wasi_snapshot_preview1" "random_get"
+                    (func $random_get (param i32 i32) (result i32))
+                )
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\92\90\90")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    (if (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 128))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 128)) (i64.const {nanos}))
+                        (then unreachable))
+                    (if (call $random_get (i32.const 136) (i32.const 8))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 136)) (i64.const {random}))
+                        (then unreachable))
+                    ;; ptr 16 << 32 | len 3
+                    i64.const 68719476739
+                )
+            )
+            "#,
+            nanos = nanos as i64,
+            random = random as i64,
+        ))
+        .expect("valid wat")
+    }
+
+    /// Test that plugins see the configured date and seeded random bytes.
+    #[test]
+    fn test_virtual_clock_and_seeded_random() {
+        let config = RuntimeConfig {
+            today: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+            seed: 42,
+            ..RuntimeConfig::default()
+        };
+        assert_eq!(config.clock_nanos(), 1_719_705_600 * 1_000_000_000);
+
+        let wasm = sandbox_plugin(config.clock_nanos(), SplitMix64(42).next_u64());
+        assert!(validate_plugin_module(&wasm).is_ok());
+        let plugin = Plugin::load_bytes("sandbox", &wasm, &config).unwrap();
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions::default(),
+            config: None,
+        };
+        // Every call starts from the seed
+        plugin.execute(&input, &config).unwrap();
+        plugin.execute(&input, &config).unwrap();
+
+        let tomorrow = RuntimeConfig {
+            today: NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            ..config
+        };
+        assert!(plugin.execute(&input, &tomorrow).is_err());
+        let reseeded = RuntimeConfig { seed: 7, ..config };
+        assert!(plugin.execute(&input, &reseeded).is_err());
+    }
+
+    /// Test that the WASI start-up calls of a `wasm32-wasip1` standard
+    /// library link and report an empty environment and no preopens.
+    #[test]
+    fn test_wasi_startup_stubs() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "wasi_snapshot_preview1" "environ_sizes_get"
+                    (func $environ_sizes_get (param i32 i32) (result i32))
+                )
+                (import "wasi_snapshot_preview1" "fd_prestat_get"
+                    (func $fd_prestat_get (param i32 i32) (result i32))
+                )
+                (import "wasi_snapshot_preview1" "proc_exit"
+                    (func $proc_exit (param i32))
+                )
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\92\90\90")
+                (data (i32.const 128) "\ff\ff\ff\ff\ff\ff\ff\ff")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    (if (call $environ_sizes_get (i32.const 128) (i32.const 132))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 128)) (i64.const 0))
+                        (then unreachable))
+                    ;; errno 8 (BADF): no preopened directories
+                    (if (i32.ne (call $fd_prestat_get (i32.const 3) (i32.const 136)) (i32.const 8))
+                        (then unreachable))
+                    ;; ptr 16 << 32 | len 3
+                    i64.const 68719476739
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+        assert!(validate_plugin_module(&wasm).is_ok());
+
+        let config = RuntimeConfig::default();
+        let plugin = Plugin::load_bytes("startup", &wasm, &config).unwrap();
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions::default(),
+            config: None,
+        };
+        plugin.execute(&input, &config).unwrap();
+    }
+
+    /// Test that loading a plugin with an unsupported WASI import fails
+    /// with an error naming the import rather than a link error.
+    #[test]
+    fn test_load_rejects_unsupported_wasi_import() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "wasi_snapshot_preview1" "sock_accept"
+                    (func $sock_accept (param i32 i32 i32) (result i32))
+                )
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 0
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    i64.const 0
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+
+        let err = Plugin::load_bytes("net", &wasm, &RuntimeConfig::default())
+            .err()
+            .expect("plugin with sock_accept should be rejected");
+        let err = format!("{err:#}");
+        assert!(
+            err.contains("forbidden import: wasi_snapshot_preview1::sock_accept"),
+            "error should name the import: {err}"
+        );
+    }
+
     /// Test that a module with env imports is rejected.
     #[test]
     fn test_env_import_rejected() {
@@ -674,6 +1505,238 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("process"));
     }
 
+    /// Test that an importer-only module (no `process`) passes validation.
+    #[test]
+    fn test_importer_only_plugin_validation() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 0
+                )
+                (func (export "extract") (param i32 i32) (result i64)
+                    i64.const 0
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+
+        assert!(validate_plugin_module(&wasm).is_ok());
+    }
+
+    /// Build an importer plugin whose `extract` returns a fixed
+    /// `ExtractOutput` with no directives.
+    fn importer_plugin(identified: bool) -> Vec<u8> {
+        // MessagePack array form of ExtractOutput: [identified, [], []]
+        let flag = if identified { r"\c3" } else { r"\c2" };
+        wat::parse_str(format!(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\93{flag}\90\90")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "extract") (param i32 i32) (result i64)
+                    ;; ptr 16 << 32 | len 4
+                    i64.const 68719476740
+                )
+            )
+            "#
+        ))
+        .expect("valid wat")
+    }
+
+    /// Test that files are routed to the first plugin that identifies them.
+    #[test]
+    fn test_extract_routes_to_identifying_plugin() {
+        let mut manager = PluginManager::new();
+        manager
+            .load_bytes("declines", &importer_plugin(false))
+            .unwrap();
+        manager
+            .load_bytes("accepts", &importer_plugin(true))
+            .unwrap();
+
+        let input = ExtractInput {
+            filename: "statement.qif".to_string(),
+            contents: b"!Type:Bank".to_vec(),
+            config: None,
+        };
+
+        let (name, output) = manager.extract(&input).unwrap().expect("identified");
+        assert_eq!(name, "accepts");
+        assert!(output.directives.is_empty());
+        assert!(output.errors.is_empty());
+    }
+
+    /// Test that cached outputs are stored and replayed without running
+    /// the plugin.
+    #[test]
+    fn test_execute_all_uses_cache() {
+        // `process` returns an empty PluginOutput: [[], []]
+        let empty = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\92\90\90")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    ;; ptr 16 << 32 | len 3
+                    i64.const 68719476739
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+        // `process` always traps, so it only succeeds from the cache
+        let trapping = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    unreachable
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+
+        let dir = std::env::temp_dir().join(format!(
+            "rledger-plugin-runtime-cache-{}",
+            std::process::id()
+        ));
+        let cache = PluginCache::new(&dir);
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions {
+                operating_currencies: Vec::new(),
+                title: None,
+            },
+            config: None,
+        };
+
+        let mut manager = PluginManager::new();
+        manager.set_cache(cache.clone());
+        manager.load_bytes("empty", &empty).unwrap();
+        manager.execute_all(input.clone()).unwrap();
+        let key = cache_key(&module_hash(&empty), &input, &RuntimeConfig::default()).unwrap();
+        assert!(cache.load("empty", &key).is_some());
+
+        let mut manager = PluginManager::new();
+        manager.load_bytes("trapping", &trapping).unwrap();
+        assert!(manager.execute_all(input.clone()).is_err());
+
+        let key = cache_key(&module_hash(&trapping), &input, &RuntimeConfig::default()).unwrap();
+        let output = PluginOutput {
+            directives: Vec::new(),
+            errors: Vec::new(),
+            logs: Vec::new(),
+        };
+        cache.store("trapping", &key, &output).unwrap();
+        manager.set_cache(cache.clone());
+        assert!(manager.execute_all(input).is_ok());
+
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn test_execute_all_skips_disabled() {
+        // `process` always traps, so execution only succeeds if it is skipped
+        let trapping = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    unreachable
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions {
+                operating_currencies: Vec::new(),
+                title: None,
+            },
+            config: None,
+        };
+
+        let mut manager = PluginManager::new();
+        manager.load_bytes("trapping", &trapping).unwrap();
+        assert!(manager.execute_all(input.clone()).is_err());
+
+        manager.disable("trapping");
+        assert!(manager.is_disabled("trapping"));
+        let output = manager.execute_all(input).unwrap();
+        assert!(output.errors.is_empty());
+    }
+
+    /// Test that `set_order` runs listed plugins first, keeping the rest in load order.
+    #[test]
+    fn test_plugins_to_run_respects_order() {
+        let module = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    unreachable
+                )
+            )
+            "#,
+        )
+        .expect("valid wat");
+
+        let mut manager = PluginManager::new();
+        for name in ["a", "b", "c", "d"] {
+            manager.load_bytes(name, &module).unwrap();
+        }
+        manager.disable("b");
+        manager.set_order(vec!["d".to_string(), "c".to_string()]);
+
+        let names: Vec<&str> = manager.plugins_to_run().iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["d", "c", "a"]);
+    }
+
+    /// Test that no plugin claiming a file yields `None`.
+    #[test]
+    fn test_extract_unidentified() {
+        let mut manager = PluginManager::new();
+        manager
+            .load_bytes("declines", &importer_plugin(false))
+            .unwrap();
+
+        let input = ExtractInput {
+            filename: "statement.qif".to_string(),
+            contents: Vec::new(),
+            config: None,
+        };
+
+        assert!(manager.extract(&input).unwrap().is_none());
+
+        // Importer-only plugins have no `process` entry point
+        let input = PluginInput {
+            directives: Vec::new(),
+            options: crate::types::PluginOptions::default(),
+            config: None,
+        };
+        assert!(manager.execute(0, &input).is_err());
+    }
+
     /// Test that invalid WASM bytes are rejected.
     #[test]
     fn test_invalid_wasm_rejected() {
@@ -682,12 +1745,168 @@ mod tests {
         assert!(result.is_err(), "invalid WASM should be rejected");
     }
 
+    /// Build a `process` plugin declaring `version` and `capabilities`.
+    fn versioned_plugin(version: u32, capabilities: u32) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "plugin_api_version") (result i32)
+                    i32.const {version}
+                )
+                (func (export "plugin_capabilities") (result i32)
+                    i32.const {capabilities}
+                )
+                (func (export "process") (param i32 i32) (result i64)
+                    i64.const 0
+                )
+            )
+            "#
+        ))
+        .expect("valid wat")
+    }
+
+    /// Test that declared versions and capabilities are checked on load.
+    #[test]
+    fn test_plugin_handshake() {
+        let mut manager = PluginManager::new();
+        let capabilities = PluginCapabilities::PRICES.bits() | PluginCapabilities::OPTIONS.bits();
+        manager
+            .load_bytes(
+                "current",
+                &versioned_plugin(PLUGIN_API_VERSION, capabilities),
+            )
+            .unwrap();
+        let plugin = &manager.plugins[0];
+        assert_eq!(plugin.api_version(), PLUGIN_API_VERSION);
+        assert!(plugin.capabilities().contains(PluginCapabilities::OPTIONS));
+        assert!(!plugin.capabilities().contains(PluginCapabilities::IMPORTER));
+
+        // Plugins predating the handshake target version 1
+        manager
+            .load_bytes("legacy", &importer_plugin(true))
+            .unwrap();
+        assert_eq!(manager.plugins[1].api_version(), 1);
+
+        let err = manager
+            .load_bytes("future", &versioned_plugin(PLUGIN_API_VERSION + 1, 0))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("plugin 'future' targets plugin API version"),
+            "{err}"
+        );
+
+        let err = manager
+            .load_bytes("greedy", &versioned_plugin(PLUGIN_API_VERSION, 1 << 31))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not support (bits 0x80000000)"), "{err}");
+
+        let importer = PluginCapabilities::IMPORTER.bits();
+        let err = manager
+            .load_bytes(
+                "no-extract",
+                &versioned_plugin(PLUGIN_API_VERSION, importer),
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not export 'extract'"), "{err}");
+
+        let functions = PluginCapabilities::FUNCTIONS.bits();
+        let err = manager
+            .load_bytes(
+                "no-functions",
+                &versioned_plugin(PLUGIN_API_VERSION, functions),
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not export 'functions'"), "{err}");
+
+        assert_eq!(manager.len(), 2);
+    }
+
+    /// Test listing and calling a plugin's query functions.
+    #[test]
+    fn test_plugin_functions() {
+        use crate::types::FunctionValue;
+        use std::fmt::Write;
+
+        // Canned `MessagePack` replies of the two entry points
+        let escape = |bytes: Vec<u8>| -> String {
+            bytes.iter().fold(String::new(), |mut out, b| {
+                let _ = write!(out, "\\{b:02x}");
+                out
+            })
+        };
+        let names = rmp_serde::to_vec(&["category"]).unwrap();
+        let output = rmp_serde::to_vec(&FunctionOutput {
+            values: vec![FunctionValue::String("Coffee".to_string())],
+            error: None,
+        })
+        .unwrap();
+        let wasm = wat::parse_str(format!(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "{names}")
+                (data (i32.const 256) "{output}")
+                (func (export "alloc") (param i32) (result i32)
+                    i32.const 1024
+                )
+                (func (export "plugin_capabilities") (result i32)
+                    i32.const {capabilities}
+                )
+                (func (export "functions") (param i32 i32) (result i64)
+                    i64.const {names_ptr_len}
+                )
+                (func (export "call_function") (param i32 i32) (result i64)
+                    i64.const {output_ptr_len}
+                )
+            )
+            "#,
+            capabilities = PluginCapabilities::FUNCTIONS.bits(),
+            names_ptr_len = (16u64 << 32) | names.len() as u64,
+            output_ptr_len = (256u64 << 32) | output.len() as u64,
+            names = escape(names),
+            output = escape(output),
+        ))
+        .expect("valid wat");
+
+        let config = RuntimeConfig::default();
+        let plugin = Plugin::load_bytes("shops", &wasm, &config).unwrap();
+        assert_eq!(plugin.functions(&config).unwrap(), ["category"]);
+
+        let mut input = FunctionInput {
+            function: "category".to_string(),
+            rows: vec![vec![FunctionValue::String("Coffee Shop".to_string())]],
+        };
+        let output = plugin.call_function(&input, &config).unwrap();
+        assert!(matches!(&output.values[..], [FunctionValue::String(s)] if s == "Coffee"));
+
+        // One value per row is required
+        input.rows.push(vec![FunctionValue::Null]);
+        let err = plugin
+            .call_function(&input, &config)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "shops.category returned 1 values for 2 rows");
+
+        // Plugins without the capability provide no functions
+        let plain = Plugin::load_bytes("plain", &versioned_plugin(1, 0), &config).unwrap();
+        assert_eq!(plain.functions(&config).unwrap(), Vec::<String>::new());
+    }
+
     /// Test that runtime config can be customized.
     #[test]
     fn test_runtime_config_custom() {
         let config = RuntimeConfig {
             max_memory: 512 * 1024 * 1024, // 512MB
             max_time_secs: 60,
+            ..RuntimeConfig::default()
         };
         assert_eq!(config.max_memory, 512 * 1024 * 1024);
         assert_eq!(config.max_time_secs, 60);
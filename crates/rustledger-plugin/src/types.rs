@@ -23,6 +23,198 @@ pub struct PluginOutput {
     pub directives: Vec<DirectiveWrapper>,
     /// Errors generated by the plugin.
     pub errors: Vec<PluginError>,
+    /// Lines the plugin wrote to stdout or stderr, filled in by the host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<PluginLog>,
+}
+
+/// A line of output a plugin wrote to stdout or stderr.
+///
+/// The host captures plugin stdio instead of passing it through, so plugin
+/// authors can debug with plain prints without corrupting the host's output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginLog {
+    /// Name of the plugin that wrote the line.
+    pub plugin: String,
+    /// Stream the line was written to.
+    pub stream: PluginLogStream,
+    /// The line, without its trailing newline.
+    pub message: String,
+}
+
+/// Stdio stream captured from a plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PluginLogStream {
+    /// Standard output.
+    #[serde(rename = "stdout")]
+    Stdout,
+    /// Standard error.
+    #[serde(rename = "stderr")]
+    Stderr,
+}
+
+impl PluginLog {
+    /// Split captured stream bytes into one log entry per line.
+    ///
+    /// Invalid UTF-8 is replaced rather than rejected, and a trailing
+    /// newline does not produce an empty entry.
+    pub fn from_captured(plugin: &str, stream: PluginLogStream, bytes: &[u8]) -> Vec<Self> {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .map(|line| Self {
+                plugin: plugin.to_string(),
+                stream,
+                message: line.to_string(),
+            })
+            .collect()
+    }
+
+    /// Emit this line as a `tracing` event tagged with the plugin name.
+    ///
+    /// Stdout lines are logged at `info`, stderr lines at `warn`.
+    pub fn emit(&self) {
+        match self.stream {
+            PluginLogStream::Stdout => tracing::info!(
+                target: "rustledger_plugin",
+                plugin = %self.plugin,
+                stream = "stdout",
+                "{}",
+                self.message
+            ),
+            PluginLogStream::Stderr => tracing::warn!(
+                target: "rustledger_plugin",
+                plugin = %self.plugin,
+                stream = "stderr",
+                "{}",
+                self.message
+            ),
+        }
+    }
+}
+
+/// Version of the plugin interface implemented by this host.
+///
+/// Plugins declare the version they were built against by exporting
+/// `plugin_api_version() -> u32`. Plugins without the export are taken to
+/// target version 1.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Host features a plugin declares it relies on.
+///
+/// Plugins export `plugin_capabilities() -> u32` returning these as bit
+/// flags. A plugin asking for a capability the host does not know is
+/// rejected at load time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PluginCapabilities(u32);
+
+impl PluginCapabilities {
+    /// The plugin reads `price` directives from its input.
+    pub const PRICES: Self = Self(1);
+    /// The plugin reads the ledger options in [`PluginInput::options`].
+    pub const OPTIONS: Self = Self(1 << 1);
+    /// The plugin acts as an importer and exports `extract`.
+    pub const IMPORTER: Self = Self(1 << 2);
+    /// The plugin provides query functions and exports `functions` and
+    /// `call_function`.
+    pub const FUNCTIONS: Self = Self(1 << 3);
+
+    /// Every capability this host supports.
+    pub const SUPPORTED: Self =
+        Self(Self::PRICES.0 | Self::OPTIONS.0 | Self::IMPORTER.0 | Self::FUNCTIONS.0);
+
+    /// No capabilities.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Capabilities from their bit representation, keeping unknown bits.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The bit representation.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether every capability in `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The capabilities not supported by this host.
+    pub const fn unsupported(self) -> Self {
+        Self(self.0 & !Self::SUPPORTED.0)
+    }
+}
+
+/// Input passed to an importer plugin's `extract` entry point.
+///
+/// Importer plugins follow beangulp's model: the host offers a file and the
+/// plugin decides whether it recognizes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractInput {
+    /// Name of the file being imported (without its directory).
+    pub filename: String,
+    /// Raw file contents.
+    pub contents: Vec<u8>,
+    /// Plugin-specific configuration string.
+    pub config: Option<String>,
+}
+
+/// Output returned from an importer plugin's `extract` entry point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractOutput {
+    /// Whether the plugin recognized the file. When `false`, the host
+    /// offers the file to the next importer.
+    pub identified: bool,
+    /// Extracted directives.
+    pub directives: Vec<DirectiveWrapper>,
+    /// Errors generated by the plugin.
+    pub errors: Vec<PluginError>,
+}
+
+/// A value passed to or returned from a plugin's query function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FunctionValue {
+    /// NULL.
+    Null,
+    /// Boolean.
+    Boolean(bool),
+    /// Integer.
+    Integer(i64),
+    /// Decimal number as string (preserves precision).
+    Number(String),
+    /// String, including account names.
+    String(String),
+    /// Date (YYYY-MM-DD).
+    Date(String),
+    /// Amount; positions are passed as their units.
+    Amount(AmountData),
+    /// Set of strings, such as tags or links.
+    StringSet(Vec<String>),
+}
+
+/// Input passed to a plugin's `call_function` entry point.
+///
+/// Queries call functions in batches: the plugin evaluates `function` once
+/// per row of arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionInput {
+    /// Name of the function, as listed by the plugin's `functions` entry
+    /// point.
+    pub function: String,
+    /// Arguments of each row.
+    pub rows: Vec<Vec<FunctionValue>>,
+}
+
+/// Output returned from a plugin's `call_function` entry point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionOutput {
+    /// One result per input row.
+    pub values: Vec<FunctionValue>,
+    /// Error that failed the call, e.g. for arguments of the wrong type.
+    pub error: Option<String>,
 }
 
 /// A wrapper around directives for serialization.
@@ -211,6 +403,9 @@ pub struct OpenData {
     pub currencies: Vec<String>,
     /// Booking method.
     pub booking: Option<String>,
+    /// Metadata key-value pairs.
+    #[serde(default)]
+    pub metadata: Vec<(String, MetaValueData)>,
 }
 
 /// Close account data.
@@ -353,6 +548,24 @@ impl PluginError {
         self.line_number = Some(line);
         self
     }
+
+    /// Set the source location from a directive's `filename` and `lineno`
+    /// metadata, when it has them.
+    #[must_use]
+    pub fn at_directive(mut self, metadata: &[(String, MetaValueData)]) -> Self {
+        for (key, value) in metadata {
+            match (key.as_str(), value) {
+                ("filename", MetaValueData::String(file)) => {
+                    self.source_file = Some(file.clone());
+                }
+                ("lineno", MetaValueData::Number(line)) => {
+                    self.line_number = line.parse().ok();
+                }
+                _ => {}
+            }
+        }
+        self
+    }
 }
 
 impl PluginOutput {
@@ -361,6 +574,7 @@ impl PluginOutput {
         Self {
             directives,
             errors: Vec::new(),
+            logs: Vec::new(),
         }
     }
 }
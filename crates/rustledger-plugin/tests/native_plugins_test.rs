@@ -3,8 +3,10 @@
 //! Tests are converted from beancount's plugin test suite.
 
 use rustledger_plugin::native::{
-    CheckCommodityPlugin, ImplicitPricesPlugin, LeafOnlyPlugin, NativePlugin, NativePluginRegistry,
-    NoDuplicatesPlugin, OneCommodityPlugin, UniquePricesPlugin,
+    AccountFilter, AutoAccountsPlugin, CheckCommodityPlugin, CloseTreePlugin, CoherentCostPlugin,
+    HashFields, ImplicitPricesPlugin, LeafOnlyPlugin, NativePlugin, NativePluginRegistry,
+    NoDuplicatesPlugin, OneCommodityPlugin, TRANSACTION_HASH_KEY, UniquePricesPlugin,
+    transaction_hash,
 };
 use rustledger_plugin::types::*;
 
@@ -31,6 +33,7 @@ fn make_open(date: &str, account: &str) -> DirectiveWrapper {
             account: account.to_string(),
             currencies: vec![],
             booking: None,
+            metadata: vec![],
         }),
     }
 }
@@ -290,6 +293,101 @@ fn test_noduplicates_ok_different_amounts() {
     assert!(output.errors.is_empty(), "expected no errors");
 }
 
+/// Test each transaction gets its hash in metadata.
+#[test]
+fn test_noduplicates_hash_in_metadata() {
+    let plugin = NoDuplicatesPlugin;
+    let txn = make_transaction(
+        "2024-01-15",
+        "Grocery Store",
+        vec![
+            ("Expenses:Food", "50.00", "USD"),
+            ("Assets:Bank", "-50.00", "USD"),
+        ],
+    );
+    let DirectiveData::Transaction(data) = &txn.data else {
+        unreachable!()
+    };
+    let expected = transaction_hash(&txn.date, data, &HashFields::default());
+
+    let output = plugin.process(make_input(vec![txn.clone()]));
+    let DirectiveData::Transaction(data) = &output.directives[0].data else {
+        panic!("expected transaction");
+    };
+    assert_eq!(data.metadata.len(), 1);
+    assert_eq!(data.metadata[0].0, TRANSACTION_HASH_KEY);
+    assert!(matches!(&data.metadata[0].1, MetaValueData::String(h) if *h == expected));
+
+    // Processing again keeps a single, unchanged hash
+    let output = plugin.process(make_input(output.directives));
+    let DirectiveData::Transaction(data) = &output.directives[0].data else {
+        panic!("expected transaction");
+    };
+    assert_eq!(data.metadata.len(), 1);
+    assert!(matches!(&data.metadata[0].1, MetaValueData::String(h) if *h == expected));
+}
+
+/// Test the config selects which fields are hashed.
+#[test]
+fn test_noduplicates_hash_fields_config() {
+    let plugin = NoDuplicatesPlugin;
+    let with_narration = |narration: &str| {
+        make_transaction(
+            "2024-01-15",
+            narration,
+            vec![
+                ("Expenses:Food", "50.00", "USD"),
+                ("Assets:Bank", "-50.00", "USD"),
+            ],
+        )
+    };
+    let directives = vec![with_narration("Groceries"), with_narration("Food shopping")];
+
+    // Narration is hashed by default
+    let output = plugin.process(make_input(directives.clone()));
+    assert!(output.errors.is_empty(), "expected no errors");
+
+    let mut input = make_input(directives);
+    input.config = Some("{'narration': False}".to_string());
+    let output = plugin.process(input);
+    assert_eq!(output.errors.len(), 1, "expected 1 duplicate error");
+
+    let mut input = make_input(vec![]);
+    input.config = Some("{'payee': True}".to_string());
+    let output = plugin.process(input);
+    assert!(output.errors[0].message.contains("unknown field 'payee'"));
+}
+
+/// Test metadata is only hashed when enabled.
+#[test]
+fn test_noduplicates_hash_meta() {
+    let base = make_transaction(
+        "2024-01-15",
+        "Grocery Store",
+        vec![("Expenses:Food", "50.00", "USD")],
+    );
+    let DirectiveData::Transaction(plain) = &base.data else {
+        unreachable!()
+    };
+    let mut tagged = plain.clone();
+    tagged.metadata.push((
+        "receipt".to_string(),
+        MetaValueData::String("a.pdf".to_string()),
+    ));
+
+    let fields = HashFields::default();
+    assert_eq!(
+        transaction_hash(&base.date, plain, &fields),
+        transaction_hash(&base.date, &tagged, &fields)
+    );
+
+    let fields = HashFields::parse("{'meta': True}").unwrap();
+    assert_ne!(
+        transaction_hash(&base.date, plain, &fields),
+        transaction_hash(&base.date, &tagged, &fields)
+    );
+}
+
 // ============================================================================
 // OneCommodityPlugin Tests (from onecommodity_test.py)
 // ============================================================================
@@ -370,6 +468,214 @@ fn test_onecommodity_ok_single_currency() {
     assert!(output.errors.is_empty(), "expected no errors");
 }
 
+/// Opening balances legitimately mix currencies in one equity account; an
+/// account filter leaves it out.
+#[test]
+fn test_onecommodity_account_filters() {
+    let directives = vec![
+        make_open("2024-01-01", "Assets:Checking"),
+        make_open("2024-01-01", "Assets:Savings:EUR"),
+        make_open("2024-01-01", "Equity:Opening-Balances"),
+        make_transaction(
+            "2024-01-01",
+            "Opening balance",
+            vec![
+                ("Assets:Checking", "1000.00", "USD"),
+                ("Equity:Opening-Balances", "-1000.00", "USD"),
+            ],
+        ),
+        make_transaction(
+            "2024-01-01",
+            "Opening balance",
+            vec![
+                ("Assets:Savings:EUR", "500.00", "EUR"),
+                ("Equity:Opening-Balances", "-500.00", "EUR"),
+            ],
+        ),
+    ];
+
+    let output = OneCommodityPlugin.process(make_input(directives.clone()));
+    assert_eq!(output.errors.len(), 1);
+    assert!(
+        output.errors[0]
+            .message
+            .contains("More than one currency in account 'Equity:Opening-Balances': EUR, USD")
+    );
+
+    let mut input = make_input(directives.clone());
+    input.config = Some("{'exclude': 'Equity:'}".to_string());
+    assert!(OneCommodityPlugin.process(input).errors.is_empty());
+
+    // A bare pattern selects the accounts to check, as in beancount
+    let mut input = make_input(directives);
+    input.config = Some("Assets:.*".to_string());
+    assert!(OneCommodityPlugin.process(input).errors.is_empty());
+}
+
+/// Accounts opened with `onecommodity: FALSE` or with several currencies
+/// are not checked.
+#[test]
+fn test_onecommodity_open_opt_out() {
+    let mut conversions = make_open("2024-01-01", "Equity:Conversions");
+    if let DirectiveData::Open(open) = &mut conversions.data {
+        open.metadata
+            .push(("onecommodity".to_string(), MetaValueData::Bool(false)));
+    }
+    let mut wallet = make_open("2024-01-01", "Assets:Wallet");
+    if let DirectiveData::Open(open) = &mut wallet.data {
+        open.currencies = vec!["USD".to_string(), "EUR".to_string()];
+    }
+
+    let input = make_input(vec![
+        conversions,
+        wallet,
+        make_transaction(
+            "2024-02-01",
+            "Exchange",
+            vec![
+                ("Assets:Wallet", "-110.00", "USD"),
+                ("Equity:Conversions", "110.00", "USD"),
+                ("Equity:Conversions", "-100.00", "EUR"),
+                ("Assets:Wallet", "100.00", "EUR"),
+            ],
+        ),
+    ]);
+    assert!(OneCommodityPlugin.process(input).errors.is_empty());
+}
+
+/// Mixed cost currencies are reported at the transaction that mixed them.
+#[test]
+fn test_onecommodity_cost_currencies() {
+    let mut second = make_transaction_with_cost(
+        "2024-02-01",
+        "Buy in CAD",
+        "Assets:Broker",
+        ("5", "HOOL"),
+        ("130.00", "CAD"),
+        "Assets:Cash",
+    );
+    if let DirectiveData::Transaction(txn) = &mut second.data {
+        txn.metadata.push((
+            "filename".to_string(),
+            MetaValueData::String("main.beancount".to_string()),
+        ));
+        txn.metadata.push((
+            "lineno".to_string(),
+            MetaValueData::Number("42".to_string()),
+        ));
+    }
+
+    let mut input = make_input(vec![
+        make_transaction_with_cost(
+            "2024-01-15",
+            "Buy",
+            "Assets:Broker",
+            ("10", "HOOL"),
+            ("100.00", "USD"),
+            "Assets:Cash",
+        ),
+        second,
+    ]);
+    input.config = Some("{'include': 'Assets:Broker'}".to_string());
+
+    let output = OneCommodityPlugin.process(input);
+    assert_eq!(output.errors.len(), 1);
+    let error = &output.errors[0];
+    assert!(
+        error
+            .message
+            .contains("More than one cost currency in account 'Assets:Broker': CAD, USD")
+    );
+    assert!(error.message.contains("2024-02-01"));
+    assert_eq!(error.source_file.as_deref(), Some("main.beancount"));
+    assert_eq!(error.line_number, Some(42));
+}
+
+#[test]
+fn test_account_filter_config() {
+    let filter =
+        AccountFilter::parse("{'include': 'Assets:', \"exclude\": 'Assets:Broker:(Old|Closed)'}")
+            .unwrap();
+    assert!(filter.matches("Assets:Checking"));
+    assert!(!filter.matches("Assets:Broker:Old:HOOL"));
+    assert!(!filter.matches("Expenses:Food"));
+
+    // Patterns match at the start of the account name
+    assert!(
+        !AccountFilter::parse("Broker")
+            .unwrap()
+            .matches("Assets:Broker")
+    );
+    assert!(AccountFilter::parse("{'exclude': '('}").is_err());
+    assert!(AccountFilter::parse("{'skip': 'Equity:'}").is_err());
+
+    let mut input = make_input(vec![]);
+    input.config = Some("{'include': 'Assets}".to_string());
+    let output = OneCommodityPlugin.process(input);
+    assert_eq!(output.errors.len(), 1);
+    assert!(
+        output.errors[0]
+            .message
+            .contains("Invalid onecommodity config")
+    );
+}
+
+// ============================================================================
+// CoherentCostPlugin Tests (from coherent_cost_test.py)
+// ============================================================================
+
+fn coherent_cost_ledger() -> Vec<DirectiveWrapper> {
+    vec![
+        make_open("2024-01-01", "Assets:Broker"),
+        make_open("2024-01-01", "Assets:Cash"),
+        make_transaction_with_cost(
+            "2024-01-15",
+            "Buy",
+            "Assets:Broker",
+            ("10", "HOOL"),
+            ("100.00", "USD"),
+            "Assets:Cash",
+        ),
+        // Sold without its lot
+        make_transaction(
+            "2024-03-01",
+            "Sell",
+            vec![
+                ("Assets:Broker", "-10", "HOOL"),
+                ("Assets:Cash", "1200.00", "USD"),
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn test_coherent_cost_with_and_without_cost() {
+    let output = CoherentCostPlugin.process(make_input(coherent_cost_ledger()));
+    assert_eq!(output.errors.len(), 1);
+    let message = &output.errors[0].message;
+    assert!(message.contains("Currency 'HOOL' is used both with and without cost"));
+    assert!(message.contains("2024-03-01"));
+}
+
+#[test]
+fn test_coherent_cost_opt_out() {
+    let mut directives = coherent_cost_ledger();
+    if let DirectiveData::Open(open) = &mut directives[0].data {
+        open.metadata
+            .push(("coherent_cost".to_string(), MetaValueData::Bool(false)));
+    }
+    assert!(
+        CoherentCostPlugin
+            .process(make_input(directives))
+            .errors
+            .is_empty()
+    );
+
+    let mut input = make_input(coherent_cost_ledger());
+    input.config = Some("{'exclude': 'Assets:Broker'}".to_string());
+    assert!(CoherentCostPlugin.process(input).errors.is_empty());
+}
+
 // ============================================================================
 // CheckCommodityPlugin Tests (from check_commodity_test.py)
 // ============================================================================
@@ -535,6 +841,182 @@ fn test_implicit_prices_from_cost() {
     }
 }
 
+// ============================================================================
+// AutoAccounts Plugin Tests
+// ============================================================================
+
+fn make_close(date: &str, account: &str) -> DirectiveWrapper {
+    DirectiveWrapper {
+        directive_type: "close".to_string(),
+        date: date.to_string(),
+        data: DirectiveData::Close(CloseData {
+            account: account.to_string(),
+        }),
+    }
+}
+
+/// `(type, date, account)` of opens and closes, in output order.
+fn account_events(output: &PluginOutput) -> Vec<(&str, &str, &str)> {
+    output
+        .directives
+        .iter()
+        .filter_map(|d| match &d.data {
+            DirectiveData::Open(data) => Some(("open", d.date.as_str(), data.account.as_str())),
+            DirectiveData::Close(data) => Some(("close", d.date.as_str(), data.account.as_str())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_auto_accounts_opens_at_first_use() {
+    let plugin = AutoAccountsPlugin;
+    // Input is not in date order: the earliest use of Assets:Cash comes second
+    let input = make_input(vec![
+        make_open("2024-01-01", "Assets:Bank"),
+        make_transaction(
+            "2024-03-01",
+            "Later",
+            vec![("Assets:Cash", "-5", "USD"), ("Expenses:Food", "5", "USD")],
+        ),
+        make_transaction(
+            "2024-02-01",
+            "Earlier",
+            vec![("Assets:Bank", "-10", "USD"), ("Assets:Cash", "10", "USD")],
+        ),
+    ]);
+
+    let output = plugin.process(input);
+    assert!(output.errors.is_empty());
+    assert_eq!(
+        account_events(&output),
+        [
+            ("open", "2024-01-01", "Assets:Bank"),
+            ("open", "2024-02-01", "Assets:Cash"),
+            ("open", "2024-03-01", "Expenses:Food"),
+        ]
+    );
+
+    // Each generated open precedes the transaction on the same date
+    let types: Vec<_> = output
+        .directives
+        .iter()
+        .map(|d| d.directive_type.as_str())
+        .collect();
+    assert_eq!(
+        types,
+        ["open", "open", "transaction", "open", "transaction"]
+    );
+}
+
+#[test]
+fn test_auto_accounts_is_deterministic() {
+    let plugin = AutoAccountsPlugin;
+    let make = || {
+        make_input(vec![make_transaction(
+            "2024-01-01",
+            "Split",
+            vec![
+                ("Expenses:C", "1", "USD"),
+                ("Expenses:A", "1", "USD"),
+                ("Expenses:B", "1", "USD"),
+                ("Assets:Cash", "-3", "USD"),
+            ],
+        )])
+    };
+
+    let first = plugin.process(make());
+    let accounts: Vec<_> = account_events(&first).into_iter().map(|e| e.2).collect();
+    assert_eq!(
+        accounts,
+        ["Assets:Cash", "Expenses:A", "Expenses:B", "Expenses:C"]
+    );
+    for _ in 0..5 {
+        assert_eq!(
+            account_events(&plugin.process(make())),
+            account_events(&first)
+        );
+    }
+}
+
+// ============================================================================
+// CloseTree Plugin Tests
+// ============================================================================
+
+#[test]
+fn test_close_tree_closes_descendants_after_activity() {
+    let plugin = CloseTreePlugin;
+    let input = make_input(vec![
+        make_open("2024-01-01", "Assets:Bank"),
+        make_open("2024-01-01", "Assets:Bank:Checking"),
+        make_open("2024-01-01", "Assets:Bank:Savings:Bonus"),
+        make_open("2024-01-01", "Assets:Banking"),
+        make_close("2024-06-30", "Assets:Bank"),
+        make_transaction(
+            "2024-06-30",
+            "Last transfer",
+            vec![
+                ("Assets:Bank:Checking", "-10", "USD"),
+                ("Assets:Banking", "10", "USD"),
+            ],
+        ),
+    ]);
+
+    let output = plugin.process(input);
+    let closes: Vec<_> = account_events(&output)
+        .into_iter()
+        .filter(|e| e.0 == "close")
+        .collect();
+    assert_eq!(
+        closes,
+        [
+            ("close", "2024-06-30", "Assets:Bank"),
+            ("close", "2024-06-30", "Assets:Bank:Checking"),
+            ("close", "2024-06-30", "Assets:Bank:Savings:Bonus"),
+        ]
+    );
+
+    // Closes follow the transaction on the close date
+    let last = output
+        .directives
+        .iter()
+        .rposition(|d| d.directive_type == "transaction");
+    let first_close = output
+        .directives
+        .iter()
+        .position(|d| d.directive_type == "close");
+    assert!(last < first_close);
+}
+
+#[test]
+fn test_close_tree_respects_closed_children() {
+    let plugin = CloseTreePlugin;
+    let input = make_input(vec![
+        make_open("2024-01-01", "Assets:Bank"),
+        make_open("2024-01-01", "Assets:Bank:Old"),
+        make_open("2024-01-01", "Assets:Bank:Sub:Checking"),
+        make_close("2024-03-01", "Assets:Bank:Old"),
+        make_close("2024-09-01", "Assets:Bank"),
+        make_close("2024-06-01", "Assets:Bank:Sub"),
+    ]);
+
+    let output = plugin.process(input);
+    assert_eq!(
+        account_events(&output)
+            .into_iter()
+            .filter(|e| e.0 == "close")
+            .collect::<Vec<_>>(),
+        [
+            // The explicit close of a child is kept and not duplicated
+            ("close", "2024-03-01", "Assets:Bank:Old"),
+            // Descendants close with their nearest closed ancestor
+            ("close", "2024-06-01", "Assets:Bank:Sub"),
+            ("close", "2024-06-01", "Assets:Bank:Sub:Checking"),
+            ("close", "2024-09-01", "Assets:Bank"),
+        ]
+    );
+}
+
 // ============================================================================
 // NativePluginRegistry Tests
 // ============================================================================
@@ -581,3 +1063,76 @@ fn test_registry_list_all() {
     // Should have at least 13 plugins (14 minus auto_tag which might be different)
     assert!(plugins.len() >= 13, "should have at least 13 plugins");
 }
+
+// ============================================================================
+// SellGains Plugin Tests
+// ============================================================================
+
+fn sellgains_ledger(with_gain_posting: bool) -> Vec<DirectiveWrapper> {
+    use rust_decimal_macros::dec;
+    use rustledger_core::{
+        Amount, CostSpec, Directive, NaiveDate, Open, Posting, PriceAnnotation, Transaction,
+    };
+
+    let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+    let buy = |day: NaiveDate, cost| {
+        Directive::Transaction(
+            Transaction::new(day, "Buy")
+                .with_posting(
+                    Posting::new("Assets:Stock", Amount::new(dec!(10), "AAPL")).with_cost(
+                        CostSpec::empty()
+                            .with_number_per(cost)
+                            .with_currency("USD")
+                            .with_date(day),
+                    ),
+                )
+                .with_posting(Posting::new(
+                    "Assets:Cash",
+                    Amount::new(-cost * dec!(10), "USD"),
+                )),
+        )
+    };
+
+    // Sell 15 from the oldest lots at 160: 10 * 60 + 5 * 10 = 650 gain
+    let mut sale = Transaction::new(date(3, 1), "Sell")
+        .with_posting(
+            Posting::new("Assets:Stock", Amount::new(dec!(-15), "AAPL"))
+                .with_cost(CostSpec::empty())
+                .with_price(PriceAnnotation::Unit(Amount::new(dec!(160), "USD"))),
+        )
+        .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(2400), "USD")));
+    if with_gain_posting {
+        sale = sale.with_posting(Posting::new("Income:Gains", Amount::new(dec!(-650), "USD")));
+    }
+
+    let directives = vec![
+        Directive::Open(Open::new(date(1, 1), "Assets:Stock").with_booking("FIFO")),
+        buy(date(1, 1), dec!(100)),
+        buy(date(2, 1), dec!(150)),
+        Directive::Transaction(sale),
+    ];
+    rustledger_plugin::directives_to_wrappers(&directives)
+}
+
+#[test]
+fn test_sellgains_expected_gain_from_matched_lots() {
+    let plugin = rustledger_plugin::native::SellGainsPlugin;
+    let output = plugin.process(make_input(sellgains_ledger(false)));
+
+    assert_eq!(output.errors.len(), 1, "{:?}", output.errors);
+    assert!(
+        output.errors[0]
+            .message
+            .contains("expected gain/loss of 650"),
+        "{}",
+        output.errors[0].message
+    );
+}
+
+#[test]
+fn test_sellgains_with_gain_posting() {
+    let plugin = rustledger_plugin::native::SellGainsPlugin;
+    let output = plugin.process(make_input(sellgains_ledger(true)));
+
+    assert_eq!(output.errors.len(), 0, "{:?}", output.errors);
+}
@@ -4,7 +4,7 @@
 //! a SQL-like query language for financial data analysis.
 
 use rust_decimal::Decimal;
-use rustledger_core::NaiveDate;
+use rustledger_core::{Amount, NaiveDate};
 
 /// A complete BQL query.
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +17,8 @@ pub enum Query {
     Balances(BalancesQuery),
     /// PRINT shorthand query.
     Print(PrintQuery),
+    /// EXPORT query (entries in another tool's syntax).
+    Export(ExportQuery),
 }
 
 /// A SELECT query.
@@ -54,6 +56,8 @@ pub struct Target {
 /// FROM clause with transaction-level modifiers.
 #[derive(Debug, Clone, PartialEq)]
 pub struct FromClause {
+    /// Table rows are read from (`#postings` or `#transactions`).
+    pub table: Table,
     /// OPEN ON date - summarize entries before this date.
     pub open_on: Option<NaiveDate>,
     /// CLOSE ON date - truncate entries after this date.
@@ -66,6 +70,16 @@ pub struct FromClause {
     pub subquery: Option<Box<SelectQuery>>,
 }
 
+/// Table a SELECT reads its rows from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Table {
+    /// One row per posting (default).
+    #[default]
+    Postings,
+    /// One row per transaction; posting columns are not available.
+    Transactions,
+}
+
 /// ORDER BY specification.
 #[derive(Debug, Clone, PartialEq)]
 pub struct OrderSpec {
@@ -112,6 +126,26 @@ pub struct PrintQuery {
     pub from: Option<FromClause>,
 }
 
+/// EXPORT query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportQuery {
+    /// Target syntax.
+    pub format: ExportFormat,
+    /// Optional FROM clause.
+    pub from: Option<FromClause>,
+}
+
+/// Target syntax for EXPORT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Beancount syntax.
+    Beancount,
+    /// ledger-cli syntax.
+    Ledger,
+    /// hledger syntax.
+    Hledger,
+}
+
 /// An expression in BQL.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
@@ -144,6 +178,8 @@ pub enum Literal {
     Integer(i64),
     /// Date literal.
     Date(NaiveDate),
+    /// Amount literal, as in `1000 USD`.
+    Amount(Amount),
     /// Boolean literal.
     Boolean(bool),
     /// NULL literal.
@@ -330,6 +366,7 @@ impl FromClause {
     /// Create a new empty FROM clause.
     pub const fn new() -> Self {
         Self {
+            table: Table::Postings,
             open_on: None,
             close_on: None,
             clear: false,
@@ -341,6 +378,7 @@ impl FromClause {
     /// Create a FROM clause from a subquery.
     pub fn from_subquery(query: SelectQuery) -> Self {
         Self {
+            table: Table::Postings,
             open_on: None,
             close_on: None,
             clear: false,
@@ -349,6 +387,12 @@ impl FromClause {
         }
     }
 
+    /// Set the table rows are read from.
+    pub const fn table(mut self, table: Table) -> Self {
+        self.table = table;
+        self
+    }
+
     /// Set the OPEN ON date.
     pub const fn open_on(mut self, date: NaiveDate) -> Self {
         self.open_on = Some(date);
@@ -94,6 +94,8 @@ pub enum BqlContext {
     AfterBalances,
     /// After PRINT keyword.
     AfterPrint,
+    /// After EXPORT keyword.
+    AfterExport,
     /// Inside a function call, after opening paren.
     InFunction(String),
     /// After a comparison operator, expecting value.
@@ -141,6 +143,39 @@ pub fn complete(partial_query: &str, cursor_pos: usize) -> CompletionResult {
     }
 }
 
+/// Look up the documentation of a BQL keyword, column or function.
+///
+/// Matching ignores case, and function names match without their
+/// parentheses (`sum` finds `SUM(`). Used for hover text in editors.
+///
+/// ```
+/// use rustledger_query::completions::{CompletionCategory, describe};
+///
+/// let sum = describe("sum").unwrap();
+/// assert_eq!(sum.category, CompletionCategory::Function);
+/// ```
+#[must_use]
+pub fn describe(word: &str) -> Option<Completion> {
+    [
+        BqlContext::Start,
+        BqlContext::AfterSelect,
+        BqlContext::AfterSelectTargets,
+        BqlContext::AfterFrom,
+        BqlContext::InWhereExpr,
+        BqlContext::AfterOrderBy,
+        BqlContext::AfterExport,
+    ]
+    .iter()
+    .flat_map(get_completions_for_context)
+    .find(|completion| {
+        completion.description.is_some()
+            && completion
+                .text
+                .trim_end_matches(['(', ')'])
+                .eq_ignore_ascii_case(word)
+    })
+}
+
 /// Simple tokenizer for BQL.
 fn tokenize_bql(text: &str) -> Vec<String> {
     let mut tokens = Vec::new();
@@ -204,7 +239,7 @@ fn determine_context(tokens: &[String]) -> BqlContext {
 
     // Check for incomplete string
     if let Some(last) = tokens.last() {
-        if last.starts_with('"') && !last.ends_with('"') {
+        if last.starts_with('"') && (last.len() == 1 || !last.ends_with('"')) {
             return BqlContext::InString;
         }
     }
@@ -217,6 +252,7 @@ fn determine_context(tokens: &[String]) -> BqlContext {
         "JOURNAL" => BqlContext::AfterJournal,
         "BALANCES" => BqlContext::AfterBalances,
         "PRINT" => BqlContext::AfterPrint,
+        "EXPORT" => BqlContext::AfterExport,
         _ => BqlContext::Start,
     }
 }
@@ -329,6 +365,7 @@ fn get_completions_for_context(context: &BqlContext) -> Vec<Completion> {
             keyword("BALANCES", Some("Show account balances")),
             keyword("JOURNAL", Some("Show account journal")),
             keyword("PRINT", Some("Print transactions")),
+            keyword("EXPORT", Some("Export entries to ledger or hledger syntax")),
         ],
 
         BqlContext::AfterSelect => {
@@ -352,6 +389,8 @@ fn get_completions_for_context(context: &BqlContext) -> Vec<Completion> {
         ],
 
         BqlContext::AfterFrom => vec![
+            keyword("#postings", Some("One row per posting (default)")),
+            keyword("#transactions", Some("One row per transaction")),
             keyword("OPEN ON", Some("Summarize entries before date")),
             keyword("CLOSE ON", Some("Truncate entries after date")),
             keyword("CLEAR", Some("Transfer income/expense to equity")),
@@ -391,6 +430,9 @@ fn get_completions_for_context(context: &BqlContext) -> Vec<Completion> {
                 operator("<=", Some("Less or equal")),
                 operator(">=", Some("Greater or equal")),
                 keyword("IN", Some("Set membership")),
+                keyword("BETWEEN", Some("Inclusive range")),
+                keyword("IN LAST", Some("Relative date range, e.g. LAST 90 DAYS")),
+                keyword("IN THIS", Some("Current period, e.g. THIS MONTH")),
                 keyword("GROUP BY", Some("Group results")),
                 keyword("ORDER BY", Some("Sort results")),
                 keyword("LIMIT", Some("Limit result count")),
@@ -402,6 +444,7 @@ fn get_completions_for_context(context: &BqlContext) -> Vec<Completion> {
         BqlContext::AfterGroupBy => {
             let mut completions = column_completions();
             completions.extend(vec![
+                function("ROLLUP(", "Group into the account and its ancestors"),
                 keyword("ORDER BY", Some("Sort results")),
                 keyword("LIMIT", Some("Limit result count")),
                 operator(",", Some("Add another group column")),
@@ -429,6 +472,14 @@ fn get_completions_for_context(context: &BqlContext) -> Vec<Completion> {
             keyword("FROM", Some("Specify data source")),
         ],
 
+        BqlContext::AfterExport => vec![
+            keyword("TO", Some("Target syntax")),
+            keyword("LEDGER", Some("ledger-cli syntax")),
+            keyword("HLEDGER", Some("hledger syntax")),
+            keyword("BEANCOUNT", Some("Beancount syntax")),
+            keyword("FROM", Some("Specify data source")),
+        ],
+
         BqlContext::AfterAs | BqlContext::InString | BqlContext::InFunction(_) => vec![],
     }
 }
@@ -480,16 +531,23 @@ fn column_completions() -> Vec<Completion> {
     vec![
         column("account", "Account name"),
         column("date", "Transaction date"),
+        column("time", "Transaction time of day (time metadata)"),
         column("narration", "Transaction description"),
         column("payee", "Transaction payee"),
         column("flag", "Transaction flag"),
         column("tags", "Transaction tags"),
         column("links", "Document links"),
         column("position", "Posting amount"),
+        column("posting", "The posting, for capital gains functions"),
         column("units", "Posting units"),
         column("cost", "Cost basis"),
         column("weight", "Balancing weight"),
         column("balance", "Running balance"),
+        column("lots", "Lots matched by a reduction"),
+        column("gain", "Realized gain of a reduction"),
+        column("ledger", "Ledger the posting came from"),
+        column("id", "Stable content hash of the transaction"),
+        column("accounts", "Accounts of the transaction's postings"),
         column("year", "Transaction year"),
         column("month", "Transaction month"),
         column("day", "Transaction day"),
@@ -515,6 +573,8 @@ fn function_completions() -> Vec<Completion> {
         function("WEEKDAY(", "Day of week (0=Mon)"),
         function("YMONTH(", "Year-month format"),
         function("TODAY()", "Current date"),
+        function("DATE_TRUNC(", "Start of day/week/month/quarter/year"),
+        function("DATE_ADD(", "Shift a date by days or another unit"),
         // String functions
         function("LENGTH(", "String length"),
         function("UPPER(", "Uppercase"),
@@ -531,6 +591,12 @@ fn function_completions() -> Vec<Completion> {
         function("CURRENCY(", "Extract currency"),
         function("ABS(", "Absolute value"),
         function("ROUND(", "Round number"),
+        // Portfolio return functions
+        function("TWRR(", "Time-weighted return"),
+        function("XIRR(", "Money-weighted annualized return"),
+        // Capital gains functions
+        function("REALIZED_GAIN(", "Realized gain of a reduction"),
+        function("HOLDING_PERIOD(", "Days the reduced lots were held"),
     ]
 }
 
@@ -575,6 +641,23 @@ mod tests {
         assert!(result.completions.iter().any(|c| c.text == "BY"));
     }
 
+    #[test]
+    fn test_describe() {
+        let account = describe("ACCOUNT").unwrap();
+        assert_eq!(account.category, CompletionCategory::Column);
+        assert_eq!(account.description.as_deref(), Some("Account name"));
+
+        assert_eq!(
+            describe("today").unwrap().category,
+            CompletionCategory::Function
+        );
+        assert_eq!(
+            describe("where").unwrap().category,
+            CompletionCategory::Keyword
+        );
+        assert!(describe("bogus").is_none());
+    }
+
     #[test]
     fn test_tokenize_bql() {
         let tokens = tokenize_bql("SELECT account, SUM(position)");
@@ -54,4 +54,14 @@ pub enum QueryError {
     /// Evaluation error.
     #[error("evaluation error: {0}")]
     Evaluation(String),
+    /// Arithmetic or comparison between amounts of different currencies.
+    #[error("currency mismatch: cannot {op} {left} and {right}")]
+    CurrencyMismatch {
+        /// The operation attempted (`add`, `subtract`, `divide`, `compare`).
+        op: &'static str,
+        /// The left operand.
+        left: String,
+        /// The right operand.
+        right: String,
+    },
 }
@@ -3,6 +3,7 @@
 //! Executes parsed BQL queries against a set of Beancount directives.
 
 use std::cell::RefCell;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
@@ -10,15 +11,22 @@ use chrono::Datelike;
 use regex::Regex;
 use rust_decimal::Decimal;
 use rustledger_core::{
-    Amount, Directive, InternedStr, Inventory, NaiveDate, Position, Transaction,
+    Amount, BookingMethod, BookingResult, Directive, InternedStr, Inventory, LotMatch, MixedAmount,
+    NaiveDate, Position, Posting, Transaction, directive_id,
 };
 
 use crate::ast::{
-    BalancesQuery, BinaryOp, BinaryOperator, Expr, FromClause, FunctionCall, JournalQuery, Literal,
-    OrderSpec, PrintQuery, Query, SelectQuery, SortDirection, Target, UnaryOp, UnaryOperator,
-    WindowFunction,
+    BalancesQuery, BinaryOp, BinaryOperator, ExportQuery, Expr, FromClause, FunctionCall,
+    JournalQuery, Literal, OrderSpec, PrintQuery, Query, SelectQuery, SortDirection, Table, Target,
+    UnaryOp, UnaryOperator, WindowFunction,
 };
 use crate::error::QueryError;
+use crate::functions::ScalarFunction;
+
+/// Columns that describe a single posting rather than its transaction.
+const POSTING_COLUMNS: &[&str] = &[
+    "account", "position", "posting", "units", "cost", "weight", "balance", "lots", "gain",
+];
 
 /// A value that can result from evaluating a BQL expression.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -116,6 +124,77 @@ fn hash_single_value(value: &Value) -> u64 {
     hasher.finish()
 }
 
+/// A call of a user-defined function, keying its cached result.
+#[derive(PartialEq, Eq)]
+struct FunctionCallKey {
+    /// Lowercased function name.
+    function: String,
+    /// Evaluated arguments.
+    args: Vec<Value>,
+}
+
+impl Hash for FunctionCallKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.function.hash(state);
+        for value in &self.args {
+            value.hash_value(state);
+        }
+    }
+}
+
+/// Compare two values for sorting purposes.
+fn compare_values_for_sort(left: &Value, right: &Value) -> std::cmp::Ordering {
+    match (left, right) {
+        (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
+        (Value::Null, _) => std::cmp::Ordering::Greater, // Nulls sort last
+        (_, Value::Null) => std::cmp::Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => a.cmp(b),
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::Number(a), Value::Integer(b)) => a.cmp(&Decimal::from(*b)),
+        (Value::Integer(a), Value::Number(b)) => Decimal::from(*a).cmp(b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Date(a), Value::Date(b)) => a.cmp(b),
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        // Compare amounts by their numeric value (same currency assumed)
+        (Value::Amount(a), Value::Amount(b)) => a.number.cmp(&b.number),
+        // Compare positions by their units' numeric value
+        (Value::Position(a), Value::Position(b)) => a.units.number.cmp(&b.units.number),
+        // Compare inventories by first position's value (for single-currency)
+        (Value::Inventory(a), Value::Inventory(b)) => {
+            let a_val = a.positions().first().map(|p| &p.units.number);
+            let b_val = b.positions().first().map(|p| &p.units.number);
+            match (a_val, b_val) {
+                (Some(av), Some(bv)) => av.cmp(bv),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        }
+        _ => std::cmp::Ordering::Equal, // Can't compare other types
+    }
+}
+
+/// Convert a value to string for display/grouping.
+fn value_to_string(val: &Value) -> String {
+    match val {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Date(d) => d.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Amount(a) => format!("{} {}", a.number, a.currency),
+        Value::Position(p) => format!("{}", p.units),
+        Value::Inventory(inv) => inv
+            .positions()
+            .iter()
+            .map(|p| format!("{}", p.units))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::StringSet(ss) => ss.join(", "),
+        Value::Null => "NULL".to_string(),
+    }
+}
+
 /// A row of query results.
 pub type Row = Vec<Value>;
 
@@ -151,6 +230,347 @@ impl QueryResult {
     pub fn is_empty(&self) -> bool {
         self.rows.is_empty()
     }
+
+    /// Pivot the result into a matrix.
+    ///
+    /// Each distinct value of `pivot_column` becomes a column (in sorted
+    /// order) holding the matching `value_column` cell. The remaining
+    /// columns identify the rows, which keep the order in which they first
+    /// appear; cells without a matching row are NULL. Pivoting
+    /// `account, month, sum(position)` on column 1 with values from column 2
+    /// gives one row per account and one column per month.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either column is out of range or both are the
+    /// same column.
+    pub fn pivot(&self, pivot_column: usize, value_column: usize) -> Result<Self, QueryError> {
+        let width = self.columns.len();
+        if pivot_column >= width || value_column >= width {
+            return Err(QueryError::Evaluation(format!(
+                "PIVOT column index out of range (result has {width} columns)"
+            )));
+        }
+        if pivot_column == value_column {
+            return Err(QueryError::Evaluation(
+                "PIVOT column and value column must differ".to_string(),
+            ));
+        }
+
+        // Sorting compares amounts by number only, so equal numbers in
+        // different currencies are ordered by their text and deduplicated
+        // on the full (number, currency) hash rather than by adjacency.
+        let mut sorted: Vec<&Value> = self.rows.iter().map(|row| &row[pivot_column]).collect();
+        sorted.sort_by(|a, b| {
+            compare_values_for_sort(a, b).then_with(|| value_to_string(a).cmp(&value_to_string(b)))
+        });
+        let mut pivot_values: Vec<Value> = Vec::new();
+        let mut pivot_index: HashMap<u64, usize> = HashMap::new();
+        for value in sorted {
+            if let Entry::Vacant(entry) = pivot_index.entry(hash_single_value(value)) {
+                entry.insert(pivot_values.len());
+                pivot_values.push(value.clone());
+            }
+        }
+
+        let key_columns: Vec<usize> = (0..width)
+            .filter(|&i| i != pivot_column && i != value_column)
+            .collect();
+        let mut columns: Vec<String> = key_columns
+            .iter()
+            .map(|&i| self.columns[i].clone())
+            .collect();
+        columns.extend(pivot_values.iter().map(value_to_string));
+
+        let mut result = Self::new(columns);
+        let mut row_index: HashMap<Vec<String>, usize> = HashMap::new();
+        for row in &self.rows {
+            let key: Vec<String> = key_columns
+                .iter()
+                .map(|&i| value_to_string(&row[i]))
+                .collect();
+            let idx = *row_index.entry(key).or_insert_with(|| {
+                let mut new_row: Row = key_columns.iter().map(|&i| row[i].clone()).collect();
+                new_row.resize(key_columns.len() + pivot_values.len(), Value::Null);
+                result.rows.push(new_row);
+                result.rows.len() - 1
+            });
+            let cell = key_columns.len() + pivot_index[&hash_single_value(&row[pivot_column])];
+            // With several source rows per cell, keep the first non-NULL one
+            if result.rows[idx][cell] == Value::Null {
+                result.rows[idx][cell] = row[value_column].clone();
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Split amount columns into one number column per currency.
+    ///
+    /// This mirrors bean-query's `--numberify` for spreadsheet import: a
+    /// column holding amounts, positions or inventories is replaced by
+    /// `<name> (<CURRENCY>)` columns, one per currency found in it (in
+    /// sorted order), holding plain numbers. Positions contribute their
+    /// units and inventories are summed per currency; cells without that
+    /// currency are NULL. Other columns are kept as they are.
+    #[must_use]
+    pub fn numberify(&self) -> Self {
+        let currencies: Vec<Vec<String>> = (0..self.columns.len())
+            .map(|i| {
+                let mut found: Vec<String> = Vec::new();
+                for row in &self.rows {
+                    for currency in value_units(&row[i]).currencies() {
+                        if !found.iter().any(|c| c == currency.as_str()) {
+                            found.push(currency.to_string());
+                        }
+                    }
+                }
+                found.sort();
+                found
+            })
+            .collect();
+
+        let mut columns = Vec::new();
+        for (name, found) in self.columns.iter().zip(&currencies) {
+            if found.is_empty() {
+                columns.push(name.clone());
+            } else {
+                columns.extend(found.iter().map(|currency| format!("{name} ({currency})")));
+            }
+        }
+
+        let mut result = Self::new(columns);
+        for row in &self.rows {
+            let mut new_row = Row::new();
+            for (value, found) in row.iter().zip(&currencies) {
+                if found.is_empty() {
+                    new_row.push(value.clone());
+                    continue;
+                }
+                let units = value_units(value);
+                for currency in found {
+                    new_row.push(
+                        units
+                            .get(currency)
+                            .map_or(Value::Null, |n| Value::Number(*n)),
+                    );
+                }
+            }
+            result.add_row(new_row);
+        }
+        result
+    }
+}
+
+/// The units held by an amount-like value, summed per currency.
+fn value_units(value: &Value) -> MixedAmount {
+    match value {
+        Value::Amount(a) => MixedAmount::from(a.clone()),
+        Value::Position(p) => MixedAmount::from(p.units.clone()),
+        Value::Inventory(inv) => inv.positions().iter().map(|p| p.units.clone()).collect(),
+        _ => MixedAmount::new(),
+    }
+}
+
+/// An integer held by a numeric value without a fractional part.
+fn whole_number(value: &Value) -> Option<i64> {
+    use rust_decimal::prelude::ToPrimitive;
+    match value {
+        Value::Integer(i) => Some(*i),
+        Value::Number(n) if n.fract().is_zero() => n.to_i64(),
+        _ => None,
+    }
+}
+
+/// Date arithmetic: `date + days`, `days + date`, `date - days`, and
+/// `date - date` giving the number of days between them.
+fn date_arithmetic(op: BinaryOperator, left: &Value, right: &Value) -> Result<Value, QueryError> {
+    let days = |value: &Value| {
+        whole_number(value).ok_or_else(|| {
+            QueryError::Type("date arithmetic requires a whole number of days".to_string())
+        })
+    };
+    match (op, left, right) {
+        (BinaryOperator::Sub, Value::Date(a), Value::Date(b)) => {
+            Ok(Value::Integer((*a - *b).num_days()))
+        }
+        (BinaryOperator::Add, Value::Date(date), n)
+        | (BinaryOperator::Add, n, Value::Date(date)) => {
+            shift_date(*date, days(n)?, "day").map(Value::Date)
+        }
+        (BinaryOperator::Sub, Value::Date(date), n) => {
+            shift_date(*date, -days(n)?, "day").map(Value::Date)
+        }
+        _ => Err(QueryError::Type(
+            "cannot subtract a date from a number".to_string(),
+        )),
+    }
+}
+
+/// Whether a value carries a currency, making arithmetic on it amount
+/// arithmetic.
+const fn is_amount_like(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Amount(_) | Value::Position(_) | Value::Inventory(_)
+    )
+}
+
+/// Amount arithmetic: `amount + amount` and `amount - amount` in the same
+/// currency, `amount * number`, `number * amount` and `amount / number`
+/// giving amounts, and `amount / amount` in the same currency giving their
+/// ratio. Positions take part through their units.
+///
+/// Amounts are never silently reduced to numbers: mixing currencies is a
+/// [`QueryError::CurrencyMismatch`], and adding a plain number to an amount
+/// is a type error pointing at `NUMBER()`.
+fn amount_arithmetic(op: BinaryOperator, left: &Value, right: &Value) -> Result<Value, QueryError> {
+    let amount = |value: &Value| match value {
+        Value::Amount(amount) => Some(amount.clone()),
+        Value::Position(position) => Some(position.units.clone()),
+        _ => None,
+    };
+    let number = |value: &Value| match value {
+        Value::Number(n) => Some(*n),
+        Value::Integer(i) => Some(Decimal::from(*i)),
+        _ => None,
+    };
+    let verb = match op {
+        BinaryOperator::Add => "add",
+        BinaryOperator::Sub => "subtract",
+        BinaryOperator::Mul => "multiply",
+        _ => "divide",
+    };
+    if matches!(left, Value::Inventory(_)) || matches!(right, Value::Inventory(_)) {
+        return Err(QueryError::Type(format!(
+            "cannot {verb} inventories; use UNITS(), GETITEM() or CONVERT() first"
+        )));
+    }
+    let divide = |a: Decimal, b: Decimal| {
+        a.checked_div(b)
+            .ok_or_else(|| QueryError::Evaluation("division by zero".to_string()))
+    };
+
+    match (amount(left), amount(right)) {
+        (Some(a), Some(b)) => {
+            if a.currency != b.currency {
+                return Err(QueryError::CurrencyMismatch {
+                    op: verb,
+                    left: a.to_string(),
+                    right: b.to_string(),
+                });
+            }
+            match op {
+                BinaryOperator::Add => {
+                    Ok(Value::Amount(Amount::new(a.number + b.number, a.currency)))
+                }
+                BinaryOperator::Sub => {
+                    Ok(Value::Amount(Amount::new(a.number - b.number, a.currency)))
+                }
+                BinaryOperator::Div => divide(a.number, b.number).map(Value::Number),
+                _ => Err(QueryError::Type("cannot multiply two amounts".to_string())),
+            }
+        }
+        (Some(a), None) => match (op, number(right)) {
+            (BinaryOperator::Mul, Some(n)) => {
+                Ok(Value::Amount(Amount::new(a.number * n, a.currency)))
+            }
+            (BinaryOperator::Div, Some(n)) => {
+                divide(a.number, n).map(|number| Value::Amount(Amount::new(number, a.currency)))
+            }
+            _ => Err(amount_type_error(verb)),
+        },
+        (None, Some(b)) => match (op, number(left)) {
+            (BinaryOperator::Mul, Some(n)) => {
+                Ok(Value::Amount(Amount::new(n * b.number, b.currency)))
+            }
+            _ => Err(amount_type_error(verb)),
+        },
+        (None, None) => Err(amount_type_error(verb)),
+    }
+}
+
+/// The units of an amount, position or inventory in `amount`'s currency, to
+/// compare them with `amount`, as in `HAVING SUM(position) > 1000 USD`.
+///
+/// An inventory holds no units of a currency it does not contain, while an
+/// amount or position in another currency is a
+/// [`QueryError::CurrencyMismatch`].
+fn units_to_compare(value: &Value, amount: &Amount) -> Result<Decimal, QueryError> {
+    let units = match value {
+        Value::Inventory(inventory) => return Ok(inventory.units(amount.currency.as_str())),
+        Value::Position(position) => &position.units,
+        Value::Amount(units) => units,
+        _ => return Err(QueryError::Type("cannot compare values".to_string())),
+    };
+    if units.currency != amount.currency {
+        return Err(QueryError::CurrencyMismatch {
+            op: "compare",
+            left: units.to_string(),
+            right: amount.to_string(),
+        });
+    }
+    Ok(units.number)
+}
+
+fn amount_type_error(verb: &str) -> QueryError {
+    QueryError::Type(format!(
+        "cannot {verb} an amount and a non-amount; use NUMBER() to work with the number"
+    ))
+}
+
+/// Shift a date by `n` calendar units (`day`, `week`, `month`, `quarter`,
+/// `year`). Month-based shifts clamp to the end of shorter months.
+fn shift_date(date: NaiveDate, n: i64, unit: &str) -> Result<NaiveDate, QueryError> {
+    let months = |count: i64| {
+        let count = u32::try_from(count.unsigned_abs()).ok()?;
+        if n < 0 {
+            date.checked_sub_months(chrono::Months::new(count))
+        } else {
+            date.checked_add_months(chrono::Months::new(count))
+        }
+    };
+    let days = |count: i64| {
+        let count = chrono::Days::new(count.unsigned_abs());
+        if n < 0 {
+            date.checked_sub_days(count)
+        } else {
+            date.checked_add_days(count)
+        }
+    };
+    let shifted = match unit.trim_end_matches('s') {
+        "day" => days(n),
+        "week" => n.checked_mul(7).and_then(days),
+        "month" => months(n),
+        "quarter" => n.checked_mul(3).and_then(months),
+        "year" => n.checked_mul(12).and_then(months),
+        _ => {
+            return Err(QueryError::Evaluation(format!(
+                "unknown date unit '{unit}'"
+            )));
+        }
+    };
+    shifted.ok_or_else(|| QueryError::Evaluation("date out of range".to_string()))
+}
+
+/// The first day of the calendar `unit` (`day`, `week`, `month`, `quarter`,
+/// `year`) containing a date. Weeks start on Monday.
+fn truncate_date(date: NaiveDate, unit: &str) -> Result<NaiveDate, QueryError> {
+    let truncated = match unit.trim_end_matches('s') {
+        "day" => Some(date),
+        "week" => date.checked_sub_days(chrono::Days::new(
+            date.weekday().num_days_from_monday().into(),
+        )),
+        "month" => date.with_day(1),
+        "quarter" => NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1),
+        "year" => NaiveDate::from_ymd_opt(date.year(), 1, 1),
+        _ => {
+            return Err(QueryError::Evaluation(format!(
+                "unknown date unit '{unit}'"
+            )));
+        }
+    };
+    truncated.ok_or_else(|| QueryError::Evaluation("date out of range".to_string()))
 }
 
 /// Context for a single posting being evaluated.
@@ -158,10 +578,15 @@ impl QueryResult {
 pub struct PostingContext<'a> {
     /// The transaction this posting belongs to.
     pub transaction: &'a Transaction,
-    /// The posting index within the transaction.
+    /// The posting index within the transaction; past the last posting
+    /// for rows read `FROM #transactions`.
     pub posting_index: usize,
     /// Running balance after this posting (optional).
     pub balance: Option<Inventory>,
+    /// Lots this posting reduced, if it reduced a position held at cost.
+    pub booking: Option<BookingResult>,
+    /// Index of the ledger the transaction came from.
+    pub ledger: usize,
 }
 
 /// Context for window function evaluation.
@@ -175,10 +600,33 @@ pub struct WindowContext {
     pub dense_rank: usize,
 }
 
+/// Inputs of a LAG or LEAD window function evaluated for one aggregated row.
+#[derive(Debug)]
+struct OffsetWindowRow {
+    /// Key of the row's partition.
+    partition: String,
+    /// Values of the window's ORDER BY expressions.
+    order: Vec<Value>,
+    /// Value other rows take from this one.
+    value: Value,
+    /// How many rows back (LAG) or ahead (LEAD) to look.
+    offset: usize,
+    /// Value to use when there is no row at the offset.
+    default: Value,
+}
+
+/// A set of directives queried by an [`Executor`].
+struct Source<'a> {
+    /// Ledger name, reported by the `ledger` column.
+    name: Option<String>,
+    /// The ledger's directives.
+    directives: &'a [Directive],
+}
+
 /// Query executor.
 pub struct Executor<'a> {
-    /// All directives to query over.
-    directives: &'a [Directive],
+    /// Ledgers to query over.
+    sources: Vec<Source<'a>>,
     /// Account balances (built up during query).
     balances: HashMap<InternedStr, Inventory>,
     /// Price database for `VALUE()` conversions.
@@ -187,18 +635,83 @@ pub struct Executor<'a> {
     target_currency: Option<String>,
     /// Cache for compiled regex patterns.
     regex_cache: RefCell<HashMap<String, Option<Regex>>>,
+    /// User-defined functions by lowercased name.
+    functions: HashMap<String, Box<dyn ScalarFunction + 'a>>,
+    /// Results of user-defined function calls in the current query.
+    function_results: RefCell<HashMap<FunctionCallKey, Value>>,
+    /// Booking method for accounts whose `open` names none.
+    booking_method: BookingMethod,
 }
 
 impl<'a> Executor<'a> {
     /// Create a new executor with the given directives.
     pub fn new(directives: &'a [Directive]) -> Self {
-        let price_db = crate::price::PriceDatabase::from_directives(directives);
-        Self {
+        Self::from_sources(vec![Source {
+            name: None,
             directives,
+        }])
+    }
+
+    /// Create an executor over several named ledgers.
+    ///
+    /// Directives of all ledgers are visited together in date order, so
+    /// balances and aggregates are consolidated across ledgers; the `ledger`
+    /// column tells which ledger a posting came from.
+    ///
+    /// ```
+    /// use rustledger_core::{Directive, NaiveDate, Open};
+    /// use rustledger_query::{Executor, parse};
+    ///
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    /// let personal = vec![Directive::Open(Open::new(date, "Assets:Cash"))];
+    /// let business = vec![Directive::Open(Open::new(date, "Assets:Bank"))];
+    ///
+    /// let mut executor = Executor::with_sources([("personal", &personal), ("business", &business)]);
+    /// let query = parse("SELECT ledger, sum(position) GROUP BY ledger").unwrap();
+    /// let result = executor.execute(&query).unwrap();
+    /// assert!(result.is_empty());
+    /// ```
+    pub fn with_sources<I, S, D>(sources: I) -> Self
+    where
+        I: IntoIterator<Item = (S, &'a D)>,
+        S: Into<String>,
+        D: AsRef<[Directive]> + ?Sized + 'a,
+    {
+        Self::from_sources(
+            sources
+                .into_iter()
+                .map(|(name, directives)| Source {
+                    name: Some(name.into()),
+                    directives: directives.as_ref(),
+                })
+                .collect(),
+        )
+    }
+
+    fn from_sources(sources: Vec<Source<'a>>) -> Self {
+        let price_db = crate::price::PriceDatabase::from_directives(
+            sources.iter().flat_map(|source| source.directives),
+        );
+        Self {
+            sources,
             balances: HashMap::new(),
             price_db,
             target_currency: None,
             regex_cache: RefCell::new(HashMap::new()),
+            functions: HashMap::new(),
+            function_results: RefCell::new(HashMap::new()),
+            booking_method: BookingMethod::default(),
+        }
+    }
+
+    /// Directives of all ledgers merged in date order, with their ledger index.
+    fn directives(&self) -> MergedDirectives<'a> {
+        MergedDirectives {
+            sources: self
+                .sources
+                .iter()
+                .map(|source| source.directives.iter().peekable())
+                .collect(),
         }
     }
 
@@ -227,6 +740,27 @@ impl<'a> Executor<'a> {
         self.target_currency = Some(currency.into());
     }
 
+    /// Set the booking method used to match lots for `REALIZED_GAIN()` and
+    /// `HOLDING_PERIOD()` in accounts whose `open` names none, e.g. from
+    /// the ledger's `option "booking_method"`.
+    pub const fn set_booking_method(&mut self, method: BookingMethod) {
+        self.booking_method = method;
+    }
+
+    /// Register a user-defined scalar function under `name`.
+    ///
+    /// Names are case-insensitive and may be qualified with a dot, e.g.
+    /// `myplugin.category`. Built-in functions take precedence over
+    /// registered ones of the same name. See [`crate::functions`].
+    pub fn register_function(
+        &mut self,
+        name: impl Into<String>,
+        function: impl ScalarFunction + 'a,
+    ) {
+        self.functions
+            .insert(name.into().to_lowercase(), Box::new(function));
+    }
+
     /// Execute a query and return the results.
     ///
     /// # Errors
@@ -240,12 +774,74 @@ impl<'a> Executor<'a> {
     /// - [`QueryError::Aggregation`] - Error in aggregate function (SUM, COUNT, etc.)
     /// - [`QueryError::Evaluation`] - General expression evaluation error
     pub fn execute(&mut self, query: &Query) -> Result<QueryResult, QueryError> {
+        self.function_results.borrow_mut().clear();
         match query {
             Query::Select(select) => self.execute_select(select),
             Query::Journal(journal) => self.execute_journal(journal),
             Query::Balances(balances) => self.execute_balances(balances),
             Query::Print(print) => self.execute_print(print),
+            Query::Export(export) => self.execute_export(export),
+        }
+    }
+
+    /// Execute a query and return an iterator over its rows.
+    ///
+    /// Plain `SELECT` queries (no aggregates, window functions, `PIVOT BY`,
+    /// `ORDER BY` or subquery) are evaluated lazily, one posting at a time,
+    /// so reading the first rows of a huge result does not build the rest.
+    /// Other queries are executed in full and their rows handed out from
+    /// the result. Use [`QueryRows::with_row_limit`] to stop after a page.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QueryError`] if the query cannot be set up; errors found
+    /// while evaluating rows are returned by the iterator, which then ends.
+    /// See [`Executor::execute`] for the possible errors.
+    pub fn execute_iter<'e>(
+        &'e mut self,
+        query: &'e Query,
+    ) -> Result<QueryRows<'e, 'a>, QueryError> {
+        if let Query::Select(select) = query {
+            if Self::is_streamable(select) {
+                let columns = self.resolve_column_names(&select.targets)?;
+                let executor: &'e Self = self;
+                return Ok(QueryRows::new(
+                    columns,
+                    RowSource::Lazy {
+                        executor,
+                        query: select,
+                        cursor: Box::new(PostingCursor::new(
+                            executor.directives(),
+                            executor.booking_method,
+                        )),
+                        seen: HashSet::new(),
+                        remaining: select.limit.map(|limit| limit as usize),
+                    },
+                ));
+            }
         }
+
+        let result = self.execute(query)?;
+        Ok(QueryRows::new(
+            result.columns,
+            RowSource::Materialized(result.rows.into_iter()),
+        ))
+    }
+
+    /// Whether a SELECT query can be evaluated one posting at a time.
+    fn is_streamable(query: &SelectQuery) -> bool {
+        query
+            .from
+            .as_ref()
+            .and_then(|from| from.subquery.as_ref())
+            .is_none()
+            && !query
+                .targets
+                .iter()
+                .any(|t| Self::is_aggregate_expr(&t.expr))
+            && !Self::has_window_functions(&query.targets)
+            && query.pivot_by.is_none()
+            && query.order_by.is_none()
     }
 
     /// Execute a SELECT query.
@@ -263,6 +859,12 @@ impl<'a> Executor<'a> {
 
         // Collect matching postings
         let postings = self.collect_postings(query.from.as_ref(), query.where_clause.as_ref())?;
+        let evaluated = query
+            .targets
+            .iter()
+            .map(|t| &t.expr)
+            .chain(query.group_by.iter().flatten());
+        self.prefetch_functions(evaluated, &postings)?;
 
         // Check if this is an aggregate query
         let is_aggregate = query
@@ -272,9 +874,18 @@ impl<'a> Executor<'a> {
 
         if is_aggregate {
             // Group and aggregate
-            let grouped = self.group_postings(&postings, query.group_by.as_ref())?;
-            for (_, group) in grouped {
-                let row = self.evaluate_aggregate_row(&query.targets, &group)?;
+            let group_by = query
+                .group_by
+                .as_ref()
+                .map(|exprs| Self::resolve_group_by(exprs, &query.targets));
+            let grouped = self.group_postings(&postings, group_by.as_ref())?;
+            let has_windows = Self::has_window_functions(&query.targets);
+            let mut window_inputs = Vec::new();
+            for (key, group) in grouped {
+                let mut row = self.evaluate_aggregate_row(&query.targets, &group)?;
+                if let Some(group_exprs) = &group_by {
+                    Self::apply_rollup_keys(&mut row, &query.targets, group_exprs, &key);
+                }
 
                 // Apply HAVING filter on aggregated row
                 if let Some(having_expr) = &query.having {
@@ -289,8 +900,14 @@ impl<'a> Executor<'a> {
                     }
                 }
 
+                if has_windows {
+                    window_inputs.push(self.evaluate_offset_windows(&query.targets, &group)?);
+                }
                 result.add_row(row);
             }
+            if has_windows {
+                Self::apply_offset_windows(&mut result.rows, &query.targets, &window_inputs);
+            }
         } else {
             // Check if query has window functions
             let has_windows = Self::has_window_functions(&query.targets);
@@ -531,12 +1148,12 @@ impl<'a> Executor<'a> {
         let mut result = QueryResult::new(columns);
 
         // Filter transactions that touch the account
-        for directive in self.directives {
+        for (ledger, directive) in self.directives() {
             if let Directive::Transaction(txn) = directive {
                 // Apply FROM clause filter if present
                 if let Some(from) = &query.from {
                     if let Some(filter) = &from.filter {
-                        if !self.evaluate_from_filter(filter, txn)? {
+                        if !self.evaluate_from_filter(filter, txn, ledger)? {
                             continue;
                         }
                     }
@@ -674,13 +1291,13 @@ impl<'a> Executor<'a> {
         let columns = vec!["directive".to_string()];
         let mut result = QueryResult::new(columns);
 
-        for directive in self.directives {
+        for (ledger, directive) in self.directives() {
             // Apply FROM clause filter if present
             if let Some(from) = &query.from {
                 if let Some(filter) = &from.filter {
                     // PRINT filters at transaction level
                     if let Directive::Transaction(txn) = directive {
-                        if !self.evaluate_from_filter(filter, txn)? {
+                        if !self.evaluate_from_filter(filter, txn, ledger)? {
                             continue;
                         }
                     }
@@ -695,6 +1312,29 @@ impl<'a> Executor<'a> {
         Ok(result)
     }
 
+    /// Execute an EXPORT query.
+    fn execute_export(&self, query: &ExportQuery) -> Result<QueryResult, QueryError> {
+        let columns = vec!["entry".to_string()];
+        let mut result = QueryResult::new(columns);
+
+        for (ledger, directive) in self.directives() {
+            // EXPORT filters at transaction level, like PRINT
+            if let (Some(from), Directive::Transaction(txn)) = (&query.from, directive) {
+                if let Some(filter) = &from.filter {
+                    if !self.evaluate_from_filter(filter, txn, ledger)? {
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(entry) = crate::export::export_directive(directive, query.format) {
+                result.add_row(vec![Value::String(entry)]);
+            }
+        }
+
+        Ok(result)
+    }
+
     /// Format a directive for PRINT output.
     fn format_directive(&self, directive: &Directive) -> String {
         match directive {
@@ -777,12 +1417,12 @@ impl<'a> Executor<'a> {
 
     /// Build up account balances with optional FROM filtering.
     fn build_balances_with_filter(&mut self, from: Option<&FromClause>) -> Result<(), QueryError> {
-        for directive in self.directives {
+        for (ledger, directive) in self.directives() {
             if let Directive::Transaction(txn) = directive {
                 // Apply FROM filter if present
                 if let Some(from_clause) = from {
                     if let Some(filter) = &from_clause.filter {
-                        if !self.evaluate_from_filter(filter, txn)? {
+                        if !self.evaluate_from_filter(filter, txn, ledger)? {
                             continue;
                         }
                     }
@@ -816,72 +1456,20 @@ impl<'a> Executor<'a> {
         where_clause: Option<&Expr>,
     ) -> Result<Vec<PostingContext<'a>>, QueryError> {
         let mut postings = Vec::new();
-        // Track running balance per account
-        let mut running_balances: HashMap<InternedStr, Inventory> = HashMap::new();
-
-        for directive in self.directives {
-            if let Directive::Transaction(txn) = directive {
-                // Check FROM clause (transaction-level filter)
-                if let Some(from) = from {
-                    // Apply date filters
-                    if let Some(open_date) = from.open_on {
-                        if txn.date < open_date {
-                            // Update balances but don't include in results
-                            for posting in &txn.postings {
-                                if let Some(units) = posting.amount() {
-                                    let balance = running_balances
-                                        .entry(posting.account.clone())
-                                        .or_default();
-                                    balance.add(Position::simple(units.clone()));
-                                }
-                            }
-                            continue;
-                        }
-                    }
-                    if let Some(close_date) = from.close_on {
-                        if txn.date > close_date {
-                            continue;
-                        }
-                    }
-                    // Apply filter expression
-                    if let Some(filter) = &from.filter {
-                        if !self.evaluate_from_filter(filter, txn)? {
-                            continue;
-                        }
-                    }
-                }
-
-                // Add postings with running balance
-                for (i, posting) in txn.postings.iter().enumerate() {
-                    // Update running balance for this account
-                    if let Some(units) = posting.amount() {
-                        let balance = running_balances.entry(posting.account.clone()).or_default();
-                        balance.add(Position::simple(units.clone()));
-                    }
-
-                    let ctx = PostingContext {
-                        transaction: txn,
-                        posting_index: i,
-                        balance: running_balances.get(&posting.account).cloned(),
-                    };
-
-                    // Check WHERE clause (posting-level filter)
-                    if let Some(where_expr) = where_clause {
-                        if self.evaluate_predicate(where_expr, &ctx)? {
-                            postings.push(ctx);
-                        }
-                    } else {
-                        postings.push(ctx);
-                    }
-                }
-            }
+        let mut cursor = PostingCursor::new(self.directives(), self.booking_method);
+        while let Some(ctx) = cursor.next(self, from, where_clause)? {
+            postings.push(ctx);
         }
-
         Ok(postings)
     }
 
     /// Evaluate a FROM filter on a transaction.
-    fn evaluate_from_filter(&self, filter: &Expr, txn: &Transaction) -> Result<bool, QueryError> {
+    fn evaluate_from_filter(
+        &self,
+        filter: &Expr,
+        txn: &Transaction,
+        ledger: usize,
+    ) -> Result<bool, QueryError> {
         // Handle special FROM predicates
         match filter {
             Expr::Function(func) => {
@@ -915,6 +1503,8 @@ impl<'a> Executor<'a> {
                         transaction: txn,
                         posting_index: 0,
                         balance: None,
+                        booking: None,
+                        ledger,
                     };
                     self.evaluate_predicate(filter, &dummy_ctx)
                 }
@@ -966,6 +1556,8 @@ impl<'a> Executor<'a> {
                             transaction: txn,
                             posting_index: 0,
                             balance: None,
+                            booking: None,
+                            ledger,
                         };
                         self.evaluate_predicate(filter, &dummy_ctx)
                     }
@@ -977,6 +1569,8 @@ impl<'a> Executor<'a> {
                     transaction: txn,
                     posting_index: 0,
                     balance: None,
+                    booking: None,
+                    ledger,
                 };
                 self.evaluate_predicate(filter, &dummy_ctx)
             }
@@ -1015,11 +1609,8 @@ impl<'a> Executor<'a> {
 
     /// Evaluate a column reference.
     fn evaluate_column(&self, name: &str, ctx: &PostingContext) -> Result<Value, QueryError> {
-        let posting = &ctx.transaction.postings[ctx.posting_index];
-
         match name {
             "date" => Ok(Value::Date(ctx.transaction.date)),
-            "account" => Ok(Value::String(posting.account.to_string())),
             "narration" => Ok(Value::String(ctx.transaction.narration.to_string())),
             "payee" => Ok(ctx
                 .transaction
@@ -1027,6 +1618,14 @@ impl<'a> Executor<'a> {
                 .as_ref()
                 .map_or(Value::Null, |p| Value::String(p.to_string()))),
             "flag" => Ok(Value::String(ctx.transaction.flag.to_string())),
+            "ledger" => Ok(self
+                .sources
+                .get(ctx.ledger)
+                .and_then(|source| source.name.as_ref())
+                .map_or(Value::Null, |name| Value::String(name.clone()))),
+            "time" => Ok(ctx.transaction.time().map_or(Value::Null, |time| {
+                Value::String(time.format("%H:%M:%S").to_string())
+            })),
             "tags" => Ok(Value::StringSet(
                 ctx.transaction
                     .tags
@@ -1041,7 +1640,43 @@ impl<'a> Executor<'a> {
                     .map(ToString::to_string)
                     .collect(),
             )),
-            "position" | "units" => Ok(posting
+            "year" => Ok(Value::Integer(ctx.transaction.date.year().into())),
+            "month" => Ok(Value::Integer(ctx.transaction.date.month().into())),
+            "day" => Ok(Value::Integer(ctx.transaction.date.day().into())),
+            "id" => Ok(Value::String(directive_id(&Directive::Transaction(
+                ctx.transaction.clone(),
+            )))),
+            "accounts" => {
+                let mut accounts: Vec<String> = ctx
+                    .transaction
+                    .postings
+                    .iter()
+                    .map(|p| p.account.to_string())
+                    .collect();
+                accounts.sort();
+                accounts.dedup();
+                Ok(Value::StringSet(accounts))
+            }
+            _ => match ctx.transaction.postings.get(ctx.posting_index) {
+                Some(posting) => self.evaluate_posting_column(name, posting, ctx),
+                None if POSTING_COLUMNS.contains(&name) => Err(QueryError::Evaluation(format!(
+                    "column {name} is per posting and not available FROM #transactions"
+                ))),
+                None => Err(QueryError::UnknownColumn(name.to_string())),
+            },
+        }
+    }
+
+    /// Evaluate a column of the posting being visited.
+    fn evaluate_posting_column(
+        &self,
+        name: &str,
+        posting: &Posting,
+        ctx: &PostingContext,
+    ) -> Result<Value, QueryError> {
+        match name {
+            "account" => Ok(Value::String(posting.account.to_string())),
+            "position" | "posting" | "units" => Ok(posting
                 .amount()
                 .map_or(Value::Null, |u| Value::Amount(u.clone()))),
             "cost" => {
@@ -1085,13 +1720,47 @@ impl<'a> Executor<'a> {
                     Ok(Value::Null)
                 }
             }
-            "year" => Ok(Value::Integer(ctx.transaction.date.year().into())),
-            "month" => Ok(Value::Integer(ctx.transaction.date.month().into())),
-            "day" => Ok(Value::Integer(ctx.transaction.date.day().into())),
+            "lots" => {
+                // Lots matched by this posting's reduction, units taken from each
+                Ok(ctx.booking.as_ref().map_or(Value::Null, |booking| {
+                    Value::Inventory(
+                        booking
+                            .lots
+                            .iter()
+                            .map(|lot| match &lot.cost {
+                                Some(cost) => Position::with_cost(lot.units.clone(), cost.clone()),
+                                None => Position::simple(lot.units.clone()),
+                            })
+                            .collect(),
+                    )
+                }))
+            }
+            "gain" => {
+                // Realized gain of this posting's reduction at its price
+                let gain = ctx
+                    .booking
+                    .as_ref()
+                    .and_then(|booking| booking.realized_gain(&Self::posting_unit_price(posting)?));
+                Ok(gain.map_or(Value::Null, Value::Amount))
+            }
             _ => Err(QueryError::UnknownColumn(name.to_string())),
         }
     }
 
+    /// Per-unit price of a posting, from a `@` or `@@` annotation.
+    fn posting_unit_price(posting: &Posting) -> Option<Amount> {
+        let price = posting.price.as_ref()?;
+        let amount = price.amount()?;
+        if price.is_unit() {
+            return Some(amount.clone());
+        }
+        let units = posting.amount()?;
+        Some(Amount::new(
+            amount.number / units.number.abs(),
+            amount.currency.clone(),
+        ))
+    }
+
     /// Evaluate a literal.
     fn evaluate_literal(&self, lit: &Literal) -> Result<Value, QueryError> {
         Ok(match lit {
@@ -1099,6 +1768,7 @@ impl<'a> Executor<'a> {
             Literal::Number(n) => Value::Number(*n),
             Literal::Integer(i) => Value::Integer(*i),
             Literal::Date(d) => Value::Date(*d),
+            Literal::Amount(a) => Value::Amount(a.clone()),
             Literal::Boolean(b) => Value::Boolean(*b),
             Literal::Null => Value::Null,
         })
@@ -1115,9 +1785,8 @@ impl<'a> Executor<'a> {
         let name = func.name.to_uppercase();
         match name.as_str() {
             // Date functions
-            "YEAR" | "MONTH" | "DAY" | "WEEKDAY" | "QUARTER" | "YMONTH" | "TODAY" => {
-                self.eval_date_function(&name, func, ctx)
-            }
+            "YEAR" | "MONTH" | "DAY" | "WEEKDAY" | "QUARTER" | "YMONTH" | "TODAY"
+            | "DATE_TRUNC" | "DATE_ADD" => self.eval_date_function(&name, func, ctx),
             // String functions
             "LENGTH" | "UPPER" | "LOWER" | "SUBSTR" | "SUBSTRING" | "TRIM" | "STARTSWITH"
             | "ENDSWITH" => self.eval_string_function(&name, func, ctx),
@@ -1131,30 +1800,181 @@ impl<'a> Executor<'a> {
             "NUMBER" | "CURRENCY" | "GETITEM" | "GET" | "UNITS" | "COST" | "WEIGHT" | "VALUE" => {
                 self.eval_position_function(&name, func, ctx)
             }
+            // Currency conversion
+            "CONVERT" => self.eval_convert(func, ctx),
+            // Portfolio return functions
+            "TWRR" | "XIRR" => self.eval_return_function(&name, func, ctx),
+            // Capital gains functions
+            "REALIZED_GAIN" | "HOLDING_PERIOD" => self.eval_gain_function(&name, func, ctx),
             // Utility functions
             "COALESCE" => self.eval_coalesce(func, ctx),
             // Aggregate functions return Null when evaluated on a single row
             // They're handled specially in aggregate evaluation
             "SUM" | "COUNT" | "MIN" | "MAX" | "FIRST" | "LAST" | "AVG" => Ok(Value::Null),
+            _ if self.functions.contains_key(&func.name.to_lowercase()) => {
+                let args = func
+                    .args
+                    .iter()
+                    .map(|arg| self.evaluate_expr(arg, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call_user_function(&func.name, args)
+            }
             _ => Err(QueryError::UnknownFunction(func.name.clone())),
         }
     }
 
-    /// Evaluate date functions: `YEAR`, `MONTH`, `DAY`, `WEEKDAY`, `QUARTER`, `YMONTH`, `TODAY`.
-    fn eval_date_function(
+    /// Call a user-defined function, reusing the result of an earlier call
+    /// with the same arguments.
+    fn call_user_function(&self, name: &str, args: Vec<Value>) -> Result<Value, QueryError> {
+        let key = FunctionCallKey {
+            function: name.to_lowercase(),
+            args,
+        };
+        if let Some(value) = self.function_results.borrow().get(&key) {
+            return Ok(value.clone());
+        }
+        let function = self
+            .functions
+            .get(&key.function)
+            .ok_or_else(|| QueryError::UnknownFunction(name.to_string()))?;
+        let value = function.call(&key.args)?;
+        self.function_results
+            .borrow_mut()
+            .insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Call the user-defined functions in `exprs` for all postings up front,
+    /// one batch per function, so rows are later evaluated from the cache.
+    ///
+    /// Only calls whose arguments are plain row expressions are batched;
+    /// calls whose arguments fail to evaluate are left to row-by-row
+    /// evaluation, which reports the error.
+    fn prefetch_functions<'e>(
         &self,
-        name: &str,
-        func: &FunctionCall,
-        ctx: &PostingContext,
-    ) -> Result<Value, QueryError> {
-        if name == "TODAY" {
-            if !func.args.is_empty() {
-                return Err(QueryError::InvalidArguments(
-                    "TODAY".to_string(),
-                    "expected 0 arguments".to_string(),
-                ));
-            }
-            return Ok(Value::Date(chrono::Local::now().date_naive()));
+        exprs: impl Iterator<Item = &'e Expr>,
+        postings: &[PostingContext],
+    ) -> Result<(), QueryError> {
+        if self.functions.is_empty() {
+            return Ok(());
+        }
+        let mut calls = Vec::new();
+        for expr in exprs {
+            self.collect_batchable_calls(expr, &mut calls);
+        }
+
+        for call in calls {
+            let function_name = call.name.to_lowercase();
+            let mut pending = HashSet::new();
+            let cached = self.function_results.borrow();
+            for ctx in postings {
+                let Ok(args) = call
+                    .args
+                    .iter()
+                    .map(|arg| self.evaluate_expr(arg, ctx))
+                    .collect::<Result<Vec<_>, _>>()
+                else {
+                    continue;
+                };
+                let key = FunctionCallKey {
+                    function: function_name.clone(),
+                    args,
+                };
+                if !cached.contains_key(&key) {
+                    pending.insert(key);
+                }
+            }
+            drop(cached);
+            if pending.is_empty() {
+                continue;
+            }
+
+            let pending: Vec<FunctionCallKey> = pending.into_iter().collect();
+            let rows: Vec<Vec<Value>> = pending.iter().map(|key| key.args.clone()).collect();
+            let values = self.functions[&function_name].call_batch(&rows)?;
+            if values.len() != rows.len() {
+                return Err(QueryError::Evaluation(format!(
+                    "{} returned {} values for {} rows",
+                    call.name,
+                    values.len(),
+                    rows.len()
+                )));
+            }
+            self.function_results
+                .borrow_mut()
+                .extend(pending.into_iter().zip(values));
+        }
+        Ok(())
+    }
+
+    /// Collect the user-defined function calls in `expr` whose arguments can
+    /// be evaluated on a single posting.
+    fn collect_batchable_calls<'e>(&self, expr: &'e Expr, calls: &mut Vec<&'e FunctionCall>) {
+        match expr {
+            Expr::Function(func) => {
+                let batchable = self.functions.contains_key(&func.name.to_lowercase())
+                    && !func.args.iter().any(|arg| {
+                        Self::is_aggregate_expr(arg) || self.contains_user_function(arg)
+                    });
+                if batchable {
+                    calls.push(func);
+                } else {
+                    for arg in &func.args {
+                        self.collect_batchable_calls(arg, calls);
+                    }
+                }
+            }
+            Expr::BinaryOp(op) => {
+                self.collect_batchable_calls(&op.left, calls);
+                self.collect_batchable_calls(&op.right, calls);
+            }
+            Expr::UnaryOp(op) => self.collect_batchable_calls(&op.operand, calls),
+            Expr::Paren(inner) => self.collect_batchable_calls(inner, calls),
+            _ => {}
+        }
+    }
+
+    /// Whether `expr` calls a user-defined function.
+    fn contains_user_function(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Function(func) => {
+                self.functions.contains_key(&func.name.to_lowercase())
+                    || func.args.iter().any(|arg| self.contains_user_function(arg))
+            }
+            Expr::Window(func) => func.args.iter().any(|arg| self.contains_user_function(arg)),
+            Expr::BinaryOp(op) => {
+                self.contains_user_function(&op.left) || self.contains_user_function(&op.right)
+            }
+            Expr::UnaryOp(op) => self.contains_user_function(&op.operand),
+            Expr::Paren(inner) => self.contains_user_function(inner),
+            _ => false,
+        }
+    }
+
+    /// Evaluate date functions: `YEAR`, `MONTH`, `DAY`, `WEEKDAY`, `QUARTER`, `YMONTH`, `TODAY`,
+    /// `DATE_TRUNC`, `DATE_ADD`.
+    fn eval_date_function(
+        &self,
+        name: &str,
+        func: &FunctionCall,
+        ctx: &PostingContext,
+    ) -> Result<Value, QueryError> {
+        if name == "DATE_TRUNC" || name == "DATE_ADD" {
+            let args = func
+                .args
+                .iter()
+                .map(|arg| self.evaluate_expr(arg, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Self::eval_calendar_function(name, &args);
+        }
+        if name == "TODAY" {
+            if !func.args.is_empty() {
+                return Err(QueryError::InvalidArguments(
+                    "TODAY".to_string(),
+                    "expected 0 arguments".to_string(),
+                ));
+            }
+            return Ok(Value::Date(chrono::Local::now().date_naive()));
         }
 
         // All other date functions expect exactly 1 argument
@@ -1189,6 +2009,37 @@ impl<'a> Executor<'a> {
         }
     }
 
+    /// Evaluate `DATE_TRUNC(unit, date)`, the start of the calendar period
+    /// containing a date, and `DATE_ADD(date, n[, unit])`, a date shifted
+    /// by `n` units (days by default).
+    fn eval_calendar_function(name: &str, args: &[Value]) -> Result<Value, QueryError> {
+        let unit = |value: &Value| match value {
+            Value::String(unit) => Ok(unit.to_lowercase()),
+            _ => Err(QueryError::Type(format!("{name} expects a unit string"))),
+        };
+        let date = |value: &Value| match value {
+            Value::Date(date) => Ok(*date),
+            _ => Err(QueryError::Type(format!("{name} expects a date"))),
+        };
+
+        let result = if name == "DATE_TRUNC" {
+            Self::require_args_count(name, args, 2)?;
+            truncate_date(date(&args[1])?, &unit(&args[0])?)?
+        } else {
+            if args.len() != 2 && args.len() != 3 {
+                return Err(QueryError::InvalidArguments(
+                    name.to_string(),
+                    "expected 2 or 3 arguments".to_string(),
+                ));
+            }
+            let n = whole_number(&args[1])
+                .ok_or_else(|| QueryError::Type(format!("{name} expects an integer count")))?;
+            let unit = args.get(2).map_or_else(|| Ok("day".to_string()), unit)?;
+            shift_date(date(&args[0])?, n, &unit)?
+        };
+        Ok(Value::Date(result))
+    }
+
     /// Evaluate string functions: `LENGTH`, `UPPER`, `LOWER`, `SUBSTR`, `TRIM`, `STARTSWITH`, `ENDSWITH`.
     fn eval_string_function(
         &self,
@@ -1508,6 +2359,7 @@ impl<'a> Executor<'a> {
                     Value::Position(p) => Ok(Value::Number(p.units.number)),
                     Value::Number(n) => Ok(Value::Number(n)),
                     Value::Integer(i) => Ok(Value::Number(Decimal::from(i))),
+                    Value::Null => Ok(Value::Null),
                     _ => Err(QueryError::Type(
                         "NUMBER expects an amount or position".to_string(),
                     )),
@@ -1519,6 +2371,7 @@ impl<'a> Executor<'a> {
                 match val {
                     Value::Amount(a) => Ok(Value::String(a.currency.to_string())),
                     Value::Position(p) => Ok(Value::String(p.units.currency.to_string())),
+                    Value::Null => Ok(Value::Null),
                     _ => Err(QueryError::Type(
                         "CURRENCY expects an amount or position".to_string(),
                     )),
@@ -1707,6 +2560,222 @@ impl<'a> Executor<'a> {
         }
     }
 
+    /// Evaluate `CONVERT(value, currency [, date])`.
+    fn eval_convert(&self, func: &FunctionCall, ctx: &PostingContext) -> Result<Value, QueryError> {
+        let args = func
+            .args
+            .iter()
+            .map(|arg| self.evaluate_expr(arg, ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.convert(&args)
+    }
+
+    /// Convert an amount, position or inventory to a currency.
+    ///
+    /// Prices are looked up as of the given date, falling back to the
+    /// nearest earlier price; without a date the latest price is used.
+    /// Returns `NULL` when a price is missing, so conversions at a date
+    /// before the first price do not silently keep the original currency.
+    fn convert(&self, args: &[Value]) -> Result<Value, QueryError> {
+        if args.len() < 2 || args.len() > 3 {
+            return Err(QueryError::InvalidArguments(
+                "CONVERT".to_string(),
+                "expected 2-3 arguments".to_string(),
+            ));
+        }
+
+        let Value::String(currency) = &args[1] else {
+            return Err(QueryError::Type(
+                "CONVERT second argument must be a currency string".to_string(),
+            ));
+        };
+        let date = match args.get(2) {
+            None | Some(Value::Null) => None,
+            Some(Value::Date(date)) => Some(*date),
+            Some(_) => {
+                return Err(QueryError::Type(
+                    "CONVERT third argument must be a date".to_string(),
+                ));
+            }
+        };
+
+        let convert = |amount: &Amount| match date {
+            Some(date) => self.price_db.convert(amount, currency, date),
+            None => self.price_db.convert_latest(amount, currency),
+        };
+
+        match &args[0] {
+            Value::Amount(a) => Ok(convert(a).map_or(Value::Null, Value::Amount)),
+            Value::Position(p) => Ok(convert(&p.units).map_or(Value::Null, Value::Amount)),
+            Value::Inventory(inv) => {
+                let mut total = Decimal::ZERO;
+                for pos in inv.positions() {
+                    let Some(converted) = convert(&pos.units) else {
+                        return Ok(Value::Null);
+                    };
+                    total += converted.number;
+                }
+                Ok(Value::Amount(Amount::new(total, currency.as_str())))
+            }
+            Value::Null => Ok(Value::Null),
+            _ => Err(QueryError::Type(
+                "CONVERT expects an amount, position or inventory".to_string(),
+            )),
+        }
+    }
+
+    /// Evaluate capital gains functions: `REALIZED_GAIN(posting [, min_days
+    /// [, max_days]])` and `HOLDING_PERIOD(posting)`.
+    ///
+    /// Both read the lots the row's posting reduced, as matched by the
+    /// booking engine, and are NULL for postings that reduced nothing.
+    /// `HOLDING_PERIOD` is the number of days the most recently acquired
+    /// matched lot was held. `REALIZED_GAIN` sums the gain at the posting's
+    /// price over the matched lots, optionally only those held at least
+    /// `min_days` and less than `max_days`, so short-term and long-term gains
+    /// can be reported separately.
+    fn eval_gain_function(
+        &self,
+        name: &str,
+        func: &FunctionCall,
+        ctx: &PostingContext,
+    ) -> Result<Value, QueryError> {
+        let max_args = if name == "REALIZED_GAIN" { 3 } else { 1 };
+        if func.args.is_empty() || func.args.len() > max_args {
+            return Err(QueryError::InvalidArguments(
+                name.to_string(),
+                format!("expected 1-{max_args} arguments"),
+            ));
+        }
+        if !matches!(&func.args[0], Expr::Column(c) if c == "posting" || c == "position") {
+            return Err(QueryError::InvalidArguments(
+                name.to_string(),
+                "first argument must be the row's posting".to_string(),
+            ));
+        }
+        // Fails FROM #transactions, where rows have no posting
+        self.evaluate_expr(&func.args[0], ctx)?;
+
+        let Some(booking) = &ctx.booking else {
+            return Ok(Value::Null);
+        };
+        let held = |lot: &LotMatch| {
+            lot.date()
+                .map(|date| (ctx.transaction.date - date).num_days())
+        };
+
+        if name == "HOLDING_PERIOD" {
+            return Ok(booking
+                .lots
+                .iter()
+                .filter_map(held)
+                .min()
+                .map_or(Value::Null, Value::Integer));
+        }
+
+        let mut bounds = [None, None];
+        for (bound, arg) in bounds.iter_mut().zip(&func.args[1..]) {
+            *bound = match self.evaluate_expr(arg, ctx)? {
+                Value::Null => None,
+                value => Some(whole_number(&value).ok_or_else(|| {
+                    QueryError::Type(format!("{name} expects whole numbers of days"))
+                })?),
+            };
+        }
+        let Some(price) = ctx
+            .transaction
+            .postings
+            .get(ctx.posting_index)
+            .and_then(Self::posting_unit_price)
+        else {
+            return Ok(Value::Null);
+        };
+
+        let range = bounds[0].unwrap_or(i64::MIN)..bounds[1].unwrap_or(i64::MAX);
+        let gain = booking
+            .lots
+            .iter()
+            .filter(|&lot| {
+                bounds == [None, None] || held(lot).is_some_and(|days| range.contains(&days))
+            })
+            .filter_map(|lot| lot.realized_gain(&price))
+            .reduce(|total, gain| Amount::new(total.number + gain.number, total.currency));
+        Ok(gain.map_or(Value::Null, Value::Amount))
+    }
+
+    /// Evaluate portfolio return functions: `TWRR(account, from, to [, currency])`
+    /// and `XIRR(account [, currency])`.
+    ///
+    /// Both are computed over the whole ledger, independent of the current row.
+    /// `XIRR` values the portfolio as of the latest directive date.
+    fn eval_return_function(
+        &self,
+        name: &str,
+        func: &FunctionCall,
+        ctx: &PostingContext,
+    ) -> Result<Value, QueryError> {
+        let fixed_args = if name == "TWRR" { 3 } else { 1 };
+        if func.args.len() != fixed_args && func.args.len() != fixed_args + 1 {
+            return Err(QueryError::InvalidArguments(
+                name.to_string(),
+                format!("expected {} or {} arguments", fixed_args, fixed_args + 1),
+            ));
+        }
+
+        let account = match self.evaluate_expr(&func.args[0], ctx)? {
+            Value::String(s) => s,
+            _ => {
+                return Err(QueryError::Type(format!(
+                    "{name} first argument must be an account string"
+                )));
+            }
+        };
+
+        let currency = match func.args.get(fixed_args) {
+            Some(arg) => match self.evaluate_expr(arg, ctx)? {
+                Value::String(s) => s,
+                _ => {
+                    return Err(QueryError::Type(format!(
+                        "{name} currency argument must be a string"
+                    )));
+                }
+            },
+            None => self.target_currency.clone().ok_or_else(|| {
+                QueryError::InvalidArguments(
+                    name.to_string(),
+                    format!(
+                        "no target currency set; pass the currency as the last argument to {name}"
+                    ),
+                )
+            })?,
+        };
+
+        let calc = crate::returns::ReturnCalculator::new(
+            self.directives().map(|(_, directive)| directive),
+            &self.price_db,
+            &account,
+            &currency,
+        );
+
+        let rate = if name == "TWRR" {
+            let mut dates = [NaiveDate::MIN; 2];
+            for (slot, arg) in dates.iter_mut().zip(&func.args[1..3]) {
+                *slot = match self.evaluate_expr(arg, ctx)? {
+                    Value::Date(d) => d,
+                    _ => return Err(QueryError::Type("TWRR expects date bounds".to_string())),
+                };
+            }
+            calc.twrr(dates[0], dates[1])
+        } else {
+            self.directives()
+                .map(|(_, directive)| directive.date())
+                .max()
+                .and_then(|as_of| calc.xirr(as_of))
+        };
+
+        Ok(rate.map_or(Value::Null, Value::Number))
+    }
+
     /// Evaluate COALESCE function.
     fn eval_coalesce(
         &self,
@@ -1749,6 +2818,7 @@ impl<'a> Executor<'a> {
                     _ => Err(QueryError::Type("DAY expects a date".to_string())),
                 }
             }
+            "DATE_TRUNC" | "DATE_ADD" => Self::eval_calendar_function(&name_upper, args),
             // String functions
             "LENGTH" => {
                 Self::require_args_count(&name_upper, args, 1)?;
@@ -1810,6 +2880,8 @@ impl<'a> Executor<'a> {
                     _ => Err(QueryError::Type("ROUND expects a number".to_string())),
                 }
             }
+            // Currency conversion
+            "CONVERT" => self.convert(args),
             // Utility functions
             "COALESCE" => {
                 for arg in args {
@@ -1821,6 +2893,9 @@ impl<'a> Executor<'a> {
             }
             // Aggregate functions return Null when evaluated on a single row
             "SUM" | "COUNT" | "MIN" | "MAX" | "FIRST" | "LAST" | "AVG" => Ok(Value::Null),
+            _ if self.functions.contains_key(&name.to_lowercase()) => {
+                self.call_user_function(name, args.to_vec())
+            }
             _ => Err(QueryError::UnknownFunction(name.to_string())),
         }
     }
@@ -1887,8 +2962,13 @@ impl<'a> Executor<'a> {
                         ));
                     }
                 };
-                // Simple contains check (full regex would need regex crate)
-                Ok(Value::Boolean(s.contains(&pattern)))
+                // Use regex cache for pattern matching
+                let matches = if let Some(regex) = self.get_or_compile_regex(&pattern) {
+                    regex.is_match(&s)
+                } else {
+                    s.contains(&pattern)
+                };
+                Ok(Value::Boolean(matches))
             }
             BinaryOperator::In => {
                 // Check if left value is in right set
@@ -1909,6 +2989,19 @@ impl<'a> Executor<'a> {
                     )),
                 }
             }
+            BinaryOperator::Add | BinaryOperator::Sub
+                if matches!(left, Value::Date(_)) || matches!(right, Value::Date(_)) =>
+            {
+                date_arithmetic(op.op, &left, &right)
+            }
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div
+                if is_amount_like(&left) || is_amount_like(&right) =>
+            {
+                amount_arithmetic(op.op, &left, &right)
+            }
             BinaryOperator::Add => self.arithmetic_op(&left, &right, |a, b| a + b),
             BinaryOperator::Sub => self.arithmetic_op(&left, &right, |a, b| a - b),
             BinaryOperator::Mul => self.arithmetic_op(&left, &right, |a, b| a * b),
@@ -1932,6 +3025,8 @@ impl<'a> Executor<'a> {
             UnaryOperator::Neg => match val {
                 Value::Number(n) => Ok(Value::Number(-*n)),
                 Value::Integer(i) => Ok(Value::Integer(-*i)),
+                Value::Amount(a) => Ok(Value::Amount(-a)),
+                Value::Position(p) => Ok(Value::Amount(-&p.units)),
                 _ => Err(QueryError::Type(
                     "negation requires numeric value".to_string(),
                 )),
@@ -1967,6 +3062,12 @@ impl<'a> Executor<'a> {
             (Value::Integer(a), Value::Number(b)) => Decimal::from(*a).cmp(b),
             (Value::String(a), Value::String(b)) => a.cmp(b),
             (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::Amount(_) | Value::Position(_) | Value::Inventory(_), Value::Amount(b)) => {
+                units_to_compare(left, b)?.cmp(&b.number)
+            }
+            (Value::Amount(a), Value::Position(_) | Value::Inventory(_)) => {
+                a.number.cmp(&units_to_compare(right, a)?)
+            }
             _ => return Err(QueryError::Type("cannot compare values".to_string())),
         };
         Ok(Value::Boolean(pred(ord)))
@@ -2021,13 +3122,14 @@ impl<'a> Executor<'a> {
                 matches!(
                     func.name.to_uppercase().as_str(),
                     "SUM" | "COUNT" | "MIN" | "MAX" | "FIRST" | "LAST" | "AVG"
-                )
+                ) || func.args.iter().any(Self::is_aggregate_expr)
             }
             Expr::BinaryOp(op) => {
                 Self::is_aggregate_expr(&op.left) || Self::is_aggregate_expr(&op.right)
             }
             Expr::UnaryOp(op) => Self::is_aggregate_expr(&op.operand),
             Expr::Paren(inner) => Self::is_aggregate_expr(inner),
+            Expr::Window(wf) => wf.args.iter().any(Self::is_aggregate_expr),
             _ => false,
         }
     }
@@ -2091,11 +3193,11 @@ impl<'a> Executor<'a> {
                         .map_or(Value::Null, |p| Value::String(p.to_string())),
                 );
                 row.push(Value::String(ctx.transaction.narration.to_string()));
-                let posting = &ctx.transaction.postings[ctx.posting_index];
-                row.push(Value::String(posting.account.to_string()));
+                let posting = ctx.transaction.postings.get(ctx.posting_index);
+                row.push(posting.map_or(Value::Null, |p| Value::String(p.account.to_string())));
                 row.push(
                     posting
-                        .amount()
+                        .and_then(Posting::amount)
                         .map_or(Value::Null, |u| Value::Amount(u.clone())),
                 );
             } else if let Expr::Window(wf) = &target.expr {
@@ -2122,6 +3224,10 @@ impl<'a> Executor<'a> {
             "ROW_NUMBER" => Ok(Value::Integer(ctx.row_number as i64)),
             "RANK" => Ok(Value::Integer(ctx.rank as i64)),
             "DENSE_RANK" => Ok(Value::Integer(ctx.dense_rank as i64)),
+            "LAG" | "LEAD" => Err(QueryError::Evaluation(format!(
+                "Window function '{}' requires an aggregate query",
+                wf.name
+            ))),
             _ => Err(QueryError::Evaluation(format!(
                 "Window function '{}' not yet implemented",
                 wf.name
@@ -2189,22 +3295,7 @@ impl<'a> Executor<'a> {
             if spec.order_by.is_some() {
                 let order_specs = spec.order_by.as_ref().unwrap();
                 sorted_indices.sort_by(|&a, &b| {
-                    let vals_a = &order_values[a];
-                    let vals_b = &order_values[b];
-                    for (i, (va, vb)) in vals_a.iter().zip(vals_b.iter()).enumerate() {
-                        let cmp = self.compare_values_for_sort(va, vb);
-                        if cmp != std::cmp::Ordering::Equal {
-                            return if order_specs
-                                .get(i)
-                                .is_some_and(|s| s.direction == SortDirection::Desc)
-                            {
-                                cmp.reverse()
-                            } else {
-                                cmp
-                            };
-                        }
-                    }
-                    std::cmp::Ordering::Equal
+                    Self::compare_window_order(&order_values[a], &order_values[b], order_specs)
                 });
             }
 
@@ -2243,6 +3334,145 @@ impl<'a> Executor<'a> {
         Ok(window_contexts)
     }
 
+    /// Compare two rows' window ORDER BY values.
+    fn compare_window_order(
+        vals_a: &[Value],
+        vals_b: &[Value],
+        order_specs: &[OrderSpec],
+    ) -> std::cmp::Ordering {
+        for (i, (va, vb)) in vals_a.iter().zip(vals_b.iter()).enumerate() {
+            let cmp = compare_values_for_sort(va, vb);
+            if cmp != std::cmp::Ordering::Equal {
+                return if order_specs
+                    .get(i)
+                    .is_some_and(|s| s.direction == SortDirection::Desc)
+                {
+                    cmp.reverse()
+                } else {
+                    cmp
+                };
+            }
+        }
+        std::cmp::Ordering::Equal
+    }
+
+    /// Evaluate the inputs of each LAG or LEAD target for one group.
+    ///
+    /// Returns one entry per window target, in target order.
+    fn evaluate_offset_windows(
+        &self,
+        targets: &[Target],
+        group: &[&PostingContext],
+    ) -> Result<Vec<OffsetWindowRow>, QueryError> {
+        use rust_decimal::prelude::ToPrimitive;
+
+        let mut inputs = Vec::new();
+        for target in targets {
+            let Expr::Window(wf) = &target.expr else {
+                continue;
+            };
+            let name = wf.name.to_uppercase();
+            if name != "LAG" && name != "LEAD" {
+                return Err(QueryError::Evaluation(format!(
+                    "Window function '{}' is not supported in aggregate queries",
+                    wf.name
+                )));
+            }
+            if wf.args.is_empty() || wf.args.len() > 3 {
+                return Err(QueryError::InvalidArguments(
+                    name,
+                    "expected 1 to 3 arguments".to_string(),
+                ));
+            }
+
+            let value = self.evaluate_aggregate_expr(&wf.args[0], group)?;
+            let offset = match wf.args.get(1) {
+                Some(Expr::Literal(Literal::Integer(n))) => usize::try_from(*n).ok(),
+                Some(Expr::Literal(Literal::Number(n))) if n.is_integer() => n.to_usize(),
+                Some(_) => None,
+                None => Some(1),
+            };
+            let Some(offset) = offset else {
+                return Err(QueryError::InvalidArguments(
+                    name,
+                    "offset must be a non-negative integer literal".to_string(),
+                ));
+            };
+            let default = match wf.args.get(2) {
+                Some(Expr::Literal(lit)) => self.evaluate_literal(lit)?,
+                Some(arg) => self.evaluate_aggregate_expr(arg, group)?,
+                None => Value::Null,
+            };
+
+            let mut partition = Vec::new();
+            for expr in wf.over.partition_by.iter().flatten() {
+                partition.push(self.evaluate_aggregate_expr(expr, group)?);
+            }
+            let mut order = Vec::new();
+            for order_spec in wf.over.order_by.iter().flatten() {
+                order.push(self.evaluate_aggregate_expr(&order_spec.expr, group)?);
+            }
+
+            inputs.push(OffsetWindowRow {
+                partition: Self::make_group_key(&partition),
+                order,
+                value,
+                offset,
+                default,
+            });
+        }
+        Ok(inputs)
+    }
+
+    /// Fill the LAG and LEAD columns of aggregated rows.
+    ///
+    /// `inputs[i]` holds the window inputs of `rows[i]`, as returned by
+    /// [`Self::evaluate_offset_windows`]. Each row takes the value of the row
+    /// `offset` places before (LAG) or after (LEAD) it in its partition.
+    fn apply_offset_windows(rows: &mut [Row], targets: &[Target], inputs: &[Vec<OffsetWindowRow>]) {
+        let windows = targets.iter().enumerate().filter_map(|(column, target)| {
+            if let Expr::Window(wf) = &target.expr {
+                Some((column, wf))
+            } else {
+                None
+            }
+        });
+
+        for (slot, (column, wf)) in windows.enumerate() {
+            let lead = wf.name.eq_ignore_ascii_case("LEAD");
+            let order_specs = wf.over.order_by.as_deref().unwrap_or_default();
+
+            let mut partitions: HashMap<&str, Vec<usize>> = HashMap::new();
+            for (row, input) in inputs.iter().enumerate() {
+                partitions
+                    .entry(input[slot].partition.as_str())
+                    .or_default()
+                    .push(row);
+            }
+
+            for mut indices in partitions.into_values() {
+                indices.sort_by(|&a, &b| {
+                    Self::compare_window_order(
+                        &inputs[a][slot].order,
+                        &inputs[b][slot].order,
+                        order_specs,
+                    )
+                });
+                for (position, &row) in indices.iter().enumerate() {
+                    let input = &inputs[row][slot];
+                    let source = if lead {
+                        position.checked_add(input.offset)
+                    } else {
+                        position.checked_sub(input.offset)
+                    };
+                    rows[row][column] = source
+                        .and_then(|source| indices.get(source))
+                        .map_or_else(|| input.default.clone(), |&s| inputs[s][slot].value.clone());
+                }
+            }
+        }
+    }
+
     /// Extract the first window function from targets (for getting the window spec).
     fn find_window_function(targets: &[Target]) -> Option<&WindowFunction> {
         for target in targets {
@@ -2310,6 +3540,90 @@ impl<'a> Executor<'a> {
         key
     }
 
+    /// Resolve GROUP BY ordinals (`GROUP BY 1, 2`) and aliases to the
+    /// SELECT expressions they refer to, including inside `ROLLUP(...)`.
+    fn resolve_group_by(group_exprs: &[Expr], targets: &[Target]) -> Vec<Expr> {
+        group_exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Function(func) if Self::rollup_arg(expr).is_some() => {
+                    Expr::Function(FunctionCall {
+                        name: func.name.clone(),
+                        args: vec![Self::resolve_group_expr(&func.args[0], targets)],
+                    })
+                }
+                _ => Self::resolve_group_expr(expr, targets),
+            })
+            .collect()
+    }
+
+    /// Resolve a single GROUP BY ordinal or alias.
+    fn resolve_group_expr(expr: &Expr, targets: &[Target]) -> Expr {
+        use rust_decimal::prelude::ToPrimitive;
+
+        let target = match expr {
+            Expr::Literal(Literal::Integer(n)) => usize::try_from(*n)
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| targets.get(i)),
+            Expr::Literal(Literal::Number(n)) => n
+                .to_usize()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| targets.get(i)),
+            Expr::Column(name) => targets.iter().find(|t| {
+                t.alias
+                    .as_ref()
+                    .is_some_and(|alias| alias.eq_ignore_ascii_case(name))
+            }),
+            _ => None,
+        };
+        target.map_or_else(|| expr.clone(), |t| t.expr.clone())
+    }
+
+    /// The account expression of a `ROLLUP(expr)` (or `ANCESTORS(expr)`)
+    /// group key.
+    fn rollup_arg(expr: &Expr) -> Option<&Expr> {
+        match expr {
+            Expr::Function(func)
+                if func.args.len() == 1
+                    && matches!(func.name.to_uppercase().as_str(), "ROLLUP" | "ANCESTORS") =>
+            {
+                Some(&func.args[0])
+            }
+            _ => None,
+        }
+    }
+
+    /// An account and each of its ancestors, from the root down.
+    ///
+    /// Values that are not account names are returned unchanged.
+    fn account_ancestors(value: Value) -> Vec<Value> {
+        let Value::String(account) = value else {
+            return vec![value];
+        };
+        let mut ancestors: Vec<Value> = account
+            .match_indices(':')
+            .map(|(i, _)| Value::String(account[..i].to_string()))
+            .collect();
+        ancestors.push(Value::String(account));
+        ancestors
+    }
+
+    /// Show the group's rolled-up account in the targets that select the
+    /// rolled-up expression, instead of the account of its first posting.
+    fn apply_rollup_keys(row: &mut Row, targets: &[Target], group_exprs: &[Expr], key: &[Value]) {
+        for (expr, value) in group_exprs.iter().zip(key) {
+            let Some(arg) = Self::rollup_arg(expr) else {
+                continue;
+            };
+            for (cell, target) in row.iter_mut().zip(targets) {
+                if target.expr == *arg {
+                    cell.clone_from(value);
+                }
+            }
+        }
+    }
+
     /// Group postings by the GROUP BY expressions.
     /// Uses `HashMap` for O(1) key lookup instead of O(n) linear search.
     fn group_postings<'b>(
@@ -2323,17 +3637,40 @@ impl<'a> Executor<'a> {
                 HashMap::new();
 
             for ctx in postings {
-                let mut key_values = Vec::with_capacity(group_exprs.len());
+                // A ROLLUP key puts the posting in one group per ancestor account
+                let mut keys = vec![Vec::with_capacity(group_exprs.len())];
                 for expr in group_exprs {
-                    key_values.push(self.evaluate_expr(expr, ctx)?);
+                    if let Some(arg) = Self::rollup_arg(expr) {
+                        let ancestors = Self::account_ancestors(self.evaluate_expr(arg, ctx)?);
+                        keys = keys
+                            .iter()
+                            .flat_map(|key| {
+                                ancestors.iter().map(move |ancestor| {
+                                    let mut key = key.clone();
+                                    key.push(ancestor.clone());
+                                    key
+                                })
+                            })
+                            .collect();
+                    } else {
+                        let value = self.evaluate_expr(expr, ctx)?;
+                        if let Some((last, rest)) = keys.split_last_mut() {
+                            for key in rest {
+                                key.push(value.clone());
+                            }
+                            last.push(value);
+                        }
+                    }
                 }
-                let hash_key = Self::make_group_key(&key_values);
 
-                group_map
-                    .entry(hash_key)
-                    .or_insert_with(|| (key_values, Vec::new()))
-                    .1
-                    .push(ctx);
+                for key_values in keys {
+                    let hash_key = Self::make_group_key(&key_values);
+                    group_map
+                        .entry(hash_key)
+                        .or_insert_with(|| (key_values, Vec::new()))
+                        .1
+                        .push(ctx);
+                }
             }
 
             Ok(group_map.into_values().collect())
@@ -2351,7 +3688,12 @@ impl<'a> Executor<'a> {
     ) -> Result<Row, QueryError> {
         let mut row = Vec::new();
         for target in targets {
-            row.push(self.evaluate_aggregate_expr(&target.expr, group)?);
+            if matches!(target.expr, Expr::Window(_)) {
+                // Filled in once all groups are known
+                row.push(Value::Null);
+            } else {
+                row.push(self.evaluate_aggregate_expr(&target.expr, group)?);
+            }
         }
         Ok(row)
     }
@@ -2515,6 +3857,15 @@ impl<'a> Executor<'a> {
                             Ok(Value::Number(sum / Decimal::from(count)))
                         }
                     }
+                    _ if func.args.iter().any(Self::is_aggregate_expr) => {
+                        // Non-aggregate function over aggregates, e.g. CONVERT(SUM(position), "USD")
+                        let args = func
+                            .args
+                            .iter()
+                            .map(|arg| self.evaluate_aggregate_expr(arg, group))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        self.evaluate_function_on_values(&func.name, &args)
+                    }
                     _ => {
                         // Non-aggregate function
                         if let Some(ctx) = group.first() {
@@ -2620,6 +3971,19 @@ impl<'a> Executor<'a> {
                     )),
                 }
             }
+            BinaryOperator::Add | BinaryOperator::Sub
+                if matches!(left, Value::Date(_)) || matches!(right, Value::Date(_)) =>
+            {
+                date_arithmetic(op, left, right)
+            }
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div
+                if is_amount_like(left) || is_amount_like(right) =>
+            {
+                amount_arithmetic(op, left, right)
+            }
             BinaryOperator::Add => self.arithmetic_op(left, right, |a, b| a + b),
             BinaryOperator::Sub => self.arithmetic_op(left, right, |a, b| a - b),
             BinaryOperator::Mul => self.arithmetic_op(left, right, |a, b| a * b),
@@ -2682,7 +4046,7 @@ impl<'a> Executor<'a> {
                 if *idx >= a.len() || *idx >= b.len() {
                     continue;
                 }
-                let ord = self.compare_values_for_sort(&a[*idx], &b[*idx]);
+                let ord = compare_values_for_sort(&a[*idx], &b[*idx]);
                 if ord != std::cmp::Ordering::Equal {
                     return if *ascending { ord } else { ord.reverse() };
                 }
@@ -2693,38 +4057,6 @@ impl<'a> Executor<'a> {
         Ok(())
     }
 
-    /// Compare two values for sorting purposes.
-    fn compare_values_for_sort(&self, left: &Value, right: &Value) -> std::cmp::Ordering {
-        match (left, right) {
-            (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
-            (Value::Null, _) => std::cmp::Ordering::Greater, // Nulls sort last
-            (_, Value::Null) => std::cmp::Ordering::Less,
-            (Value::Number(a), Value::Number(b)) => a.cmp(b),
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::Number(a), Value::Integer(b)) => a.cmp(&Decimal::from(*b)),
-            (Value::Integer(a), Value::Number(b)) => Decimal::from(*a).cmp(b),
-            (Value::String(a), Value::String(b)) => a.cmp(b),
-            (Value::Date(a), Value::Date(b)) => a.cmp(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
-            // Compare amounts by their numeric value (same currency assumed)
-            (Value::Amount(a), Value::Amount(b)) => a.number.cmp(&b.number),
-            // Compare positions by their units' numeric value
-            (Value::Position(a), Value::Position(b)) => a.units.number.cmp(&b.units.number),
-            // Compare inventories by first position's value (for single-currency)
-            (Value::Inventory(a), Value::Inventory(b)) => {
-                let a_val = a.positions().first().map(|p| &p.units.number);
-                let b_val = b.positions().first().map(|p| &p.units.number);
-                match (a_val, b_val) {
-                    (Some(av), Some(bv)) => av.cmp(bv),
-                    (Some(_), None) => std::cmp::Ordering::Less,
-                    (None, Some(_)) => std::cmp::Ordering::Greater,
-                    (None, None) => std::cmp::Ordering::Equal,
-                }
-            }
-            _ => std::cmp::Ordering::Equal, // Can't compare other types
-        }
-    }
-
     /// Evaluate a HAVING clause filter on an aggregated row.
     ///
     /// The HAVING clause can reference:
@@ -2824,104 +4156,40 @@ impl<'a> Executor<'a> {
 
     /// Apply PIVOT BY transformation to results.
     ///
-    /// PIVOT BY transforms rows into columns based on pivot column values.
-    /// For example: `SELECT account, YEAR(date), SUM(amount) GROUP BY 1, 2 PIVOT BY YEAR(date)`
-    /// would create columns for each year.
+    /// The first expression selects the column whose values become columns;
+    /// the optional second one selects the cells, which default to the last
+    /// column. For example, `SELECT account, month, SUM(position) GROUP BY 1, 2
+    /// PIVOT BY month` gives an account × month matrix. See
+    /// [`QueryResult::pivot`].
     fn apply_pivot(
         &self,
         result: &QueryResult,
         pivot_exprs: &[Expr],
-        _targets: &[Target],
+        targets: &[Target],
     ) -> Result<QueryResult, QueryError> {
-        if pivot_exprs.is_empty() {
-            return Ok(result.clone());
-        }
-
-        // For simplicity, we'll pivot on the first expression only
-        // A full implementation would support multiple pivot columns
-        let pivot_expr = &pivot_exprs[0];
-
-        // Find which column in the result matches the pivot expression
-        let pivot_col_idx = self.find_pivot_column(result, pivot_expr)?;
-
-        // Collect unique pivot values
-        let mut pivot_values: Vec<Value> = result
-            .rows
-            .iter()
-            .map(|row| row.get(pivot_col_idx).cloned().unwrap_or(Value::Null))
-            .collect();
-        pivot_values.sort_by(|a, b| self.compare_values_for_sort(a, b));
-        pivot_values.dedup();
-
-        // Build new column names: original columns (except pivot) + pivot values
-        let mut new_columns: Vec<String> = result
-            .columns
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| *i != pivot_col_idx)
-            .map(|(_, c)| c.clone())
-            .collect();
-
-        // Identify the "value" column (usually the last one, or the one with aggregate)
-        let value_col_idx = result.columns.len() - 1;
-
-        // Add pivot value columns
-        for pv in &pivot_values {
-            new_columns.push(self.value_to_string(pv));
-        }
-
-        let mut new_result = QueryResult::new(new_columns);
-
-        // Group rows by non-pivot, non-value columns
-        let group_cols: Vec<usize> = (0..result.columns.len())
-            .filter(|i| *i != pivot_col_idx && *i != value_col_idx)
-            .collect();
-
-        let mut groups: HashMap<String, Vec<&Row>> = HashMap::new();
-        for row in &result.rows {
-            let key: String = group_cols
-                .iter()
-                .map(|&i| self.value_to_string(&row[i]))
-                .collect::<Vec<_>>()
-                .join("|");
-            groups.entry(key).or_default().push(row);
-        }
-
-        // Build pivoted rows
-        for (_key, group_rows) in groups {
-            let mut new_row: Vec<Value> = group_cols
-                .iter()
-                .map(|&i| group_rows[0][i].clone())
-                .collect();
-
-            // Build O(1) pivot value -> row index for this group
-            let pivot_index: HashMap<u64, usize> = group_rows
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, row)| {
-                    row.get(pivot_col_idx).map(|v| (hash_single_value(v), idx))
-                })
-                .collect();
-
-            // Add pivot values with O(1) lookup
-            for pv in &pivot_values {
-                let pv_hash = hash_single_value(pv);
-                if let Some(&row_idx) = pivot_index.get(&pv_hash) {
-                    new_row.push(
-                        group_rows[row_idx]
-                            .get(value_col_idx)
-                            .cloned()
-                            .unwrap_or(Value::Null),
-                    );
-                } else {
-                    new_row.push(Value::Null);
-                }
+        let (pivot_expr, value_expr) = match pivot_exprs {
+            [] => return Ok(result.clone()),
+            [pivot] => (pivot, None),
+            [pivot, value] => (pivot, Some(value)),
+            _ => {
+                return Err(QueryError::Evaluation(
+                    "PIVOT BY takes a pivot column and an optional value column".to_string(),
+                ));
             }
+        };
 
-            new_result.add_row(new_row);
-        }
+        let pivot_col_idx = self.find_pivot_column(result, pivot_expr, targets)?;
+        let value_col_idx = match value_expr {
+            Some(expr) => self.find_pivot_column(result, expr, targets)?,
+            None => (0..result.columns.len())
+                .rev()
+                .find(|&i| i != pivot_col_idx)
+                .ok_or_else(|| {
+                    QueryError::Evaluation("PIVOT BY needs a value column".to_string())
+                })?,
+        };
 
-        Ok(new_result)
+        result.pivot(pivot_col_idx, value_col_idx)
     }
 
     /// Find the column index matching the pivot expression.
@@ -2929,7 +4197,15 @@ impl<'a> Executor<'a> {
         &self,
         result: &QueryResult,
         pivot_expr: &Expr,
+        targets: &[Target],
     ) -> Result<usize, QueryError> {
+        // Expressions like `YEAR(date)` match the SELECT target they repeat
+        if let Some(idx) = targets.iter().position(|t| t.expr == *pivot_expr) {
+            if idx < result.columns.len() {
+                return Ok(idx);
+            }
+        }
+
         match pivot_expr {
             Expr::Column(name) => {
                 let upper_name = name.to_uppercase();
@@ -2965,35 +4241,334 @@ impl<'a> Executor<'a> {
                     )))
                 }
             }
-            _ => {
-                // For complex expressions, try to find a matching column by string representation
-                // This is a simplified approach
-                Err(QueryError::Evaluation(
-                    "PIVOT BY must reference a column name or index".to_string(),
-                ))
+            _ => Err(QueryError::Evaluation(
+                "PIVOT BY must reference a column name, index or SELECT expression".to_string(),
+            )),
+        }
+    }
+}
+
+/// Rows of a query, produced one at a time by [`Executor::execute_iter`].
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct QueryRows<'e, 'a> {
+    /// Column names.
+    columns: Vec<String>,
+    /// Where rows come from.
+    source: RowSource<'e, 'a>,
+    /// Rows that may still be returned under the row limit, if any.
+    budget: Option<usize>,
+    /// Whether the row limit stopped the iterator before the last row.
+    truncated: bool,
+}
+
+/// Source of the rows of [`QueryRows`].
+enum RowSource<'e, 'a> {
+    /// Evaluate a plain SELECT posting by posting.
+    Lazy {
+        /// Executor evaluating expressions.
+        executor: &'e Executor<'a>,
+        /// The query being evaluated.
+        query: &'e SelectQuery,
+        /// Position in the directives.
+        cursor: Box<PostingCursor<'a>>,
+        /// Hashes of rows already returned, for DISTINCT.
+        seen: HashSet<u64>,
+        /// Rows left under the query's LIMIT clause, if any.
+        remaining: Option<usize>,
+    },
+    /// Rows of an already executed query.
+    Materialized(std::vec::IntoIter<Row>),
+    /// No more rows.
+    Done,
+}
+
+impl<'e, 'a> QueryRows<'e, 'a> {
+    /// Create an iterator over rows from `source`.
+    const fn new(columns: Vec<String>, source: RowSource<'e, 'a>) -> Self {
+        Self {
+            columns,
+            source,
+            budget: None,
+            truncated: false,
+        }
+    }
+
+    /// Stop after `limit` rows.
+    ///
+    /// Unlike a `LIMIT` clause, this records whether more rows were
+    /// available; see [`QueryRows::is_truncated`].
+    #[must_use]
+    pub const fn with_row_limit(mut self, limit: usize) -> Self {
+        self.budget = Some(limit);
+        self
+    }
+
+    /// Column names.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Whether the row limit cut the result short.
+    ///
+    /// Only known once the iterator has returned `None`.
+    pub const fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Collect the remaining rows into a [`QueryResult`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error found while evaluating rows.
+    pub fn into_result(mut self) -> Result<QueryResult, QueryError> {
+        let mut rows = Vec::new();
+        for row in self.by_ref() {
+            rows.push(row?);
+        }
+        Ok(QueryResult {
+            columns: self.columns,
+            rows,
+        })
+    }
+
+    /// Produce the next row from the source, ignoring the row limit.
+    fn next_row(&mut self) -> Option<Result<Row, QueryError>> {
+        let row = match &mut self.source {
+            RowSource::Lazy {
+                executor,
+                query,
+                cursor,
+                seen,
+                remaining,
+            } => loop {
+                if *remaining == Some(0) {
+                    break None;
+                }
+                let ctx =
+                    match cursor.next(executor, query.from.as_ref(), query.where_clause.as_ref()) {
+                        Ok(Some(ctx)) => ctx,
+                        Ok(None) => break None,
+                        Err(e) => break Some(Err(e)),
+                    };
+                let row = match executor.evaluate_row(&query.targets, &ctx) {
+                    Ok(row) => row,
+                    Err(e) => break Some(Err(e)),
+                };
+                if query.distinct && !seen.insert(hash_row(&row)) {
+                    continue;
+                }
+                if let Some(remaining) = remaining {
+                    *remaining -= 1;
+                }
+                break Some(Ok(row));
+            },
+            RowSource::Materialized(rows) => rows.next().map(Ok),
+            RowSource::Done => None,
+        };
+        if !matches!(row, Some(Ok(_))) {
+            self.source = RowSource::Done;
+        }
+        row
+    }
+}
+
+impl Iterator for QueryRows<'_, '_> {
+    type Item = Result<Row, QueryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.budget {
+            Some(0) => {
+                // Check for one more row so the caller knows there are more
+                if !matches!(self.source, RowSource::Done) {
+                    self.truncated = self.next_row().is_some();
+                    self.source = RowSource::Done;
+                }
+                None
+            }
+            Some(ref mut budget) => {
+                *budget -= 1;
+                self.next_row()
             }
+            None => self.next_row(),
         }
     }
+}
 
-    /// Convert a value to string for display/grouping.
-    fn value_to_string(&self, val: &Value) -> String {
-        match val {
-            Value::String(s) => s.clone(),
-            Value::Number(n) => n.to_string(),
-            Value::Integer(i) => i.to_string(),
-            Value::Date(d) => d.to_string(),
-            Value::Boolean(b) => b.to_string(),
-            Value::Amount(a) => format!("{} {}", a.number, a.currency),
-            Value::Position(p) => format!("{}", p.units),
-            Value::Inventory(inv) => inv
-                .positions()
-                .iter()
-                .map(|p| format!("{}", p.units))
-                .collect::<Vec<_>>()
-                .join(", "),
-            Value::StringSet(ss) => ss.join(", "),
-            Value::Null => "NULL".to_string(),
+/// Walks transaction postings in order, keeping running balances.
+struct PostingCursor<'a> {
+    /// Directives not yet visited.
+    directives: MergedDirectives<'a>,
+    /// Transaction being visited, its ledger, and the index of its next posting.
+    current: Option<(&'a Transaction, usize, usize)>,
+    /// Running balance per account.
+    running_balances: HashMap<InternedStr, Inventory>,
+    /// Lots held at cost per account, for realized gains.
+    lots: HashMap<InternedStr, Inventory>,
+    /// Booking method per account, from `open` directives.
+    booking_methods: HashMap<InternedStr, BookingMethod>,
+    /// Booking method for accounts without one.
+    default_booking: BookingMethod,
+}
+
+impl<'a> PostingCursor<'a> {
+    /// Start before the first directive.
+    fn new(directives: MergedDirectives<'a>, default_booking: BookingMethod) -> Self {
+        Self {
+            directives,
+            current: None,
+            running_balances: HashMap::new(),
+            lots: HashMap::new(),
+            booking_methods: HashMap::new(),
+            default_booking,
+        }
+    }
+
+    /// Apply a posting to the running balance and the lots held at cost.
+    ///
+    /// Returns the booking result when the posting reduced lots.
+    fn apply(&mut self, txn: &Transaction, posting: &Posting) -> Option<BookingResult> {
+        let units = posting.amount()?;
+        self.running_balances
+            .entry(posting.account.clone())
+            .or_default()
+            .add(Position::simple(units.clone()));
+
+        let cost_spec = posting.cost.as_ref()?;
+        let method = self
+            .booking_methods
+            .get(&posting.account)
+            .copied()
+            .unwrap_or(self.default_booking);
+        self.lots
+            .entry(posting.account.clone())
+            .or_default()
+            .book_at_cost(units, cost_spec, method, txn.date)
+            .ok()
+            .flatten()
+    }
+
+    /// Next posting matching the FROM and WHERE clauses, or the next
+    /// transaction when reading `FROM #transactions`.
+    fn next(
+        &mut self,
+        executor: &Executor<'a>,
+        from: Option<&FromClause>,
+        where_clause: Option<&Expr>,
+    ) -> Result<Option<PostingContext<'a>>, QueryError> {
+        loop {
+            if let Some((txn, ledger, i)) = self.current {
+                if let Some(posting) = txn.postings.get(i) {
+                    self.current = Some((txn, ledger, i + 1));
+
+                    // Update running balance and lots for this account
+                    let booking = self.apply(txn, posting);
+
+                    let ctx = PostingContext {
+                        transaction: txn,
+                        posting_index: i,
+                        balance: self.running_balances.get(&posting.account).cloned(),
+                        booking,
+                        ledger,
+                    };
+
+                    // Check WHERE clause (posting-level filter)
+                    if let Some(where_expr) = where_clause {
+                        if !executor.evaluate_predicate(where_expr, &ctx)? {
+                            continue;
+                        }
+                    }
+                    return Ok(Some(ctx));
+                }
+                self.current = None;
+            }
+
+            let Some((ledger, directive)) = self.directives.next() else {
+                return Ok(None);
+            };
+            let txn = match directive {
+                Directive::Transaction(txn) => txn,
+                Directive::Open(open) => {
+                    if let Some(method) = open.booking.as_deref().and_then(|b| b.parse().ok()) {
+                        self.booking_methods.insert(open.account.clone(), method);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            // Check FROM clause (transaction-level filter)
+            if let Some(from) = from {
+                // Apply date filters
+                if let Some(open_date) = from.open_on {
+                    if txn.date < open_date {
+                        // Update balances but don't include in results
+                        for posting in &txn.postings {
+                            self.apply(txn, posting);
+                        }
+                        continue;
+                    }
+                }
+                if let Some(close_date) = from.close_on {
+                    if txn.date > close_date {
+                        continue;
+                    }
+                }
+                // Apply filter expression
+                if let Some(filter) = &from.filter {
+                    if !executor.evaluate_from_filter(filter, txn, ledger)? {
+                        continue;
+                    }
+                }
+            }
+
+            if from.is_some_and(|from| from.table == Table::Transactions) {
+                // One row for the whole transaction
+                for posting in &txn.postings {
+                    self.apply(txn, posting);
+                }
+                let ctx = PostingContext {
+                    transaction: txn,
+                    posting_index: txn.postings.len(),
+                    balance: None,
+                    booking: None,
+                    ledger,
+                };
+                if let Some(where_expr) = where_clause {
+                    if !executor.evaluate_predicate(where_expr, &ctx)? {
+                        continue;
+                    }
+                }
+                return Ok(Some(ctx));
+            }
+
+            self.current = Some((txn, ledger, 0));
+        }
+    }
+}
+
+/// Directives of several ledgers merged in date order.
+///
+/// Each ledger's own order is kept; on equal dates earlier ledgers go first.
+struct MergedDirectives<'a> {
+    sources: Vec<std::iter::Peekable<std::slice::Iter<'a, Directive>>>,
+}
+
+impl<'a> Iterator for MergedDirectives<'a> {
+    type Item = (usize, &'a Directive);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut next: Option<(usize, NaiveDate)> = None;
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            if let Some(directive) = source.peek() {
+                let date = directive.date();
+                if next.map_or(true, |(_, earliest)| date < earliest) {
+                    next = Some((i, date));
+                }
+            }
         }
+        let (i, _) = next?;
+        self.sources[i].next().map(|directive| (i, directive))
     }
 }
 
@@ -3062,6 +4637,192 @@ mod tests {
         assert_eq!(result.len(), 2); // Only expense postings
     }
 
+    #[test]
+    fn test_from_transactions_table() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse(
+            "SELECT date, payee, accounts, id FROM #transactions WHERE payee = \"Coffee Shop\"",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 1); // One row per transaction, not per posting
+        assert_eq!(
+            result.rows[0][2],
+            Value::StringSet(vec![
+                "Assets:Bank:Checking".to_string(),
+                "Expenses:Food:Coffee".to_string(),
+            ])
+        );
+        // The id is the content hash of the transaction, shared by its postings
+        let id = Value::String(directive_id(&directives[0]));
+        assert_eq!(result.rows[0][3], id);
+        let postings = executor
+            .execute(&parse("SELECT id WHERE payee = \"Coffee Shop\"").unwrap())
+            .unwrap();
+        assert_eq!(postings.rows, vec![vec![id.clone()], vec![id]]);
+
+        // Streaming gives the same rows
+        let all = parse("SELECT narration FROM #transactions").unwrap();
+        let rows = executor.execute_iter(&all).unwrap().into_result().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(executor.execute(&all).unwrap().rows, rows.rows);
+
+        // Posting columns have no value for a whole transaction
+        let query = parse("SELECT account FROM #transactions").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::Evaluation(_))
+        ));
+        let query = parse("SELECT nonsense FROM #transactions").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::UnknownColumn(_))
+        ));
+    }
+
+    #[test]
+    fn test_lots_and_gain_columns() {
+        use rustledger_core::{CostSpec, Open, PriceAnnotation};
+
+        let buy = |day: NaiveDate, cost| {
+            Directive::Transaction(
+                Transaction::new(day, "Buy")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(10), "AAPL")).with_cost(
+                            CostSpec::empty()
+                                .with_number_per(cost)
+                                .with_currency("USD")
+                                .with_date(day),
+                        ),
+                    )
+                    .with_posting(Posting::new(
+                        "Assets:Cash",
+                        Amount::new(-cost * dec!(10), "USD"),
+                    )),
+            )
+        };
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Stock").with_booking("FIFO")),
+            buy(date(2024, 1, 1), dec!(100)),
+            buy(date(2024, 2, 1), dec!(150)),
+            Directive::Transaction(
+                Transaction::new(date(2024, 3, 1), "Sell")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(-15), "AAPL"))
+                            .with_cost(CostSpec::empty())
+                            .with_price(PriceAnnotation::Total(Amount::new(dec!(2400), "USD"))),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(2400), "USD"))),
+            ),
+        ];
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("SELECT date, lots, gain WHERE account = \"Assets:Stock\"").unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 3);
+
+        // Buys reduce nothing
+        assert_eq!(result.rows[0][1], Value::Null);
+        assert_eq!(result.rows[0][2], Value::Null);
+
+        // The sale takes 10 from the first lot and 5 from the second at 160
+        let Value::Inventory(lots) = &result.rows[2][1] else {
+            panic!("expected lots inventory, got {:?}", result.rows[2][1]);
+        };
+        let taken: Vec<_> = lots
+            .positions()
+            .iter()
+            .map(|p| (p.units.number, p.cost.as_ref().and_then(|c| c.date)))
+            .collect();
+        assert_eq!(
+            taken,
+            vec![
+                (dec!(10), Some(date(2024, 1, 1))),
+                (dec!(5), Some(date(2024, 2, 1))),
+            ]
+        );
+        assert_eq!(
+            result.rows[2][2],
+            Value::Amount(Amount::new(dec!(650), "USD"))
+        );
+    }
+
+    #[test]
+    fn test_realized_gain_and_holding_period() {
+        use rustledger_core::{CostSpec, Open, PriceAnnotation};
+
+        let buy = |day: NaiveDate, cost| {
+            Directive::Transaction(
+                Transaction::new(day, "Buy")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(10), "AAPL")).with_cost(
+                            CostSpec::empty()
+                                .with_number_per(cost)
+                                .with_currency("USD")
+                                .with_date(day),
+                        ),
+                    )
+                    .with_posting(Posting::new(
+                        "Assets:Cash",
+                        Amount::new(-cost * dec!(10), "USD"),
+                    )),
+            )
+        };
+        let directives = vec![
+            Directive::Open(Open::new(date(2023, 1, 1), "Assets:Stock").with_booking("FIFO")),
+            buy(date(2023, 1, 1), dec!(100)),
+            buy(date(2024, 2, 1), dec!(150)),
+            Directive::Transaction(
+                Transaction::new(date(2024, 3, 1), "Sell")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(-15), "AAPL"))
+                            .with_cost(CostSpec::empty())
+                            .with_price(PriceAnnotation::Unit(Amount::new(dec!(160), "USD"))),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(2400), "USD"))),
+            ),
+        ];
+        let mut executor = Executor::new(&directives);
+
+        let query = parse(
+            "SELECT HOLDING_PERIOD(posting), REALIZED_GAIN(posting), \
+             REALIZED_GAIN(posting, 0, 366), REALIZED_GAIN(posting, 366) \
+             WHERE account = \"Assets:Stock\"",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(result.rows[0].iter().all(|value| *value == Value::Null));
+
+        // FIFO sells 10 units held 425 days and 5 held 29 days
+        let usd = |number| Value::Amount(Amount::new(number, "USD"));
+        assert_eq!(
+            result.rows[2],
+            vec![
+                Value::Integer(29),
+                usd(dec!(650)),
+                usd(dec!(50)),
+                usd(dec!(600)),
+            ]
+        );
+
+        // Without the `open`, the ledger-wide booking method applies
+        let mut executor = Executor::new(&directives[1..]);
+        let result = executor.execute(&query).unwrap();
+        assert!(result.rows[2].iter().all(|value| *value == Value::Null));
+        executor.set_booking_method(BookingMethod::Fifo);
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.rows[2][0], Value::Integer(29));
+
+        let query = parse("SELECT REALIZED_GAIN(account)").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::InvalidArguments(..))
+        ));
+    }
+
     #[test]
     fn test_balances() {
         let directives = sample_directives();
@@ -3253,6 +5014,69 @@ mod tests {
         assert_eq!(result.len(), 4);
     }
 
+    #[test]
+    fn test_execute_iter_matches_execute() {
+        let directives = sample_directives();
+
+        for q in [
+            "SELECT date, account, position, balance",
+            "SELECT DISTINCT payee WHERE account ~ \"Expenses:\"",
+            "SELECT account LIMIT 3",
+            "SELECT account, SUM(position) GROUP BY account ORDER BY account",
+            "SELECT account ORDER BY account DESC",
+            "BALANCES",
+        ] {
+            let query = parse(q).unwrap();
+            let expected = Executor::new(&directives).execute(&query).unwrap();
+
+            let mut executor = Executor::new(&directives);
+            let result = executor
+                .execute_iter(&query)
+                .unwrap()
+                .into_result()
+                .unwrap();
+            assert_eq!(result.columns, expected.columns, "{q}");
+            assert_eq!(result.rows, expected.rows, "{q}");
+        }
+    }
+
+    #[test]
+    fn test_execute_iter_row_limit() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("SELECT date, account").unwrap();
+        let mut rows = executor.execute_iter(&query).unwrap().with_row_limit(3);
+        assert_eq!(rows.columns(), ["date", "account"]);
+        assert_eq!(rows.by_ref().count(), 3);
+        assert!(rows.is_truncated());
+
+        // A limit covering every row is not a truncation
+        let mut rows = executor.execute_iter(&query).unwrap().with_row_limit(4);
+        assert_eq!(rows.by_ref().count(), 4);
+        assert!(!rows.is_truncated());
+
+        // Nor is stopping at the query's own LIMIT
+        let query = parse("SELECT date LIMIT 2").unwrap();
+        let mut rows = executor.execute_iter(&query).unwrap().with_row_limit(2);
+        assert_eq!(rows.by_ref().count(), 2);
+        assert!(!rows.is_truncated());
+    }
+
+    #[test]
+    fn test_execute_iter_error_ends_iteration() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("SELECT account WHERE nosuchfn(account)").unwrap();
+        let mut rows = executor.execute_iter(&query).unwrap();
+        assert!(matches!(
+            rows.next(),
+            Some(Err(QueryError::UnknownFunction(_)))
+        ));
+        assert!(rows.next().is_none());
+    }
+
     #[test]
     fn test_group_by_with_count() {
         let directives = sample_directives();
@@ -3267,6 +5091,54 @@ mod tests {
         assert_eq!(result.len(), 2);
     }
 
+    #[test]
+    fn test_group_by_rollup() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+        let counts = |result: &QueryResult| -> Vec<(String, i64)> {
+            result
+                .rows
+                .iter()
+                .map(|row| match (&row[0], &row[1]) {
+                    (Value::String(account), Value::Integer(n)) => (account.clone(), *n),
+                    other => panic!("unexpected row {other:?}"),
+                })
+                .collect()
+        };
+
+        let query =
+            parse("SELECT account, COUNT(account) GROUP BY ROLLUP(account) ORDER BY account")
+                .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            counts(&result),
+            vec![
+                ("Assets".to_string(), 2),
+                ("Assets:Bank".to_string(), 2),
+                ("Assets:Bank:Checking".to_string(), 2),
+                ("Expenses".to_string(), 2),
+                ("Expenses:Food".to_string(), 2),
+                ("Expenses:Food:Coffee".to_string(), 1),
+                ("Expenses:Food:Groceries".to_string(), 1),
+            ]
+        );
+
+        // Aliases resolve inside the rollup, and ANCESTORS is a synonym
+        let query = parse(
+            "SELECT account AS name, COUNT(account) WHERE account ~ \"Expenses:\" \
+             GROUP BY ANCESTORS(name) HAVING COUNT(account) > 1 ORDER BY name",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            counts(&result),
+            vec![
+                ("Expenses".to_string(), 2),
+                ("Expenses:Food".to_string(), 2)
+            ]
+        );
+    }
+
     #[test]
     fn test_count_aggregate() {
         let directives = sample_directives();
@@ -3285,6 +5157,86 @@ mod tests {
         assert_eq!(result.len(), 2); // Assets, Expenses
     }
 
+    #[test]
+    fn test_user_defined_function() {
+        use std::cell::Cell;
+
+        /// Classifies accounts by their root, counting calls.
+        struct Classify<'c> {
+            calls: &'c Cell<usize>,
+            batches: &'c Cell<usize>,
+        }
+
+        impl ScalarFunction for Classify<'_> {
+            fn call(&self, args: &[Value]) -> Result<Value, QueryError> {
+                self.calls.set(self.calls.get() + 1);
+                match args {
+                    [Value::String(account)] => Ok(Value::String(
+                        if account.starts_with("Expenses:") {
+                            "spending"
+                        } else {
+                            "other"
+                        }
+                        .to_string(),
+                    )),
+                    _ => Err(QueryError::Evaluation("expected an account".to_string())),
+                }
+            }
+
+            fn call_batch(&self, rows: &[Vec<Value>]) -> Result<Vec<Value>, QueryError> {
+                self.batches.set(self.batches.get() + 1);
+                rows.iter().map(|args| self.call(args)).collect()
+            }
+        }
+
+        let directives = sample_directives();
+        let calls = Cell::new(0);
+        let batches = Cell::new(0);
+        let mut executor = Executor::new(&directives);
+        executor.register_function(
+            "test.classify",
+            Classify {
+                calls: &calls,
+                batches: &batches,
+            },
+        );
+
+        // Targets are evaluated in one batch, once per distinct argument
+        let query = parse(
+            "SELECT test.classify(account) AS kind, COUNT(account) GROUP BY test.classify(account) ORDER BY kind",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::String("other".into()), Value::Integer(2)],
+                vec![Value::String("spending".into()), Value::Integer(2)],
+            ]
+        );
+        assert_eq!(batches.get(), 1);
+        assert_eq!(calls.get(), 3);
+
+        // WHERE calls are made row by row
+        let query = parse("SELECT account WHERE TEST.CLASSIFY(account) = \"spending\"").unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(batches.get(), 1);
+
+        // Errors from the function surface as query errors
+        let query = parse("SELECT test.classify(date)").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::Evaluation(_))
+        ));
+
+        let query = parse("SELECT test.unknown(account)").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::UnknownFunction(name)) if name == "test.unknown"
+        ));
+    }
+
     #[test]
     fn test_journal_query() {
         let directives = sample_directives();
@@ -3316,6 +5268,133 @@ mod tests {
         assert_eq!(result.len(), 2);
     }
 
+    #[test]
+    fn test_time_column() {
+        let mut directives = sample_directives();
+        if let Directive::Transaction(txn) = &mut directives[1] {
+            txn.meta.insert(
+                rustledger_core::TIME_META_KEY.to_string(),
+                rustledger_core::MetaValue::String("9:05".to_string()),
+            );
+        }
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("SELECT DISTINCT date, time").unwrap();
+        let result = executor.execute(&query).unwrap();
+
+        assert_eq!(result.rows[0][1], Value::Null);
+        assert_eq!(result.rows[1][1], Value::String("09:05:00".to_string()));
+    }
+
+    fn eur_directives() -> Vec<Directive> {
+        use rustledger_core::Price;
+        vec![
+            Directive::Price(Price::new(
+                date(2024, 3, 31),
+                "EUR",
+                Amount::new(dec!(1.08), "USD"),
+            )),
+            Directive::Price(Price::new(
+                date(2024, 6, 28),
+                "EUR",
+                Amount::new(dec!(1.07), "USD"),
+            )),
+            Directive::Price(Price::new(
+                date(2024, 9, 30),
+                "EUR",
+                Amount::new(dec!(1.11), "USD"),
+            )),
+            Directive::Transaction(
+                Transaction::new(date(2024, 2, 1), "Deposit")
+                    .with_posting(Posting::new(
+                        "Assets:Bank:Euro",
+                        Amount::new(dec!(100), "EUR"),
+                    ))
+                    .with_posting(Posting::new(
+                        "Equity:Opening",
+                        Amount::new(dec!(-100), "EUR"),
+                    )),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_convert_at_date() {
+        let directives = eur_directives();
+        let mut executor = Executor::new(&directives);
+
+        // Nearest earlier price is the one from 2024-06-28
+        let query = parse(
+            "SELECT CONVERT(position, \"USD\", 2024-06-30) WHERE account = \"Assets:Bank:Euro\"",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows[0][0],
+            Value::Amount(Amount::new(dec!(107.00), "USD"))
+        );
+
+        // Without a date the latest price is used
+        let query = parse("SELECT CONVERT(position, \"USD\") WHERE account = \"Assets:Bank:Euro\"")
+            .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows[0][0],
+            Value::Amount(Amount::new(dec!(111.00), "USD"))
+        );
+
+        // No price before the first one
+        let query = parse(
+            "SELECT CONVERT(position, \"USD\", 2024-01-31) WHERE account = \"Assets:Bank:Euro\"",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.rows[0][0], Value::Null);
+    }
+
+    #[test]
+    fn test_convert_aggregate() {
+        let directives = eur_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse(
+            "SELECT account, CONVERT(SUM(position), \"USD\", 2024-03-31) GROUP BY account ORDER BY account",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result.rows[0][1],
+            Value::Amount(Amount::new(dec!(108.00), "USD"))
+        );
+        assert_eq!(
+            result.rows[1][1],
+            Value::Amount(Amount::new(dec!(-108.00), "USD"))
+        );
+
+        let query = parse("SELECT CONVERT(position, \"USD\", \"June\")").unwrap();
+        assert!(matches!(executor.execute(&query), Err(QueryError::Type(_))));
+    }
+
+    #[test]
+    fn test_export_query() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("EXPORT TO HLEDGER FROM payee = \"Supermarket\"").unwrap();
+        let result = executor.execute(&query).unwrap();
+
+        assert_eq!(result.columns, vec!["entry"]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result.rows[0][0],
+            Value::String(
+                "2024-01-16 * Supermarket | Groceries\n    Expenses:Food:Groceries  50.00 USD\n    Assets:Bank:Checking  -50.00 USD\n"
+                    .to_string()
+            )
+        );
+    }
+
     #[test]
     fn test_empty_directives() {
         let directives: Vec<Directive> = vec![];
@@ -3353,6 +5432,47 @@ mod tests {
         assert_eq!(result.len(), 2); // First transaction postings (Jan 15)
     }
 
+    #[test]
+    fn test_date_arithmetic_and_ranges() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse("SELECT date WHERE date BETWEEN 2024-01-16 AND 2024-01-31").unwrap();
+        assert_eq!(executor.execute(&query).unwrap().len(), 2);
+
+        let query =
+            parse("SELECT date + 30, date - 2024-01-01 WHERE date - 1 = 2024-01-14").unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result.rows[0][0], Value::Date(date(2024, 2, 14)));
+        assert_eq!(result.rows[0][1], Value::Integer(14));
+
+        let query = parse(
+            "SELECT DATE_TRUNC(\"quarter\", date), DATE_TRUNC(\"week\", date), \
+             DATE_ADD(date, 1, \"month\"), DATE_ADD(date, -1) LIMIT 1",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows[0],
+            vec![
+                Value::Date(date(2024, 1, 1)),
+                Value::Date(date(2024, 1, 15)),
+                Value::Date(date(2024, 2, 15)),
+                Value::Date(date(2024, 1, 14)),
+            ]
+        );
+
+        // The sample transactions are long past
+        let query = parse("SELECT date WHERE date IN LAST 30 DAYS").unwrap();
+        assert!(executor.execute(&query).unwrap().is_empty());
+        let query = parse("SELECT date WHERE date IN THIS YEAR OR date < TODAY()").unwrap();
+        assert_eq!(executor.execute(&query).unwrap().len(), 4);
+
+        let query = parse("SELECT date + \"x\"").unwrap();
+        assert!(executor.execute(&query).is_err());
+    }
+
     #[test]
     fn test_logical_operators() {
         let directives = sample_directives();
@@ -3386,6 +5506,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_amount_arithmetic() {
+        let directives = sample_directives();
+        let mut executor = Executor::new(&directives);
+
+        let query = parse(
+            "SELECT position * 0.21, 2 * units(position), position + position, \
+             -position, position / 4, position / units(position) \
+             WHERE account ~ \"Groceries\"",
+        )
+        .unwrap();
+        let result = executor.execute(&query).unwrap();
+        let usd = |n| Value::Amount(Amount::new(n, "USD"));
+        assert_eq!(
+            result.rows[0],
+            vec![
+                usd(dec!(10.50)),
+                usd(dec!(100)),
+                usd(dec!(100)),
+                usd(dec!(-50)),
+                usd(dec!(12.5)),
+                Value::Number(dec!(1)),
+            ]
+        );
+
+        let query =
+            parse("SELECT NUMBER(position) + 1, CURRENCY(position) WHERE account ~ \"Groceries\"")
+                .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows[0],
+            vec![Value::Number(dec!(51)), Value::String("USD".to_string())]
+        );
+
+        // Amounts are not coerced to numbers
+        for expr in [
+            "position + 1",
+            "1 - position",
+            "position * position",
+            "1 / position",
+        ] {
+            let query = parse(&format!("SELECT {expr}")).unwrap();
+            assert!(matches!(executor.execute(&query), Err(QueryError::Type(_))));
+        }
+        let query = parse("SELECT position / 0").unwrap();
+        assert!(matches!(
+            executor.execute(&query),
+            Err(QueryError::Evaluation(_))
+        ));
+
+        let err = amount_arithmetic(
+            BinaryOperator::Add,
+            &usd(dec!(1)),
+            &Value::Amount(Amount::new(dec!(1), "EUR")),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            QueryError::CurrencyMismatch { op: "add", .. }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "currency mismatch: cannot add 1 USD and 1 EUR"
+        );
+    }
+
     #[test]
     fn test_first_last_aggregates() {
         let directives = sample_directives();
@@ -3436,4 +5622,67 @@ mod tests {
         result.add_row(vec![Value::Integer(2), Value::String("b".to_string())]);
         assert_eq!(result.len(), 2);
     }
+
+    #[test]
+    fn test_with_sources_ledger_column() {
+        let personal = sample_directives();
+        let business = vec![Directive::Transaction(
+            Transaction::new(date(2024, 1, 15), "Lunch")
+                .with_flag('*')
+                .with_posting(Posting::new(
+                    "Expenses:Food:Coffee",
+                    Amount::new(dec!(12.00), "USD"),
+                ))
+                .with_posting(Posting::new(
+                    "Assets:Bank:Checking",
+                    Amount::new(dec!(-12.00), "USD"),
+                )),
+        )];
+        let mut executor =
+            Executor::with_sources([("personal", &personal), ("business", &business)]);
+
+        // Postings of both ledgers are visited in date order
+        let query =
+            parse("SELECT ledger, narration, balance WHERE account = \"Assets:Bank:Checking\"")
+                .unwrap();
+        let result = executor.execute(&query).unwrap();
+        let rows: Vec<_> = result
+            .rows
+            .iter()
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (
+                    Value::String("personal".into()),
+                    Value::String("Coffee".into())
+                ),
+                (
+                    Value::String("business".into()),
+                    Value::String("Lunch".into())
+                ),
+                (
+                    Value::String("personal".into()),
+                    Value::String("Groceries".into())
+                ),
+            ]
+        );
+        let Value::Inventory(balance) = &result.rows[2][2] else {
+            panic!("expected inventory, got {:?}", result.rows[2][2]);
+        };
+        assert_eq!(balance.units("USD"), dec!(-67.00));
+
+        // FROM filters by ledger too
+        let query = parse("SELECT narration FROM ledger = \"business\"").unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result.rows[0][0], Value::String("Lunch".into()));
+
+        // A single unnamed ledger has no name
+        let mut executor = Executor::new(&personal);
+        let query = parse("SELECT DISTINCT ledger").unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Null]]);
+    }
 }
@@ -0,0 +1,246 @@
+//! Conversion of directives to other plain-text accounting syntaxes.
+//!
+//! Backs the `EXPORT` statement. Transactions, account openings, commodity
+//! declarations and prices are converted; directives without an equivalent
+//! in the target syntax (balance assertions, pads, notes, events, ...) are
+//! skipped.
+//!
+//! ledger-cli keeps beancount's lot costs (`{...}`). hledger does not track
+//! lots, so costs are exported as transaction prices (`@`/`@@`), which keeps
+//! the transaction balanced.
+
+use std::fmt::Write;
+
+use rustledger_core::{
+    Amount, CostSpec, Directive, FormatConfig, IncompleteAmount, PriceAnnotation, Transaction,
+    format_directive,
+};
+
+use crate::ast::ExportFormat;
+
+/// Convert a directive to the given syntax.
+///
+/// Returns `None` for directives the target syntax cannot express.
+pub fn export_directive(directive: &Directive, format: ExportFormat) -> Option<String> {
+    if format == ExportFormat::Beancount {
+        return Some(format_directive(directive, &FormatConfig::default()));
+    }
+
+    match directive {
+        Directive::Transaction(txn) => Some(export_transaction(txn, format)),
+        Directive::Open(open) => Some(format!("account {}\n", open.account)),
+        Directive::Commodity(comm) => Some(format!("commodity {}\n", commodity(&comm.currency))),
+        Directive::Price(price) => Some(format!(
+            "P {} {} {}\n",
+            date(price.date, format),
+            commodity(&price.currency),
+            amount(&price.amount)
+        )),
+        _ => None,
+    }
+}
+
+/// Convert a transaction to ledger or hledger syntax.
+fn export_transaction(txn: &Transaction, format: ExportFormat) -> String {
+    let mut out = format!("{} {}", date(txn.date, format), txn.flag);
+
+    // hledger splits the description into `payee | note`; ledger treats the
+    // whole description as the payee, so the narration becomes a note.
+    let mut note = None;
+    match (&txn.payee, format) {
+        (Some(payee), ExportFormat::Hledger) => {
+            write!(out, " {payee} | {}", txn.narration).unwrap();
+        }
+        (Some(payee), _) => {
+            write!(out, " {payee}").unwrap();
+            if !txn.narration.is_empty() {
+                note = Some(&txn.narration);
+            }
+        }
+        (None, _) => write!(out, " {}", txn.narration).unwrap(),
+    }
+    out.push('\n');
+
+    if let Some(note) = note {
+        writeln!(out, "    ; {note}").unwrap();
+    }
+
+    if !txn.tags.is_empty() {
+        let tags: Vec<_> = txn.tags.iter().map(ToString::to_string).collect();
+        if format == ExportFormat::Hledger {
+            writeln!(out, "    ; {}:", tags.join(":, ")).unwrap();
+        } else {
+            writeln!(out, "    ; :{}:", tags.join(":")).unwrap();
+        }
+    }
+
+    for link in &txn.links {
+        writeln!(out, "    ; link: {link}").unwrap();
+    }
+
+    for posting in &txn.postings {
+        out.push_str("    ");
+        if let Some(flag) = posting.flag {
+            write!(out, "{flag} ").unwrap();
+        }
+        out.push_str(&posting.account);
+
+        let units = match &posting.units {
+            Some(IncompleteAmount::Complete(units)) => Some(amount(units)),
+            Some(IncompleteAmount::NumberOnly(number)) => Some(number.to_string()),
+            Some(IncompleteAmount::CurrencyOnly(_)) | None => None,
+        };
+        if let Some(units) = units {
+            write!(out, "  {units}").unwrap();
+
+            let cost = posting.cost.as_ref().and_then(|cost| match format {
+                ExportFormat::Hledger => cost_as_price(cost),
+                _ => lot_cost(cost, format),
+            });
+            if let Some(cost) = &cost {
+                write!(out, " {cost}").unwrap();
+            }
+
+            // hledger allows a single price per posting; the cost wins.
+            if cost.is_none() || format != ExportFormat::Hledger {
+                if let Some(price) = posting.price.as_ref().and_then(price) {
+                    write!(out, " {price}").unwrap();
+                }
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Format a lot cost in ledger syntax (`{per}`/`{{total}}` plus `[date]`).
+fn lot_cost(cost: &CostSpec, format: ExportFormat) -> Option<String> {
+    let currency = commodity(cost.currency.as_ref()?);
+    let mut out = if let Some(total) = cost.number_total {
+        format!("{{{{{total} {currency}}}}}")
+    } else {
+        format!("{{{} {currency}}}", cost.number_per?)
+    };
+    if let Some(lot_date) = cost.date {
+        write!(out, " [{}]", date(lot_date, format)).unwrap();
+    }
+    Some(out)
+}
+
+/// Format a lot cost as an hledger transaction price.
+fn cost_as_price(cost: &CostSpec) -> Option<String> {
+    let currency = commodity(cost.currency.as_ref()?);
+    if let Some(total) = cost.number_total {
+        Some(format!("@@ {total} {currency}"))
+    } else {
+        Some(format!("@ {} {currency}", cost.number_per?))
+    }
+}
+
+/// Format a price annotation; incomplete prices are dropped.
+fn price(price: &PriceAnnotation) -> Option<String> {
+    let marker = match price {
+        PriceAnnotation::Unit(_) | PriceAnnotation::UnitIncomplete(_) => "@",
+        _ => "@@",
+    };
+    price
+        .amount()
+        .map(|value| format!("{marker} {}", amount(value)))
+}
+
+/// Format an amount, quoting the commodity when needed.
+fn amount(value: &Amount) -> String {
+    format!("{} {}", value.number, commodity(&value.currency))
+}
+
+/// Quote commodities that ledger and hledger would not parse bare.
+fn commodity(currency: &str) -> String {
+    if currency.chars().all(char::is_alphabetic) {
+        currency.to_string()
+    } else {
+        format!("\"{currency}\"")
+    }
+}
+
+/// Format a date in the target syntax's conventional style.
+fn date(value: rustledger_core::NaiveDate, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Ledger => value.format("%Y/%m/%d").to_string(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{NaiveDate, Posting, Price};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn purchase() -> Directive {
+        Directive::Transaction(
+            Transaction::new(date(2024, 3, 1), "Buy shares")
+                .with_flag('*')
+                .with_payee("Broker")
+                .with_tag("invest")
+                .with_link("trade-1")
+                .with_posting(
+                    Posting::new("Assets:Broker", Amount::new(dec!(10), "VTI")).with_cost(
+                        CostSpec::empty()
+                            .with_number_per(dec!(200))
+                            .with_currency("USD"),
+                    ),
+                )
+                .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(-2000), "USD"))),
+        )
+    }
+
+    #[test]
+    fn test_export_ledger_transaction() {
+        let out = export_directive(&purchase(), ExportFormat::Ledger).unwrap();
+        assert_eq!(
+            out,
+            "2024/03/01 * Broker\n    ; Buy shares\n    ; :invest:\n    ; link: trade-1\n    Assets:Broker  10 VTI {200 USD}\n    Assets:Cash  -2000 USD\n"
+        );
+    }
+
+    #[test]
+    fn test_export_hledger_transaction() {
+        let out = export_directive(&purchase(), ExportFormat::Hledger).unwrap();
+        assert_eq!(
+            out,
+            "2024-03-01 * Broker | Buy shares\n    ; invest:\n    ; link: trade-1\n    Assets:Broker  10 VTI @ 200 USD\n    Assets:Cash  -2000 USD\n"
+        );
+    }
+
+    #[test]
+    fn test_export_price_and_unsupported() {
+        let price = Directive::Price(Price::new(
+            date(2024, 3, 2),
+            "VTI",
+            Amount::new(dec!(205.5), "USD"),
+        ));
+        assert_eq!(
+            export_directive(&price, ExportFormat::Ledger).unwrap(),
+            "P 2024/03/02 VTI 205.5 USD\n"
+        );
+
+        let note = Directive::Note(rustledger_core::Note::new(
+            date(2024, 3, 2),
+            "Assets:Cash",
+            "checked",
+        ));
+        assert!(export_directive(&note, ExportFormat::Hledger).is_none());
+        assert!(export_directive(&note, ExportFormat::Beancount).is_some());
+    }
+
+    #[test]
+    fn test_export_quotes_commodities() {
+        assert_eq!(commodity("USD"), "USD");
+        assert_eq!(commodity("VBR2"), "\"VBR2\"");
+    }
+}
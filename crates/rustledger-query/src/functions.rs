@@ -0,0 +1,56 @@
+//! User-defined scalar functions.
+//!
+//! Functions registered with [`Executor::register_function`] can be called
+//! from BQL like built-in ones, usually under a qualified name such as
+//! `myplugin.category(payee)`:
+//!
+//! ```
+//! use rustledger_core::{Directive, NaiveDate, Open};
+//! use rustledger_query::{Executor, QueryError, Value, parse};
+//!
+//! let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+//! let directives = vec![Directive::Open(Open::new(date, "Assets:Cash"))];
+//!
+//! let mut executor = Executor::new(&directives);
+//! executor.register_function("demo.shout", |args: &[Value]| match args {
+//!     [Value::String(s)] => Ok(Value::String(s.to_uppercase())),
+//!     _ => Err(QueryError::Evaluation("demo.shout expects a string".to_string())),
+//! });
+//! let query = parse("SELECT demo.shout(account)").unwrap();
+//! assert!(executor.execute(&query).unwrap().is_empty());
+//! ```
+//!
+//! Results are cached per distinct argument list for the duration of a
+//! query, so functions should be deterministic. For the target and
+//! `GROUP BY` expressions of a `SELECT`, the executor gathers the
+//! arguments of all rows first and makes a single [`ScalarFunction::call_batch`]
+//! call per function; calls in `WHERE` are made row by row.
+//!
+//! [`Executor::register_function`]: crate::Executor::register_function
+
+use crate::error::QueryError;
+use crate::executor::Value;
+
+/// A scalar function callable from BQL.
+pub trait ScalarFunction {
+    /// Evaluate the function on the arguments of one row.
+    fn call(&self, args: &[Value]) -> Result<Value, QueryError>;
+
+    /// Evaluate the function on the arguments of many rows at once,
+    /// returning one value per row.
+    ///
+    /// The default calls [`call`](Self::call) for each row; implementations
+    /// with a high per-call cost, such as WASM plugins, should override it.
+    fn call_batch(&self, rows: &[Vec<Value>]) -> Result<Vec<Value>, QueryError> {
+        rows.iter().map(|args| self.call(args)).collect()
+    }
+}
+
+impl<F> ScalarFunction for F
+where
+    F: Fn(&[Value]) -> Result<Value, QueryError>,
+{
+    fn call(&self, args: &[Value]) -> Result<Value, QueryError> {
+        self(args)
+    }
+}
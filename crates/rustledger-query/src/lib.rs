@@ -13,6 +13,7 @@
 //! - `JOURNAL` - Shorthand for account statements
 //! - `BALANCES` - Shorthand for account balance tables
 //! - `PRINT` - Output filtered transactions in Beancount syntax
+//! - `EXPORT` - Output filtered entries in Beancount, ledger or hledger syntax
 //!
 //! # Example
 //!
@@ -30,11 +31,15 @@ pub mod ast;
 pub mod completions;
 pub mod error;
 pub mod executor;
+pub mod export;
+pub mod functions;
 pub mod parser;
 pub mod price;
+pub mod returns;
 
 pub use ast::*;
 pub use error::{ParseError, QueryError};
-pub use executor::{Executor, QueryResult, Value};
+pub use executor::{Executor, QueryResult, QueryRows, Value};
+pub use functions::ScalarFunction;
 pub use parser::parse;
 pub use price::PriceDatabase;
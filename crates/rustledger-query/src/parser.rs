@@ -7,12 +7,19 @@ use rust_decimal::Decimal;
 use std::str::FromStr;
 
 use crate::ast::{
-    BalancesQuery, BinaryOperator, Expr, FromClause, FunctionCall, JournalQuery, Literal,
-    OrderSpec, PrintQuery, Query, SelectQuery, SortDirection, Target, UnaryOperator,
-    WindowFunction, WindowSpec,
+    BalancesQuery, BinaryOperator, ExportFormat, ExportQuery, Expr, FromClause, FunctionCall,
+    JournalQuery, Literal, OrderSpec, PrintQuery, Query, SelectQuery, SortDirection, Table, Target,
+    UnaryOperator, WindowFunction, WindowSpec,
 };
 use crate::error::{ParseError, ParseErrorKind};
-use rustledger_core::NaiveDate;
+use rustledger_core::{Amount, NaiveDate};
+
+/// Keywords that may follow a number in a query, which are never currencies.
+const KEYWORDS_AFTER_NUMBER: &[&str] = &[
+    "AND", "AS", "ASC", "AT", "BETWEEN", "CLEAR", "CLOSE", "DAY", "DAYS", "DESC", "FROM", "GROUP",
+    "HAVING", "IN", "LIMIT", "MONTH", "MONTHS", "NOT", "ON", "OPEN", "OR", "ORDER", "PIVOT",
+    "QUARTER", "QUARTERS", "TO", "WEEK", "WEEKS", "WHERE", "YEAR", "YEARS",
+];
 
 type ParserInput<'a> = &'a str;
 type ParserExtra<'a> = extra::Err<Rich<'a, char>>;
@@ -71,6 +78,7 @@ fn query_parser<'a>() -> impl Parser<'a, ParserInput<'a>, Query, ParserExtra<'a>
         journal_query().map(Query::Journal),
         balances_query().map(Query::Balances),
         print_query().map(Query::Print),
+        export_query().map(Query::Export),
     )))
     .then_ignore(ws())
     .then_ignore(just(';').or_not())
@@ -170,7 +178,15 @@ fn target<'a>() -> impl Parser<'a, ParserInput<'a>, Target, ParserExtra<'a>> + C
         .map(|(expr, alias)| Target { expr, alias })
 }
 
-/// Parse FROM modifiers (OPEN ON, CLOSE ON, CLEAR, filter).
+/// Parse a table name (`#postings` or `#transactions`).
+fn table<'a>() -> impl Parser<'a, ParserInput<'a>, Table, ParserExtra<'a>> + Clone {
+    just('#').ignore_then(choice((
+        kw("postings").to(Table::Postings),
+        kw("transactions").to(Table::Transactions),
+    )))
+}
+
+/// Parse FROM modifiers (table, OPEN ON, CLOSE ON, CLEAR, filter).
 fn from_modifiers<'a>() -> impl Parser<'a, ParserInput<'a>, FromClause, ParserExtra<'a>> + Clone {
     let open_on = kw("OPEN")
         .ignore_then(ws1())
@@ -187,18 +203,40 @@ fn from_modifiers<'a>() -> impl Parser<'a, ParserInput<'a>, FromClause, ParserEx
     let clear = kw("CLEAR").then_ignore(ws());
 
     // Parse modifiers in order: OPEN ON, CLOSE ON, CLEAR, filter
-    open_on
+    let modifiers = open_on
         .or_not()
         .then(close_on.or_not())
         .then(clear.or_not().map(|c| c.is_some()))
         .then(from_filter().or_not())
         .map(|(((open_on, close_on), clear), filter)| FromClause {
+            table: Table::Postings,
             open_on,
             close_on,
             clear,
             filter,
             subquery: None,
-        })
+        });
+
+    // A table name may come first; only take the whitespace after it if
+    // modifiers follow, so a WHERE clause can still start right after it
+    let clause = choice((
+        kw("WHERE"),
+        kw("GROUP"),
+        kw("HAVING"),
+        kw("PIVOT"),
+        kw("ORDER"),
+        kw("LIMIT"),
+    ));
+    let after_table = ws1()
+        .ignore_then(modifiers.clone().and_is(clause.not()))
+        .filter(|from: &FromClause| *from != FromClause::new())
+        .or_not()
+        .map(Option::unwrap_or_default);
+
+    table()
+        .then(after_table)
+        .map(|(table, from)| from.table(table))
+        .or(modifiers)
 }
 
 /// Parse FROM filter expression (predicates).
@@ -342,6 +380,28 @@ fn print_query<'a>() -> impl Parser<'a, ParserInput<'a>, PrintQuery, ParserExtra
         .map(|from| PrintQuery { from })
 }
 
+/// Parse EXPORT query (e.g., `EXPORT TO LEDGER FROM year = 2024`).
+fn export_query<'a>() -> impl Parser<'a, ParserInput<'a>, ExportQuery, ParserExtra<'a>> + Clone {
+    let format = choice((
+        kw("BEANCOUNT").to(ExportFormat::Beancount),
+        kw("LEDGER").to(ExportFormat::Ledger),
+        kw("HLEDGER").to(ExportFormat::Hledger),
+    ));
+
+    kw("EXPORT")
+        .ignore_then(ws1())
+        .ignore_then(kw("TO").then_ignore(ws1()).or_not())
+        .ignore_then(format)
+        .then(
+            ws1()
+                .ignore_then(kw("FROM"))
+                .ignore_then(ws1())
+                .ignore_then(from_modifiers())
+                .or_not(),
+        )
+        .map(|(format, from)| ExportQuery { format, from })
+}
+
 /// Parse AT function (e.g., AT cost, AT units).
 fn at_function<'a>() -> impl Parser<'a, ParserInput<'a>, String, ParserExtra<'a>> + Clone {
     ws1()
@@ -393,21 +453,36 @@ fn expr<'a>() -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone
             |left, (op, right)| Expr::binary(left, op, right),
         );
 
-        // Comparison: = != < <= > >= ~ IN
+        // Comparison: = != < <= > >= ~ IN, BETWEEN, and IN over a relative
+        // date range. Each form yields the bounds the left operand is
+        // compared against, joined with AND.
+        let between = ws1()
+            .ignore_then(kw("BETWEEN"))
+            .ignore_then(ws1())
+            .ignore_then(additive.clone())
+            .then_ignore(ws1())
+            .then_ignore(kw("AND"))
+            .then_ignore(ws1())
+            .then(additive.clone())
+            .map(|(low, high)| vec![(BinaryOperator::Ge, low), (BinaryOperator::Le, high)]);
+        let in_range = ws1()
+            .ignore_then(kw("IN"))
+            .ignore_then(ws1())
+            .ignore_then(date_range());
+        let compare = ws()
+            .ignore_then(comparison_op())
+            .then_ignore(ws())
+            .then(additive.clone())
+            .map(|bound| vec![bound]);
         let comparison = additive
-            .clone()
-            .then(
-                ws().ignore_then(comparison_op())
-                    .then_ignore(ws())
-                    .then(additive)
-                    .or_not(),
-            )
-            .map(|(left, rest)| {
-                if let Some((op, right)) = rest {
-                    Expr::binary(left, op, right)
-                } else {
-                    left
-                }
+            .then(choice((between, in_range, compare)).or_not())
+            .map(|(left, bounds)| {
+                bounds
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(op, bound)| Expr::binary(left.clone(), op, bound))
+                    .reduce(|acc, cond| Expr::binary(acc, BinaryOperator::And, cond))
+                    .unwrap_or(left)
             });
 
         // NOT
@@ -458,6 +533,66 @@ fn comparison_op<'a>() -> impl Parser<'a, ParserInput<'a>, BinaryOperator, Parse
     ))
 }
 
+/// Parse a relative date range, as the bounds a date is compared against.
+///
+/// - `LAST n DAYS|WEEKS|MONTHS|YEARS`: the `n` units up to and including today
+/// - `THIS DAY|WEEK|MONTH|QUARTER|YEAR`: the current calendar period
+/// - `LAST DAY|WEEK|MONTH|QUARTER|YEAR`: the previous calendar period
+///
+/// Bounds are built from `TODAY()`, `DATE_TRUNC` and `DATE_ADD`, so they
+/// are resolved when the query runs.
+fn date_range<'a>()
+-> impl Parser<'a, ParserInput<'a>, Vec<(BinaryOperator, Expr)>, ParserExtra<'a>> + Clone {
+    let today = || Expr::function("TODAY", vec![]);
+    let trunc = move |unit: &str| Expr::function("DATE_TRUNC", vec![Expr::string(unit), today()]);
+    let add = |date: Expr, n: i64, unit: &str| {
+        Expr::function("DATE_ADD", vec![date, Expr::integer(n), Expr::string(unit)])
+    };
+
+    let last_n = kw("LAST")
+        .ignore_then(ws1())
+        .ignore_then(integer())
+        .then_ignore(ws1())
+        .then(date_unit())
+        .map(move |(n, unit)| {
+            vec![
+                (BinaryOperator::Gt, add(today(), -n, unit)),
+                (BinaryOperator::Le, today()),
+            ]
+        });
+    let last = kw("LAST")
+        .ignore_then(ws1())
+        .ignore_then(date_unit())
+        .map(move |unit| {
+            vec![
+                (BinaryOperator::Ge, add(trunc(unit), -1, unit)),
+                (BinaryOperator::Lt, trunc(unit)),
+            ]
+        });
+    let this = kw("THIS")
+        .ignore_then(ws1())
+        .ignore_then(date_unit())
+        .map(move |unit| {
+            vec![
+                (BinaryOperator::Ge, trunc(unit)),
+                (BinaryOperator::Lt, add(trunc(unit), 1, unit)),
+            ]
+        });
+
+    choice((last_n, last, this))
+}
+
+/// Parse a calendar unit for relative date ranges.
+fn date_unit<'a>() -> impl Parser<'a, ParserInput<'a>, &'static str, ParserExtra<'a>> + Clone {
+    choice((
+        kw("DAYS").or(kw("DAY")).to("day"),
+        kw("WEEKS").or(kw("WEEK")).to("week"),
+        kw("MONTHS").or(kw("MONTH")).to("month"),
+        kw("QUARTERS").or(kw("QUARTER")).to("quarter"),
+        kw("YEARS").or(kw("YEAR")).to("year"),
+    ))
+}
+
 /// Parse primary expressions.
 fn primary_expr<'a>(
     expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone + 'a,
@@ -466,12 +601,12 @@ fn primary_expr<'a>(
         // Parenthesized expression
         just('(')
             .ignore_then(ws())
-            .ignore_then(expr)
+            .ignore_then(expr.clone())
             .then_ignore(ws())
             .then_ignore(just(')'))
             .map(|e| Expr::Paren(Box::new(e))),
         // Function call or column reference (must come before wildcard check)
-        function_call_or_column(),
+        function_call_or_column(expr),
         // Literals
         literal().map(Expr::Literal),
         // Wildcard (fallback if nothing else matched)
@@ -480,13 +615,24 @@ fn primary_expr<'a>(
 }
 
 /// Parse function call, window function, or column reference.
-fn function_call_or_column<'a>() -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone
-{
-    identifier()
+///
+/// Function names may be qualified with a dot, as in
+/// `myplugin.category(payee)`, to call user-defined functions.
+fn function_call_or_column<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone + 'a,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    let qualified_function = identifier()
+        .then_ignore(just('.'))
+        .then(identifier())
+        .then_ignore(ws().then(just('(')).rewind())
+        .map(|(namespace, name)| format!("{namespace}.{name}"));
+
+    qualified_function
+        .or(identifier())
         .then(
             ws().ignore_then(just('('))
                 .ignore_then(ws())
-                .ignore_then(function_args())
+                .ignore_then(function_args(expr))
                 .then_ignore(ws())
                 .then_ignore(just(')'))
                 .or_not(),
@@ -574,10 +720,14 @@ fn window_order_spec<'a>() -> impl Parser<'a, ParserInput<'a>, OrderSpec, Parser
 }
 
 /// Parse function arguments.
-fn function_args<'a>() -> impl Parser<'a, ParserInput<'a>, Vec<Expr>, ParserExtra<'a>> + Clone {
-    // Allow empty args or comma-separated expressions
-    // Simple version: only allow columns and wildcards as function args (not full expressions)
-    simple_arg()
+fn function_args<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone + 'a,
+) -> impl Parser<'a, ParserInput<'a>, Vec<Expr>, ParserExtra<'a>> + Clone {
+    // Allow empty args or comma-separated expressions, including nested
+    // function calls such as `CONVERT(SUM(position), "USD")`
+    just('*')
+        .to(Expr::Wildcard)
+        .or(expr)
         .separated_by(ws().then(just(',')).then(ws()))
         .collect()
 }
@@ -600,6 +750,11 @@ fn literal<'a>() -> impl Parser<'a, ParserInput<'a>, Literal, ParserExtra<'a>> +
         kw("NULL").to(Literal::Null),
         // Date literal (must be before number to avoid parsing year as number)
         date_literal().map(Literal::Date),
+        // Amount (must be before number so the currency is not left over)
+        decimal()
+            .then_ignore(ws1())
+            .then(currency())
+            .map(|(number, currency)| Literal::Amount(Amount::new(number, currency))),
         // Number
         decimal().map(Literal::Number),
         // String
@@ -612,6 +767,25 @@ fn identifier<'a>() -> impl Parser<'a, ParserInput<'a>, String, ParserExtra<'a>>
     text::ident().map(|s: &str| s.to_string())
 }
 
+/// Parse a currency, as in the `USD` of `1000 USD`.
+///
+/// Uppercase keywords are not currencies, so `HAVING cnt > 2 ORDER BY cnt`
+/// still parses.
+fn currency<'a>() -> impl Parser<'a, ParserInput<'a>, String, ParserExtra<'a>> + Clone {
+    any()
+        .filter(char::is_ascii_uppercase)
+        .then(
+            any()
+                .filter(|c: &char| {
+                    c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(*c)
+                })
+                .repeated(),
+        )
+        .to_slice()
+        .filter(|currency: &&str| !KEYWORDS_AFTER_NUMBER.contains(currency))
+        .map(str::to_string)
+}
+
 /// Parse a string literal.
 fn string_literal<'a>() -> impl Parser<'a, ParserInput<'a>, String, ParserExtra<'a>> + Clone {
     // Double-quoted string
@@ -813,6 +987,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_table() {
+        let query = parse("SELECT date, id FROM #transactions WHERE year = 2024").unwrap();
+        match query {
+            Query::Select(sel) => {
+                let from = sel.from.unwrap();
+                assert_eq!(from.table, Table::Transactions);
+                assert!(from.filter.is_none());
+                assert!(sel.where_clause.is_some());
+            }
+            _ => panic!("Expected SELECT query"),
+        }
+
+        let query = parse("SELECT * FROM #transactions CLOSE ON 2024-12-31").unwrap();
+        match query {
+            Query::Select(sel) => {
+                let from = sel.from.unwrap();
+                assert_eq!(from.table, Table::Transactions);
+                assert!(from.close_on.is_some());
+            }
+            _ => panic!("Expected SELECT query"),
+        }
+
+        let query = parse("SELECT * FROM #postings year = 2024").unwrap();
+        match query {
+            Query::Select(sel) => {
+                let from = sel.from.unwrap();
+                assert_eq!(from.table, Table::Postings);
+                assert!(from.filter.is_some());
+            }
+            _ => panic!("Expected SELECT query"),
+        }
+
+        // Without a table name, rows are postings
+        let query = parse("SELECT * FROM year = 2024").unwrap();
+        match query {
+            Query::Select(sel) => assert_eq!(sel.from.unwrap().table, Table::Postings),
+            _ => panic!("Expected SELECT query"),
+        }
+
+        assert!(parse("SELECT * FROM #entries").is_err());
+    }
+
     #[test]
     fn test_journal_query() {
         let query = parse("JOURNAL \"Assets:Bank\" AT cost").unwrap();
@@ -843,6 +1060,29 @@ mod tests {
         assert!(matches!(query, Query::Print(_)));
     }
 
+    #[test]
+    fn test_export_query() {
+        let query = parse("EXPORT TO LEDGER FROM year = 2024").unwrap();
+        match query {
+            Query::Export(e) => {
+                assert_eq!(e.format, ExportFormat::Ledger);
+                assert!(e.from.is_some());
+            }
+            _ => panic!("Expected EXPORT query"),
+        }
+
+        let query = parse("EXPORT HLEDGER").unwrap();
+        assert!(matches!(
+            query,
+            Query::Export(ExportQuery {
+                format: ExportFormat::Hledger,
+                from: None
+            })
+        ));
+
+        assert!(parse("EXPORT TO CSV").is_err());
+    }
+
     #[test]
     fn test_complex_expression() {
         let query = parse("SELECT * WHERE date >= 2024-01-01 AND account ~ \"Expenses:\"").unwrap();
@@ -874,6 +1114,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_amount_literal() {
+        let query = parse(
+            "SELECT account, SUM(position) GROUP BY account \
+             HAVING SUM(position) > 1000 USD AND COUNT(*) > 2 ORDER BY account",
+        )
+        .unwrap();
+        match query {
+            Query::Select(sel) => match sel.having.unwrap() {
+                Expr::BinaryOp(op) => match (op.left, op.right) {
+                    (Expr::BinaryOp(amount), Expr::BinaryOp(count)) => {
+                        assert_eq!(
+                            amount.right,
+                            Expr::Literal(Literal::Amount(Amount::new(dec!(1000), "USD")))
+                        );
+                        assert_eq!(count.right, Expr::Literal(Literal::Number(dec!(2))));
+                    }
+                    _ => panic!("Expected comparisons"),
+                },
+                _ => panic!("Expected binary op"),
+            },
+            _ => panic!("Expected SELECT query"),
+        }
+    }
+
     #[test]
     fn test_semicolon_optional() {
         assert!(parse("SELECT *").is_ok());
@@ -931,6 +1196,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_qualified_function_call() {
+        let query = parse("SELECT myplugin.category(payee), account").unwrap();
+        let Query::Select(sel) = query else {
+            panic!("Expected SELECT query");
+        };
+        match &sel.targets[0].expr {
+            Expr::Function(f) => {
+                assert_eq!(f.name, "myplugin.category");
+                assert!(matches!(&f.args[..], [Expr::Column(c)] if c == "payee"));
+            }
+            other => panic!("Expected function, got {other:?}"),
+        }
+        assert!(matches!(&sel.targets[1].expr, Expr::Column(c) if c == "account"));
+    }
+
     #[test]
     fn test_nested_subquery() {
         // Two levels of nesting
@@ -945,4 +1226,54 @@ mod tests {
             _ => panic!("Expected SELECT query"),
         }
     }
+
+    #[test]
+    fn test_between_and_date_ranges() {
+        let where_of = |q: &str| match parse(q).unwrap() {
+            Query::Select(sel) => sel.where_clause.unwrap(),
+            _ => panic!("Expected SELECT query"),
+        };
+
+        let expr =
+            where_of("SELECT date WHERE date BETWEEN 2024-01-01 AND 2024-01-31 AND year = 2024");
+        let Expr::BinaryOp(and) = expr else {
+            panic!("Expected AND");
+        };
+        assert_eq!(and.op, BinaryOperator::And);
+        let Expr::BinaryOp(between) = &and.left else {
+            panic!("Expected BETWEEN bounds");
+        };
+        assert_eq!(between.op, BinaryOperator::And);
+        assert!(matches!(&between.left, Expr::BinaryOp(b) if b.op == BinaryOperator::Ge));
+        assert!(matches!(&between.right, Expr::BinaryOp(b) if b.op == BinaryOperator::Le));
+
+        let expr = where_of("SELECT date WHERE date + 7 > 2024-01-01");
+        let Expr::BinaryOp(cmp) = expr else {
+            panic!("Expected comparison");
+        };
+        assert!(matches!(&cmp.left, Expr::BinaryOp(b) if b.op == BinaryOperator::Add));
+
+        let expr = where_of("SELECT date WHERE date IN LAST 90 DAYS");
+        let Expr::BinaryOp(range) = expr else {
+            panic!("Expected range");
+        };
+        match &range.left {
+            Expr::BinaryOp(low) => {
+                assert_eq!(low.op, BinaryOperator::Gt);
+                assert!(matches!(
+                    &low.right,
+                    Expr::Function(f) if f.name == "DATE_ADD" && f.args[1] == Expr::integer(-90)
+                ));
+            }
+            other => panic!("Expected lower bound, got {other:?}"),
+        }
+
+        for range in ["THIS MONTH", "LAST YEAR", "LAST 2 WEEKS", "THIS QUARTER"] {
+            assert!(
+                parse(&format!("SELECT date WHERE date IN {range}")).is_ok(),
+                "{range}"
+            );
+        }
+        assert!(parse("SELECT date WHERE date IN LAST 3 FORTNIGHTS").is_err());
+    }
 }
@@ -38,7 +38,7 @@ impl PriceDatabase {
     }
 
     /// Build a price database from directives.
-    pub fn from_directives(directives: &[Directive]) -> Self {
+    pub fn from_directives<'a>(directives: impl IntoIterator<Item = &'a Directive>) -> Self {
         let mut db = Self::new();
 
         for directive in directives {
@@ -210,6 +210,13 @@ impl PriceDatabase {
             .map(|price| Amount::new(amount.number * price, to_currency))
     }
 
+    /// Get all price entries for a base currency, sorted by date.
+    ///
+    /// Entries may be quoted in different currencies.
+    pub fn prices_for(&self, base: &str) -> &[PriceEntry] {
+        self.prices.get(base).map_or(&[], Vec::as_slice)
+    }
+
     /// Get all currencies that have prices defined.
     pub fn currencies(&self) -> impl Iterator<Item = &str> {
         self.prices.keys().map(InternedStr::as_str)
@@ -286,6 +293,28 @@ mod tests {
         assert_eq!(db.get_price("AAPL", "USD", date(2023, 12, 31)), None);
     }
 
+    #[test]
+    fn test_prices_for() {
+        let price = |d, number| {
+            Directive::Price(PriceDirective {
+                date: d,
+                currency: "AAPL".into(),
+                amount: Amount::new(number, "USD"),
+                meta: Default::default(),
+            })
+        };
+        let db = PriceDatabase::from_directives(&[
+            price(date(2024, 6, 1), dec!(180.00)),
+            price(date(2024, 1, 1), dec!(150.00)),
+        ]);
+
+        let entries = db.prices_for("AAPL");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].date, date(2024, 1, 1));
+        assert_eq!(entries[1].price, dec!(180.00));
+        assert!(db.prices_for("MSFT").is_empty());
+    }
+
     #[test]
     fn test_inverse_price() {
         let mut db = PriceDatabase::new();
@@ -0,0 +1,392 @@
+//! Portfolio return calculations.
+//!
+//! Provides time-weighted (TWRR) and money-weighted (XIRR) returns for an
+//! account subtree, valued in a single currency using the [`PriceDatabase`].
+//!
+//! # Cash flows
+//!
+//! A transaction is an external cash flow when it moves value between the
+//! portfolio (the account and its descendants) and another balance sheet
+//! account (`Assets`, `Liabilities` or `Equity`). Income and expense
+//! counterparts, such as dividends or fees, are treated as part of the
+//! portfolio's return rather than as contributions.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rustledger_core::{Directive, MixedAmount, NaiveDate, Posting, Transaction};
+
+use crate::price::PriceDatabase;
+
+/// A dated cash flow into (positive) or out of (negative) a portfolio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CashFlow {
+    /// Date of the flow.
+    pub date: NaiveDate,
+    /// Amount in the valuation currency.
+    pub amount: Decimal,
+}
+
+/// Return calculator for an account subtree.
+pub struct ReturnCalculator<'a> {
+    /// Transactions touching the portfolio, sorted by date.
+    transactions: Vec<&'a Transaction>,
+    price_db: &'a PriceDatabase,
+    account: &'a str,
+    currency: &'a str,
+}
+
+impl<'a> ReturnCalculator<'a> {
+    /// Create a calculator for `account` (and its descendants) valued in `currency`.
+    pub fn new(
+        directives: impl IntoIterator<Item = &'a Directive>,
+        price_db: &'a PriceDatabase,
+        account: &'a str,
+        currency: &'a str,
+    ) -> Self {
+        let mut transactions: Vec<&Transaction> = directives
+            .into_iter()
+            .filter_map(|d| match d {
+                Directive::Transaction(txn) => Some(txn),
+                _ => None,
+            })
+            .filter(|txn| txn.postings.iter().any(|p| in_subtree(&p.account, account)))
+            .collect();
+        transactions.sort_by_key(|txn| txn.date);
+
+        Self {
+            transactions,
+            price_db,
+            account,
+            currency,
+        }
+    }
+
+    /// External cash flows, one per date, in chronological order.
+    pub fn cash_flows(&self) -> Vec<CashFlow> {
+        let mut by_date: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+
+        for txn in &self.transactions {
+            let is_external = txn.postings.iter().any(|p| {
+                !in_subtree(&p.account, self.account) && is_balance_sheet_account(&p.account)
+            });
+            if !is_external {
+                continue;
+            }
+
+            let flow: Decimal = txn
+                .postings
+                .iter()
+                .filter(|p| in_subtree(&p.account, self.account))
+                .filter_map(|p| self.posting_value(p, txn.date))
+                .sum();
+
+            if !flow.is_zero() {
+                *by_date.entry(txn.date).or_default() += flow;
+            }
+        }
+
+        by_date
+            .into_iter()
+            .map(|(date, amount)| CashFlow { date, amount })
+            .collect()
+    }
+
+    /// Market value of the portfolio at the end of `date`.
+    pub fn market_value(&self, date: NaiveDate) -> Decimal {
+        let mut units = MixedAmount::new();
+        let mut last_price: HashMap<&str, Decimal> = HashMap::new();
+
+        for txn in self.transactions.iter().take_while(|txn| txn.date <= date) {
+            for posting in &txn.postings {
+                if !in_subtree(&posting.account, self.account) {
+                    continue;
+                }
+                let Some(amount) = posting.amount() else {
+                    continue;
+                };
+                units += amount;
+                if let Some(price) = self.annotated_unit_price(posting) {
+                    last_price.insert(amount.currency.as_ref(), price);
+                }
+            }
+        }
+
+        units
+            .iter()
+            .filter_map(|(currency, number)| {
+                let price = self
+                    .price_db
+                    .get_price(currency.as_str(), self.currency, date)
+                    .or_else(|| last_price.get(currency.as_str()).copied())?;
+                Some(number * price)
+            })
+            .sum()
+    }
+
+    /// Time-weighted rate of return between `from` and `to` (inclusive).
+    ///
+    /// Chains the holding-period returns between consecutive cash flows.
+    /// Returns `None` when the portfolio holds no value during the period.
+    pub fn twrr(&self, from: NaiveDate, to: NaiveDate) -> Option<Decimal> {
+        let day_before = from.pred_opt()?;
+        let mut start_value = self.market_value(day_before);
+        let mut growth = Decimal::ONE;
+        let mut has_period = false;
+
+        for flow in self
+            .cash_flows()
+            .into_iter()
+            .filter(|f| f.date >= from && f.date <= to)
+        {
+            let end_value = self.market_value(flow.date);
+            if !start_value.is_zero() {
+                growth *= (end_value - flow.amount) / start_value;
+                has_period = true;
+            }
+            start_value = end_value;
+        }
+
+        if !start_value.is_zero() {
+            growth *= self.market_value(to) / start_value;
+            has_period = true;
+        }
+
+        has_period.then(|| growth - Decimal::ONE)
+    }
+
+    /// Money-weighted annualized rate of return (XIRR) up to `as_of`.
+    ///
+    /// The portfolio's market value at `as_of` is treated as a final
+    /// withdrawal. Returns `None` if no rate can be solved for.
+    pub fn xirr(&self, as_of: NaiveDate) -> Option<Decimal> {
+        // Investor perspective: contributions are outflows.
+        let mut flows: Vec<CashFlow> = self
+            .cash_flows()
+            .into_iter()
+            .filter(|f| f.date <= as_of)
+            .map(|f| CashFlow {
+                date: f.date,
+                amount: -f.amount,
+            })
+            .collect();
+        flows.push(CashFlow {
+            date: as_of,
+            amount: self.market_value(as_of),
+        });
+        xirr(&flows)
+    }
+
+    /// Value of a posting's units in the valuation currency on `date`.
+    fn posting_value(&self, posting: &Posting, date: NaiveDate) -> Option<Decimal> {
+        let amount = posting.amount()?;
+        let price = self
+            .price_db
+            .get_price(&amount.currency, self.currency, date)
+            .or_else(|| self.annotated_unit_price(posting))?;
+        Some(amount.number * price)
+    }
+
+    /// Per-unit price from the posting's price annotation or cost, if it is
+    /// in the valuation currency.
+    fn annotated_unit_price(&self, posting: &Posting) -> Option<Decimal> {
+        let units = posting.amount()?;
+
+        if let Some(price) = &posting.price {
+            if let Some(amount) = price.amount() {
+                if amount.currency.as_ref() == self.currency {
+                    return if price.is_unit() {
+                        Some(amount.number)
+                    } else if units.number.is_zero() {
+                        None
+                    } else {
+                        Some(amount.number / units.number.abs())
+                    };
+                }
+            }
+        }
+
+        let cost = posting.cost.as_ref()?;
+        if cost.currency.as_deref() != Some(self.currency) {
+            return None;
+        }
+        cost.number_per.or_else(|| {
+            let total = cost.number_total?;
+            (!units.number.is_zero()).then(|| total / units.number.abs())
+        })
+    }
+}
+
+/// Solve for the annualized internal rate of return of dated cash flows.
+///
+/// Uses bisection on the net present value, which is robust for the usual
+/// case of a single sign change. Returns `None` if the flows do not contain
+/// both positive and negative amounts or no root is bracketed.
+pub fn xirr(flows: &[CashFlow]) -> Option<Decimal> {
+    let first = flows.iter().map(|f| f.date).min()?;
+    let points: Vec<(f64, f64)> = flows
+        .iter()
+        .map(|f| {
+            let years = (f.date - first).num_days() as f64 / 365.0;
+            (years, f.amount.to_f64().unwrap_or(0.0))
+        })
+        .collect();
+
+    if !points.iter().any(|(_, a)| *a > 0.0) || !points.iter().any(|(_, a)| *a < 0.0) {
+        return None;
+    }
+
+    let npv = |rate: f64| -> f64 {
+        points
+            .iter()
+            .map(|(years, amount)| amount / (1.0 + rate).powf(*years))
+            .sum()
+    };
+
+    let (mut low, mut high) = (-0.999_999, 100.0);
+    let (mut npv_low, npv_high) = (npv(low), npv(high));
+    if npv_low.signum() == npv_high.signum() {
+        return None;
+    }
+
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        let npv_mid = npv(mid);
+        if npv_mid.abs() < 1e-9 || (high - low) < 1e-12 {
+            low = mid;
+            break;
+        }
+        if npv_mid.signum() == npv_low.signum() {
+            low = mid;
+            npv_low = npv_mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Decimal::from_f64(low).map(|rate| rate.round_dp(8))
+}
+
+/// Check whether `account` is `root` or one of its descendants.
+fn in_subtree(account: &str, root: &str) -> bool {
+    account
+        .strip_prefix(root)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+}
+
+/// Check whether an account belongs to the balance sheet.
+fn is_balance_sheet_account(account: &str) -> bool {
+    ["Assets", "Liabilities", "Equity"]
+        .iter()
+        .any(|root| in_subtree(account, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{Amount, CostSpec, Price};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn buy(d: NaiveDate, shares: Decimal, cost: Decimal) -> Directive {
+        Directive::Transaction(
+            Transaction::new(d, "Buy")
+                .with_posting(
+                    Posting::new("Assets:Invest:Stock", Amount::new(shares, "STK"))
+                        .with_cost(CostSpec::empty().with_number_per(cost).with_currency("USD")),
+                )
+                .with_posting(Posting::new(
+                    "Assets:Bank",
+                    Amount::new(-shares * cost, "USD"),
+                )),
+        )
+    }
+
+    fn price(d: NaiveDate, number: Decimal) -> Directive {
+        Directive::Price(Price::new(d, "STK", Amount::new(number, "USD")))
+    }
+
+    fn ledger() -> Vec<Directive> {
+        vec![
+            buy(date(2024, 1, 1), dec!(10), dec!(100)),
+            price(date(2024, 1, 1), dec!(100)),
+            price(date(2024, 6, 30), dec!(110)),
+            buy(date(2024, 7, 1), dec!(10), dec!(110)),
+            price(date(2024, 12, 31), dec!(121)),
+        ]
+    }
+
+    #[test]
+    fn test_in_subtree() {
+        assert!(in_subtree("Assets:Invest", "Assets:Invest"));
+        assert!(in_subtree("Assets:Invest:Stock", "Assets:Invest"));
+        assert!(!in_subtree("Assets:Investments", "Assets:Invest"));
+    }
+
+    #[test]
+    fn test_cash_flows_and_market_value() {
+        let directives = ledger();
+        let price_db = PriceDatabase::from_directives(&directives);
+        let calc = ReturnCalculator::new(&directives, &price_db, "Assets:Invest", "USD");
+
+        let flows = calc.cash_flows();
+        assert_eq!(flows.len(), 2);
+        assert_eq!(flows[0].amount, dec!(1000));
+        assert_eq!(flows[1].amount, dec!(1100));
+
+        assert_eq!(calc.market_value(date(2024, 12, 31)), dec!(2420));
+    }
+
+    #[test]
+    fn test_twrr() {
+        let directives = ledger();
+        let price_db = PriceDatabase::from_directives(&directives);
+        let calc = ReturnCalculator::new(&directives, &price_db, "Assets:Invest", "USD");
+
+        // The share price grew 100 -> 121 regardless of contributions: 21%.
+        let twrr = calc.twrr(date(2024, 1, 1), date(2024, 12, 31)).unwrap();
+        assert_eq!(twrr.round_dp(6), dec!(0.21));
+    }
+
+    #[test]
+    fn test_xirr_simple_doubling() {
+        let flows = [
+            CashFlow {
+                date: date(2023, 1, 1),
+                amount: dec!(-1000),
+            },
+            CashFlow {
+                date: date(2024, 1, 1),
+                amount: dec!(1100),
+            },
+        ];
+        let rate = xirr(&flows).unwrap();
+        assert_eq!(rate.round_dp(4), dec!(0.1));
+    }
+
+    #[test]
+    fn test_xirr_requires_sign_change() {
+        let flows = [CashFlow {
+            date: date(2024, 1, 1),
+            amount: dec!(100),
+        }];
+        assert!(xirr(&flows).is_none());
+    }
+
+    #[test]
+    fn test_xirr_account() {
+        let directives = ledger();
+        let price_db = PriceDatabase::from_directives(&directives);
+        let calc = ReturnCalculator::new(&directives, &price_db, "Assets:Invest", "USD");
+
+        let rate = calc.xirr(date(2024, 12, 31)).unwrap();
+        assert!(
+            rate > dec!(0.2) && rate < dec!(0.3),
+            "unexpected rate {rate}"
+        );
+    }
+}
@@ -3,7 +3,9 @@
 //! Tests cover parsing, execution, aggregation, filtering, and real-world query scenarios.
 
 use rust_decimal_macros::dec;
-use rustledger_core::{Amount, Directive, NaiveDate, Open, Posting, Transaction};
+use rustledger_core::{
+    Amount, Directive, Inventory, NaiveDate, Open, Position, Posting, Transaction,
+};
 use rustledger_query::{Executor, QueryResult, Value, parse};
 
 // ============================================================================
@@ -646,6 +648,46 @@ fn test_having_filters_all() {
     );
 }
 
+#[test]
+fn test_having_amount_threshold() {
+    let directives = make_test_directives();
+    let result = execute_query(
+        r"SELECT account, SUM(position) GROUP BY account HAVING SUM(position) > 1000 USD",
+        &directives,
+    );
+    let accounts: Vec<_> = result.rows.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(
+        accounts,
+        vec![Value::String("Assets:Bank:Checking".to_string())]
+    );
+
+    let result = execute_query(
+        r"SELECT account, SUM(position) AS total GROUP BY account
+          HAVING total >= 1000 USD AND total < 5000.00 USD ORDER BY account",
+        &directives,
+    );
+    let accounts: Vec<_> = result.rows.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(
+        accounts,
+        vec![
+            Value::String("Assets:Bank:Checking".to_string()),
+            Value::String("Assets:Bank:Savings".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_having_amount_currency_mismatch() {
+    let directives = make_test_directives();
+    let query = parse(r"SELECT account, COUNT(*) GROUP BY account HAVING FIRST(position) > 10 EUR")
+        .expect("should parse");
+    let mut executor = Executor::new(&directives);
+    let err = executor
+        .execute(&query)
+        .expect_err("USD and EUR do not compare");
+    assert!(err.to_string().contains("currency mismatch"), "{err}");
+}
+
 // ============================================================================
 // PIVOT BY Tests
 // ============================================================================
@@ -658,6 +700,183 @@ fn test_parse_pivot_by() {
     assert!(matches!(query, rustledger_query::Query::Select(_)));
 }
 
+fn make_monthly_directives() -> Vec<Directive> {
+    let spend = |d: NaiveDate, account: &str, amount| {
+        Directive::Transaction(
+            Transaction::new(d, "Spend")
+                .with_posting(Posting::new(account, Amount::new(amount, "USD")))
+                .with_posting(Posting::new(
+                    "Assets:Bank:Checking",
+                    Amount::new(-amount, "USD"),
+                )),
+        )
+    };
+    vec![
+        spend(date(2024, 1, 5), "Expenses:Rent", dec!(1000)),
+        spend(date(2024, 1, 20), "Expenses:Food", dec!(150)),
+        spend(date(2024, 2, 5), "Expenses:Rent", dec!(1000)),
+        spend(date(2024, 3, 5), "Expenses:Rent", dec!(1000)),
+        spend(date(2024, 3, 12), "Expenses:Food", dec!(80)),
+        spend(date(2024, 3, 18), "Expenses:Food", dec!(20)),
+    ]
+}
+
+fn cell_number(value: &Value) -> Option<rust_decimal::Decimal> {
+    match value {
+        Value::Inventory(inv) => inv.positions().first().map(|p| p.units.number),
+        Value::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_execute_pivot_by_expression() {
+    let directives = make_monthly_directives();
+    let result = execute_query(
+        r#"SELECT account, MONTH(date), SUM(position) WHERE account ~ "Expenses:"
+           GROUP BY 1, 2 PIVOT BY MONTH(date) ORDER BY account"#,
+        &directives,
+    );
+
+    // One row per account, one column per month
+    assert_eq!(result.columns, vec!["account", "1", "2", "3"]);
+    assert_eq!(result.len(), 2);
+
+    let food = &result.rows[0];
+    assert_eq!(food[0], Value::String("Expenses:Food".to_string()));
+    assert_eq!(cell_number(&food[1]), Some(dec!(150)));
+    assert_eq!(food[2], Value::Null);
+    assert_eq!(cell_number(&food[3]), Some(dec!(100)));
+
+    let rent = &result.rows[1];
+    assert_eq!(rent[0], Value::String("Expenses:Rent".to_string()));
+    for cell in &rent[1..] {
+        assert_eq!(cell_number(cell), Some(dec!(1000)));
+    }
+}
+
+#[test]
+fn test_execute_pivot_by_with_value_column() {
+    let directives = make_monthly_directives();
+    let result = execute_query(
+        r#"SELECT account, YEAR(date) AS year, COUNT(*) AS n, SUM(position) AS total
+           WHERE account ~ "Expenses:" GROUP BY 1, 2 PIVOT BY year, total"#,
+        &directives,
+    );
+
+    // The unused aggregate stays a row key
+    assert_eq!(result.columns, vec!["account", "n", "2024"]);
+    let totals: Vec<_> = result.rows.iter().map(|r| cell_number(&r[2])).collect();
+    assert_eq!(totals.len(), 2);
+    assert!(totals.contains(&Some(dec!(3000))));
+    assert!(totals.contains(&Some(dec!(250))));
+}
+
+#[test]
+fn test_query_result_pivot() {
+    let mut result = QueryResult::new(vec![
+        "account".to_string(),
+        "month".to_string(),
+        "total".to_string(),
+    ]);
+    for (account, month, total) in [("B", 2, 20), ("A", 1, 10), ("B", 1, 5)] {
+        result.add_row(vec![
+            Value::String(account.to_string()),
+            Value::Integer(month),
+            Value::Integer(total),
+        ]);
+    }
+
+    let matrix = result.pivot(1, 2).expect("should pivot");
+    assert_eq!(matrix.columns, vec!["account", "1", "2"]);
+    // Rows keep their first-appearance order
+    assert_eq!(
+        matrix.rows,
+        vec![
+            vec![
+                Value::String("B".to_string()),
+                Value::Integer(5),
+                Value::Integer(20)
+            ],
+            vec![
+                Value::String("A".to_string()),
+                Value::Integer(10),
+                Value::Null
+            ],
+        ]
+    );
+
+    assert!(result.pivot(1, 1).is_err());
+    assert!(result.pivot(3, 2).is_err());
+}
+
+#[test]
+fn test_query_result_pivot_amounts_by_currency() {
+    let mut result = QueryResult::new(vec!["account".to_string(), "price".to_string()]);
+    for (account, currency) in [("A", "USD"), ("B", "EUR"), ("C", "USD")] {
+        result.add_row(vec![
+            Value::String(account.to_string()),
+            Value::Amount(Amount::new(dec!(10), currency)),
+        ]);
+    }
+
+    // Equal numbers in different currencies are separate columns, and
+    // repeated amounts share one
+    let matrix = result.pivot(1, 0).expect("should pivot");
+    assert_eq!(matrix.columns, vec!["10 EUR", "10 USD"]);
+    assert_eq!(
+        matrix.rows,
+        vec![vec![
+            Value::String("B".to_string()),
+            Value::String("A".to_string())
+        ]]
+    );
+}
+
+#[test]
+fn test_query_result_numberify() {
+    let mut inventory = Inventory::new();
+    inventory.add(Position::simple(Amount::new(dec!(10), "USD")));
+    inventory.add(Position::simple(Amount::new(dec!(3), "EUR")));
+
+    let mut result = QueryResult::new(vec!["account".to_string(), "total".to_string()]);
+    result.add_row(vec![
+        Value::String("Assets:Cash".to_string()),
+        Value::Amount(Amount::new(dec!(5), "USD")),
+    ]);
+    result.add_row(vec![
+        Value::String("Assets:Bank".to_string()),
+        Value::Inventory(inventory),
+    ]);
+    result.add_row(vec![Value::String("Assets:Empty".to_string()), Value::Null]);
+
+    let numbers = result.numberify();
+    assert_eq!(
+        numbers.columns,
+        vec!["account", "total (EUR)", "total (USD)"]
+    );
+    assert_eq!(
+        numbers.rows,
+        vec![
+            vec![
+                Value::String("Assets:Cash".to_string()),
+                Value::Null,
+                Value::Number(dec!(5))
+            ],
+            vec![
+                Value::String("Assets:Bank".to_string()),
+                Value::Number(dec!(3)),
+                Value::Number(dec!(10))
+            ],
+            vec![
+                Value::String("Assets:Empty".to_string()),
+                Value::Null,
+                Value::Null
+            ],
+        ]
+    );
+}
+
 // ============================================================================
 // Window Function Tests
 // ============================================================================
@@ -744,6 +963,113 @@ fn test_execute_window_with_partition_by() {
     // Each partition should have its own row numbering starting from 1
 }
 
+fn make_monthly_expense_directives() -> Vec<Directive> {
+    let expense = |day: NaiveDate, account: &str, amount: rust_decimal::Decimal| {
+        Directive::Transaction(
+            Transaction::new(day, "Expense")
+                .with_posting(Posting::new(account, Amount::new(amount, "USD")))
+                .with_posting(Posting::new(
+                    "Assets:Bank:Checking",
+                    Amount::new(-amount, "USD"),
+                )),
+        )
+    };
+    vec![
+        expense(date(2024, 1, 5), "Expenses:Food", dec!(100)),
+        expense(date(2024, 1, 20), "Expenses:Food", dec!(50)),
+        expense(date(2024, 1, 22), "Expenses:Transport", dec!(30)),
+        expense(date(2024, 2, 3), "Expenses:Food", dec!(120)),
+        expense(date(2024, 3, 8), "Expenses:Food", dec!(90)),
+        expense(date(2024, 3, 9), "Expenses:Transport", dec!(40)),
+    ]
+}
+
+fn usd(number: rust_decimal::Decimal) -> Value {
+    let mut inventory = Inventory::new();
+    inventory.add(Position::simple(Amount::new(number, "USD")));
+    Value::Inventory(inventory)
+}
+
+#[test]
+fn test_execute_window_lag_over_groups() {
+    let directives = make_monthly_expense_directives();
+    let result = execute_query(
+        r#"SELECT month, SUM(position) AS total,
+                  LAG(SUM(position)) OVER (ORDER BY month) AS previous
+           WHERE account = "Expenses:Food"
+           GROUP BY month ORDER BY month"#,
+        &directives,
+    );
+
+    assert_eq!(result.columns, vec!["month", "total", "previous"]);
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![Value::Integer(1), usd(dec!(150)), Value::Null],
+            vec![Value::Integer(2), usd(dec!(120)), usd(dec!(150))],
+            vec![Value::Integer(3), usd(dec!(90)), usd(dec!(120))],
+        ]
+    );
+}
+
+#[test]
+fn test_execute_window_lead_with_partition() {
+    let directives = make_monthly_expense_directives();
+    let result = execute_query(
+        r#"SELECT account, month,
+                  LEAD(SUM(position), 1, 0) OVER (PARTITION BY account ORDER BY month) AS next
+           WHERE account ~ "^Expenses:"
+           GROUP BY account, month ORDER BY account, month"#,
+        &directives,
+    );
+
+    let food = Value::String("Expenses:Food".to_string());
+    let transport = Value::String("Expenses:Transport".to_string());
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![food.clone(), Value::Integer(1), usd(dec!(120))],
+            vec![food.clone(), Value::Integer(2), usd(dec!(90))],
+            vec![food, Value::Integer(3), Value::Number(dec!(0))],
+            vec![transport.clone(), Value::Integer(1), usd(dec!(40))],
+            vec![transport, Value::Integer(3), Value::Number(dec!(0))],
+        ]
+    );
+}
+
+#[test]
+fn test_execute_window_lag_period_delta() {
+    let directives = make_monthly_expense_directives();
+    let result = execute_query(
+        r#"SELECT month, purchases - previous AS change FROM (
+               SELECT month, COUNT(*) AS purchases,
+                      LAG(COUNT(*), 1, 0) OVER (ORDER BY month) AS previous
+               WHERE account ~ "^Expenses:"
+               GROUP BY month ORDER BY month)"#,
+        &directives,
+    );
+
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![Value::Integer(1), Value::Number(dec!(3))],
+            vec![Value::Integer(2), Value::Number(dec!(-2))],
+            vec![Value::Integer(3), Value::Number(dec!(1))],
+        ]
+    );
+}
+
+#[test]
+fn test_execute_window_lag_requires_aggregate() {
+    let directives = make_monthly_expense_directives();
+    let query = parse("SELECT date, LAG(number) OVER (ORDER BY date)").expect("should parse");
+    let mut executor = Executor::new(&directives);
+    let err = executor
+        .execute(&query)
+        .expect_err("LAG needs grouped rows");
+    assert!(err.to_string().contains("aggregate"), "{err}");
+}
+
 // ============================================================================
 // Tags and Links Tests
 // ============================================================================
@@ -0,0 +1,576 @@
+//! Synthetic ledger generation.
+//!
+//! [`LedgerGenerator`] writes a plausible personal ledger: a salary paid twice
+//! a month, rent, card spending across expense categories paid off monthly,
+//! savings with interest, and a monthly index fund purchase with prices.
+//! Running balances are tracked so every generated balance assertion holds.
+//!
+//! The output depends only on the configuration, so the same seed always
+//! reproduces the same ledger.
+
+use chrono::{Datelike, Months, NaiveDate};
+use std::fmt::Write;
+
+/// Built-in expense categories: account suffix, payees, and amount range in
+/// cents.
+const CATEGORIES: &[(&str, &[&str], i64, i64)] = &[
+    (
+        "Food:Groceries",
+        &["Whole Foods", "Trader Joe's", "Safeway"],
+        2_000,
+        15_000,
+    ),
+    (
+        "Food:Restaurants",
+        &["Chipotle", "Pizza Place", "Sushi Bar"],
+        1_200,
+        8_000,
+    ),
+    ("Food:Coffee", &["Blue Bottle", "Starbucks"], 300, 900),
+    ("Transport:Fuel", &["Shell", "Chevron"], 3_000, 7_000),
+    ("Transport:Transit", &["Metro Transit"], 250, 5_000),
+    (
+        "Home:Utilities",
+        &["City Power", "Water District"],
+        4_000,
+        15_000,
+    ),
+    ("Home:Internet", &["Comcast"], 6_000, 8_000),
+    ("Health:Pharmacy", &["CVS", "Walgreens"], 500, 6_000),
+    ("Shopping:Clothing", &["Uniqlo", "H&M"], 2_000, 12_000),
+    (
+        "Shopping:Electronics",
+        &["Best Buy", "Apple Store"],
+        1_500,
+        60_000,
+    ),
+    ("Leisure:Books", &["Bookshop", "Amazon"], 800, 4_000),
+    ("Leisure:Movies", &["AMC Theatres", "Netflix"], 1_000, 3_000),
+    (
+        "Travel:Flights",
+        &["United Airlines", "Delta"],
+        15_000,
+        60_000,
+    ),
+    ("Travel:Hotels", &["Marriott", "Hilton"], 10_000, 45_000),
+    ("Gifts", &["Etsy", "Florist"], 2_000, 10_000),
+];
+
+const CHECKING: &str = "Assets:Bank:Checking";
+const SAVINGS: &str = "Assets:Bank:Savings";
+const BROKER_CASH: &str = "Assets:Broker:Cash";
+const BROKER_FUND: &str = "Assets:Broker:VTI";
+const CARD: &str = "Liabilities:CreditCard";
+const SALARY: &str = "Income:Salary";
+const INTEREST: &str = "Income:Interest";
+const RENT: &str = "Expenses:Home:Rent";
+const TAXES: &str = "Expenses:Taxes:Income";
+const OPENING: &str = "Equity:Opening-Balances";
+
+/// Generates deterministic synthetic ledgers of configurable size.
+///
+/// # Example
+///
+/// ```
+/// use rustledger_testutil::LedgerGenerator;
+///
+/// let source = LedgerGenerator::new()
+///     .with_accounts(5)
+///     .with_years(1)
+///     .with_transactions_per_month(20)
+///     .generate();
+/// assert!(source.contains("open Expenses:Food:Groceries"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LedgerGenerator {
+    accounts: usize,
+    years: u32,
+    transactions_per_month: usize,
+    start_year: i32,
+    until: Option<NaiveDate>,
+    seed: u64,
+}
+
+impl Default for LedgerGenerator {
+    fn default() -> Self {
+        Self {
+            accounts: 10,
+            years: 3,
+            transactions_per_month: 40,
+            start_year: 2022,
+            until: None,
+            seed: 42,
+        }
+    }
+}
+
+impl LedgerGenerator {
+    /// Create a generator with the default size: 10 expense accounts, 3 years
+    /// from 2022 and 40 purchases a month.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of expense accounts purchases are spread over.
+    ///
+    /// The first 15 are realistic categories; further ones are numbered
+    /// `Expenses:Misc:CategoryN` accounts.
+    #[must_use]
+    pub const fn with_accounts(mut self, accounts: usize) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Set the number of years covered.
+    #[must_use]
+    pub const fn with_years(mut self, years: u32) -> Self {
+        self.years = years;
+        self
+    }
+
+    /// Set the number of card and cash purchases per month, on top of the
+    /// fixed monthly entries.
+    #[must_use]
+    pub const fn with_transactions_per_month(mut self, transactions: usize) -> Self {
+        self.transactions_per_month = transactions;
+        self
+    }
+
+    /// Set the year the ledger starts in, on January 1st.
+    #[must_use]
+    pub const fn with_start_year(mut self, year: i32) -> Self {
+        self.start_year = year;
+        self
+    }
+
+    /// Leave out entries dated after `date`, e.g. to avoid future entries.
+    #[must_use]
+    pub const fn with_until(mut self, date: NaiveDate) -> Self {
+        self.until = Some(date);
+        self
+    }
+
+    /// Set the seed of the random choices.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generate the ledger as beancount source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start year is outside the range supported by dates.
+    #[must_use]
+    pub fn generate(&self) -> String {
+        let start = NaiveDate::from_ymd_opt(self.start_year, 1, 1).expect("valid start year");
+        let categories = self.categories();
+        let mut rng = Rng::new(self.seed);
+        let mut ledger = Ledger {
+            checking: 250_000,
+            fund_price: 20_000,
+            ..Ledger::default()
+        };
+
+        let mut out = String::new();
+        out.push_str("option \"title\" \"Demo Ledger\"\n");
+        out.push_str("option \"operating_currency\" \"USD\"\n\n");
+        let _ = writeln!(out, "{start} commodity USD");
+        let _ = writeln!(out, "{start} commodity VTI\n");
+        for (account, currency) in [
+            (CHECKING, "USD"),
+            (SAVINGS, "USD"),
+            (BROKER_CASH, "USD"),
+            (BROKER_FUND, "VTI"),
+            (CARD, "USD"),
+            (SALARY, "USD"),
+            (INTEREST, "USD"),
+        ] {
+            let _ = writeln!(out, "{start} open {account} {currency}");
+        }
+        for account in [RENT, TAXES, OPENING] {
+            let _ = writeln!(out, "{start} open {account}");
+        }
+        for category in &categories {
+            let _ = writeln!(out, "{start} open Expenses:{}", category.account);
+        }
+        out.push('\n');
+        out.push_str(&transaction(
+            start,
+            None,
+            "Opening balance",
+            None,
+            &[(CHECKING, ledger.checking), (OPENING, -ledger.checking)],
+        ));
+
+        for month in 0..self.years * 12 {
+            let Some(first) = start.checked_add_months(Months::new(month)) else {
+                break;
+            };
+            if self.until.is_some_and(|until| first > until) {
+                break;
+            }
+            let mut entries = ledger.month(
+                first,
+                month > 0,
+                &categories,
+                self.transactions_per_month,
+                &mut rng,
+            );
+            entries.sort_by_key(|(date, _)| *date);
+            for (date, entry) in entries {
+                if !self.until.is_some_and(|until| date > until) {
+                    out.push_str(&entry);
+                }
+            }
+        }
+        out
+    }
+
+    fn categories(&self) -> Vec<Category> {
+        (0..self.accounts)
+            .map(|i| match CATEGORIES.get(i) {
+                Some(&(account, payees, min, max)) => Category {
+                    account: account.to_string(),
+                    payees: payees.iter().map(ToString::to_string).collect(),
+                    min,
+                    max,
+                    tag: account.starts_with("Travel:").then_some("travel"),
+                },
+                None => Category {
+                    account: format!("Misc:Category{}", i + 1),
+                    payees: vec![format!("Vendor {}", i + 1)],
+                    min: 1_000,
+                    max: 5_000,
+                    tag: None,
+                },
+            })
+            .collect()
+    }
+}
+
+/// An expense account purchases are booked to.
+struct Category {
+    account: String,
+    payees: Vec<String>,
+    min: i64,
+    max: i64,
+    tag: Option<&'static str>,
+}
+
+/// Running balances, in cents, and the fund price.
+#[derive(Default)]
+struct Ledger {
+    checking: i64,
+    savings: i64,
+    broker_cash: i64,
+    card_owed: i64,
+    fund_price: i64,
+}
+
+impl Ledger {
+    /// Entries of the month starting on `first`, in no particular order.
+    fn month(
+        &mut self,
+        first: NaiveDate,
+        assert_balances: bool,
+        categories: &[Category],
+        purchases: usize,
+        rng: &mut Rng,
+    ) -> Vec<(NaiveDate, String)> {
+        let day = |d: u32| first.with_day(d).expect("day within month");
+        let mut entries = Vec::new();
+
+        if assert_balances {
+            for (account, cents) in [
+                (CHECKING, self.checking),
+                (SAVINGS, self.savings),
+                (CARD, -self.card_owed),
+            ] {
+                entries.push((
+                    first,
+                    format!("{first} balance {account}  {}\n\n", usd(cents)),
+                ));
+            }
+        }
+
+        entries.push((
+            first,
+            transaction(
+                first,
+                Some("Landlord"),
+                "Rent",
+                None,
+                &[(RENT, 185_000), (CHECKING, -185_000)],
+            ),
+        ));
+        self.checking -= 185_000;
+
+        for payday in [day(1), day(15)] {
+            entries.push((
+                payday,
+                transaction(
+                    payday,
+                    Some("Acme Corp"),
+                    "Salary",
+                    None,
+                    &[(CHECKING, 350_000), (TAXES, 130_000), (SALARY, -480_000)],
+                ),
+            ));
+            self.checking += 350_000;
+        }
+
+        entries.push((
+            day(2),
+            transaction(
+                day(2),
+                None,
+                "Transfer to savings",
+                None,
+                &[(SAVINGS, 50_000), (CHECKING, -50_000)],
+            ),
+        ));
+        self.checking -= 50_000;
+        self.savings += 50_000;
+
+        entries.push((
+            day(3),
+            transaction(
+                day(3),
+                None,
+                "Transfer to brokerage",
+                None,
+                &[(BROKER_CASH, 100_000), (CHECKING, -100_000)],
+            ),
+        ));
+        self.checking -= 100_000;
+        self.broker_cash += 100_000;
+
+        let shares = self.broker_cash / self.fund_price;
+        if shares > 0 {
+            let cost = shares * self.fund_price;
+            entries.push((
+                day(3),
+                format!(
+                    "{} * \"Buy VTI\"\n  {BROKER_FUND}  {shares} VTI {{{}}}\n  {BROKER_CASH}  {}\n\n",
+                    day(3),
+                    usd(self.fund_price),
+                    usd(-cost)
+                ),
+            ));
+            self.broker_cash -= cost;
+        }
+
+        if !categories.is_empty() {
+            for _ in 0..purchases {
+                let date = day(rng.range(1, 28) as u32);
+                let category = rng.pick(categories);
+                let payee = rng.pick(&category.payees);
+                let cents = rng.range(category.min, category.max);
+                let expense = format!("Expenses:{}", category.account);
+                let source = if rng.range(1, 4) == 1 {
+                    self.checking -= cents;
+                    CHECKING
+                } else {
+                    self.card_owed += cents;
+                    CARD
+                };
+                entries.push((
+                    date,
+                    transaction(
+                        date,
+                        Some(payee),
+                        category.account.rsplit(':').next().unwrap_or_default(),
+                        category.tag,
+                        &[(&expense, cents), (source, -cents)],
+                    ),
+                ));
+            }
+        }
+
+        let interest = self.savings * 3 / 1000;
+        if interest > 0 {
+            entries.push((
+                day(28),
+                transaction(
+                    day(28),
+                    Some("Bank"),
+                    "Interest",
+                    None,
+                    &[(SAVINGS, interest), (INTEREST, -interest)],
+                ),
+            ));
+            self.savings += interest;
+        }
+
+        // Pushed last so the stable sort keeps it after the day's purchases
+        if self.card_owed > 0 {
+            entries.push((
+                day(28),
+                transaction(
+                    day(28),
+                    None,
+                    "Credit card payment",
+                    None,
+                    &[(CARD, self.card_owed), (CHECKING, -self.card_owed)],
+                ),
+            ));
+            self.checking -= self.card_owed;
+            self.card_owed = 0;
+        }
+
+        self.fund_price = (self.fund_price * (1000 + rng.range(-40, 55)) / 1000).max(100);
+        entries.push((
+            day(28),
+            format!("{} price VTI  {}\n\n", day(28), usd(self.fund_price)),
+        ));
+
+        entries
+    }
+}
+
+/// Format a completed transaction with amounts in USD cents.
+fn transaction(
+    date: NaiveDate,
+    payee: Option<&str>,
+    narration: &str,
+    tag: Option<&str>,
+    postings: &[(&str, i64)],
+) -> String {
+    let mut out = format!("{date} *");
+    if let Some(payee) = payee {
+        let _ = write!(out, " \"{payee}\"");
+    }
+    let _ = write!(out, " \"{narration}\"");
+    if let Some(tag) = tag {
+        let _ = write!(out, " #{tag}");
+    }
+    out.push('\n');
+    for (account, cents) in postings {
+        let _ = writeln!(out, "  {account}  {}", usd(*cents));
+    }
+    out.push('\n');
+    out
+}
+
+/// Format cents as a USD amount.
+fn usd(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.abs();
+    format!("{sign}{}.{:02} USD", cents / 100, cents % 100)
+}
+
+/// Small deterministic random number generator (`SplitMix64`).
+struct Rng(u64);
+
+impl Rng {
+    const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number between `low` and `high`, inclusive.
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + (self.next() % (high - low + 1) as u64) as i64
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[(self.next() % items.len() as u64) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustledger_core::Directive;
+
+    fn parse(source: &str) -> Vec<Directive> {
+        let result = rustledger_parser::parse(source);
+        assert!(
+            result.errors.is_empty(),
+            "parse errors: {:?}",
+            result.errors
+        );
+        result.directives.into_iter().map(|d| d.value).collect()
+    }
+
+    fn count(directives: &[Directive], type_name: &str) -> usize {
+        directives
+            .iter()
+            .filter(|d| d.type_name() == type_name)
+            .count()
+    }
+
+    #[test]
+    fn test_generated_ledger_validates() {
+        let source = LedgerGenerator::new().with_years(2).generate();
+        let directives = parse(&source);
+
+        let errors = rustledger_validate::validate(&directives);
+        assert!(errors.is_empty(), "validation errors: {errors:?}");
+        // 23 months of checking, savings and card assertions
+        assert_eq!(count(&directives, "balance"), 23 * 3);
+        assert_eq!(count(&directives, "price"), 24);
+    }
+
+    #[test]
+    fn test_generator_size() {
+        let small = parse(
+            &LedgerGenerator::new()
+                .with_accounts(3)
+                .with_years(1)
+                .with_transactions_per_month(5)
+                .generate(),
+        );
+        let large = parse(
+            &LedgerGenerator::new()
+                .with_accounts(20)
+                .with_years(2)
+                .with_transactions_per_month(50)
+                .generate(),
+        );
+
+        // Fixed accounts plus one per expense category
+        assert_eq!(count(&small, "open"), 10 + 3);
+        assert_eq!(count(&large, "open"), 10 + 20);
+        assert!(count(&large, "transaction") > 5 * count(&small, "transaction"));
+        assert!(large.iter().any(
+            |d| matches!(d, Directive::Open(open) if open.account == "Expenses:Misc:Category20")
+        ));
+    }
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let generator = LedgerGenerator::new().with_years(1);
+        assert_eq!(generator.generate(), generator.generate());
+        assert_ne!(generator.generate(), generator.with_seed(7).generate());
+    }
+
+    #[test]
+    fn test_generator_until() {
+        let until = NaiveDate::from_ymd_opt(2022, 3, 10).unwrap();
+        let directives = parse(&LedgerGenerator::new().with_until(until).generate());
+        assert!(directives.iter().all(|d| d.date() <= until));
+        assert!(
+            directives
+                .iter()
+                .any(|d| d.date() > until - chrono::Days::new(10))
+        );
+    }
+
+    #[test]
+    fn test_usd() {
+        assert_eq!(usd(123_456), "1234.56 USD");
+        assert_eq!(usd(-5), "-0.05 USD");
+        assert_eq!(usd(0), "0.00 USD");
+    }
+}
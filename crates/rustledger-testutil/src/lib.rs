@@ -0,0 +1,21 @@
+//! Test and demo utilities for rustledger.
+//!
+//! This crate provides:
+//! - [`LedgerGenerator`]: deterministic synthetic ledgers of configurable
+//!   size, for tests, benchmarks, screenshots and the web UI's demo mode
+//!
+//! # Example
+//!
+//! ```
+//! use rustledger_testutil::LedgerGenerator;
+//!
+//! let source = LedgerGenerator::new().with_years(1).with_seed(7).generate();
+//! assert!(source.starts_with("option \"title\" \"Demo Ledger\""));
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+mod generator;
+
+pub use generator::LedgerGenerator;
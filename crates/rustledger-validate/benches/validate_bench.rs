@@ -9,7 +9,7 @@ use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_m
 use chrono::NaiveDate;
 use rust_decimal_macros::dec;
 use rustledger_core::{Amount, Balance, Directive, Open, Posting, Transaction};
-use rustledger_validate::validate;
+use rustledger_validate::{ValidationOptions, validate, validate_parallel, validate_with_options};
 
 fn date(year: i32, month: u32, day: u32) -> NaiveDate {
     NaiveDate::from_ymd_opt(year, month, day).unwrap()
@@ -192,10 +192,46 @@ fn bench_validate_balance_assertions(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_validate_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate_parallel");
+
+    let size = 5000;
+    let directives = generate_valid_ledger(size);
+    group.throughput(Throughput::Elements(size as u64));
+
+    group.bench_with_input(
+        BenchmarkId::new("sequential", size),
+        &directives,
+        |b, directives| {
+            b.iter(|| {
+                std::hint::black_box(validate_with_options(
+                    std::hint::black_box(directives),
+                    ValidationOptions::default(),
+                ))
+            });
+        },
+    );
+    group.bench_with_input(
+        BenchmarkId::new("parallel", size),
+        &directives,
+        |b, directives| {
+            b.iter(|| {
+                std::hint::black_box(validate_parallel(
+                    std::hint::black_box(directives),
+                    ValidationOptions::default(),
+                ))
+            });
+        },
+    );
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_validate_valid,
     bench_validate_with_errors,
     bench_validate_balance_assertions,
+    bench_validate_parallel,
 );
 criterion_main!(benches);
@@ -4,10 +4,14 @@
 //!
 //! - Account lifecycle (opened before use, not used after close)
 //! - Balance assertions
-//! - Transaction balancing
+//! - Transaction balancing, within tolerances inferred as beancount infers
+//!   them (see [`tolerance`])
 //! - Currency constraints
 //! - Booking validation (lot matching, sufficient units)
 //!
+//! [`validate_parallel`] produces the same errors as [`validate_with_options`]
+//! while checking independent accounts on separate threads.
+//!
 //! # Error Codes
 //!
 //! All error codes follow the spec in `spec/validation.md`:
@@ -19,47 +23,74 @@
 //! | E1003 | Account already closed |
 //! | E1004 | Account close with non-zero balance |
 //! | E1005 | Invalid account name |
+//! | E1006 | Posting to non-leaf account (warning, opt-in) |
+//! | E1007 | Account name violates naming rules (opt-in) |
 //! | E2001 | Balance assertion failed |
 //! | E2002 | Balance exceeds explicit tolerance |
 //! | E2003 | Pad without subsequent balance |
 //! | E2004 | Multiple pads for same balance |
+//! | E2005 | Last balance assertion is stale (info, opt-in) |
 //! | E3001 | Transaction does not balance |
 //! | E3002 | Multiple missing amounts in transaction |
 //! | E3003 | Transaction has no postings |
 //! | E3004 | Transaction has single posting (warning) |
+//! | E3005 | Posting with zero units (warning, opt-in) |
+//! | E3006 | Transaction has no net effect (warning, opt-in) |
 //! | E4001 | No matching lot for reduction |
 //! | E4002 | Insufficient units in lot |
 //! | E4003 | Ambiguous lot match |
-//! | E4004 | Reduction would create negative inventory |
+//! | E4004 | Reduction went short under the `warn` negative lots policy (warning) |
+//! | E4005 | Addition at cost without a cost number or currency in a STRICT account |
 //! | E5001 | Currency not declared |
 //! | E5002 | Currency not allowed in account |
+//! | E5003 | Non-operating currency in unconstrained account (warning, opt-in) |
+//! | E5004 | Price is zero or negative |
+//! | E5005 | Conflicting prices on the same date (warning) |
+//! | E5006 | Price quoted in the priced currency itself |
+//! | E5007 | Invalid currency code |
 //! | E6001 | Duplicate metadata key |
 //! | E6002 | Invalid metadata value |
 //! | E7001 | Unknown option |
 //! | E7002 | Invalid option value |
 //! | E7003 | Duplicate option |
 //! | E8001 | Document file not found |
+//! | E8002 | Transaction link has no matching document (warning) |
+//! | E8003 | Document file not referenced by any directive (warning, opt-in) |
 //! | E10001 | Date out of order (info) |
 //! | E10002 | Entry dated in the future (warning) |
+//! | E10003 | Entry dated before the ledger's `first_date` (warning, opt-in) |
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
 use chrono::{Local, NaiveDate};
 use rayon::prelude::*;
+use regex::Regex;
 use rust_decimal::Decimal;
+use rustledger_booking::InterpolationError;
 use rustledger_core::{
-    Amount, Balance, BookingMethod, Close, Directive, Document, InternedStr, Inventory, Open, Pad,
-    Position, Posting, Transaction,
+    Amount, Balance, BookingMethod, Close, Commodity, Currency, Custom, Directive, Document,
+    InternedStr, Inventory, MetaValue, NegativeLotsPolicy, Note, Open, Pad, Position, Posting,
+    Price, Transaction, cmp_directives,
 };
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::Path;
 use thiserror::Error;
 
+mod parallel;
+mod report;
+pub mod tolerance;
+
+pub use parallel::validate_parallel;
+pub use report::ValidationReport;
+pub use tolerance::ToleranceOptions;
+
 /// Validation error codes.
 ///
 /// Error codes follow the spec in `spec/validation.md`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Codes order by their number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ErrorCode {
     // === Account Errors (E1xxx) ===
     /// E1001: Account used before it was opened.
@@ -72,6 +103,10 @@ pub enum ErrorCode {
     AccountCloseNotEmpty,
     /// E1005: Invalid account name.
     InvalidAccountName,
+    /// E1006: Posting to an account that has child accounts (warning).
+    NonLeafPosting,
+    /// E1007: Account name violates the configured naming rules.
+    AccountNamingViolation,
 
     // === Balance Errors (E2xxx) ===
     /// E2001: Balance assertion failed.
@@ -82,6 +117,9 @@ pub enum ErrorCode {
     PadWithoutBalance,
     /// E2004: Multiple pads for same balance assertion.
     MultiplePadForBalance,
+    /// E2005: Account's last balance assertion is older than the configured
+    /// number of days (info only).
+    StaleBalanceAssertion,
 
     // === Transaction Errors (E3xxx) ===
     /// E3001: Transaction does not balance.
@@ -92,6 +130,11 @@ pub enum ErrorCode {
     NoPostings,
     /// E3004: Transaction has single posting (warning).
     SinglePosting,
+    /// E3005: Posting with exactly zero units (warning).
+    ZeroPosting,
+    /// E3006: Transaction whose postings cancel out within each account
+    /// (warning).
+    NoOpTransaction,
 
     // === Booking Errors (E4xxx) ===
     /// E4001: No matching lot for reduction.
@@ -100,14 +143,30 @@ pub enum ErrorCode {
     InsufficientUnits,
     /// E4003: Ambiguous lot match in STRICT mode.
     AmbiguousLotMatch,
-    /// E4004: Reduction would create negative inventory.
+    /// E4004: Reduction went short in an account whose negative lots
+    /// policy is `warn` (warning).
     NegativeInventory,
+    /// E4005: Addition at cost, in a STRICT account, whose cost spec lacks
+    /// the number or the currency.
+    IncompleteCost,
 
     // === Currency Errors (E5xxx) ===
     /// E5001: Currency not declared (when strict mode enabled).
     UndeclaredCurrency,
     /// E5002: Currency not allowed in account.
     CurrencyNotAllowed,
+    /// E5003: Balance sheet account without currency constraints holds a
+    /// non-operating currency (warning).
+    NonOperatingCurrency,
+    /// E5004: Price directive with a zero or negative price.
+    NonPositivePrice,
+    /// E5005: Several different prices for a currency pair on one date
+    /// (warning).
+    ConflictingPrice,
+    /// E5006: Price directive quoting a currency in itself.
+    SelfReferentialPrice,
+    /// E5007: Commodity or Open directive naming an invalid currency code.
+    InvalidCurrency,
 
     // === Metadata Errors (E6xxx) ===
     /// E6001: Duplicate metadata key.
@@ -126,12 +185,18 @@ pub enum ErrorCode {
     // === Document Errors (E8xxx) ===
     /// E8001: Document file not found.
     DocumentNotFound,
+    /// E8002: Transaction link has no matching document (warning).
+    DocumentLinkNotFound,
+    /// E8003: Document file not referenced by any document directive (warning).
+    UnlinkedDocument,
 
     // === Date Errors (E10xxx) ===
     /// E10001: Date out of order (info only).
     DateOutOfOrder,
     /// E10002: Entry dated in the future (warning).
     FutureDate,
+    /// E10003: Entry dated before the ledger's first date (warning).
+    BeforeFirstDate,
 }
 
 impl ErrorCode {
@@ -145,24 +210,35 @@ impl ErrorCode {
             Self::AccountClosed => "E1003",
             Self::AccountCloseNotEmpty => "E1004",
             Self::InvalidAccountName => "E1005",
+            Self::NonLeafPosting => "E1006",
+            Self::AccountNamingViolation => "E1007",
             // Balance errors
             Self::BalanceAssertionFailed => "E2001",
             Self::BalanceToleranceExceeded => "E2002",
             Self::PadWithoutBalance => "E2003",
             Self::MultiplePadForBalance => "E2004",
+            Self::StaleBalanceAssertion => "E2005",
             // Transaction errors
             Self::TransactionUnbalanced => "E3001",
             Self::MultipleInterpolation => "E3002",
             Self::NoPostings => "E3003",
             Self::SinglePosting => "E3004",
+            Self::ZeroPosting => "E3005",
+            Self::NoOpTransaction => "E3006",
             // Booking errors
             Self::NoMatchingLot => "E4001",
             Self::InsufficientUnits => "E4002",
             Self::AmbiguousLotMatch => "E4003",
             Self::NegativeInventory => "E4004",
+            Self::IncompleteCost => "E4005",
             // Currency errors
             Self::UndeclaredCurrency => "E5001",
             Self::CurrencyNotAllowed => "E5002",
+            Self::NonOperatingCurrency => "E5003",
+            Self::NonPositivePrice => "E5004",
+            Self::ConflictingPrice => "E5005",
+            Self::SelfReferentialPrice => "E5006",
+            Self::InvalidCurrency => "E5007",
             // Metadata errors
             Self::DuplicateMetadataKey => "E6001",
             Self::InvalidMetadataValue => "E6002",
@@ -172,9 +248,12 @@ impl ErrorCode {
             Self::DuplicateOption => "E7003",
             // Document errors
             Self::DocumentNotFound => "E8001",
+            Self::DocumentLinkNotFound => "E8002",
+            Self::UnlinkedDocument => "E8003",
             // Date errors
             Self::DateOutOfOrder => "E10001",
             Self::FutureDate => "E10002",
+            Self::BeforeFirstDate => "E10003",
         }
     }
 
@@ -184,16 +263,26 @@ impl ErrorCode {
         matches!(
             self,
             Self::FutureDate
+                | Self::BeforeFirstDate
                 | Self::SinglePosting
+                | Self::ZeroPosting
+                | Self::NoOpTransaction
                 | Self::AccountCloseNotEmpty
+                | Self::NonLeafPosting
+                | Self::NegativeInventory
+                | Self::NonOperatingCurrency
+                | Self::ConflictingPrice
+                | Self::DocumentLinkNotFound
+                | Self::UnlinkedDocument
                 | Self::DateOutOfOrder
+                | Self::StaleBalanceAssertion
         )
     }
 
     /// Check if this is just informational.
     #[must_use]
     pub const fn is_info(&self) -> bool {
-        matches!(self, Self::DateOutOfOrder)
+        matches!(self, Self::DateOutOfOrder | Self::StaleBalanceAssertion)
     }
 
     /// Get the severity level.
@@ -210,7 +299,9 @@ impl ErrorCode {
 }
 
 /// Severity level for validation messages.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Levels order from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Severity {
     /// Ledger is invalid.
     Error,
@@ -238,6 +329,9 @@ pub struct ValidationError {
     pub date: NaiveDate,
     /// Additional context.
     pub context: Option<String>,
+    /// Index, in the validated slice, of the directive being checked when
+    /// the error was found. `None` for errors about the ledger as a whole.
+    pub directive: Option<usize>,
 }
 
 impl ValidationError {
@@ -249,6 +343,7 @@ impl ValidationError {
             message: message.into(),
             date,
             context: None,
+            directive: None,
         }
     }
 
@@ -272,6 +367,9 @@ struct AccountState {
     /// Booking method (stored for future use in booking validation).
     #[allow(dead_code)]
     booking: BookingMethod,
+    /// Negative lots policy from the `negative_lots` metadata, overriding
+    /// [`ValidationOptions::negative_lots`].
+    negative_lots: Option<NegativeLotsPolicy>,
 }
 
 /// Validation options.
@@ -283,8 +381,195 @@ pub struct ValidationOptions {
     pub check_documents: bool,
     /// Whether to warn about future-dated entries.
     pub warn_future_dates: bool,
+    /// Days past today an entry may be dated before it counts as in the
+    /// future, to allow post-dated entries.
+    pub future_date_horizon_days: u32,
+    /// Start of the ledger (the `first_date` option). Earlier entries are
+    /// reported, except `open` and `commodity` declarations.
+    pub first_date: Option<NaiveDate>,
+    /// Whether to warn about postings to accounts that have child accounts
+    /// (the behavior of beancount's `leafonly` plugin).
+    pub warn_non_leaf_postings: bool,
+    /// Whether to warn about files in `documents_dirs` that no document
+    /// directive references.
+    pub warn_unlinked_documents: bool,
+    /// Whether to warn about transaction links that no `document`
+    /// directive carries, in ledgers that link documents to transactions.
+    pub warn_document_links: bool,
+    /// Whether to warn about postings with zero units and transactions
+    /// with no net effect, which usually come from import glitches.
+    pub warn_zero_postings: bool,
+    /// Whether to warn about `Assets`/`Liabilities` accounts without
+    /// currency constraints holding currencies not in
+    /// `operating_currencies`.
+    pub warn_non_operating_currencies: bool,
+    /// Whether balance, note and document directives may refer to an
+    /// account after it was closed. Transactions and pads are always
+    /// checked.
+    pub allow_entries_after_close: bool,
+    /// Report open accounts whose last balance assertion is more than this
+    /// many days old, to spot accounts that are no longer reconciled.
+    pub stale_balance_days: Option<u32>,
+    /// Operating currencies (the `operating_currency` option).
+    pub operating_currencies: Vec<String>,
+    /// What to do when a reduction exceeds the units held, for accounts
+    /// without `negative_lots` metadata on their `open` directive.
+    pub negative_lots: NegativeLotsPolicy,
+    /// Account naming conventions, extended by `custom "validate.naming"`
+    /// directives in the ledger.
+    pub naming: NamingRules,
     /// Base directory for resolving relative document paths.
     pub document_base: Option<std::path::PathBuf>,
+    /// Document directories (the `documents` option) scanned for
+    /// unreferenced files.
+    pub documents_dirs: Vec<std::path::PathBuf>,
+    /// How much a transaction may be off and still balance (the
+    /// `inferred_tolerance_*` and `infer_tolerance_from_cost` options).
+    pub tolerance: ToleranceOptions,
+}
+
+/// Custom directive type configuring [`NamingRules`].
+pub const NAMING_CUSTOM_TYPE: &str = "validate.naming";
+
+/// Account naming conventions, checked when accounts are opened (E1007).
+///
+/// Rules can be set here or in the ledger with `custom "validate.naming"`
+/// directives, one rule per directive:
+///
+/// ```beancount
+/// 2000-01-01 custom "validate.naming" "max_depth" 4
+/// 2000-01-01 custom "validate.naming" "second_level" "Assets" "US" "CA"
+/// 2000-01-01 custom "validate.naming" "allow" "Expenses" "^Expenses(:[A-Z][a-z]+)+$"
+/// 2000-01-01 custom "validate.naming" "deny" "Assets" ":Misc"
+/// ```
+///
+/// Rules for a root (e.g. `Assets`) apply to every account under it.
+#[derive(Debug, Clone, Default)]
+pub struct NamingRules {
+    /// Maximum number of components, including the root
+    /// (`Assets:US:Checking` has three).
+    pub max_depth: Option<usize>,
+    /// Allowed second-level components per root, such as countries or
+    /// institutions. Roots without an entry are not restricted.
+    pub second_level: BTreeMap<String, Vec<String>>,
+    /// Patterns per root of which an account must match at least one.
+    pub allow: BTreeMap<String, Vec<Regex>>,
+    /// Patterns per root that an account must not match.
+    pub deny: BTreeMap<String, Vec<Regex>>,
+}
+
+impl NamingRules {
+    /// Whether no rule is configured.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.max_depth.is_none()
+            && self.second_level.is_empty()
+            && self.allow.is_empty()
+            && self.deny.is_empty()
+    }
+
+    /// Add the rule given by a `custom "validate.naming"` directive.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the rule is unknown or its
+    /// arguments are invalid.
+    pub fn add_custom(&mut self, custom: &Custom) -> Result<(), String> {
+        let args: Vec<String> = custom
+            .values
+            .iter()
+            .map(|value| match value {
+                MetaValue::String(s) | MetaValue::Account(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+
+        match args.as_slice() {
+            [rule, depth] if rule == "max_depth" => {
+                let depth = depth
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&depth| depth > 0)
+                    .ok_or_else(|| format!("max_depth must be a positive integer, got {depth}"))?;
+                self.max_depth = Some(depth);
+            }
+            [rule, root, components @ ..] if rule == "second_level" && !components.is_empty() => {
+                self.second_level
+                    .entry(root.clone())
+                    .or_default()
+                    .extend(components.iter().cloned());
+            }
+            [rule, root, patterns @ ..]
+                if (rule == "allow" || rule == "deny") && !patterns.is_empty() =>
+            {
+                let regexes = patterns
+                    .iter()
+                    .map(|p| Regex::new(p).map_err(|e| format!("invalid pattern \"{p}\": {e}")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let rules = if rule == "allow" {
+                    &mut self.allow
+                } else {
+                    &mut self.deny
+                };
+                rules.entry(root.clone()).or_default().extend(regexes);
+            }
+            [rule, ..] => {
+                return Err(format!(
+                    "unknown rule or missing arguments for \"{rule}\" \
+                     (expected max_depth, second_level, allow or deny)"
+                ));
+            }
+            [] => return Err("missing rule name".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Check an account name against the rules.
+    ///
+    /// Returns the reason the name is rejected, if it is.
+    #[must_use]
+    pub fn check(&self, account: &str) -> Option<String> {
+        let components: Vec<&str> = account.split(':').collect();
+        let root = components[0];
+
+        if let Some(max_depth) = self.max_depth {
+            if components.len() > max_depth {
+                return Some(format!(
+                    "has {} components, more than the maximum of {max_depth}",
+                    components.len()
+                ));
+            }
+        }
+
+        if let Some(allowed) = self.second_level.get(root) {
+            match components.get(1) {
+                Some(second) if allowed.iter().any(|a| a == second) => {}
+                Some(second) => {
+                    return Some(format!(
+                        "second component \"{second}\" is not one of {}",
+                        allowed.join(", ")
+                    ));
+                }
+                None => return Some("is missing the required second component".to_string()),
+            }
+        }
+
+        if let Some(patterns) = self.allow.get(root) {
+            if !patterns.iter().any(|p| p.is_match(account)) {
+                return Some(format!("does not match any allowed pattern for {root}"));
+            }
+        }
+
+        if let Some(pattern) = self
+            .deny
+            .get(root)
+            .and_then(|patterns| patterns.iter().find(|p| p.is_match(account)))
+        {
+            return Some(format!("matches denied pattern \"{pattern}\""));
+        }
+
+        None
+    }
 }
 
 /// Pending pad directive info.
@@ -313,6 +598,10 @@ pub struct LedgerState {
     options: ValidationOptions,
     /// Track previous directive date for out-of-order detection.
     last_date: Option<NaiveDate>,
+    /// Date of each account's most recent balance assertion.
+    last_assertions: HashMap<InternedStr, NaiveDate>,
+    /// Prices seen, by base currency, quote currency and date.
+    prices: HashMap<(InternedStr, InternedStr, NaiveDate), Decimal>,
 }
 
 impl LedgerState {
@@ -346,6 +635,64 @@ impl LedgerState {
         self.options.warn_future_dates = warn;
     }
 
+    /// Set how many days ahead an entry may be dated without being
+    /// reported as in the future.
+    pub fn set_future_date_horizon_days(&mut self, days: u32) {
+        self.options.future_date_horizon_days = days;
+    }
+
+    /// Set the start of the ledger; earlier entries are reported.
+    pub fn set_first_date(&mut self, date: Option<NaiveDate>) {
+        self.options.first_date = date;
+    }
+
+    /// Set whether to warn about postings to non-leaf accounts.
+    pub fn set_warn_non_leaf_postings(&mut self, warn: bool) {
+        self.options.warn_non_leaf_postings = warn;
+    }
+
+    /// Set whether to warn about unreferenced files in document directories.
+    pub fn set_warn_unlinked_documents(&mut self, warn: bool) {
+        self.options.warn_unlinked_documents = warn;
+    }
+
+    /// Set whether to warn about transaction links without a document.
+    pub fn set_warn_document_links(&mut self, warn: bool) {
+        self.options.warn_document_links = warn;
+    }
+
+    /// Set whether to warn about zero-unit postings and no-op transactions.
+    pub fn set_warn_zero_postings(&mut self, warn: bool) {
+        self.options.warn_zero_postings = warn;
+    }
+
+    /// Set whether to warn about non-operating currencies in balance sheet
+    /// accounts without currency constraints.
+    pub fn set_warn_non_operating_currencies(&mut self, warn: bool) {
+        self.options.warn_non_operating_currencies = warn;
+    }
+
+    /// Set the age in days after which an account's last balance assertion
+    /// is reported as stale; `None` disables the check.
+    pub fn set_stale_balance_days(&mut self, days: Option<u32>) {
+        self.options.stale_balance_days = days;
+    }
+
+    /// Set the operating currencies.
+    pub fn set_operating_currencies(&mut self, currencies: Vec<String>) {
+        self.options.operating_currencies = currencies;
+    }
+
+    /// Set the default negative lots policy.
+    pub fn set_negative_lots(&mut self, policy: NegativeLotsPolicy) {
+        self.options.negative_lots = policy;
+    }
+
+    /// Set the account naming rules.
+    pub fn set_naming_rules(&mut self, rules: NamingRules) {
+        self.options.naming = rules;
+    }
+
     /// Set the document base directory.
     pub fn set_document_base(&mut self, base: impl Into<std::path::PathBuf>) {
         self.options.document_base = Some(base.into());
@@ -381,39 +728,11 @@ pub fn validate_with_options(
     let mut errors = Vec::new();
 
     let today = Local::now().date_naive();
+    let (order, sorted) = prepare(directives, &mut state, &mut errors);
 
-    // Sort directives by date, then by type priority (parallel)
-    // (e.g., balance assertions before transactions on the same day)
-    let mut sorted: Vec<&Directive> = directives.iter().collect();
-    sorted.par_sort_by(|a, b| {
-        a.date()
-            .cmp(&b.date())
-            .then_with(|| a.priority().cmp(&b.priority()))
-    });
-
-    for directive in sorted {
-        let date = directive.date();
-
-        // Check for date ordering (info only - we sort anyway)
-        if let Some(last) = state.last_date {
-            if date < last {
-                errors.push(ValidationError::new(
-                    ErrorCode::DateOutOfOrder,
-                    format!("Directive date {date} is before previous directive {last}"),
-                    date,
-                ));
-            }
-        }
-        state.last_date = Some(date);
-
-        // Check for future dates if enabled
-        if state.options.warn_future_dates && date > today {
-            errors.push(ValidationError::new(
-                ErrorCode::FutureDate,
-                format!("Entry dated in the future: {date}"),
-                date,
-            ));
-        }
+    for (&index, &directive) in order.iter().zip(&sorted) {
+        let first_error = errors.len();
+        check_directive_date(&mut state, directive, today, &mut errors);
 
         match directive {
             Directive::Open(open) => {
@@ -429,16 +748,157 @@ pub fn validate_with_options(
                 validate_balance(&mut state, bal, &mut errors);
             }
             Directive::Commodity(comm) => {
-                state.commodities.insert(comm.currency.clone());
+                validate_commodity(&mut state, comm, &mut errors);
             }
             Directive::Pad(pad) => {
                 validate_pad(&mut state, pad, &mut errors);
             }
+            Directive::Note(note) => {
+                validate_note(&state, note, &mut errors);
+            }
             Directive::Document(doc) => {
                 validate_document(&state, doc, &mut errors);
             }
+            Directive::Price(price) => {
+                validate_price(&mut state, price, &mut errors);
+            }
             _ => {}
         }
+
+        for error in &mut errors[first_error..] {
+            error.directive = Some(index);
+        }
+    }
+
+    validate_ledger_wide(&state, &sorted, today, &mut errors);
+
+    errors
+}
+
+/// Sort the directives and read the naming rules declared in them.
+///
+/// Returns the sorted directives along with their indices in `directives`.
+fn prepare<'a>(
+    directives: &'a [Directive],
+    state: &mut LedgerState,
+    errors: &mut Vec<ValidationError>,
+) -> (Vec<usize>, Vec<&'a Directive>) {
+    // Sort directives by date, then by type priority, then time (parallel)
+    // (e.g., balance assertions before transactions on the same day)
+    let mut order: Vec<usize> = (0..directives.len()).collect();
+    order.par_sort_by(|&a, &b| cmp_directives(&directives[a], &directives[b]));
+    let sorted: Vec<&Directive> = order.iter().map(|&index| &directives[index]).collect();
+
+    // Naming rules apply to the whole ledger, wherever they are declared
+    for &directive in &sorted {
+        if let Directive::Custom(custom) = directive {
+            if custom.custom_type == NAMING_CUSTOM_TYPE {
+                if let Err(e) = state.options.naming.add_custom(custom) {
+                    errors.push(
+                        ValidationError::new(
+                            ErrorCode::InvalidOptionValue,
+                            format!("Invalid {NAMING_CUSTOM_TYPE} rule: {e}"),
+                            custom.date,
+                        )
+                        .with_context(custom.to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    (order, sorted)
+}
+
+/// Check a directive's date against the previous one and today.
+fn check_directive_date(
+    state: &mut LedgerState,
+    directive: &Directive,
+    today: NaiveDate,
+    errors: &mut Vec<ValidationError>,
+) {
+    let date = directive.date();
+
+    // Check for date ordering (info only - we sort anyway)
+    if let Some(last) = state.last_date {
+        if date < last {
+            errors.push(ValidationError::new(
+                ErrorCode::DateOutOfOrder,
+                format!("Directive date {date} is before previous directive {last}"),
+                date,
+            ));
+        }
+    }
+    state.last_date = Some(date);
+
+    // Check for future dates if enabled; forecast entries (flagged `#` with
+    // `forecast` metadata, as the forecast plugin generates) lie ahead by design
+    let horizon = state.options.future_date_horizon_days;
+    let latest = today
+        .checked_add_days(chrono::Days::new(u64::from(horizon)))
+        .unwrap_or(NaiveDate::MAX);
+    if state.options.warn_future_dates && date > latest && !is_forecast(directive) {
+        let message = if horizon == 0 {
+            format!("Entry dated in the future: {date}")
+        } else {
+            format!("Entry dated in the future: {date} (more than {horizon} days ahead)")
+        };
+        errors.push(ValidationError::new(ErrorCode::FutureDate, message, date));
+    }
+
+    if let Some(first_date) = state.options.first_date {
+        if date < first_date && !matches!(directive, Directive::Open(_) | Directive::Commodity(_)) {
+            errors.push(ValidationError::new(
+                ErrorCode::BeforeFirstDate,
+                format!("Entry dated {date}, before the ledger's first_date {first_date}"),
+                date,
+            ));
+        }
+    }
+}
+
+/// Whether a directive is a forecast transaction.
+fn is_forecast(directive: &Directive) -> bool {
+    matches!(directive, Directive::Transaction(txn)
+        if txn.is_bookmarked() && txn.meta.contains_key("forecast"))
+}
+
+/// Checks that need the whole ledger, run after every directive was seen.
+fn validate_ledger_wide(
+    state: &LedgerState,
+    sorted: &[&Directive],
+    today: NaiveDate,
+    errors: &mut Vec<ValidationError>,
+) {
+    // Check for postings to non-leaf accounts (E1006)
+    if state.options.warn_non_leaf_postings {
+        validate_leaf_only(state, sorted, errors);
+    }
+
+    // Check for zero-unit postings and no-op transactions (E3005, E3006)
+    if state.options.warn_zero_postings {
+        validate_zero_postings(sorted, errors);
+    }
+
+    // Check for non-operating currencies in balance sheet accounts (E5003)
+    if state.options.warn_non_operating_currencies && !state.options.operating_currencies.is_empty()
+    {
+        validate_operating_currencies(state, sorted, errors);
+    }
+
+    // Check that links to documents resolve (E8002)
+    if state.options.warn_document_links {
+        validate_document_links(sorted, errors);
+    }
+
+    // Check for unreferenced document files (E8003)
+    if state.options.warn_unlinked_documents {
+        validate_unlinked_documents(state, sorted, errors);
+    }
+
+    // Check for accounts no longer reconciled (E2005)
+    if let Some(days) = state.options.stale_balance_days {
+        validate_stale_assertions(state, days, today, errors);
     }
 
     // Check for unused pads (E2003)
@@ -456,8 +916,344 @@ pub fn validate_with_options(
             }
         }
     }
+}
 
-    errors
+/// Report open accounts whose most recent balance assertion is more than
+/// `days` days before `today`.
+///
+/// Accounts that were never asserted are not reported: the check is meant
+/// to catch reconciliation that stopped, not accounts that never had any.
+/// Errors are dated at the last assertion, oldest first.
+fn validate_stale_assertions(
+    state: &LedgerState,
+    days: u32,
+    today: NaiveDate,
+    errors: &mut Vec<ValidationError>,
+) {
+    let mut stale: Vec<(NaiveDate, &str)> = state
+        .last_assertions
+        .iter()
+        .filter(|(account, _)| {
+            state
+                .accounts
+                .get(*account)
+                .is_some_and(|account| account.closed.is_none())
+        })
+        .filter(|&(_, &last)| (today - last).num_days() > i64::from(days))
+        .map(|(account, &last)| (last, account.as_str()))
+        .collect();
+    stale.sort_unstable();
+
+    for (last, account) in stale {
+        errors.push(
+            ValidationError::new(
+                ErrorCode::StaleBalanceAssertion,
+                format!(
+                    "Account {account} has not had a balance assertion since {last} ({} days)",
+                    (today - last).num_days()
+                ),
+                last,
+            )
+            .with_context(format!("threshold: {days} days")),
+        );
+    }
+}
+
+/// Warn about postings to accounts that have child accounts.
+///
+/// Runs after the main pass so that children opened after a posting still
+/// make its account a parent, matching beancount's `leafonly` plugin.
+fn validate_leaf_only(
+    state: &LedgerState,
+    directives: &[&Directive],
+    errors: &mut Vec<ValidationError>,
+) {
+    // Map each parent account in the opened account tree to one child
+    let mut children: HashMap<&str, &str> = HashMap::new();
+    for account in state.accounts.keys() {
+        let account = account.as_str();
+        let mut end = account.len();
+        while let Some(pos) = account[..end].rfind(':') {
+            children.entry(&account[..pos]).or_insert(account);
+            end = pos;
+        }
+    }
+
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        for posting in &txn.postings {
+            if let Some(child) = children.get(posting.account.as_str()) {
+                errors.push(
+                    ValidationError::new(
+                        ErrorCode::NonLeafPosting,
+                        format!(
+                            "Posting to non-leaf account {}; it has child accounts",
+                            posting.account
+                        ),
+                        txn.date,
+                    )
+                    .with_context(format!("child account: {child}")),
+                );
+            }
+        }
+    }
+}
+
+/// Warn about postings with zero units and transactions with no net effect.
+///
+/// A transaction has no net effect when, after interpolation, the units of
+/// every currency cancel out within each account it posts to. Transactions
+/// with postings held at cost are skipped, as those may exchange lots, and
+/// so are transactions whose postings are all zero, which E3005 covers.
+fn validate_zero_postings(directives: &[&Directive], errors: &mut Vec<ValidationError>) {
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+
+        let mut all_zero = true;
+        for posting in &txn.postings {
+            let Some(units) = posting.amount() else {
+                all_zero = false;
+                continue;
+            };
+            if units.number.is_zero() {
+                errors.push(ValidationError::new(
+                    ErrorCode::ZeroPosting,
+                    format!("Posting to {} has zero units ({units})", posting.account),
+                    txn.date,
+                ));
+            } else {
+                all_zero = false;
+            }
+        }
+
+        if all_zero || txn.postings.len() < 2 || txn.postings.iter().any(|p| p.cost.is_some()) {
+            continue;
+        }
+        let Ok(completed) = interpolate_transaction(txn) else {
+            continue;
+        };
+        let mut net: BTreeMap<(&str, &str), Decimal> = BTreeMap::new();
+        for posting in &completed.postings {
+            let Some(units) = posting.amount() else {
+                continue;
+            };
+            *net.entry((posting.account.as_str(), units.currency.as_str()))
+                .or_default() += units.number;
+        }
+        if !net.is_empty() && net.values().all(Decimal::is_zero) {
+            let accounts: BTreeSet<&str> = net.keys().map(|(account, _)| *account).collect();
+            errors.push(
+                ValidationError::new(
+                    ErrorCode::NoOpTransaction,
+                    "Transaction has no net effect; its postings cancel out within each account",
+                    txn.date,
+                )
+                .with_context(format!(
+                    "accounts: {}",
+                    accounts.into_iter().collect::<Vec<_>>().join(", ")
+                )),
+            );
+        }
+    }
+}
+
+/// Warn about balance sheet accounts holding non-operating currencies.
+///
+/// Accounts that declare their currencies on `open` are already checked by
+/// E5002, and positions held at cost (stocks, funds) are expected to be in
+/// other commodities, so only unconstrained accounts and plain amounts are
+/// considered. This mostly catches typos such as `USDD`. Each account and
+/// currency is reported once, at its first posting.
+fn validate_operating_currencies(
+    state: &LedgerState,
+    directives: &[&Directive],
+    errors: &mut Vec<ValidationError>,
+) {
+    let operating: HashSet<&str> = state
+        .options
+        .operating_currencies
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let mut reported: HashSet<(&str, &str)> = HashSet::new();
+
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        for posting in &txn.postings {
+            let account = posting.account.as_str();
+            let root = account.split(':').next().unwrap_or(account);
+            if root != "Assets" && root != "Liabilities" {
+                continue;
+            }
+            if posting.cost.is_some() {
+                continue;
+            }
+            let constrained = state
+                .accounts
+                .get(account)
+                .is_some_and(|state| !state.currencies.is_empty());
+            if constrained {
+                continue;
+            }
+            let Some(units) = posting.amount() else {
+                continue;
+            };
+            let currency = units.currency.as_str();
+            if operating.contains(currency) || !reported.insert((account, currency)) {
+                continue;
+            }
+            errors.push(
+                ValidationError::new(
+                    ErrorCode::NonOperatingCurrency,
+                    format!("Account {account} holds non-operating currency {currency}"),
+                    txn.date,
+                )
+                .with_context(format!(
+                    "operating currencies: {}; declare the account's currencies on open to allow it",
+                    state.options.operating_currencies.join(", ")
+                )),
+            );
+        }
+    }
+}
+
+/// Warn about transaction links that should point to a document but don't.
+///
+/// Only ledgers that tie documents to transactions with links are checked,
+/// i.e. those with at least one linked `document` directive. A link found on
+/// a single transaction is then taken to reference a document, since a link
+/// shared by several transactions groups them instead.
+fn validate_document_links(directives: &[&Directive], errors: &mut Vec<ValidationError>) {
+    let document_links: HashSet<&str> = directives
+        .iter()
+        .filter_map(|d| match d {
+            Directive::Document(doc) => Some(doc.links.iter().map(InternedStr::as_str)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    if document_links.is_empty() {
+        return;
+    }
+
+    // Transactions using each link, with the first one's date
+    let mut link_uses: HashMap<&str, (usize, NaiveDate)> = HashMap::new();
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        for link in &txn.links {
+            link_uses
+                .entry(link.as_str())
+                .and_modify(|(count, _)| *count += 1)
+                .or_insert((1, txn.date));
+        }
+    }
+
+    let mut missing: Vec<_> = link_uses
+        .into_iter()
+        .filter(|(link, (count, _))| *count == 1 && !document_links.contains(link))
+        .collect();
+    missing.sort_by_key(|&(link, (_, date))| (date, link));
+
+    for (link, (_, date)) in missing {
+        errors.push(ValidationError::new(
+            ErrorCode::DocumentLinkNotFound,
+            format!("Transaction link ^{link} has no matching document"),
+            date,
+        ));
+    }
+}
+
+/// Warn about files in the document directories that no document directive
+/// references.
+fn validate_unlinked_documents(
+    state: &LedgerState,
+    directives: &[&Directive],
+    errors: &mut Vec<ValidationError>,
+) {
+    let referenced: HashSet<std::path::PathBuf> = directives
+        .iter()
+        .filter_map(|d| match d {
+            Directive::Document(doc) => resolve_document_path(state, &doc.path).canonicalize().ok(),
+            _ => None,
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for dir in &state.options.documents_dirs {
+        collect_files(dir, &mut files);
+    }
+    files.sort();
+
+    for file in files {
+        let linked = file
+            .canonicalize()
+            .is_ok_and(|canonical| referenced.contains(&canonical));
+        if !linked {
+            errors.push(ValidationError::new(
+                ErrorCode::UnlinkedDocument,
+                format!(
+                    "Document {} is not referenced by any document directive",
+                    file.display()
+                ),
+                document_file_date(&file),
+            ));
+        }
+    }
+}
+
+/// Recursively collect regular files under a directory, skipping hidden
+/// entries.
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+/// Date of a document file: the `YYYY-MM-DD` prefix beancount's document
+/// naming convention uses, else the file's modification date.
+fn document_file_date(path: &Path) -> NaiveDate {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.get(..10)
+        .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok())
+        .or_else(|| {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            Some(chrono::DateTime::<Local>::from(modified).date_naive())
+        })
+        .unwrap_or_else(|| Local::now().date_naive())
+}
+
+/// Resolve a document path against the document base directory.
+fn resolve_document_path(state: &LedgerState, path: &str) -> std::path::PathBuf {
+    let doc_path = Path::new(path);
+    if doc_path.is_absolute() {
+        doc_path.to_path_buf()
+    } else if let Some(base) = &state.options.document_base {
+        base.join(doc_path)
+    } else {
+        doc_path.to_path_buf()
+    }
 }
 
 /// Valid account root types in beancount.
@@ -528,6 +1324,24 @@ fn validate_open(state: &mut LedgerState, open: &Open, errors: &mut Vec<Validati
             .with_context(open.account.to_string()),
         );
         // Continue anyway to allow further validation
+    } else if let Some(reason) = state.options.naming.check(&open.account) {
+        errors.push(
+            ValidationError::new(
+                ErrorCode::AccountNamingViolation,
+                format!("Account {} violates naming rules: {}", open.account, reason),
+                open.date,
+            )
+            .with_context(open.account.to_string()),
+        );
+    }
+
+    for currency in &open.currencies {
+        if let Err(e) = Currency::new(currency.clone()) {
+            errors.push(
+                ValidationError::new(ErrorCode::InvalidCurrency, e.to_string(), open.date)
+                    .with_context(open.account.to_string()),
+            );
+        }
     }
 
     // Check if already open
@@ -549,6 +1363,31 @@ fn validate_open(state: &mut LedgerState, open: &Open, errors: &mut Vec<Validati
         .and_then(|b| b.parse::<BookingMethod>().ok())
         .unwrap_or_default();
 
+    let negative_lots = match open.meta.get("negative_lots") {
+        Some(MetaValue::String(value)) => match value.parse() {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                errors.push(
+                    ValidationError::new(ErrorCode::InvalidMetadataValue, e, open.date)
+                        .with_context(open.account.to_string()),
+                );
+                None
+            }
+        },
+        Some(_) => {
+            errors.push(
+                ValidationError::new(
+                    ErrorCode::InvalidMetadataValue,
+                    "negative_lots must be \"allow\", \"warn\" or \"error\"",
+                    open.date,
+                )
+                .with_context(open.account.to_string()),
+            );
+            None
+        }
+        None => None,
+    };
+
     state.accounts.insert(
         open.account.clone(),
         AccountState {
@@ -556,6 +1395,7 @@ fn validate_open(state: &mut LedgerState, open: &Open, errors: &mut Vec<Validati
             closed: None,
             currencies: open.currencies.iter().cloned().collect(),
             booking,
+            negative_lots,
         },
     );
 
@@ -564,6 +1404,20 @@ fn validate_open(state: &mut LedgerState, open: &Open, errors: &mut Vec<Validati
         .insert(open.account.clone(), Inventory::new());
 }
 
+fn validate_commodity(
+    state: &mut LedgerState,
+    comm: &Commodity,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Err(e) = Currency::new(comm.currency.clone()) {
+        errors.push(
+            ValidationError::new(ErrorCode::InvalidCurrency, e.to_string(), comm.date)
+                .with_context(comm.currency.to_string()),
+        );
+    }
+    state.commodities.insert(comm.currency.clone());
+}
+
 fn validate_close(state: &mut LedgerState, close: &Close, errors: &mut Vec<ValidationError>) {
     match state.accounts.get_mut(&close.account) {
         Some(account_state) => {
@@ -574,26 +1428,8 @@ fn validate_close(state: &mut LedgerState, close: &Close, errors: &mut Vec<Valid
                     close.date,
                 ));
             } else {
-                // Check if account has non-zero balance (warning)
                 if let Some(inv) = state.inventories.get(&close.account) {
-                    if !inv.is_empty() {
-                        let positions: Vec<String> = inv
-                            .positions()
-                            .iter()
-                            .map(|p| format!("{} {}", p.units.number, p.units.currency))
-                            .collect();
-                        errors.push(
-                            ValidationError::new(
-                                ErrorCode::AccountCloseNotEmpty,
-                                format!(
-                                    "Cannot close account {} with non-zero balance",
-                                    close.account
-                                ),
-                                close.date,
-                            )
-                            .with_context(format!("balance: {}", positions.join(", "))),
-                        );
-                    }
+                    check_close_balance(inv, close, errors);
                 }
                 account_state.closed = Some(close.date);
             }
@@ -608,6 +1444,28 @@ fn validate_close(state: &mut LedgerState, close: &Close, errors: &mut Vec<Valid
     }
 }
 
+/// Warn when an account is closed with a non-zero balance.
+fn check_close_balance(inv: &Inventory, close: &Close, errors: &mut Vec<ValidationError>) {
+    if !inv.is_empty() {
+        let positions: Vec<String> = inv
+            .positions()
+            .iter()
+            .map(|p| format!("{} {}", p.units.number, p.units.currency))
+            .collect();
+        errors.push(
+            ValidationError::new(
+                ErrorCode::AccountCloseNotEmpty,
+                format!(
+                    "Cannot close account {} with non-zero balance",
+                    close.account
+                ),
+                close.date,
+            )
+            .with_context(format!("balance: {}", positions.join(", "))),
+        );
+    }
+}
+
 fn validate_transaction(
     state: &mut LedgerState,
     txn: &Transaction,
@@ -621,11 +1479,17 @@ fn validate_transaction(
     // Check each posting's account lifecycle and currency constraints
     validate_posting_accounts(state, txn, errors);
 
-    // Check transaction balance
-    validate_transaction_balance(txn, errors);
-
-    // Update inventories with booking validation
-    update_inventories(state, txn, errors);
+    // Fill in elided amounts so balancing and booking see the whole transaction
+    match interpolate_transaction(txn) {
+        Ok(completed) => {
+            validate_transaction_balance(txn, &completed, &state.options.tolerance, errors);
+            update_inventories(state, &completed, errors);
+        }
+        Err(error) => {
+            errors.push(error);
+            update_inventories(state, txn, errors);
+        }
+    }
 }
 
 /// Validate transaction structure (must have postings).
@@ -661,7 +1525,13 @@ fn validate_posting_accounts(
     for posting in &txn.postings {
         match state.accounts.get(&posting.account) {
             Some(account_state) => {
-                validate_account_lifecycle(txn, posting, account_state, errors);
+                validate_account_lifecycle(
+                    "Account",
+                    &posting.account,
+                    txn.date,
+                    account_state,
+                    errors,
+                );
                 validate_posting_currency(state, txn, posting, account_state, errors);
             }
             None => {
@@ -675,33 +1545,34 @@ fn validate_posting_accounts(
     }
 }
 
-/// Validate that an account is open at transaction time and not closed.
+/// Validate that an account is open on `date` and not yet closed.
+///
+/// `label` names the role of the account in the message, e.g. `"Account"`
+/// for postings or `"Pad source account"` for pads.
 fn validate_account_lifecycle(
-    txn: &Transaction,
-    posting: &Posting,
+    label: &str,
+    account: &str,
+    date: NaiveDate,
     account_state: &AccountState,
     errors: &mut Vec<ValidationError>,
 ) {
-    if txn.date < account_state.opened {
+    if date < account_state.opened {
         errors.push(ValidationError::new(
             ErrorCode::AccountNotOpen,
             format!(
-                "Account {} used on {} but not opened until {}",
-                posting.account, txn.date, account_state.opened
+                "{label} {account} used on {date} but not opened until {}",
+                account_state.opened
             ),
-            txn.date,
+            date,
         ));
     }
 
     if let Some(closed) = account_state.closed {
-        if txn.date >= closed {
+        if date >= closed {
             errors.push(ValidationError::new(
                 ErrorCode::AccountClosed,
-                format!(
-                    "Account {} used on {} but was closed on {}",
-                    posting.account, txn.date, closed
-                ),
-                txn.date,
+                format!("{label} {account} used on {date} but was closed on {closed}"),
+                date,
             ));
         }
     }
@@ -742,11 +1613,19 @@ fn validate_posting_currency(
 }
 
 /// Validate that the transaction balances within tolerance.
-fn validate_transaction_balance(txn: &Transaction, errors: &mut Vec<ValidationError>) {
-    let residuals = rustledger_booking::calculate_residual(txn);
+///
+/// Tolerances are inferred from `txn` as written; the residual is taken
+/// from `completed`, its interpolated form.
+fn validate_transaction_balance(
+    txn: &Transaction,
+    completed: &Transaction,
+    tolerance: &ToleranceOptions,
+    errors: &mut Vec<ValidationError>,
+) {
+    let tolerances = tolerance.infer(txn);
+    let residuals = rustledger_booking::calculate_residual(completed);
     for (currency, residual) in residuals {
-        // Use a default tolerance of 0.005 for now
-        if residual.abs() > Decimal::new(5, 3) {
+        if residual.abs() > tolerances.get(&currency) {
             errors.push(ValidationError::new(
                 ErrorCode::TransactionUnbalanced,
                 format!("Transaction does not balance: residual {residual} {currency}"),
@@ -756,6 +1635,27 @@ fn validate_transaction_balance(txn: &Transaction, errors: &mut Vec<ValidationEr
     }
 }
 
+/// Interpolate missing posting amounts.
+///
+/// Transactions without missing amounts are borrowed as-is. If an amount
+/// cannot be inferred because several postings leave the same currency open,
+/// returns the E3002 error; other failures fall through to the balance check.
+fn interpolate_transaction(txn: &Transaction) -> Result<Cow<'_, Transaction>, ValidationError> {
+    if txn.postings.iter().all(|p| p.amount().is_some()) {
+        return Ok(Cow::Borrowed(txn));
+    }
+
+    match rustledger_booking::interpolate(txn) {
+        Ok(result) => Ok(Cow::Owned(result.transaction)),
+        Err(InterpolationError::MultipleMissing { currency, .. }) => Err(ValidationError::new(
+            ErrorCode::MultipleInterpolation,
+            format!("Cannot interpolate: multiple postings missing amounts for {currency}"),
+            txn.date,
+        )),
+        Err(_) => Ok(Cow::Borrowed(txn)),
+    }
+}
+
 /// Update inventories with booking validation for each posting.
 fn update_inventories(
     state: &mut LedgerState,
@@ -770,19 +1670,58 @@ fn update_inventories(
             continue;
         };
 
-        let booking_method = state
-            .accounts
-            .get(&posting.account)
-            .map(|a| a.booking)
-            .unwrap_or_default();
+        let (booking_method, negative_lots) =
+            booking_policy(&state.accounts, &state.options, &posting.account);
+        book_posting(
+            inv,
+            posting,
+            units,
+            booking_method,
+            negative_lots,
+            txn,
+            errors,
+        );
+    }
+}
 
-        let is_reduction = units.number.is_sign_negative() && posting.cost.is_some();
+/// The booking method and negative lots policy in effect for an account.
+fn booking_policy(
+    accounts: &HashMap<InternedStr, AccountState>,
+    options: &ValidationOptions,
+    account: &InternedStr,
+) -> (BookingMethod, NegativeLotsPolicy) {
+    let account_state = accounts.get(account);
+    let booking_method = account_state.map(|a| a.booking).unwrap_or_default();
+    let negative_lots = account_state
+        .and_then(|a| a.negative_lots)
+        .unwrap_or(options.negative_lots);
+    (booking_method, negative_lots)
+}
 
-        if is_reduction {
-            process_inventory_reduction(inv, posting, units, booking_method, txn, errors);
-        } else {
-            process_inventory_addition(inv, posting, units, txn);
-        }
+/// Book one posting's units into its account's inventory.
+fn book_posting(
+    inv: &mut Inventory,
+    posting: &Posting,
+    units: &Amount,
+    booking_method: BookingMethod,
+    negative_lots: NegativeLotsPolicy,
+    txn: &Transaction,
+    errors: &mut Vec<ValidationError>,
+) {
+    let is_reduction = units.number.is_sign_negative() && posting.cost.is_some();
+
+    if is_reduction {
+        process_inventory_reduction(
+            inv,
+            posting,
+            units,
+            booking_method,
+            negative_lots,
+            txn,
+            errors,
+        );
+    } else {
+        process_inventory_addition(inv, posting, units, booking_method, txn, errors);
     }
 }
 
@@ -792,11 +1731,34 @@ fn process_inventory_reduction(
     posting: &Posting,
     units: &Amount,
     booking_method: BookingMethod,
+    negative_lots: NegativeLotsPolicy,
     txn: &Transaction,
     errors: &mut Vec<ValidationError>,
 ) {
-    match inv.reduce(units, posting.cost.as_ref(), booking_method) {
-        Ok(_) => {}
+    match inv.reduce_with_policy(
+        units,
+        posting.cost.as_ref(),
+        booking_method,
+        negative_lots,
+        txn.date,
+    ) {
+        Ok(result) => {
+            if negative_lots == NegativeLotsPolicy::Warn {
+                if let Some(short) = result.short {
+                    errors.push(
+                        ValidationError::new(
+                            ErrorCode::NegativeInventory,
+                            format!(
+                                "Reduction in {} went short by {}",
+                                posting.account, short.units
+                            ),
+                            txn.date,
+                        )
+                        .with_context(format!("currency: {}", units.currency)),
+                    );
+                }
+            }
+        }
         Err(rustledger_core::BookingError::InsufficientUnits {
             requested,
             available,
@@ -847,45 +1809,82 @@ fn process_inventory_reduction(
 }
 
 /// Process an inventory addition (buying/adding units).
+///
+/// Units at cost become a lot at the resolved cost, dated the transaction
+/// date unless the spec gives one. In STRICT accounts a cost spec that
+/// cannot be resolved is an error and the units are not added, since a lot
+/// without its cost would be matched wrongly by later reductions.
 fn process_inventory_addition(
     inv: &mut Inventory,
     posting: &Posting,
     units: &Amount,
+    booking_method: BookingMethod,
     txn: &Transaction,
+    errors: &mut Vec<ValidationError>,
 ) {
-    let position = if let Some(cost_spec) = &posting.cost {
-        if let Some(cost) = cost_spec.resolve(units.number, txn.date) {
-            rustledger_core::Position::with_cost(units.clone(), cost)
-        } else {
-            rustledger_core::Position::simple(units.clone())
-        }
-    } else {
-        rustledger_core::Position::simple(units.clone())
+    let position = match &posting.cost {
+        Some(cost_spec) => match cost_spec.resolve(units.number, txn.date) {
+            Some(cost) => rustledger_core::Position::with_cost(units.clone(), cost),
+            None if matches!(
+                booking_method,
+                BookingMethod::Strict | BookingMethod::StrictWithSize
+            ) =>
+            {
+                errors.push(
+                    ValidationError::new(
+                        ErrorCode::IncompleteCost,
+                        format!(
+                            "Addition of {} to {} needs a cost number and currency",
+                            units, posting.account
+                        ),
+                        txn.date,
+                    )
+                    .with_context(format!("cost spec: {cost_spec}")),
+                );
+                return;
+            }
+            None => rustledger_core::Position::simple(units.clone()),
+        },
+        None => rustledger_core::Position::simple(units.clone()),
     };
 
     inv.add(position);
 }
 
 fn validate_pad(state: &mut LedgerState, pad: &Pad, errors: &mut Vec<ValidationError>) {
-    // Check that the target account exists
-    if !state.accounts.contains_key(&pad.account) {
+    // Check that the target account exists and is open on the pad date
+    let Some(target_state) = state.accounts.get(&pad.account) else {
         errors.push(ValidationError::new(
             ErrorCode::AccountNotOpen,
             format!("Pad target account {} was never opened", pad.account),
             pad.date,
         ));
         return;
-    }
+    };
+    validate_account_lifecycle(
+        "Pad target account",
+        &pad.account,
+        pad.date,
+        target_state,
+        errors,
+    );
 
-    // Check that the source account exists
-    if !state.accounts.contains_key(&pad.source_account) {
+    // Check that the source account exists and is open on the pad date
+    let Some(source_state) = state.accounts.get(&pad.source_account) else {
         errors.push(ValidationError::new(
             ErrorCode::AccountNotOpen,
             format!("Pad source account {} was never opened", pad.source_account),
             pad.date,
         ));
         return;
-    }
+    };
+    validate_account_lifecycle(
+        "Pad source account",
+        &pad.source_account,
+        pad.date,
+        source_state,
+        errors,
+    );
 
     // Add to pending pads list for this account
     let pending_pad = PendingPad {
@@ -901,40 +1900,15 @@ fn validate_pad(state: &mut LedgerState, pad: &Pad, errors: &mut Vec<ValidationE
 }
 
 fn validate_balance(state: &mut LedgerState, bal: &Balance, errors: &mut Vec<ValidationError>) {
-    // Check account exists
-    if !state.accounts.contains_key(&bal.account) {
-        errors.push(ValidationError::new(
-            ErrorCode::AccountNotOpen,
-            format!("Account {} was never opened", bal.account),
-            bal.date,
-        ));
+    if !check_balance_account(state, bal, errors) {
         return;
     }
+    state.last_assertions.insert(bal.account.clone(), bal.date);
 
     // Check if there are pending pads for this account
     // Use get_mut instead of remove - a pad can apply to multiple currencies
     if let Some(pending_pads) = state.pending_pads.get_mut(&bal.account) {
-        // Check for multiple pads (E2004) - only warn if none have been used yet
-        if pending_pads.len() > 1 && !pending_pads.iter().any(|p| p.used) {
-            errors.push(
-                ValidationError::new(
-                    ErrorCode::MultiplePadForBalance,
-                    format!(
-                        "Multiple pad directives for {} {} before balance assertion",
-                        bal.account, bal.amount.currency
-                    ),
-                    bal.date,
-                )
-                .with_context(format!(
-                    "pad dates: {}",
-                    pending_pads
-                        .iter()
-                        .map(|p| p.date.to_string())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )),
-            );
-        }
+        check_multiple_pads(pending_pads, bal, errors);
 
         // Use the most recent pad
         if let Some(pending_pad) = pending_pads.last_mut() {
@@ -945,6 +1919,8 @@ fn validate_balance(state: &mut LedgerState, bal: &Balance, errors: &mut Vec<Val
                 let difference = expected - actual;
 
                 if difference != Decimal::ZERO {
+                    pad_currency_errors(&state.accounts, bal, pending_pad, errors);
+
                     // Add padding amount to target account
                     if let Some(target_inv) = state.inventories.get_mut(&bal.account) {
                         target_inv.add(Position::simple(Amount::new(
@@ -972,54 +1948,241 @@ fn validate_balance(state: &mut LedgerState, bal: &Balance, errors: &mut Vec<Val
 
     // Get inventory and check balance (no padding case)
     if let Some(inv) = state.inventories.get(&bal.account) {
-        let actual = inv.units(&bal.amount.currency);
-        let expected = bal.amount.number;
-        let difference = (actual - expected).abs();
+        check_balance(inv, bal, errors);
+    }
+}
+
+/// Check that a balance assertion's account was opened.
+fn check_balance_account(
+    state: &LedgerState,
+    bal: &Balance,
+    errors: &mut Vec<ValidationError>,
+) -> bool {
+    let opened = state.accounts.contains_key(&bal.account);
+    if opened {
+        check_entry_after_close(state, "Balance assertion", &bal.account, bal.date, errors);
+    } else {
+        errors.push(ValidationError::new(
+            ErrorCode::AccountNotOpen,
+            format!("Account {} was never opened", bal.account),
+            bal.date,
+        ));
+    }
+    opened
+}
+
+/// Flag a balance, note or document directive dated after its account was
+/// closed, unless [`ValidationOptions::allow_entries_after_close`] is set.
+///
+/// `kind` names the directive in the message, e.g. `"Note"`.
+fn check_entry_after_close(
+    state: &LedgerState,
+    kind: &str,
+    account: &InternedStr,
+    date: NaiveDate,
+    errors: &mut Vec<ValidationError>,
+) {
+    if state.options.allow_entries_after_close {
+        return;
+    }
+    let closed = state.accounts.get(account).and_then(|a| a.closed);
+    if let Some(closed) = closed.filter(|&closed| date >= closed) {
+        errors.push(ValidationError::new(
+            ErrorCode::AccountClosed,
+            format!("{kind} for {account} on {date} but the account was closed on {closed}"),
+            date,
+        ));
+    }
+}
+
+/// Check for multiple pads (E2004) - only warn if none have been used yet.
+fn check_multiple_pads(
+    pending_pads: &[PendingPad],
+    bal: &Balance,
+    errors: &mut Vec<ValidationError>,
+) {
+    if pending_pads.len() > 1 && !pending_pads.iter().any(|p| p.used) {
+        errors.push(
+            ValidationError::new(
+                ErrorCode::MultiplePadForBalance,
+                format!(
+                    "Multiple pad directives for {} {} before balance assertion",
+                    bal.account, bal.amount.currency
+                ),
+                bal.date,
+            )
+            .with_context(format!(
+                "pad dates: {}",
+                pending_pads
+                    .iter()
+                    .map(|p| p.date.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        );
+    }
+}
+
+/// Check that the padded adjustment's currency is allowed in both accounts.
+///
+/// The inserted adjustment posts to the padded account and the pad's source
+/// account, so the currency must be allowed by each of them.
+fn pad_currency_errors(
+    accounts: &HashMap<InternedStr, AccountState>,
+    bal: &Balance,
+    pending_pad: &PendingPad,
+    errors: &mut Vec<ValidationError>,
+) {
+    for account in [&bal.account, &pending_pad.source_account] {
+        if let Some(account_state) = accounts.get(account) {
+            if !account_state.currencies.is_empty()
+                && !account_state.currencies.contains(&bal.amount.currency)
+            {
+                errors.push(
+                    ValidationError::new(
+                        ErrorCode::CurrencyNotAllowed,
+                        format!(
+                            "Currency {} not allowed in account {}",
+                            bal.amount.currency, account
+                        ),
+                        pending_pad.date,
+                    )
+                    .with_context(format!(
+                        "pad from {} to {} for balance on {}",
+                        pending_pad.source_account, bal.account, bal.date
+                    )),
+                );
+            }
+        }
+    }
+}
+
+/// Check a balance assertion against the account's inventory.
+fn check_balance(inv: &Inventory, bal: &Balance, errors: &mut Vec<ValidationError>) {
+    let actual = inv.units(&bal.amount.currency);
+    let expected = bal.amount.number;
+    let difference = (actual - expected).abs();
+
+    // Determine tolerance and whether it was explicitly specified
+    let (tolerance, is_explicit) = if let Some(t) = bal.tolerance {
+        (t, true)
+    } else {
+        (bal.amount.inferred_tolerance(), false)
+    };
 
-        // Determine tolerance and whether it was explicitly specified
-        let (tolerance, is_explicit) = if let Some(t) = bal.tolerance {
-            (t, true)
+    if difference > tolerance {
+        // Use E2002 for explicit tolerance, E2001 for inferred
+        let error_code = if is_explicit {
+            ErrorCode::BalanceToleranceExceeded
         } else {
-            (bal.amount.inferred_tolerance(), false)
+            ErrorCode::BalanceAssertionFailed
         };
 
-        if difference > tolerance {
-            // Use E2002 for explicit tolerance, E2001 for inferred
-            let error_code = if is_explicit {
-                ErrorCode::BalanceToleranceExceeded
-            } else {
-                ErrorCode::BalanceAssertionFailed
-            };
+        let message = if is_explicit {
+            format!(
+                "Balance exceeds explicit tolerance for {}: expected {} {} ~ {}, got {} {} (difference: {})",
+                bal.account,
+                expected,
+                bal.amount.currency,
+                tolerance,
+                actual,
+                bal.amount.currency,
+                difference
+            )
+        } else {
+            // Compare units only: balance assertions ignore cost basis
+            let actual_units: Inventory = inv
+                .positions()
+                .iter()
+                .filter(|p| p.units.currency == bal.amount.currency)
+                .map(|p| Position::simple(p.units.clone()))
+                .collect();
+            let expected_units: Inventory =
+                std::iter::once(Position::simple(bal.amount.clone())).collect();
 
-            let message = if is_explicit {
-                format!(
-                    "Balance exceeds explicit tolerance for {}: expected {} {} ~ {}, got {} {} (difference: {})",
-                    bal.account,
-                    expected,
-                    bal.amount.currency,
-                    tolerance,
-                    actual,
-                    bal.amount.currency,
-                    difference
-                )
-            } else {
+            format!(
+                "Balance assertion failed for {}: expected {} {}, got {} {} ({})",
+                bal.account,
+                expected,
+                bal.amount.currency,
+                actual,
+                bal.amount.currency,
+                actual_units.diff(&expected_units)
+            )
+        };
+
+        errors.push(
+            ValidationError::new(error_code, message, bal.date)
+                .with_context(format!("difference: {difference}, tolerance: {tolerance}")),
+        );
+    }
+}
+
+/// Check that a price is positive, quoted in another currency, and agrees
+/// with any other price for the same pair on the same date.
+///
+/// Identical duplicates are common when prices are fetched repeatedly and
+/// are not reported.
+fn validate_price(state: &mut LedgerState, price: &Price, errors: &mut Vec<ValidationError>) {
+    let quote = &price.amount.currency;
+    if *quote == price.currency {
+        errors.push(
+            ValidationError::new(
+                ErrorCode::SelfReferentialPrice,
+                format!("Price of {} is quoted in {quote} itself", price.currency),
+                price.date,
+            )
+            .with_context(price.to_string()),
+        );
+        return;
+    }
+
+    if price.amount.number <= Decimal::ZERO {
+        errors.push(
+            ValidationError::new(
+                ErrorCode::NonPositivePrice,
                 format!(
-                    "Balance assertion failed for {}: expected {} {}, got {} {}",
-                    bal.account, expected, bal.amount.currency, actual, bal.amount.currency
-                )
-            };
+                    "Price of {} in {quote} must be positive, got {}",
+                    price.currency, price.amount.number
+                ),
+                price.date,
+            )
+            .with_context(price.to_string()),
+        );
+        return;
+    }
 
+    let key = (price.currency.clone(), quote.clone(), price.date);
+    match state.prices.get(&key) {
+        Some(&previous) if previous != price.amount.number => {
             errors.push(
-                ValidationError::new(error_code, message, bal.date)
-                    .with_context(format!("difference: {difference}, tolerance: {tolerance}")),
+                ValidationError::new(
+                    ErrorCode::ConflictingPrice,
+                    format!(
+                        "Conflicting prices for {}/{quote} on {}: {previous} and {}",
+                        price.currency, price.date, price.amount.number
+                    ),
+                    price.date,
+                )
+                .with_context(price.to_string()),
             );
         }
+        Some(_) => {}
+        None => {
+            state.prices.insert(key, price.amount.number);
+        }
     }
 }
 
+fn validate_note(state: &LedgerState, note: &Note, errors: &mut Vec<ValidationError>) {
+    check_entry_after_close(state, "Note", &note.account, note.date, errors);
+}
+
 fn validate_document(state: &LedgerState, doc: &Document, errors: &mut Vec<ValidationError>) {
     // Check account exists
-    if !state.accounts.contains_key(&doc.account) {
+    if state.accounts.contains_key(&doc.account) {
+        check_entry_after_close(state, "Document", &doc.account, doc.date, errors);
+    } else {
         errors.push(ValidationError::new(
             ErrorCode::AccountNotOpen,
             format!("Account {} was never opened", doc.account),
@@ -1029,15 +2192,7 @@ fn validate_document(state: &LedgerState, doc: &Document, errors: &mut Vec<Valid
 
     // Check if document file exists (if enabled)
     if state.options.check_documents {
-        let doc_path = Path::new(&doc.path);
-
-        let full_path = if doc_path.is_absolute() {
-            doc_path.to_path_buf()
-        } else if let Some(base) = &state.options.document_base {
-            base.join(doc_path)
-        } else {
-            doc_path.to_path_buf()
-        };
+        let full_path = resolve_document_path(state, &doc.path);
 
         if !full_path.exists() {
             errors.push(
@@ -1056,7 +2211,7 @@ fn validate_document(state: &LedgerState, doc: &Document, errors: &mut Vec<Valid
 mod tests {
     use super::*;
     use rust_decimal_macros::dec;
-    use rustledger_core::{Amount, NaiveDate, Posting};
+    use rustledger_core::{Amount, InternedSet, NaiveDate, Posting};
 
     fn date(year: i32, month: u32, day: u32) -> NaiveDate {
         NaiveDate::from_ymd_opt(year, month, day).unwrap()
@@ -1172,11 +2327,11 @@ mod tests {
         ];
 
         let errors = validate(&directives);
-        assert!(
-            errors
-                .iter()
-                .any(|e| e.code == ErrorCode::BalanceAssertionFailed)
-        );
+        let error = errors
+            .iter()
+            .find(|e| e.code == ErrorCode::BalanceAssertionFailed)
+            .expect("expected E2001");
+        assert!(error.message.ends_with("(extra 500.00 USD)"));
     }
 
     #[test]
@@ -1206,34 +2361,157 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_currency_not_allowed() {
+    fn test_validate_errors_point_at_directive() {
+        // Out of date order, so sorted positions differ from input indices
         let directives = vec![
-            Directive::Open(
-                Open::new(date(2024, 1, 1), "Assets:Bank").with_currencies(vec!["USD".into()]),
-            ),
-            Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
             Directive::Transaction(
-                Transaction::new(date(2024, 1, 15), "Test")
-                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(100.00), "EUR"))) // EUR not allowed!
-                    .with_posting(Posting::new(
-                        "Income:Salary",
-                        Amount::new(dec!(-100.00), "EUR"),
-                    )),
+                Transaction::new(date(2024, 1, 15), "Unbalanced")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-50), "USD")))
+                    .with_posting(Posting::new("Expenses:Food", Amount::new(dec!(40), "USD"))),
             ),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Food")),
+            Directive::Open(Open::new(date(2024, 1, 2), "Assets:Bank")),
         ];
 
-        let errors = validate(&directives);
-        assert!(
-            errors
-                .iter()
-                .any(|e| e.code == ErrorCode::CurrencyNotAllowed)
-        );
+        for errors in [
+            validate(&directives),
+            validate_parallel(&directives, ValidationOptions::default()),
+        ] {
+            let located: Vec<_> = errors.iter().map(|e| (e.code, e.directive)).collect();
+            assert_eq!(
+                located,
+                [
+                    (ErrorCode::AccountAlreadyOpen, Some(3)),
+                    (ErrorCode::TransactionUnbalanced, Some(0)),
+                ]
+            );
+        }
     }
 
     #[test]
-    fn test_validate_future_date_warning() {
-        // Create a date in the future
-        let future_date = Local::now().date_naive() + chrono::Duration::days(30);
+    fn test_validate_balance_tolerance_options() {
+        // Off by 0.004 BTC: within half the last digit of 1.00 BTC but not
+        // of 1.000 BTC
+        let directives = |paid: Decimal| {
+            vec![
+                Directive::Open(Open::new(date(2024, 1, 1), "Assets:Wallet")),
+                Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Fees")),
+                Directive::Transaction(
+                    Transaction::new(date(2024, 1, 15), "Fee")
+                        .with_posting(Posting::new("Assets:Wallet", Amount::new(-paid, "BTC")))
+                        .with_posting(Posting::new(
+                            "Expenses:Fees",
+                            Amount::new(dec!(0.996), "BTC"),
+                        )),
+                ),
+            ]
+        };
+        // Both validators must agree
+        let unbalanced = |directives: &[Directive], options: ValidationOptions| {
+            validate_parallel(directives, options.clone())
+                .iter()
+                .chain(&validate_with_options(directives, options))
+                .filter(|e| e.code == ErrorCode::TransactionUnbalanced)
+                .count()
+        };
+
+        let options = ValidationOptions::default();
+        assert_eq!(unbalanced(&directives(dec!(1.00)), options.clone()), 0);
+        assert_eq!(unbalanced(&directives(dec!(1.000)), options), 2);
+
+        let options = ValidationOptions {
+            tolerance: ToleranceOptions {
+                multiplier: dec!(0.1),
+                ..ToleranceOptions::default()
+            },
+            ..ValidationOptions::default()
+        };
+        assert_eq!(unbalanced(&directives(dec!(1.00)), options), 2);
+
+        let options = ValidationOptions {
+            tolerance: ToleranceOptions {
+                defaults: HashMap::from([("BTC".to_string(), dec!(0.01))]),
+                ..ToleranceOptions::default()
+            },
+            ..ValidationOptions::default()
+        };
+        assert_eq!(unbalanced(&directives(dec!(1.000)), options), 0);
+    }
+
+    #[test]
+    fn test_validate_interpolates_missing_amounts() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Food")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Drinks")),
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, 15), "Lunch")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-50), "USD")))
+                    .with_posting(Posting::auto("Expenses:Food")),
+            ),
+            Directive::Balance(Balance::new(
+                date(2024, 1, 16),
+                "Expenses:Food",
+                Amount::new(dec!(50), "USD"),
+            )),
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, 20), "Ambiguous")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-30), "USD")))
+                    .with_posting(Posting::with_incomplete(
+                        "Expenses:Food",
+                        rustledger_core::IncompleteAmount::CurrencyOnly("USD".into()),
+                    ))
+                    .with_posting(Posting::with_incomplete(
+                        "Expenses:Drinks",
+                        rustledger_core::IncompleteAmount::CurrencyOnly("USD".into()),
+                    )),
+            ),
+        ];
+
+        let errors = validate(&directives);
+        let codes: Vec<_> = errors.iter().map(|e| (e.code, e.date)).collect();
+        assert_eq!(
+            codes,
+            [(ErrorCode::MultipleInterpolation, date(2024, 1, 20))]
+        );
+        assert!(errors[0].message.ends_with("missing amounts for USD"));
+        let parallel: Vec<_> = validate_parallel(&directives, ValidationOptions::default())
+            .iter()
+            .map(|e| (e.code, e.date))
+            .collect();
+        assert_eq!(parallel, codes);
+    }
+
+    #[test]
+    fn test_validate_currency_not_allowed() {
+        let directives = vec![
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Assets:Bank").with_currencies(vec!["USD".into()]),
+            ),
+            Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, 15), "Test")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(100.00), "EUR"))) // EUR not allowed!
+                    .with_posting(Posting::new(
+                        "Income:Salary",
+                        Amount::new(dec!(-100.00), "EUR"),
+                    )),
+            ),
+        ];
+
+        let errors = validate(&directives);
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.code == ErrorCode::CurrencyNotAllowed)
+        );
+    }
+
+    #[test]
+    fn test_validate_future_date_warning() {
+        // Create a date in the future
+        let future_date = Local::now().date_naive() + chrono::Duration::days(30);
 
         let directives = vec![Directive::Open(Open {
             date: future_date,
@@ -1262,6 +2540,200 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_future_date_horizon() {
+        let today = Local::now().date_naive();
+        let directives: Vec<_> = [5, 10]
+            .into_iter()
+            .map(|days| {
+                Directive::Open(Open::new(
+                    today + chrono::Duration::days(days),
+                    format!("Assets:Bank{days}"),
+                ))
+            })
+            .collect();
+
+        let options = ValidationOptions {
+            warn_future_dates: true,
+            future_date_horizon_days: 7,
+            ..Default::default()
+        };
+        let warnings: Vec<_> = validate_with_options(&directives, options)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::FutureDate)
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].date, today + chrono::Duration::days(10));
+        assert!(warnings[0].message.contains("more than 7 days ahead"));
+    }
+
+    #[test]
+    fn test_validate_before_first_date() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2019, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2019, 1, 1), "Income:Salary")),
+            Directive::Transaction(
+                Transaction::new(date(2019, 12, 31), "Typo year")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(10), "USD")))
+                    .with_posting(Posting::new("Income:Salary", Amount::new(dec!(-10), "USD"))),
+            ),
+            Directive::Transaction(
+                Transaction::new(date(2020, 1, 1), "Salary")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(10), "USD")))
+                    .with_posting(Posting::new("Income:Salary", Amount::new(dec!(-10), "USD"))),
+            ),
+        ];
+
+        // Off unless a first date is set
+        let errors = validate(&directives);
+        assert!(!errors.iter().any(|e| e.code == ErrorCode::BeforeFirstDate));
+
+        let options = ValidationOptions {
+            first_date: Some(date(2020, 1, 1)),
+            ..Default::default()
+        };
+        let errors: Vec<_> = validate_with_options(&directives, options)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::BeforeFirstDate)
+            .collect();
+        // Open directives before the first date are declarations, not entries
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].date, date(2019, 12, 31));
+        assert_eq!(errors[0].code.severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn test_validate_future_date_skips_forecast() {
+        let future_date = Local::now().date_naive() + chrono::Duration::days(30);
+        let forecast = |flag: char, meta: bool| {
+            let mut txn = Transaction::new(future_date, "Rent").with_flag(flag);
+            if meta {
+                txn.meta.insert(
+                    "forecast".to_string(),
+                    MetaValue::String("MONTHLY".to_string()),
+                );
+            }
+            Directive::Transaction(txn)
+        };
+
+        let options = ValidationOptions {
+            warn_future_dates: true,
+            ..Default::default()
+        };
+        let future_warnings = |directive: Directive| {
+            validate_with_options(&[directive], options.clone())
+                .iter()
+                .filter(|e| e.code == ErrorCode::FutureDate)
+                .count()
+        };
+
+        assert_eq!(future_warnings(forecast('#', true)), 0);
+        assert_eq!(future_warnings(forecast('#', false)), 1);
+        assert_eq!(future_warnings(forecast('*', true)), 1);
+    }
+
+    #[test]
+    fn test_validate_non_leaf_posting_warning() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, 15), "Deposit")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(100), "USD")))
+                    .with_posting(Posting::new(
+                        "Income:Salary",
+                        Amount::new(dec!(-100), "USD"),
+                    )),
+            ),
+            // Child opened after the posting still makes Assets:Bank a parent
+            Directive::Open(Open::new(date(2024, 2, 1), "Assets:Bank:Checking")),
+        ];
+
+        // Opt-in: no warning by default
+        let errors = validate(&directives);
+        assert!(!errors.iter().any(|e| e.code == ErrorCode::NonLeafPosting));
+
+        let options = ValidationOptions {
+            warn_non_leaf_postings: true,
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives, options);
+        let warnings: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::NonLeafPosting)
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("Assets:Bank"));
+        assert_eq!(
+            warnings[0].context.as_deref(),
+            Some("child account: Assets:Bank:Checking")
+        );
+        assert!(ErrorCode::NonLeafPosting.is_warning());
+    }
+
+    #[test]
+    fn test_validate_zero_postings_and_no_op_transactions() {
+        let deposit = |day, a: &str, b: &str, n| {
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, day), "Import")
+                    .with_posting(Posting::new(a, Amount::new(n, "USD")))
+                    .with_posting(Posting::new(b, Amount::new(-n, "USD"))),
+            )
+        };
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
+            deposit(10, "Assets:Bank", "Income:Salary", dec!(100)),
+            deposit(11, "Assets:Bank", "Income:Salary", dec!(0)),
+            deposit(12, "Assets:Bank", "Assets:Bank", dec!(50)),
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, 13), "Import")
+                    .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(20), "USD")))
+                    .with_posting(Posting::auto("Assets:Bank")),
+            ),
+        ];
+
+        // Opt-in: no warning by default
+        let errors = validate(&directives);
+        assert!(
+            !errors
+                .iter()
+                .any(|e| matches!(e.code, ErrorCode::ZeroPosting | ErrorCode::NoOpTransaction))
+        );
+
+        let options = ValidationOptions {
+            warn_zero_postings: true,
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives, options.clone());
+        let found: Vec<_> = errors
+            .iter()
+            .filter(|e| matches!(e.code, ErrorCode::ZeroPosting | ErrorCode::NoOpTransaction))
+            .map(|e| (e.code, e.date))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ErrorCode::ZeroPosting, date(2024, 1, 11)),
+                (ErrorCode::ZeroPosting, date(2024, 1, 11)),
+                (ErrorCode::NoOpTransaction, date(2024, 1, 12)),
+                (ErrorCode::NoOpTransaction, date(2024, 1, 13)),
+            ]
+        );
+        let no_op = errors
+            .iter()
+            .find(|e| e.code == ErrorCode::NoOpTransaction)
+            .unwrap();
+        assert_eq!(no_op.context.as_deref(), Some("accounts: Assets:Bank"));
+        assert!(ErrorCode::ZeroPosting.is_warning());
+
+        let parallel = validate_parallel(&directives, options);
+        assert_eq!(
+            parallel.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_validate_document_not_found() {
         let directives = vec![
@@ -1270,8 +2742,8 @@ mod tests {
                 date: date(2024, 1, 15),
                 account: "Assets:Bank".into(),
                 path: "/nonexistent/path/to/document.pdf".to_string(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 meta: Default::default(),
             }),
         ];
@@ -1301,8 +2773,8 @@ mod tests {
             date: date(2024, 1, 15),
             account: "Assets:Unknown".into(),
             path: "receipt.pdf".to_string(),
-            tags: vec![],
-            links: vec![],
+            tags: InternedSet::new(),
+            links: InternedSet::new(),
             meta: Default::default(),
         })];
 
@@ -1313,6 +2785,230 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_non_operating_currency() {
+        let transfer = |day: u32, account: &str, currency: &str| {
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, day), "Transfer")
+                    .with_posting(Posting::new(account, Amount::new(dec!(10), currency)))
+                    .with_posting(Posting::new(
+                        "Income:Salary",
+                        Amount::new(dec!(-10), currency),
+                    )),
+            )
+        };
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Assets:Travel").with_currencies(vec!["JPY".into()]),
+            ),
+            Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
+            transfer(10, "Assets:Bank", "USD"),
+            transfer(11, "Assets:Bank", "USDD"),
+            transfer(12, "Assets:Bank", "USDD"),
+            transfer(13, "Assets:Travel", "JPY"),
+        ];
+
+        // Opt-in
+        let errors = validate(&directives);
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::NonOperatingCurrency)
+        );
+
+        let options = ValidationOptions {
+            warn_non_operating_currencies: true,
+            operating_currencies: vec!["USD".to_string()],
+            ..Default::default()
+        };
+        let warnings: Vec<_> = validate_with_options(&directives, options)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::NonOperatingCurrency)
+            .collect();
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].message.contains("Assets:Bank"));
+        assert!(warnings[0].message.contains("USDD"));
+        assert_eq!(warnings[0].date, date(2024, 1, 11));
+        assert!(warnings[0].code.is_warning());
+    }
+
+    #[test]
+    fn test_validate_prices() {
+        let price = |day: u32, currency: &str, number: Decimal, quote: &str| {
+            Directive::Price(Price::new(
+                date(2024, 1, day),
+                currency,
+                Amount::new(number, quote),
+            ))
+        };
+        let directives = vec![
+            price(15, "HOOL", dec!(520.00), "USD"),
+            price(15, "HOOL", dec!(520.00), "USD"),
+            price(15, "HOOL", dec!(525.00), "USD"),
+            price(15, "HOOL", dec!(470.00), "EUR"),
+            price(16, "HOOL", dec!(525.00), "USD"),
+            price(16, "ACME", dec!(0), "USD"),
+            price(16, "ACME", dec!(-3), "USD"),
+            price(17, "USD", dec!(1), "USD"),
+        ];
+
+        let errors = validate(&directives);
+        let codes: Vec<_> = errors.iter().map(|e| (e.code, e.date)).collect();
+        assert_eq!(
+            codes,
+            [
+                (ErrorCode::ConflictingPrice, date(2024, 1, 15)),
+                (ErrorCode::NonPositivePrice, date(2024, 1, 16)),
+                (ErrorCode::NonPositivePrice, date(2024, 1, 16)),
+                (ErrorCode::SelfReferentialPrice, date(2024, 1, 17)),
+            ]
+        );
+        assert!(errors[0].message.contains("HOOL/USD"));
+        assert!(errors[0].message.contains("520.00 and 525.00"));
+        assert!(errors[0].code.is_warning());
+        assert!(!errors[1].code.is_warning());
+        let parallel: Vec<_> = validate_parallel(&directives, ValidationOptions::default())
+            .iter()
+            .map(|e| (e.code, e.date))
+            .collect();
+        assert_eq!(parallel, codes);
+    }
+
+    #[test]
+    fn test_validate_currency_codes() {
+        let directives = vec![
+            Directive::Commodity(Commodity::new(date(2024, 1, 1), "BRK.B")),
+            Directive::Commodity(Commodity::new(date(2024, 1, 1), "usd")),
+            Directive::Open(
+                Open::new(date(2024, 1, 2), "Assets:Bank")
+                    .with_currencies(vec!["EUR".into(), "US$".into()]),
+            ),
+        ];
+
+        let errors = validate(&directives);
+        let codes: Vec<_> = errors.iter().map(|e| (e.code, e.date)).collect();
+        assert_eq!(
+            codes,
+            [
+                (ErrorCode::InvalidCurrency, date(2024, 1, 1)),
+                (ErrorCode::InvalidCurrency, date(2024, 1, 2)),
+            ]
+        );
+        assert_eq!(errors[0].code.code(), "E5007");
+        assert!(errors[1].message.contains("\"US$\""));
+        let parallel: Vec<_> = validate_parallel(&directives, ValidationOptions::default())
+            .iter()
+            .map(|e| (e.code, e.date))
+            .collect();
+        assert_eq!(parallel, codes);
+    }
+
+    fn linked_purchase(day: u32, link: &str) -> Directive {
+        Directive::Transaction(
+            Transaction::new(date(2024, 1, day), "Purchase")
+                .with_link(link)
+                .with_posting(Posting::new("Expenses:Food", Amount::new(dec!(10), "USD")))
+                .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-10), "USD"))),
+        )
+    }
+
+    #[test]
+    fn test_validate_document_links() {
+        let mut directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Food")),
+            linked_purchase(10, "receipt-1"),
+            linked_purchase(11, "receipt-2"),
+            // A link shared by transactions groups them; not a document reference
+            linked_purchase(12, "trip"),
+            linked_purchase(13, "trip"),
+        ];
+
+        let options = ValidationOptions {
+            warn_document_links: true,
+            ..Default::default()
+        };
+
+        // Ledgers that never link documents are not checked
+        let errors = validate_with_options(&directives, options.clone());
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::DocumentLinkNotFound)
+        );
+
+        directives.push(Directive::Document(Document {
+            date: date(2024, 1, 10),
+            account: "Assets:Bank".into(),
+            path: "receipt-1.pdf".to_string(),
+            tags: InternedSet::new(),
+            links: std::iter::once("receipt-1").collect(),
+            meta: Default::default(),
+        }));
+
+        // Off by default
+        let errors = validate(&directives);
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::DocumentLinkNotFound)
+        );
+
+        let errors = validate_with_options(&directives, options);
+        let missing: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::DocumentLinkNotFound)
+            .collect();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            missing[0].message,
+            "Transaction link ^receipt-2 has no matching document"
+        );
+        assert_eq!(missing[0].date, date(2024, 1, 11));
+        assert!(ErrorCode::DocumentLinkNotFound.is_warning());
+    }
+
+    #[test]
+    fn test_validate_unlinked_documents() {
+        let dir =
+            std::env::temp_dir().join(format!("rledger-unlinked-documents-{}", std::process::id()));
+        let account_dir = dir.join("Assets").join("Bank");
+        std::fs::create_dir_all(&account_dir).unwrap();
+        std::fs::write(account_dir.join("2024-01-15.statement.pdf"), b"").unwrap();
+        std::fs::write(account_dir.join("2024-02-15.statement.pdf"), b"").unwrap();
+        std::fs::write(account_dir.join(".DS_Store"), b"").unwrap();
+
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Document(Document {
+                date: date(2024, 1, 15),
+                account: "Assets:Bank".into(),
+                path: "Assets/Bank/2024-01-15.statement.pdf".to_string(),
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
+                meta: Default::default(),
+            }),
+        ];
+
+        let options = ValidationOptions {
+            warn_unlinked_documents: true,
+            document_base: Some(dir.clone()),
+            documents_dirs: vec![dir.clone()],
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives, options);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let unlinked: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::UnlinkedDocument)
+            .collect();
+        assert_eq!(unlinked.len(), 1);
+        assert!(unlinked[0].message.contains("2024-02-15.statement.pdf"));
+        assert_eq!(unlinked[0].date, date(2024, 2, 15));
+    }
+
     #[test]
     fn test_error_code_is_warning() {
         assert!(!ErrorCode::AccountNotOpen.is_warning());
@@ -1407,6 +3103,216 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_pad_before_target_opened() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 2, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Equity:Opening")),
+            Directive::Pad(Pad::new(date(2024, 1, 15), "Assets:Bank", "Equity:Opening")),
+        ];
+
+        let errors = validate(&directives);
+        assert!(
+            errors.iter().any(|e| e.code == ErrorCode::AccountNotOpen
+                && e.message.contains("Pad target account Assets:Bank")),
+            "Should error for pad before target account is opened: {errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_pad_source_closed() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Equity:Opening")),
+            Directive::Close(Close::new(date(2024, 3, 1), "Equity:Opening")),
+            Directive::Pad(Pad::new(date(2024, 3, 2), "Assets:Bank", "Equity:Opening")),
+            Directive::Balance(Balance::new(
+                date(2024, 3, 3),
+                "Assets:Bank",
+                Amount::new(dec!(100.00), "USD"),
+            )),
+        ];
+
+        let errors = validate(&directives);
+        assert!(
+            errors.iter().any(|e| e.code == ErrorCode::AccountClosed
+                && e.message.contains("Pad source account Equity:Opening")),
+            "Should error for pad from a closed source account: {errors:?}"
+        );
+        // The pad still applies, so the balance itself does not fail
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::BalanceAssertionFailed),
+            "Balance should still be satisfied by the pad: {errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_entries_after_close() {
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            // Same-day entries come before the close
+            Directive::Balance(Balance::new(
+                date(2024, 3, 1),
+                "Assets:Bank",
+                Amount::new(dec!(0), "USD"),
+            )),
+            Directive::Close(Close::new(date(2024, 3, 1), "Assets:Bank")),
+            Directive::Balance(Balance::new(
+                date(2024, 3, 2),
+                "Assets:Bank",
+                Amount::new(dec!(0), "USD"),
+            )),
+            Directive::Note(Note::new(
+                date(2024, 3, 3),
+                "Assets:Bank",
+                "Called the bank",
+            )),
+            Directive::Document(Document::new(date(2024, 3, 4), "Assets:Bank", "final.pdf")),
+        ];
+
+        let errors = validate(&directives);
+        let closed: Vec<&str> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::AccountClosed)
+            .map(|e| e.message.as_str())
+            .collect();
+        assert_eq!(
+            closed,
+            [
+                "Balance assertion for Assets:Bank on 2024-03-02 but the account was closed on 2024-03-01",
+                "Note for Assets:Bank on 2024-03-03 but the account was closed on 2024-03-01",
+                "Document for Assets:Bank on 2024-03-04 but the account was closed on 2024-03-01",
+            ]
+        );
+
+        let options = ValidationOptions {
+            allow_entries_after_close: true,
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives, options.clone());
+        assert!(
+            !errors.iter().any(|e| e.code == ErrorCode::AccountClosed),
+            "Entries after close should be allowed: {errors:?}"
+        );
+        let messages = |errors: Vec<ValidationError>| -> Vec<String> {
+            errors.into_iter().map(|e| e.message).collect()
+        };
+        assert_eq!(
+            messages(validate_parallel(&directives, options)),
+            messages(errors)
+        );
+    }
+
+    #[test]
+    fn test_validate_stale_balance_assertions() {
+        let today = Local::now().date_naive();
+        let days_ago = |days: i64| today - chrono::Duration::days(days);
+        let zero = |day: NaiveDate, account: &str| {
+            Directive::Balance(Balance::new(day, account, Amount::new(dec!(0), "USD")))
+        };
+        let directives = vec![
+            Directive::Open(Open::new(days_ago(400), "Assets:Bank")),
+            Directive::Open(Open::new(days_ago(400), "Assets:Savings")),
+            Directive::Open(Open::new(days_ago(400), "Assets:Wallet")),
+            Directive::Open(Open::new(days_ago(400), "Assets:Old")),
+            zero(days_ago(200), "Assets:Bank"),
+            zero(days_ago(10), "Assets:Bank"),
+            zero(days_ago(120), "Assets:Savings"),
+            zero(days_ago(300), "Assets:Old"),
+            Directive::Close(Close::new(days_ago(250), "Assets:Old")),
+        ];
+
+        // Opt-in
+        let errors = validate(&directives);
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::StaleBalanceAssertion)
+        );
+
+        let options = ValidationOptions {
+            stale_balance_days: Some(90),
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives, options.clone());
+        let stale: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::StaleBalanceAssertion)
+            .collect();
+        // Recently asserted, never asserted and closed accounts are skipped
+        assert_eq!(stale.len(), 1, "{errors:?}");
+        assert_eq!(
+            stale[0].message,
+            format!(
+                "Account Assets:Savings has not had a balance assertion since {} (120 days)",
+                days_ago(120)
+            )
+        );
+        assert_eq!(stale[0].date, days_ago(120));
+        assert_eq!(stale[0].code.severity(), Severity::Info);
+
+        let parallel = validate_parallel(&directives, options);
+        assert!(
+            parallel
+                .iter()
+                .any(|e| e.code == ErrorCode::StaleBalanceAssertion)
+        );
+    }
+
+    #[test]
+    fn test_validate_pad_currency_not_allowed_in_source() {
+        let directives = vec![
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Assets:Bank").with_currencies(vec!["USD".into()]),
+            ),
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Equity:Opening").with_currencies(vec!["EUR".into()]),
+            ),
+            Directive::Pad(Pad::new(date(2024, 1, 1), "Assets:Bank", "Equity:Opening")),
+            Directive::Balance(Balance::new(
+                date(2024, 1, 2),
+                "Assets:Bank",
+                Amount::new(dec!(1000.00), "USD"),
+            )),
+        ];
+
+        let errors = validate(&directives);
+        let currency_errors: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::CurrencyNotAllowed)
+            .collect();
+        assert_eq!(currency_errors.len(), 1, "{errors:?}");
+        assert!(currency_errors[0].message.contains("Equity:Opening"));
+        assert_eq!(currency_errors[0].date, date(2024, 1, 1));
+    }
+
+    #[test]
+    fn test_validate_pad_no_adjustment_skips_currency_check() {
+        // A pad that inserts nothing cannot violate currency constraints
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Equity:Opening").with_currencies(vec!["EUR".into()]),
+            ),
+            Directive::Pad(Pad::new(date(2024, 1, 1), "Assets:Bank", "Equity:Opening")),
+            Directive::Balance(Balance::new(
+                date(2024, 1, 2),
+                "Assets:Bank",
+                Amount::new(dec!(0), "USD"),
+            )),
+        ];
+
+        let errors = validate(&directives);
+        assert!(
+            !errors
+                .iter()
+                .any(|e| e.code == ErrorCode::CurrencyNotAllowed),
+            "{errors:?}"
+        );
+    }
+
     #[test]
     fn test_validate_pad_negative_adjustment() {
         // Test that pad can reduce a balance too
@@ -1484,6 +3390,167 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_negative_lots_policy() {
+        use rustledger_core::CostSpec;
+
+        let cost_spec = CostSpec::empty()
+            .with_number_per(dec!(2))
+            .with_currency("USD");
+        let sell_short = |account: &str| {
+            Directive::Transaction(
+                Transaction::new(date(2024, 2, 1), "Write calls")
+                    .with_posting(
+                        Posting::new(account, Amount::new(dec!(-10), "CALL"))
+                            .with_cost(cost_spec.clone()),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(20), "USD"))),
+            )
+        };
+        let open_with_policy = |account: &str, policy: &str| {
+            let mut open = Open::new(date(2024, 1, 1), account);
+            open.meta.insert(
+                "negative_lots".to_string(),
+                MetaValue::String(policy.to_string()),
+            );
+            Directive::Open(open)
+        };
+
+        let directives = vec![
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Cash")),
+            open_with_policy("Assets:Options:Allowed", "allow"),
+            open_with_policy("Assets:Options:Warned", "warn"),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Options:Default")),
+            sell_short("Assets:Options:Allowed"),
+            sell_short("Assets:Options:Warned"),
+            sell_short("Assets:Options:Default"),
+            Directive::Balance(Balance::new(
+                date(2024, 2, 2),
+                "Assets:Options:Allowed",
+                Amount::new(dec!(-10), "CALL"),
+            )),
+        ];
+
+        let errors = validate(&directives);
+        let booking_errors: Vec<_> = errors
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e.code,
+                    ErrorCode::NegativeInventory
+                        | ErrorCode::InsufficientUnits
+                        | ErrorCode::NoMatchingLot
+                        | ErrorCode::BalanceAssertionFailed
+                )
+            })
+            .collect();
+
+        assert_eq!(booking_errors.len(), 2, "{booking_errors:?}");
+        assert!(booking_errors[0].message.contains("Assets:Options:Warned"));
+        assert_eq!(booking_errors[0].code, ErrorCode::NegativeInventory);
+        assert_eq!(booking_errors[0].code.severity(), Severity::Warning);
+        assert!(booking_errors[1].message.contains("Assets:Options:Default"));
+        assert_ne!(booking_errors[1].code, ErrorCode::NegativeInventory);
+
+        // The default policy applies to accounts without metadata
+        let errors = validate_with_options(
+            &directives,
+            ValidationOptions {
+                negative_lots: NegativeLotsPolicy::Allow,
+                ..Default::default()
+            },
+        );
+        assert!(
+            !errors.iter().any(|e| matches!(
+                e.code,
+                ErrorCode::InsufficientUnits | ErrorCode::NoMatchingLot
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_negative_lots_metadata() {
+        let mut open = Open::new(date(2024, 1, 1), "Assets:Options");
+        open.meta.insert(
+            "negative_lots".to_string(),
+            MetaValue::String("sometimes".to_string()),
+        );
+
+        let errors = validate(&[Directive::Open(open)]);
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.code == ErrorCode::InvalidMetadataValue),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_naming_rules() {
+        let rule = |values: &[MetaValue]| {
+            let mut custom = Custom::new(date(2000, 1, 1), NAMING_CUSTOM_TYPE);
+            custom.values = values.to_vec();
+            Directive::Custom(custom)
+        };
+        let s = |v: &str| MetaValue::String(v.to_string());
+
+        let directives = vec![
+            rule(&[s("max_depth"), MetaValue::Number(dec!(3))]),
+            rule(&[s("second_level"), s("Assets"), s("US"), s("CA")]),
+            rule(&[s("deny"), s("Expenses"), s(":Misc$")]),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:US:Checking")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:DE:Checking")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:CA:Bank:Checking")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Misc")),
+            Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Food")),
+        ];
+
+        let errors = validate(&directives);
+        let mut violations: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code == ErrorCode::AccountNamingViolation)
+            .map(|e| e.context.as_deref().unwrap())
+            .collect();
+        violations.sort_unstable();
+        assert_eq!(
+            violations,
+            vec![
+                "Assets:CA:Bank:Checking",
+                "Assets:DE:Checking",
+                "Expenses:Misc"
+            ]
+        );
+
+        // Rules can also come from the options
+        let mut naming = NamingRules::default();
+        naming.allow.insert(
+            "Expenses".to_string(),
+            vec![Regex::new("^Expenses:(Food|Rent)$").unwrap()],
+        );
+        let options = ValidationOptions {
+            naming,
+            ..Default::default()
+        };
+        let errors = validate_with_options(&directives[3..], options);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].context.as_deref(), Some("Expenses:Misc"));
+    }
+
+    #[test]
+    fn test_validate_invalid_naming_rule() {
+        let mut custom = Custom::new(date(2000, 1, 1), NAMING_CUSTOM_TYPE);
+        custom.values = vec![
+            MetaValue::String("allow".to_string()),
+            MetaValue::String("Assets".to_string()),
+            MetaValue::String("(".to_string()),
+        ];
+
+        let errors = validate(&[Directive::Custom(custom)]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, ErrorCode::InvalidOptionValue);
+    }
+
     #[test]
     fn test_validate_no_matching_lot() {
         use rustledger_core::CostSpec;
@@ -1629,6 +3696,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_incomplete_cost_addition() {
+        use rustledger_core::CostSpec;
+
+        let buy = |account: &str, day: u32, cost: CostSpec| {
+            Directive::Transaction(
+                Transaction::new(date(2024, 1, day), "Buy")
+                    .with_posting(
+                        Posting::new(account, Amount::new(dec!(10), "AAPL")).with_cost(cost),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(-1500), "USD"))),
+            )
+        };
+        let per_share = CostSpec::empty()
+            .with_number_per(dec!(150))
+            .with_currency("USD");
+
+        let directives = vec![
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Assets:Strict").with_booking("STRICT".to_string()),
+            ),
+            Directive::Open(
+                Open::new(date(2024, 1, 1), "Assets:Fifo").with_booking("FIFO".to_string()),
+            ),
+            Directive::Open(Open::new(date(2024, 1, 1), "Assets:Cash")),
+            buy("Assets:Strict", 10, CostSpec::empty().with_currency("USD")),
+            buy("Assets:Fifo", 10, CostSpec::empty().with_currency("USD")),
+            buy("Assets:Strict", 15, per_share.clone()),
+            // The lot bought on the 15th is dated the 15th, so it is the only
+            // one this reduction can match
+            Directive::Transaction(
+                Transaction::new(date(2024, 6, 1), "Sell")
+                    .with_posting(
+                        Posting::new("Assets:Strict", Amount::new(dec!(-10), "AAPL"))
+                            .with_cost(per_share.with_date(date(2024, 1, 15))),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(1500), "USD"))),
+            ),
+        ];
+
+        let errors = validate(&directives);
+        let booking_errors: Vec<_> = errors
+            .iter()
+            .filter(|e| e.code.code().starts_with("E4"))
+            .collect();
+        assert_eq!(booking_errors.len(), 1, "{booking_errors:?}");
+        assert_eq!(booking_errors[0].code, ErrorCode::IncompleteCost);
+        assert_eq!(booking_errors[0].date, date(2024, 1, 10));
+        assert!(booking_errors[0].message.contains("Assets:Strict"));
+    }
+
     #[test]
     fn test_validate_account_already_open() {
         let directives = vec![
@@ -0,0 +1,428 @@
+//! Parallel validation.
+//!
+//! [`validate_parallel`] splits the work of [`validate_with_options`] into
+//! stages after sorting:
+//!
+//! 1. A sequential pass over the sorted directives keeps track of opened and
+//!    closed accounts, commodities and pads, and runs every check that only
+//!    needs that bookkeeping. Work that needs an inventory is recorded
+//!    instead of done.
+//! 2. Transactions are checked for balance in parallel.
+//! 3. The recorded inventory work is replayed in parallel across groups of
+//!    accounts. Inventories are independent except for pads, which move
+//!    units between two accounts, so accounts linked by a pad share a group.
+//!
+//! Every error is tagged with the position of the directive that caused it
+//! and the stage that found it, and the tags are sorted at the end, so the
+//! errors come out in exactly the order [`validate_with_options`] reports
+//! them.
+//!
+//! [`validate_with_options`]: crate::validate_with_options
+
+use chrono::Local;
+use rayon::prelude::*;
+use rustledger_core::{
+    Amount, Balance, BookingMethod, Close, Directive, InternedStr, Inventory, NegativeLotsPolicy,
+    Position, Posting, Transaction,
+};
+use std::collections::HashMap;
+
+use crate::{
+    LedgerState, ValidationError, ValidationOptions, book_posting, booking_policy, check_balance,
+    check_balance_account, check_close_balance, check_directive_date, check_multiple_pads,
+    interpolate_transaction, pad_currency_errors, prepare, validate_close, validate_commodity,
+    validate_document, validate_ledger_wide, validate_note, validate_open, validate_pad,
+    validate_posting_accounts, validate_price, validate_transaction_balance,
+    validate_transaction_structure,
+};
+
+/// The stage of a directive's validation that found an error, in the order
+/// the sequential validator runs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    /// Account lifecycle, currency and pad checks.
+    Bookkeeping,
+    /// Transaction balance checks.
+    Balancing,
+    /// Inventory booking and balance assertions.
+    Booking,
+}
+
+/// Where an error belongs in the output: the directive's position in sorted
+/// order, the stage, and the posting within a transaction.
+type Slot = (usize, Stage, usize);
+
+/// Inventory work recorded by the bookkeeping pass.
+enum InventoryWork<'a> {
+    /// Book a posting into its account.
+    Posting {
+        txn: &'a Transaction,
+        posting: &'a Posting,
+        units: &'a Amount,
+        booking_method: BookingMethod,
+        negative_lots: NegativeLotsPolicy,
+    },
+    /// Warn if the account is closed with a non-zero balance.
+    Close(&'a Close),
+    /// Check a balance assertion.
+    Balance(&'a Balance),
+    /// Pad the account up to a balance assertion from `source`.
+    Pad {
+        bal: &'a Balance,
+        source: InternedStr,
+        /// Reported only if an adjustment is actually needed.
+        currency_errors: Vec<ValidationError>,
+    },
+}
+
+impl InventoryWork<'_> {
+    /// The account whose inventory the work reads.
+    const fn account(&self) -> &InternedStr {
+        match self {
+            Self::Posting { posting, .. } => &posting.account,
+            Self::Close(close) => &close.account,
+            Self::Balance(bal) | Self::Pad { bal, .. } => &bal.account,
+        }
+    }
+}
+
+/// Validate a stream of directives with custom options, using all cores.
+///
+/// Returns the same errors as [`validate_with_options`](crate::validate_with_options),
+/// in the same order. Large ledgers with many accounts validate several
+/// times faster; small ones are not worth the overhead.
+pub fn validate_parallel(
+    directives: &[Directive],
+    options: ValidationOptions,
+) -> Vec<ValidationError> {
+    let mut state = LedgerState::with_options(options);
+    let mut errors = Vec::new();
+
+    let today = Local::now().date_naive();
+    let (order, sorted) = prepare(directives, &mut state, &mut errors);
+
+    // Interpolation needs no ledger state, so it can run up front
+    let completed: Vec<_> = sorted
+        .par_iter()
+        .map(|directive| match directive {
+            Directive::Transaction(txn) => Some(interpolate_transaction(txn)),
+            _ => None,
+        })
+        .collect();
+
+    let mut slotted: Vec<(Slot, ValidationError)> = Vec::new();
+    let mut to_balance: Vec<(usize, &Transaction, &Transaction)> = Vec::new();
+    let mut work: Vec<(Slot, InventoryWork<'_>)> = Vec::new();
+
+    // Stage 1: bookkeeping, in order
+    for (index, &directive) in sorted.iter().enumerate() {
+        let mut scratch = Vec::new();
+        check_directive_date(&mut state, directive, today, &mut scratch);
+
+        match directive {
+            Directive::Open(open) => {
+                validate_open(&mut state, open, &mut scratch);
+            }
+            Directive::Close(close) => {
+                // Inventories stay empty in this pass, so the non-zero
+                // balance check is left for later
+                if state
+                    .accounts
+                    .get(&close.account)
+                    .is_some_and(|account| account.closed.is_none())
+                {
+                    work.push(((index, Stage::Booking, 0), InventoryWork::Close(close)));
+                }
+                validate_close(&mut state, close, &mut scratch);
+            }
+            Directive::Transaction(txn) => {
+                if validate_transaction_structure(txn, &mut scratch) {
+                    validate_posting_accounts(&state, txn, &mut scratch);
+                    match &completed[index] {
+                        Some(Ok(completed)) => {
+                            to_balance.push((index, txn, completed));
+                            record_postings(&state, index, completed, &mut work);
+                        }
+                        Some(Err(error)) => {
+                            slotted.push(((index, Stage::Balancing, 0), error.clone()));
+                            record_postings(&state, index, txn, &mut work);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Directive::Balance(bal) => {
+                record_balance(&mut state, index, bal, &mut work, &mut scratch);
+            }
+            Directive::Commodity(comm) => {
+                validate_commodity(&mut state, comm, &mut scratch);
+            }
+            Directive::Pad(pad) => {
+                validate_pad(&mut state, pad, &mut scratch);
+            }
+            Directive::Note(note) => {
+                validate_note(&state, note, &mut scratch);
+            }
+            Directive::Document(doc) => {
+                validate_document(&state, doc, &mut scratch);
+            }
+            Directive::Price(price) => {
+                validate_price(&mut state, price, &mut scratch);
+            }
+            _ => {}
+        }
+
+        slotted.extend(
+            scratch
+                .into_iter()
+                .map(|error| ((index, Stage::Bookkeeping, 0), error)),
+        );
+    }
+
+    // Stage 2: transaction balance
+    let tolerance = &state.options.tolerance;
+    slotted.par_extend(
+        to_balance
+            .into_par_iter()
+            .flat_map_iter(|(index, txn, completed)| {
+                let mut errors = Vec::new();
+                validate_transaction_balance(txn, completed, tolerance, &mut errors);
+                errors
+                    .into_iter()
+                    .map(move |error| ((index, Stage::Balancing, 0), error))
+            }),
+    );
+
+    // Stage 3: inventories, one group of linked accounts per task
+    slotted.par_extend(
+        group_by_account(work)
+            .into_par_iter()
+            .flat_map_iter(run_inventory_work),
+    );
+
+    slotted.par_sort_by_key(|(slot, _)| *slot);
+    errors.extend(slotted.into_iter().map(|((index, _, _), mut error)| {
+        error.directive = Some(order[index]);
+        error
+    }));
+
+    validate_ledger_wide(&state, &sorted, today, &mut errors);
+
+    errors
+}
+
+/// Record the booking of a transaction's postings.
+fn record_postings<'a>(
+    state: &LedgerState,
+    index: usize,
+    txn: &'a Transaction,
+    work: &mut Vec<(Slot, InventoryWork<'a>)>,
+) {
+    for (i, posting) in txn.postings.iter().enumerate() {
+        let Some(units) = posting.amount() else {
+            continue;
+        };
+        // Opened accounts are the ones with an inventory
+        if !state.accounts.contains_key(&posting.account) {
+            continue;
+        }
+
+        let (booking_method, negative_lots) =
+            booking_policy(&state.accounts, &state.options, &posting.account);
+        work.push((
+            (index, Stage::Booking, i),
+            InventoryWork::Posting {
+                txn,
+                posting,
+                units,
+                booking_method,
+                negative_lots,
+            },
+        ));
+    }
+}
+
+/// The bookkeeping part of a balance assertion; see `validate_balance`.
+fn record_balance<'a>(
+    state: &mut LedgerState,
+    index: usize,
+    bal: &'a Balance,
+    work: &mut Vec<(Slot, InventoryWork<'a>)>,
+    errors: &mut Vec<ValidationError>,
+) {
+    if !check_balance_account(state, bal, errors) {
+        return;
+    }
+    state.last_assertions.insert(bal.account.clone(), bal.date);
+    let slot = (index, Stage::Booking, 0);
+
+    let Some(pending_pads) = state.pending_pads.get_mut(&bal.account) else {
+        work.push((slot, InventoryWork::Balance(bal)));
+        return;
+    };
+    check_multiple_pads(pending_pads, bal, errors);
+
+    // Use the most recent pad
+    if let Some(pending_pad) = pending_pads.last_mut() {
+        let mut currency_errors = Vec::new();
+        pad_currency_errors(&state.accounts, bal, pending_pad, &mut currency_errors);
+        work.push((
+            slot,
+            InventoryWork::Pad {
+                bal,
+                source: pending_pad.source_account.clone(),
+                currency_errors,
+            },
+        ));
+        pending_pad.used = true;
+    }
+}
+
+/// Split inventory work into groups of accounts linked by pads, keeping the
+/// order within each group.
+fn group_by_account(work: Vec<(Slot, InventoryWork<'_>)>) -> Vec<Vec<(Slot, InventoryWork<'_>)>> {
+    let mut ids: HashMap<InternedStr, usize> = HashMap::new();
+    let mut parent: Vec<usize> = Vec::new();
+    let mut id = |account: &InternedStr, parent: &mut Vec<usize>| {
+        *ids.entry(account.clone()).or_insert_with(|| {
+            parent.push(parent.len());
+            parent.len() - 1
+        })
+    };
+
+    let mut accounts = Vec::with_capacity(work.len());
+    for (_, item) in &work {
+        let account = id(item.account(), &mut parent);
+        if let InventoryWork::Pad { source, .. } = item {
+            let source = id(source, &mut parent);
+            let (a, b) = (find(&mut parent, account), find(&mut parent, source));
+            parent[a] = b;
+        }
+        accounts.push(account);
+    }
+
+    let mut group_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut groups: Vec<Vec<_>> = Vec::new();
+    for (item, account) in work.into_iter().zip(accounts) {
+        let root = find(&mut parent, account);
+        let group = *group_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(item);
+    }
+    groups
+}
+
+/// The representative of a set in a union-find forest.
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+/// Replay a group's inventory work in order.
+fn run_inventory_work(group: Vec<(Slot, InventoryWork<'_>)>) -> Vec<(Slot, ValidationError)> {
+    let mut inventories: HashMap<InternedStr, Inventory> = HashMap::new();
+    let mut slotted = Vec::new();
+
+    for (slot, item) in group {
+        let mut errors = Vec::new();
+        match item {
+            InventoryWork::Posting {
+                txn,
+                posting,
+                units,
+                booking_method,
+                negative_lots,
+            } => {
+                let inv = inventories.entry(posting.account.clone()).or_default();
+                book_posting(
+                    inv,
+                    posting,
+                    units,
+                    booking_method,
+                    negative_lots,
+                    txn,
+                    &mut errors,
+                );
+            }
+            InventoryWork::Close(close) => {
+                let inv = inventories.entry(close.account.clone()).or_default();
+                check_close_balance(inv, close, &mut errors);
+            }
+            InventoryWork::Balance(bal) => {
+                let inv = inventories.entry(bal.account.clone()).or_default();
+                check_balance(inv, bal, &mut errors);
+            }
+            InventoryWork::Pad {
+                bal,
+                source,
+                currency_errors,
+            } => {
+                let inv = inventories.entry(bal.account.clone()).or_default();
+                let difference = bal.amount.number - inv.units(&bal.amount.currency);
+                if !difference.is_zero() {
+                    errors.extend(currency_errors);
+                    inv.add(Position::simple(Amount::new(
+                        difference,
+                        &bal.amount.currency,
+                    )));
+                    inventories
+                        .entry(source)
+                        .or_default()
+                        .add(Position::simple(Amount::new(
+                            -difference,
+                            &bal.amount.currency,
+                        )));
+                }
+            }
+        }
+        slotted.extend(errors.into_iter().map(|error| (slot, error)));
+    }
+
+    slotted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_pads_link_account_groups() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let balances: Vec<Balance> = ["Assets:A", "Assets:B", "Assets:A"]
+            .into_iter()
+            .map(|account| Balance::new(date, account, Amount::new(dec!(1), "USD")))
+            .collect();
+        let work = vec![
+            ((0, Stage::Booking, 0), InventoryWork::Balance(&balances[0])),
+            ((1, Stage::Booking, 0), InventoryWork::Balance(&balances[1])),
+            (
+                (2, Stage::Booking, 0),
+                InventoryWork::Pad {
+                    bal: &balances[2],
+                    source: "Equity:Opening".into(),
+                    currency_errors: Vec::new(),
+                },
+            ),
+            ((3, Stage::Booking, 0), InventoryWork::Balance(&balances[2])),
+        ];
+
+        let groups = group_by_account(work);
+        let slots: Vec<Vec<usize>> = groups
+            .iter()
+            .map(|group| group.iter().map(|((index, _, _), _)| *index).collect())
+            .collect();
+        assert_eq!(slots, [vec![0, 2, 3], vec![1]]);
+
+        // Only the assertion before the pad fails
+        let errors = run_inventory_work(groups.into_iter().next().unwrap());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, (0, Stage::Booking, 0));
+    }
+}
@@ -0,0 +1,280 @@
+//! Summary statistics over validation errors.
+//!
+//! [`ValidationReport`] wraps the errors returned by [`validate`](crate::validate)
+//! in a stable order and answers the questions front ends ask of them: how
+//! many errors and warnings there are, which codes occur, and which files
+//! they belong to.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{ErrorCode, Severity, ValidationError};
+
+/// Validation errors with grouping and counting helpers.
+///
+/// Errors are ordered by date, then code, then message, so the same ledger
+/// always produces the same report regardless of validation order.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// The errors, in report order.
+    errors: Vec<ValidationError>,
+    /// File each error belongs to, if known (parallel to `errors`).
+    files: Vec<Option<PathBuf>>,
+}
+
+impl ValidationReport {
+    /// Create a report from validation errors with no file information.
+    #[must_use]
+    pub fn new(errors: Vec<ValidationError>) -> Self {
+        let files = vec![None; errors.len()];
+        Self::with_files(errors.into_iter().zip(files))
+    }
+
+    /// Create a report from validation errors paired with their files.
+    #[must_use]
+    pub fn with_files(
+        errors: impl IntoIterator<Item = (ValidationError, Option<PathBuf>)>,
+    ) -> Self {
+        let mut entries: Vec<_> = errors.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| {
+            a.date
+                .cmp(&b.date)
+                .then_with(|| a.code.cmp(&b.code))
+                .then_with(|| a.message.cmp(&b.message))
+        });
+        let (errors, files) = entries.into_iter().unzip();
+        Self { errors, files }
+    }
+
+    /// Attribute every error without a file to `file`.
+    #[must_use]
+    pub fn with_default_file(mut self, file: impl Into<PathBuf>) -> Self {
+        let file = file.into();
+        for slot in &mut self.files {
+            slot.get_or_insert_with(|| file.clone());
+        }
+        self
+    }
+
+    /// The errors, in report order.
+    #[must_use]
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    /// Consume the report, returning the errors in report order.
+    #[must_use]
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.errors
+    }
+
+    /// Iterate over errors with the file each belongs to, if known.
+    pub fn iter(&self) -> impl Iterator<Item = (&ValidationError, Option<&Path>)> {
+        self.errors
+            .iter()
+            .zip(self.files.iter().map(Option::as_deref))
+    }
+
+    /// Total number of errors, warnings and informational messages.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Whether validation found nothing at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Number of messages of exactly `severity`.
+    #[must_use]
+    pub fn count(&self, severity: Severity) -> usize {
+        self.errors
+            .iter()
+            .filter(|e| e.code.severity() == severity)
+            .count()
+    }
+
+    /// Number of messages that make the ledger invalid.
+    #[must_use]
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// Number of warnings, including informational messages.
+    #[must_use]
+    pub fn warning_count(&self) -> usize {
+        self.len() - self.error_count()
+    }
+
+    /// Whether any message makes the ledger invalid.
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|e| !e.code.is_warning())
+    }
+
+    /// Keep only messages at least as severe as `threshold`.
+    ///
+    /// `Severity::Warning` drops informational messages, and
+    /// `Severity::Error` keeps only errors.
+    #[must_use]
+    pub fn at_least(&self, threshold: Severity) -> Self {
+        let (errors, files) = self
+            .errors
+            .iter()
+            .zip(&self.files)
+            .filter(|(e, _)| e.code.severity() <= threshold)
+            .map(|(e, f)| (e.clone(), f.clone()))
+            .unzip();
+        Self { errors, files }
+    }
+
+    /// Number of messages per severity, most severe first.
+    #[must_use]
+    pub fn counts_by_severity(&self) -> BTreeMap<Severity, usize> {
+        let mut counts = BTreeMap::new();
+        for error in &self.errors {
+            *counts.entry(error.code.severity()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Number of messages per error code, in code order.
+    #[must_use]
+    pub fn counts_by_code(&self) -> BTreeMap<ErrorCode, usize> {
+        let mut counts = BTreeMap::new();
+        for error in &self.errors {
+            *counts.entry(error.code).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Number of messages per file; errors without a file are keyed by `None`.
+    #[must_use]
+    pub fn counts_by_file(&self) -> BTreeMap<Option<&Path>, usize> {
+        let mut counts = BTreeMap::new();
+        for file in &self.files {
+            *counts.entry(file.as_deref()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Messages grouped by error code, in code order.
+    #[must_use]
+    pub fn group_by_code(&self) -> BTreeMap<ErrorCode, Vec<&ValidationError>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for error in &self.errors {
+            groups.entry(error.code).or_default().push(error);
+        }
+        groups
+    }
+}
+
+impl From<Vec<ValidationError>> for ValidationReport {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Self::new(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn error(code: ErrorCode, day: u32, message: &str) -> ValidationError {
+        ValidationError::new(
+            code,
+            message,
+            NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+        )
+    }
+
+    fn sample() -> ValidationReport {
+        ValidationReport::with_files([
+            (
+                error(ErrorCode::BalanceAssertionFailed, 3, "balance"),
+                Some(PathBuf::from("b.beancount")),
+            ),
+            (error(ErrorCode::DateOutOfOrder, 1, "order"), None),
+            (
+                error(ErrorCode::AccountNotOpen, 2, "second"),
+                Some(PathBuf::from("a.beancount")),
+            ),
+            (
+                error(ErrorCode::AccountNotOpen, 2, "first"),
+                Some(PathBuf::from("a.beancount")),
+            ),
+            (error(ErrorCode::FutureDate, 4, "future"), None),
+        ])
+    }
+
+    #[test]
+    fn test_stable_order() {
+        let report = sample();
+        let messages: Vec<_> = report.errors().iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["order", "first", "second", "balance", "future"]);
+        let files: Vec<_> = report.iter().map(|(_, f)| f).collect();
+        assert_eq!(files[1], Some(Path::new("a.beancount")));
+        assert_eq!(files[0], None);
+    }
+
+    #[test]
+    fn test_counts() {
+        let report = sample();
+        assert_eq!(report.len(), 5);
+        assert_eq!(report.error_count(), 3);
+        assert_eq!(report.warning_count(), 2);
+        assert!(report.has_errors());
+
+        let by_severity: Vec<_> = report.counts_by_severity().into_iter().collect();
+        assert_eq!(
+            by_severity,
+            [
+                (Severity::Error, 3),
+                (Severity::Warning, 1),
+                (Severity::Info, 1)
+            ]
+        );
+
+        let by_code: Vec<_> = report
+            .counts_by_code()
+            .into_iter()
+            .map(|(code, n)| (code.code(), n))
+            .collect();
+        assert_eq!(
+            by_code,
+            [("E1001", 2), ("E2001", 1), ("E10001", 1), ("E10002", 1)]
+        );
+
+        let by_file = report.counts_by_file();
+        assert_eq!(by_file[&None], 2);
+        assert_eq!(by_file[&Some(Path::new("a.beancount"))], 2);
+
+        let groups = report.group_by_code();
+        assert_eq!(groups[&ErrorCode::AccountNotOpen].len(), 2);
+    }
+
+    #[test]
+    fn test_severity_threshold() {
+        let report = sample();
+        let warnings = report.at_least(Severity::Warning);
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(warnings.count(Severity::Info), 0);
+
+        let errors = report.at_least(Severity::Error);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors.warning_count(), 0);
+
+        assert_eq!(report.at_least(Severity::Info).len(), 5);
+    }
+
+    #[test]
+    fn test_default_file() {
+        let report = sample().with_default_file("main.beancount");
+        let by_file = report.counts_by_file();
+        assert!(!by_file.contains_key(&None));
+        assert_eq!(by_file[&Some(Path::new("main.beancount"))], 2);
+        assert_eq!(by_file[&Some(Path::new("b.beancount"))], 1);
+    }
+}
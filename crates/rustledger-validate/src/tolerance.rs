@@ -0,0 +1,206 @@
+//! Balancing tolerances inferred the way beancount infers them.
+//!
+//! A transaction balances when every currency's residual is within that
+//! currency's tolerance. Tolerances come from the precision of the amounts
+//! the user wrote: `10.25 USD` allows a residual of half a cent with the
+//! default multiplier of 0.5, while integer amounts are exact unless
+//! `inferred_tolerance_default` says otherwise.
+
+use rust_decimal::Decimal;
+use rustledger_core::{IncompleteAmount, MixedAmount, Transaction};
+use std::collections::HashMap;
+
+/// Key of [`ToleranceOptions::defaults`] that applies to every currency
+/// without an entry of its own.
+pub const ANY_CURRENCY: &str = "*";
+
+/// Upper bound for tolerances inferred from costs and prices.
+const MAXIMUM_TOLERANCE: Decimal = Decimal::from_parts(5, 0, 0, false, 1);
+
+/// Options controlling how balancing tolerances are inferred.
+///
+/// These mirror beancount's `inferred_tolerance_default`,
+/// `inferred_tolerance_multiplier` and `infer_tolerance_from_cost` options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToleranceOptions {
+    /// Tolerance per currency when none can be inferred or the inferred one
+    /// is smaller. [`ANY_CURRENCY`] sets the fallback for all currencies.
+    pub defaults: HashMap<String, Decimal>,
+    /// Fraction of the last digit of an amount allowed as residual.
+    pub multiplier: Decimal,
+    /// Whether postings held at cost or converted at a price widen the
+    /// tolerance of the cost or price currency.
+    pub infer_from_cost: bool,
+}
+
+impl Default for ToleranceOptions {
+    fn default() -> Self {
+        Self {
+            defaults: HashMap::new(),
+            multiplier: Decimal::new(5, 1),
+            infer_from_cost: true,
+        }
+    }
+}
+
+impl ToleranceOptions {
+    /// Infer the tolerances for a transaction.
+    ///
+    /// Only amounts written in the transaction count, so pass it before
+    /// interpolation: filled-in amounts carry whatever precision the
+    /// arithmetic produced.
+    #[must_use]
+    pub fn infer(&self, txn: &Transaction) -> Tolerances {
+        let mut tolerances = self.defaults.clone();
+        let mut cost_tolerances = MixedAmount::new();
+
+        for posting in &txn.postings {
+            let Some(IncompleteAmount::Complete(units)) = &posting.units else {
+                continue;
+            };
+            let scale = units.number.scale();
+            if scale == 0 {
+                continue;
+            }
+
+            let tolerance = Decimal::new(1, scale) * self.multiplier;
+            raise(&mut tolerances, units.currency.as_str(), tolerance);
+
+            if !self.infer_from_cost {
+                continue;
+            }
+
+            if let Some((cost, currency)) = posting
+                .cost
+                .as_ref()
+                .and_then(|cost| cost.currency.as_ref().map(|currency| (cost, currency)))
+            {
+                let cost_tolerance = [cost.number_total, cost.number_per]
+                    .into_iter()
+                    .flatten()
+                    .map(|number| tolerance * number.abs())
+                    .fold(MAXIMUM_TOLERANCE, Decimal::min);
+                cost_tolerances.add_number(currency.clone(), cost_tolerance);
+            }
+
+            if let Some((price, amount)) = posting
+                .price
+                .as_ref()
+                .and_then(|price| price.amount().map(|amount| (price, amount)))
+            {
+                let per_unit = if price.is_unit() || units.number.is_zero() {
+                    amount.number
+                } else {
+                    amount.number / units.number
+                };
+                let price_tolerance = (tolerance * per_unit.abs()).min(MAXIMUM_TOLERANCE);
+                cost_tolerances.add_number(amount.currency.clone(), price_tolerance);
+            }
+        }
+
+        for (currency, tolerance) in &cost_tolerances {
+            raise(&mut tolerances, currency, *tolerance);
+        }
+
+        let default = tolerances.remove(ANY_CURRENCY).unwrap_or_default();
+        Tolerances {
+            by_currency: tolerances,
+            default,
+        }
+    }
+}
+
+/// Set `currency`'s tolerance to `tolerance` unless it is already larger.
+fn raise(tolerances: &mut HashMap<String, Decimal>, currency: &str, tolerance: Decimal) {
+    tolerances
+        .entry(currency.to_string())
+        .and_modify(|existing| *existing = (*existing).max(tolerance))
+        .or_insert(tolerance);
+}
+
+/// Tolerances inferred for one transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tolerances {
+    by_currency: HashMap<String, Decimal>,
+    default: Decimal,
+}
+
+impl Tolerances {
+    /// The largest residual allowed in `currency`.
+    #[must_use]
+    pub fn get(&self, currency: &str) -> Decimal {
+        self.by_currency
+            .get(currency)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{Amount, CostSpec, Posting, PriceAnnotation};
+
+    fn txn(postings: Vec<Posting>) -> Transaction {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        postings
+            .into_iter()
+            .fold(Transaction::new(date, "Test"), Transaction::with_posting)
+    }
+
+    #[test]
+    fn test_tolerance_from_precision() {
+        let tolerances = ToleranceOptions::default().infer(&txn(vec![
+            Posting::new("Expenses:Food", Amount::new(dec!(10.25), "USD")),
+            Posting::new("Assets:Cash", Amount::new(dec!(-10.2), "USD")),
+            Posting::new("Assets:Points", Amount::new(dec!(3), "PTS")),
+        ]));
+
+        assert_eq!(tolerances.get("USD"), dec!(0.05));
+        assert_eq!(tolerances.get("PTS"), Decimal::ZERO);
+        assert_eq!(tolerances.get("EUR"), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_tolerance_defaults_and_multiplier() {
+        let options = ToleranceOptions {
+            defaults: HashMap::from([
+                ("*".to_string(), dec!(0.01)),
+                ("USD".to_string(), dec!(0.003)),
+            ]),
+            multiplier: dec!(0.6),
+            ..ToleranceOptions::default()
+        };
+        let tolerances = options.infer(&txn(vec![Posting::new(
+            "Expenses:Food",
+            Amount::new(dec!(10.25), "USD"),
+        )]));
+
+        assert_eq!(tolerances.get("USD"), dec!(0.006));
+        assert_eq!(tolerances.get("PTS"), dec!(0.01));
+    }
+
+    #[test]
+    fn test_tolerance_from_cost_and_price() {
+        let stock = Posting::new("Assets:Stock", Amount::new(dec!(10.5), "AAPL")).with_cost(
+            CostSpec::empty()
+                .with_number_per(dec!(150))
+                .with_currency("USD"),
+        );
+        let fx = Posting::new("Assets:Euro", Amount::new(dec!(100.00), "EUR"))
+            .with_price(PriceAnnotation::Total(Amount::new(dec!(110), "USD")));
+        let transaction = txn(vec![stock, fx]);
+
+        // 0.05 * 150 = 7.5, capped at 0.5; 0.005 * 1.1 = 0.0055
+        let tolerances = ToleranceOptions::default().infer(&transaction);
+        assert_eq!(tolerances.get("USD"), dec!(0.5055));
+
+        let options = ToleranceOptions {
+            infer_from_cost: false,
+            ..ToleranceOptions::default()
+        };
+        assert_eq!(options.infer(&transaction).get("USD"), Decimal::ZERO);
+    }
+}
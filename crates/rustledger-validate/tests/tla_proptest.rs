@@ -8,7 +8,9 @@
 use chrono::NaiveDate;
 use proptest::prelude::*;
 use rust_decimal::Decimal;
-use rustledger_core::{Amount, Balance, Directive, IncompleteAmount, Open, Posting, Transaction};
+use rustledger_core::{
+    Amount, Balance, Directive, IncompleteAmount, InternedSet, Open, Posting, Transaction,
+};
 use rustledger_validate::{ErrorCode, validate};
 
 // ============================================================================
@@ -79,8 +81,8 @@ proptest! {
                 flag: '*',
                 payee: None,
                 narration: "Initial deposit".into(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 postings: vec![
                     Posting {
                         account: account.clone().into(),
@@ -89,6 +91,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                     Posting {
                         account: "Equity:Opening".into(),
@@ -97,6 +100,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                 ],
                 meta: Default::default(),
@@ -155,8 +159,8 @@ proptest! {
                 flag: '*',
                 payee: None,
                 narration: "Initial deposit".into(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 postings: vec![
                     Posting {
                         account: account.clone().into(),
@@ -165,6 +169,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                     Posting {
                         account: "Equity:Opening".into(),
@@ -173,6 +178,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                 ],
                 meta: Default::default(),
@@ -227,8 +233,8 @@ proptest! {
                 flag: '*',
                 payee: None,
                 narration: format!("Deposit {}", i + 1).into(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 postings: vec![
                     Posting {
                         account: account.clone().into(),
@@ -237,6 +243,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                     Posting {
                         account: "Income:Salary".into(),
@@ -245,6 +252,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                 ],
                 meta: Default::default(),
@@ -294,8 +302,8 @@ proptest! {
                 flag: '*',
                 payee: None,
                 narration: "Test transaction".into(),
-                tags: vec![],
-                links: vec![],
+                tags: InternedSet::new(),
+                links: InternedSet::new(),
                 postings: vec![
                     Posting {
                         account: account.clone().into(),
@@ -304,6 +312,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                     Posting {
                         account: "Equity:Opening".into(),
@@ -312,6 +321,7 @@ proptest! {
                         price: None,
                         flag: None,
                         meta: Default::default(),
+                        comment: None,
                     },
                 ],
                 meta: Default::default(),
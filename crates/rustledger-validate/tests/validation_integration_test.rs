@@ -5,9 +5,12 @@
 
 use rust_decimal_macros::dec;
 use rustledger_core::{
-    Amount, Balance, Close, Directive, NaiveDate, Open, Pad, Posting, PriceAnnotation, Transaction,
+    Amount, Balance, Close, CostSpec, Directive, NaiveDate, Open, Pad, Posting, PriceAnnotation,
+    Transaction,
+};
+use rustledger_validate::{
+    ErrorCode, ValidationOptions, validate, validate_parallel, validate_with_options,
 };
-use rustledger_validate::{ErrorCode, validate};
 
 // ============================================================================
 // Helper Functions
@@ -419,3 +422,123 @@ fn test_basic_validation() {
             .any(|e| e.code == ErrorCode::TransactionUnbalanced)
     );
 }
+
+// ============================================================================
+// Parallel Validation Tests
+// ============================================================================
+
+fn transfer(day: u32, narration: &str, to: &str, from: &str, amount: Amount) -> Directive {
+    let mut from_amount = amount.clone();
+    from_amount.number = -from_amount.number;
+    Directive::Transaction(
+        Transaction::new(date(2024, 1, day), narration)
+            .with_posting(Posting::new(to, amount))
+            .with_posting(Posting::new(from, from_amount)),
+    )
+}
+
+#[test]
+fn test_parallel_matches_sequential() {
+    let directives = vec![
+        Directive::Open(Open::new(date(2024, 1, 1), "Assets:Bank")),
+        Directive::Open(
+            Open::new(date(2024, 1, 1), "Assets:Cash").with_currencies(vec!["USD".into()]),
+        ),
+        Directive::Open(Open::new(date(2024, 1, 1), "Assets:Broker")),
+        Directive::Open(Open::new(date(2024, 1, 1), "Equity:Opening")),
+        Directive::Open(Open::new(date(2024, 1, 1), "Expenses:Food")),
+        Directive::Open(Open::new(date(2024, 1, 1), "Income:Salary")),
+        // Duplicate open (E1002)
+        Directive::Open(Open::new(date(2024, 1, 2), "Assets:Bank")),
+        // Two pads before one balance (E2004), padding a disallowed currency (E5002)
+        Directive::Pad(Pad::new(date(2024, 1, 2), "Assets:Cash", "Equity:Opening")),
+        Directive::Pad(Pad::new(date(2024, 1, 3), "Assets:Cash", "Equity:Opening")),
+        Directive::Balance(Balance::new(
+            date(2024, 1, 4),
+            "Assets:Cash",
+            Amount::new(dec!(50), "EUR"),
+        )),
+        // Pad never followed by a balance (E2003)
+        Directive::Pad(Pad::new(date(2024, 1, 4), "Assets:Bank", "Equity:Opening")),
+        transfer(
+            5,
+            "Salary",
+            "Assets:Bank",
+            "Income:Salary",
+            Amount::new(dec!(1000), "USD"),
+        ),
+        // Unopened account (E1001) and unbalanced transaction (E3001)
+        Directive::Transaction(
+            Transaction::new(date(2024, 1, 6), "Lunch")
+                .with_posting(Posting::new("Expenses:Lunch", Amount::new(dec!(12), "USD")))
+                .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(-10), "USD"))),
+        ),
+        // Selling shares that were never bought (E4001/E4002)
+        Directive::Transaction(
+            Transaction::new(date(2024, 1, 7), "Sell")
+                .with_posting(
+                    Posting::new("Assets:Broker", Amount::new(dec!(-5), "AAPL")).with_cost(
+                        CostSpec::empty()
+                            .with_number_per(dec!(100))
+                            .with_currency("USD"),
+                    ),
+                )
+                .with_posting(Posting::new("Assets:Bank", Amount::new(dec!(500), "USD"))),
+        ),
+        // Balance assertion failures (E2001) on several accounts
+        Directive::Balance(Balance::new(
+            date(2024, 1, 8),
+            "Assets:Bank",
+            Amount::new(dec!(1), "USD"),
+        )),
+        Directive::Balance(Balance::new(
+            date(2024, 1, 8),
+            "Expenses:Food",
+            Amount::new(dec!(1), "USD"),
+        )),
+        // Balance on an unopened account (E1001)
+        Directive::Balance(Balance::new(
+            date(2024, 1, 8),
+            "Assets:Unknown",
+            Amount::new(dec!(1), "USD"),
+        )),
+        // Closing accounts with a balance (E1004), twice (E1003)
+        Directive::Close(Close::new(date(2024, 1, 9), "Assets:Cash")),
+        Directive::Close(Close::new(date(2024, 1, 9), "Assets:Bank")),
+        Directive::Close(Close::new(date(2024, 1, 10), "Assets:Bank")),
+        // Use after close (E1003)
+        transfer(
+            11,
+            "Late",
+            "Expenses:Food",
+            "Assets:Bank",
+            Amount::new(dec!(3), "USD"),
+        ),
+    ];
+
+    let describe = |errors: Vec<rustledger_validate::ValidationError>| -> Vec<String> {
+        errors
+            .iter()
+            .map(|e| format!("{} {} {:?} {:?}", e.date, e, e.context, e.directive))
+            .collect()
+    };
+    let sequential = describe(validate_with_options(
+        &directives,
+        ValidationOptions::default(),
+    ));
+    let parallel = describe(validate_parallel(&directives, ValidationOptions::default()));
+
+    assert!(sequential.len() > 10, "{sequential:#?}");
+    assert_eq!(parallel, sequential);
+
+    // Directives on the same day keep their input order
+    let mut reversed = directives;
+    reversed.reverse();
+    assert_eq!(
+        describe(validate_parallel(&reversed, ValidationOptions::default())),
+        describe(validate_with_options(
+            &reversed,
+            ValidationOptions::default()
+        ))
+    );
+}
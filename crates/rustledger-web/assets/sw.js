@@ -0,0 +1,59 @@
+// Service worker for the mobile quick-entry page (/m).
+//
+// Keeps a copy of the page and the files it needs so it opens without a
+// connection. Transactions entered while offline are queued by the page
+// itself and sent once the phone is back online.
+
+const CACHE = 'rustledger-m-v1';
+
+const PRECACHE = [
+    '/m',
+    '/assets/manifest.webmanifest',
+    '/assets/icon.svg',
+];
+
+// Third-party scripts used by the page, stored as opaque responses
+const PRECACHE_CROSS_ORIGIN = [
+    'https://cdn.tailwindcss.com',
+];
+
+self.addEventListener('install', event => {
+    event.waitUntil(
+        caches.open(CACHE).then(cache => Promise.all([
+            cache.addAll(PRECACHE),
+            ...PRECACHE_CROSS_ORIGIN.map(url =>
+                fetch(new Request(url, { mode: 'no-cors' }))
+                    .then(response => cache.put(url, response))
+                    .catch(() => {})
+            ),
+        ])).then(() => self.skipWaiting())
+    );
+});
+
+self.addEventListener('activate', event => {
+    event.waitUntil(
+        caches.keys()
+            .then(keys => Promise.all(keys.filter(key => key !== CACHE).map(key => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+});
+
+// Network first, so the page shows fresh payees and accounts when online,
+// falling back to the cached copy otherwise.
+self.addEventListener('fetch', event => {
+    if (event.request.method !== 'GET') {
+        return;
+    }
+
+    event.respondWith(
+        fetch(event.request)
+            .then(response => {
+                if (response.ok || response.type === 'opaque') {
+                    const copy = response.clone();
+                    caches.open(CACHE).then(cache => cache.put(event.request, copy));
+                }
+                return response;
+            })
+            .catch(() => caches.match(event.request).then(cached => cached || Response.error()))
+    );
+});
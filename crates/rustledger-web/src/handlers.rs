@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -5,35 +6,80 @@ use std::sync::Arc;
 
 use axum::{
     Form, Json,
-    extract::{Path as AxumPath, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    extract::{Path as AxumPath, Query, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
 };
 use tera::Context;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, RwLock, broadcast};
 
+use rust_decimal::Decimal;
+use rustledger_booking::{
+    InterpolationError, calculate_residual, calculate_tolerance, interpolate,
+};
+use rustledger_core::{AmountParser, DiffSummary, Directive, Period, PeriodKind};
 use rustledger_loader::{LoadResult, Loader};
 
 use crate::models::{
-    CloseAccountRequest, CreateTransactionRequest, DeleteTransactionRequest,
-    EditTransactionRequest, GetEditFormRequest, IncomeExpenseStats, NetWorthStats,
-    OpenAccountRequest, ToggleStatusRequest,
+    AccountNode, BudgetRequest, CloseAccountRequest, CreateTransactionRequest,
+    DeleteTransactionRequest, EditTransactionRequest, GetEditFormRequest, IncomeExpenseStats,
+    NetWorthStats, OpenAccountRequest, PostingInput, PostingRow, QueryExportRequest,
+    RegisterExportRequest, SankeyRequest, ToggleStatusRequest, TransactionFormErrors,
+    TransactionListRequest,
 };
+use crate::undo::{self, UndoEntry};
 use crate::utils::{
-    build_account_tree, calculate_account_balance, calculate_cash_flow_history,
-    calculate_monthly_income_expenses, calculate_net_worth, calculate_net_worth_history,
-    detect_operating_currency, extract_account_transactions, extract_accounts, extract_payees,
-    extract_recent_transactions, get_sub_accounts, get_top_accounts,
+    TransactionIssues, annotate_transactions, build_account_tree_with_balances,
+    build_price_database, calculate_account_balance, calculate_account_totals, calculate_budgets,
+    calculate_cash_flow_history, calculate_commodity_holdings, calculate_monthly_income_expenses,
+    calculate_net_worth, calculate_net_worth_history, calculate_portfolio, calculate_sankey,
+    commodity_declaration, commodity_price_history, commodity_quote_currency, currency_registry,
+    detect_operating_currency, extract_account_transactions, extract_accounts, extract_commodities,
+    extract_matching_transactions, extract_payees, extract_recent_transactions,
+    format_commodity_holdings, frequent_accounts, frequent_payees, get_sub_accounts,
+    get_top_accounts, ledger_snapshot, query_result_csv, register_csv, summarize_commodities,
+    summarize_tags, transaction_issues,
 };
 
 /// Shared application state
 pub struct AppState {
     pub ledger_path: PathBuf,
+    /// Generated ledger served instead of the file at `ledger_path` in demo
+    /// mode, where changes are rejected
+    pub demo_source: Option<String>,
     pub tera: tera::Tera,
     /// Cached ledger data, protected by RwLock for concurrent reads
     pub cached_ledger: RwLock<Option<LoadResult>>,
+    /// Sidebar account tree with balances, computed with the cached ledger
+    pub cached_account_tree: RwLock<Option<BTreeMap<String, AccountNode>>>,
+    /// Validation problems of each transaction, computed with the cached
+    /// ledger
+    pub cached_issues: RwLock<Option<TransactionIssues>>,
     /// Mutex to serialize file write operations
     pub write_lock: Mutex<()>,
+    /// Notified when the ledger changes on disk, for live reload
+    pub ledger_changes: broadcast::Sender<()>,
+}
+
+/// Load the templates and register the functions they call.
+///
+/// `demo` is what the `demo_mode()` template function reports.
+pub fn build_tera(demo: bool) -> tera::Result<tera::Tera> {
+    // Use CARGO_MANIFEST_DIR to find templates relative to the crate
+    let template_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*");
+    let mut tera = tera::Tera::new(template_dir)
+        .or_else(|_| tera::Tera::new("templates/**/*"))
+        .or_else(|_| tera::Tera::new("crates/rustledger-web/templates/**/*"))?;
+
+    // Disable auto-escaping for HTML content if needed
+    tera.autoescape_on(vec![".html", ".sql"]);
+
+    tera.register_function(
+        "demo_mode",
+        move |_: &std::collections::HashMap<String, tera::Value>| Ok(tera::Value::Bool(demo)),
+    );
+    Ok(tera)
 }
 
 /// Validates that a path is safe to access (within the ledger directory).
@@ -117,18 +163,39 @@ async fn load_ledger(state: &Arc<AppState>) -> anyhow::Result<LoadResult> {
 
     // Actually load the ledger
     let mut loader = Loader::new();
-    let result = loader.load(&state.ledger_path)?;
+    let result = match &state.demo_source {
+        Some(source) => loader.load_source(&state.ledger_path, source)?,
+        None => loader.load(&state.ledger_path)?,
+    };
     
-    // Store in cache
+    // Store in cache, along with the sidebar tree and validation problems
+    // computed from it
+    *state.cached_account_tree.write().await =
+        Some(build_account_tree_with_balances(&result.directives));
+    *state.cached_issues.write().await = Some(transaction_issues(&result));
     *cache = Some(clone_load_result(&result));
     
     Ok(result)
 }
 
+/// Middleware rejecting requests that would change the ledger in demo mode.
+pub async fn read_only_guard(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.demo_source.is_some() && !request.method().is_safe() {
+        return (StatusCode::FORBIDDEN, "The demo ledger is read-only").into_response();
+    }
+    next.run(request).await
+}
+
 /// Invalidate the cached ledger (call after file modifications)
-async fn invalidate_cache(state: &Arc<AppState>) {
+pub(crate) async fn invalidate_cache(state: &AppState) {
     let mut cache = state.cached_ledger.write().await;
     *cache = None;
+    *state.cached_account_tree.write().await = None;
+    *state.cached_issues.write().await = None;
 }
 
 /// Clone a LoadResult for caching purposes.
@@ -141,7 +208,31 @@ fn clone_load_result(result: &LoadResult) -> LoadResult {
         plugins: result.plugins.clone(),
         source_map: result.source_map.clone(),
         errors: Vec::new(), // Errors are not cloneable, but we don't need them for cached reads
+        file_stats: result.file_stats.clone(),
+    }
+}
+
+/// Sidebar account tree for a ledger returned by [`load_ledger`].
+///
+/// The tree is cached with the ledger; it is only recomputed when the cache
+/// was invalidated after `load_result` was loaded.
+async fn account_tree(
+    state: &Arc<AppState>,
+    load_result: &LoadResult,
+) -> BTreeMap<String, AccountNode> {
+    if let Some(tree) = state.cached_account_tree.read().await.as_ref() {
+        return tree.clone();
+    }
+    build_account_tree_with_balances(&load_result.directives)
+}
+
+/// Validation problems of the transactions in a ledger returned by
+/// [`load_ledger`], cached like [`account_tree`].
+async fn ledger_issues(state: &Arc<AppState>, load_result: &LoadResult) -> TransactionIssues {
+    if let Some(issues) = state.cached_issues.read().await.as_ref() {
+        return issues.clone();
     }
+    transaction_issues(load_result)
 }
 
 /// Handler for the main dashboard page.
@@ -152,9 +243,13 @@ pub async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     };
 
     let accounts = extract_accounts(&load_result.directives);
-    let account_tree = build_account_tree(&accounts);
-    let recent_txns =
-        extract_recent_transactions(&load_result.directives, &load_result.directive_sources, 10);
+    let account_tree = account_tree(&state, &load_result).await;
+    let recent_txns = annotate_transactions(
+        extract_recent_transactions(&load_result.directives, &load_result.directive_sources, 10),
+        &ledger_issues(&state, &load_result).await,
+        false,
+        10,
+    );
 
     // Get operating currency - use configured option or detect from ledger
     let operating_currency = load_result
@@ -187,6 +282,17 @@ pub async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     );
     context.insert("source_file", &state.ledger_path.to_string_lossy());
     context.insert("errors", &error_strings);
+    context.insert("load_summary", &load_result.summary());
+    context.insert(
+        "ledger_dir",
+        // Loaded file paths are canonical
+        &state
+            .ledger_path
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.parent().map(|dir| format!("{}/", dir.display())))
+            .unwrap_or_default(),
+    );
     context.insert("account_tree", &account_tree);
     context.insert("recent_transactions", &recent_txns);
     context.insert("accounts", &accounts);
@@ -253,6 +359,23 @@ fn validate_string_field(s: &str) -> bool {
     !s.contains('\n') && !s.contains('\r')
 }
 
+/// Normalizes an amount typed into the quick-add form, so that `$1,234.56`
+/// is written as `1234.56 USD` and `12,50 €` as `12.50 EUR`.
+///
+/// Anything that is not a plain amount, such as an amount with a cost or
+/// price, is kept as typed.
+fn normalize_amount(amount: &str) -> String {
+    let parser = AmountParser::new();
+    let parsed = parser
+        .parse_number(amount)
+        .or_else(|_| parser.with_decimal_comma(true).parse_number(amount));
+    match parsed {
+        Ok((number, Some(currency))) => format!("{} {}", number, currency),
+        Ok((number, None)) => number.to_string(),
+        Err(_) => amount.trim().to_string(),
+    }
+}
+
 /// Handler to create a new transaction.
 pub async fn create_transaction(
     State(state): State<Arc<AppState>>,
@@ -297,7 +420,12 @@ pub async fn create_transaction(
 
     let mut txn_text = format!(
         "\n{} {} {}{}\n  {} {}\n",
-        payload.date, flag, payee_str, narration_str, payload.account_1, payload.amount_1
+        payload.date,
+        flag,
+        payee_str,
+        narration_str,
+        payload.account_1,
+        normalize_amount(&payload.amount_1)
     );
 
     if let (Some(acc2), Some(amt2)) = (payload.account_2, payload.amount_2) {
@@ -306,7 +434,7 @@ pub async fn create_transaction(
                 return Html("<div class='text-red-500'>Invalid second account name.</div>")
                     .into_response();
             }
-            txn_text.push_str(&format!("  {} {}\n", acc2, amt2));
+            txn_text.push_str(&format!("  {} {}\n", acc2, normalize_amount(&amt2)));
         }
     }
 
@@ -492,6 +620,12 @@ pub async fn delete_transaction(
         return (StatusCode::BAD_REQUEST, "Invalid offset/length").into_response();
     }
 
+    let removed =
+        match std::str::from_utf8(&buffer[payload.offset..payload.offset + payload.length]) {
+            Ok(text) => text.to_string(),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid offset/length").into_response(),
+        };
+
     // Remove the bytes
     buffer.drain(payload.offset..payload.offset + payload.length);
 
@@ -499,6 +633,11 @@ pub async fn delete_transaction(
         return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to write file").into_response();
     }
 
+    let entry = UndoEntry::new("Delete transaction", &path, payload.offset, removed, "");
+    if let Err(e) = undo::record(&state.ledger_path, &entry) {
+        tracing::warn!("Failed to record undo entry: {}", e);
+    }
+
     // Invalidate cache after successful write
     invalidate_cache(&state).await;
 
@@ -574,9 +713,8 @@ pub async fn get_edit_form(
         narration = n.to_string();
     }
 
-    // Extract accounts (simple heuristic looking for 2 spaces indentation)
-    let mut accounts = Vec::new();
-    let mut amounts = Vec::new();
+    // Extract postings (simple heuristic looking for 2 spaces indentation)
+    let mut postings = Vec::new();
 
     for line in raw_txn.lines().skip(1) {
         let trimmed = line.trim();
@@ -586,36 +724,76 @@ pub async fn get_edit_form(
 
         let parts: Vec<&str> = trimmed.split_whitespace().collect();
         if let Some(first) = parts.first() {
-            accounts.push(first.to_string());
-            if parts.len() > 1 {
+            postings.push(PostingInput {
+                account: first.to_string(),
                 // Join the rest as amount
-                amounts.push(parts[1..].join(" "));
-            } else {
-                amounts.push(String::new());
-            }
+                amount: parts[1..].join(" "),
+            });
         }
     }
 
+    let request = EditTransactionRequest {
+        original_offset: params.offset,
+        original_length: params.length,
+        original_source_path: params.source_path,
+        date: date.to_string(),
+        payee: Some(payee),
+        narration,
+        cleared: cleared.then(|| "on".to_string()),
+        postings,
+    };
+
+    render_edit_form(&state, &request, &TransactionFormErrors::default()).await
+}
+
+/// Renders the edit form for `request`, showing `errors` next to their fields.
+async fn render_edit_form(
+    state: &Arc<AppState>,
+    request: &EditTransactionRequest,
+    errors: &TransactionFormErrors,
+) -> Response {
     // Load accounts for dropdown
-    let load_result = match load_ledger(&state).await {
+    let load_result = match load_ledger(state).await {
         Ok(res) => res,
         Err(_) => return Html("Error loading accounts".to_string()).into_response(),
     };
     let all_accounts = extract_accounts(&load_result.directives);
     let all_payees = extract_payees(&load_result.directives);
 
+    let mut postings: Vec<PostingRow> = request
+        .postings
+        .iter()
+        .enumerate()
+        .map(|(i, posting)| {
+            let (account_error, amount_error) = errors.postings.get(i).cloned().unwrap_or_default();
+            PostingRow {
+                account: posting.account.clone(),
+                amount: posting.amount.clone(),
+                account_error,
+                amount_error,
+            }
+        })
+        .collect();
+    // Always offer at least two rows to fill in
+    while postings.len() < 2 {
+        postings.push(PostingRow {
+            account: String::new(),
+            amount: String::new(),
+            account_error: None,
+            amount_error: None,
+        });
+    }
+
     let mut context = Context::new();
-    context.insert("date", date);
-    context.insert("payee", &payee);
-    context.insert("narration", &narration);
-    context.insert("cleared", &cleared);
-    context.insert("account_1", accounts.first().unwrap_or(&String::new()));
-    context.insert("amount_1", amounts.first().unwrap_or(&String::new()));
-    context.insert("account_2", accounts.get(1).unwrap_or(&String::new()));
-    context.insert("amount_2", amounts.get(1).unwrap_or(&String::new()));
-    context.insert("original_offset", &params.offset);
-    context.insert("original_length", &params.length);
-    context.insert("original_source_path", &params.source_path);
+    context.insert("date", &request.date);
+    context.insert("payee", request.payee.as_deref().unwrap_or(""));
+    context.insert("narration", &request.narration);
+    context.insert("cleared", &request.cleared.is_some());
+    context.insert("postings", &postings);
+    context.insert("errors", errors);
+    context.insert("original_offset", &request.original_offset);
+    context.insert("original_length", &request.original_length);
+    context.insert("original_source_path", &request.original_source_path);
     context.insert("accounts", &all_accounts);
     context.insert("payees", &all_payees);
 
@@ -630,26 +808,214 @@ pub async fn get_edit_form(
     Html(rendered).into_response()
 }
 
-/// Handler to process the update (delete old + create new).
-pub async fn update_transaction(
-    State(state): State<Arc<AppState>>,
-    Form(payload): Form<EditTransactionRequest>,
-) -> impl IntoResponse {
-    // Validate inputs
-    if !validate_date(&payload.date) {
-        return Html("<div class='text-red-500'>Invalid date format. Use YYYY-MM-DD.</div>")
-            .into_response();
+/// Handler returning an empty posting row for the edit form.
+pub async fn get_posting_row(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state
+        .tera
+        .render("partials/posting_row.html", &Context::new())
+    {
+        Ok(t) => Html(t),
+        Err(e) => Html(format!("Template Error: {}", e)),
     }
+}
 
-    if !validate_account(&payload.account_1) {
-        return Html("<div class='text-red-500'>Invalid account name.</div>").into_response();
+/// Builds an edit request from the submitted form fields.
+///
+/// Posting rows are paired up in order; rows with neither an account nor an
+/// amount are dropped.
+fn parse_edit_form(fields: Vec<(String, String)>) -> Result<EditTransactionRequest, &'static str> {
+    let mut request = EditTransactionRequest::default();
+    let mut offset = None;
+    let mut length = None;
+    let mut accounts = Vec::new();
+    let mut amounts = Vec::new();
+
+    for (key, value) in fields {
+        match key.as_str() {
+            "original_offset" => offset = value.parse().ok(),
+            "original_length" => length = value.parse().ok(),
+            "original_source_path" => request.original_source_path = value,
+            "date" => request.date = value,
+            "payee" => request.payee = Some(value),
+            "narration" => request.narration = value,
+            "cleared" => request.cleared = Some(value),
+            "account" => accounts.push(value),
+            "amount" => amounts.push(value),
+            _ => {}
+        }
     }
 
-    if !validate_string_field(&payload.narration) {
-        return Html("<div class='text-red-500'>Narration contains invalid characters.</div>")
-            .into_response();
+    let (Some(offset), Some(length)) = (offset, length) else {
+        return Err("Missing original transaction location");
+    };
+    request.original_offset = offset;
+    request.original_length = length;
+
+    amounts.resize(accounts.len().max(amounts.len()), String::new());
+    accounts.resize(amounts.len(), String::new());
+    request.postings = accounts
+        .into_iter()
+        .zip(amounts)
+        .map(|(account, amount)| PostingInput {
+            account: account.trim().to_string(),
+            amount: amount.trim().to_string(),
+        })
+        .filter(|p| !p.account.is_empty() || !p.amount.is_empty())
+        .collect();
+
+    Ok(request)
+}
+
+/// Validates an edited transaction and renders it as beancount text.
+///
+/// Fields are checked first, then the rendered text is parsed and
+/// interpolated so that syntax errors and a non-zero residual are reported
+/// instead of being written to the ledger.
+fn check_edited_transaction(
+    request: &EditTransactionRequest,
+) -> Result<String, TransactionFormErrors> {
+    let mut errors = TransactionFormErrors {
+        postings: vec![(None, None); request.postings.len()],
+        ..Default::default()
+    };
+
+    if !validate_date(&request.date) {
+        errors.date = Some("Invalid date format. Use YYYY-MM-DD.".to_string());
+    }
+    if request.narration.trim().is_empty() {
+        errors.narration = Some("Narration is required.".to_string());
+    } else if !validate_string_field(&request.narration) {
+        errors.narration = Some("Narration contains invalid characters.".to_string());
+    }
+    if let Some(ref payee) = request.payee {
+        if !validate_string_field(payee) {
+            errors.payee = Some("Payee contains invalid characters.".to_string());
+        }
+    }
+    if request.postings.is_empty() {
+        errors
+            .form
+            .push("A transaction needs at least one posting.".to_string());
+    }
+    for (posting, (account_error, amount_error)) in
+        request.postings.iter().zip(&mut errors.postings)
+    {
+        if posting.account.is_empty() {
+            *account_error = Some("Account is required.".to_string());
+        } else if !validate_account(&posting.account) {
+            *account_error = Some("Invalid account name.".to_string());
+        }
+        if !validate_string_field(&posting.amount) {
+            *amount_error = Some("Amount contains invalid characters.".to_string());
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let flag = if request.cleared.is_some() { "*" } else { "!" };
+
+    // Escape quotes in payee and narration
+    let payee_str = match request.payee.as_deref() {
+        Some(p) if !p.is_empty() => format!(" \"{}\"", p.replace('"', "\\\"")),
+        _ => String::new(),
+    };
+    let narration_str = format!("\"{}\"", request.narration.replace('"', "\\\""));
+
+    let mut txn_text = format!("{} {}{} {}\n", request.date, flag, payee_str, narration_str);
+    for posting in &request.postings {
+        txn_text.push_str(format!("  {} {}", posting.account, posting.amount).trim_end());
+        txn_text.push('\n');
+    }
+
+    // Line 0 is the header; line N is posting N-1
+    let result = rustledger_parser::parse(&txn_text);
+    for error in &result.errors {
+        let line = txn_text[..error.span.start.min(txn_text.len())]
+            .matches('\n')
+            .count();
+        match line.checked_sub(1).and_then(|i| errors.postings.get_mut(i)) {
+            Some((_, amount_error)) => {
+                amount_error.get_or_insert_with(|| error.message());
+            }
+            None => errors.form.push(error.message()),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let txn = match result.directives.as_slice() {
+        [spanned] => match &spanned.value {
+            Directive::Transaction(txn) => txn,
+            _ => {
+                errors.form.push("Entry is not a transaction.".to_string());
+                return Err(errors);
+            }
+        },
+        _ => {
+            errors
+                .form
+                .push("Entry did not parse as a single transaction.".to_string());
+            return Err(errors);
+        }
+    };
+
+    let interpolated = match interpolate(txn) {
+        Ok(result) => result.transaction,
+        Err(e) => {
+            let row = match &e {
+                InterpolationError::CannotInferCurrency { account } => request
+                    .postings
+                    .iter()
+                    .position(|p| p.account == account.as_str()),
+                _ => None,
+            };
+            match row {
+                Some(i) => errors.postings[i].1 = Some(e.to_string()),
+                None => errors.form.push(e.to_string()),
+            }
+            return Err(errors);
+        }
+    };
+
+    // Interpolation only balances elided amounts; check the residual too
+    let amounts: Vec<_> = txn.postings.iter().filter_map(|p| p.amount()).collect();
+    let tolerances = calculate_tolerance(&amounts);
+    for (currency, residual) in calculate_residual(&interpolated) {
+        let tolerance = tolerances
+            .get(&currency)
+            .copied()
+            .unwrap_or(Decimal::new(5, 3));
+        if residual.abs() > tolerance {
+            errors
+                .form
+                .push(InterpolationError::DoesNotBalance { currency, residual }.to_string());
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
+    Ok(txn_text)
+}
+
+/// Handler to process the update (delete old + create new).
+pub async fn update_transaction(
+    State(state): State<Arc<AppState>>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let payload = match parse_edit_form(fields) {
+        Ok(p) => p,
+        Err(e) => return Html(format!("Error: {}", e)).into_response(),
+    };
+
+    // Validate before touching the file; re-render the form with errors inline
+    let txn_text = match check_edited_transaction(&payload) {
+        Ok(text) => text,
+        Err(errors) => return render_edit_form(&state, &payload, &errors).await,
+    };
+
     // 1. Delete original
     let del_req = DeleteTransactionRequest {
         offset: payload.original_offset,
@@ -675,39 +1041,17 @@ pub async fn update_transaction(
         return Html("Error: Invalid bounds".to_string()).into_response();
     }
 
+    let removed =
+        match std::str::from_utf8(&file_content[del_req.offset..del_req.offset + del_req.length]) {
+            Ok(text) => text.to_string(),
+            Err(_) => return Html("Error: Invalid bounds".to_string()).into_response(),
+        };
+
     // remove old
     file_content.drain(del_req.offset..del_req.offset + del_req.length);
 
-    // 2. Construct new transaction text
-    let flag = if payload.cleared.is_some() { "*" } else { "!" };
-
-    // Escape quotes in payee and narration
-    let payee_str = if let Some(p) = payload.payee {
-        if !p.is_empty() {
-            format!(" \"{}\"", p.replace('"', "\\\""))
-        } else {
-            String::new()
-        }
-    } else {
-        String::new()
-    };
-
-    let narration_str = format!("\"{}\"", payload.narration.replace('"', "\\\""));
-
-    let mut new_txn_text = format!(
-        "\n{} {} {}{}\n  {} {}\n",
-        payload.date, flag, payee_str, narration_str, payload.account_1, payload.amount_1
-    );
-
-    if let (Some(acc2), Some(amt2)) = (payload.account_2, payload.amount_2) {
-        if !acc2.is_empty() {
-            if !validate_account(&acc2) {
-                return Html("<div class='text-red-500'>Invalid second account name.</div>")
-                    .into_response();
-            }
-            new_txn_text.push_str(&format!("  {} {}\n", acc2, amt2));
-        }
-    }
+    // 2. Insert the validated transaction text
+    let new_txn_text = format!("\n{}", txn_text);
 
     // Append new to end of file content (which is simpler than inserting in place)
     // Or insert at original position? Inserting at original position keeps date order roughly.
@@ -732,6 +1076,17 @@ pub async fn update_transaction(
         return Html(format!("Error writing file: {}", e)).into_response();
     }
 
+    let entry = UndoEntry::new(
+        "Update transaction",
+        &path,
+        del_req.offset,
+        removed,
+        new_txn_text,
+    );
+    if let Err(e) = undo::record(&state.ledger_path, &entry) {
+        tracing::warn!("Failed to record undo entry: {}", e);
+    }
+
     // Invalidate cache after successful write
     invalidate_cache(&state).await;
 
@@ -743,24 +1098,65 @@ pub async fn update_transaction(
         .into_response()
 }
 
+/// Handler to revert the most recent delete or update.
+pub async fn undo_last_change(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    // Acquire write lock to serialize file modifications
+    let _write_guard = state.write_lock.lock().await;
+
+    match undo::undo_last(&state.ledger_path) {
+        Ok(Some(entry)) => {
+            invalidate_cache(&state).await;
+            let summary = DiffSummary::of(&entry.undo_changes());
+            (
+                [("HX-Redirect", "/transactions")],
+                Html(format!(
+                    "<div>Undid: {} ({} restored, {} removed, {} reverted). Redirecting...</div>",
+                    entry.description, summary.added, summary.removed, summary.modified
+                )),
+            )
+                .into_response()
+        }
+        Ok(None) => (StatusCode::CONFLICT, "Nothing to undo").into_response(),
+        Err(e) => (StatusCode::CONFLICT, format!("Undo failed: {}", e)).into_response(),
+    }
+}
+
 /// Handler for the transactions list page.
-pub async fn transactions_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn transactions_page(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TransactionListRequest>,
+) -> impl IntoResponse {
     let load_result = match load_ledger(&state).await {
         Ok(res) => res,
         Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
     };
 
     let accounts = extract_accounts(&load_result.directives);
-    let account_tree = build_account_tree(&accounts);
-    // Get more transactions for the full list
-    let transactions =
-        extract_recent_transactions(&load_result.directives, &load_result.directive_sources, 100);
+    let account_tree = account_tree(&state, &load_result).await;
+    // Get more transactions for the full list; problems are searched for
+    // in all of them
+    let limit = if params.problems { usize::MAX } else { 100 };
+    let transactions = annotate_transactions(
+        extract_recent_transactions(
+            &load_result.directives,
+            &load_result.directive_sources,
+            limit,
+        ),
+        &ledger_issues(&state, &load_result).await,
+        params.problems,
+        100,
+    );
 
     let mut context = Context::new();
     context.insert("current_page", "transactions");
+    context.insert("only_problems", &params.problems);
     context.insert("account_tree", &account_tree);
     context.insert("transactions", &transactions);
     context.insert("accounts", &accounts);
+    context.insert(
+        "can_undo",
+        &undo::entries(&state.ledger_path).is_ok_and(|entries| !entries.is_empty()),
+    );
 
     let rendered = match state.tera.render("transactions.html", &context) {
         Ok(t) => t,
@@ -778,7 +1174,7 @@ pub async fn add_transaction_page(State(state): State<Arc<AppState>>) -> impl In
     };
 
     let accounts = extract_accounts(&load_result.directives);
-    let account_tree = build_account_tree(&accounts);
+    let account_tree = account_tree(&state, &load_result).await;
     let payees = extract_payees(&load_result.directives);
 
     let mut context = Context::new();
@@ -795,6 +1191,55 @@ pub async fn add_transaction_page(State(state): State<Arc<AppState>>) -> impl In
     Html(rendered)
 }
 
+/// Number of quick-pick buttons shown per group on the mobile page.
+const MOBILE_QUICK_PICKS: usize = 8;
+
+/// Handler for the mobile quick-entry page.
+pub async fn mobile_entry_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let directives = &load_result.directives;
+    let mut context = Context::new();
+    context.insert("payees", &frequent_payees(directives, MOBILE_QUICK_PICKS));
+    context.insert(
+        "expense_accounts",
+        &frequent_accounts(directives, &["Expenses"], MOBILE_QUICK_PICKS),
+    );
+    context.insert(
+        "funding_accounts",
+        &frequent_accounts(directives, &["Assets", "Liabilities"], MOBILE_QUICK_PICKS),
+    );
+    context.insert("accounts", &extract_accounts(directives));
+    context.insert("currency", &detect_operating_currency(directives));
+
+    let rendered = match state.tera.render("mobile_entry.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Service worker for the mobile quick-entry page.
+///
+/// Served under `/m` instead of `/assets` so it may control the page.
+pub async fn mobile_service_worker() -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript; charset=utf-8"),
+            (
+                header::HeaderName::from_static("service-worker-allowed"),
+                "/m",
+            ),
+        ],
+        include_str!("../assets/sw.js"),
+    )
+        .into_response()
+}
+
 /// API endpoint to get payees list.
 pub async fn get_payees(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let load_result = match load_ledger(&state).await {
@@ -838,6 +1283,22 @@ pub async fn get_net_worth_stats(State(state): State<Arc<AppState>>) -> impl Int
     .into_response()
 }
 
+/// API endpoint for the load summary (per-file statistics).
+pub async fn get_load_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+
+    Json(load_result.summary()).into_response()
+}
+
 /// API endpoint for income/expenses stats.
 pub async fn get_income_expense_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let load_result = match load_ledger(&state).await {
@@ -898,6 +1359,56 @@ pub async fn get_cash_flow(State(state): State<Arc<AppState>>) -> impl IntoRespo
     Json(cash_flow).into_response()
 }
 
+/// Resolves the period and depth of a sankey request.
+fn sankey_params(params: &SankeyRequest) -> Result<(Period, usize), String> {
+    let period = match params.period.as_deref() {
+        None | Some("") => Period::containing(
+            chrono::Local::now().date_naive(),
+            PeriodKind::Year { start_month: 1 },
+        ),
+        Some(period) => period.parse()?,
+    };
+    Ok((period, params.depth.unwrap_or(2).max(1)))
+}
+
+/// API endpoint for the cash flow sankey chart.
+pub async fn get_sankey(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SankeyRequest>,
+) -> impl IntoResponse {
+    let (period, depth) = match sankey_params(&params) {
+        Ok(params) => params,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e})),
+            )
+                .into_response();
+        }
+    };
+
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+
+    let operating_currency = load_result
+        .options
+        .operating_currency
+        .first()
+        .cloned()
+        .unwrap_or_else(|| detect_operating_currency(&load_result.directives));
+
+    let sankey = calculate_sankey(&load_result.directives, &operating_currency, &period, depth);
+    Json(sankey).into_response()
+}
+
 /// API endpoint for net worth history.
 pub async fn get_net_worth_history(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let load_result = match load_ledger(&state).await {
@@ -1055,7 +1566,7 @@ pub async fn accounts_page(State(state): State<Arc<AppState>>) -> impl IntoRespo
     };
 
     let accounts = extract_accounts(&load_result.directives);
-    let account_tree = build_account_tree(&accounts);
+    let account_tree = account_tree(&state, &load_result).await;
 
     let mut context = Context::new();
     context.insert("current_page", "accounts");
@@ -1075,6 +1586,7 @@ pub async fn accounts_page(State(state): State<Arc<AppState>>) -> impl IntoRespo
 pub async fn account_detail(
     State(state): State<Arc<AppState>>,
     AxumPath(account_path): AxumPath<String>,
+    Query(params): Query<TransactionListRequest>,
 ) -> Html<String> {
     let load_result = match load_ledger(&state).await {
         Ok(res) => res,
@@ -1087,7 +1599,7 @@ pub async fn account_detail(
         .unwrap_or(account_path);
 
     let accounts = extract_accounts(&load_result.directives);
-    let account_tree = build_account_tree(&accounts);
+    let account_tree = account_tree(&state, &load_result).await;
 
     // Check if this is an exact account or a prefix
     let is_exact_account = accounts.contains(&account_name);
@@ -1096,10 +1608,16 @@ pub async fn account_detail(
         sub_accounts.len() > 1 || (!is_exact_account && !sub_accounts.is_empty());
 
     // Get transactions for this account/prefix
-    let transactions = extract_account_transactions(
-        &load_result.directives,
-        &load_result.directive_sources,
-        &account_name,
+    let limit = if params.problems { usize::MAX } else { 100 };
+    let transactions = annotate_transactions(
+        extract_account_transactions(
+            &load_result.directives,
+            &load_result.directive_sources,
+            &account_name,
+            limit,
+        ),
+        &ledger_issues(&state, &load_result).await,
+        params.problems,
         100,
     );
 
@@ -1120,6 +1638,7 @@ pub async fn account_detail(
     context.insert("sub_accounts", &sub_accounts);
     context.insert("has_sub_accounts", &has_sub_accounts);
     context.insert("transactions", &transactions);
+    context.insert("only_problems", &params.problems);
     context.insert("balances", &balance_display);
     context.insert("transaction_count", &transactions.len());
 
@@ -1131,12 +1650,416 @@ pub async fn account_detail(
     Html(rendered)
 }
 
+/// Handler for the commodities list page.
+pub async fn commodities_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(&state, &load_result).await;
+    let operating_currency = detect_operating_currency(&load_result.directives);
+    let prices = build_price_database(&load_result.directives);
+    let commodities = summarize_commodities(&load_result.directives, &prices, &operating_currency);
+
+    let mut context = Context::new();
+    context.insert("current_page", "commodities");
+    context.insert("account_tree", &account_tree);
+    context.insert("commodities", &commodities);
+
+    let rendered = match state.tera.render("commodities.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the commodity detail page.
+/// Shows declaration metadata, price history, holdings and total exposure.
+pub async fn commodity_detail(
+    State(state): State<Arc<AppState>>,
+    AxumPath(name): AxumPath<String>,
+) -> Html<String> {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let commodity = urlencoding::decode(&name)
+        .map(|s| s.into_owned())
+        .unwrap_or(name);
+
+    if !extract_commodities(&load_result.directives).contains(&commodity) {
+        return Html(format!(
+            "<h1>Unknown commodity</h1><p>{}</p>",
+            tera::escape_html(&commodity)
+        ));
+    }
+
+    let account_tree = account_tree(&state, &load_result).await;
+    let operating_currency = detect_operating_currency(&load_result.directives);
+    let prices = build_price_database(&load_result.directives);
+
+    let declaration = commodity_declaration(&load_result.directives, &commodity);
+    let registry = currency_registry(&load_result.directives);
+    let quote_currency = commodity_quote_currency(&prices, &commodity, &operating_currency);
+    let latest_price = quote_currency
+        .as_deref()
+        .and_then(|quote| Some((prices.get_latest_price(&commodity, quote)?, quote)));
+    let price_history = quote_currency
+        .as_deref()
+        .map(|quote| commodity_price_history(&prices, &commodity, quote))
+        .unwrap_or_default();
+
+    let (holdings, total_units) = calculate_commodity_holdings(&load_result.directives, &commodity);
+    let holdings = format_commodity_holdings(&holdings, &commodity, latest_price);
+    let exposure =
+        latest_price.map(|(price, quote)| format!("{:.2} {}", total_units * price, quote));
+
+    let mut context = Context::new();
+    context.insert("current_page", "commodity_detail");
+    context.insert("account_tree", &account_tree);
+    context.insert("commodity", &commodity);
+    context.insert("full_name", &registry.name(&commodity));
+    context.insert("declared_on", &declaration.as_ref().map(|(date, _)| date));
+    context.insert(
+        "metadata",
+        &declaration.map(|(_, meta)| meta).unwrap_or_default(),
+    );
+    context.insert("quote_currency", &quote_currency);
+    context.insert(
+        "latest_price",
+        &latest_price.map(|(price, quote)| format!("{} {}", price, quote)),
+    );
+    context.insert("price_history", &price_history);
+    context.insert("holdings", &holdings);
+    context.insert("total_units", &format!("{} {}", total_units, commodity));
+    context.insert("exposure", &exposure);
+
+    let rendered = match state.tera.render("commodity_detail.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the portfolio page.
+/// Shows current holdings grouped by asset class with cost basis, market value and gains.
+pub async fn portfolio_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(&state, &load_result).await;
+    let operating_currency = detect_operating_currency(&load_result.directives);
+    let prices = build_price_database(&load_result.directives);
+    let portfolio = calculate_portfolio(&load_result.directives, &prices, &operating_currency);
+
+    let mut context = Context::new();
+    context.insert("current_page", "portfolio");
+    context.insert("account_tree", &account_tree);
+    context.insert("portfolio", &portfolio);
+
+    let rendered = match state.tera.render("portfolio.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the tags list page.
+pub async fn tags_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(&state, &load_result).await;
+    let tags = summarize_tags(&load_result.directives);
+
+    let mut context = Context::new();
+    context.insert("current_page", "tags");
+    context.insert("account_tree", &account_tree);
+    context.insert("tags", &tags);
+
+    let rendered = match state.tera.render("tags.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the page listing the transactions with a tag.
+pub async fn tag_detail(
+    State(state): State<Arc<AppState>>,
+    AxumPath(tag): AxumPath<String>,
+) -> Html<String> {
+    let tag = urlencoding::decode(&tag)
+        .map(|s| s.into_owned())
+        .unwrap_or(tag);
+    label_detail(&state, "tag", &tag, |txn| txn.tags.contains(&tag)).await
+}
+
+/// Handler for the page listing the transactions sharing a link.
+pub async fn link_detail(
+    State(state): State<Arc<AppState>>,
+    AxumPath(link): AxumPath<String>,
+) -> Html<String> {
+    let link = urlencoding::decode(&link)
+        .map(|s| s.into_owned())
+        .unwrap_or(link);
+    label_detail(&state, "link", &link, |txn| txn.links.contains(&link)).await
+}
+
+/// Renders the transactions matching a tag or link, with totals per account.
+async fn label_detail(
+    state: &Arc<AppState>,
+    kind: &str,
+    name: &str,
+    filter: impl Fn(&rustledger_core::Transaction) -> bool,
+) -> Html<String> {
+    let load_result = match load_ledger(state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(state, &load_result).await;
+    let transactions = annotate_transactions(
+        extract_matching_transactions(
+            &load_result.directives,
+            &load_result.directive_sources,
+            &filter,
+        ),
+        &ledger_issues(state, &load_result).await,
+        false,
+        usize::MAX,
+    );
+    let account_totals = calculate_account_totals(&load_result.directives, &filter);
+
+    let mut context = Context::new();
+    context.insert("current_page", &format!("{}_detail", kind));
+    context.insert("account_tree", &account_tree);
+    context.insert("kind", kind);
+    context.insert("sigil", if kind == "link" { "^" } else { "#" });
+    context.insert("name", name);
+    context.insert("transactions", &transactions);
+    context.insert("account_totals", &account_totals);
+
+    let rendered = match state.tera.render("label_detail.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the budget dashboard.
+pub async fn budget_page(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<BudgetRequest>,
+) -> impl IntoResponse {
+    let today = chrono::Local::now().date_naive();
+    let month = params
+        .period
+        .as_deref()
+        .and_then(|period| {
+            chrono::NaiveDate::parse_from_str(&format!("{period}-01"), "%Y-%m-%d").ok()
+        })
+        .or_else(|| today.with_day(1))
+        .unwrap_or(today);
+
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(&state, &load_result).await;
+    let budgets = calculate_budgets(&load_result.directives, month);
+
+    let mut context = Context::new();
+    context.insert("current_page", "budget");
+    context.insert("account_tree", &account_tree);
+    context.insert("period", &month.format("%Y-%m").to_string());
+    context.insert("period_label", &month.format("%B %Y").to_string());
+    context.insert(
+        "previous_period",
+        &(month - chrono::Months::new(1)).format("%Y-%m").to_string(),
+    );
+    context.insert(
+        "next_period",
+        &(month + chrono::Months::new(1)).format("%Y-%m").to_string(),
+    );
+    context.insert("budgets", &budgets);
+
+    let rendered = match state.tera.render("budget.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Handler for the cash flow sankey page.
+pub async fn sankey_page(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SankeyRequest>,
+) -> impl IntoResponse {
+    let (period, depth) = match sankey_params(&params) {
+        Ok(params) => params,
+        Err(e) => {
+            return Html(format!(
+                "<h1>Invalid period</h1><p>{}</p>",
+                tera::escape_html(&e)
+            ));
+        }
+    };
+
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return Html(format!("<h1>Error loading ledger</h1><p>{}</p>", e)),
+    };
+
+    let account_tree = account_tree(&state, &load_result).await;
+
+    let mut context = Context::new();
+    context.insert("current_page", "sankey");
+    context.insert("account_tree", &account_tree);
+    context.insert("period", &period.to_string());
+    context.insert("previous_period", &period.prev().to_string());
+    context.insert("next_period", &period.next().to_string());
+    context.insert("depth", &depth);
+
+    let rendered = match state.tera.render("sankey.html", &context) {
+        Ok(t) => t,
+        Err(e) => return Html(format!("<h1>Template Error</h1><p>{}</p>", e)),
+    };
+
+    Html(rendered)
+}
+
+/// Builds a file download response.
+fn download(content_type: &'static str, filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Parses an optional date query parameter, treating empty as absent.
+fn parse_optional_date(date: Option<&str>) -> Result<Option<chrono::NaiveDate>, ()> {
+    match date {
+        None | Some("") => Ok(None),
+        Some(date) if validate_date(date) => date.parse().map(Some).map_err(|_| ()),
+        Some(_) => Err(()),
+    }
+}
+
+/// Export endpoint for the register of an account as CSV.
+pub async fn export_register_csv(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RegisterExportRequest>,
+) -> Response {
+    let account = params.account.as_deref().filter(|a| !a.is_empty());
+    if let Some(account) = account {
+        if !validate_account(account) {
+            return (StatusCode::BAD_REQUEST, "Invalid account name").into_response();
+        }
+    }
+    let (Ok(from), Ok(to)) = (
+        parse_optional_date(params.from.as_deref()),
+        parse_optional_date(params.to.as_deref()),
+    ) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Invalid date format. Use YYYY-MM-DD.",
+        )
+            .into_response();
+    };
+
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let csv = register_csv(&load_result.directives, account, from, to);
+    download("text/csv; charset=utf-8", "register.csv", csv)
+}
+
+/// Export endpoint for a flattened beancount snapshot of the ledger.
+pub async fn export_ledger(State(state): State<Arc<AppState>>) -> Response {
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    download(
+        "text/plain; charset=utf-8",
+        "ledger.beancount",
+        ledger_snapshot(&load_result),
+    )
+}
+
+/// Export endpoint for the result of a BQL query as CSV.
+pub async fn export_query_csv(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<QueryExportRequest>,
+) -> Response {
+    let query = match rustledger_query::parse(&params.q) {
+        Ok(query) => query,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, format!("Invalid query: {}", e)).into_response();
+        }
+    };
+
+    let load_result = match load_ledger(&state).await {
+        Ok(res) => res,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let directives: Vec<_> = load_result
+        .directives
+        .into_iter()
+        .map(|d| d.value)
+        .collect();
+    let mut executor = rustledger_query::Executor::new(&directives);
+    match executor.execute(&query) {
+        Ok(result) => download(
+            "text/csv; charset=utf-8",
+            "query.csv",
+            query_result_csv(&result),
+        ),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("Query failed: {}", e)).into_response(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs::{self, File};
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    #[test]
+    fn test_normalize_amount() {
+        assert_eq!(normalize_amount("$1,234.56"), "1234.56 USD");
+        assert_eq!(normalize_amount(" (12,50 €) "), "-12.50 EUR");
+        assert_eq!(normalize_amount("-50.00 USD"), "-50.00 USD");
+        assert_eq!(normalize_amount("50"), "50");
+        assert_eq!(normalize_amount(""), "");
+        assert_eq!(normalize_amount("10 AAPL {150 USD}"), "10 AAPL {150 USD}");
+    }
+
     #[test]
     fn test_determine_target_file() {
         // Create a unique temp dir
@@ -1190,4 +2113,281 @@ mod tests {
         // Cleanup
         let _ = fs::remove_dir_all(dir);
     }
+
+    fn edit_request(postings: &[(&str, &str)]) -> EditTransactionRequest {
+        EditTransactionRequest {
+            date: "2024-01-15".to_string(),
+            narration: "Groceries".to_string(),
+            cleared: Some("on".to_string()),
+            postings: postings
+                .iter()
+                .map(|(account, amount)| PostingInput {
+                    account: account.to_string(),
+                    amount: amount.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_edit_form_postings() {
+        let fields = [
+            ("original_offset", "10"),
+            ("original_length", "42"),
+            ("original_source_path", "main.beancount"),
+            ("date", "2024-01-15"),
+            ("narration", "Split"),
+            ("account", "Expenses:Food"),
+            ("amount", "10 USD"),
+            ("account", ""),
+            ("amount", ""),
+            ("account", "Expenses:Home"),
+            ("amount", "5 USD"),
+            ("account", "Assets:Cash"),
+            ("amount", ""),
+        ];
+        let fields = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let request = parse_edit_form(fields).unwrap();
+        assert_eq!(request.original_offset, 10);
+        assert_eq!(request.original_length, 42);
+        let accounts: Vec<_> = request
+            .postings
+            .iter()
+            .map(|p| p.account.as_str())
+            .collect();
+        assert_eq!(accounts, ["Expenses:Food", "Expenses:Home", "Assets:Cash"]);
+        assert_eq!(request.postings[2].amount, "");
+
+        assert!(parse_edit_form(vec![("date".to_string(), "2024-01-15".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_check_edited_transaction_balanced() {
+        let request = edit_request(&[
+            ("Expenses:Food", "10.00 USD"),
+            ("Expenses:Home", "5.00 USD"),
+            ("Assets:Cash", ""),
+        ]);
+        let text = check_edited_transaction(&request).unwrap();
+        assert_eq!(
+            text,
+            "2024-01-15 * \"Groceries\"\n  Expenses:Food 10.00 USD\n  Expenses:Home 5.00 USD\n  Assets:Cash\n"
+        );
+    }
+
+    #[test]
+    fn test_check_edited_transaction_residual() {
+        let request = edit_request(&[("Expenses:Food", "10.00 USD"), ("Assets:Cash", "-9.00 USD")]);
+        let errors = check_edited_transaction(&request).unwrap_err();
+        assert_eq!(errors.form.len(), 1);
+        assert!(
+            errors.form[0].contains("does not balance"),
+            "{:?}",
+            errors.form
+        );
+    }
+
+    #[test]
+    fn test_check_edited_transaction_field_errors() {
+        let mut request = edit_request(&[("Food", "10.00 USD"), ("Assets:Cash", "ten USD")]);
+        request.date = "15/01/2024".to_string();
+        let errors = check_edited_transaction(&request).unwrap_err();
+        assert!(errors.date.is_some());
+        assert_eq!(
+            errors.postings[0].0.as_deref(),
+            Some("Invalid account name.")
+        );
+        assert!(errors.form.is_empty());
+
+        // Syntax errors are reported on the amount of the offending row
+        let request = edit_request(&[("Expenses:Food", "10.00 USD"), ("Assets:Cash", "ten USD")]);
+        let errors = check_edited_transaction(&request).unwrap_err();
+        assert!(errors.postings[0].1.is_none());
+        assert!(errors.postings[1].1.is_some(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_edit_form_renders_errors() {
+        let tera = build_tera(false).unwrap();
+        let rows = vec![PostingRow {
+            account: "Expenses:Food".to_string(),
+            amount: "10 USD".to_string(),
+            account_error: None,
+            amount_error: Some("bad amount".to_string()),
+        }];
+        let errors = TransactionFormErrors {
+            form: vec!["transaction does not balance".to_string()],
+            ..Default::default()
+        };
+
+        let mut context = Context::new();
+        context.insert("date", "2024-01-15");
+        context.insert("payee", "");
+        context.insert("narration", "Groceries");
+        context.insert("cleared", &true);
+        context.insert("postings", &rows);
+        context.insert("errors", &errors);
+        context.insert("original_offset", &0);
+        context.insert("original_length", &0);
+        context.insert("original_source_path", "main.beancount");
+        context.insert("accounts", &Vec::<String>::new());
+        context.insert("payees", &Vec::<String>::new());
+
+        let html = tera
+            .render("partials/transaction_edit_form.html", &context)
+            .unwrap();
+        assert!(html.contains("transaction does not balance"));
+        assert!(html.contains("bad amount"));
+        assert_eq!(html.matches("class=\"posting-row").count(), 1);
+
+        let row = tera
+            .render("partials/posting_row.html", &Context::new())
+            .unwrap();
+        assert!(row.contains("name=\"account\""));
+    }
+
+    #[test]
+    fn test_label_detail_renders() {
+        let tera = build_tera(false).unwrap();
+        let source = r#"2024-03-01 * "Train" #trip ^booking
+  Expenses:Travel  80 EUR
+  Assets:Cash
+2024-03-05 * "Refund" ^booking
+  Expenses:Travel  -20 EUR
+  Assets:Cash
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let sources = vec![PathBuf::from("main.beancount"); directives.len()];
+        let booking = |txn: &rustledger_core::Transaction| !txn.links.is_empty();
+
+        let mut context = Context::new();
+        context.insert("current_page", "link_detail");
+        context.insert("kind", "link");
+        context.insert("sigil", "^");
+        context.insert("name", "booking");
+        context.insert(
+            "transactions",
+            &extract_matching_transactions(&directives, &sources, booking),
+        );
+        context.insert(
+            "account_totals",
+            &calculate_account_totals(&directives, booking),
+        );
+
+        let html = tera.render("label_detail.html", &context).unwrap();
+        assert!(html.contains("2 transactions"));
+        assert!(html.contains("from 2024-03-01 to 2024-03-05"));
+        assert!(html.contains("60.00 EUR"));
+        assert!(html.contains("href=\"/tags/trip\""));
+        assert!(html.contains("href=\"/links/booking\""));
+
+        // Names are escaped
+        context.insert("name", "<b>x</b>");
+        context.insert(
+            "transactions",
+            &Vec::<crate::models::RecentTransaction>::new(),
+        );
+        let html = tera.render("label_detail.html", &context).unwrap();
+        assert!(html.contains("^&lt;b&gt;x&lt;&#x2F;b&gt;"));
+        assert!(html.contains("0 transactions"));
+    }
+
+    #[test]
+    fn test_budget_page_renders() {
+        let tera = build_tera(false).unwrap();
+        let source = r#"2024-01-01 custom "budget" Expenses:Food "monthly" 100 EUR
+2024-03-02 * "Market"
+  Expenses:Food  120 EUR
+  Assets:Cash
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let month = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        let mut context = Context::new();
+        context.insert("current_page", "budget");
+        context.insert("period", "2024-03");
+        context.insert("period_label", "March 2024");
+        context.insert("previous_period", "2024-02");
+        context.insert("next_period", "2024-04");
+        context.insert("budgets", &calculate_budgets(&directives, month));
+
+        let html = tera.render("budget.html", &context).unwrap();
+        assert!(html.contains("href=\"/budget?period=2024-02\""));
+        assert!(html.contains("120%"));
+        assert!(html.contains("-20.00 EUR"));
+        assert!(html.contains("style=\"width: 100%\""));
+
+        context.insert("budgets", &Vec::<crate::models::BudgetRow>::new());
+        let html = tera.render("budget.html", &context).unwrap();
+        assert!(html.contains("No budgets in effect for March 2024"));
+    }
+
+    #[test]
+    fn test_portfolio_page_renders() {
+        let tera = build_tera(false).unwrap();
+        let source = r#"2024-01-01 commodity VTI
+  asset_class: "equity"
+2024-01-01 * "Deposit"
+  Assets:Cash  2000 USD
+  Equity:Opening
+2024-01-02 * "Buy"
+  Assets:Broker  10 VTI {200 USD}
+  Assets:Cash  -2000 USD
+2024-02-01 price VTI 180 USD
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let prices = build_price_database(&directives);
+        let portfolio = calculate_portfolio(&directives, &prices, "USD");
+
+        let mut context = Context::new();
+        context.insert("current_page", "portfolio");
+        context.insert("portfolio", &portfolio);
+
+        let html = tera.render("portfolio.html", &context).unwrap();
+        assert!(html.contains("equity"));
+        assert!(html.contains("1800.00 USD"));
+        assert!(html.contains("-200.00 USD"));
+        assert!(html.contains("href=\"/commodities/VTI\""));
+
+        let empty = calculate_portfolio(&[], &prices, "USD");
+        context.insert("portfolio", &empty);
+        let html = tera.render("portfolio.html", &context).unwrap();
+        assert!(html.contains("No holdings found"));
+    }
+
+    #[test]
+    fn test_sankey_page_renders() {
+        let tera = build_tera(false).unwrap();
+        let params = SankeyRequest {
+            period: Some("2024-Q2".to_string()),
+            depth: Some(3),
+        };
+        let (period, depth) = sankey_params(&params).unwrap();
+
+        let mut context = Context::new();
+        context.insert("current_page", "sankey");
+        context.insert("period", &period.to_string());
+        context.insert("previous_period", &period.prev().to_string());
+        context.insert("next_period", &period.next().to_string());
+        context.insert("depth", &depth);
+
+        let html = tera.render("sankey.html", &context).unwrap();
+        assert!(html.contains("/api/charts/sankey?period=2024-Q2&depth=3"));
+        assert!(html.contains("href=\"/sankey?period=2024-Q1&depth=3\""));
+        assert!(html.contains("<option value=\"3\" selected>"));
+
+        let invalid = SankeyRequest {
+            period: Some("2024-13".to_string()),
+            depth: None,
+        };
+        assert_eq!(
+            sankey_params(&invalid).unwrap_err(),
+            "invalid period: 2024-13"
+        );
+    }
 }
@@ -0,0 +1,120 @@
+//! Live reload of open pages when the ledger changes on disk.
+//!
+//! A background task polls the modification times of the ledger and every
+//! file it includes. When one changes (e.g. after saving in an editor) the
+//! cached ledger is dropped and connected browsers are told through the
+//! `/api/events` server-sent event stream, so pages reload their data.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::Stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::handlers::{AppState, invalidate_cache};
+
+/// How often ledger files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the event sent when the ledger changed.
+const LEDGER_CHANGED: &str = "ledger-changed";
+
+/// Modification time of each watched file; `None` if it could not be read.
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+/// Watch the ledger files, notifying subscribers of changes until the
+/// process exits.
+pub async fn watch_ledger(state: Arc<AppState>) {
+    let mut ticker = tokio::time::interval(POLL_INTERVAL);
+    let mut seen = snapshot(&watched_files(&state).await).await;
+
+    loop {
+        ticker.tick().await;
+        let current = snapshot(&watched_files(&state).await).await;
+        if changed(&seen, &current) {
+            tracing::info!("Ledger changed on disk, reloading");
+            invalidate_cache(&state).await;
+            // No receivers just means no page is open
+            let _ = state.ledger_changes.send(());
+        }
+        seen = current;
+    }
+}
+
+/// Server-sent event stream telling pages to reload when the ledger changes.
+pub async fn ledger_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.ledger_changes.subscribe();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            // Missed notifications still mean the ledger changed
+            Ok(()) | Err(RecvError::Lagged(_)) => Some((
+                Ok(Event::default().event(LEDGER_CHANGED).data("")),
+                receiver,
+            )),
+            Err(RecvError::Closed) => None,
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// The main ledger file and, once loaded, every file it includes.
+async fn watched_files(state: &AppState) -> Vec<PathBuf> {
+    let mut files = vec![state.ledger_path.clone()];
+    if let Some(ledger) = state.cached_ledger.read().await.as_ref() {
+        files.extend(ledger.file_stats.iter().map(|stats| stats.path.clone()));
+    }
+    files
+}
+
+async fn snapshot(files: &[PathBuf]) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    for path in files {
+        let modified = tokio::fs::metadata(path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        snapshot.insert(path.clone(), modified);
+    }
+    snapshot
+}
+
+/// Whether a file watched in both snapshots changed.
+///
+/// Files only in `current` were just discovered as includes of a reloaded
+/// ledger and do not count as changes by themselves.
+fn changed(seen: &Snapshot, current: &Snapshot) -> bool {
+    current
+        .iter()
+        .any(|(path, modified)| seen.get(path).is_some_and(|before| before != modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(1);
+        let main = PathBuf::from("main.beancount");
+        let include = PathBuf::from("2024.beancount");
+
+        let seen = Snapshot::from([(main.clone(), Some(t0))]);
+        assert!(!changed(&seen, &seen));
+        // A newly watched include is not a change
+        let discovered = Snapshot::from([(main.clone(), Some(t0)), (include.clone(), Some(t0))]);
+        assert!(!changed(&seen, &discovered));
+
+        let edited = Snapshot::from([(main.clone(), Some(t0)), (include.clone(), Some(t1))]);
+        assert!(changed(&discovered, &edited));
+        let deleted = Snapshot::from([(main, Some(t0)), (include, None)]);
+        assert!(changed(&discovered, &deleted));
+    }
+}
@@ -1,5 +1,7 @@
 mod handlers;
+mod live_reload;
 mod models;
+mod undo;
 mod utils;
 
 use std::net::SocketAddr;
@@ -7,12 +9,13 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::{
-    Router,
+    Router, middleware,
     routing::{get, post},
 };
+use chrono::Datelike;
 use clap::Parser;
-use tera::Tera;
-use tokio::sync::{Mutex, RwLock};
+use rustledger_testutil::LedgerGenerator;
+use tokio::sync::{Mutex, RwLock, broadcast};
 use tower_http::services::ServeDir;
 
 use crate::handlers::AppState;
@@ -27,6 +30,38 @@ struct Args {
     /// Port to listen on
     #[arg(short, long, default_value_t = 3000)]
     port: u16,
+
+    /// Don't reload open pages when the ledger changes on disk
+    #[arg(long)]
+    no_live_reload: bool,
+
+    /// Serve a generated, read-only demo ledger instead of a file
+    #[arg(long)]
+    demo: bool,
+
+    /// Number of expense accounts in the demo ledger
+    #[arg(long, default_value_t = 15)]
+    demo_accounts: usize,
+
+    /// Number of years covered by the demo ledger, ending today
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..=100))]
+    demo_years: u32,
+
+    /// Number of purchases per month in the demo ledger
+    #[arg(long, default_value_t = 40)]
+    demo_transactions: usize,
+}
+
+/// Generate the demo ledger, covering whole years up to today.
+fn demo_ledger(args: &Args) -> String {
+    let today = chrono::Local::now().date_naive();
+    LedgerGenerator::new()
+        .with_accounts(args.demo_accounts)
+        .with_years(args.demo_years)
+        .with_transactions_per_month(args.demo_transactions)
+        .with_start_year(today.year() - args.demo_years as i32 + 1)
+        .with_until(today)
+        .generate()
 }
 
 #[tokio::main]
@@ -36,8 +71,15 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
 
+    let demo_source = args.demo.then(|| demo_ledger(&args));
+    let ledger_path = if args.demo {
+        PathBuf::from("demo.beancount")
+    } else {
+        args.ledger_file.clone()
+    };
+
     // Check if ledger file exists
-    if !args.ledger_file.exists() {
+    if demo_source.is_none() && !args.ledger_file.exists() {
         eprintln!(
             "Error: Ledger file '{}' not found.",
             args.ledger_file.display()
@@ -46,30 +88,41 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Initialize Tera templates
-    // Use CARGO_MANIFEST_DIR to find templates relative to the crate
-    let template_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*");
-    let mut tera = Tera::new(template_dir)
-        .or_else(|_| Tera::new("templates/**/*"))
-        .or_else(|_| Tera::new("crates/rustledger-web/templates/**/*"))?;
-
-    // Disable auto-escaping for HTML content if needed
-    tera.autoescape_on(vec![".html", ".sql"]);
+    let tera = handlers::build_tera(args.demo)?;
 
     // Shared state with caching and write synchronization
     let state = Arc::new(AppState {
-        ledger_path: args.ledger_file.clone(),
+        ledger_path,
+        demo_source,
         tera,
         cached_ledger: RwLock::new(None),
+        cached_account_tree: RwLock::new(None),
+        cached_issues: RwLock::new(None),
         write_lock: Mutex::new(()),
+        ledger_changes: broadcast::channel(16).0,
     });
 
+    if !args.no_live_reload && !args.demo {
+        tokio::spawn(live_reload::watch_ledger(state.clone()));
+    }
+
     // Build router
     let app = Router::new()
         .route("/", get(handlers::index))
         .route("/transactions", get(handlers::transactions_page))
         .route("/add", get(handlers::add_transaction_page))
+        .route("/m", get(handlers::mobile_entry_page))
+        .route("/m/sw.js", get(handlers::mobile_service_worker))
         .route("/accounts", get(handlers::accounts_page))
         .route("/accounts/*account", get(handlers::account_detail))
+        .route("/commodities", get(handlers::commodities_page))
+        .route("/commodities/:name", get(handlers::commodity_detail))
+        .route("/portfolio", get(handlers::portfolio_page))
+        .route("/tags", get(handlers::tags_page))
+        .route("/tags/:tag", get(handlers::tag_detail))
+        .route("/links/:link", get(handlers::link_detail))
+        .route("/budget", get(handlers::budget_page))
+        .route("/sankey", get(handlers::sankey_page))
         .route("/api/transactions", post(handlers::create_transaction))
         .route(
             "/api/transactions/toggle-status",
@@ -80,27 +133,42 @@ async fn main() -> anyhow::Result<()> {
             post(handlers::delete_transaction),
         )
         .route("/api/transactions/edit-form", get(handlers::get_edit_form))
+        .route(
+            "/api/transactions/posting-row",
+            get(handlers::get_posting_row),
+        )
         .route(
             "/api/transactions/update",
             post(handlers::update_transaction),
         )
+        .route("/api/undo", post(handlers::undo_last_change))
         .route("/api/accounts/open", post(handlers::open_account))
         .route("/api/accounts/close", post(handlers::close_account))
         .route("/api/payees", get(handlers::get_payees))
+        .route("/api/events", get(live_reload::ledger_events))
         .route("/api/stats/net-worth", get(handlers::get_net_worth_stats))
         .route(
             "/api/stats/income-expenses",
             get(handlers::get_income_expense_stats),
         )
         .route("/api/stats/cash-flow", get(handlers::get_cash_flow))
+        .route("/api/charts/sankey", get(handlers::get_sankey))
+        .route("/api/stats/load", get(handlers::get_load_stats))
         .route(
             "/api/stats/net-worth-history",
             get(handlers::get_net_worth_history),
         )
+        .route("/export/register.csv", get(handlers::export_register_csv))
+        .route("/export/ledger.beancount", get(handlers::export_ledger))
+        .route("/export/query.csv", get(handlers::export_query_csv))
         .nest_service(
             "/assets",
             ServeDir::new("assets").fallback(ServeDir::new("crates/rustledger-web/assets")),
         )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            handlers::read_only_guard,
+        ))
         .with_state(state);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], args.port));
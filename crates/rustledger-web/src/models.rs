@@ -32,7 +32,7 @@ pub struct ToggleStatusRequest {
 }
 
 /// A node in the account tree hierarchy.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct AccountNode {
     /// Short name of the account (leaf segment).
     pub name: String,
@@ -40,6 +40,12 @@ pub struct AccountNode {
     pub full_name: String,
     /// Child accounts.
     pub children: BTreeMap<String, AccountNode>,
+    /// Formatted non-zero balances, children included, one per currency.
+    pub balances: Vec<String>,
+    /// Whether this account and all its sub-accounts hold no balance.
+    pub is_zero: bool,
+    /// Whether this account and all its sub-accounts are closed.
+    pub is_closed: bool,
 }
 
 /// A single posting within a transaction.
@@ -64,12 +70,27 @@ pub struct RecentTransaction {
     pub narration: String,
     /// List of postings.
     pub postings: Vec<TransactionPosting>,
+    /// Tags, without the leading `#`.
+    pub tags: Vec<String>,
+    /// Links, without the leading `^`.
+    pub links: Vec<String>,
     /// Byte offset in source file.
     pub offset: usize,
     /// Length in bytes.
     pub length: usize,
     /// Source file path.
     pub source_path: String,
+    /// Validation problems affecting the transaction, such as an unbalanced
+    /// total, a posting to a closed account or a duplicate entry.
+    pub issues: Vec<String>,
+}
+
+/// Query parameters for transaction lists.
+#[derive(Deserialize, Debug, Default)]
+pub struct TransactionListRequest {
+    /// Only list transactions with validation problems.
+    #[serde(default)]
+    pub problems: bool,
 }
 
 /// Request payload for deleting a transaction.
@@ -84,7 +105,10 @@ pub struct DeleteTransactionRequest {
 }
 
 /// Request payload for updating an existing transaction.
-#[derive(Deserialize, Debug)]
+///
+/// Posting rows repeat the `account` and `amount` fields, so this is built
+/// from the raw form pairs rather than deserialized directly.
+#[derive(Debug, Default)]
 pub struct EditTransactionRequest {
     /// Original byte offset.
     pub original_offset: usize,
@@ -100,14 +124,59 @@ pub struct EditTransactionRequest {
     pub narration: String,
     /// New cleared status.
     pub cleared: Option<String>,
-    /// New first account.
-    pub account_1: String,
-    /// New first amount.
-    pub amount_1: String,
-    /// New second account.
-    pub account_2: Option<String>,
-    /// New second amount.
-    pub amount_2: Option<String>,
+    /// New postings, in form order.
+    pub postings: Vec<PostingInput>,
+}
+
+/// A posting row submitted from the transaction edit form.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PostingInput {
+    /// Account name.
+    pub account: String,
+    /// Amount text, possibly with cost and price annotations.
+    pub amount: String,
+}
+
+/// A posting row rendered in the transaction edit form.
+#[derive(Serialize, Debug)]
+pub struct PostingRow {
+    /// Account name.
+    pub account: String,
+    /// Amount text.
+    pub amount: String,
+    /// Error shown under the account field.
+    pub account_error: Option<String>,
+    /// Error shown under the amount field.
+    pub amount_error: Option<String>,
+}
+
+/// Errors found while validating an edited transaction.
+#[derive(Serialize, Debug, Default)]
+pub struct TransactionFormErrors {
+    /// Errors not tied to a single field, such as a non-zero residual.
+    pub form: Vec<String>,
+    /// Error for the date field.
+    pub date: Option<String>,
+    /// Error for the payee field.
+    pub payee: Option<String>,
+    /// Error for the narration field.
+    pub narration: Option<String>,
+    /// Account and amount errors, parallel to the submitted postings.
+    pub postings: Vec<(Option<String>, Option<String>)>,
+}
+
+impl TransactionFormErrors {
+    /// Whether no field has an error.
+    pub fn is_empty(&self) -> bool {
+        self.form.is_empty()
+            && self.date.is_none()
+            && self.payee.is_none()
+            && self.narration.is_none()
+            && self
+                .postings
+                .iter()
+                .all(|(account, amount)| account.is_none() && amount.is_none())
+    }
 }
 
 /// Query parameters for fetching the edit form.
@@ -160,6 +229,47 @@ pub struct CashFlowPoint {
     pub expenses: f64,
 }
 
+/// Query parameters for the cash flow sankey chart.
+#[derive(Deserialize, Debug)]
+pub struct SankeyRequest {
+    /// Period to chart (`2024`, `2024-Q2`, `2024-05`); the current year when
+    /// omitted.
+    pub period: Option<String>,
+    /// Number of account name components to aggregate at (default 2).
+    pub depth: Option<usize>,
+}
+
+/// A node of the cash flow sankey chart: an account at the chosen depth.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SankeyNode {
+    /// Account name, truncated to the chart depth.
+    pub name: String,
+}
+
+/// Money flowing from one sankey node to another.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SankeyLink {
+    /// Node the money leaves.
+    pub source: String,
+    /// Node the money goes to.
+    pub target: String,
+    /// Amount in the operating currency.
+    pub value: f64,
+}
+
+/// Cash flow between accounts over a period, in ECharts sankey layout.
+#[derive(Serialize, Debug)]
+pub struct SankeyData {
+    /// The charted period (e.g. "2024").
+    pub period: String,
+    /// Currency of the flows.
+    pub currency: String,
+    /// Accounts with money flowing in or out.
+    pub nodes: Vec<SankeyNode>,
+    /// Flows between accounts.
+    pub links: Vec<SankeyLink>,
+}
+
 /// Net worth history data point.
 #[derive(Serialize, Debug)]
 pub struct NetWorthPoint {
@@ -180,6 +290,154 @@ pub struct AccountBalance {
     pub balance_numeric: f64,
 }
 
+/// Summary of a commodity for the commodities list.
+#[derive(Serialize, Debug)]
+pub struct CommoditySummary {
+    /// Commodity code (e.g. AAPL).
+    pub name: String,
+    /// Full name, from the commodity's `name` metadata or ISO 4217.
+    pub full_name: Option<String>,
+    /// Whether a commodity directive declares it.
+    pub declared: bool,
+    /// Number of price points.
+    pub price_count: usize,
+    /// Formatted latest price, if any.
+    pub latest_price: Option<String>,
+}
+
+/// Summary of a tag for the tags list.
+#[derive(Serialize, Debug)]
+pub struct TagSummary {
+    /// Tag name, without the leading `#`.
+    pub name: String,
+    /// Number of tagged transactions.
+    pub transaction_count: usize,
+    /// Date of the first tagged transaction.
+    pub first_date: String,
+    /// Date of the last tagged transaction.
+    pub last_date: String,
+}
+
+/// Net amounts posted to an account by a set of transactions.
+#[derive(Serialize, Debug)]
+pub struct AccountTotal {
+    /// Account name.
+    pub account: String,
+    /// Formatted non-zero totals, one per currency.
+    pub totals: Vec<String>,
+}
+
+/// Budget vs actual spending of one account over a month.
+#[derive(Serialize, Debug)]
+pub struct BudgetRow {
+    /// Budgeted account.
+    pub account: String,
+    /// Budget currency.
+    pub currency: String,
+    /// Formatted budget for the month.
+    pub budget: String,
+    /// Formatted amount spent in the month, including sub-accounts.
+    pub actual: String,
+    /// Formatted budget left over (negative when over budget).
+    pub remaining: String,
+    /// Share of the budget spent, in percent.
+    pub percent: u32,
+    /// Whether spending exceeds the budget.
+    pub over_budget: bool,
+    /// Share of the budget spent in each of the trailing 12 months, oldest
+    /// first.
+    pub history: Vec<BudgetCell>,
+}
+
+/// One month of a budget heat map.
+#[derive(Serialize, Debug)]
+pub struct BudgetCell {
+    /// Month (YYYY-MM).
+    pub month: String,
+    /// Share of the budget spent, in percent; `None` without a budget.
+    pub percent: Option<u32>,
+    /// Formatted amount spent.
+    pub actual: String,
+}
+
+/// Query parameters for the budget page.
+#[derive(Deserialize, Debug)]
+pub struct BudgetRequest {
+    /// Month to show (YYYY-MM); the current month when omitted.
+    pub period: Option<String>,
+}
+
+/// A single price point for charting.
+#[derive(Serialize, Debug)]
+pub struct PricePoint {
+    /// Date string.
+    pub date: String,
+    /// Price value.
+    pub price: f64,
+}
+
+/// An account holding a commodity.
+#[derive(Serialize, Debug)]
+pub struct CommodityHolding {
+    /// Account name.
+    pub account: String,
+    /// Formatted units held.
+    pub units: String,
+    /// Formatted value at the latest price, if known.
+    pub value: Option<String>,
+}
+
+/// Current holdings grouped by asset class, valued in the operating currency.
+#[derive(Serialize, Debug)]
+pub struct Portfolio {
+    /// Currency the portfolio is valued in.
+    pub currency: String,
+    /// Asset classes, largest first.
+    pub classes: Vec<AssetClassAllocation>,
+    /// Formatted total market value.
+    pub market_value: String,
+    /// Formatted total cost basis.
+    pub cost_basis: String,
+    /// Formatted total unrealized gain.
+    pub unrealized_gain: String,
+}
+
+/// The holdings of one asset class.
+#[derive(Serialize, Debug)]
+pub struct AssetClassAllocation {
+    /// Asset class, from the `asset_class` metadata of commodity directives.
+    pub name: String,
+    /// Share of the portfolio's market value, in percent.
+    pub percent: f64,
+    /// Formatted market value.
+    pub market_value: String,
+    /// Formatted cost basis.
+    pub cost_basis: String,
+    /// Formatted unrealized gain.
+    pub unrealized_gain: String,
+    /// Commodities in the class, largest first.
+    pub holdings: Vec<PortfolioHolding>,
+}
+
+/// A commodity held across Assets and Liabilities accounts.
+#[derive(Serialize, Debug)]
+pub struct PortfolioHolding {
+    /// Commodity code.
+    pub commodity: String,
+    /// Formatted units held.
+    pub units: String,
+    /// Share of the portfolio's market value, in percent; `None` without a price.
+    pub percent: Option<f64>,
+    /// Formatted cost basis, if it could be valued.
+    pub cost_basis: Option<String>,
+    /// Formatted value at the latest price, if known.
+    pub market_value: Option<String>,
+    /// Formatted market value minus cost basis, if both are known.
+    pub unrealized_gain: Option<String>,
+    /// Whether the holding is worth less than it cost.
+    pub loss: bool,
+}
+
 /// Request payload for opening an account.
 #[derive(Deserialize, Debug)]
 pub struct OpenAccountRequest {
@@ -200,6 +458,24 @@ pub struct CloseAccountRequest {
     pub account: String,
 }
 
+/// Query parameters for the register CSV export.
+#[derive(Deserialize, Debug)]
+pub struct RegisterExportRequest {
+    /// Account or account prefix to export; all accounts when omitted.
+    pub account: Option<String>,
+    /// First date to include (YYYY-MM-DD).
+    pub from: Option<String>,
+    /// Last date to include (YYYY-MM-DD).
+    pub to: Option<String>,
+}
+
+/// Query parameters for the query CSV export.
+#[derive(Deserialize, Debug)]
+pub struct QueryExportRequest {
+    /// BQL query to run.
+    pub q: String,
+}
+
 /// Recurring transaction template (for future use).
 #[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,238 @@
+//! Undo journal for edits made through the web interface.
+//!
+//! Deleting or updating a transaction rewrites a byte range of a ledger
+//! file. Before each such edit, the replaced text and its location are
+//! appended to `.rustledger-web-undo` next to the main ledger file, one JSON
+//! object per line. Undoing pops the last entry and puts the original text
+//! back, provided the file still contains the replacement at that location.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rustledger_core::{Change, Directive, diff_ledgers};
+use serde::{Deserialize, Serialize};
+
+/// Name of the undo journal file, stored in the ledger directory.
+pub const UNDO_FILE_NAME: &str = ".rustledger-web-undo";
+
+/// A single recorded edit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UndoEntry {
+    /// What the edit did, e.g. "Delete transaction".
+    pub description: String,
+    /// The file that was edited.
+    pub path: PathBuf,
+    /// Byte offset of the edit.
+    pub offset: usize,
+    /// Text that was removed from the file.
+    pub removed: String,
+    /// Text that was inserted in its place.
+    pub inserted: String,
+    /// When the edit was made (RFC 3339).
+    pub timestamp: String,
+}
+
+impl UndoEntry {
+    /// Create an entry for an edit made now.
+    pub fn new(
+        description: impl Into<String>,
+        path: &Path,
+        offset: usize,
+        removed: impl Into<String>,
+        inserted: impl Into<String>,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            path: path.to_path_buf(),
+            offset,
+            removed: removed.into(),
+            inserted: inserted.into(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+        }
+    }
+
+    /// The directive changes that undoing this entry makes.
+    ///
+    /// Directives in the inserted text are replaced by those in the removed
+    /// text; text that does not parse is ignored.
+    pub fn undo_changes(&self) -> Vec<Change> {
+        let directives = |text: &str| -> Vec<Directive> {
+            rustledger_parser::parse(text)
+                .directives
+                .into_iter()
+                .map(|spanned| spanned.value)
+                .collect()
+        };
+        diff_ledgers(&directives(&self.inserted), &directives(&self.removed))
+    }
+}
+
+/// Location of the undo journal for a ledger.
+pub fn undo_log_path(ledger_path: &Path) -> PathBuf {
+    ledger_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(UNDO_FILE_NAME)
+}
+
+/// Append an entry to the undo journal.
+pub fn record(ledger_path: &Path, entry: &UndoEntry) -> anyhow::Result<()> {
+    let line = serde_json::to_string(entry)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(undo_log_path(ledger_path))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read all entries in the undo journal, oldest first.
+pub fn entries(ledger_path: &Path) -> anyhow::Result<Vec<UndoEntry>> {
+    let log_path = undo_log_path(ledger_path);
+    if !log_path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(log_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Revert the most recent edit and remove it from the journal.
+///
+/// Returns `Ok(None)` when there is nothing to undo. Fails without touching
+/// anything if the file no longer holds the inserted text at the recorded
+/// offset (e.g. it was edited by hand since).
+pub fn undo_last(ledger_path: &Path) -> anyhow::Result<Option<UndoEntry>> {
+    let mut log = entries(ledger_path)?;
+    let Some(entry) = log.pop() else {
+        return Ok(None);
+    };
+
+    let mut content = fs::read_to_string(&entry.path)?;
+    let end = entry.offset + entry.inserted.len();
+    if content.get(entry.offset..end) != Some(entry.inserted.as_str()) {
+        anyhow::bail!(
+            "{} has changed since the last edit; cannot undo",
+            entry.path.display()
+        );
+    }
+    content.replace_range(entry.offset..end, &entry.removed);
+    fs::write(&entry.path, content)?;
+
+    let mut remaining = String::new();
+    for e in &log {
+        remaining.push_str(&serde_json::to_string(e)?);
+        remaining.push('\n');
+    }
+    fs::write(undo_log_path(ledger_path), remaining)?;
+
+    Ok(Some(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_record_and_undo() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rustledger_test_undo_{}", timestamp));
+        fs::create_dir_all(&dir).unwrap();
+        let ledger = dir.join("main.beancount");
+
+        let original = "2024-01-01 * \"A\"\n  Assets:Cash 1 USD\n\n2024-01-02 * \"B\"\n";
+        fs::write(&ledger, original).unwrap();
+        assert_eq!(undo_last(&ledger).unwrap(), None);
+
+        // Delete the first transaction
+        let removed = &original[..37];
+        fs::write(&ledger, &original[37..]).unwrap();
+        record(
+            &ledger,
+            &UndoEntry::new("Delete transaction", &ledger, 0, removed, ""),
+        )
+        .unwrap();
+
+        // Then edit the second one
+        let edited = original[37..].replace("\"B\"", "\"C\"");
+        fs::write(&ledger, &edited).unwrap();
+        record(
+            &ledger,
+            &UndoEntry::new(
+                "Update transaction",
+                &ledger,
+                1,
+                "2024-01-02 * \"B\"\n",
+                "2024-01-02 * \"C\"\n",
+            ),
+        )
+        .unwrap();
+        assert_eq!(entries(&ledger).unwrap().len(), 2);
+
+        let undone = undo_last(&ledger).unwrap().unwrap();
+        assert_eq!(undone.description, "Update transaction");
+        assert_eq!(fs::read_to_string(&ledger).unwrap(), &original[37..]);
+
+        undo_last(&ledger).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&ledger).unwrap(), original);
+        assert!(entries(&ledger).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_file() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rustledger_test_undo_changed_{}", timestamp));
+        fs::create_dir_all(&dir).unwrap();
+        let ledger = dir.join("main.beancount");
+
+        fs::write(&ledger, "new text\n").unwrap();
+        record(
+            &ledger,
+            &UndoEntry::new("Update transaction", &ledger, 0, "old text\n", "new text\n"),
+        )
+        .unwrap();
+        fs::write(&ledger, "edited by hand\n").unwrap();
+
+        assert!(undo_last(&ledger).is_err());
+        assert_eq!(fs::read_to_string(&ledger).unwrap(), "edited by hand\n");
+        assert_eq!(entries(&ledger).unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_undo_changes() {
+        let ledger = Path::new("main.beancount");
+        let txn = "2024-01-02 * \"B\"\n  Assets:Cash 1 USD\n  Income:Job\n";
+
+        let delete = UndoEntry::new("Delete transaction", ledger, 0, txn, "");
+        assert!(matches!(delete.undo_changes()[..], [Change::Added(_)]));
+
+        let update = UndoEntry::new(
+            "Update transaction",
+            ledger,
+            0,
+            txn,
+            txn.replace("1 USD", "2 USD"),
+        );
+        assert!(matches!(
+            update.undo_changes()[..],
+            [Change::Modified { .. }]
+        ));
+
+        let noop = UndoEntry::new("Update transaction", ledger, 0, txn, txn);
+        assert_eq!(noop.undo_changes(), []);
+    }
+}
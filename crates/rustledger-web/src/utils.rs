@@ -1,13 +1,23 @@
 use crate::models::{
-    AccountBalance, AccountNode, CashFlowPoint, NetWorthPoint, RecentTransaction,
+    AccountBalance, AccountNode, AccountTotal, AssetClassAllocation, BudgetCell, BudgetRow,
+    CashFlowPoint, CommodityHolding, CommoditySummary, NetWorthPoint, Portfolio, PortfolioHolding,
+    PricePoint, RecentTransaction, SankeyData, SankeyLink, SankeyNode, TagSummary,
     TransactionPosting,
 };
-use chrono::Datelike;
+use chrono::{Datelike, Months, NaiveDate};
 use rust_decimal::Decimal;
-use rustledger_core::Directive;
+use rust_decimal::prelude::ToPrimitive;
+use rustledger_booking::interpolate;
+use rustledger_core::{
+    BookingMethod, CurrencyRegistry, Directive, FormatConfig, Inventory, MetaValue, Period,
+    Position, Transaction, cmp_directives, format_directive,
+};
+use rustledger_loader::LoadResult;
 use rustledger_parser::Spanned;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
-use std::path::PathBuf;
+use rustledger_query::{PriceDatabase, QueryResult, Value};
+use rustledger_validate::{ToleranceOptions, ValidationOptions, validate_parallel};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Extracts a sorted list of unique account names from directives.
 ///
@@ -67,6 +77,67 @@ pub fn extract_payees(directives: &[Spanned<Directive>]) -> Vec<String> {
     payees.into_iter().collect()
 }
 
+/// Returns the `limit` payees used most often, most frequent first.
+pub fn frequent_payees(directives: &[Spanned<Directive>], limit: usize) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for directive in directives {
+        if let Directive::Transaction(txn) = &directive.value {
+            if let Some(payee) = &txn.payee {
+                let payee = payee.to_string();
+                if !payee.is_empty() {
+                    *counts.entry(payee).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    most_frequent(counts, limit)
+}
+
+/// Returns the `limit` open accounts under one of `roots` (e.g. "Expenses")
+/// that are posted to most often, most frequent first.
+pub fn frequent_accounts(
+    directives: &[Spanned<Directive>],
+    roots: &[&str],
+    limit: usize,
+) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut closed = BTreeSet::new();
+
+    for directive in directives {
+        match &directive.value {
+            Directive::Transaction(txn) => {
+                for posting in &txn.postings {
+                    let account = posting.account.to_string();
+                    let root = account.split(':').next().unwrap_or_default();
+                    if roots.contains(&root) {
+                        *counts.entry(account).or_insert(0) += 1;
+                    }
+                }
+            }
+            Directive::Close(close) => {
+                closed.insert(close.account.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    counts.retain(|account, _| !closed.contains(account));
+    most_frequent(counts, limit)
+}
+
+/// Sorts names by descending count, then by name, keeping the first `limit`.
+fn most_frequent(counts: HashMap<String, usize>, limit: usize) -> Vec<String> {
+    let mut entries: Vec<_> = counts.into_iter().collect();
+    entries.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    entries
+        .into_iter()
+        .take(limit)
+        .map(|(name, _)| name)
+        .collect()
+}
+
 /// Calculates account balances from directives.
 /// Returns a map of account name -> (balance, currency).
 pub fn calculate_balances(directives: &[Spanned<Directive>]) -> HashMap<String, (Decimal, String)> {
@@ -251,6 +322,99 @@ pub fn calculate_cash_flow_history(
         .collect()
 }
 
+/// Calculates how money flowed between accounts during a period.
+///
+/// Accounts are aggregated to their first `depth` components, so depth 1
+/// charts Income → Assets → Expenses. Within a transaction, each posting
+/// money leaves feeds the postings it goes to in proportion to their size.
+/// Only amounts in the operating currency count. Flows in both directions
+/// between two nodes (a refund, a transfer back) are netted.
+pub fn calculate_sankey(
+    directives: &[Spanned<Directive>],
+    operating_currency: &str,
+    period: &Period,
+    depth: usize,
+) -> SankeyData {
+    let node = |account: &str| {
+        account
+            .split(':')
+            .take(depth.max(1))
+            .collect::<Vec<_>>()
+            .join(":")
+    };
+    let mut flows: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+
+    for directive in directives {
+        let Directive::Transaction(txn) = &directive.value else {
+            continue;
+        };
+        if !period.contains(txn.date) {
+            continue;
+        }
+        let interpolated = interpolate(txn).map(|result| result.transaction);
+        let txn = interpolated.as_ref().unwrap_or(txn);
+
+        let mut sources = Vec::new();
+        let mut targets = Vec::new();
+        for posting in &txn.postings {
+            let Some(amount) = posting.amount() else {
+                continue;
+            };
+            if amount.currency != operating_currency || amount.number.is_zero() {
+                continue;
+            }
+            if amount.number.is_sign_negative() {
+                sources.push((node(&posting.account), -amount.number));
+            } else {
+                targets.push((node(&posting.account), amount.number));
+            }
+        }
+
+        let total: Decimal = targets.iter().map(|(_, number)| number).sum();
+        if total.is_zero() {
+            continue;
+        }
+        for (source, out) in &sources {
+            for (target, into) in &targets {
+                if source != target {
+                    *flows.entry((source.clone(), target.clone())).or_default() +=
+                        out * into / total;
+                }
+            }
+        }
+    }
+
+    let mut links = Vec::new();
+    for ((source, target), value) in &flows {
+        let reverse = flows
+            .get(&(target.clone(), source.clone()))
+            .copied()
+            .unwrap_or_default();
+        let net = (value - reverse).round_dp(2);
+        if net > Decimal::ZERO {
+            links.push(SankeyLink {
+                source: source.clone(),
+                target: target.clone(),
+                value: net.to_f64().unwrap_or(0.0),
+            });
+        }
+    }
+    let names: BTreeSet<&String> = links
+        .iter()
+        .flat_map(|link| [&link.source, &link.target])
+        .collect();
+
+    SankeyData {
+        period: period.to_string(),
+        currency: operating_currency.to_string(),
+        nodes: names
+            .into_iter()
+            .map(|name| SankeyNode { name: name.clone() })
+            .collect(),
+        links,
+    }
+}
+
 /// Calculates net worth over time (monthly snapshots).
 pub fn calculate_net_worth_history(
     directives: &[Spanned<Directive>],
@@ -377,7 +541,7 @@ pub fn build_account_tree(accounts: &[String]) -> BTreeMap<String, AccountNode>
                 .or_insert_with(|| AccountNode {
                     name: part.to_string(),
                     full_name: full_name_acc.clone(),
-                    children: BTreeMap::new(),
+                    ..AccountNode::default()
                 })
                 .children;
         }
@@ -386,6 +550,89 @@ pub fn build_account_tree(accounts: &[String]) -> BTreeMap<String, AccountNode>
     root
 }
 
+/// Builds the account tree with balances rolled up from sub-accounts.
+///
+/// Each node shows the sum of its own postings and those of its
+/// descendants, and is flagged when its whole subtree is empty or closed so
+/// the sidebar can hide it. Postings without an amount are interpolated
+/// first, so auto-balanced accounts are included. Balances are shown with
+/// their currency's usual precision, or as summed when it is unknown.
+pub fn build_account_tree_with_balances(
+    directives: &[Spanned<Directive>],
+) -> BTreeMap<String, AccountNode> {
+    let mut own: HashMap<String, BTreeMap<String, Decimal>> = HashMap::new();
+    let mut closed = HashSet::new();
+
+    for directive in directives {
+        match &directive.value {
+            Directive::Transaction(txn) => {
+                let interpolated = interpolate(txn).map(|result| result.transaction);
+                let txn = interpolated.as_ref().unwrap_or(txn);
+                for posting in &txn.postings {
+                    if let Some(units) = &posting.units {
+                        if let (Some(number), Some(currency)) = (units.number(), units.currency()) {
+                            *own.entry(posting.account.to_string())
+                                .or_default()
+                                .entry(currency.to_string())
+                                .or_insert(Decimal::ZERO) += number;
+                        }
+                    }
+                }
+            }
+            Directive::Close(close) => {
+                closed.insert(close.account.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    let accounts = extract_accounts(directives);
+    let known: HashSet<&str> = accounts.iter().map(String::as_str).collect();
+    let registry = currency_registry(directives);
+    let mut tree = build_account_tree(&accounts);
+    for node in tree.values_mut() {
+        roll_up_balances(node, &own, &known, &closed, &registry);
+    }
+    tree
+}
+
+/// Fills in the balances and flags of `node` and its descendants, returning
+/// the node's rolled-up balance per currency.
+fn roll_up_balances(
+    node: &mut AccountNode,
+    own: &HashMap<String, BTreeMap<String, Decimal>>,
+    known: &HashSet<&str>,
+    closed: &HashSet<String>,
+    registry: &CurrencyRegistry,
+) -> BTreeMap<String, Decimal> {
+    let mut totals = own.get(&node.full_name).cloned().unwrap_or_default();
+    let mut subtree_zero = totals.values().all(Decimal::is_zero);
+    // Intermediate nodes that are not accounts themselves count as closed
+    // once every child is.
+    let mut subtree_closed =
+        closed.contains(&node.full_name) || !known.contains(node.full_name.as_str());
+
+    for child in node.children.values_mut() {
+        for (currency, number) in roll_up_balances(child, own, known, closed, registry) {
+            *totals.entry(currency).or_insert(Decimal::ZERO) += number;
+        }
+        subtree_zero &= child.is_zero;
+        subtree_closed &= child.is_closed;
+    }
+
+    node.balances = totals
+        .iter()
+        .filter(|(_, number)| !number.is_zero())
+        .map(|(currency, number)| match registry.precision(currency) {
+            Some(precision) => format!("{:.*} {}", precision as usize, number, currency),
+            None => format!("{} {}", number, currency),
+        })
+        .collect();
+    node.is_zero = subtree_zero;
+    node.is_closed = subtree_closed;
+    totals
+}
+
 /// Extracts transactions for a specific account or account prefix.
 ///
 /// If `account_filter` is "Assets:Bank", it will match transactions
@@ -411,35 +658,7 @@ pub fn extract_account_transactions(
                     return None;
                 }
 
-                let postings = txn
-                    .postings
-                    .iter()
-                    .map(|p| {
-                        let amount_str = if let Some(units) = &p.units {
-                            let number = units.number().map(|d| d.to_string()).unwrap_or_default();
-                            let currency = units.currency().unwrap_or("");
-                            format!("{} {}", number, currency)
-                        } else {
-                            String::new()
-                        };
-
-                        TransactionPosting {
-                            account: p.account.to_string(),
-                            amount: amount_str,
-                        }
-                    })
-                    .collect();
-
-                Some(RecentTransaction {
-                    date: txn.date.to_string(),
-                    flag: txn.flag.to_string(),
-                    payee: txn.payee.clone().unwrap_or_default().to_string(),
-                    narration: txn.narration.to_string(),
-                    postings,
-                    offset: d.span.start,
-                    length: d.span.len(),
-                    source_path: source.to_string_lossy().to_string(),
-                })
+                Some(recent_transaction(d, txn, source))
             } else {
                 None
             }
@@ -485,6 +704,391 @@ pub fn get_sub_accounts(accounts: &[String], prefix: &str) -> Vec<String> {
         .collect()
 }
 
+/// Builds the shared price database from the ledger's price directives.
+pub fn build_price_database(directives: &[Spanned<Directive>]) -> PriceDatabase {
+    let directives: Vec<Directive> = directives.iter().map(|d| d.value.clone()).collect();
+    PriceDatabase::from_directives(&directives)
+}
+
+/// Extracts a sorted list of commodities that are declared, used in
+/// postings or balances, or priced.
+pub fn extract_commodities(directives: &[Spanned<Directive>]) -> Vec<String> {
+    let mut commodities = BTreeSet::new();
+
+    for directive in directives {
+        match &directive.value {
+            Directive::Commodity(comm) => {
+                commodities.insert(comm.currency.to_string());
+            }
+            Directive::Transaction(txn) => {
+                for posting in &txn.postings {
+                    if let Some(currency) = posting.units.as_ref().and_then(|u| u.currency()) {
+                        commodities.insert(currency.to_string());
+                    }
+                }
+            }
+            Directive::Balance(bal) => {
+                commodities.insert(bal.amount.currency.to_string());
+            }
+            Directive::Price(price) => {
+                commodities.insert(price.currency.to_string());
+                commodities.insert(price.amount.currency.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    commodities.into_iter().collect()
+}
+
+/// Picks the currency to quote a commodity's prices in.
+///
+/// Prefers the operating currency, falling back to the currency of the
+/// most recent price.
+pub fn commodity_quote_currency(
+    prices: &PriceDatabase,
+    commodity: &str,
+    operating_currency: &str,
+) -> Option<String> {
+    let entries = prices.prices_for(commodity);
+    if entries.iter().any(|e| e.currency == operating_currency) {
+        Some(operating_currency.to_string())
+    } else {
+        entries.last().map(|e| e.currency.to_string())
+    }
+}
+
+/// Summarizes every commodity in the ledger for the commodities list.
+pub fn summarize_commodities(
+    directives: &[Spanned<Directive>],
+    prices: &PriceDatabase,
+    operating_currency: &str,
+) -> Vec<CommoditySummary> {
+    let declared: BTreeSet<String> = directives
+        .iter()
+        .filter_map(|d| match &d.value {
+            Directive::Commodity(comm) => Some(comm.currency.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let registry = currency_registry(directives);
+
+    extract_commodities(directives)
+        .into_iter()
+        .map(|name| {
+            let latest_price = commodity_quote_currency(prices, &name, operating_currency)
+                .and_then(|quote| {
+                    prices
+                        .get_latest_price(&name, &quote)
+                        .map(|price| format!("{} {}", price, quote))
+                });
+            CommoditySummary {
+                full_name: registry.name(&name).map(str::to_string),
+                declared: declared.contains(&name),
+                price_count: prices.prices_for(&name).len(),
+                latest_price,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Known currency names and precisions: ISO 4217 plus the ledger's own
+/// commodity declarations.
+pub fn currency_registry(directives: &[Spanned<Directive>]) -> CurrencyRegistry {
+    let mut registry = CurrencyRegistry::iso4217();
+    registry.register_commodities(directives.iter().map(|d| &d.value));
+    registry
+}
+
+/// Returns the commodity directive's date and sorted metadata, if declared.
+pub fn commodity_declaration(
+    directives: &[Spanned<Directive>],
+    commodity: &str,
+) -> Option<(String, Vec<(String, String)>)> {
+    directives.iter().find_map(|d| match &d.value {
+        Directive::Commodity(comm) if comm.currency == commodity => {
+            let meta: BTreeMap<String, String> = comm
+                .meta
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_string()))
+                .collect();
+            Some((comm.date.to_string(), meta.into_iter().collect()))
+        }
+        _ => None,
+    })
+}
+
+/// Collects the commodity's price points in the given quote currency.
+pub fn commodity_price_history(
+    prices: &PriceDatabase,
+    commodity: &str,
+    quote_currency: &str,
+) -> Vec<PricePoint> {
+    prices
+        .prices_for(commodity)
+        .iter()
+        .filter(|e| e.currency == quote_currency)
+        .map(|e| PricePoint {
+            date: e.date.to_string(),
+            price: e.price.to_string().parse().unwrap_or(0.0),
+        })
+        .collect()
+}
+
+/// Calculates the units of a commodity held per account.
+///
+/// Returns the accounts with a non-zero balance, sorted by name, along
+/// with the total units held across them.
+pub fn calculate_commodity_holdings(
+    directives: &[Spanned<Directive>],
+    commodity: &str,
+) -> (Vec<(String, Decimal)>, Decimal) {
+    let mut holdings: BTreeMap<String, Decimal> = BTreeMap::new();
+
+    for directive in directives {
+        if let Directive::Transaction(txn) = &directive.value {
+            for posting in &txn.postings {
+                if let Some(units) = &posting.units {
+                    if let (Some(number), Some(currency)) = (units.number(), units.currency()) {
+                        if currency == commodity {
+                            *holdings.entry(posting.account.to_string()).or_default() += number;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    holdings.retain(|_, units| !units.is_zero());
+    let total = holdings
+        .iter()
+        .filter(|(account, _)| {
+            account.starts_with("Assets:") || account.starts_with("Liabilities:")
+        })
+        .map(|(_, units)| *units)
+        .sum();
+
+    (holdings.into_iter().collect(), total)
+}
+
+/// Formats commodity holdings, valuing each at the latest price.
+pub fn format_commodity_holdings(
+    holdings: &[(String, Decimal)],
+    commodity: &str,
+    latest_price: Option<(Decimal, &str)>,
+) -> Vec<CommodityHolding> {
+    holdings
+        .iter()
+        .map(|(account, units)| CommodityHolding {
+            account: account.clone(),
+            units: format!("{} {}", units, commodity),
+            value: latest_price.map(|(price, quote)| format!("{:.2} {}", units * price, quote)),
+        })
+        .collect()
+}
+
+/// Commodity metadata naming the asset class shown on the portfolio page.
+const ASSET_CLASS_KEY: &str = "asset_class";
+
+/// Books the lots held in Assets and Liabilities accounts.
+///
+/// Each account is booked with the method of its `open` directive. A
+/// reduction the method rejects falls back to FIFO, and then to plain
+/// units, so the units held always add up.
+fn book_holdings(directives: &[Spanned<Directive>]) -> Inventory {
+    let mut sorted: Vec<&Directive> = directives.iter().map(|d| &d.value).collect();
+    sorted.sort_by(|a, b| cmp_directives(a, b));
+
+    let mut methods: HashMap<String, BookingMethod> = HashMap::new();
+    let mut accounts: BTreeMap<String, Inventory> = BTreeMap::new();
+    for directive in sorted {
+        match directive {
+            Directive::Open(open) => {
+                let method = open.booking.as_deref().and_then(|b| b.parse().ok());
+                if let Some(method) = method {
+                    methods.insert(open.account.to_string(), method);
+                }
+            }
+            Directive::Transaction(txn) => {
+                let interpolated = interpolate(txn).map(|result| result.transaction);
+                let txn = interpolated.as_ref().unwrap_or(txn);
+                for posting in &txn.postings {
+                    let Some(units) = posting.amount() else {
+                        continue;
+                    };
+                    let account = posting.account.as_str();
+                    if !account.starts_with("Assets:") && !account.starts_with("Liabilities:") {
+                        continue;
+                    }
+                    let inventory = accounts.entry(account.to_string()).or_default();
+                    let Some(spec) = &posting.cost else {
+                        inventory.add(Position::simple(units.clone()));
+                        continue;
+                    };
+                    let method = methods.get(account).copied().unwrap_or_default();
+                    let booked = inventory
+                        .book_at_cost(units, spec, method, txn.date)
+                        .or_else(|_| {
+                            inventory.book_at_cost(units, spec, BookingMethod::Fifo, txn.date)
+                        });
+                    if booked.is_err() {
+                        inventory.add(Position::simple(units.clone()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut holdings = Inventory::new();
+    for inventory in accounts.values() {
+        holdings.merge(inventory);
+    }
+    holdings
+}
+
+/// A holding valued in the portfolio currency, before formatting.
+struct ValuedHolding {
+    commodity: String,
+    units: Decimal,
+    cost_basis: Option<Decimal>,
+    market_value: Option<Decimal>,
+}
+
+impl ValuedHolding {
+    fn unrealized_gain(&self) -> Option<Decimal> {
+        Some(self.market_value? - self.cost_basis?)
+    }
+}
+
+/// Sums the values that could be converted, skipping the rest.
+fn sum_known(values: impl Iterator<Item = Option<Decimal>>) -> Decimal {
+    values.flatten().sum()
+}
+
+/// Values the current holdings in `currency` and groups them by asset class.
+///
+/// Holdings are valued at their latest price. Units held without a cost
+/// count at their own value, so cash shows no gain. The asset class is the
+/// `asset_class` metadata of the commodity directive, defaulting to "Cash"
+/// for `currency` itself and "Unclassified" for everything else.
+pub fn calculate_portfolio(
+    directives: &[Spanned<Directive>],
+    prices: &PriceDatabase,
+    currency: &str,
+) -> Portfolio {
+    let value_in = |number: Decimal, commodity: &str| {
+        if commodity == currency {
+            Some(number)
+        } else {
+            prices
+                .get_latest_price(commodity, currency)
+                .map(|price| number * price)
+        }
+    };
+
+    // Units and cost basis per commodity
+    let mut totals: BTreeMap<String, (Decimal, Option<Decimal>)> = BTreeMap::new();
+    for position in book_holdings(directives).positions() {
+        let cost = match &position.cost {
+            Some(cost) => value_in(position.units.number * cost.number, cost.currency.as_str()),
+            None => value_in(position.units.number, position.units.currency.as_str()),
+        };
+        let (units, basis) = totals
+            .entry(position.units.currency.to_string())
+            .or_insert((Decimal::ZERO, Some(Decimal::ZERO)));
+        *units += position.units.number;
+        *basis = basis.zip(cost).map(|(a, b)| a + b);
+    }
+
+    let asset_classes: HashMap<String, String> = directives
+        .iter()
+        .filter_map(|d| match &d.value {
+            Directive::Commodity(comm) => match comm.meta.get(ASSET_CLASS_KEY) {
+                Some(MetaValue::String(class)) => Some((comm.currency.to_string(), class.clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    let mut classes: BTreeMap<String, Vec<ValuedHolding>> = BTreeMap::new();
+    for (commodity, (units, cost_basis)) in totals {
+        if units.is_zero() {
+            continue;
+        }
+        let class = asset_classes.get(&commodity).cloned().unwrap_or_else(|| {
+            if commodity == currency {
+                "Cash".to_string()
+            } else {
+                "Unclassified".to_string()
+            }
+        });
+        classes.entry(class).or_default().push(ValuedHolding {
+            market_value: value_in(units, commodity.as_str()),
+            commodity,
+            units,
+            cost_basis,
+        });
+    }
+
+    let all = || classes.values().flatten();
+    let total = sum_known(all().map(|h| h.market_value));
+    let percent = |value: Decimal| {
+        if total.is_zero() {
+            0.0
+        } else {
+            (value / total * Decimal::ONE_HUNDRED)
+                .round_dp(1)
+                .to_f64()
+                .unwrap_or(0.0)
+        }
+    };
+    let money = |value: Decimal| format!("{value:.2} {currency}");
+
+    let mut allocations: Vec<(Decimal, AssetClassAllocation)> = classes
+        .iter()
+        .map(|(name, holdings)| {
+            let market_value = sum_known(holdings.iter().map(|h| h.market_value));
+            let mut rows: Vec<_> = holdings.iter().collect();
+            rows.sort_by(|a, b| b.market_value.cmp(&a.market_value));
+            let allocation = AssetClassAllocation {
+                name: name.clone(),
+                percent: percent(market_value),
+                market_value: money(market_value),
+                cost_basis: money(sum_known(holdings.iter().map(|h| h.cost_basis))),
+                unrealized_gain: money(sum_known(
+                    holdings.iter().map(ValuedHolding::unrealized_gain),
+                )),
+                holdings: rows
+                    .into_iter()
+                    .map(|h| PortfolioHolding {
+                        commodity: h.commodity.clone(),
+                        units: format!("{} {}", h.units, h.commodity),
+                        percent: h.market_value.map(percent),
+                        cost_basis: h.cost_basis.map(money),
+                        market_value: h.market_value.map(money),
+                        unrealized_gain: h.unrealized_gain().map(money),
+                        loss: h.unrealized_gain().is_some_and(|gain| gain < Decimal::ZERO),
+                    })
+                    .collect(),
+            };
+            (market_value, allocation)
+        })
+        .collect();
+    allocations.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Portfolio {
+        currency: currency.to_string(),
+        classes: allocations.into_iter().map(|(_, class)| class).collect(),
+        market_value: money(total),
+        cost_basis: money(sum_known(all().map(|h| h.cost_basis))),
+        unrealized_gain: money(sum_known(all().map(ValuedHolding::unrealized_gain))),
+    }
+}
+
 /// Extracts the most recent transactions from the directive list.
 ///
 /// Returns a list of `RecentTransaction` structs, limited by `limit`.
@@ -499,35 +1103,7 @@ pub fn extract_recent_transactions(
         .zip(sources.iter())
         .filter_map(|(d, source)| {
             if let Directive::Transaction(txn) = &d.value {
-                let postings = txn
-                    .postings
-                    .iter()
-                    .map(|p| {
-                        let amount_str = if let Some(units) = &p.units {
-                            let number = units.number().map(|d| d.to_string()).unwrap_or_default();
-                            let currency = units.currency().unwrap_or("");
-                            format!("{} {}", number, currency)
-                        } else {
-                            String::new()
-                        };
-
-                        TransactionPosting {
-                            account: p.account.to_string(),
-                            amount: amount_str,
-                        }
-                    })
-                    .collect();
-
-                Some(RecentTransaction {
-                    date: txn.date.to_string(),
-                    flag: txn.flag.to_string(),
-                    payee: txn.payee.clone().unwrap_or_default().to_string(),
-                    narration: txn.narration.to_string(),
-                    postings,
-                    offset: d.span.start,
-                    length: d.span.len(),
-                    source_path: source.to_string_lossy().to_string(),
-                })
+                Some(recent_transaction(d, txn, source))
             } else {
                 None
             }
@@ -537,6 +1113,541 @@ pub fn extract_recent_transactions(
         .collect()
 }
 
+/// Builds the display form of a transaction, with the location used for
+/// editing it.
+fn recent_transaction(
+    directive: &Spanned<Directive>,
+    txn: &Transaction,
+    source: &Path,
+) -> RecentTransaction {
+    let postings = txn
+        .postings
+        .iter()
+        .map(|p| {
+            let amount_str = if let Some(units) = &p.units {
+                let number = units.number().map(|d| d.to_string()).unwrap_or_default();
+                let currency = units.currency().unwrap_or("");
+                format!("{} {}", number, currency)
+            } else {
+                String::new()
+            };
+
+            TransactionPosting {
+                account: p.account.to_string(),
+                amount: amount_str,
+            }
+        })
+        .collect();
+
+    RecentTransaction {
+        date: txn.date.to_string(),
+        flag: txn.flag.to_string(),
+        payee: txn.payee.clone().unwrap_or_default().to_string(),
+        narration: txn.narration.to_string(),
+        postings,
+        tags: txn.tags.iter().map(|tag| tag.to_string()).collect(),
+        links: txn.links.iter().map(|link| link.to_string()).collect(),
+        offset: directive.span.start,
+        length: directive.span.len(),
+        source_path: source.to_string_lossy().to_string(),
+        issues: Vec::new(),
+    }
+}
+
+/// Validation problems of transactions, keyed by source file and byte offset
+/// as in [`RecentTransaction`].
+pub type TransactionIssues = HashMap<(String, usize), Vec<String>>;
+
+/// Validates the ledger and collects the problems of each transaction.
+///
+/// Validation errors are joined to the transaction being checked when they
+/// were found, through its span. Transactions identical to an earlier one
+/// (metadata aside, as beancount's `noduplicates` plugin compares them) are
+/// flagged as duplicates.
+pub fn transaction_issues(load_result: &LoadResult) -> TransactionIssues {
+    let location = |index: usize| {
+        let directive = &load_result.directives[index];
+        let source = load_result.directive_sources.get(index)?;
+        Some((source.to_string_lossy().to_string(), directive.span.start))
+    };
+    let options = &load_result.options;
+    let validation_options = ValidationOptions {
+        tolerance: ToleranceOptions {
+            defaults: options.inferred_tolerance_default.clone(),
+            multiplier: options.inferred_tolerance_multiplier,
+            infer_from_cost: options.infer_tolerance_from_cost,
+        },
+        ..ValidationOptions::default()
+    };
+
+    let directives: Vec<Directive> = load_result
+        .directives
+        .iter()
+        .map(|d| d.value.clone())
+        .collect();
+    let mut issues = TransactionIssues::new();
+    for error in validate_parallel(&directives, validation_options) {
+        let Some(index) = error.directive else {
+            continue;
+        };
+        if !matches!(directives[index], Directive::Transaction(_)) {
+            continue;
+        }
+        if let Some(key) = location(index) {
+            issues.entry(key).or_default().push(error.to_string());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for (index, directive) in directives.iter().enumerate() {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        if !seen.insert(txn.to_string()) {
+            if let Some(key) = location(index) {
+                issues
+                    .entry(key)
+                    .or_default()
+                    .push("Duplicate of an earlier transaction".to_string());
+            }
+        }
+    }
+
+    issues
+}
+
+/// Attaches `issues` to `transactions`, dropping the transactions without
+/// any when `only_problems` is set, and keeps at most `limit`.
+pub fn annotate_transactions(
+    transactions: Vec<RecentTransaction>,
+    issues: &TransactionIssues,
+    only_problems: bool,
+    limit: usize,
+) -> Vec<RecentTransaction> {
+    transactions
+        .into_iter()
+        .map(|mut txn| {
+            if let Some(found) = issues.get(&(txn.source_path.clone(), txn.offset)) {
+                txn.issues = found.clone();
+            }
+            txn
+        })
+        .filter(|txn| !only_problems || !txn.issues.is_empty())
+        .take(limit)
+        .collect()
+}
+
+/// Summarizes every tag used on transactions, sorted by name.
+pub fn summarize_tags(directives: &[Spanned<Directive>]) -> Vec<TagSummary> {
+    let mut tags: BTreeMap<String, (usize, NaiveDate, NaiveDate)> = BTreeMap::new();
+
+    for directive in directives {
+        if let Directive::Transaction(txn) = &directive.value {
+            for tag in &txn.tags {
+                let (count, first, last) = tags
+                    .entry(tag.to_string())
+                    .or_insert((0, txn.date, txn.date));
+                *count += 1;
+                *first = (*first).min(txn.date);
+                *last = (*last).max(txn.date);
+            }
+        }
+    }
+
+    tags.into_iter()
+        .map(|(name, (transaction_count, first, last))| TagSummary {
+            name,
+            transaction_count,
+            first_date: first.to_string(),
+            last_date: last.to_string(),
+        })
+        .collect()
+}
+
+/// Extracts the transactions accepted by `filter`, newest first.
+pub fn extract_matching_transactions(
+    directives: &[Spanned<Directive>],
+    sources: &[PathBuf],
+    filter: impl Fn(&Transaction) -> bool,
+) -> Vec<RecentTransaction> {
+    directives
+        .iter()
+        .zip(sources.iter())
+        .filter_map(|(d, source)| match &d.value {
+            Directive::Transaction(txn) if filter(txn) => Some(recent_transaction(d, txn, source)),
+            _ => None,
+        })
+        .rev()
+        .collect()
+}
+
+/// Totals the postings of the transactions accepted by `filter` per account,
+/// sorted by account name.
+///
+/// Missing posting amounts are interpolated first.
+pub fn calculate_account_totals(
+    directives: &[Spanned<Directive>],
+    filter: impl Fn(&Transaction) -> bool,
+) -> Vec<AccountTotal> {
+    let mut totals: BTreeMap<String, BTreeMap<String, Decimal>> = BTreeMap::new();
+
+    for directive in directives {
+        let Directive::Transaction(txn) = &directive.value else {
+            continue;
+        };
+        if !filter(txn) {
+            continue;
+        }
+        let interpolated = interpolate(txn).map(|result| result.transaction);
+        let txn = interpolated.as_ref().unwrap_or(txn);
+        for posting in &txn.postings {
+            if let Some(units) = &posting.units {
+                if let (Some(number), Some(currency)) = (units.number(), units.currency()) {
+                    *totals
+                        .entry(posting.account.to_string())
+                        .or_default()
+                        .entry(currency.to_string())
+                        .or_insert(Decimal::ZERO) += number;
+                }
+            }
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(account, by_currency)| AccountTotal {
+            account,
+            totals: by_currency
+                .iter()
+                .filter(|(_, number)| !number.is_zero())
+                .map(|(currency, number)| format!("{:.2} {}", number, currency))
+                .collect(),
+        })
+        .collect()
+}
+
+/// A budget declared with `custom "budget" Expenses:Food "monthly" 400 USD`.
+struct Budget {
+    date: NaiveDate,
+    account: String,
+    period: String,
+    amount: Decimal,
+    currency: String,
+}
+
+impl Budget {
+    /// The budget for the month starting at `month`, or `None` for an
+    /// unknown period.
+    fn for_month(&self, month: NaiveDate) -> Option<Decimal> {
+        let days = Decimal::from((month + Months::new(1) - month).num_days());
+        match self.period.as_str() {
+            "daily" => Some(self.amount * days),
+            "weekly" => Some(self.amount * days / Decimal::from(7)),
+            "monthly" => Some(self.amount),
+            "quarterly" => Some(self.amount / Decimal::from(3)),
+            "yearly" => Some(self.amount / Decimal::from(12)),
+            _ => None,
+        }
+    }
+}
+
+/// Extracts the budget custom directives, oldest first.
+fn extract_budgets(directives: &[Spanned<Directive>]) -> Vec<Budget> {
+    let mut budgets: Vec<Budget> = directives
+        .iter()
+        .filter_map(|d| match &d.value {
+            Directive::Custom(custom) if custom.custom_type == "budget" => {
+                let (account, period, amount, currency) = match &custom.values[..] {
+                    [
+                        MetaValue::Account(account),
+                        MetaValue::String(period),
+                        MetaValue::Amount(amount),
+                        ..,
+                    ] => (account, period, amount.number, amount.currency.to_string()),
+                    [
+                        MetaValue::Account(account),
+                        MetaValue::String(period),
+                        MetaValue::Number(number),
+                        MetaValue::Currency(currency),
+                        ..,
+                    ] => (account, period, *number, currency.clone()),
+                    _ => return None,
+                };
+                Some(Budget {
+                    date: custom.date,
+                    account: account.clone(),
+                    period: period.to_lowercase(),
+                    amount,
+                    currency,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    budgets.sort_by_key(|b| b.date);
+    budgets
+}
+
+/// Compares budgets against actual spending for the month starting at
+/// `month`, with a heat map of the trailing 12 months.
+///
+/// The budget in effect for a month is the latest one declared for the
+/// account on or before the month's last day. Actual spending includes
+/// sub-accounts; missing posting amounts are interpolated first.
+pub fn calculate_budgets(directives: &[Spanned<Directive>], month: NaiveDate) -> Vec<BudgetRow> {
+    let budgets = extract_budgets(directives);
+    let months: Vec<NaiveDate> = (0..12).rev().map(|n| month - Months::new(n)).collect();
+    let first = months[0];
+    let end = month + Months::new(1);
+
+    // Spending per month, account and currency over the heat map window
+    let mut spent: HashMap<(NaiveDate, String, String), Decimal> = HashMap::new();
+    for directive in directives {
+        let Directive::Transaction(txn) = &directive.value else {
+            continue;
+        };
+        if txn.date < first || txn.date >= end {
+            continue;
+        }
+        let txn_month = txn.date.with_day(1).unwrap_or(txn.date);
+        let interpolated = interpolate(txn).map(|result| result.transaction);
+        let txn = interpolated.as_ref().unwrap_or(txn);
+        for posting in &txn.postings {
+            if let Some(units) = &posting.units {
+                if let (Some(number), Some(currency)) = (units.number(), units.currency()) {
+                    *spent
+                        .entry((txn_month, posting.account.to_string(), currency.to_string()))
+                        .or_insert(Decimal::ZERO) += number;
+                }
+            }
+        }
+    }
+
+    let budget_for = |account: &str, currency: &str, month: NaiveDate| {
+        let last_day = month + Months::new(1) - chrono::Days::new(1);
+        budgets
+            .iter()
+            .rev()
+            .find(|b| b.account == account && b.currency == currency && b.date <= last_day)
+            .and_then(|b| b.for_month(month))
+    };
+    let actual_for = |account: &str, currency: &str, month: NaiveDate| {
+        spent
+            .iter()
+            .filter(|((m, a, c), _)| {
+                *m == month
+                    && c == currency
+                    && (a == account
+                        || a.strip_prefix(account)
+                            .is_some_and(|rest| rest.starts_with(':')))
+            })
+            .map(|(_, number)| *number)
+            .sum::<Decimal>()
+    };
+    let percent = |actual: Decimal, budget: Decimal| {
+        if budget.is_zero() {
+            return if actual > Decimal::ZERO { 100 } else { 0 };
+        }
+        (actual * Decimal::ONE_HUNDRED / budget)
+            .round()
+            .to_u32()
+            .unwrap_or(0)
+    };
+
+    let categories: BTreeSet<(&str, &str)> = budgets
+        .iter()
+        .map(|b| (b.account.as_str(), b.currency.as_str()))
+        .collect();
+    categories
+        .into_iter()
+        .filter_map(|(account, currency)| {
+            let budget = budget_for(account, currency, month)?;
+            let actual = actual_for(account, currency, month);
+            let history = months
+                .iter()
+                .map(|&m| {
+                    let actual = actual_for(account, currency, m);
+                    BudgetCell {
+                        month: m.format("%Y-%m").to_string(),
+                        percent: budget_for(account, currency, m).map(|b| percent(actual, b)),
+                        actual: format!("{:.2} {}", actual, currency),
+                    }
+                })
+                .collect();
+            Some(BudgetRow {
+                account: account.to_string(),
+                currency: currency.to_string(),
+                budget: format!("{:.2} {}", budget, currency),
+                actual: format!("{:.2} {}", actual, currency),
+                remaining: format!("{:.2} {}", budget - actual, currency),
+                percent: percent(actual, budget),
+                over_budget: actual > budget,
+                history,
+            })
+        })
+        .collect()
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Builds a register of postings as CSV.
+///
+/// One row per posting to `account_filter` (or any account when `None`),
+/// in date order, with a running balance per currency. The balance counts
+/// postings before `from`, so an exported range starts at the real balance.
+/// Missing posting amounts are interpolated first.
+pub fn register_csv(
+    directives: &[Spanned<Directive>],
+    account_filter: Option<&str>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> String {
+    let mut transactions: Vec<_> = directives
+        .iter()
+        .filter_map(|d| match &d.value {
+            Directive::Transaction(txn) => Some(txn),
+            _ => None,
+        })
+        .collect();
+    transactions.sort_by_key(|txn| txn.date);
+
+    let mut out = String::from("date,flag,payee,narration,account,amount,currency,balance\n");
+    let mut balances: HashMap<String, Decimal> = HashMap::new();
+
+    for txn in transactions {
+        if to.is_some_and(|to| txn.date > to) {
+            break;
+        }
+        let interpolated = interpolate(txn).map(|result| result.transaction);
+        let txn = interpolated.as_ref().unwrap_or(txn);
+        for posting in &txn.postings {
+            let account = posting.account.to_string();
+            if let Some(filter) = account_filter {
+                if account != filter && !account.starts_with(&format!("{}:", filter)) {
+                    continue;
+                }
+            }
+            let Some(units) = &posting.units else {
+                continue;
+            };
+            let (Some(number), Some(currency)) = (units.number(), units.currency()) else {
+                continue;
+            };
+
+            let balance = balances
+                .entry(currency.to_string())
+                .or_insert(Decimal::ZERO);
+            *balance += number;
+
+            if from.is_some_and(|from| txn.date < from) {
+                continue;
+            }
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                txn.date,
+                txn.flag,
+                csv_field(txn.payee.as_deref().unwrap_or_default()),
+                csv_field(&txn.narration),
+                csv_field(&account),
+                number,
+                csv_field(currency),
+                balance
+            ));
+        }
+    }
+
+    out
+}
+
+/// Flattens a loaded ledger into a single beancount file.
+///
+/// Includes are resolved, so the snapshot is self-contained: every option
+/// that differs from its default and the plugins come first, followed by
+/// every directive in date order.
+pub fn ledger_snapshot(load_result: &LoadResult) -> String {
+    let mut out = String::new();
+
+    for (key, value) in load_result.options.entries() {
+        out.push_str(&format!(
+            "option \"{}\" \"{}\"\n",
+            key,
+            value.replace('"', "\\\"")
+        ));
+    }
+    for plugin in &load_result.plugins {
+        match &plugin.config {
+            Some(config) => out.push_str(&format!(
+                "plugin \"{}\" \"{}\"\n",
+                plugin.name,
+                config.replace('"', "\\\"")
+            )),
+            None => out.push_str(&format!("plugin \"{}\"\n", plugin.name)),
+        }
+    }
+
+    let mut directives: Vec<&Directive> = load_result.directives.iter().map(|d| &d.value).collect();
+    directives.sort_by(|a, b| cmp_directives(a, b));
+
+    let config = FormatConfig::default();
+    for directive in directives {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format_directive(directive, &config));
+    }
+
+    out
+}
+
+/// Formats a query result as CSV, one row per result row.
+pub fn query_result_csv(result: &QueryResult) -> String {
+    let header: Vec<String> = result.columns.iter().map(|c| csv_field(c)).collect();
+    let mut out = header.join(",");
+    out.push('\n');
+
+    for row in &result.rows {
+        let values: Vec<String> = row.iter().map(|v| csv_field(&format_value(v))).collect();
+        out.push_str(&values.join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Formats a single query value for CSV output.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Date(d) => d.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Amount(a) => format!("{} {}", a.number, a.currency),
+        Value::Position(p) => match &p.cost {
+            Some(cost) => format!(
+                "{} {} {{{} {}}}",
+                p.units.number, p.units.currency, cost.number, cost.currency
+            ),
+            None => format!("{} {}", p.units.number, p.units.currency),
+        },
+        Value::Inventory(inv) => inv
+            .positions()
+            .iter()
+            .map(|p| format!("{} {}", p.units.number, p.units.currency))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::StringSet(set) => set.join(", "),
+        Value::Null => String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -564,4 +1675,425 @@ mod tests {
         assert!(bank.children.contains_key("Checking"));
         assert_eq!(bank.children["Checking"].full_name, "Assets:Bank:Checking");
     }
+
+    #[test]
+    fn test_build_account_tree_with_balances() {
+        let source = r#"2024-01-01 open Assets:Bank:Checking
+2024-01-01 open Assets:Bank:Old
+2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Food
+2024-01-01 open Income:Salary
+2024-01-02 * "Salary"
+  Assets:Bank:Checking  100 EUR
+  Income:Salary
+2024-01-02 * "Tip"
+  Assets:Cash  5 USD
+  Income:Salary
+2024-01-02 * "Gifts"
+  Assets:Cash  1000 JPY
+  Assets:Cash  0.125 AAPL
+  Income:Salary  -1000 JPY
+  Income:Salary  -0.125 AAPL
+2024-01-03 * "Groceries"
+  Expenses:Food  30 EUR
+  Assets:Bank:Checking
+2024-01-04 close Assets:Bank:Old
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let tree = build_account_tree_with_balances(&directives);
+
+        let assets = &tree["Assets"];
+        assert_eq!(
+            assets.balances,
+            vec!["0.125 AAPL", "70.00 EUR", "1000 JPY", "5.00 USD"]
+        );
+        assert!(!assets.is_zero);
+        assert!(!assets.is_closed);
+
+        // Auto-balanced postings are interpolated
+        let checking = &assets.children["Bank"].children["Checking"];
+        assert_eq!(checking.balances, vec!["70.00 EUR"]);
+
+        let old = &assets.children["Bank"].children["Old"];
+        assert_eq!(old.balances, Vec::<String>::new());
+        assert!(old.is_zero);
+        assert!(old.is_closed);
+        assert!(!assets.children["Bank"].is_closed);
+
+        assert_eq!(tree["Expenses"].balances, vec!["30.00 EUR"]);
+        assert_eq!(
+            tree["Income"].balances,
+            vec!["-0.125 AAPL", "-100.00 EUR", "-1000 JPY", "-5.00 USD"]
+        );
+    }
+
+    #[test]
+    fn test_frequent_payees_and_accounts() {
+        let source = r#"2024-01-01 open Assets:Cash
+2024-01-01 open Assets:Wallet
+2024-01-02 * "Bakery" "Bread"
+  Expenses:Food  3 EUR
+  Assets:Cash
+2024-01-03 * "Bakery" "Croissant"
+  Expenses:Food  2 EUR
+  Assets:Cash
+2024-01-04 * "Kiosk" "Paper"
+  Expenses:News  2 EUR
+  Assets:Wallet
+2024-01-05 close Assets:Wallet
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+
+        assert_eq!(frequent_payees(&directives, 5), vec!["Bakery", "Kiosk"]);
+        assert_eq!(frequent_payees(&directives, 1), vec!["Bakery"]);
+        assert_eq!(
+            frequent_accounts(&directives, &["Expenses"], 5),
+            vec!["Expenses:Food", "Expenses:News"]
+        );
+        // Closed accounts are left out
+        assert_eq!(
+            frequent_accounts(&directives, &["Assets", "Liabilities"], 5),
+            vec!["Assets:Cash"]
+        );
+    }
+
+    #[test]
+    fn test_calculate_sankey() {
+        let source = r#"2024-01-31 * "Salary"
+  Income:Salary  -1000 USD
+  Assets:Bank:Checking
+2024-02-01 * "Groceries and rent"
+  Expenses:Food  100 USD
+  Expenses:Rent:Home  500 USD
+  Assets:Bank:Checking
+2024-02-10 * "Refund"
+  Expenses:Food  -20 USD
+  Assets:Bank:Checking
+2024-03-01 * "Trip"
+  Expenses:Travel  50 EUR
+  Assets:Bank:Checking
+2023-12-31 * "Last year"
+  Income:Salary  -1000 USD
+  Assets:Bank:Checking
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let period: Period = "2024".parse().unwrap();
+
+        let sankey = calculate_sankey(&directives, "USD", &period, 2);
+        assert_eq!(sankey.period, "2024");
+        let names: Vec<_> = sankey.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Assets:Bank",
+                "Expenses:Food",
+                "Expenses:Rent",
+                "Income:Salary"
+            ]
+        );
+        let links: Vec<_> = sankey
+            .links
+            .iter()
+            .map(|l| (l.source.as_str(), l.target.as_str(), l.value))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("Assets:Bank", "Expenses:Food", 80.0),
+                ("Assets:Bank", "Expenses:Rent", 500.0),
+                ("Income:Salary", "Assets:Bank", 1000.0),
+            ]
+        );
+
+        let roots = calculate_sankey(&directives, "USD", &period, 1);
+        assert_eq!(roots.nodes.len(), 3);
+        assert_eq!(roots.links[0].source, "Assets");
+        assert_eq!(roots.links[0].target, "Expenses");
+        assert_eq!(roots.links[0].value, 580.0);
+    }
+
+    #[test]
+    fn test_commodity_holdings_and_prices() {
+        let source = r#"2024-01-01 commodity AAPL
+  name: "Apple Inc."
+2024-01-01 open Assets:Broker
+2024-01-01 open Assets:Cash
+2024-01-02 * "Buy"
+  Assets:Broker  10 AAPL {150 USD}
+  Assets:Cash  -1500 USD
+2024-01-01 price AAPL 150 USD
+2024-02-01 price AAPL 180 USD
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let prices = build_price_database(&directives);
+
+        assert_eq!(extract_commodities(&directives), vec!["AAPL", "USD"]);
+
+        let (date, meta) = commodity_declaration(&directives, "AAPL").unwrap();
+        assert_eq!(date, "2024-01-01");
+        assert_eq!(meta[0].0, "name");
+
+        let history = commodity_price_history(&prices, "AAPL", "USD");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].price, 180.0);
+
+        let (holdings, total) = calculate_commodity_holdings(&directives, "AAPL");
+        assert_eq!(
+            holdings,
+            vec![("Assets:Broker".to_string(), Decimal::from(10))]
+        );
+        assert_eq!(total, Decimal::from(10));
+
+        let summary = summarize_commodities(&directives, &prices, "USD");
+        assert!(summary[0].declared);
+        assert_eq!(summary[0].full_name.as_deref(), Some("Apple Inc."));
+        assert_eq!(summary[0].latest_price.as_deref(), Some("180 USD"));
+        assert!(!summary[1].declared);
+        assert_eq!(summary[1].full_name.as_deref(), Some("US Dollar"));
+    }
+
+    #[test]
+    fn test_calculate_portfolio() {
+        let source = r#"2024-01-01 commodity AAPL
+  asset_class: "equity"
+2024-01-01 commodity BND
+  asset_class: "bond"
+2024-01-01 open Assets:Broker
+2024-01-01 open Assets:Cash
+2024-01-01 open Equity:Opening
+2024-01-01 * "Opening"
+  Assets:Cash  5000 USD
+  Equity:Opening
+2024-01-02 * "Buy stock"
+  Assets:Broker  10 AAPL {150 USD}
+  Assets:Cash  -1500 USD
+2024-01-03 * "Buy bonds"
+  Assets:Broker  20 BND {100 USD}
+  Assets:Cash  -2000 USD
+2024-02-01 price AAPL 180 USD
+2024-02-01 price BND 95 USD
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let prices = build_price_database(&directives);
+
+        let portfolio = calculate_portfolio(&directives, &prices, "USD");
+        assert_eq!(portfolio.market_value, "5200.00 USD");
+        assert_eq!(portfolio.cost_basis, "5000.00 USD");
+        assert_eq!(portfolio.unrealized_gain, "200.00 USD");
+
+        let names: Vec<_> = portfolio.classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["bond", "equity", "Cash"]);
+        assert_eq!(portfolio.classes[0].percent, 36.5);
+        assert_eq!(portfolio.classes[1].percent, 34.6);
+
+        let bond = &portfolio.classes[0].holdings[0];
+        assert_eq!(bond.units, "20 BND");
+        assert_eq!(bond.unrealized_gain.as_deref(), Some("-100.00 USD"));
+        assert!(bond.loss);
+
+        let equity = &portfolio.classes[1].holdings[0];
+        assert_eq!(equity.cost_basis.as_deref(), Some("1500.00 USD"));
+        assert_eq!(equity.market_value.as_deref(), Some("1800.00 USD"));
+        assert!(!equity.loss);
+
+        let cash = &portfolio.classes[2].holdings[0];
+        assert_eq!(cash.unrealized_gain.as_deref(), Some("0.00 USD"));
+    }
+
+    #[test]
+    fn test_register_csv() {
+        let source = r#"2024-01-01 open Assets:Bank
+2024-01-05 * "Cafe, Inc." "Coffee"
+  Expenses:Food  4.50 USD
+  Assets:Bank
+2024-01-01 * "Employer" "Salary"
+  Income:Salary  -100 USD
+  Assets:Bank  100 USD
+2024-02-01 * "Rent"
+  Expenses:Rent  50 USD
+  Assets:Bank  -50 USD
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+
+        let csv = register_csv(&directives, Some("Assets"), None, None);
+        assert_eq!(
+            csv,
+            "date,flag,payee,narration,account,amount,currency,balance\n\
+             2024-01-01,*,Employer,Salary,Assets:Bank,100,USD,100\n\
+             2024-01-05,*,\"Cafe, Inc.\",Coffee,Assets:Bank,-4.50,USD,95.50\n\
+             2024-02-01,*,,Rent,Assets:Bank,-50,USD,45.50\n"
+        );
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 2);
+        let csv = register_csv(&directives, Some("Expenses"), from, from);
+        assert_eq!(csv.lines().count(), 1);
+
+        let csv = register_csv(&directives, None, from, None);
+        assert!(csv.contains("2024-01-05,*,\"Cafe, Inc.\",Coffee,Expenses:Food,4.50,USD,4.50\n"));
+    }
+
+    #[test]
+    fn test_ledger_snapshot_keeps_options() {
+        let source = r#"option "title" "Books"
+option "booking_method" "FIFO"
+option "inferred_tolerance_default" "USD:0.01"
+2024-01-01 open Assets:Bank
+"#;
+        let load_result = rustledger_loader::Loader::new()
+            .load_source(Path::new("main.beancount"), source)
+            .unwrap();
+
+        let snapshot = ledger_snapshot(&load_result);
+        assert!(snapshot.starts_with(
+            "option \"title\" \"Books\"\n\
+             option \"inferred_tolerance_default\" \"USD:0.01\"\n\
+             option \"booking_method\" \"FIFO\"\n"
+        ));
+        // Options the ledger left alone are not written out
+        assert_eq!(snapshot.matches("option ").count(), 3);
+        assert!(snapshot.contains("2024-01-01 open Assets:Bank"));
+    }
+
+    #[test]
+    fn test_tags_and_account_totals() {
+        let source = r#"2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Travel
+2024-01-01 open Expenses:Food
+2024-03-01 * "Train" #trip-rome ^booking-1
+  Expenses:Travel  80 EUR
+  Assets:Cash
+2024-03-02 * "Dinner" #trip-rome #food
+  Expenses:Food  40 EUR
+  Assets:Cash
+2024-03-05 * "Hotel refund" #trip-rome ^booking-1
+  Expenses:Travel  -20 EUR
+  Assets:Cash
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+
+        let tags = summarize_tags(&directives);
+        let names: Vec<_> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["food", "trip-rome"]);
+        assert_eq!(tags[1].transaction_count, 3);
+        assert_eq!(tags[1].first_date, "2024-03-01");
+        assert_eq!(tags[1].last_date, "2024-03-05");
+
+        let on_trip = |txn: &Transaction| txn.tags.contains("trip-rome");
+        let totals = calculate_account_totals(&directives, on_trip);
+        let rows: Vec<_> = totals
+            .iter()
+            .map(|t| (t.account.as_str(), t.totals.clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("Assets:Cash", vec!["-100.00 EUR".to_string()]),
+                ("Expenses:Food", vec!["40.00 EUR".to_string()]),
+                ("Expenses:Travel", vec!["60.00 EUR".to_string()]),
+            ]
+        );
+
+        let sources = vec![PathBuf::from("main.beancount"); directives.len()];
+        let linked = extract_matching_transactions(&directives, &sources, |txn| {
+            txn.links.contains("booking-1")
+        });
+        let narrations: Vec<_> = linked.iter().map(|t| t.narration.as_str()).collect();
+        assert_eq!(narrations, ["Hotel refund", "Train"]);
+        assert_eq!(linked[1].tags, ["trip-rome"]);
+        assert_eq!(linked[1].links, ["booking-1"]);
+    }
+
+    #[test]
+    fn test_transaction_issues() {
+        let source = r#"2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Food
+2024-02-01 close Assets:Cash
+2024-01-05 * "Lunch"
+  Expenses:Food  12.00 EUR
+  Assets:Cash  -10.00 EUR
+2024-01-06 * "Coffee"
+  Expenses:Food  3.00 EUR
+  Assets:Cash
+2024-01-06 * "Coffee"
+  Expenses:Food  3.00 EUR
+  Assets:Cash
+2024-01-07 * "Bread"
+  Expenses:Food  2.00 EUR
+  Assets:Cash
+2024-03-01 * "Late"
+  Expenses:Food  1.00 EUR
+  Assets:Cash
+"#;
+        let load_result = rustledger_loader::Loader::new()
+            .load_source(Path::new("main.beancount"), source)
+            .unwrap();
+        let issues = transaction_issues(&load_result);
+        let transactions = || {
+            extract_recent_transactions(
+                &load_result.directives,
+                &load_result.directive_sources,
+                usize::MAX,
+            )
+        };
+
+        let all = annotate_transactions(transactions(), &issues, false, usize::MAX);
+        assert_eq!(all.len(), 5);
+
+        let problems = annotate_transactions(transactions(), &issues, true, usize::MAX);
+        let narrations: Vec<_> = problems.iter().map(|t| t.narration.as_str()).collect();
+        assert_eq!(narrations, ["Late", "Coffee", "Lunch"]);
+        assert!(problems[0].issues[0].starts_with("[E1003]"));
+        assert_eq!(problems[1].issues, ["Duplicate of an earlier transaction"]);
+        assert!(problems[2].issues[0].starts_with("[E3001]"));
+
+        let limited = annotate_transactions(transactions(), &issues, true, 1);
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[test]
+    fn test_calculate_budgets() {
+        let source = r#"2024-01-01 custom "budget" Expenses:Food "monthly" 400 USD
+2024-01-01 custom "budget" Expenses:Fun "weekly" 7 USD
+2024-03-01 custom "budget" Expenses:Food "monthly" 300 USD
+2024-02-10 * "Groceries"
+  Expenses:Food:Groceries  260 USD
+  Assets:Cash
+2024-03-02 * "Restaurant"
+  Expenses:Food  330 USD
+  Assets:Cash
+2024-03-03 * "Cinema"
+  Expenses:Fun  12 USD
+  Assets:Cash
+"#;
+        let directives = rustledger_parser::parse(source).directives;
+        let march = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        let rows = calculate_budgets(&directives, march);
+        assert_eq!(rows.len(), 2);
+
+        let food = &rows[0];
+        assert_eq!(food.account, "Expenses:Food");
+        assert_eq!(food.budget, "300.00 USD");
+        assert_eq!(food.actual, "330.00 USD");
+        assert_eq!(food.remaining, "-30.00 USD");
+        assert_eq!(food.percent, 110);
+        assert!(food.over_budget);
+
+        // Weekly budgets are scaled to the month's length
+        let fun = &rows[1];
+        assert_eq!(fun.budget, "31.00 USD");
+        assert_eq!(fun.percent, 39);
+
+        // The heat map covers 12 months, with no budget before January
+        assert_eq!(food.history.len(), 12);
+        assert_eq!(food.history[0].month, "2023-04");
+        assert_eq!(food.history[0].percent, None);
+        assert_eq!(food.history[10].month, "2024-02");
+        assert_eq!(food.history[10].percent, Some(65));
+        assert_eq!(food.history[10].actual, "260.00 USD");
+
+        // No budgets are in effect before the first declaration
+        let earlier = NaiveDate::from_ymd_opt(2023, 12, 1).unwrap();
+        assert!(calculate_budgets(&directives, earlier).is_empty());
+    }
 }
@@ -0,0 +1,8 @@
+//! bean-file - Python beancount compatibility wrapper.
+//!
+//! This binary provides backwards compatibility with bean-file from Python beancount.
+//! It delegates to the rledger-file implementation.
+
+fn main() -> std::process::ExitCode {
+    rustledger::cmd::file_cmd::main_with_name("bean-file")
+}
@@ -0,0 +1,8 @@
+//! bean-identify - Python beancount compatibility wrapper.
+//!
+//! This binary provides backwards compatibility with bean-identify from Python beancount.
+//! It delegates to the rledger-identify implementation.
+
+fn main() -> std::process::ExitCode {
+    rustledger::cmd::identify_cmd::main_with_name("bean-identify")
+}
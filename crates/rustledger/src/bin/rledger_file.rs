@@ -0,0 +1,7 @@
+//! rledger-file - File downloaded statements into the documents tree.
+//!
+//! Primary binary for moving downloaded statements into the documents tree.
+
+fn main() -> std::process::ExitCode {
+    rustledger::cmd::file_cmd::main()
+}
@@ -0,0 +1,7 @@
+//! rledger-identify - Show which importer claims each downloaded file.
+//!
+//! Primary binary for identifying the importer of each downloaded file.
+
+fn main() -> std::process::ExitCode {
+    rustledger::cmd::identify_cmd::main()
+}
@@ -0,0 +1,4 @@
+//! rledger-init - Scaffold a new ledger directory.
+fn main() -> std::process::ExitCode {
+    rustledger::cmd::init_cmd::main()
+}
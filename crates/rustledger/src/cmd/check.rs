@@ -6,19 +6,25 @@ use anyhow::{Context, Result};
 use chrono::NaiveDate;
 use clap::{Parser, ValueEnum};
 use rayon::prelude::*;
-use rustledger_booking::{InterpolationError, interpolate};
-use rustledger_core::Directive;
+use rustledger_booking::{InterpolationError, absorb_rounding, interpolate};
+use rustledger_core::{Directive, diff_ledgers};
 use rustledger_loader::{
-    CacheEntry, CachedOptions, CachedPlugin, LoadError, LoadResult, Loader, load_cache_entry,
-    reintern_directives, save_cache_entry,
+    CacheEntry, CachedOptions, CachedPlugin, LoadError, LoadResult, Loader, PluginProcessingMode,
+    load_cache_entry, reintern_directives, save_cache_entry,
+};
+use rustledger_plugin::{
+    NativePluginRegistry, PluginError, PluginInput, PluginOptions, wrappers_to_directives,
 };
 #[cfg(feature = "python-plugin-wasm")]
-use rustledger_plugin::PluginManager;
-use rustledger_plugin::{NativePluginRegistry, PluginInput, PluginOptions, wrappers_to_directives};
-use rustledger_validate::validate;
+use rustledger_plugin::{PluginCache, PluginManager, RuntimeConfig};
+use rustledger_validate::{
+    ToleranceOptions, ValidationOptions, ValidationReport, validate_parallel,
+};
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use tracing::Level;
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -109,7 +115,7 @@ pub struct Args {
     #[arg(short, long)]
     pub quiet: bool,
 
-    /// Disable the binary cache for parsed directives
+    /// Disable the binary cache for parsed directives and plugin results
     #[arg(short = 'C', long = "no-cache")]
     pub no_cache: bool,
 
@@ -126,10 +132,70 @@ pub struct Args {
     #[arg(long = "plugin", value_name = "WASM_FILE")]
     pub plugins: Vec<PathBuf>,
 
+    /// Date WASM plugins see as today, for reproducible plugin output
+    /// (defaults to the current date)
+    #[cfg(feature = "python-plugin-wasm")]
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub date: Option<NaiveDate>,
+
+    /// Seed for the random numbers WASM plugins draw
+    #[cfg(feature = "python-plugin-wasm")]
+    #[arg(long, value_name = "SEED", default_value_t = 0)]
+    pub seed: u64,
+
     /// Run built-in native plugins (e.g., `implicit_prices`, `check_commodity`)
     #[arg(long = "native-plugin", value_name = "NAME")]
     pub native_plugins: Vec<String>,
 
+    /// Print the directives added, modified or removed by plugins instead
+    /// of validating the ledger
+    #[arg(long)]
+    pub plugins_diff: bool,
+
+    /// Warn about postings to accounts that have child accounts
+    #[arg(long)]
+    pub leaf_only: bool,
+
+    /// Warn about files in the `documents` directories that no document
+    /// directive references
+    #[arg(long)]
+    pub unlinked_documents: bool,
+
+    /// Warn about transaction links used once that no document directive
+    /// carries, in ledgers that link documents to transactions
+    #[arg(long)]
+    pub document_links: bool,
+
+    /// Warn about postings with zero units and transactions whose postings
+    /// cancel out within each account
+    #[arg(long)]
+    pub zero_postings: bool,
+
+    /// Warn about Assets/Liabilities accounts without currency constraints
+    /// that hold currencies other than the `operating_currency` options
+    #[arg(long)]
+    pub operating_currencies: bool,
+
+    /// Report open accounts whose last balance assertion is more than DAYS
+    /// days old
+    #[arg(long, value_name = "DAYS")]
+    pub stale_balances: Option<u32>,
+
+    /// Allow balance, note and document directives for accounts after they
+    /// were closed
+    #[arg(long)]
+    pub allow_entries_after_close: bool,
+
+    /// Warn about entries dated in the future, allowing post-dated entries
+    /// up to DAYS days ahead
+    #[arg(long, value_name = "DAYS", num_args = 0..=1, default_missing_value = "0")]
+    pub future_dates: Option<u32>,
+
+    /// Only report problems introduced by these files, checked in the
+    /// context of the whole ledger (for pre-commit hooks)
+    #[arg(long, value_name = "FILE", num_args = 1..)]
+    pub staged: Vec<PathBuf>,
+
     /// Output format (text or json)
     #[arg(long, short = 'f', value_enum, default_value = "text")]
     pub format: OutputFormat,
@@ -151,8 +217,18 @@ fn run(args: &Args) -> Result<ExitCode> {
     let json_mode = matches!(args.format, OutputFormat::Json);
     let mut diagnostics: Vec<JsonDiagnostic> = Vec::new();
 
-    // Try loading from cache first (unless --no-cache)
-    let cache_entry = if args.no_cache {
+    // With --staged, problems the rest of the ledger already had are not
+    // reported. The loader canonicalizes the paths it records.
+    let staged: HashSet<PathBuf> = args
+        .staged
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+        .collect();
+    let staged_mode = !staged.is_empty();
+
+    // Try loading from cache first (unless --no-cache). The cache does not
+    // record which file each directive came from, which --staged needs.
+    let cache_entry = if args.no_cache || staged_mode {
         None
     } else {
         load_cache_entry(file)
@@ -191,10 +267,14 @@ fn run(args: &Args) -> Result<ExitCode> {
 
         let result = rustledger_loader::LoadResult {
             directives: entry.directives,
+            // Per-directive source files are not stored in the cache
+            directive_sources: Vec::new(),
             options: entry.options.into(),
             plugins,
             source_map,
             errors: Vec::new(),
+            // Nothing is parsed on a cache hit
+            file_stats: Vec::new(),
         };
         (result, true)
     } else {
@@ -269,6 +349,7 @@ fn run(args: &Args) -> Result<ExitCode> {
     // Report load/parse errors
     for load_error in &load_result.errors {
         match load_error {
+            LoadError::ParseErrors { path, .. } if staged_mode && !staged.contains(path) => {}
             LoadError::ParseErrors { path, errors } => {
                 let source = std::fs::read_to_string(path).unwrap_or_default();
                 let path_str = path.display().to_string();
@@ -402,8 +483,14 @@ fn run(args: &Args) -> Result<ExitCode> {
 
     // Report option warnings (E7001, E7002, E7003)
     let main_file_str = file.display().to_string();
-    let option_warning_count = load_result.options.warnings.len();
-    for warning in &load_result.options.warnings {
+    let main_staged = file.canonicalize().is_ok_and(|path| staged.contains(&path));
+    let option_warnings = if staged_mode && !main_staged {
+        &[][..]
+    } else {
+        &load_result.options.warnings[..]
+    };
+    let option_warning_count = option_warnings.len();
+    for warning in option_warnings {
         if json_mode {
             diagnostics.push(JsonDiagnostic {
                 file: main_file_str.clone(),
@@ -422,6 +509,17 @@ fn run(args: &Args) -> Result<ExitCode> {
         }
     }
 
+    // The ledger without the staged files, whose problems are not reported
+    let mut baseline: Option<Vec<Directive>> = staged_mode.then(|| {
+        load_result
+            .directives
+            .iter()
+            .zip(&load_result.directive_sources)
+            .filter(|(_, source)| !staged.contains(*source))
+            .map(|(directive, _)| directive.value.clone())
+            .collect()
+    });
+
     // Destructure to enable move instead of clone
     let LoadResult {
         directives: spanned_directives,
@@ -429,23 +527,58 @@ fn run(args: &Args) -> Result<ExitCode> {
         ..
     } = load_result;
 
+    // Document paths and directories are relative to the ledger file
+    let ledger_dir = file
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    let documents_dirs: Vec<PathBuf> = options
+        .documents
+        .iter()
+        .map(|dir| ledger_dir.join(dir))
+        .collect();
+    let operating_currencies = options.operating_currency.clone();
+    let first_date = options.first_date;
+    let account_rounding = options.account_rounding.clone();
+    let tolerance = ToleranceOptions {
+        defaults: options.inferred_tolerance_default.clone(),
+        multiplier: options.inferred_tolerance_multiplier,
+        infer_from_cost: options.infer_tolerance_from_cost,
+    };
+
     // Extract directives (move, not clone)
     let mut directives: Vec<_> = spanned_directives.into_iter().map(|s| s.value).collect();
 
     // Build list of native plugins to run
     let mut native_plugins_to_run = args.native_plugins.clone();
 
-    // If --auto is set, add auto-plugins
-    if args.auto && !native_plugins_to_run.contains(&"auto_accounts".to_string()) {
+    // If --auto is set, add auto-plugins, unless the ledger asks for raw processing
+    if args.auto
+        && options.plugin_processing_mode == PluginProcessingMode::Default
+        && !native_plugins_to_run.contains(&"auto_accounts".to_string())
+    {
         native_plugins_to_run.insert(0, "auto_accounts".to_string());
     }
 
+    // Plugins turned off by the ledger never run
+    native_plugins_to_run.retain(|name| {
+        let disabled = options.is_plugin_disabled(name);
+        if disabled && args.verbose && !args.quiet {
+            eprintln!("Skipping disabled plugin: {name}");
+        }
+        !disabled
+    });
+    options.order_plugins(&mut native_plugins_to_run);
+
     // Run plugins if specified
     #[cfg(feature = "python-plugin-wasm")]
     let has_wasm_plugins = !args.plugins.is_empty();
     #[cfg(not(feature = "python-plugin-wasm"))]
     let has_wasm_plugins = false;
 
+    // Keep the plugin input around to show what the plugins changed
+    let before_plugins = args.plugins_diff.then(|| directives.clone());
+
     if !native_plugins_to_run.is_empty() || has_wasm_plugins {
         if args.verbose && !args.quiet {
             eprintln!("Running plugins...");
@@ -471,9 +604,10 @@ fn run(args: &Args) -> Result<ExitCode> {
                 }
                 let output = plugin.process(current_input.clone());
 
-                for err in &output.errors {
+                // Plugin errors are only located when the plugin knows where
+                for err in output.errors.iter().filter(|_| !staged_mode) {
                     if !args.quiet {
-                        writeln!(stdout, "{:?}: {}", err.severity, err.message)?;
+                        writeln!(stdout, "{}", format_plugin_error(err))?;
                     }
                     error_count += 1;
                 }
@@ -490,7 +624,21 @@ fn run(args: &Args) -> Result<ExitCode> {
 
         #[cfg(feature = "python-plugin-wasm")]
         if !args.plugins.is_empty() {
-            let mut wasm_manager = PluginManager::new();
+            let mut runtime_config = RuntimeConfig {
+                seed: args.seed,
+                ..RuntimeConfig::default()
+            };
+            if let Some(today) = args.date {
+                runtime_config.today = today;
+            }
+            let mut wasm_manager = PluginManager::with_config(runtime_config);
+            if !args.no_cache {
+                wasm_manager.set_cache(PluginCache::for_ledger(file));
+            }
+            for name in &options.disabled_plugins {
+                wasm_manager.disable(name);
+            }
+            wasm_manager.set_order(options.plugin_order.clone());
 
             for plugin_path in &args.plugins {
                 if args.verbose && !args.quiet {
@@ -516,9 +664,9 @@ fn run(args: &Args) -> Result<ExitCode> {
 
                 match wasm_manager.execute_all(current_input.clone()) {
                     Ok(output) => {
-                        for err in &output.errors {
+                        for err in output.errors.iter().filter(|_| !staged_mode) {
                             if !args.quiet {
-                                writeln!(stdout, "{:?}: {}", err.severity, err.message)?;
+                                writeln!(stdout, "{}", format_plugin_error(err))?;
                             }
                             error_count += 1;
                         }
@@ -552,27 +700,29 @@ fn run(args: &Args) -> Result<ExitCode> {
         }
     }
 
+    if let Some(before) = before_plugins {
+        if !args.quiet {
+            report::print_directive_diff(&diff_ledgers(&before, &directives), &mut stdout)?;
+        }
+        return Ok(if error_count > 0 {
+            ExitCode::from(1)
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+
     // Run interpolation on transactions (parallel)
     if args.verbose && !args.quiet {
         eprintln!("Interpolating {} directives...", directives.len());
     }
 
-    let interpolation_errors: Vec<(NaiveDate, String, InterpolationError)> = directives
-        .par_iter_mut()
-        .filter_map(|directive| {
-            if let Directive::Transaction(txn) = directive {
-                match interpolate(txn) {
-                    Ok(result) => {
-                        *txn = result.transaction;
-                        None
-                    }
-                    Err(e) => Some((txn.date, txn.narration.to_string(), e)),
-                }
-            } else {
-                None
-            }
-        })
-        .collect();
+    let mut interpolation_errors = interpolate_all(&mut directives, account_rounding.as_deref());
+    if let Some(baseline) = &mut baseline {
+        let known = interpolate_all(baseline, account_rounding.as_deref());
+        interpolation_errors = introduced(interpolation_errors, &known, |(date, narration, e)| {
+            (*date, narration.clone(), e.to_string())
+        });
+    }
 
     if !interpolation_errors.is_empty() {
         if json_mode {
@@ -599,25 +749,41 @@ fn run(args: &Args) -> Result<ExitCode> {
     }
     error_count += interpolation_errors.len();
 
-    // Validate the directives
+    // Validate the directives, booking independent accounts in parallel
     if args.verbose && !args.quiet {
         eprintln!("Validating {} directives...", directives.len());
     }
 
-    let validation_errors = validate(&directives);
-    let validation_error_count = validation_errors
-        .iter()
-        .filter(|e| !e.code.is_warning())
-        .count();
-    let validation_warning_count = validation_errors
-        .iter()
-        .filter(|e| e.code.is_warning())
-        .count();
-    error_count += validation_error_count;
+    let validation_options = ValidationOptions {
+        warn_non_leaf_postings: args.leaf_only,
+        warn_unlinked_documents: args.unlinked_documents,
+        warn_document_links: args.document_links,
+        warn_zero_postings: args.zero_postings,
+        warn_non_operating_currencies: args.operating_currencies,
+        allow_entries_after_close: args.allow_entries_after_close,
+        stale_balance_days: args.stale_balances,
+        warn_future_dates: args.future_dates.is_some(),
+        future_date_horizon_days: args.future_dates.unwrap_or(0),
+        first_date,
+        operating_currencies,
+        document_base: Some(ledger_dir),
+        documents_dirs,
+        tolerance,
+        ..Default::default()
+    };
+    let mut validation_errors = validate_parallel(&directives, validation_options.clone());
+    if let Some(baseline) = baseline {
+        let known = validate_parallel(&baseline, validation_options);
+        validation_errors = introduced(validation_errors, &known, |e| {
+            (e.code.code(), e.message.clone(), e.date, e.context.clone())
+        });
+    }
+    let validation_report = ValidationReport::new(validation_errors);
+    error_count += validation_report.error_count();
 
-    if !validation_errors.is_empty() {
+    if !validation_report.is_empty() {
         if json_mode {
-            for err in &validation_errors {
+            for err in validation_report.errors() {
                 let severity = if err.code.is_warning() {
                     "warning"
                 } else {
@@ -637,13 +803,13 @@ fn run(args: &Args) -> Result<ExitCode> {
                 });
             }
         } else if !args.quiet {
-            report::report_validation_errors(&validation_errors, &cache, &mut stdout)?;
+            report::report_validation_errors(validation_report.errors(), &cache, &mut stdout)?;
         }
     }
 
     // Print summary / output
     let elapsed = start.elapsed();
-    let warning_count = option_warning_count + validation_warning_count;
+    let warning_count = option_warning_count + validation_report.warning_count();
 
     if json_mode {
         let output = JsonOutput {
@@ -663,6 +829,7 @@ fn run(args: &Args) -> Result<ExitCode> {
             )?;
         }
         report::print_summary(error_count, warning_count, &mut stdout)?;
+        report::print_validation_breakdown(&validation_report, &mut stdout)?;
     }
 
     if error_count > 0 {
@@ -672,6 +839,60 @@ fn run(args: &Args) -> Result<ExitCode> {
     }
 }
 
+/// Interpolate missing posting amounts in place, returning the transactions
+/// that could not be completed.
+fn interpolate_all(
+    directives: &mut [Directive],
+    account_rounding: Option<&str>,
+) -> Vec<(NaiveDate, String, InterpolationError)> {
+    directives
+        .par_iter_mut()
+        .filter_map(|directive| {
+            if let Directive::Transaction(txn) = directive {
+                match interpolate(txn) {
+                    Ok(result) => {
+                        *txn = result.transaction;
+                        if let Some(account) = account_rounding {
+                            absorb_rounding(txn, account);
+                        }
+                        None
+                    }
+                    Err(e) => Some((txn.date, txn.narration.to_string(), e)),
+                }
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Format a plugin error, prefixed with its location when the plugin gave one.
+fn format_plugin_error(err: &PluginError) -> String {
+    match (&err.source_file, err.line_number) {
+        (Some(file), Some(line)) => format!("{file}:{line}: {:?}: {}", err.severity, err.message),
+        _ => format!("{:?}: {}", err.severity, err.message),
+    }
+}
+
+/// The problems in `found` that `known` does not have, matching repeated
+/// problems one for one by `key`.
+fn introduced<T, K: Eq + Hash>(found: Vec<T>, known: &[T], key: impl Fn(&T) -> K) -> Vec<T> {
+    let mut counts: HashMap<K, usize> = HashMap::new();
+    for problem in known {
+        *counts.entry(key(problem)).or_default() += 1;
+    }
+    found
+        .into_iter()
+        .filter(|problem| match counts.get_mut(&key(problem)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
 /// Main entry point for the check command.
 pub fn main() -> ExitCode {
     main_with_name("rledger-check")
@@ -702,3 +923,15 @@ pub fn main_with_name(bin_name: &str) -> ExitCode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_introduced_skips_known_problems() {
+        let found = vec!["a", "b", "a", "c"];
+        let known = ["a", "c", "d"];
+        assert_eq!(introduced(found, &known, |p| *p), ["b", "a"]);
+    }
+}
@@ -10,20 +10,34 @@
 //! bean-doctor linked ledger.beancount ^trip-2024  # Find linked transactions
 //! bean-doctor missing-open ledger.beancount  # Generate missing Open directives
 //! bean-doctor list-options                 # List available options
+//! rledger-doctor stats ledger.beancount --format json  # Machine-readable statistics
+//! rledger-doctor validate-includes ledger.beancount --format dot  # Include graph
+//! rledger-doctor bench ledger.beancount --format json > bench.json  # Time the pipeline
+//! rledger-doctor bench ledger.beancount --baseline bench.json  # Compare against a previous run
+//! rledger-doctor diff old.beancount new.beancount  # Show added, removed and modified directives
+//! rledger-doctor diff --git HEAD~1 ledger.beancount  # Compare against a git revision
 //! ```
 
 use crate::cmd::completions::ShellType;
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use rust_decimal;
+use rustledger_booking::interpolate;
+use rustledger_core::intern::{self, InternStats};
+use rustledger_core::memory::{MemoryUsage, memory_usage};
 use rustledger_core::{Directive, InternedStr, NaiveDate};
-use rustledger_loader::Loader;
+use rustledger_loader::{LoadSummary, Loader};
 use rustledger_parser;
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use rustledger_query::{Executor, parse as parse_query};
+use rustledger_validate::validate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
 /// Debugging tool for beancount files.
 #[derive(Parser, Debug)]
@@ -91,6 +105,9 @@ enum Command {
     Stats {
         /// The beancount file
         file: PathBuf,
+        /// Output format
+        #[arg(long, short = 'f', value_enum, default_value = "text")]
+        format: StatsFormat,
     },
 
     /// Display the decimal precision context inferred from the file
@@ -126,6 +143,71 @@ enum Command {
         #[arg(long, value_enum)]
         conversion: Option<Conversion>,
     },
+
+    /// Validate includes and print the include dependency graph; exits
+    /// non-zero when problems are found
+    ValidateIncludes {
+        /// The root beancount file
+        file: PathBuf,
+        /// Output format
+        #[arg(long, short = 'f', value_enum, default_value = "text")]
+        format: GraphFormat,
+    },
+
+    /// Time parse, load, booking, validation, and queries over a ledger
+    Bench {
+        /// The beancount file
+        file: PathBuf,
+        /// Number of timed runs per phase
+        #[arg(long, short = 'n', default_value = "5")]
+        iterations: usize,
+        /// Additional BQL query to time (repeatable)
+        #[arg(long = "query", short = 'q', value_name = "BQL")]
+        queries: Vec<String>,
+        /// JSON output of a previous run to compare against
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        /// Output format
+        #[arg(long, short = 'f', value_enum, default_value = "text")]
+        format: StatsFormat,
+    },
+
+    /// Show the directives added, removed or modified between two ledgers,
+    /// and how account balances changed
+    ///
+    /// With `--git REV`, FILE as of REV is compared with the working tree
+    /// copy; with `--git` given twice, the two revisions are compared.
+    Diff {
+        /// The old beancount file, or the ledger file with --git
+        #[arg(value_name = "FILE")]
+        old: PathBuf,
+        /// The new beancount file
+        #[arg(required_unless_present = "git", conflicts_with = "git")]
+        new: Option<PathBuf>,
+        /// Load the ledger as of a git revision (may be given twice)
+        #[arg(long, value_name = "REV", num_args = 1, action = clap::ArgAction::Append)]
+        git: Vec<String>,
+    },
+}
+
+/// Output format for the include graph
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    /// Human-readable report
+    Text,
+    /// Graphviz DOT
+    Dot,
+    /// JSON
+    Json,
+}
+
+/// Output format for ledger statistics and benchmarks
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum StatsFormat {
+    /// Human-readable report
+    Text,
+    /// JSON
+    Json,
 }
 
 /// Conversion type for region balances
@@ -179,7 +261,7 @@ fn run(command: Command) -> Result<()> {
         Command::MissingOpen { file } => cmd_missing_open(&file, &mut stdout),
         Command::ListOptions => cmd_list_options(&mut stdout),
         Command::PrintOptions { file } => cmd_print_options(&file, &mut stdout),
-        Command::Stats { file } => cmd_stats(&file, &mut stdout),
+        Command::Stats { file, format } => cmd_stats(&file, format, &mut stdout),
         Command::DisplayContext { file } => cmd_display_context(&file, &mut stdout),
         Command::Roundtrip { file } => cmd_roundtrip(&file, &mut stdout),
         Command::Directories { file, dirs } => cmd_directories(&file, &dirs, &mut stdout),
@@ -189,6 +271,36 @@ fn run(command: Command) -> Result<()> {
             end_line,
             conversion,
         } => cmd_region(&file, start_line, end_line, conversion, &mut stdout),
+        Command::ValidateIncludes { file, format } => {
+            cmd_validate_includes(&file, format, &mut stdout)
+        }
+        Command::Bench {
+            file,
+            iterations,
+            queries,
+            baseline,
+            format,
+        } => cmd_bench(
+            &file,
+            iterations,
+            &queries,
+            baseline.as_deref(),
+            format,
+            &mut stdout,
+        ),
+        Command::Diff { old, new, git } => match (new, git.as_slice()) {
+            (Some(new), []) => cmd_diff(&old, &new, &mut stdout),
+            (None, [rev]) => {
+                let checkout = GitCheckout::new(&old, rev)?;
+                cmd_diff(&checkout.ledger, &old, &mut stdout)
+            }
+            (None, [old_rev, new_rev]) => {
+                let old_checkout = GitCheckout::new(&old, old_rev)?;
+                let new_checkout = GitCheckout::new(&old, new_rev)?;
+                cmd_diff(&old_checkout.ledger, &new_checkout.ledger, &mut stdout)
+            }
+            _ => anyhow::bail!("diff takes two files, or one file and --git REV (at most twice)"),
+        },
     }
 }
 
@@ -307,7 +419,7 @@ fn cmd_linked<W: Write>(file: &PathBuf, location: &str, writer: &mut W) -> Resul
             .iter()
             .filter(|d| {
                 if let Directive::Transaction(txn) = d {
-                    txn.links.iter().any(|l| l.as_str() == link_name)
+                    txn.links.contains(link_name)
                 } else {
                     false
                 }
@@ -320,7 +432,7 @@ fn cmd_linked<W: Write>(file: &PathBuf, location: &str, writer: &mut W) -> Resul
             .iter()
             .filter(|d| {
                 if let Directive::Transaction(txn) = d {
-                    txn.tags.iter().any(|t| t.as_str() == tag_name)
+                    txn.tags.contains(tag_name)
                 } else {
                     false
                 }
@@ -548,7 +660,12 @@ fn cmd_list_options<W: Write>(writer: &mut W) -> Result<()> {
             "string",
             "Default booking method (STRICT, FIFO, LIFO, etc.)",
         ),
-        ("plugin_processing_mode", "string", "Plugin processing mode"),
+        (
+            "plugin_processing_mode",
+            "string",
+            "Plugin processing mode (default, raw)",
+        ),
+        ("disable_plugin", "string", "Plugin that must not run"),
         (
             "long_string_maxlines",
             "int",
@@ -622,7 +739,24 @@ fn cmd_print_options<W: Write>(file: &PathBuf, writer: &mut W) -> Result<()> {
     Ok(())
 }
 
-fn cmd_stats<W: Write>(file: &PathBuf, writer: &mut W) -> Result<()> {
+/// Statistics reported by `stats`.
+#[derive(Debug, Serialize)]
+struct LedgerStats {
+    first_date: Option<NaiveDate>,
+    last_date: Option<NaiveDate>,
+    transactions: usize,
+    postings: usize,
+    accounts: usize,
+    commodities: usize,
+    balances: usize,
+    prices: usize,
+    #[serde(flatten)]
+    load: LoadSummary,
+    memory: MemoryUsage,
+    interning: InternStats,
+}
+
+fn cmd_stats<W: Write>(file: &PathBuf, format: StatsFormat, writer: &mut W) -> Result<()> {
     let mut loader = Loader::new();
     let load_result = loader
         .load(file)
@@ -671,11 +805,35 @@ fn cmd_stats<W: Write>(file: &PathBuf, writer: &mut W) -> Result<()> {
         }
     }
 
+    let stats = LedgerStats {
+        first_date,
+        last_date,
+        transactions,
+        postings,
+        accounts,
+        commodities: commodities_set.len(),
+        balances: balance_assertions,
+        prices,
+        load: load_result.summary(),
+        memory: memory_usage(load_result.directives.iter().map(|spanned| &spanned.value)),
+        interning: intern::stats(),
+    };
+
+    match format {
+        StatsFormat::Text => write_stats_text(file, &stats, writer),
+        StatsFormat::Json => {
+            writeln!(writer, "{}", serde_json::to_string_pretty(&stats)?)?;
+            Ok(())
+        }
+    }
+}
+
+fn write_stats_text<W: Write>(file: &Path, stats: &LedgerStats, writer: &mut W) -> Result<()> {
     writeln!(writer, "Ledger Statistics for {}", file.display())?;
     writeln!(writer, "{}", "=".repeat(60))?;
     writeln!(writer)?;
 
-    if let (Some(first), Some(last)) = (first_date, last_date) {
+    if let (Some(first), Some(last)) = (stats.first_date, stats.last_date) {
         writeln!(writer, "Date range: {first} to {last}")?;
         writeln!(writer)?;
     }
@@ -683,20 +841,105 @@ fn cmd_stats<W: Write>(file: &PathBuf, writer: &mut W) -> Result<()> {
     writeln!(
         writer,
         "Directives:       {:>8}",
-        load_result.directives.len()
+        stats.load.directive_count
+    )?;
+    writeln!(writer, "  Transactions:   {:>8}", stats.transactions)?;
+    writeln!(writer, "  Postings:       {:>8}", stats.postings)?;
+    writeln!(writer, "  Accounts:       {:>8}", stats.accounts)?;
+    writeln!(writer, "  Commodities:    {:>8}", stats.commodities)?;
+    writeln!(writer, "  Balances:       {:>8}", stats.balances)?;
+    writeln!(writer, "  Prices:         {:>8}", stats.prices)?;
+    writeln!(writer)?;
+    // Parse errors from every loaded file, plus includes that could not be read
+    writeln!(
+        writer,
+        "Parse errors:     {:>8}",
+        stats.load.parse_errors + stats.load.load_errors
     )?;
-    writeln!(writer, "  Transactions:   {transactions:>8}")?;
-    writeln!(writer, "  Postings:       {postings:>8}")?;
-    writeln!(writer, "  Accounts:       {accounts:>8}")?;
-    writeln!(writer, "  Commodities:    {:>8}", commodities_set.len())?;
-    writeln!(writer, "  Balances:       {balance_assertions:>8}")?;
-    writeln!(writer, "  Prices:         {prices:>8}")?;
+
+    if stats.load.files.len() > 1 {
+        let root_dir = file
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf));
+        writeln!(writer)?;
+        writeln!(writer, "Files:")?;
+        for file_stats in &stats.load.files {
+            let path = root_dir
+                .as_deref()
+                .and_then(|root| file_stats.path.strip_prefix(root).ok())
+                .unwrap_or(&file_stats.path);
+            writeln!(
+                writer,
+                "  {}  ({} directives, {} parse errors, {:.2} ms)",
+                path.display(),
+                file_stats.directive_count,
+                file_stats.parse_errors,
+                file_stats.parse_ms
+            )?;
+        }
+    }
+
     writeln!(writer)?;
-    writeln!(writer, "Parse errors:     {:>8}", load_result.errors.len())?;
+    write_memory_text(&stats.memory, &stats.interning, writer)
+}
 
+fn write_memory_text<W: Write>(
+    memory: &MemoryUsage,
+    interning: &InternStats,
+    writer: &mut W,
+) -> Result<()> {
+    writeln!(
+        writer,
+        "Memory (approx.): {:>10}",
+        format_bytes(memory.total_bytes() as u64)
+    )?;
+    for (kind, usage) in &memory.by_type {
+        writeln!(
+            writer,
+            "  {:<15} {:>10}  ({} directives)",
+            kind,
+            format_bytes(usage.bytes as u64),
+            usage.count
+        )?;
+    }
+    let strings = &memory.strings;
+    writeln!(
+        writer,
+        "  {:<15} {:>10}  ({} references to {} strings, {} unshared)",
+        "strings",
+        format_bytes(strings.bytes as u64),
+        strings.references,
+        strings.allocations,
+        format_bytes(strings.unshared_bytes as u64)
+    )?;
+    writeln!(
+        writer,
+        "Interner:         {} lookups, {:.1}% hits, {} strings ({})",
+        interning.lookups,
+        interning.hit_rate() * 100.0,
+        interning.strings,
+        format_bytes(interning.bytes)
+    )?;
     Ok(())
 }
 
+/// Format a byte count with a binary unit suffix.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
 fn cmd_display_context<W: Write>(file: &PathBuf, writer: &mut W) -> Result<()> {
     let mut loader = Loader::new();
     let load_result = loader
@@ -1093,3 +1336,877 @@ fn cmd_region<W: Write>(
 
     Ok(())
 }
+
+/// A file reached by following includes from the root file.
+#[derive(Debug, Serialize)]
+struct IncludeNode {
+    path: PathBuf,
+    directives: usize,
+    parse_errors: usize,
+    parse_ms: f64,
+    includes: Vec<PathBuf>,
+}
+
+/// An include directive whose target could not be found.
+#[derive(Debug, Serialize)]
+struct MissingInclude {
+    from: PathBuf,
+    include: String,
+}
+
+/// The include graph of a ledger, plus any problems found while building it.
+#[derive(Debug, Serialize)]
+struct IncludeGraph {
+    root_dir: PathBuf,
+    files: Vec<IncludeNode>,
+    missing: Vec<MissingInclude>,
+    outside_root: Vec<PathBuf>,
+    unused: Vec<PathBuf>,
+}
+
+impl IncludeGraph {
+    fn problem_count(&self) -> usize {
+        self.missing.len() + self.outside_root.len() + self.unused.len()
+    }
+
+    /// Display a path relative to the root directory when possible.
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// Follow includes from `file`, parsing each reachable file once.
+///
+/// Files in the root file's directory tree that are never included are
+/// reported as unused, and included files outside that tree are flagged.
+fn build_include_graph(file: &Path) -> Result<IncludeGraph> {
+    let root_file = file
+        .canonicalize()
+        .with_context(|| format!("failed to resolve {}", file.display()))?;
+    let root_dir = root_file
+        .parent()
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+
+    let mut graph = IncludeGraph {
+        root_dir,
+        files: Vec::new(),
+        missing: Vec::new(),
+        outside_root: Vec::new(),
+        unused: Vec::new(),
+    };
+
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let mut queue: VecDeque<PathBuf> = VecDeque::new();
+    visited.insert(root_file.clone());
+    queue.push_back(root_file);
+
+    while let Some(path) = queue.pop_front() {
+        let source = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        let start = Instant::now();
+        let result = rustledger_parser::parse(&source);
+        let parse_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut includes = Vec::new();
+        for (include_path, _span) in &result.includes {
+            let Ok(canonical) = base_dir.join(include_path).canonicalize() else {
+                graph.missing.push(MissingInclude {
+                    from: path.clone(),
+                    include: include_path.clone(),
+                });
+                continue;
+            };
+
+            if !canonical.starts_with(&graph.root_dir) && !graph.outside_root.contains(&canonical) {
+                graph.outside_root.push(canonical.clone());
+            }
+            if visited.insert(canonical.clone()) {
+                queue.push_back(canonical.clone());
+            }
+            includes.push(canonical);
+        }
+
+        graph.files.push(IncludeNode {
+            path,
+            directives: result.directives.len(),
+            parse_errors: result.errors.len(),
+            parse_ms,
+            includes,
+        });
+    }
+
+    // Ledger files in the root directory that nothing includes
+    for entry in walkdir(&graph.root_dir)? {
+        let entry = entry?;
+        let is_ledger = entry
+            .path()
+            .extension()
+            .is_some_and(|ext| ext == "beancount" || ext == "bean");
+        if !entry.file_type().is_file() || !is_ledger {
+            continue;
+        }
+        let canonical = entry
+            .path()
+            .canonicalize()
+            .unwrap_or_else(|_| entry.path().clone());
+        if !visited.contains(&canonical) {
+            graph.unused.push(canonical);
+        }
+    }
+    graph.unused.sort();
+
+    Ok(graph)
+}
+
+fn cmd_validate_includes<W: Write>(
+    file: &PathBuf,
+    format: GraphFormat,
+    writer: &mut W,
+) -> Result<()> {
+    let graph = build_include_graph(file)?;
+
+    match format {
+        GraphFormat::Text => write_include_graph_text(&graph, writer)?,
+        GraphFormat::Dot => write_include_graph_dot(&graph, writer)?,
+        GraphFormat::Json => writeln!(writer, "{}", serde_json::to_string_pretty(&graph)?)?,
+    }
+
+    // Fail so scripts and CI notice broken include trees
+    let problems = graph.problem_count();
+    if problems > 0 {
+        anyhow::bail!("found {problems} include problems");
+    }
+    Ok(())
+}
+
+fn write_include_graph_text<W: Write>(graph: &IncludeGraph, writer: &mut W) -> Result<()> {
+    writeln!(writer, "Include graph for {}", graph.root_dir.display())?;
+    writeln!(writer, "{}", "=".repeat(60))?;
+    writeln!(writer)?;
+
+    for node in &graph.files {
+        writeln!(
+            writer,
+            "{}  ({} directives, {} parse errors, {:.2} ms)",
+            graph.display_path(&node.path),
+            node.directives,
+            node.parse_errors,
+            node.parse_ms
+        )?;
+        for include in &node.includes {
+            writeln!(writer, "  -> {}", graph.display_path(include))?;
+        }
+    }
+
+    if !graph.missing.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Missing includes:")?;
+        for missing in &graph.missing {
+            writeln!(
+                writer,
+                "  ERROR: {} includes \"{}\", which does not exist",
+                graph.display_path(&missing.from),
+                missing.include
+            )?;
+        }
+    }
+
+    if !graph.outside_root.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Includes outside the ledger directory:")?;
+        for path in &graph.outside_root {
+            writeln!(writer, "  WARNING: {}", path.display())?;
+        }
+    }
+
+    if !graph.unused.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Files never included:")?;
+        for path in &graph.unused {
+            writeln!(writer, "  WARNING: {}", graph.display_path(path))?;
+        }
+    }
+
+    writeln!(writer)?;
+    let problems = graph.problem_count();
+    if problems == 0 {
+        writeln!(writer, "Include validation complete.")?;
+    } else {
+        writeln!(writer, "Found {problems} problems.")?;
+    }
+
+    Ok(())
+}
+
+fn write_include_graph_dot<W: Write>(graph: &IncludeGraph, writer: &mut W) -> Result<()> {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let quote = |s: &str| format!("\"{}\"", escape(s));
+
+    writeln!(writer, "digraph includes {{")?;
+    writeln!(writer, "  node [shape=box];")?;
+    for node in &graph.files {
+        let name = graph.display_path(&node.path);
+        let label = format!("\"{}\\n{} directives\"", escape(&name), node.directives);
+        let style = if graph.outside_root.contains(&node.path) {
+            ", color=orange"
+        } else {
+            ""
+        };
+        writeln!(writer, "  {} [label={label}{style}];", quote(&name))?;
+        for include in &node.includes {
+            writeln!(
+                writer,
+                "  {} -> {};",
+                quote(&name),
+                quote(&graph.display_path(include))
+            )?;
+        }
+    }
+    for missing in &graph.missing {
+        writeln!(
+            writer,
+            "  {} [style=dashed, color=red];",
+            quote(&missing.include)
+        )?;
+        writeln!(
+            writer,
+            "  {} -> {} [style=dashed, color=red];",
+            quote(&graph.display_path(&missing.from)),
+            quote(&missing.include)
+        )?;
+    }
+    for path in &graph.unused {
+        writeln!(
+            writer,
+            "  {} [style=dotted];",
+            quote(&graph.display_path(path))
+        )?;
+    }
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
+
+/// Queries timed by `bench` on every run, alongside any given with `--query`.
+const BENCH_QUERIES: &[&str] = &[
+    "SELECT account, sum(position) GROUP BY account",
+    "SELECT date, narration, account, position WHERE account ~ \"^Expenses\"",
+    "BALANCES",
+];
+
+/// Timing of one benchmarked phase.
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchPhase {
+    name: String,
+    min_ms: f64,
+    mean_ms: f64,
+    max_ms: f64,
+    /// Mean of the same phase in the baseline run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    baseline_ms: Option<f64>,
+    /// Relative change of the mean against the baseline, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    change_pct: Option<f64>,
+}
+
+/// Result of a `bench` run, also read back as a `--baseline`.
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchReport {
+    version: String,
+    file: PathBuf,
+    iterations: usize,
+    directives: usize,
+    phases: Vec<BenchPhase>,
+}
+
+/// Run `run` `iterations` times on fresh input from `setup`, timing only `run`.
+fn bench_phase<S, T>(
+    name: impl Into<String>,
+    iterations: usize,
+    mut setup: impl FnMut() -> S,
+    mut run: impl FnMut(S) -> Result<T>,
+) -> Result<BenchPhase> {
+    let name = name.into();
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let input = setup();
+        let start = Instant::now();
+        let output = run(input).with_context(|| format!("{name} failed"))?;
+        samples.push(start.elapsed().as_secs_f64() * 1000.0);
+        std::hint::black_box(output);
+    }
+
+    let min_ms = samples.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_ms = samples.iter().copied().fold(0.0, f64::max);
+    let mean_ms = samples.iter().sum::<f64>() / samples.len() as f64;
+    Ok(BenchPhase {
+        name,
+        min_ms,
+        mean_ms,
+        max_ms,
+        baseline_ms: None,
+        change_pct: None,
+    })
+}
+
+/// Interpolate every transaction in place, leaving ones that fail untouched.
+fn interpolate_all(directives: &mut [Directive]) {
+    for directive in directives {
+        let Directive::Transaction(txn) = directive else {
+            continue;
+        };
+        if let Ok(result) = interpolate(txn) {
+            *txn = result.transaction;
+        }
+    }
+}
+
+fn run_bench(file: &Path, iterations: usize, queries: &[String]) -> Result<BenchReport> {
+    if iterations == 0 {
+        anyhow::bail!("--iterations must be at least 1");
+    }
+
+    let mut loader = Loader::new();
+    let load_result = loader
+        .load(file)
+        .with_context(|| format!("failed to load {}", file.display()))?;
+    let sources: Vec<_> = load_result
+        .source_map
+        .files()
+        .iter()
+        .map(|f| f.source.clone())
+        .collect();
+    let directives: Vec<Directive> = load_result
+        .directives
+        .iter()
+        .map(|spanned| spanned.value.clone())
+        .collect();
+
+    let mut phases = Vec::new();
+
+    phases.push(bench_phase(
+        "parse",
+        iterations,
+        || (),
+        |()| {
+            Ok(sources
+                .iter()
+                .map(|source| rustledger_parser::parse(source).directives.len())
+                .sum::<usize>())
+        },
+    )?);
+
+    phases.push(bench_phase(
+        "load",
+        iterations,
+        || (),
+        |()| Ok(Loader::new().load(file)?),
+    )?);
+
+    phases.push(bench_phase(
+        "booking",
+        iterations,
+        || directives.clone(),
+        |mut directives| {
+            interpolate_all(&mut directives);
+            Ok(directives)
+        },
+    )?);
+
+    let mut booked = directives;
+    interpolate_all(&mut booked);
+
+    phases.push(bench_phase(
+        "validate",
+        iterations,
+        || (),
+        |()| Ok(validate(&booked)),
+    )?);
+
+    let all_queries = BENCH_QUERIES
+        .iter()
+        .copied()
+        .chain(queries.iter().map(String::as_str));
+    for query_str in all_queries {
+        let query = parse_query(query_str)
+            .with_context(|| format!("failed to parse query: {query_str}"))?;
+        phases.push(bench_phase(
+            format!("query: {query_str}"),
+            iterations,
+            || (),
+            |()| Ok(Executor::new(&booked).execute(&query)?),
+        )?);
+    }
+
+    Ok(BenchReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        file: file.to_path_buf(),
+        iterations,
+        directives: booked.len(),
+        phases,
+    })
+}
+
+/// Fill in baseline means and relative changes for phases present in `baseline`.
+fn compare_bench(report: &mut BenchReport, baseline: &BenchReport) {
+    for phase in &mut report.phases {
+        let Some(base) = baseline.phases.iter().find(|b| b.name == phase.name) else {
+            continue;
+        };
+        phase.baseline_ms = Some(base.mean_ms);
+        if base.mean_ms > 0.0 {
+            phase.change_pct = Some((phase.mean_ms - base.mean_ms) / base.mean_ms * 100.0);
+        }
+    }
+}
+
+fn cmd_bench<W: Write>(
+    file: &Path,
+    iterations: usize,
+    queries: &[String],
+    baseline: Option<&Path>,
+    format: StatsFormat,
+    writer: &mut W,
+) -> Result<()> {
+    let mut report = run_bench(file, iterations, queries)?;
+
+    let baseline_version = match baseline {
+        Some(path) => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let baseline: BenchReport = serde_json::from_str(&contents)
+                .with_context(|| format!("invalid benchmark JSON in {}", path.display()))?;
+            compare_bench(&mut report, &baseline);
+            Some(baseline.version)
+        }
+        None => None,
+    };
+
+    match format {
+        StatsFormat::Text => write_bench_text(&report, baseline_version.as_deref(), writer),
+        StatsFormat::Json => {
+            writeln!(writer, "{}", serde_json::to_string_pretty(&report)?)?;
+            Ok(())
+        }
+    }
+}
+
+fn write_bench_text<W: Write>(
+    report: &BenchReport,
+    baseline_version: Option<&str>,
+    writer: &mut W,
+) -> Result<()> {
+    writeln!(writer, "Benchmark for {}", report.file.display())?;
+    writeln!(writer, "{}", "=".repeat(60))?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "rustledger {}, {} directives, {} runs per phase",
+        report.version, report.directives, report.iterations
+    )?;
+    if let Some(version) = baseline_version {
+        writeln!(writer, "Baseline: rustledger {version}")?;
+    }
+    writeln!(writer)?;
+
+    let name_width = report
+        .phases
+        .iter()
+        .map(|p| p.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Phase".len());
+
+    write!(
+        writer,
+        "{:<name_width$}  {:>10}  {:>10}  {:>10}",
+        "Phase", "Min (ms)", "Mean (ms)", "Max (ms)"
+    )?;
+    if baseline_version.is_some() {
+        write!(writer, "  {:>10}  {:>8}", "Base (ms)", "Change")?;
+    }
+    writeln!(writer)?;
+
+    for phase in &report.phases {
+        write!(
+            writer,
+            "{:<name_width$}  {:>10.2}  {:>10.2}  {:>10.2}",
+            phase.name, phase.min_ms, phase.mean_ms, phase.max_ms
+        )?;
+        if baseline_version.is_some() {
+            let base = phase
+                .baseline_ms
+                .map_or_else(|| "-".to_string(), |ms| format!("{ms:.2}"));
+            let change = phase
+                .change_pct
+                .map_or_else(|| "-".to_string(), |pct| format!("{pct:+.1}%"));
+            write!(writer, "  {base:>10}  {change:>8}")?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn cmd_diff<W: Write>(old: &Path, new: &Path, writer: &mut W) -> Result<()> {
+    let load = |file: &Path| -> Result<Vec<Directive>> {
+        let mut loader = Loader::new();
+        let load_result = loader
+            .load(file)
+            .with_context(|| format!("failed to load {}", file.display()))?;
+        Ok(load_result
+            .directives
+            .into_iter()
+            .map(|spanned| spanned.value)
+            .collect())
+    };
+    let (old, new) = (load(old)?, load(new)?);
+    let changes = rustledger_core::diff_ledgers(&old, &new);
+    crate::report::print_directive_diff(&changes, writer)?;
+
+    let deltas = balance_deltas(&old, &new);
+    if !deltas.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Balance changes:")?;
+        let width = deltas
+            .keys()
+            .map(|(account, _)| account.len())
+            .max()
+            .unwrap_or(0);
+        for ((account, currency), delta) in &deltas {
+            let sign = if delta.is_sign_positive() { "+" } else { "" };
+            let account = account.as_str();
+            writeln!(writer, "  {account:<width$}  {sign}{delta} {currency}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Net change in units held, per account and currency, between two ledgers.
+///
+/// Balances are summed from interpolated transaction postings; amounts
+/// inserted by `pad` directives are not included.
+fn balance_deltas(
+    old: &[Directive],
+    new: &[Directive],
+) -> BTreeMap<(InternedStr, InternedStr), rust_decimal::Decimal> {
+    let mut deltas: BTreeMap<(InternedStr, InternedStr), rust_decimal::Decimal> = BTreeMap::new();
+    for (directives, sign) in [
+        (old, rust_decimal::Decimal::NEGATIVE_ONE),
+        (new, rust_decimal::Decimal::ONE),
+    ] {
+        let mut directives = directives.to_vec();
+        interpolate_all(&mut directives);
+        for directive in &directives {
+            let Directive::Transaction(txn) = directive else {
+                continue;
+            };
+            for posting in &txn.postings {
+                if let Some(amount) = posting.amount() {
+                    *deltas
+                        .entry((posting.account.clone(), amount.currency.clone()))
+                        .or_default() += amount.number * sign;
+                }
+            }
+        }
+    }
+    deltas.retain(|_, delta| !delta.is_zero());
+    deltas
+}
+
+/// A ledger as of a git revision, checked out into a temporary worktree
+/// that is removed again on drop.
+struct GitCheckout {
+    repo: PathBuf,
+    worktree: PathBuf,
+    /// The ledger file inside the worktree.
+    ledger: PathBuf,
+}
+
+impl GitCheckout {
+    fn new(file: &Path, rev: &str) -> Result<Self> {
+        // A per-process counter keeps the directory unique even when the
+        // same revision is checked out twice, and keeps `rev` out of the path.
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let file = file
+            .canonicalize()
+            .with_context(|| format!("failed to find {}", file.display()))?;
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let repo = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+        let relative = file
+            .strip_prefix(repo.canonicalize()?)
+            .with_context(|| format!("{} is not inside {}", file.display(), repo.display()))?
+            .to_path_buf();
+
+        let worktree = std::env::temp_dir().join(format!(
+            "rledger-doctor-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let worktree_arg = worktree.to_string_lossy();
+        git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--detach",
+                "--quiet",
+                &worktree_arg,
+                "--",
+                rev,
+            ],
+        )?;
+        Ok(Self {
+            ledger: worktree.join(relative),
+            repo,
+            worktree,
+        })
+    }
+}
+
+impl Drop for GitCheckout {
+    fn drop(&mut self) {
+        let worktree = self.worktree.to_string_lossy();
+        let _ = git(&self.repo, &["worktree", "remove", "--force", &worktree]);
+    }
+}
+
+/// Run a git command in `dir` and return its standard output.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/valid-ledger.beancount")
+    }
+
+    #[test]
+    fn test_bench_report_phases() {
+        let extra = vec!["SELECT count(*)".to_string()];
+        let report = run_bench(&fixture(), 2, &extra).unwrap();
+
+        let names: Vec<_> = report.phases.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(&names[..4], ["parse", "load", "booking", "validate"]);
+        assert_eq!(names.len(), 4 + BENCH_QUERIES.len() + 1);
+        assert_eq!(names.last(), Some(&"query: SELECT count(*)"));
+        assert!(report.directives > 0);
+        for phase in &report.phases {
+            assert!(phase.min_ms <= phase.mean_ms && phase.mean_ms <= phase.max_ms);
+        }
+    }
+
+    #[test]
+    fn test_bench_baseline_comparison() {
+        let mut report = run_bench(&fixture(), 1, &[]).unwrap();
+        let json = serde_json::to_string(&report).unwrap();
+        let mut baseline: BenchReport = serde_json::from_str(&json).unwrap();
+        baseline.phases.retain(|p| p.name != "validate");
+        for phase in &mut baseline.phases {
+            phase.mean_ms = 2.0;
+        }
+        for phase in &mut report.phases {
+            phase.mean_ms = 3.0;
+        }
+
+        compare_bench(&mut report, &baseline);
+        let parse = &report.phases[0];
+        assert_eq!(parse.baseline_ms, Some(2.0));
+        assert_eq!(parse.change_pct, Some(50.0));
+        let validate = report.phases.iter().find(|p| p.name == "validate").unwrap();
+        assert_eq!(validate.baseline_ms, None);
+
+        let mut out = Vec::new();
+        write_bench_text(&report, Some("0.0.0"), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Baseline: rustledger 0.0.0"));
+        assert!(text.contains("+50.0%"));
+    }
+
+    #[test]
+    fn test_bench_rejects_zero_iterations() {
+        assert!(run_bench(&fixture(), 0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_stats_reports_memory() {
+        let mut out = Vec::new();
+        cmd_stats(&fixture(), StatsFormat::Json, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert!(json["memory"]["by_type"]["transaction"]["bytes"].as_u64() > Some(0));
+        assert!(json["memory"]["strings"]["references"].as_u64() > Some(0));
+        assert!(json["interning"]["lookups"].is_u64());
+
+        let mut out = Vec::new();
+        cmd_stats(&fixture(), StatsFormat::Text, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Memory (approx.):"));
+        assert!(text.contains("Interner:"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_validate_includes_exit_status() {
+        let dir =
+            std::env::temp_dir().join(format!("rledger-doctor-includes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.beancount");
+        fs::write(&main, "include \"accounts.beancount\"\n").unwrap();
+        fs::write(
+            dir.join("accounts.beancount"),
+            "2024-01-01 open Assets:Cash\n",
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        cmd_validate_includes(&main, GraphFormat::Text, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Include validation complete."));
+
+        fs::write(
+            &main,
+            "include \"accounts.beancount\"\ninclude \"missing.beancount\"\n",
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        let err = cmd_validate_includes(&main, GraphFormat::Json, &mut out).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(err.to_string(), "found 1 include problems");
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["missing"][0]["include"], "missing.beancount");
+    }
+
+    #[test]
+    fn test_diff_reports_changes() {
+        let dir = std::env::temp_dir().join(format!("rledger-doctor-diff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("old.beancount");
+        let new = dir.join("new.beancount");
+        fs::write(
+            &old,
+            "2024-01-01 open Assets:Cash\n2024-01-02 close Assets:Cash\n",
+        )
+        .unwrap();
+        fs::write(
+            &new,
+            "2024-01-01 open Assets:Cash USD\n2024-01-03 close Assets:Cash\n",
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        cmd_diff(&old, &new, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("- 2024-01-01 open Assets:Cash\n+ 2024-01-01 open Assets:Cash"));
+        assert!(text.contains("- 2024-01-02 close Assets:Cash"));
+        assert!(text.contains("+ 2024-01-03 close Assets:Cash"));
+        assert!(text.ends_with("1 added, 1 removed, 1 modified\n"));
+
+        let mut out = Vec::new();
+        cmd_diff(&old, &old, &mut out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "No changes\n");
+    }
+
+    const LEDGER: &str = "2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Food
+2024-01-05 * \"Lunch\"
+  Expenses:Food  12.50 USD
+  Assets:Cash
+";
+
+    #[test]
+    fn test_diff_reports_balance_changes() {
+        let dir = std::env::temp_dir().join(format!(
+            "rledger-doctor-diff-balances-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("old.beancount");
+        let new = dir.join("new.beancount");
+        fs::write(&old, LEDGER).unwrap();
+        fs::write(&new, LEDGER.replace("12.50", "15.00")).unwrap();
+
+        let mut out = Vec::new();
+        cmd_diff(&old, &new, &mut out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("0 added, 0 removed, 1 modified\n"));
+        assert!(text.ends_with(
+            "Balance changes:\n  Assets:Cash    -2.50 USD\n  Expenses:Food  +2.50 USD\n"
+        ));
+    }
+
+    #[test]
+    fn test_git_checkout_of_revision() {
+        let dir =
+            std::env::temp_dir().join(format!("rledger-doctor-diff-git-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ledger = dir.join("main.beancount");
+        fs::write(&ledger, LEDGER).unwrap();
+        git(&dir, &["init", "--quiet"]).unwrap();
+        git(&dir, &["add", "main.beancount"]).unwrap();
+        git(
+            &dir,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "ledger",
+            ],
+        )
+        .unwrap();
+        fs::write(&ledger, LEDGER.replace("Lunch", "Dinner")).unwrap();
+
+        let checkout = GitCheckout::new(&ledger, "HEAD").unwrap();
+        assert!(
+            fs::read_to_string(&checkout.ledger)
+                .unwrap()
+                .contains("Lunch")
+        );
+        let worktree = checkout.worktree.clone();
+        let mut out = Vec::new();
+        cmd_diff(&checkout.ledger, &ledger, &mut out).unwrap();
+        drop(checkout);
+        assert!(!worktree.exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("+ 2024-01-05 * \"Dinner\""));
+        // Same postings, so the renamed transaction is a modification
+        assert!(text.contains("0 added, 0 removed, 1 modified"));
+        assert!(!text.contains("Balance changes"));
+    }
+}
@@ -7,16 +7,56 @@
 //!
 //! ```bash
 //! rledger-extract bank.csv --account Assets:Bank:Checking
-//! rledger-extract statement.csv --config bank-config.json
+//! rledger-extract downloads/ --config importers.toml
+//! rledger-extract statements.zip --config importers.toml
+//! rledger-extract statement.qif --plugin qif_importer.wasm
+//! rledger-extract bank.csv --ledger main.beancount
+//! rledger-extract --open-banking bank.toml --since 2024-01-01 --ledger main.beancount
+//! rledger-extract giro.csv --delimiter ";" --decimal-comma --encoding windows-1252
 //! ```
+//!
+//! When extracting from a directory or a zip archive, each file it contains
+//! (recursing into sub-directories and nested archives) is routed to the
+//! first importer declared in the registry config whose `match` patterns
+//! accept it. Without `--config`, `importers.toml` in the current directory
+//! is used. Files are reported by their origin, such as
+//! `statements.zip!2024/jan.csv`, in progress lines and warnings.
+//!
+//! WASM plugins loaded with `--plugin` that export an `extract` entry point
+//! act as importers for formats the built-in importers don't handle: files
+//! no registry importer matches (and non-CSV files in single-file mode) are
+//! offered to each plugin in turn until one identifies them.
+//!
+//! Registry importers can also list `post_process` plugins, native or WASM,
+//! that are run over the directives they extract (see
+//! `rustledger_importer::post_process`).
+//!
+//! With `--ledger`, counter-accounts the importers leave as
+//! `Expenses:Unknown`/`Income:Unknown` are replaced by the account of the
+//! most similar past transaction in that ledger, and transactions whose
+//! bank transaction id already appears in the ledger are dropped.
+//! `--fx-currency` (or `fx_currency` in the registry config) also prices
+//! postings in other currencies at the ledger's rate on the transaction
+//! date.
+//!
+//! When built with the `open-banking` feature, `--open-banking CONFIG`
+//! fetches transactions from a GoCardless/Nordigen-compatible API instead
+//! of reading files (see `rustledger_importer::open_banking` for the config
+//! format).
 
 use crate::cmd::completions::ShellType;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use rustledger_core::{FormatConfig, format_directive};
-use rustledger_importer::ImporterConfig;
+use rustledger_importer::{
+    Categorizer, Deduplicator, FxRates, ImportResult, ImporterConfig, ImporterRegistry,
+    SourceFiles, archive,
+};
+use rustledger_loader::Loader;
+#[cfg(feature = "python-plugin-wasm")]
+use rustledger_plugin::{ExtractInput, PluginManager, wrappers_to_directives};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 /// Extract transactions from bank files.
@@ -28,10 +68,38 @@ struct Args {
     #[arg(long, value_name = "SHELL", hide = true)]
     generate_completions: Option<ShellType>,
 
-    /// The file to extract transactions from
+    /// The file, directory or zip archive to extract transactions from
     #[arg(value_name = "FILE")]
     file: Option<PathBuf>,
 
+    /// Importer registry config (importers.toml) used to route files
+    #[arg(long, value_name = "CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Load a WASM importer plugin (can be specified multiple times)
+    #[cfg(feature = "python-plugin-wasm")]
+    #[arg(long = "plugin", value_name = "WASM_FILE")]
+    plugins: Vec<PathBuf>,
+
+    /// Existing ledger to learn counter-accounts from and skip known transactions
+    #[arg(long, value_name = "LEDGER")]
+    ledger: Option<PathBuf>,
+
+    /// Price postings in other currencies in this currency, at the rate of
+    /// the transaction date from the ledger's price directives
+    #[arg(long, value_name = "CURRENCY", requires = "ledger")]
+    fx_currency: Option<String>,
+
+    /// Fetch transactions from an Open Banking API using this config
+    #[cfg(feature = "open-banking")]
+    #[arg(long, value_name = "CONFIG", conflicts_with = "file")]
+    open_banking: Option<PathBuf>,
+
+    /// Only fetch transactions booked on or after this date (YYYY-MM-DD)
+    #[cfg(feature = "open-banking")]
+    #[arg(long, value_name = "DATE", requires = "open_banking")]
+    since: Option<chrono::NaiveDate>,
+
     /// Target account for imported transactions
     #[arg(short, long, default_value = "Assets:Bank:Checking")]
     account: String,
@@ -56,6 +124,10 @@ struct Args {
     #[arg(long)]
     payee_column: Option<String>,
 
+    /// Transaction reference column, used to skip rows imported before
+    #[arg(long)]
+    id_column: Option<String>,
+
     /// Amount column name or index
     #[arg(long, default_value = "Amount")]
     amount_column: String,
@@ -80,9 +152,21 @@ struct Args {
     #[arg(long)]
     invert_sign: bool,
 
+    /// Amounts use a comma as the decimal separator (e.g. 1.234,56)
+    #[arg(long)]
+    decimal_comma: bool,
+
+    /// File encoding (e.g. windows-1252, iso-8859-15; default: UTF-8)
+    #[arg(long)]
+    encoding: Option<String>,
+
     /// CSV has no header row
     #[arg(long)]
     no_header: bool,
+
+    /// Keep each row's raw text and line number as `source_desc`/`source_line` metadata
+    #[arg(long)]
+    source_metadata: bool,
 }
 
 /// Main entry point for the extract command.
@@ -100,6 +184,17 @@ pub fn main_with_name(bin_name: &str) -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
+    #[cfg(feature = "open-banking")]
+    if let Some(ref config) = args.open_banking {
+        return match run_open_banking(&args, config) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e:#}");
+                ExitCode::from(1)
+            }
+        };
+    }
+
     // File is required when not generating completions
     let Some(ref file) = args.file else {
         eprintln!("error: FILE is required");
@@ -116,8 +211,47 @@ pub fn main_with_name(bin_name: &str) -> ExitCode {
     }
 }
 
+/// Default registry config file looked up when extracting a directory or
+/// archive.
+pub(crate) const DEFAULT_REGISTRY_CONFIG: &str = "importers.toml";
+
 fn run(args: &Args, file: &PathBuf) -> Result<()> {
-    let mut stdout = io::stdout().lock();
+    let plugins = PluginImporters::load(args)?;
+    let history = args.ledger.as_deref().map(History::load).transpose()?;
+    let history = history.as_ref();
+
+    let is_container = file.is_dir() || archive::is_archive(file);
+    let registry_config = args.config.clone().or_else(|| {
+        let default = PathBuf::from(DEFAULT_REGISTRY_CONFIG);
+        (is_container && default.is_file()).then_some(default)
+    });
+
+    if let Some(config_path) = registry_config {
+        let registry = ImporterRegistry::from_config(&config_path)?;
+        return run_registry(&registry, &plugins, history, file);
+    }
+
+    if is_container {
+        anyhow::bail!(
+            "extracting a directory or archive requires --config or ./{DEFAULT_REGISTRY_CONFIG}"
+        );
+    }
+
+    // Offer non-CSV files to importer plugins before the CSV importer
+    let is_csv = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if !is_csv {
+        if let Some((name, result)) = plugins.extract(file)? {
+            eprintln!("{} -> {name}", file.display());
+            return print_result(
+                result,
+                &file.display(),
+                history,
+                args.fx_currency.as_deref(),
+            );
+        }
+    }
 
     // Build the importer configuration
     let mut builder = ImporterConfig::csv()
@@ -130,12 +264,22 @@ fn run(args: &Args, file: &PathBuf) -> Result<()> {
         .delimiter(args.delimiter)
         .skip_rows(args.skip_rows)
         .invert_sign(args.invert_sign)
+        .decimal_comma(args.decimal_comma)
+        .source_metadata(args.source_metadata)
         .has_header(!args.no_header);
 
+    if let Some(encoding) = &args.encoding {
+        builder = builder.encoding(encoding);
+    }
+
     if let Some(payee) = &args.payee_column {
         builder = builder.payee_column(payee);
     }
 
+    if let Some(id) = &args.id_column {
+        builder = builder.id_column(id);
+    }
+
     if let Some(debit) = &args.debit_column {
         builder = builder.debit_column(debit);
     }
@@ -148,10 +292,221 @@ fn run(args: &Args, file: &PathBuf) -> Result<()> {
 
     // Extract transactions
     let result = config.extract(file)?;
+    print_result(
+        result,
+        &file.display(),
+        history,
+        args.fx_currency.as_deref(),
+    )
+}
+
+/// Fetch and print the transactions of every account in an Open Banking config.
+#[cfg(feature = "open-banking")]
+fn run_open_banking(args: &Args, config: &Path) -> Result<()> {
+    use rustledger_importer::{OpenBankingClient, OpenBankingConfig};
+
+    let history = args.ledger.as_deref().map(History::load).transpose()?;
+    let client = OpenBankingClient::connect(OpenBankingConfig::from_file(config)?)?;
+
+    for (mapping, result) in client.extract(args.since)? {
+        print_result(
+            result,
+            &mapping.account,
+            history.as_ref(),
+            args.fx_currency.as_deref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Extract every file routed by an importer registry.
+///
+/// Directories and zip archives are expanded into the files they contain.
+/// A file that fails to extract is reported and skipped, and makes the run
+/// fail once every file has been tried.
+fn run_registry(
+    registry: &ImporterRegistry,
+    plugins: &PluginImporters,
+    history: Option<&History>,
+    path: &Path,
+) -> Result<()> {
+    let files = SourceFiles::collect(path)?;
+    let mut failed = 0;
+
+    for file in &files {
+        let origin = &file.origin;
+        let Some(importer) = registry.identify(&file.path) else {
+            match plugins.extract(&file.path) {
+                Ok(Some((name, result))) => {
+                    eprintln!("{origin} -> {name}");
+                    print_result(result, origin, history, None)?;
+                }
+                Ok(None) => eprintln!("skipping {origin}: no matching importer"),
+                Err(e) => {
+                    eprintln!("error: {origin}: {e:#}");
+                    failed += 1;
+                }
+            }
+            continue;
+        };
+
+        eprintln!("{origin} -> {}", importer.name());
+        match importer.extract(&file.path) {
+            Ok(result) => {
+                print_result(result, origin, history, importer.fx_currency())?;
+            }
+            Err(e) => {
+                eprintln!("error: {origin}: {e:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    // Keep going past a bad file, but still fail the run
+    if failed > 0 {
+        anyhow::bail!("{failed} file(s) could not be extracted");
+    }
+    Ok(())
+}
+
+/// WASM plugins that can act as importers.
+#[cfg(feature = "python-plugin-wasm")]
+struct PluginImporters(PluginManager);
+
+/// Placeholder when WASM plugin support is compiled out.
+#[cfg(not(feature = "python-plugin-wasm"))]
+struct PluginImporters;
+
+impl PluginImporters {
+    /// Load the plugins given with `--plugin`.
+    #[cfg(feature = "python-plugin-wasm")]
+    fn load(args: &Args) -> Result<Self> {
+        let mut manager = PluginManager::new();
+        for path in &args.plugins {
+            manager
+                .load(path)
+                .with_context(|| format!("failed to load plugin {}", path.display()))?;
+        }
+        Ok(Self(manager))
+    }
+
+    #[cfg(not(feature = "python-plugin-wasm"))]
+    #[allow(clippy::unnecessary_wraps)]
+    const fn load(_args: &Args) -> Result<Self> {
+        Ok(Self)
+    }
+
+    /// Offer a file to the importer plugins.
+    ///
+    /// Returns the name of the plugin that identified the file and the
+    /// extracted directives, or `None` if no plugin claims it.
+    #[cfg(feature = "python-plugin-wasm")]
+    fn extract(&self, file: &Path) -> Result<Option<(String, ImportResult)>> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+
+        let input = ExtractInput {
+            filename: file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            contents: std::fs::read(file)?,
+            config: None,
+        };
+
+        let Some((name, output)) = self.0.extract(&input)? else {
+            return Ok(None);
+        };
+
+        let directives = wrappers_to_directives(&output.directives)
+            .with_context(|| format!("plugin {name} returned invalid directives"))?;
+        let mut result = ImportResult::new(directives);
+        for error in output.errors {
+            result = result.with_warning(format!("{name}: {}", error.message));
+        }
+
+        Ok(Some((name.to_string(), result)))
+    }
+
+    #[cfg(not(feature = "python-plugin-wasm"))]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    const fn extract(&self, _file: &Path) -> Result<Option<(String, ImportResult)>> {
+        Ok(None)
+    }
+}
+
+/// Recursively collect the files below a directory.
+pub(crate) fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// What is learned from an existing ledger given with `--ledger`.
+struct History {
+    categorizer: Categorizer,
+    deduplicator: Deduplicator,
+    rates: FxRates,
+}
+
+impl History {
+    /// Learn counter-accounts, known transaction ids and exchange rates
+    /// from a ledger.
+    fn load(ledger: &Path) -> Result<Self> {
+        let result = Loader::new()
+            .load(ledger)
+            .with_context(|| format!("failed to load {}", ledger.display()))?;
+        let directives: Vec<_> = result.directives.into_iter().map(|d| d.value).collect();
+        Ok(Self {
+            categorizer: Categorizer::from_directives(&directives),
+            deduplicator: Deduplicator::from_directives(&directives),
+            rates: FxRates::from_directives(&directives),
+        })
+    }
+}
+
+/// Print warnings and extracted directives in beancount format.
+///
+/// Postings in currencies other than `fx_currency` are priced in it when
+/// a ledger was given.
+fn print_result(
+    mut result: ImportResult,
+    source: &dyn std::fmt::Display,
+    history: Option<&History>,
+    fx_currency: Option<&str>,
+) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+
+    if let Some(history) = history {
+        let removed = history
+            .deduplicator
+            .remove_duplicates(&mut result.directives);
+        if removed > 0 {
+            eprintln!("Skipped {removed} transactions already in the ledger");
+        }
+        let count = history.categorizer.categorize(&mut result.directives);
+        eprintln!("Categorized {count} transactions from ledger history");
+        if let Some(currency) = fx_currency {
+            let count = history.rates.annotate(&mut result, currency);
+            eprintln!("Priced {count} postings in {currency} from ledger prices");
+        }
+    } else if let Some(currency) = fx_currency {
+        result = result.with_warning(format!(
+            "not pricing postings in {currency}: exchange rates need --ledger"
+        ));
+    }
 
     // Print warnings
     for warning in &result.warnings {
-        eprintln!("warning: {warning}");
+        eprintln!("warning: {source}: {warning}");
     }
 
     // Print extracted directives in beancount format
@@ -164,7 +519,7 @@ fn run(args: &Args, file: &PathBuf) -> Result<()> {
     eprintln!(
         "Extracted {} transactions from {}",
         result.directives.len(),
-        file.display()
+        source
     );
 
     Ok(())
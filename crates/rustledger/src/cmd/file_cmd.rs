@@ -0,0 +1,147 @@
+//! rledger-file - File downloaded statements into the documents tree.
+//!
+//! The rustledger equivalent of `bean-file`: every file claimed by an
+//! importer is moved to `<documents>/<account>/YYYY-MM-DD.<name>`, where the
+//! account comes from the importer and the date from the statement. A
+//! `document` directive for each filed statement is printed to stdout.
+//!
+//! # Usage
+//!
+//! ```bash
+//! rledger-file downloads/ --output documents/
+//! rledger-file downloads/ -o documents/ --config importers.toml --dry-run
+//! ```
+//!
+//! Files no importer claims are left where they are. Existing files in the
+//! documents tree are never overwritten.
+
+use crate::cmd::completions::ShellType;
+use crate::cmd::identify_cmd::{downloaded_files, load_registry};
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use rustledger_core::{Directive, FormatConfig, format_directive};
+use rustledger_importer::filing::{self, FiledDocument};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// File downloaded statements into the documents tree.
+#[derive(Parser, Debug)]
+#[command(name = "rledger-file")]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Generate shell completions and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    generate_completions: Option<ShellType>,
+
+    /// Files or directories of downloaded files
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Root of the documents tree to file into
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+
+    /// Importer registry config (importers.toml)
+    #[arg(long, value_name = "CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Show where files would go without moving them
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+/// Main entry point for the file command.
+pub fn main() -> ExitCode {
+    main_with_name("rledger-file")
+}
+
+/// Main entry point with custom binary name (for bean-file compatibility).
+pub fn main_with_name(bin_name: &str) -> ExitCode {
+    let args = Args::parse();
+
+    if let Some(shell) = args.generate_completions {
+        crate::cmd::completions::generate_completions::<Args>(shell, bin_name);
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(ref output) = args.output else {
+        eprintln!("error: --output is required");
+        eprintln!("For more information, try '--help'");
+        return ExitCode::from(2);
+    };
+    if args.paths.is_empty() {
+        eprintln!("error: PATH is required");
+        eprintln!("For more information, try '--help'");
+        return ExitCode::from(2);
+    }
+
+    match run(&args, output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run(args: &Args, documents: &Path) -> Result<()> {
+    let registry = load_registry(args.config.as_deref())?;
+    let fmt_config = FormatConfig::default();
+    let mut stdout = io::stdout().lock();
+    let mut filed = 0;
+
+    for file in downloaded_files(&args.paths)? {
+        let Some(importer) = registry.identify(&file) else {
+            eprintln!("skipping {}: no matching importer", file.display());
+            continue;
+        };
+
+        let plan = match filing::plan(importer.as_ref(), &file, documents) {
+            Ok(plan) => plan,
+            Err(e) => {
+                eprintln!("error: {}: {e:#}", file.display());
+                continue;
+            }
+        };
+
+        eprintln!("{} -> {}", file.display(), plan.destination.display());
+        if !args.dry_run {
+            move_file(&plan)?;
+        }
+
+        let directive = Directive::Document(plan.document);
+        write!(stdout, "{}", format_directive(&directive, &fmt_config))?;
+        filed += 1;
+    }
+
+    let verb = if args.dry_run { "Would file" } else { "Filed" };
+    eprintln!("{verb} {filed} documents into {}", documents.display());
+    Ok(())
+}
+
+/// Move a file to its destination, creating account directories as needed.
+fn move_file(plan: &FiledDocument) -> Result<()> {
+    if plan.destination.exists() {
+        bail!("{} already exists", plan.destination.display());
+    }
+    if let Some(parent) = plan.destination.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    // A rename fails across filesystems; fall back to copy and remove
+    if fs::rename(&plan.source, &plan.destination).is_err() {
+        fs::copy(&plan.source, &plan.destination).with_context(|| {
+            format!(
+                "failed to copy {} to {}",
+                plan.source.display(),
+                plan.destination.display()
+            )
+        })?;
+        fs::remove_file(&plan.source)
+            .with_context(|| format!("failed to remove {}", plan.source.display()))?;
+    }
+    Ok(())
+}
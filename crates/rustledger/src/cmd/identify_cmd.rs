@@ -0,0 +1,115 @@
+//! rledger-identify - Show which importer claims each downloaded file.
+//!
+//! The rustledger equivalent of `bean-identify`: walks a downloads directory
+//! and reports, for every file, the first importer in the registry config
+//! whose `match` patterns accept it and the account it imports into.
+//!
+//! # Usage
+//!
+//! ```bash
+//! rledger-identify downloads/
+//! rledger-identify downloads/ --config importers.toml
+//! ```
+//!
+//! Without `--config`, `importers.toml` in the current directory is used.
+
+use crate::cmd::completions::ShellType;
+use crate::cmd::extract_cmd::{DEFAULT_REGISTRY_CONFIG, collect_files};
+use anyhow::{Context, Result};
+use clap::Parser;
+use rustledger_importer::ImporterRegistry;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Show which importer claims each downloaded file.
+#[derive(Parser, Debug)]
+#[command(name = "rledger-identify")]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Generate shell completions and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    generate_completions: Option<ShellType>,
+
+    /// Files or directories of downloaded files
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Importer registry config (importers.toml)
+    #[arg(long, value_name = "CONFIG")]
+    config: Option<PathBuf>,
+}
+
+/// Main entry point for the identify command.
+pub fn main() -> ExitCode {
+    main_with_name("rledger-identify")
+}
+
+/// Main entry point with custom binary name (for bean-identify compatibility).
+pub fn main_with_name(bin_name: &str) -> ExitCode {
+    let args = Args::parse();
+
+    if let Some(shell) = args.generate_completions {
+        crate::cmd::completions::generate_completions::<Args>(shell, bin_name);
+        return ExitCode::SUCCESS;
+    }
+
+    if args.paths.is_empty() {
+        eprintln!("error: PATH is required");
+        eprintln!("For more information, try '--help'");
+        return ExitCode::from(2);
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<()> {
+    let registry = load_registry(args.config.as_deref())?;
+    let mut stdout = io::stdout().lock();
+
+    for file in downloaded_files(&args.paths)? {
+        match registry.identify(&file) {
+            Some(importer) => {
+                let account = importer.account(&file).unwrap_or("-");
+                writeln!(
+                    stdout,
+                    "{}: {} ({account})",
+                    file.display(),
+                    importer.name()
+                )?;
+            }
+            None => writeln!(stdout, "{}: no matching importer", file.display())?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the importer registry from `config`, or `./importers.toml`.
+pub(crate) fn load_registry(config: Option<&Path>) -> Result<ImporterRegistry> {
+    let config = config.unwrap_or_else(|| Path::new(DEFAULT_REGISTRY_CONFIG));
+    ImporterRegistry::from_config(config)
+        .with_context(|| format!("failed to load importer config {}", config.display()))
+}
+
+/// Expand directories into the files below them, in sorted order.
+pub(crate) fn downloaded_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            collect_files(path, &mut found)?;
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,379 @@
+//! rledger-init - Scaffold a new ledger directory.
+//!
+//! Creates a ready-to-use layout for a new ledger:
+//!
+//! ```text
+//! ledger/
+//!   main.beancount        options and includes
+//!   accounts.beancount    account openings and opening balances
+//!   2024/journal.beancount
+//!   documents/            statements, see `rledger-file`
+//!   importers.toml        importer registry template, see `rledger-extract`
+//!   .gitignore
+//! ```
+//!
+//! Opening balances are recorded with `pad` directives against
+//! `Equity:Opening-Balances`, followed by a `balance` assertion on the first
+//! day of the ledger.
+//!
+//! # Usage
+//!
+//! ```bash
+//! rledger-init ledger/
+//! rledger-init ledger/ --yes --currency EUR --opening-balance Assets:Bank:Checking=1250.00
+//! ```
+//!
+//! When run in a terminal, settings not given on the command line are asked
+//! for; `--yes` uses the defaults instead. Existing files are never
+//! overwritten.
+
+use crate::cmd::completions::ShellType;
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use clap::Parser;
+use rust_decimal::Decimal;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+/// Account opening balances are padded from.
+const OPENING_BALANCES_ACCOUNT: &str = "Equity:Opening-Balances";
+
+/// Scaffold a new ledger directory.
+#[derive(Parser, Debug)]
+#[command(name = "rledger-init")]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Generate shell completions and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    generate_completions: Option<ShellType>,
+
+    /// Directory to create the ledger in
+    #[arg(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Title of the ledger
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Operating currency
+    #[arg(long, value_name = "CURRENCY")]
+    currency: Option<String>,
+
+    /// First year of the ledger (default: this year)
+    #[arg(long)]
+    year: Option<i32>,
+
+    /// Opening balance of an account in the operating currency (repeatable)
+    #[arg(long = "opening-balance", value_name = "ACCOUNT=AMOUNT")]
+    opening_balances: Vec<String>,
+
+    /// Use defaults for anything not given instead of asking
+    #[arg(short, long)]
+    yes: bool,
+}
+
+/// Settings of a new ledger.
+#[derive(Debug, Clone)]
+struct LedgerSettings {
+    title: String,
+    currency: String,
+    year: i32,
+    opening_balances: Vec<(String, Decimal)>,
+}
+
+/// Main entry point for the init command.
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if let Some(shell) = args.generate_completions {
+        crate::cmd::completions::generate_completions::<Args>(shell, "rledger-init");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<()> {
+    let mut opening_balances = args
+        .opening_balances
+        .iter()
+        .map(|arg| {
+            let (account, amount) = arg
+                .split_once('=')
+                .with_context(|| format!("expected ACCOUNT=AMOUNT, got '{arg}'"))?;
+            Ok((account.trim().to_string(), parse_amount(amount)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let interactive = !args.yes && io::stdin().is_terminal();
+    let mut prompt = Prompt::new(interactive);
+
+    let title = match &args.title {
+        Some(title) => title.clone(),
+        None => prompt.ask("Ledger title", "Personal Finances")?,
+    };
+    let currency = match &args.currency {
+        Some(currency) => currency.clone(),
+        None => prompt.ask("Operating currency", "USD")?,
+    };
+    let year = if let Some(year) = args.year {
+        year
+    } else {
+        let this_year = Local::now().year().to_string();
+        let answer = prompt.ask("First year of the ledger", &this_year)?;
+        answer
+            .parse()
+            .with_context(|| format!("invalid year: {answer}"))?
+    };
+    if args.opening_balances.is_empty() {
+        loop {
+            let account = prompt.ask("Account with an opening balance (blank to finish)", "")?;
+            if account.is_empty() {
+                break;
+            }
+            let amount = prompt.ask(&format!("Opening balance of {account} in {currency}"), "")?;
+            opening_balances.push((account, parse_amount(&amount)?));
+        }
+    }
+
+    let settings = LedgerSettings {
+        title,
+        currency,
+        year,
+        opening_balances,
+    };
+    for file in scaffold(&args.dir, &settings)? {
+        eprintln!("created {}", file.display());
+    }
+    eprintln!(
+        "\nCheck the new ledger with: rledger-check {}",
+        args.dir.join("main.beancount").display()
+    );
+    Ok(())
+}
+
+fn parse_amount(amount: &str) -> Result<Decimal> {
+    Decimal::from_str(amount.trim()).with_context(|| format!("invalid amount: {amount}"))
+}
+
+/// Line-based prompts on stdin, answering with defaults when not
+/// interactive.
+struct Prompt {
+    interactive: bool,
+}
+
+impl Prompt {
+    const fn new(interactive: bool) -> Self {
+        Self { interactive }
+    }
+
+    /// Ask a question, returning `default` for an empty answer.
+    fn ask(&mut self, question: &str, default: &str) -> Result<String> {
+        if !self.interactive {
+            return Ok(default.to_string());
+        }
+        let mut stderr = io::stderr().lock();
+        if default.is_empty() {
+            write!(stderr, "{question}: ")?;
+        } else {
+            write!(stderr, "{question} [{default}]: ")?;
+        }
+        stderr.flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            // End of input: stop asking
+            self.interactive = false;
+        }
+        let answer = answer.trim();
+        Ok(if answer.is_empty() { default } else { answer }.to_string())
+    }
+}
+
+/// Write the files of a new ledger into `dir`, returning their paths.
+///
+/// Fails without writing anything if any of the files already exists.
+fn scaffold(dir: &Path, settings: &LedgerSettings) -> Result<Vec<PathBuf>> {
+    for (account, _) in &settings.opening_balances {
+        if !is_valid_account(account) {
+            bail!("invalid account name: {account}");
+        }
+    }
+
+    let journal = format!("{}/journal.beancount", settings.year);
+    let files = [
+        ("main.beancount", main_file(settings, &journal)),
+        ("accounts.beancount", accounts_file(settings)),
+        (journal.as_str(), journal_file(settings)),
+        ("importers.toml", IMPORTERS_TEMPLATE.to_string()),
+        (".gitignore", GITIGNORE.to_string()),
+        ("documents/.gitkeep", String::new()),
+    ];
+
+    let paths: Vec<PathBuf> = files.iter().map(|(name, _)| dir.join(name)).collect();
+    if let Some(existing) = paths.iter().find(|path| path.exists()) {
+        bail!("{} already exists", existing.display());
+    }
+
+    for (path, (_, contents)) in paths.iter().zip(&files) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(paths)
+}
+
+/// Whether `account` is a valid account name, e.g. `Assets:Bank:Checking`.
+fn is_valid_account(account: &str) -> bool {
+    let result = rustledger_parser::parse(&format!("2000-01-01 open {account}\n"));
+    result.errors.is_empty() && result.directives.len() == 1
+}
+
+/// First day of the ledger.
+fn start_date(settings: &LedgerSettings) -> NaiveDate {
+    NaiveDate::from_ymd_opt(settings.year, 1, 1).unwrap_or(NaiveDate::MIN)
+}
+
+fn main_file(settings: &LedgerSettings, journal: &str) -> String {
+    format!(
+        r#";; -*- mode: beancount -*-
+
+option "title" "{title}"
+option "operating_currency" "{currency}"
+option "documents" "documents"
+
+include "accounts.beancount"
+include "{journal}"
+"#,
+        title = settings.title.replace('"', "'"),
+        currency = settings.currency,
+    )
+}
+
+fn accounts_file(settings: &LedgerSettings) -> String {
+    // Opening balances are padded the day before the ledger starts and
+    // asserted on its first day
+    let start = start_date(settings);
+    let day_before = start - Duration::days(1);
+    let currency = &settings.currency;
+
+    let mut out = String::from(";; Accounts\n\n");
+    out.push_str(&format!("{day_before} open {OPENING_BALANCES_ACCOUNT}\n"));
+    for (account, _) in &settings.opening_balances {
+        out.push_str(&format!("{day_before} open {account} {currency}\n"));
+    }
+
+    if !settings.opening_balances.is_empty() {
+        out.push_str("\n;; Opening balances\n\n");
+        for (account, _) in &settings.opening_balances {
+            out.push_str(&format!(
+                "{day_before} pad {account} {OPENING_BALANCES_ACCOUNT}\n"
+            ));
+        }
+        out.push('\n');
+        for (account, amount) in &settings.opening_balances {
+            out.push_str(&format!("{start} balance {account} {amount} {currency}\n"));
+        }
+    }
+    out
+}
+
+fn journal_file(settings: &LedgerSettings) -> String {
+    format!(
+        ";; Transactions of {year}\n\n\
+         ;; {year}-01-15 * \"Payee\" \"Description\"\n\
+         ;;   Expenses:Groceries   42.00 {currency}\n\
+         ;;   Assets:Bank:Checking\n",
+        year = settings.year,
+        currency = settings.currency,
+    )
+}
+
+const IMPORTERS_TEMPLATE: &str = r#"# Importer registry for rledger-extract, rledger-identify and rledger-file.
+#
+# Importers are tried in order; the first whose `match` patterns accept a
+# downloaded file's name is used. Uncomment and adapt:
+#
+# [[importer]]
+# name = "checking"
+# type = "csv"
+# match = ["*checking*.csv"]
+# account = "Assets:Bank:Checking"
+# currency = "USD"
+#
+# [importer.options]
+# date_column = "Date"
+# date_format = "%Y-%m-%d"
+# narration_column = "Description"
+# amount_column = "Amount"
+#
+# [[importer.rules]]
+# pattern = "GROCERY"
+# account = "Expenses:Groceries"
+"#;
+
+const GITIGNORE: &str =
+    "# Loader cache files\n*.cache\n\n# Statements waiting to be imported\ndownloads/\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn settings() -> LedgerSettings {
+        LedgerSettings {
+            title: "Test \"Ledger\"".to_string(),
+            currency: "EUR".to_string(),
+            year: 2024,
+            opening_balances: vec![
+                ("Assets:Bank:Checking".to_string(), dec!(1250.00)),
+                ("Liabilities:CreditCard".to_string(), dec!(-300)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_scaffold_loads_cleanly() {
+        let dir = std::env::temp_dir().join(format!("rledger-init-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let files = scaffold(&dir, &settings()).unwrap();
+        assert_eq!(files.len(), 6);
+        assert!(dir.join("2024/journal.beancount").exists());
+        assert!(dir.join("documents").is_dir());
+
+        let result = rustledger_loader::load(&dir.join("main.beancount")).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.options.title.as_deref(), Some("Test 'Ledger'"));
+        let directives: Vec<_> = result.directives.into_iter().map(|d| d.value).collect();
+        let errors = rustledger_validate::validate(&directives);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        // Never overwrite an existing ledger
+        let err = scaffold(&dir, &settings()).unwrap_err().to_string();
+        assert!(err.contains("already exists"), "{err}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_account_rejected() {
+        let mut settings = settings();
+        settings.opening_balances = vec![("bank".to_string(), dec!(1))];
+        let dir = std::env::temp_dir().join("rledger-init-test-invalid");
+        let err = scaffold(&dir, &settings).unwrap_err().to_string();
+        assert_eq!(err, "invalid account name: bank");
+        assert!(!dir.exists());
+    }
+}
@@ -7,7 +7,10 @@ pub mod check;
 pub mod completions;
 pub mod doctor;
 pub mod extract_cmd;
+pub mod file_cmd;
 pub mod format;
+pub mod identify_cmd;
+pub mod init_cmd;
 pub mod price_cmd;
 pub mod query;
 pub mod report_cmd;
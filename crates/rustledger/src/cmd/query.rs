@@ -8,15 +8,22 @@
 //! ```bash
 //! rledger-query ledger.beancount "SELECT account, SUM(position) GROUP BY account"
 //! rledger-query ledger.beancount -F query.bql
+//! rledger-query ledger.beancount "EXPORT TO LEDGER FROM year = 2024" > 2024.ledger
 //! rledger-query ledger.beancount  # Interactive mode
 //! ```
+//!
+//! WASM plugins given with `--plugin` can provide query functions, called
+//! by plugin name: `rledger-query --plugin shops.wasm ledger.beancount
+//! "SELECT shops.category(payee), SUM(position) GROUP BY 1"`.
 
 use crate::cmd::completions::ShellType;
 use anyhow::{Context, Result};
 use clap::Parser;
-use rustledger_core::Directive;
+use rustledger_core::{BookingMethod, Directive};
 use rustledger_loader::Loader;
-use rustledger_query::{Executor, Value, parse as parse_query};
+#[cfg(feature = "python-plugin-wasm")]
+use rustledger_plugin::{FunctionInput, FunctionValue, Plugin, RuntimeConfig};
+use rustledger_query::{Executor, Query, Value, parse as parse_query};
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
 use rustyline::{DefaultEditor, Editor};
@@ -50,14 +57,21 @@ struct Args {
     #[arg(short = 'o', long, value_name = "OUTPUT_FILE")]
     output: Option<PathBuf>,
 
-    /// Output format (text, csv, json, beancount)
+    /// Output format (text/table, csv, json, beancount)
     #[arg(short = 'f', long, default_value = "text")]
     format: OutputFormat,
 
-    /// Numberify output (remove currencies, output raw numbers)
+    /// Numberify output: split amount columns into one number column per
+    /// currency, for spreadsheet import
     #[arg(short = 'm', long)]
     numberify: bool,
 
+    /// Load a WASM plugin providing query functions (can be specified
+    /// multiple times)
+    #[cfg(feature = "python-plugin-wasm")]
+    #[arg(long = "plugin", value_name = "WASM_FILE")]
+    plugins: Vec<PathBuf>,
+
     /// Do not report ledger validation errors on load
     #[arg(short = 'q', long = "no-errors")]
     no_errors: bool,
@@ -69,6 +83,7 @@ struct Args {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 enum OutputFormat {
+    #[value(alias = "table")]
     Text,
     Csv,
     Json,
@@ -143,6 +158,11 @@ fn run(args: &Args) -> Result<()> {
     if args.verbose {
         eprintln!("Loaded {} directives", directives.len());
     }
+    let booking_method = load_result
+        .options
+        .booking_method
+        .parse()
+        .unwrap_or_default();
 
     // Determine query source
     let query_str = if !args.query.is_empty() {
@@ -152,11 +172,11 @@ fn run(args: &Args) -> Result<()> {
             .with_context(|| format!("failed to read query file {}", query_file.display()))?
     } else {
         // Interactive mode
-        return run_interactive(file, &directives, args);
+        return run_interactive(file, &directives, args, booking_method);
     };
 
     // Execute the query
-    let settings = ShellSettings::from_args(args);
+    let settings = ShellSettings::from_args(args, booking_method)?;
     execute_query(&query_str, &directives, &settings, &mut io::stdout())
 }
 
@@ -166,16 +186,21 @@ struct ShellSettings {
     numberify: bool,
     pager: bool,
     output_file: Option<PathBuf>,
+    functions: PluginFunctions,
+    /// The ledger's `option "booking_method"`.
+    booking_method: BookingMethod,
 }
 
 impl ShellSettings {
-    fn from_args(args: &Args) -> Self {
-        Self {
+    fn from_args(args: &Args, booking_method: BookingMethod) -> Result<Self> {
+        Ok(Self {
             format: args.format,
             numberify: args.numberify,
             pager: true,
             output_file: args.output.clone(),
-        }
+            functions: PluginFunctions::load(args)?,
+            booking_method,
+        })
     }
 }
 
@@ -190,14 +215,27 @@ fn execute_query<W: Write>(
 
     // Execute
     let mut executor = Executor::new(directives);
+    executor.set_booking_method(settings.booking_method);
+    settings.functions.register(&mut executor);
     let result = executor
         .execute(&query)
         .with_context(|| "failed to execute query")?;
 
-    // Output results
+    // Output results; EXPORT entries are already in their target syntax
+    if matches!(query, Query::Export(_)) {
+        return write_beancount(&result, writer);
+    }
+
+    // Beancount output keeps amounts intact
+    let result = if settings.numberify && settings.format != OutputFormat::Beancount {
+        result.numberify()
+    } else {
+        result
+    };
+
     match settings.format {
-        OutputFormat::Text => write_text(&result, writer, settings.numberify)?,
-        OutputFormat::Csv => write_csv(&result, writer, settings.numberify)?,
+        OutputFormat::Text => write_text(&result, writer)?,
+        OutputFormat::Csv => write_csv(&result, writer)?,
         OutputFormat::Json => write_json(&result, writer)?,
         OutputFormat::Beancount => write_beancount(&result, writer)?,
     }
@@ -205,11 +243,145 @@ fn execute_query<W: Write>(
     Ok(())
 }
 
-fn write_text<W: Write>(
-    result: &rustledger_query::QueryResult,
-    writer: &mut W,
-    numberify: bool,
-) -> Result<()> {
+/// Query functions provided by the plugins given with `--plugin`.
+#[derive(Default)]
+struct PluginFunctions {
+    #[cfg(feature = "python-plugin-wasm")]
+    functions: Vec<PluginFunction>,
+}
+
+impl PluginFunctions {
+    /// Load the plugins and list their functions.
+    #[cfg(feature = "python-plugin-wasm")]
+    fn load(args: &Args) -> Result<Self> {
+        let config = RuntimeConfig::default();
+        let mut functions = Vec::new();
+        for path in &args.plugins {
+            let plugin = Plugin::load(path, &config)
+                .with_context(|| format!("failed to load plugin {}", path.display()))?;
+            let plugin = std::rc::Rc::new(plugin);
+            for function in plugin.functions(&config)? {
+                functions.push(PluginFunction {
+                    plugin: plugin.clone(),
+                    function,
+                });
+            }
+        }
+        Ok(Self { functions })
+    }
+
+    #[cfg(not(feature = "python-plugin-wasm"))]
+    #[allow(clippy::unnecessary_wraps)]
+    fn load(_args: &Args) -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    /// Make the functions callable as `plugin.function(...)`.
+    #[cfg(feature = "python-plugin-wasm")]
+    fn register(&self, executor: &mut Executor<'_>) {
+        for function in &self.functions {
+            let name = format!("{}.{}", function.plugin.name(), function.function);
+            executor.register_function(name, function.clone());
+        }
+    }
+
+    #[cfg(not(feature = "python-plugin-wasm"))]
+    const fn register(&self, _executor: &mut Executor<'_>) {}
+}
+
+/// A function of a WASM plugin, called once per batch of rows.
+#[cfg(feature = "python-plugin-wasm")]
+#[derive(Clone)]
+struct PluginFunction {
+    plugin: std::rc::Rc<Plugin>,
+    function: String,
+}
+
+#[cfg(feature = "python-plugin-wasm")]
+impl rustledger_query::ScalarFunction for PluginFunction {
+    fn call(&self, args: &[Value]) -> Result<Value, rustledger_query::QueryError> {
+        let mut values = self.call_batch(&[args.to_vec()])?;
+        Ok(values.pop().unwrap_or(Value::Null))
+    }
+
+    fn call_batch(&self, rows: &[Vec<Value>]) -> Result<Vec<Value>, rustledger_query::QueryError> {
+        use rustledger_query::QueryError;
+
+        let input = FunctionInput {
+            function: self.function.clone(),
+            rows: rows
+                .iter()
+                .map(|args| args.iter().map(to_function_value).collect())
+                .collect(),
+        };
+        let output = self
+            .plugin
+            .call_function(&input, &RuntimeConfig::default())
+            .map_err(|e| QueryError::Evaluation(format!("{e:#}")))?;
+        output
+            .values
+            .into_iter()
+            .map(|value| {
+                from_function_value(value).map_err(|e| {
+                    QueryError::Evaluation(format!("{}.{}: {e}", self.plugin.name(), self.function))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Convert a query value for passing to a plugin.
+#[cfg(feature = "python-plugin-wasm")]
+fn to_function_value(value: &Value) -> FunctionValue {
+    use rustledger_plugin::types::AmountData;
+
+    let amount = |amount: &rustledger_core::Amount| {
+        FunctionValue::Amount(AmountData {
+            number: amount.number.to_string(),
+            currency: amount.currency.to_string(),
+        })
+    };
+    match value {
+        Value::String(s) => FunctionValue::String(s.clone()),
+        Value::Number(n) => FunctionValue::Number(n.to_string()),
+        Value::Integer(i) => FunctionValue::Integer(*i),
+        Value::Date(d) => FunctionValue::Date(d.to_string()),
+        Value::Boolean(b) => FunctionValue::Boolean(*b),
+        Value::Amount(a) => amount(a),
+        Value::Position(p) => amount(&p.units),
+        // Inventories have no single amount; pass them as text
+        Value::Inventory(_) => FunctionValue::String(format_value(value)),
+        Value::StringSet(set) => FunctionValue::StringSet(set.clone()),
+        Value::Null => FunctionValue::Null,
+    }
+}
+
+/// Convert a value returned by a plugin.
+#[cfg(feature = "python-plugin-wasm")]
+fn from_function_value(value: FunctionValue) -> Result<Value, String> {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    let number =
+        |s: &str| Decimal::from_str(s).map_err(|_| format!("invalid number returned: {s}"));
+    Ok(match value {
+        FunctionValue::Null => Value::Null,
+        FunctionValue::Boolean(b) => Value::Boolean(b),
+        FunctionValue::Integer(i) => Value::Integer(i),
+        FunctionValue::Number(n) => Value::Number(number(&n)?),
+        FunctionValue::String(s) => Value::String(s),
+        FunctionValue::Date(d) => Value::Date(
+            rustledger_core::NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+                .map_err(|_| format!("invalid date returned: {d}"))?,
+        ),
+        FunctionValue::Amount(a) => {
+            Value::Amount(rustledger_core::Amount::new(number(&a.number)?, a.currency))
+        }
+        FunctionValue::StringSet(set) => Value::StringSet(set),
+    })
+}
+
+fn write_text<W: Write>(result: &rustledger_query::QueryResult, writer: &mut W) -> Result<()> {
     if result.columns.is_empty() {
         return Ok(());
     }
@@ -223,7 +395,7 @@ fn write_text<W: Write>(
 
     for row in &result.rows {
         for (i, value) in row.iter().enumerate() {
-            let len = format_value(value, numberify).len();
+            let len = format_value(value).len();
             if i < widths.len() && len > widths[i] {
                 widths[i] = len;
             }
@@ -254,7 +426,7 @@ fn write_text<W: Write>(
             if i > 0 {
                 write!(writer, "  ")?;
             }
-            let formatted = format_value(value, numberify);
+            let formatted = format_value(value);
             if i < widths.len() {
                 write!(writer, "{:width$}", formatted, width = widths[i])?;
             } else {
@@ -270,20 +442,13 @@ fn write_text<W: Write>(
     Ok(())
 }
 
-fn write_csv<W: Write>(
-    result: &rustledger_query::QueryResult,
-    writer: &mut W,
-    numberify: bool,
-) -> Result<()> {
+fn write_csv<W: Write>(result: &rustledger_query::QueryResult, writer: &mut W) -> Result<()> {
     // Print header
     writeln!(writer, "{}", result.columns.join(","))?;
 
     // Print rows
     for row in &result.rows {
-        let values: Vec<String> = row
-            .iter()
-            .map(|v| escape_csv(&format_value(v, numberify)))
-            .collect();
+        let values: Vec<String> = row.iter().map(|v| escape_csv(&format_value(v))).collect();
         writeln!(writer, "{}", values.join(","))?;
     }
     Ok(())
@@ -319,48 +484,32 @@ fn write_beancount<W: Write>(result: &rustledger_query::QueryResult, writer: &mu
     // This is mainly useful for PRINT queries
     for row in &result.rows {
         for value in row {
-            writeln!(writer, "{}", format_value(value, false))?;
+            writeln!(writer, "{}", format_value(value))?;
         }
     }
     Ok(())
 }
 
-fn format_value(value: &Value, numberify: bool) -> String {
+fn format_value(value: &Value) -> String {
     match value {
         Value::String(s) => s.clone(),
         Value::Number(n) => n.to_string(),
         Value::Integer(i) => i.to_string(),
         Value::Date(d) => d.to_string(),
         Value::Boolean(b) => b.to_string(),
-        Value::Amount(a) => {
-            if numberify {
-                a.number.to_string()
-            } else {
-                format!("{} {}", a.number, a.currency)
-            }
-        }
+        Value::Amount(a) => format!("{} {}", a.number, a.currency),
         Value::Position(p) => {
-            if numberify {
-                p.units.number.to_string()
-            } else {
-                let mut s = format!("{} {}", p.units.number, p.units.currency);
-                if let Some(ref cost) = p.cost {
-                    s.push_str(&format!(" {{{} {}}}", cost.number, cost.currency));
-                }
-                s
+            let mut s = format!("{} {}", p.units.number, p.units.currency);
+            if let Some(ref cost) = p.cost {
+                s.push_str(&format!(" {{{} {}}}", cost.number, cost.currency));
             }
+            s
         }
         Value::Inventory(inv) => {
             let positions: Vec<String> = inv
                 .positions()
                 .iter()
-                .map(|p| {
-                    if numberify {
-                        p.units.number.to_string()
-                    } else {
-                        format!("{} {}", p.units.number, p.units.currency)
-                    }
-                })
+                .map(|p| format!("{} {}", p.units.number, p.units.currency))
                 .collect();
             positions.join(", ")
         }
@@ -434,7 +583,12 @@ fn count_statistics(directives: &[Directive]) -> (usize, usize, usize) {
     (directives.len(), num_transactions, num_postings)
 }
 
-fn run_interactive(file: &PathBuf, directives: &[Directive], args: &Args) -> Result<()> {
+fn run_interactive(
+    file: &PathBuf,
+    directives: &[Directive],
+    args: &Args,
+    booking_method: BookingMethod,
+) -> Result<()> {
     // Create readline editor
     let mut rl: Editor<(), DefaultHistory> = DefaultEditor::new()?;
 
@@ -469,7 +623,7 @@ fn run_interactive(file: &PathBuf, directives: &[Directive], args: &Args) -> Res
     println!();
 
     // Shell settings
-    let mut settings = ShellSettings::from_args(args);
+    let mut settings = ShellSettings::from_args(args, booking_method)?;
 
     loop {
         let readline = rl.readline("beanquery> ");
@@ -582,6 +736,7 @@ fn handle_dot_command(cmd: &str, settings: &mut ShellSettings, directives: &[Dir
             println!("  BALANCES ...     Show account balances");
             println!("  JOURNAL ...      Show account journal");
             println!("  PRINT ...        Print entries in beancount format");
+            println!("  EXPORT TO ...    Export entries to ledger or hledger syntax");
             println!();
         }
         "set" => {
@@ -610,7 +765,7 @@ fn handle_dot_command(cmd: &str, settings: &mut ShellSettings, directives: &[Dir
                 // Set a setting
                 match args[0] {
                     "format" => match args[1] {
-                        "text" => settings.format = OutputFormat::Text,
+                        "text" | "table" => settings.format = OutputFormat::Text,
                         "csv" => settings.format = OutputFormat::Csv,
                         "json" => settings.format = OutputFormat::Json,
                         "beancount" => settings.format = OutputFormat::Beancount,
@@ -644,7 +799,7 @@ fn handle_dot_command(cmd: &str, settings: &mut ShellSettings, directives: &[Dir
                 println!("format: {}", settings.format);
             } else if args.len() == 1 {
                 match args[0] {
-                    "text" => settings.format = OutputFormat::Text,
+                    "text" | "table" => settings.format = OutputFormat::Text,
                     "csv" => settings.format = OutputFormat::Csv,
                     "json" => settings.format = OutputFormat::Json,
                     "beancount" => settings.format = OutputFormat::Beancount,
@@ -798,7 +953,7 @@ fn handle_dot_command(cmd: &str, settings: &mut ShellSettings, directives: &[Dir
                         println!("  3. Execute query: {query:?}");
                         println!("  4. Format results as {}", settings.format);
                         if settings.numberify {
-                            println!("  5. Numberify output (remove currencies)");
+                            println!("  5. Numberify output (one number column per currency)");
                         }
                         println!();
                         println!("Tables available: entries, postings");
@@ -813,3 +968,72 @@ fn handle_dot_command(cmd: &str, settings: &mut ShellSettings, directives: &[Dir
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{Amount, NaiveDate, Posting, Transaction};
+
+    fn directives() -> Vec<Directive> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        vec![Directive::Transaction(
+            Transaction::new(date, "Trip")
+                .with_posting(Posting::new(
+                    "Expenses:Travel",
+                    Amount::new(dec!(100), "EUR"),
+                ))
+                .with_posting(Posting::new(
+                    "Expenses:Travel",
+                    Amount::new(dec!(20), "USD"),
+                ))
+                .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(-100), "EUR")))
+                .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(-20), "USD"))),
+        )]
+    }
+
+    fn run_query(query: &str, format: OutputFormat, numberify: bool) -> String {
+        let settings = ShellSettings {
+            format,
+            numberify,
+            pager: false,
+            output_file: None,
+            functions: PluginFunctions::default(),
+            booking_method: BookingMethod::default(),
+        };
+        let mut out = Vec::new();
+        execute_query(query, &directives(), &settings, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_format_table_alias() {
+        let args = Args::parse_from(["rledger-query", "ledger.beancount", "-f", "table"]);
+        assert_eq!(args.format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_csv_numberify() {
+        let query = "SELECT account, sum(position) AS total GROUP BY account ORDER BY account";
+        assert_eq!(
+            run_query(query, OutputFormat::Csv, false),
+            "account,total\nAssets:Cash,\"-100 EUR, -20 USD\"\nExpenses:Travel,\"100 EUR, 20 USD\"\n"
+        );
+        assert_eq!(
+            run_query(query, OutputFormat::Csv, true),
+            "account,total (EUR),total (USD)\nAssets:Cash,-100,-20\nExpenses:Travel,100,20\n"
+        );
+    }
+
+    #[test]
+    fn test_json_numberify() {
+        let query = "SELECT account, sum(position) AS total GROUP BY account ORDER BY account";
+        let output: serde_json::Value =
+            serde_json::from_str(&run_query(query, OutputFormat::Json, true)).unwrap();
+        assert_eq!(
+            output["columns"],
+            serde_json::json!(["account", "total (EUR)", "total (USD)"])
+        );
+        assert_eq!(output["rows"][1]["total (USD)"], "20");
+    }
+}
@@ -14,10 +14,21 @@
 //! # Reports
 //!
 //! - `balances` - Show account balances
+//! - `holdings` - Show lots held with market value and unrealized gains
 //! - `accounts` - List all accounts
 //! - `commodities` - List all commodities
 //! - `prices` - Show price history
+//! - `gains` - Show realized gains per matched lot
+//! - `returns` - Show time- and money-weighted portfolio returns
 //! - `stats` - Show ledger statistics
+//!
+//! Reports are printed as text by default. `--format csv` and `--format json`
+//! are meant for other tools, while `--format html` writes a standalone page
+//! with sortable tables and a print stylesheet, for sharing statements:
+//!
+//! ```bash
+//! rledger-report ledger.beancount --format html balsheet > balance-sheet.html
+//! ```
 
 // Allow inner helper functions after statements for cleaner report code organization
 #![allow(clippy::items_after_statements)]
@@ -27,9 +38,11 @@ use anyhow::{Context, Result};
 use chrono::Datelike;
 use clap::{Parser, Subcommand};
 use rust_decimal::Decimal;
-use rustledger_booking::interpolate;
-use rustledger_core::{Directive, InternedStr, Inventory};
+use rustledger_booking::{absorb_rounding, interpolate};
+use rustledger_core::{BookingMethod, Directive, InternedStr, Inventory, Period, PeriodKind};
 use rustledger_loader::Loader;
+use rustledger_query::PriceDatabase;
+use rustledger_query::returns::ReturnCalculator;
 use std::collections::{BTreeMap, BTreeSet};
 use std::io::{self, Write};
 use std::path::PathBuf;
@@ -56,7 +69,7 @@ struct Args {
     #[arg(short, long, global = true)]
     verbose: bool,
 
-    /// Output format (text, csv, json)
+    /// Output format (text, csv, json, html)
     #[arg(short = 'f', long, global = true, default_value = "text")]
     format: OutputFormat,
 }
@@ -67,6 +80,7 @@ enum OutputFormat {
     Text,
     Csv,
     Json,
+    Html,
 }
 
 #[derive(Subcommand, Debug)]
@@ -93,15 +107,18 @@ enum Report {
         #[arg(short, long)]
         limit: Option<usize>,
     },
-    /// Investment holdings with cost basis
+    /// Lots held with cost basis, market value and unrealized gains
     Holdings {
         /// Filter to accounts matching this prefix
         #[arg(short, long)]
         account: Option<String>,
+        /// Consolidate lots by commodity, account or cost currency
+        #[arg(long, value_enum)]
+        by: Option<HoldingsGroup>,
     },
     /// Net worth over time
     Networth {
-        /// Group by period (daily, weekly, monthly, yearly)
+        /// Group by period (daily, weekly, monthly, quarterly, yearly)
         #[arg(short, long, default_value = "monthly")]
         period: String,
     },
@@ -117,6 +134,26 @@ enum Report {
         #[arg(short, long)]
         commodity: Option<String>,
     },
+    /// Realized gains per lot matched by sales
+    Gains {
+        /// Filter to accounts matching this prefix
+        #[arg(short, long)]
+        account: Option<String>,
+    },
+    /// Portfolio returns (TWRR and XIRR) for an account subtree
+    Returns {
+        /// Portfolio account (sub-accounts are included)
+        account: String,
+        /// Start date (default: first transaction in the portfolio)
+        #[arg(long)]
+        from: Option<rustledger_core::NaiveDate>,
+        /// End date (default: last directive in the ledger)
+        #[arg(long)]
+        to: Option<rustledger_core::NaiveDate>,
+        /// Valuation currency
+        #[arg(short, long, default_value = "USD")]
+        currency: String,
+    },
 }
 
 /// Main entry point for the report command.
@@ -174,6 +211,8 @@ fn run(file: &PathBuf, report: &Report, verbose: bool, format: &OutputFormat) ->
         .load(file)
         .with_context(|| format!("failed to load {}", file.display()))?;
 
+    let account_rounding = load_result.options.account_rounding.clone();
+
     // Extract directives (move, not clone)
     let mut directives: Vec<_> = load_result
         .directives
@@ -181,11 +220,14 @@ fn run(file: &PathBuf, report: &Report, verbose: bool, format: &OutputFormat) ->
         .map(|s| s.value)
         .collect();
 
-    // Interpolate transactions
+    // Interpolate transactions, booking rounding residuals if configured
     for directive in &mut directives {
         if let Directive::Transaction(txn) = directive {
             if let Ok(result) = interpolate(txn) {
                 *txn = result.transaction;
+                if let Some(account) = &account_rounding {
+                    absorb_rounding(txn, account);
+                }
             }
         }
     }
@@ -204,8 +246,8 @@ fn run(file: &PathBuf, report: &Report, verbose: bool, format: &OutputFormat) ->
         Report::Journal { account, limit } => {
             report_journal(&directives, account.as_deref(), *limit, format, &mut stdout)?;
         }
-        Report::Holdings { account } => {
-            report_holdings(&directives, account.as_deref(), format, &mut stdout)?;
+        Report::Holdings { account, by } => {
+            report_holdings(&directives, account.as_deref(), *by, format, &mut stdout)?;
         }
         Report::Networth { period } => {
             report_networth(&directives, period, format, &mut stdout)?;
@@ -222,6 +264,25 @@ fn run(file: &PathBuf, report: &Report, verbose: bool, format: &OutputFormat) ->
         Report::Prices { commodity } => {
             report_prices(&directives, commodity.as_deref(), format, &mut stdout)?;
         }
+        Report::Gains { account } => {
+            report_gains(&directives, account.as_deref(), format, &mut stdout)?;
+        }
+        Report::Returns {
+            account,
+            from,
+            to,
+            currency,
+        } => {
+            report_returns(
+                &directives,
+                account,
+                *from,
+                *to,
+                currency,
+                format,
+                &mut stdout,
+            )?;
+        }
     }
 
     Ok(())
@@ -313,6 +374,17 @@ fn report_balances<W: Write>(
                 writeln!(writer, "  {amount:>15} {currency}")?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Account", "Amount", "Currency"]);
+            for (account, amount, currency) in &rows {
+                table.row(vec![
+                    (*account).to_string(),
+                    amount.to_string(),
+                    (*currency).to_string(),
+                ]);
+            }
+            write_html_document(writer, "Account Balances", &[table])?;
+        }
     }
 
     Ok(())
@@ -336,6 +408,191 @@ fn json_escape(s: &str) -> String {
         .replace('\t', "\\t")
 }
 
+/// Escape a string for HTML output.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A table of an HTML report.
+struct HtmlTable {
+    caption: Option<String>,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    totals: Vec<Vec<String>>,
+}
+
+impl HtmlTable {
+    fn new(headers: &[&'static str]) -> Self {
+        Self {
+            caption: None,
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+            totals: Vec::new(),
+        }
+    }
+
+    fn with_caption(mut self, caption: impl Into<String>) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+
+    /// Add a row, sorted with the others.
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Add a total row, kept below the others.
+    fn total(&mut self, cells: Vec<String>) {
+        self.totals.push(cells);
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        fn write_cells<W: Write>(writer: &mut W, cells: &[String]) -> Result<()> {
+            write!(writer, "<tr>")?;
+            for cell in cells {
+                // Numbers are right-aligned and sorted by value
+                let number = cell.strip_suffix('%').unwrap_or(cell).parse::<Decimal>();
+                match number {
+                    Ok(n) if n.is_sign_negative() => write!(writer, r#"<td class="num neg">"#)?,
+                    Ok(_) => write!(writer, r#"<td class="num">"#)?,
+                    Err(_) => write!(writer, "<td>")?,
+                }
+                write!(writer, "{}</td>", html_escape(cell))?;
+            }
+            writeln!(writer, "</tr>")?;
+            Ok(())
+        }
+
+        writeln!(writer, "<table>")?;
+        if let Some(caption) = &self.caption {
+            writeln!(writer, "<caption>{}</caption>", html_escape(caption))?;
+        }
+        write!(writer, "<thead><tr>")?;
+        for header in &self.headers {
+            write!(writer, "<th>{}</th>", html_escape(header))?;
+        }
+        writeln!(writer, "</tr></thead>")?;
+        writeln!(writer, "<tbody>")?;
+        for row in &self.rows {
+            write_cells(writer, row)?;
+        }
+        writeln!(writer, "</tbody>")?;
+        if !self.totals.is_empty() {
+            writeln!(writer, "<tfoot>")?;
+            for row in &self.totals {
+                write_cells(writer, row)?;
+            }
+            writeln!(writer, "</tfoot>")?;
+        }
+        writeln!(writer, "</table>")?;
+        Ok(())
+    }
+}
+
+/// Stylesheet embedded in HTML reports.
+const HTML_STYLE: &str = r#"body { font: 14px/1.5 system-ui, -apple-system, "Segoe UI", sans-serif; color: #1f2937; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+h1 { font-size: 1.5rem; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2rem; }
+caption { text-align: left; font-size: 1.1rem; font-weight: 600; padding-bottom: 0.5rem; }
+th, td { text-align: left; padding: 0.35rem 0.75rem; border-bottom: 1px solid #e5e7eb; }
+th { background: #f3f4f6; cursor: pointer; user-select: none; white-space: nowrap; }
+th[aria-sort="ascending"]::after { content: " \25B2"; }
+th[aria-sort="descending"]::after { content: " \25BC"; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }
+td.neg { color: #b91c1c; }
+tbody tr:nth-child(even) { background: #f9fafb; }
+tfoot td { font-weight: 600; border-top: 2px solid #9ca3af; }
+footer { color: #6b7280; font-size: 0.8rem; }
+@media print {
+  body { max-width: none; margin: 0; font-size: 10pt; }
+  th { background: none; }
+  th::after { content: none !important; }
+  tbody tr:nth-child(even) { background: none; }
+  thead { display: table-header-group; }
+  tr { break-inside: avoid; }
+}
+"#;
+
+/// Script embedded in HTML reports: sorts a table by the clicked column.
+const HTML_SCRIPT: &str = r#"document.querySelectorAll("th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table"), body = table.tBodies[0], index = th.cellIndex;
+    var ascending = th.getAttribute("aria-sort") !== "ascending";
+    table.querySelectorAll("th").forEach(function (other) { other.removeAttribute("aria-sort"); });
+    th.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    Array.from(body.rows).sort(function (a, b) {
+      var x = a.cells[index], y = b.cells[index], order;
+      if (x.classList.contains("num") && y.classList.contains("num")) {
+        order = parseFloat(x.textContent) - parseFloat(y.textContent);
+      } else {
+        order = x.textContent.localeCompare(y.textContent);
+      }
+      return ascending ? order : -order;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+/// Write a standalone HTML page holding the given tables.
+///
+/// Styling and the script sorting tables by column are embedded, so the
+/// file can be shared and printed as is.
+fn write_html_document<W: Write>(writer: &mut W, title: &str, tables: &[HtmlTable]) -> Result<()> {
+    let title = html_escape(title);
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, r#"<html lang="en">"#)?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, r#"<meta charset="utf-8">"#)?;
+    writeln!(
+        writer,
+        r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#
+    )?;
+    writeln!(writer, "<title>{title}</title>")?;
+    writeln!(writer, "<style>\n{HTML_STYLE}</style>")?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>{title}</h1>")?;
+    for table in tables {
+        table.write(writer)?;
+    }
+    writeln!(
+        writer,
+        "<footer>Generated by rustledger {}</footer>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(writer, "<script>\n{HTML_SCRIPT}</script>")?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+/// Build the HTML table of one section of a statement, with its totals.
+fn html_section(title: &str, balances: &BTreeMap<InternedStr, Inventory>) -> HtmlTable {
+    let mut table = HtmlTable::new(&["Account", "Amount", "Currency"]).with_caption(title);
+    let mut totals: BTreeMap<InternedStr, Decimal> = BTreeMap::new();
+    for (account, inventory) in balances {
+        for position in inventory.positions() {
+            table.row(vec![
+                account.to_string(),
+                position.units.number.to_string(),
+                position.units.currency.to_string(),
+            ]);
+            *totals.entry(position.units.currency.clone()).or_default() += position.units.number;
+        }
+    }
+    for (currency, total) in &totals {
+        table.total(vec![
+            format!("Total {title}"),
+            total.to_string(),
+            currency.to_string(),
+        ]);
+    }
+    table
+}
+
 /// Generate an accounts list.
 fn report_accounts<W: Write>(
     directives: &[Directive],
@@ -375,6 +632,13 @@ fn report_accounts<W: Write>(
                 writeln!(writer, "{account}")?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Account"]);
+            for account in &accounts {
+                table.row(vec![(*account).to_string()]);
+            }
+            write_html_document(writer, "Accounts", &[table])?;
+        }
     }
 
     Ok(())
@@ -436,6 +700,13 @@ fn report_commodities<W: Write>(
                 writeln!(writer, "{commodity}")?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Commodity"]);
+            for commodity in &commodities {
+                table.row(vec![(*commodity).to_string()]);
+            }
+            write_html_document(writer, "Commodities", &[table])?;
+        }
     }
 
     Ok(())
@@ -581,6 +852,18 @@ fn report_prices<W: Write>(
                 }
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Commodity", "Date", "Price", "Currency"]);
+            for price in &prices {
+                table.row(vec![
+                    price.currency.to_string(),
+                    price.date.to_string(),
+                    price.amount.number.to_string(),
+                    price.amount.currency.to_string(),
+                ]);
+            }
+            write_html_document(writer, "Price History", &[table])?;
+        }
     }
 
     Ok(())
@@ -766,6 +1049,19 @@ fn report_balsheet<W: Write>(
                 writeln!(writer, "  {total:>12} {currency:>4}")?;
             }
         }
+        OutputFormat::Html => {
+            let mut totals = HtmlTable::new(&["Amount", "Currency"]).with_caption("Net Worth");
+            for (currency, total) in &net_worth {
+                totals.row(vec![total.to_string(), currency.to_string()]);
+            }
+            let tables = [
+                html_section("Assets", &assets),
+                html_section("Liabilities", &liabilities),
+                html_section("Equity", &equity),
+                totals,
+            ];
+            write_html_document(writer, "Balance Sheet", &tables)?;
+        }
     }
 
     Ok(())
@@ -938,6 +1234,18 @@ fn report_income<W: Write>(
                 writeln!(writer, "  {total:>12} {currency:>4}")?;
             }
         }
+        OutputFormat::Html => {
+            let mut totals = HtmlTable::new(&["Amount", "Currency"]).with_caption("Net Income");
+            for (currency, total) in &net_income {
+                totals.row(vec![total.to_string(), currency.to_string()]);
+            }
+            let tables = [
+                html_section("Income", &income),
+                html_section("Expenses", &expenses),
+                totals,
+            ];
+            write_html_document(writer, "Income Statement", &tables)?;
+        }
     }
 
     Ok(())
@@ -967,7 +1275,8 @@ fn report_journal<W: Write>(
         })
         .collect();
 
-    entries.sort_by_key(|t| t.date);
+    // Stable sort: entries with the same date and time keep load order
+    entries.sort_by_key(|t| (t.date, t.time()));
 
     let entries_to_show = if let Some(n) = limit {
         entries.into_iter().rev().take(n).collect::<Vec<_>>()
@@ -1011,6 +1320,9 @@ fn report_journal<W: Write>(
                 };
                 writeln!(writer, "  {{")?;
                 writeln!(writer, r#"    "date": "{}","#, txn.date)?;
+                if let Some(time) = txn.time() {
+                    writeln!(writer, r#"    "time": "{time}","#)?;
+                }
                 writeln!(writer, r#"    "flag": "{}","#, txn.flag)?;
                 writeln!(writer, r#"    "payee": "{}","#, json_escape(payee))?;
                 writeln!(
@@ -1053,7 +1365,11 @@ fn report_journal<W: Write>(
                 } else {
                     format!("{payee} | {narration}")
                 };
-                writeln!(writer, "{} {} {}", txn.date, txn.flag, desc)?;
+                if let Some(time) = txn.time() {
+                    writeln!(writer, "{} {} {} {}", txn.date, time, txn.flag, desc)?;
+                } else {
+                    writeln!(writer, "{} {} {}", txn.date, txn.flag, desc)?;
+                }
 
                 for posting in &txn.postings {
                     if let Some(amount) = posting.amount() {
@@ -1071,129 +1387,563 @@ fn report_journal<W: Write>(
                 writeln!(writer)?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&[
+                "Date",
+                "Flag",
+                "Payee",
+                "Narration",
+                "Account",
+                "Amount",
+                "Currency",
+            ]);
+            for txn in &entries_to_show {
+                for posting in &txn.postings {
+                    let (amount, currency) = posting.amount().map_or_else(
+                        || (String::new(), String::new()),
+                        |amt| (amt.number.to_string(), amt.currency.to_string()),
+                    );
+                    table.row(vec![
+                        txn.date.to_string(),
+                        txn.flag.to_string(),
+                        txn.payee.as_deref().unwrap_or_default().to_string(),
+                        txn.narration.to_string(),
+                        posting.account.to_string(),
+                        amount,
+                        currency,
+                    ]);
+                }
+            }
+            write_html_document(writer, "Transaction Journal", &[table])?;
+        }
     }
 
     Ok(())
 }
 
-/// Generate a holdings report with cost basis.
+/// How holdings are consolidated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum HoldingsGroup {
+    /// One row per commodity and cost currency
+    Commodity,
+    /// One row per account and cost currency
+    Account,
+    /// One row per cost currency
+    Currency,
+}
+
+/// A lot held (or a consolidation of lots), valued in its cost currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HoldingRow {
+    /// Empty when consolidated across accounts.
+    account: String,
+    /// `None` when consolidated across commodities.
+    units: Option<Decimal>,
+    /// Empty when consolidated across commodities.
+    currency: String,
+    acquired: Option<rustledger_core::NaiveDate>,
+    cost_basis: Decimal,
+    /// `None` when no price is known for some lot.
+    market_value: Option<Decimal>,
+    value_currency: String,
+}
+
+impl HoldingRow {
+    fn unrealized_gain(&self) -> Option<Decimal> {
+        self.market_value.map(|value| value - self.cost_basis)
+    }
+}
+
+/// Enumerate the lots held in asset accounts, valued at the latest prices.
+fn holding_lots(directives: &[Directive], account_filter: Option<&str>) -> Vec<HoldingRow> {
+    let mut booking: BTreeMap<InternedStr, BookingMethod> = BTreeMap::new();
+    let mut lots: BTreeMap<InternedStr, Inventory> = BTreeMap::new();
+
+    for directive in directives {
+        let txn = match directive {
+            Directive::Open(open) => {
+                if let Some(method) = open.booking.as_deref().and_then(|b| b.parse().ok()) {
+                    booking.insert(open.account.clone(), method);
+                }
+                continue;
+            }
+            Directive::Transaction(txn) => txn,
+            _ => continue,
+        };
+
+        for posting in &txn.postings {
+            if !posting.account.starts_with("Assets:") {
+                continue;
+            }
+            if let Some(filter) = account_filter {
+                if !posting.account.starts_with(filter) {
+                    continue;
+                }
+            }
+            let Some(units) = posting.amount() else {
+                continue;
+            };
+            let inventory = lots.entry(posting.account.clone()).or_default();
+            if let Some(cost_spec) = &posting.cost {
+                let method = booking.get(&posting.account).copied().unwrap_or_default();
+                // Booking errors are reported by rledger-check
+                let _ = inventory.book_at_cost(units, cost_spec, method, txn.date);
+            } else {
+                inventory.add(rustledger_core::Position::simple(units.clone()));
+            }
+        }
+    }
+
+    let price_db = PriceDatabase::from_directives(directives);
+    let mut rows = Vec::new();
+    for (account, inventory) in &lots {
+        for position in inventory.positions() {
+            if position.units.number.is_zero() {
+                continue;
+            }
+            let row = if let Some(cost) = &position.cost {
+                HoldingRow {
+                    account: account.to_string(),
+                    units: Some(position.units.number),
+                    currency: position.units.currency.to_string(),
+                    acquired: cost.date,
+                    cost_basis: position.units.number * cost.number,
+                    market_value: price_db
+                        .convert_latest(&position.units, &cost.currency)
+                        .map(|value| value.number),
+                    value_currency: cost.currency.to_string(),
+                }
+            } else {
+                HoldingRow {
+                    account: account.to_string(),
+                    units: Some(position.units.number),
+                    currency: position.units.currency.to_string(),
+                    acquired: None,
+                    cost_basis: position.units.number,
+                    market_value: Some(position.units.number),
+                    value_currency: position.units.currency.to_string(),
+                }
+            };
+            rows.push(row);
+        }
+    }
+    rows
+}
+
+/// Consolidate lots into one row per group, in key order.
+fn consolidate_holdings(rows: Vec<HoldingRow>, group: HoldingsGroup) -> Vec<HoldingRow> {
+    let mut groups: BTreeMap<(String, String, String), HoldingRow> = BTreeMap::new();
+    for row in rows {
+        let (account, currency) = match group {
+            HoldingsGroup::Commodity => (String::new(), row.currency.clone()),
+            HoldingsGroup::Account => (row.account.clone(), String::new()),
+            HoldingsGroup::Currency => (String::new(), String::new()),
+        };
+        let key = (
+            account.clone(),
+            currency.clone(),
+            row.value_currency.clone(),
+        );
+        let entry = groups.entry(key).or_insert_with(|| HoldingRow {
+            account,
+            units: (group == HoldingsGroup::Commodity).then_some(Decimal::ZERO),
+            currency,
+            acquired: None,
+            cost_basis: Decimal::ZERO,
+            market_value: Some(Decimal::ZERO),
+            value_currency: row.value_currency.clone(),
+        });
+        if let (Some(total), Some(units)) = (&mut entry.units, row.units) {
+            *total += units;
+        }
+        entry.cost_basis += row.cost_basis;
+        entry.market_value = entry.market_value.zip(row.market_value).map(|(a, b)| a + b);
+    }
+    groups.into_values().collect()
+}
+
+/// Generate a holdings report, one row per lot unless consolidated.
 fn report_holdings<W: Write>(
     directives: &[Directive],
     account_filter: Option<&str>,
+    group: Option<HoldingsGroup>,
     format: &OutputFormat,
     writer: &mut W,
 ) -> Result<()> {
-    // Track holdings: account -> currency -> (units, cost_basis, cost_currency)
-    let mut holdings: BTreeMap<InternedStr, BTreeMap<String, (Decimal, Decimal, String)>> =
-        BTreeMap::new();
+    let mut rows = holding_lots(directives, account_filter);
+    if let Some(group) = group {
+        rows = consolidate_holdings(rows, group);
+    }
 
-    for directive in directives {
-        if let Directive::Transaction(txn) = directive {
-            for posting in &txn.postings {
-                if let Some(filter) = account_filter {
-                    if !posting.account.starts_with(filter) {
-                        continue;
-                    }
-                }
+    let optional = |value: Option<Decimal>| value.map(|v| v.to_string()).unwrap_or_default();
+    match format {
+        OutputFormat::Csv => {
+            writeln!(
+                writer,
+                "account,units,currency,acquired,cost_basis,market_value,unrealized_gain,value_currency"
+            )?;
+            for row in &rows {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_escape(&row.account),
+                    optional(row.units),
+                    row.currency,
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    row.cost_basis,
+                    optional(row.market_value),
+                    optional(row.unrealized_gain()),
+                    row.value_currency
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            let json_optional = |value: Option<String>| {
+                value.map_or_else(
+                    || "null".to_string(),
+                    |v| format!("\"{}\"", json_escape(&v)),
+                )
+            };
+            let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+            writeln!(writer, "[")?;
+            for (i, row) in rows.iter().enumerate() {
+                let comma = if i < rows.len() - 1 { "," } else { "" };
+                writeln!(
+                    writer,
+                    r#"  {{"account": {}, "units": {}, "currency": {}, "acquired": {}, "cost_basis": "{}", "market_value": {}, "unrealized_gain": {}, "value_currency": "{}"}}{}"#,
+                    json_optional(non_empty(&row.account)),
+                    json_optional(row.units.map(|u| u.to_string())),
+                    json_optional(non_empty(&row.currency)),
+                    json_optional(row.acquired.map(|d| d.to_string())),
+                    row.cost_basis,
+                    json_optional(row.market_value.map(|v| v.to_string())),
+                    json_optional(row.unrealized_gain().map(|g| g.to_string())),
+                    row.value_currency,
+                    comma
+                )?;
+            }
+            writeln!(writer, "]")?;
+        }
+        OutputFormat::Text => {
+            writeln!(writer, "Holdings")?;
+            writeln!(writer, "{}", "=".repeat(110))?;
+            writeln!(writer)?;
+            writeln!(
+                writer,
+                "{:36} {:>10} {:>6} {:10} {:>14} {:>14} {:>12} {:>4}",
+                "Account",
+                "Units",
+                "Curr",
+                "Acquired",
+                "Cost Basis",
+                "Market Value",
+                "Gain",
+                "Curr"
+            )?;
+            writeln!(writer, "{}", "-".repeat(110))?;
 
-                let account_str: &str = &posting.account;
-                if !account_str.starts_with("Assets:") {
-                    continue;
-                }
+            let mut totals: BTreeMap<&str, (Decimal, Option<Decimal>)> = BTreeMap::new();
+            for row in &rows {
+                writeln!(
+                    writer,
+                    "{:36} {:>10} {:>6} {:10} {:>14} {:>14} {:>12} {:>4}",
+                    row.account,
+                    optional(row.units),
+                    row.currency,
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    row.cost_basis,
+                    optional(row.market_value),
+                    optional(row.unrealized_gain()),
+                    row.value_currency
+                )?;
+                let total = totals
+                    .entry(&row.value_currency)
+                    .or_insert((Decimal::ZERO, Some(Decimal::ZERO)));
+                total.0 += row.cost_basis;
+                total.1 = total.1.zip(row.market_value).map(|(a, b)| a + b);
+            }
 
-                if let Some(amount) = posting.amount() {
-                    let account_holdings = holdings.entry(posting.account.clone()).or_default();
+            writeln!(writer, "{}", "-".repeat(110))?;
+            for (currency, (cost_basis, market_value)) in &totals {
+                writeln!(
+                    writer,
+                    "{:66} {:>14} {:>14} {:>12} {:>4}",
+                    "Total",
+                    cost_basis,
+                    optional(*market_value),
+                    optional(market_value.map(|value| value - cost_basis)),
+                    currency
+                )?;
+            }
+        }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&[
+                "Account",
+                "Units",
+                "Currency",
+                "Acquired",
+                "Cost Basis",
+                "Market Value",
+                "Unrealized Gain",
+                "Value Currency",
+            ]);
+            let mut totals: BTreeMap<&str, (Decimal, Option<Decimal>)> = BTreeMap::new();
+            for row in &rows {
+                table.row(vec![
+                    row.account.clone(),
+                    optional(row.units),
+                    row.currency.clone(),
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    row.cost_basis.to_string(),
+                    optional(row.market_value),
+                    optional(row.unrealized_gain()),
+                    row.value_currency.clone(),
+                ]);
+                let total = totals
+                    .entry(&row.value_currency)
+                    .or_insert((Decimal::ZERO, Some(Decimal::ZERO)));
+                total.0 += row.cost_basis;
+                total.1 = total.1.zip(row.market_value).map(|(a, b)| a + b);
+            }
+            for (currency, (cost_basis, market_value)) in &totals {
+                table.total(vec![
+                    "Total".to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    cost_basis.to_string(),
+                    optional(*market_value),
+                    optional(market_value.map(|value| value - cost_basis)),
+                    (*currency).to_string(),
+                ]);
+            }
+            write_html_document(writer, "Holdings", &[table])?;
+        }
+    }
 
-                    let (cost_amount, cost_currency) = if let Some(cost_spec) = &posting.cost {
-                        if let Some(cost) = cost_spec.resolve(amount.number, txn.date) {
-                            (cost.number * amount.number, cost.currency.to_string())
-                        } else {
-                            (amount.number, amount.currency.to_string())
-                        }
-                    } else {
-                        (amount.number, amount.currency.to_string())
-                    };
+    Ok(())
+}
+
+/// A lot reduced by a sale at a price, with its realized gain.
+struct GainRow {
+    date: rustledger_core::NaiveDate,
+    account: String,
+    units: Decimal,
+    currency: String,
+    acquired: Option<rustledger_core::NaiveDate>,
+    label: Option<String>,
+    cost_basis: Decimal,
+    proceeds: Decimal,
+    gain: Decimal,
+    gain_currency: String,
+}
 
-                    let entry = account_holdings
-                        .entry(amount.currency.to_string())
-                        .or_insert((Decimal::ZERO, Decimal::ZERO, cost_currency.clone()));
+/// Generate a realized gains report, one row per lot matched by a sale.
+fn report_gains<W: Write>(
+    directives: &[Directive],
+    account_filter: Option<&str>,
+    format: &OutputFormat,
+    writer: &mut W,
+) -> Result<()> {
+    let mut booking: BTreeMap<InternedStr, BookingMethod> = BTreeMap::new();
+    let mut lots: BTreeMap<InternedStr, Inventory> = BTreeMap::new();
+    let mut rows = Vec::new();
 
-                    entry.0 += amount.number;
-                    entry.1 += cost_amount;
+    for directive in directives {
+        let txn = match directive {
+            Directive::Open(open) => {
+                if let Some(method) = open.booking.as_deref().and_then(|b| b.parse().ok()) {
+                    booking.insert(open.account.clone(), method);
                 }
+                continue;
             }
-        }
-    }
+            Directive::Transaction(txn) => txn,
+            _ => continue,
+        };
 
-    // Collect rows: (account, units, currency, cost_basis, cost_currency)
-    let mut rows: Vec<(String, Decimal, String, Decimal, String)> = Vec::new();
-    for (account, currencies) in &holdings {
-        for (currency, (units, cost_basis, cost_currency)) in currencies {
-            if *units == Decimal::ZERO {
+        for posting in &txn.postings {
+            let (Some(units), Some(cost_spec)) = (posting.amount(), &posting.cost) else {
+                continue;
+            };
+            let method = booking.get(&posting.account).copied().unwrap_or_default();
+            let Ok(Some(result)) = lots
+                .entry(posting.account.clone())
+                .or_default()
+                .book_at_cost(units, cost_spec, method, txn.date)
+            else {
                 continue;
+            };
+
+            if let Some(filter) = account_filter {
+                if !posting.account.starts_with(filter) {
+                    continue;
+                }
+            }
+            let Some(price) = posting.price.as_ref() else {
+                continue;
+            };
+            let Some(price_amount) = price.amount() else {
+                continue;
+            };
+            let price_per_unit = if price.is_unit() {
+                price_amount.clone()
+            } else {
+                rustledger_core::Amount::new(
+                    price_amount.number / units.number.abs(),
+                    price_amount.currency.clone(),
+                )
+            };
+
+            for lot in &result.lots {
+                let (Some(gain), Some(cost_basis)) =
+                    (lot.realized_gain(&price_per_unit), lot.cost_basis())
+                else {
+                    continue;
+                };
+                rows.push(GainRow {
+                    date: txn.date,
+                    account: posting.account.to_string(),
+                    units: lot.units.number,
+                    currency: lot.units.currency.to_string(),
+                    acquired: lot.date(),
+                    label: lot.label().map(String::from),
+                    cost_basis: cost_basis.number,
+                    proceeds: lot.units.number * price_per_unit.number,
+                    gain: gain.number,
+                    gain_currency: gain.currency.to_string(),
+                });
             }
-            rows.push((
-                account.to_string(),
-                *units,
-                currency.clone(),
-                *cost_basis,
-                cost_currency.clone(),
-            ));
         }
     }
 
     match format {
         OutputFormat::Csv => {
-            writeln!(writer, "account,units,currency,cost_basis,cost_currency")?;
-            for (account, units, currency, cost_basis, cost_currency) in &rows {
+            writeln!(
+                writer,
+                "date,account,units,currency,acquired,label,cost_basis,proceeds,gain,gain_currency"
+            )?;
+            for row in &rows {
                 writeln!(
                     writer,
-                    "{},{},{},{},{}",
-                    csv_escape(account),
-                    units,
-                    currency,
-                    cost_basis,
-                    cost_currency
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    row.date,
+                    csv_escape(&row.account),
+                    row.units,
+                    row.currency,
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    csv_escape(row.label.as_deref().unwrap_or_default()),
+                    row.cost_basis,
+                    row.proceeds,
+                    row.gain,
+                    row.gain_currency
                 )?;
             }
         }
         OutputFormat::Json => {
             writeln!(writer, "[")?;
-            for (i, (account, units, currency, cost_basis, cost_currency)) in
-                rows.iter().enumerate()
-            {
+            for (i, row) in rows.iter().enumerate() {
                 let comma = if i < rows.len() - 1 { "," } else { "" };
+                let acquired = row
+                    .acquired
+                    .map_or_else(|| "null".to_string(), |d| format!("\"{d}\""));
+                let label = row
+                    .label
+                    .as_deref()
+                    .map_or_else(|| "null".to_string(), |l| format!("\"{}\"", json_escape(l)));
                 writeln!(
                     writer,
-                    r#"  {{"account": "{}", "units": "{}", "currency": "{}", "cost_basis": "{}", "cost_currency": "{}"}}{}"#,
-                    json_escape(account),
-                    units,
-                    currency,
-                    cost_basis,
-                    cost_currency,
+                    r#"  {{"date": "{}", "account": "{}", "units": "{}", "currency": "{}", "acquired": {}, "label": {}, "cost_basis": "{}", "proceeds": "{}", "gain": "{}", "gain_currency": "{}"}}{}"#,
+                    row.date,
+                    json_escape(&row.account),
+                    row.units,
+                    row.currency,
+                    acquired,
+                    label,
+                    row.cost_basis,
+                    row.proceeds,
+                    row.gain,
+                    row.gain_currency,
                     comma
                 )?;
             }
             writeln!(writer, "]")?;
         }
         OutputFormat::Text => {
-            writeln!(writer, "Holdings")?;
-            writeln!(writer, "{}", "=".repeat(80))?;
+            writeln!(writer, "Realized Gains")?;
+            writeln!(writer, "{}", "=".repeat(100))?;
             writeln!(writer)?;
             writeln!(
                 writer,
-                "{:50} {:>12} {:>6} {:>12} {:>6}",
-                "Account", "Units", "Curr", "Cost Basis", "Curr"
+                "{:10} {:30} {:>10} {:>6} {:10} {:>12} {:>12} {:>12}",
+                "Date", "Account", "Units", "Curr", "Acquired", "Cost Basis", "Proceeds", "Gain"
             )?;
-            writeln!(writer, "{}", "-".repeat(80))?;
+            writeln!(writer, "{}", "-".repeat(100))?;
+
+            let mut totals: BTreeMap<&str, Decimal> = BTreeMap::new();
+            for row in &rows {
+                writeln!(
+                    writer,
+                    "{:10} {:30} {:>10} {:>6} {:10} {:>12} {:>12} {:>12}",
+                    row.date.to_string(),
+                    row.account,
+                    row.units,
+                    row.currency,
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    row.cost_basis,
+                    row.proceeds,
+                    format!("{} {}", row.gain, row.gain_currency)
+                )?;
+                *totals.entry(&row.gain_currency).or_default() += row.gain;
+            }
 
-            for (account, units, currency, cost_basis, cost_currency) in &rows {
+            writeln!(writer, "{}", "-".repeat(100))?;
+            for (currency, total) in &totals {
                 writeln!(
                     writer,
-                    "{account:50} {units:>12} {currency:>6} {cost_basis:>12} {cost_currency:>6}"
+                    "{:87} {:>12}",
+                    "Total",
+                    format!("{total} {currency}")
                 )?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&[
+                "Date",
+                "Account",
+                "Units",
+                "Currency",
+                "Acquired",
+                "Label",
+                "Cost Basis",
+                "Proceeds",
+                "Gain",
+                "Gain Currency",
+            ]);
+            let mut totals: BTreeMap<&str, Decimal> = BTreeMap::new();
+            for row in &rows {
+                table.row(vec![
+                    row.date.to_string(),
+                    row.account.clone(),
+                    row.units.to_string(),
+                    row.currency.clone(),
+                    row.acquired.map(|d| d.to_string()).unwrap_or_default(),
+                    row.label.clone().unwrap_or_default(),
+                    row.cost_basis.to_string(),
+                    row.proceeds.to_string(),
+                    row.gain.to_string(),
+                    row.gain_currency.clone(),
+                ]);
+                *totals.entry(&row.gain_currency).or_default() += row.gain;
+            }
+            for (currency, total) in &totals {
+                let mut cells = vec![String::new(); 10];
+                cells[0] = "Total".to_string();
+                cells[8] = total.to_string();
+                cells[9] = (*currency).to_string();
+                table.total(cells);
+            }
+            write_html_document(writer, "Realized Gains", &[table])?;
+        }
     }
 
     Ok(())
@@ -1224,6 +1974,11 @@ fn report_networth<W: Write>(
             OutputFormat::Csv => writeln!(writer, "period,currency,amount")?,
             OutputFormat::Json => writeln!(writer, "[]")?,
             OutputFormat::Text => writeln!(writer, "No transactions found.")?,
+            OutputFormat::Html => write_html_document(
+                writer,
+                "Net Worth Over Time",
+                &[HtmlTable::new(&["Period", "Currency", "Amount"])],
+            )?,
         }
         return Ok(());
     }
@@ -1232,12 +1987,12 @@ fn report_networth<W: Write>(
     let mut liability_balance: BTreeMap<InternedStr, Decimal> = BTreeMap::new();
     let mut period_results: Vec<(String, BTreeMap<InternedStr, Decimal>)> = Vec::new();
 
+    let kind = period.parse().unwrap_or(PeriodKind::Month);
     let format_period = |date: rustledger_core::NaiveDate, period: &str| -> String {
         match period {
             "daily" => date.to_string(),
             "weekly" => format!("{}-W{:02}", date.year(), date.iso_week().week()),
-            "yearly" => format!("{}", date.year()),
-            _ => format!("{}-{:02}", date.year(), date.month()),
+            _ => Period::containing(date, kind).to_string(),
         }
     };
 
@@ -1315,6 +2070,122 @@ fn report_networth<W: Write>(
                 writeln!(writer)?;
             }
         }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Period", "Currency", "Amount"]);
+            for (period_label, net_worth) in &period_results {
+                for (currency, amount) in net_worth {
+                    table.row(vec![
+                        period_label.clone(),
+                        currency.to_string(),
+                        amount.to_string(),
+                    ]);
+                }
+            }
+            write_html_document(writer, &format!("Net Worth Over Time ({period})"), &[table])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate a portfolio returns report.
+fn report_returns<W: Write>(
+    directives: &[Directive],
+    account: &str,
+    from: Option<rustledger_core::NaiveDate>,
+    to: Option<rustledger_core::NaiveDate>,
+    currency: &str,
+    format: &OutputFormat,
+    writer: &mut W,
+) -> Result<()> {
+    let price_db = PriceDatabase::from_directives(directives);
+    let calc = ReturnCalculator::new(directives, &price_db, account, currency);
+
+    let flows = calc.cash_flows();
+    let Some(first_flow) = flows.first() else {
+        anyhow::bail!("no cash flows found for {account}");
+    };
+    let from = from.unwrap_or(first_flow.date);
+    let to = to
+        .or_else(|| directives.iter().map(Directive::date).max())
+        .unwrap_or(from);
+
+    let market_value = calc.market_value(to);
+    let twrr = calc.twrr(from, to);
+    let xirr = calc.xirr(to);
+
+    let fmt_rate = |rate: Option<Decimal>| {
+        rate.map_or_else(
+            || "n/a".to_string(),
+            |r| format!("{:.2}%", r * Decimal::ONE_HUNDRED),
+        )
+    };
+
+    match format {
+        OutputFormat::Csv => {
+            writeln!(writer, "account,from,to,currency,market_value,twrr,xirr")?;
+            writeln!(
+                writer,
+                "{},{from},{to},{currency},{market_value},{},{}",
+                csv_escape(account),
+                twrr.map(|r| r.to_string()).unwrap_or_default(),
+                xirr.map(|r| r.to_string()).unwrap_or_default(),
+            )?;
+        }
+        OutputFormat::Json => {
+            let json_rate = |rate: Option<Decimal>| {
+                rate.map_or_else(|| "null".to_string(), |r| format!("\"{r}\""))
+            };
+            writeln!(
+                writer,
+                r#"{{"account": "{}", "from": "{from}", "to": "{to}", "currency": "{currency}", "market_value": "{market_value}", "twrr": {}, "xirr": {}}}"#,
+                json_escape(account),
+                json_rate(twrr),
+                json_rate(xirr),
+            )?;
+        }
+        OutputFormat::Text => {
+            writeln!(writer, "Returns for {account} ({from} to {to})")?;
+            writeln!(writer, "{}", "=".repeat(60))?;
+            writeln!(writer)?;
+            writeln!(
+                writer,
+                "{:30} {:>20}",
+                "Market value",
+                format!("{market_value:.2} {currency}")
+            )?;
+            writeln!(writer, "{:30} {:>20}", "Cash flows", flows.len())?;
+            writeln!(
+                writer,
+                "{:30} {:>20}",
+                "Time-weighted (TWRR)",
+                fmt_rate(twrr)
+            )?;
+            writeln!(
+                writer,
+                "{:30} {:>20}",
+                "Money-weighted (XIRR, p.a.)",
+                fmt_rate(xirr)
+            )?;
+        }
+        OutputFormat::Html => {
+            let mut table = HtmlTable::new(&["Measure", "Value"]);
+            table.row(vec![
+                "Market value".to_string(),
+                format!("{market_value:.2} {currency}"),
+            ]);
+            table.row(vec!["Cash flows".to_string(), flows.len().to_string()]);
+            table.row(vec!["Time-weighted (TWRR)".to_string(), fmt_rate(twrr)]);
+            table.row(vec![
+                "Money-weighted (XIRR, p.a.)".to_string(),
+                fmt_rate(xirr),
+            ]);
+            write_html_document(
+                writer,
+                &format!("Returns for {account} ({from} to {to})"),
+                &[table],
+            )?;
+        }
     }
 
     Ok(())
@@ -1337,3 +2208,160 @@ struct LedgerStats {
     first_date: Option<rustledger_core::NaiveDate>,
     last_date: Option<rustledger_core::NaiveDate>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use rustledger_core::{
+        Amount, CostSpec, NaiveDate, Open, Posting, PriceAnnotation, Transaction,
+    };
+
+    #[test]
+    fn test_report_gains_csv() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let buy = |day: NaiveDate, cost| {
+            Directive::Transaction(
+                Transaction::new(day, "Buy")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(10), "AAPL")).with_cost(
+                            CostSpec::empty()
+                                .with_number_per(cost)
+                                .with_currency("USD")
+                                .with_date(day),
+                        ),
+                    )
+                    .with_posting(Posting::new(
+                        "Assets:Cash",
+                        Amount::new(-cost * dec!(10), "USD"),
+                    )),
+            )
+        };
+        let directives = vec![
+            Directive::Open(Open::new(date(1, 1), "Assets:Stock").with_booking("FIFO")),
+            buy(date(1, 1), dec!(100)),
+            buy(date(2, 1), dec!(150)),
+            Directive::Transaction(
+                Transaction::new(date(3, 1), "Sell")
+                    .with_posting(
+                        Posting::new("Assets:Stock", Amount::new(dec!(-15), "AAPL"))
+                            .with_cost(CostSpec::empty())
+                            .with_price(PriceAnnotation::Unit(Amount::new(dec!(160), "USD"))),
+                    )
+                    .with_posting(Posting::new("Assets:Cash", Amount::new(dec!(2400), "USD"))),
+            ),
+        ];
+
+        let mut out = Vec::new();
+        report_gains(&directives, None, &OutputFormat::Csv, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(
+            csv,
+            "date,account,units,currency,acquired,label,cost_basis,proceeds,gain,gain_currency\n\
+             2024-03-01,Assets:Stock,10,AAPL,2024-01-01,,1000,1600,600,USD\n\
+             2024-03-01,Assets:Stock,5,AAPL,2024-02-01,,750,800,50,USD\n"
+        );
+
+        let mut out = Vec::new();
+        report_gains(
+            &directives,
+            Some("Assets:Other"),
+            &OutputFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_report_balsheet_html() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let directives = vec![Directive::Transaction(
+            Transaction::new(date, "Opening")
+                .with_posting(Posting::new(
+                    "Assets:Bank:R&D",
+                    Amount::new(dec!(100), "USD"),
+                ))
+                .with_posting(Posting::new(
+                    "Liabilities:Card",
+                    Amount::new(dec!(-40), "USD"),
+                ))
+                .with_posting(Posting::new(
+                    "Equity:Opening",
+                    Amount::new(dec!(-60), "USD"),
+                )),
+        )];
+
+        let mut out = Vec::new();
+        report_balsheet(&directives, &OutputFormat::Html, &mut out).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Balance Sheet</title>"));
+        assert!(html.contains("<style>") && html.contains("@media print"));
+        assert!(html.contains("<caption>Assets</caption>"));
+        assert!(html.contains("<td>Assets:Bank:R&amp;D</td><td class=\"num\">100</td>"));
+        assert!(html.contains(
+            "<tfoot>\n<tr><td>Total Liabilities</td><td class=\"num neg\">-40</td><td>USD</td></tr>"
+        ));
+        assert!(html.contains("<caption>Net Worth</caption>"));
+    }
+
+    #[test]
+    fn test_report_holdings_lots_and_consolidation() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let buy = |day: NaiveDate, account: &str, cost| {
+            Directive::Transaction(
+                Transaction::new(day, "Buy")
+                    .with_posting(
+                        Posting::new(account, Amount::new(dec!(10), "AAPL")).with_cost(
+                            CostSpec::empty()
+                                .with_number_per(cost)
+                                .with_currency("USD")
+                                .with_date(day),
+                        ),
+                    )
+                    .with_posting(Posting::new(
+                        "Assets:Cash",
+                        Amount::new(-cost * dec!(10), "USD"),
+                    )),
+            )
+        };
+        let directives = vec![
+            buy(date(1, 1), "Assets:Broker", dec!(100)),
+            buy(date(2, 1), "Assets:Retirement", dec!(150)),
+            Directive::Price(rustledger_core::Price::new(
+                date(3, 1),
+                "AAPL",
+                Amount::new(dec!(170), "USD"),
+            )),
+        ];
+
+        let mut out = Vec::new();
+        report_holdings(
+            &directives,
+            Some("Assets:Broker"),
+            None,
+            &OutputFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "account,units,currency,acquired,cost_basis,market_value,unrealized_gain,value_currency\n\
+             Assets:Broker,10,AAPL,2024-01-01,1000,1700,700,USD\n"
+        );
+
+        let rows = consolidate_holdings(holding_lots(&directives, None), HoldingsGroup::Commodity);
+        let aapl = rows.iter().find(|row| row.currency == "AAPL").unwrap();
+        assert_eq!(aapl.units, Some(dec!(20)));
+        assert_eq!(aapl.cost_basis, dec!(2500));
+        assert_eq!(aapl.unrealized_gain(), Some(dec!(900)));
+
+        let rows = consolidate_holdings(holding_lots(&directives, None), HoldingsGroup::Currency);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].units, None);
+        // Cash spent and stock bought at cost cancel out
+        assert_eq!(rows[0].cost_basis, dec!(0));
+        assert_eq!(rows[0].market_value, Some(dec!(900)));
+    }
+}
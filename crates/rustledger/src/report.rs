@@ -2,9 +2,11 @@
 //!
 //! Uses ariadne for pretty-printed error messages with source context.
 
+use crate::format::{FormatConfig, format_directive};
 use ariadne::{ColorGenerator, Config, Label, Report, ReportKind, Source};
+use rustledger_core::{Change, DiffSummary, Directive};
 use rustledger_parser::ParseError;
-use rustledger_validate::{ErrorCode, ValidationError};
+use rustledger_validate::{ErrorCode, ValidationError, ValidationReport};
 use std::collections::HashMap;
 use std::io::Write;
 use std::path::Path;
@@ -134,3 +136,54 @@ pub fn print_summary<W: Write>(
     }
     Ok(())
 }
+
+/// Print validation message counts per error code, below the summary line.
+pub fn print_validation_breakdown<W: Write>(
+    report: &ValidationReport,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    if report.is_empty() {
+        return Ok(());
+    }
+    let counts: Vec<String> = report
+        .counts_by_code()
+        .into_iter()
+        .map(|(code, count)| format!("{code} ({count})"))
+        .collect();
+    writeln!(writer, "  by code: {}", counts.join(", "))
+}
+
+/// Print a directive diff as beancount text, with `+`/`-` line prefixes,
+/// followed by a count of the changes.
+pub fn print_directive_diff<W: Write>(changes: &[Change], writer: &mut W) -> std::io::Result<()> {
+    let config = FormatConfig::new(60, 2);
+    let write_prefixed = |writer: &mut W, prefix: char, directive: &Directive| {
+        for line in format_directive(directive, &config).lines() {
+            writeln!(writer, "{prefix} {line}")?;
+        }
+        Ok::<_, std::io::Error>(())
+    };
+
+    for change in changes {
+        match change {
+            Change::Added(directive) => write_prefixed(writer, '+', directive)?,
+            Change::Removed(directive) => write_prefixed(writer, '-', directive)?,
+            Change::Modified { old, new } => {
+                write_prefixed(writer, '-', old)?;
+                write_prefixed(writer, '+', new)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    let summary = DiffSummary::of(changes);
+    if summary.is_empty() {
+        writeln!(writer, "No changes")
+    } else {
+        writeln!(
+            writer,
+            "{} added, {} removed, {} modified",
+            summary.added, summary.removed, summary.modified
+        )
+    }
+}
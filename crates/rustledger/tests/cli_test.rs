@@ -0,0 +1,48 @@
+//! Tests running the `rledger-*` binaries on small ledgers.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Write `source` to a fresh ledger file in the temp directory.
+fn write_ledger(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rledger-cli-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.beancount");
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn test_check_plugins_diff() {
+    let ledger = write_ledger(
+        "plugins-diff",
+        r#"2024-01-01 open Assets:Cash
+2024-01-01 open Assets:Brokerage
+2024-01-15 * "Buy shares"
+  Assets:Brokerage  10 HOOL @ 150.00 USD
+  Assets:Cash  -1500.00 USD
+"#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rledger-check"))
+        .arg("--no-cache")
+        .args(["--native-plugin", "implicit_prices"])
+        .arg("--plugins-diff")
+        .arg(&ledger)
+        .output()
+        .expect("failed to run rledger-check");
+    fs::remove_dir_all(ledger.parent().unwrap()).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "rledger-check failed: {stdout}");
+    // The price the plugin inferred is the only change
+    assert!(
+        stdout.contains("+ 2024-01-15 price HOOL 150.00 USD"),
+        "diff should show the added price: {stdout}"
+    );
+    assert!(
+        stdout.ends_with("1 added, 0 removed, 0 modified\n"),
+        "diff should end with a summary: {stdout}"
+    );
+}
@@ -111,6 +111,13 @@ pub extern "C" fn alloc(size: u32) -> *mut u8 {
     unsafe { std::alloc::alloc(layout) }
 }
 
+// Plugin interface version this plugin was built against
+
+#[no_mangle]
+pub extern "C" fn plugin_api_version() -> u32 {
+    1
+}
+
 // Main plugin entry point
 
 #[no_mangle]